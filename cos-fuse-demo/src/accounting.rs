@@ -0,0 +1,145 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 限速允许的突发量：空闲之后可以立即传输这么长时间的配额
+const BURST: Duration = Duration::from_secs(1);
+
+/// 一个 uid 的累计用量
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq)]
+pub struct UidUsage {
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    /// 因限速而等待的总时间（毫秒）
+    pub throttled_ms: u64,
+}
+
+#[derive(Default)]
+struct UidState {
+    usage: UidUsage,
+    /// 按限速计算，已传输的数据在该时刻才算"还清"（早于当前时间时视为当前时间）
+    paid_until: Option<Instant>,
+}
+
+/// 按请求者 uid 统计读写字节数，并可按 uid 限速：
+/// 共享挂载上某个用户的批量任务只会拖慢自己，不会占满整个桶的带宽
+pub struct UidAccounting {
+    /// 每个 uid 每秒的读写字节数上限（None 表示不限速）
+    rate_limit: Option<u64>,
+    states: Mutex<HashMap<u32, UidState>>,
+}
+
+impl UidAccounting {
+    pub fn new(rate_limit: Option<u64>) -> Self {
+        Self {
+            rate_limit: rate_limit.filter(|rate| *rate > 0),
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 记录 `uid` 读取了 `bytes` 字节，超出限速时等待到配额允许为止
+    pub async fn record_read(&self, uid: u32, bytes: u64) {
+        let delay = self.charge(uid, bytes, Instant::now(), |usage| {
+            usage.read_bytes += bytes
+        });
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// 记录 `uid` 写入了 `bytes` 字节，超出限速时等待到配额允许为止
+    pub async fn record_write(&self, uid: u32, bytes: u64) {
+        let delay = self.charge(uid, bytes, Instant::now(), |usage| {
+            usage.write_bytes += bytes
+        });
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// 计入用量并返回需要等待的时间
+    fn charge(
+        &self,
+        uid: u32,
+        bytes: u64,
+        now: Instant,
+        count: impl FnOnce(&mut UidUsage),
+    ) -> Duration {
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(uid).or_default();
+        count(&mut state.usage);
+
+        let Some(rate) = self.rate_limit else {
+            return Duration::ZERO;
+        };
+        let cost = Duration::from_secs_f64(bytes as f64 / rate as f64);
+        let paid_until = state.paid_until.filter(|t| *t > now).unwrap_or(now) + cost;
+        state.paid_until = Some(paid_until);
+        let delay = paid_until.saturating_duration_since(now + BURST);
+        state.usage.throttled_ms += delay.as_millis() as u64;
+        delay
+    }
+
+    /// 各 uid 的累计用量，按 uid 排序
+    pub fn usage(&self) -> BTreeMap<u32, UidUsage> {
+        self.states
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(uid, state)| (*uid, state.usage))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uid_accounting() {
+        let accounting = UidAccounting::new(Some(1000));
+        let now = Instant::now();
+
+        // 一秒的突发量内不等待
+        let read = |usage: &mut UidUsage| usage.read_bytes += 1000;
+        assert_eq!(accounting.charge(1000, 1000, now, read), Duration::ZERO);
+        // 超出后按速率等待，其他 uid 不受影响
+        let read = |usage: &mut UidUsage| usage.read_bytes += 500;
+        assert_eq!(
+            accounting.charge(1000, 500, now, read),
+            Duration::from_millis(500)
+        );
+        let write = |usage: &mut UidUsage| usage.write_bytes += 2000;
+        assert_eq!(
+            accounting.charge(0, 2000, now, write),
+            Duration::from_secs(1)
+        );
+        // 空闲之后配额恢复
+        let later = now + Duration::from_secs(10);
+        let read = |usage: &mut UidUsage| usage.read_bytes += 100;
+        assert_eq!(accounting.charge(1000, 100, later, read), Duration::ZERO);
+
+        let usage = accounting.usage();
+        assert_eq!(
+            usage[&1000],
+            UidUsage {
+                read_bytes: 1600,
+                write_bytes: 0,
+                throttled_ms: 500
+            }
+        );
+        assert_eq!(usage[&0].write_bytes, 2000);
+
+        // 不限速时只统计
+        let accounting = UidAccounting::new(None);
+        let read = |usage: &mut UidUsage| usage.read_bytes += 1 << 30;
+        assert_eq!(accounting.charge(1, 1 << 30, now, read), Duration::ZERO);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        runtime.block_on(accounting.record_write(2, 10));
+        assert_eq!(accounting.usage()[&2].write_bytes, 10);
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::{anyhow, Result};
+use log::warn;
+use lru::LruCache;
+use serde::Deserialize;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::object_store::ObjectStore;
+
+/// 预设用户组
+const ALL_USERS_URI: &str = "http://cam.qcloud.com/groups/global/AllUsers";
+const AUTHENTICATED_USERS_URI: &str = "http://cam.qcloud.com/groups/global/AuthenticatedUsers";
+
+#[derive(Debug, Deserialize)]
+struct AccessControlPolicy {
+    #[serde(rename = "AccessControlList", default)]
+    access_control_list: AccessControlList,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AccessControlList {
+    #[serde(rename = "Grant", default)]
+    grants: Vec<Grant>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Grant {
+    #[serde(rename = "Grantee")]
+    grantee: Grantee,
+    #[serde(rename = "Permission")]
+    permission: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Grantee {
+    #[serde(rename = "URI")]
+    uri: Option<String>,
+}
+
+/// GET Object ACL 的结果，只保留能映射到权限位的预设用户组授权
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObjectAcl {
+    /// 匿名用户（AllUsers）的 rwx 位
+    pub all_users: u16,
+    /// 任意已认证用户（AuthenticatedUsers）的 rwx 位
+    pub authenticated_users: u16,
+}
+
+impl ObjectAcl {
+    /// 解析 GET Object ACL 的 XML 响应
+    pub fn parse(xml: &str) -> Result<Self> {
+        let policy: AccessControlPolicy = quick_xml::de::from_str(xml)
+            .map_err(|e| anyhow!("Failed to parse ACL response: {}", e))?;
+
+        let mut acl = ObjectAcl::default();
+        for grant in policy.access_control_list.grants {
+            let bits = match grant.permission.as_str() {
+                "READ" => 0o4,
+                "WRITE" => 0o2,
+                "FULL_CONTROL" => 0o6,
+                // READ_ACP/WRITE_ACP 不影响读写
+                _ => continue,
+            };
+            match grant.grantee.uri.as_deref() {
+                Some(ALL_USERS_URI) => acl.all_users |= bits,
+                Some(AUTHENTICATED_USERS_URI) => acl.authenticated_users |= bits,
+                _ => {}
+            }
+        }
+        Ok(acl)
+    }
+
+    /// 对应的权限位：属主读写，已认证用户映射到组，匿名用户映射到其他人。
+    /// 只有属主授权的对象多半沿用桶 ACL（接口无法区分），返回 None 使用默认权限
+    pub fn mode(&self) -> Option<u16> {
+        if self.all_users == 0 && self.authenticated_users == 0 {
+            return None;
+        }
+        // 匿名用户能做的事已认证用户也能做
+        let group = self.authenticated_users | self.all_users;
+        Some(0o600 | (group << 3) | self.all_users)
+    }
+}
+
+/// 按对象 ACL 推导权限位，结果缓存 `ttl`；查询失败时回退到默认权限
+pub struct AclPermissions<S> {
+    client: Arc<S>,
+    ttl: Duration,
+    cache: Mutex<LruCache<String, (Option<u16>, Instant)>>,
+}
+
+impl<S: ObjectStore> AclPermissions<S> {
+    pub fn new(client: Arc<S>, ttl: Duration, capacity: usize) -> Result<Self> {
+        let capacity = NonZeroUsize::new(capacity).ok_or_else(|| anyhow!("Invalid cache size"))?;
+        Ok(Self {
+            client,
+            ttl,
+            cache: Mutex::new(LruCache::new(capacity)),
+        })
+    }
+
+    /// 对象的权限位；None 表示使用默认（或配置的）权限
+    pub async fn mode(&self, key: &str) -> Option<u16> {
+        if let Some((mode, fetched_at)) = self.cache.lock().unwrap().get(key) {
+            if fetched_at.elapsed() < self.ttl {
+                return *mode;
+            }
+        }
+
+        let mode = match self.client.get_object_acl(key).await {
+            Ok(acl) => acl.mode(),
+            Err(e) => {
+                // 不缓存失败，下次 getattr 重试
+                warn!("Failed to get ACL for {}: {:#}", key, e);
+                return None;
+            }
+        };
+        self.cache
+            .lock()
+            .unwrap()
+            .put(key.to_string(), (mode, Instant::now()));
+        mode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(grantee: &str, permission: &str) -> String {
+        format!(
+            r#"<Grant><Grantee xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:type="Group">{}</Grantee><Permission>{}</Permission></Grant>"#,
+            grantee, permission
+        )
+    }
+
+    fn policy(grants: &[String]) -> String {
+        format!(
+            "<AccessControlPolicy><Owner><ID>qcs::cam::uin/1:uin/1</ID></Owner>\
+             <AccessControlList>{}</AccessControlList></AccessControlPolicy>",
+            grants.concat()
+        )
+    }
+
+    #[test]
+    fn test_acl_to_mode() {
+        let owner = grant("<ID>qcs::cam::uin/1:uin/1</ID>", "FULL_CONTROL");
+        let all_read = grant(&format!("<URI>{}</URI>", ALL_USERS_URI), "READ");
+        let all_write = grant(&format!("<URI>{}</URI>", ALL_USERS_URI), "WRITE");
+        let auth_full = grant(
+            &format!("<URI>{}</URI>", AUTHENTICATED_USERS_URI),
+            "FULL_CONTROL",
+        );
+
+        let acl = ObjectAcl::parse(&policy(std::slice::from_ref(&owner))).unwrap();
+        assert_eq!(acl.mode(), None);
+
+        let acl = ObjectAcl::parse(&policy(&[owner.clone(), all_read.clone()])).unwrap();
+        assert_eq!(acl.mode(), Some(0o644));
+
+        let acl = ObjectAcl::parse(&policy(&[owner.clone(), all_read, all_write])).unwrap();
+        assert_eq!(acl.mode(), Some(0o666));
+
+        let acl = ObjectAcl::parse(&policy(&[owner, auth_full])).unwrap();
+        assert_eq!(acl.mode(), Some(0o660));
+
+        assert!(ObjectAcl::parse("not xml").is_err());
+    }
+}
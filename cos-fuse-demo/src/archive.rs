@@ -0,0 +1,877 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use log::{debug, info};
+use lru::LruCache;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tar::{EntryType, Header, PaxExtensions};
+
+use crate::compression::inflate_raw;
+use crate::cos_client::{days_from_civil, ObjectMeta};
+use crate::inflight::InFlight;
+use crate::object_store::ObjectStore;
+
+/// 扫描 tar 头时每次读取的大小，小文件密集的归档一次读取能覆盖多个头
+const TAR_WINDOW: u64 = 64 * 1024;
+
+/// tar 的块大小
+const TAR_BLOCK: u64 = 512;
+
+/// PAX 扩展头、GNU 长文件名等元数据条目的大小上限
+const MAX_TAR_META_SIZE: u64 = 1024 * 1024;
+
+/// zip 结尾记录（22 字节）加上最长注释和 zip64 定位记录
+const ZIP_TAIL_SIZE: u64 = 22 + 65535 + 20;
+
+/// 中央目录的大小上限，超过时认为归档损坏
+const MAX_ZIP_DIRECTORY_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// deflate 条目解压后整体放在内存中，超过该大小的条目不支持读取
+const MAX_INFLATED_SIZE: u64 = 256 * 1024 * 1024;
+
+/// 保留在内存中的解压后条目数
+const INFLATED_CACHE_ENTRIES: usize = 8;
+
+/// zip 的压缩方式
+const ZIP_STORED: u16 = 0;
+const ZIP_DEFLATED: u16 = 8;
+
+/// 可以作为目录浏览的归档类型，按扩展名识别（不区分大小写）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+}
+
+impl ArchiveKind {
+    pub fn from_key(key: &str) -> Option<Self> {
+        let name = key.rsplit('/').next()?.to_ascii_lowercase();
+        if name.len() > 4 && name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if name.len() > 4 && name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// 归档中的一个文件
+#[derive(Debug)]
+pub struct ArchiveFile {
+    pub size: u64,
+    pub mtime: SystemTime,
+    /// 内容在归档中的偏移；zip 条目在首次读取时由本地文件头确定
+    data_offset: OnceLock<u64>,
+    /// zip 本地文件头的偏移（tar 条目没有）
+    header_offset: Option<u64>,
+    /// 内容在归档中占用的字节数
+    stored_size: u64,
+    /// zip 的压缩方式，tar 条目总是原样存放
+    method: u16,
+    encrypted: bool,
+}
+
+/// 归档中的路径对应的节点
+pub enum ArchiveNode<'a> {
+    Dir,
+    File(&'a ArchiveFile),
+}
+
+/// 归档的目录索引：由 zip 的中央目录或 tar 的各个头构建，路径不含开头的 `/`
+#[derive(Debug)]
+pub struct ArchiveIndex {
+    /// 归档对象的 ETag，对象变化后重建索引
+    pub etag: String,
+    /// 归档对象的修改时间，用作目录的时间
+    pub mtime: SystemTime,
+    files: BTreeMap<String, ArchiveFile>,
+    dirs: BTreeSet<String>,
+}
+
+impl ArchiveIndex {
+    fn new(etag: String, mtime: SystemTime) -> Self {
+        Self {
+            etag,
+            mtime,
+            files: BTreeMap::new(),
+            dirs: BTreeSet::new(),
+        }
+    }
+
+    /// 加入一个条目（`file` 为 None 时是目录）；不安全或为空的路径被忽略，同名文件后出现的为准
+    fn insert(&mut self, name: &str, file: Option<ArchiveFile>) {
+        let Some(path) = normalize_path(name) else {
+            debug!("Skipping archive entry with unusable path {:?}", name);
+            return;
+        };
+        let mut parent = path.as_str();
+        while let Some(slash) = parent.rfind('/') {
+            parent = &parent[..slash];
+            self.dirs.insert(parent.to_string());
+        }
+        match file {
+            Some(file) => {
+                self.files.insert(path, file);
+            }
+            None => {
+                self.dirs.insert(path);
+            }
+        }
+    }
+
+    /// 查找路径，空路径为归档本身
+    pub fn node(&self, path: &str) -> Option<ArchiveNode<'_>> {
+        if path.is_empty() || self.dirs.contains(path) {
+            return Some(ArchiveNode::Dir);
+        }
+        self.files.get(path).map(ArchiveNode::File)
+    }
+
+    /// 列出目录的直接子项：(名字, 是否为目录)，按名字排序
+    pub fn list(&self, dir: &str) -> Vec<(String, bool)> {
+        let prefix = if dir.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", dir)
+        };
+        let children = |paths: &mut dyn Iterator<Item = &String>| -> Vec<String> {
+            paths
+                .take_while(|path| path.starts_with(&prefix))
+                .map(|path| &path[prefix.len()..])
+                .filter(|name| !name.contains('/'))
+                .map(str::to_string)
+                .collect()
+        };
+        let dirs = children(&mut self.dirs.range(prefix.clone()..));
+        let files = children(&mut self.files.range(prefix.clone()..).map(|(path, _)| path));
+
+        let mut entries: Vec<(String, bool)> = dirs
+            .into_iter()
+            .map(|name| (name, true))
+            .chain(
+                files
+                    .into_iter()
+                    .filter(|name| !self.dirs.contains(&format!("{}{}", prefix, name)))
+                    .map(|name| (name, false)),
+            )
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+}
+
+/// 去掉开头的 `/` 和 `./`、结尾的 `/` 与重复的分隔符；含 `..` 的路径不安全，返回 None
+fn normalize_path(name: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    for part in name.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => return None,
+            part => parts.push(part),
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// 按偏移读取归档对象的内容
+trait RangeSource {
+    async fn read(&self, offset: u64, len: u64) -> Result<Bytes>;
+}
+
+/// 直接读取桶中的归档对象
+struct ObjectSource<'a, S> {
+    client: &'a S,
+    key: &'a str,
+}
+
+impl<S: ObjectStore> RangeSource for ObjectSource<'_, S> {
+    async fn read(&self, offset: u64, len: u64) -> Result<Bytes> {
+        let data = self.client.get_object_range(self.key, offset, len).await?;
+        if data.len() as u64 != len {
+            return Err(anyhow!("{} changed while reading its index", self.key));
+        }
+        Ok(data)
+    }
+}
+
+/// 归档浏览的共享状态：已加载的索引与解压后的条目
+pub struct Archives {
+    indexes: Mutex<HashMap<String, Arc<ArchiveIndex>>>,
+    loading: InFlight<Arc<ArchiveIndex>>,
+    inflated: Mutex<LruCache<String, Arc<Vec<u8>>>>,
+}
+
+impl Archives {
+    pub fn new() -> Self {
+        Self {
+            indexes: Mutex::new(HashMap::new()),
+            loading: InFlight::new(),
+            inflated: Mutex::new(LruCache::new(
+                NonZeroUsize::new(INFLATED_CACHE_ENTRIES).unwrap(),
+            )),
+        }
+    }
+
+    /// 已经加载过的索引（不检查归档是否变化）
+    pub fn loaded(&self, key: &str) -> Option<Arc<ArchiveIndex>> {
+        self.indexes.lock().unwrap().get(key).cloned()
+    }
+
+    /// 归档 `meta` 的索引：ETag 未变化时复用，否则重新读取；并发的加载只执行一次
+    pub async fn index<S: ObjectStore>(
+        &self,
+        client: &S,
+        meta: &ObjectMeta,
+    ) -> Result<Arc<ArchiveIndex>> {
+        if let Some(index) = self
+            .loaded(&meta.key)
+            .filter(|index| index.etag == meta.etag)
+        {
+            return Ok(index);
+        }
+        let kind = ArchiveKind::from_key(&meta.key)
+            .ok_or_else(|| anyhow!("{} is not an archive", meta.key))?;
+        let index = self
+            .loading
+            .run(&format!("{}\0{}", meta.key, meta.etag), || async {
+                let source = ObjectSource {
+                    client,
+                    key: &meta.key,
+                };
+                let index = load_index(&source, kind, meta).await?;
+                info!(
+                    "Indexed {} files in archive {}",
+                    index.file_count(),
+                    meta.key
+                );
+                Ok(Arc::new(index))
+            })
+            .await?;
+        self.indexes
+            .lock()
+            .unwrap()
+            .insert(meta.key.clone(), Arc::clone(&index));
+        Ok(index)
+    }
+
+    /// 读取归档 `key` 中文件 `path` 的 [offset, offset + size)，
+    /// `read_archive(offset, len)` 读取归档对象本身的内容
+    pub async fn read<F, Fut>(
+        &self,
+        key: &str,
+        index: &ArchiveIndex,
+        path: &str,
+        offset: u64,
+        size: u64,
+        read_archive: F,
+    ) -> Result<Vec<u8>>
+    where
+        F: Fn(u64, u64) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>>>,
+    {
+        let Some(ArchiveNode::File(file)) = index.node(path) else {
+            return Err(anyhow!("{} is not a file in {}", path, key));
+        };
+        let end = offset.saturating_add(size).min(file.size);
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+        if file.encrypted {
+            return Err(anyhow!("{} in {} is encrypted", path, key));
+        }
+
+        let data_offset = match (file.data_offset.get(), file.header_offset) {
+            (Some(&data_offset), _) => data_offset,
+            (None, Some(header_offset)) => {
+                let header = read_archive(header_offset, 30).await?;
+                let data_offset = zip_data_offset(&header, header_offset)?;
+                *file.data_offset.get_or_init(|| data_offset)
+            }
+            (None, None) => return Err(anyhow!("{} in {} has no data offset", path, key)),
+        };
+
+        let start = data_offset
+            .checked_add(offset)
+            .ok_or_else(|| anyhow!("corrupt entry {} in {}", path, key))?;
+        match file.method {
+            ZIP_STORED => read_archive(start, end - offset).await,
+            ZIP_DEFLATED => {
+                if file.size > MAX_INFLATED_SIZE {
+                    return Err(anyhow!(
+                        "{} in {} is compressed and too large to read ({} bytes)",
+                        path,
+                        key,
+                        file.size
+                    ));
+                }
+                let cache_key = format!("{}\0{}\0{}", key, index.etag, path);
+                let cached = self.inflated.lock().unwrap().get(&cache_key).cloned();
+                let content = match cached {
+                    Some(content) => content,
+                    None => {
+                        let compressed = read_archive(data_offset, file.stored_size).await?;
+                        let content = Arc::new(inflate_raw(&compressed, file.size)?);
+                        self.inflated
+                            .lock()
+                            .unwrap()
+                            .put(cache_key, Arc::clone(&content));
+                        content
+                    }
+                };
+                content
+                    .get(offset as usize..end as usize)
+                    .map(<[u8]>::to_vec)
+                    .ok_or_else(|| anyhow!("corrupt entry {} in {}", path, key))
+            }
+            method => Err(anyhow!(
+                "{} in {} uses unsupported compression method {}",
+                path,
+                key,
+                method
+            )),
+        }
+    }
+
+    /// 内存中解压后条目占用的字节数
+    pub fn memory_usage(&self) -> u64 {
+        let inflated = self.inflated.lock().unwrap();
+        inflated
+            .iter()
+            .map(|(key, content)| (key.len() + content.len()) as u64)
+            .sum()
+    }
+
+    /// 内存不足时丢弃解压后的条目；索引是浏览归档所必需的，保留
+    pub fn clear_inflated(&self) {
+        self.inflated.lock().unwrap().clear();
+    }
+}
+
+async fn load_index(
+    source: &impl RangeSource,
+    kind: ArchiveKind,
+    meta: &ObjectMeta,
+) -> Result<ArchiveIndex> {
+    let mut index = ArchiveIndex::new(meta.etag.clone(), meta.last_modified);
+    match kind {
+        ArchiveKind::Zip => read_zip_index(source, meta.size, &mut index).await?,
+        ArchiveKind::Tar => read_tar_index(source, meta.size, &mut index).await?,
+    }
+    Ok(index)
+}
+
+fn u16_at(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn u32_at(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(data[pos..pos + 8].try_into().unwrap())
+}
+
+/// 从结尾记录（必要时经由 zip64 结尾记录）找到中央目录，再逐条读出文件
+async fn read_zip_index(
+    source: &impl RangeSource,
+    size: u64,
+    index: &mut ArchiveIndex,
+) -> Result<()> {
+    let tail_len = size.min(ZIP_TAIL_SIZE);
+    let tail_start = size - tail_len;
+    let tail = source.read(tail_start, tail_len).await?;
+    let eocd = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&pos| u32_at(&tail, pos) == 0x0605_4b50)
+        .ok_or_else(|| anyhow!("not a zip archive (no end of central directory)"))?;
+
+    let mut entries = u16_at(&tail, eocd + 10) as u64;
+    let mut directory_size = u32_at(&tail, eocd + 12) as u64;
+    let mut directory_offset = u32_at(&tail, eocd + 16) as u64;
+    if entries == 0xffff || directory_size == 0xffff_ffff || directory_offset == 0xffff_ffff {
+        if eocd < 20 || u32_at(&tail, eocd - 20) != 0x0706_4b50 {
+            return Err(anyhow!("zip64 end of central directory locator not found"));
+        }
+        let record_offset = u64_at(&tail, eocd - 20 + 8);
+        let record = source.read(record_offset, 56).await?;
+        if u32_at(&record, 0) != 0x0606_4b50 {
+            return Err(anyhow!("corrupt zip64 end of central directory"));
+        }
+        entries = u64_at(&record, 32);
+        directory_size = u64_at(&record, 40);
+        directory_offset = u64_at(&record, 48);
+    }
+    if directory_size > MAX_ZIP_DIRECTORY_SIZE
+        || directory_offset
+            .checked_add(directory_size)
+            .is_none_or(|end| end > size)
+    {
+        return Err(anyhow!("corrupt zip central directory"));
+    }
+
+    let directory = source.read(directory_offset, directory_size).await?;
+    let mut pos = 0;
+    for _ in 0..entries {
+        if pos + 46 > directory.len() || u32_at(&directory, pos) != 0x0201_4b50 {
+            return Err(anyhow!("corrupt zip central directory entry"));
+        }
+        let entry = &directory[pos..];
+        let flags = u16_at(entry, 8);
+        let method = u16_at(entry, 10);
+        let dos_time = u16_at(entry, 12);
+        let dos_date = u16_at(entry, 14);
+        let mut stored_size = u32_at(entry, 20) as u64;
+        let mut file_size = u32_at(entry, 24) as u64;
+        let name_len = u16_at(entry, 28) as usize;
+        let extra_len = u16_at(entry, 30) as usize;
+        let comment_len = u16_at(entry, 32) as usize;
+        let mut header_offset = u32_at(entry, 42) as u64;
+        let total = 46 + name_len + extra_len + comment_len;
+        if entry.len() < total {
+            return Err(anyhow!("corrupt zip central directory entry"));
+        }
+        let name = String::from_utf8_lossy(&entry[46..46 + name_len]).into_owned();
+        let mut mtime = dos_datetime(dos_date, dos_time);
+
+        // 额外字段：zip64 的 64 位大小与偏移、Unix 时间戳
+        let mut extra = &entry[46 + name_len..46 + name_len + extra_len];
+        while extra.len() >= 4 {
+            let id = u16_at(extra, 0);
+            let len = (u16_at(extra, 2) as usize).min(extra.len() - 4);
+            let mut field = &extra[4..4 + len];
+            match id {
+                0x0001 => {
+                    for value in [&mut file_size, &mut stored_size, &mut header_offset] {
+                        if *value == 0xffff_ffff && field.len() >= 8 {
+                            *value = u64_at(field, 0);
+                            field = &field[8..];
+                        }
+                    }
+                }
+                0x5455 if len >= 5 && field[0] & 1 != 0 => {
+                    let secs = i32::from_le_bytes(field[1..5].try_into().unwrap());
+                    mtime = UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64);
+                }
+                _ => {}
+            }
+            extra = &extra[4 + len..];
+        }
+
+        if name.ends_with('/') {
+            index.insert(&name, None);
+        } else {
+            // 本地文件头与数据必须在归档之内
+            if header_offset
+                .checked_add(30)
+                .and_then(|end| end.checked_add(stored_size))
+                .is_none_or(|end| end > size)
+            {
+                return Err(anyhow!("corrupt zip entry {}", name));
+            }
+            index.insert(
+                &name,
+                Some(ArchiveFile {
+                    size: file_size,
+                    mtime,
+                    data_offset: OnceLock::new(),
+                    header_offset: Some(header_offset),
+                    stored_size,
+                    method,
+                    encrypted: flags & 1 != 0,
+                }),
+            );
+        }
+        pos += total;
+    }
+    Ok(())
+}
+
+/// 由 30 字节的本地文件头算出数据的偏移
+fn zip_data_offset(header: &[u8], header_offset: u64) -> Result<u64> {
+    if header.len() < 30 || u32_at(header, 0) != 0x0403_4b50 {
+        return Err(anyhow!("corrupt zip local header at {}", header_offset));
+    }
+    let name_len = u16_at(header, 26) as u64;
+    let extra_len = u16_at(header, 28) as u64;
+    header_offset
+        .checked_add(30 + name_len + extra_len)
+        .ok_or_else(|| anyhow!("corrupt zip local header at {}", header_offset))
+}
+
+/// MS-DOS 格式的日期时间（本地时间，这里按 UTC 处理）
+fn dos_datetime(date: u16, time: u16) -> SystemTime {
+    let year = 1980 + (date >> 9) as i64;
+    let month = ((date >> 5) & 0xf).clamp(1, 12) as u32;
+    let day = (date & 0x1f).max(1) as u32;
+    let days = days_from_civil(year, month, day).max(0) as u64;
+    let secs = days * 86400
+        + (time >> 11) as u64 * 3600
+        + ((time >> 5) & 0x3f) as u64 * 60
+        + (time & 0x1f) as u64 * 2;
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+/// 按顺序读取各个 tar 头，每个头后面是按块对齐的文件内容。头由 `tar` crate 解析；
+/// 归档来自桶中的对象，不可信，大小与偏移都经过检查，损坏的头返回错误（答复 EIO）
+async fn read_tar_index(
+    source: &impl RangeSource,
+    size: u64,
+    index: &mut ArchiveIndex,
+) -> Result<()> {
+    let mut window = (0u64, Bytes::new());
+    let mut pos = 0;
+    // PAX 扩展头和 GNU 长文件名作用于下一个条目
+    let mut next_path: Option<String> = None;
+    let mut next_size: Option<u64> = None;
+    let mut next_mtime: Option<SystemTime> = None;
+
+    while pos + TAR_BLOCK <= size {
+        let (start, data) = &window;
+        if pos < *start || pos + TAR_BLOCK > start + data.len() as u64 {
+            let len = TAR_WINDOW.min(size - pos);
+            window = (pos, source.read(pos, len).await?);
+        }
+        let at = (pos - window.0) as usize;
+        let block = &window.1[at..at + TAR_BLOCK as usize];
+        if block.iter().all(|&b| b == 0) {
+            break;
+        }
+        let header = Header::from_byte_slice(block);
+        if !tar_checksum_ok(header) {
+            return Err(anyhow!(
+                "not a tar archive (bad header checksum at {})",
+                pos
+            ));
+        }
+
+        let corrupt = || anyhow!("corrupt tar header at {}", pos);
+        let entry_type = header.entry_type();
+        let entry_size = match entry_type {
+            EntryType::XHeader
+            | EntryType::XGlobalHeader
+            | EntryType::GNULongName
+            | EntryType::GNULongLink => header.entry_size().map_err(|_| corrupt())?,
+            _ => match next_size.take() {
+                Some(size) => size,
+                None => header.entry_size().map_err(|_| corrupt())?,
+            },
+        };
+        let data_offset = pos + TAR_BLOCK;
+        let data_end = data_offset
+            .checked_add(entry_size)
+            .filter(|&end| end <= size)
+            .ok_or_else(|| anyhow!("tar entry at {} extends past the end", pos))?;
+
+        match entry_type {
+            EntryType::XHeader | EntryType::GNULongName => {
+                if entry_size > MAX_TAR_META_SIZE {
+                    return Err(anyhow!("tar metadata entry at {} is too large", pos));
+                }
+                let data = source.read(data_offset, entry_size).await?;
+                if entry_type == EntryType::GNULongName {
+                    let name = data.split(|&b| b == 0).next().unwrap_or_default();
+                    next_path = Some(String::from_utf8_lossy(name).into_owned());
+                } else {
+                    for extension in PaxExtensions::new(&data) {
+                        let extension = extension.map_err(|_| corrupt())?;
+                        let Ok(value) = extension.value() else {
+                            continue;
+                        };
+                        match extension.key() {
+                            Ok("path") => next_path = Some(value.to_string()),
+                            Ok("size") => next_size = value.parse().ok(),
+                            Ok("mtime") => {
+                                next_mtime = value
+                                    .parse::<f64>()
+                                    .ok()
+                                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                                    .and_then(|secs| UNIX_EPOCH.checked_add(secs))
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            EntryType::XGlobalHeader | EntryType::GNULongLink => {}
+            _ => {
+                let name = next_path
+                    .take()
+                    .unwrap_or_else(|| String::from_utf8_lossy(&header.path_bytes()).into_owned());
+                let mtime = next_mtime.take().unwrap_or_else(|| {
+                    header
+                        .mtime()
+                        .ok()
+                        .and_then(|secs| UNIX_EPOCH.checked_add(Duration::from_secs(secs)))
+                        .unwrap_or(index.mtime)
+                });
+                match entry_type {
+                    EntryType::Regular | EntryType::Continuous => index.insert(
+                        &name,
+                        Some(ArchiveFile {
+                            size: entry_size,
+                            mtime,
+                            data_offset: OnceLock::from(data_offset),
+                            header_offset: None,
+                            stored_size: entry_size,
+                            method: ZIP_STORED,
+                            encrypted: false,
+                        }),
+                    ),
+                    EntryType::Directory => index.insert(&name, None),
+                    // 链接、设备文件等不显示
+                    _ => debug!("Skipping tar entry {} of type {:?}", name, entry_type),
+                }
+            }
+        }
+        pos = data_end
+            .checked_next_multiple_of(TAR_BLOCK)
+            .ok_or_else(corrupt)?;
+    }
+    Ok(())
+}
+
+/// 校验和按 chksum 字段全为空格计算，`set_cksum` 即按这个规则重新计算
+fn tar_checksum_ok(header: &Header) -> bool {
+    let mut expected = header.clone();
+    expected.set_cksum();
+    matches!((header.cksum(), expected.cksum()), (Ok(a), Ok(b)) if a == b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tar::TarWriter;
+    use tokio::runtime::Runtime;
+
+    impl RangeSource for Vec<u8> {
+        async fn read(&self, offset: u64, len: u64) -> Result<Bytes> {
+            Ok(Bytes::copy_from_slice(
+                &self[offset as usize..(offset + len) as usize],
+            ))
+        }
+    }
+
+    fn meta(key: &str, size: usize) -> ObjectMeta {
+        ObjectMeta {
+            key: key.to_string(),
+            size: size as u64,
+            etag: "etag".to_string(),
+            last_modified: UNIX_EPOCH,
+            content_type: None,
+            server_side_encryption: None,
+            details: Default::default(),
+        }
+    }
+
+    /// 读取索引中的文件内容
+    fn read_file(archive: &Vec<u8>, index: &ArchiveIndex, path: &str) -> Vec<u8> {
+        let runtime = Runtime::new().unwrap();
+        runtime
+            .block_on(Archives::new().read(
+                "a",
+                index,
+                path,
+                0,
+                u64::MAX,
+                |offset, len| async move { Ok(archive.read(offset, len).await?.to_vec()) },
+            ))
+            .unwrap()
+    }
+
+    /// 只含原样存放条目的 zip
+    fn build_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut directory = Vec::new();
+        for (name, data) in files {
+            let offset = out.len() as u32;
+            let mut header = Vec::new();
+            header.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+            header.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0x21, 0x58]);
+            header.extend_from_slice(&[0; 4]);
+            header.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            header.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            // 本地头的额外字段与中央目录不同，数据偏移必须由本地头确定
+            header.extend_from_slice(&4u16.to_le_bytes());
+            header.extend_from_slice(name.as_bytes());
+            header.extend_from_slice(&[0xff, 0xff, 0, 0]);
+            out.extend_from_slice(&header);
+            out.extend_from_slice(data);
+
+            directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            directory.extend_from_slice(&[20, 3, 20, 0, 0, 0, 0, 0, 0, 0, 0x21, 0x58]);
+            directory.extend_from_slice(&[0; 4]);
+            directory.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            directory.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+        let directory_offset = out.len() as u32;
+        out.extend_from_slice(&directory);
+        out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        out.extend_from_slice(&directory_offset.to_le_bytes());
+        out.extend_from_slice(&[0; 2]);
+        out
+    }
+
+    #[test]
+    fn test_zip_index() {
+        let zip = build_zip(&[
+            ("docs/", b""),
+            ("docs/readme.txt", b"hello zip"),
+            ("./data/a.bin", b"\x01\x02\x03"),
+            ("../escape.txt", b"no"),
+        ]);
+        let runtime = Runtime::new().unwrap();
+        let index = runtime
+            .block_on(load_index(
+                &zip,
+                ArchiveKind::Zip,
+                &meta("x.zip", zip.len()),
+            ))
+            .unwrap();
+
+        assert_eq!(index.file_count(), 2);
+        assert_eq!(
+            index.list(""),
+            [("data".to_string(), true), ("docs".to_string(), true)]
+        );
+        assert_eq!(index.list("docs"), [("readme.txt".to_string(), false)]);
+        assert!(matches!(index.node("docs"), Some(ArchiveNode::Dir)));
+        let Some(ArchiveNode::File(file)) = index.node("docs/readme.txt") else {
+            panic!("missing file");
+        };
+        assert_eq!(file.size, 9);
+        // 0x5821 = 2024-01-01
+        assert_eq!(file.mtime, UNIX_EPOCH + Duration::from_secs(1_704_067_200));
+        assert_eq!(read_file(&zip, &index, "docs/readme.txt"), b"hello zip");
+        assert_eq!(read_file(&zip, &index, "data/a.bin"), b"\x01\x02\x03");
+        assert!(index.node("escape.txt").is_none());
+
+        assert!(runtime
+            .block_on(load_index(
+                &b"not a zip".to_vec(),
+                ArchiveKind::Zip,
+                &meta("x.zip", 9)
+            ))
+            .is_err());
+    }
+
+    /// 构造的损坏归档返回错误（挂载中答复 EIO），不会让进程 panic
+    #[test]
+    fn test_malformed_archives() {
+        let runtime = Runtime::new().unwrap();
+        let load = |archive: &Vec<u8>, kind, key| {
+            runtime.block_on(load_index(archive, kind, &meta(key, archive.len())))
+        };
+
+        // 大小字段接近 u64::MAX（GNU base-256 编码）
+        let mut header = Header::new_gnu();
+        header.set_path("big").unwrap();
+        header.set_size(u64::MAX - 100);
+        header.set_cksum();
+        let mut tar = header.as_bytes().to_vec();
+        tar.extend_from_slice(&[0; 1024]);
+        assert!(load(&tar, ArchiveKind::Tar, "x.tar").is_err());
+
+        // PAX 中非法的 mtime 被忽略，使用头中的时间
+        for mtime in ["-5", "inf", "NaN", "1e300"] {
+            let mut builder = tar::Builder::new(Vec::new());
+            builder
+                .append_pax_extensions([("mtime", mtime.as_bytes())])
+                .unwrap();
+            let mut header = Header::new_gnu();
+            header.set_size(2);
+            header.set_mtime(60);
+            builder
+                .append_data(&mut header, "a.txt", &b"ok"[..])
+                .unwrap();
+            let tar = builder.into_inner().unwrap();
+            let index = load(&tar, ArchiveKind::Tar, "x.tar").unwrap();
+            let Some(ArchiveNode::File(file)) = index.node("a.txt") else {
+                panic!("missing file");
+            };
+            assert_eq!(file.mtime, UNIX_EPOCH + Duration::from_secs(60));
+        }
+
+        // 中央目录的偏移与本地文件头的偏移超出归档
+        let zip = build_zip(&[("a.txt", b"hello")]);
+        let eocd = zip.len() - 22;
+        let mut bad = zip.clone();
+        bad[eocd + 16..eocd + 20].copy_from_slice(&0xffff_fff0u32.to_le_bytes());
+        assert!(load(&bad, ArchiveKind::Zip, "x.zip").is_err());
+        let directory = u32_at(&zip, eocd + 16) as usize;
+        let mut bad = zip.clone();
+        bad[directory + 42..directory + 46].copy_from_slice(&0xffff_fff0u32.to_le_bytes());
+        assert!(load(&bad, ArchiveKind::Zip, "x.zip").is_err());
+    }
+
+    #[test]
+    fn test_tar_index() {
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let long_name = format!("{}/file.txt", "d".repeat(200));
+        let mut tar = TarWriter::new(Vec::new());
+        tar.append_dir("logs", mtime).unwrap();
+        tar.begin_file("logs/app.log", 5, mtime).unwrap();
+        tar.write_data(b"hello").unwrap();
+        tar.begin_file(&long_name, 3, mtime).unwrap();
+        tar.write_data(b"abc").unwrap();
+        tar.begin_file("logs/app.log", 6, mtime).unwrap();
+        tar.write_data(b"world!").unwrap();
+        let archive = tar.finish().unwrap();
+
+        let runtime = Runtime::new().unwrap();
+        let index = runtime
+            .block_on(load_index(
+                &archive,
+                ArchiveKind::Tar,
+                &meta("x.tar", archive.len()),
+            ))
+            .unwrap();
+        assert_eq!(index.file_count(), 2);
+        assert_eq!(index.list("logs"), [("app.log".to_string(), false)]);
+        // 同名文件以后出现的为准
+        assert_eq!(read_file(&archive, &index, "logs/app.log"), b"world!");
+        assert_eq!(read_file(&archive, &index, &long_name), b"abc");
+        let Some(ArchiveNode::File(file)) = index.node(&long_name) else {
+            panic!("missing file");
+        };
+        assert_eq!(file.mtime, mtime);
+
+        assert!(runtime
+            .block_on(load_index(
+                &vec![1u8; 1024],
+                ArchiveKind::Tar,
+                &meta("x.tar", 1024)
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn test_archive_kind() {
+        assert_eq!(ArchiveKind::from_key("a/b.ZIP"), Some(ArchiveKind::Zip));
+        assert_eq!(ArchiveKind::from_key("b.tar"), Some(ArchiveKind::Tar));
+        assert_eq!(ArchiveKind::from_key("a/.zip"), None);
+        assert_eq!(ArchiveKind::from_key("b.tar.gz"), None);
+        assert_eq!(normalize_path("/a//b/./c/"), Some("a/b/c".to_string()));
+        assert_eq!(normalize_path("a/../b"), None);
+    }
+}
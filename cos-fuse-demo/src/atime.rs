@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// relatime 下 atime 至少每隔这么久更新一次（与 Linux 内核一致）
+const RELATIME_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 读取时是否更新 atime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AtimeMode {
+    /// 从不更新，atime 始终等于 mtime
+    #[default]
+    NoAtime,
+    /// atime 不晚于 mtime 或已超过一天时才更新
+    Relatime,
+    /// 每次读取都更新
+    StrictAtime,
+}
+
+impl FromStr for AtimeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "noatime" => Ok(AtimeMode::NoAtime),
+            "relatime" | "atime" => Ok(AtimeMode::Relatime),
+            "strictatime" => Ok(AtimeMode::StrictAtime),
+            _ => Err(format!(
+                "unknown atime mode: {} (expected noatime, relatime or strictatime)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AtimeMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AtimeMode::NoAtime => "noatime",
+            AtimeMode::Relatime => "relatime",
+            AtimeMode::StrictAtime => "strictatime",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 本地记录的访问时间
+///
+/// COS 没有 atime，这里的记录只在本次挂载内有效，不会写回对象；重新挂载后回到 mtime
+#[derive(Debug, Default)]
+pub struct AccessTimes {
+    mode: AtimeMode,
+    times: Mutex<HashMap<String, SystemTime>>,
+}
+
+impl AccessTimes {
+    pub fn new(mode: AtimeMode) -> Self {
+        Self {
+            mode,
+            times: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 对象当前的 atime
+    pub fn atime(&self, key: &str, mtime: SystemTime) -> SystemTime {
+        if self.mode == AtimeMode::NoAtime {
+            return mtime;
+        }
+        self.times
+            .lock()
+            .unwrap()
+            .get(key)
+            .copied()
+            .unwrap_or(mtime)
+    }
+
+    /// 记录一次读取
+    pub fn record_read(&self, key: &str, mtime: SystemTime) {
+        self.record_read_at(key, mtime, SystemTime::now());
+    }
+
+    fn record_read_at(&self, key: &str, mtime: SystemTime, now: SystemTime) {
+        let update = match self.mode {
+            AtimeMode::NoAtime => false,
+            AtimeMode::StrictAtime => true,
+            AtimeMode::Relatime => {
+                let current = self.atime(key, mtime);
+                current <= mtime
+                    || now
+                        .duration_since(current)
+                        .is_ok_and(|age| age >= RELATIME_INTERVAL)
+            }
+        };
+        if update {
+            self.times.lock().unwrap().insert(key.to_string(), now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noatime_never_updates() {
+        let times = AccessTimes::new(AtimeMode::NoAtime);
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        times.record_read("a", mtime);
+        assert_eq!(times.atime("a", mtime), mtime);
+    }
+
+    #[test]
+    fn test_relatime_updates_once_per_day() {
+        let times = AccessTimes::new(AtimeMode::Relatime);
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let first = mtime + Duration::from_secs(10);
+
+        times.record_read_at("a", mtime, first);
+        assert_eq!(times.atime("a", mtime), first);
+
+        // atime 已晚于 mtime，一天内不再更新
+        times.record_read_at("a", mtime, first + Duration::from_secs(60));
+        assert_eq!(times.atime("a", mtime), first);
+
+        let next_day = first + RELATIME_INTERVAL;
+        times.record_read_at("a", mtime, next_day);
+        assert_eq!(times.atime("a", mtime), next_day);
+    }
+
+    #[test]
+    fn test_strictatime_updates_every_read() {
+        let times = AccessTimes::new(AtimeMode::StrictAtime);
+        let mtime = SystemTime::UNIX_EPOCH;
+        let later = mtime + Duration::from_secs(5);
+
+        times.record_read_at("a", mtime, mtime + Duration::from_secs(1));
+        times.record_read_at("a", mtime, later);
+        assert_eq!(times.atime("a", mtime), later);
+        assert_eq!(times.atime("b", mtime), mtime);
+    }
+}
@@ -0,0 +1,257 @@
+//! 按 `--provider` 在运行时选择的对象存储后端
+
+use anyhow::Result;
+use bytes::Bytes;
+use reqwest::header::HeaderMap;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::acl::ObjectAcl;
+use crate::cos_client::{ClientOptions, CosClient, ListPage, ObjectMeta};
+use crate::local_backend::LocalBackend;
+use crate::object_store::ObjectStore;
+use crate::oss_client::OssClient;
+use crate::s3_client::S3Client;
+
+/// 对象存储服务商
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Provider {
+    /// 腾讯云 COS
+    #[default]
+    Cos,
+    /// Amazon S3 或 MinIO 等 S3 兼容服务
+    S3,
+    /// 阿里云 OSS
+    Oss,
+    /// 本地目录（`endpoint` 为目录），用于离线演示与测试
+    Local,
+}
+
+impl FromStr for Provider {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cos" => Ok(Provider::Cos),
+            "s3" => Ok(Provider::S3),
+            "oss" => Ok(Provider::Oss),
+            "local" => Ok(Provider::Local),
+            _ => Err(format!(
+                "unknown provider: {} (expected cos, s3, oss or local)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Provider::Cos => "cos",
+            Provider::S3 => "s3",
+            Provider::Oss => "oss",
+            Provider::Local => "local",
+        };
+        f.write_str(name)
+    }
+}
+
+/// `options.provider` 选择的客户端，[`CosFilesystemBuilder`](crate::CosFilesystemBuilder)
+/// 创建的文件系统使用它
+#[derive(Debug)]
+pub enum Backend {
+    Cos(CosClient),
+    S3(S3Client),
+    Oss(OssClient),
+    Local(LocalBackend),
+}
+
+impl Backend {
+    pub fn new(bucket: String, region: String, options: &ClientOptions) -> Result<Self> {
+        Ok(match options.provider {
+            Provider::Cos => Backend::Cos(CosClient::with_options(bucket, region, options)?),
+            Provider::S3 => Backend::S3(S3Client::with_options(bucket, region, options)?),
+            Provider::Oss => Backend::Oss(OssClient::with_options(bucket, region, options)?),
+            Provider::Local => Backend::Local(LocalBackend::with_options(bucket, options)?),
+        })
+    }
+}
+
+/// 把调用转发给具体的客户端
+macro_rules! dispatch {
+    ($self:ident, $client:ident => $call:expr) => {
+        match $self {
+            Backend::Cos($client) => $call,
+            Backend::S3($client) => $call,
+            Backend::Oss($client) => $call,
+            Backend::Local($client) => $call,
+        }
+    };
+}
+
+impl ObjectStore for Backend {
+    fn bucket(&self) -> &str {
+        dispatch!(self, client => client.bucket())
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        dispatch!(self, client => client.object_url(key))
+    }
+
+    async fn list_page(
+        &self,
+        prefix: &str,
+        delimiter: bool,
+        marker: Option<&str>,
+    ) -> Result<ListPage> {
+        dispatch!(self, client => client.list_page(prefix, delimiter, marker).await)
+    }
+
+    async fn head_object(&self, key: &str) -> Result<ObjectMeta> {
+        dispatch!(self, client => client.head_object(key).await)
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        dispatch!(self, client => client.get_object(key).await)
+    }
+
+    async fn get_object_range(&self, key: &str, offset: u64, len: u64) -> Result<Bytes> {
+        dispatch!(self, client => client.get_object_range(key, offset, len).await)
+    }
+
+    async fn get_object_range_if_match(
+        &self,
+        key: &str,
+        etag: &str,
+        offset: u64,
+        len: u64,
+    ) -> Result<Bytes> {
+        dispatch!(self, client => client.get_object_range_if_match(key, etag, offset, len).await)
+    }
+
+    async fn put_object(&self, key: &str, body: Bytes, headers: HeaderMap) -> Result<String> {
+        dispatch!(self, client => client.put_object(key, body, headers).await)
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        dispatch!(self, client => client.delete_object(key).await)
+    }
+
+    async fn copy_object(&self, source_key: &str, dest_key: &str) -> Result<()> {
+        dispatch!(self, client => client.copy_object(source_key, dest_key).await)
+    }
+
+    fn region(&self) -> &str {
+        dispatch!(self, client => client.region())
+    }
+
+    fn has_credentials(&self) -> bool {
+        dispatch!(self, client => client.has_credentials())
+    }
+
+    fn presign_url(&self, key: &str, expires: Duration) -> Result<String> {
+        dispatch!(self, client => client.presign_url(key, expires))
+    }
+
+    async fn list_first_key_under(&self, prefix: &str) -> Result<Option<String>> {
+        dispatch!(self, client => client.list_first_key_under(prefix).await)
+    }
+
+    async fn head_bucket(&self) -> Result<()> {
+        dispatch!(self, client => client.head_bucket().await)
+    }
+
+    async fn get_object_if_changed(
+        &self,
+        key: &str,
+        etag: &str,
+        max_body: u64,
+    ) -> Result<Option<(ObjectMeta, Option<Bytes>)>> {
+        dispatch!(self, client => client.get_object_if_changed(key, etag, max_body).await)
+    }
+
+    async fn replace_metadata(
+        &self,
+        key: &str,
+        etag: &str,
+        user_meta: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        dispatch!(self, client => client.replace_metadata(key, etag, user_meta).await)
+    }
+
+    async fn initiate_multipart_upload(&self, key: &str, headers: HeaderMap) -> Result<String> {
+        dispatch!(self, client => client.initiate_multipart_upload(key, headers).await)
+    }
+
+    async fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        body: Bytes,
+    ) -> Result<String> {
+        dispatch!(self, client => client.upload_part(key, upload_id, part_number, body).await)
+    }
+
+    async fn upload_part_copy(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        source_key: &str,
+        source_etag: &str,
+        range: Range<u64>,
+    ) -> Result<String> {
+        dispatch!(self, client => {
+            client
+                .upload_part_copy(key, upload_id, part_number, source_key, source_etag, range)
+                .await
+        })
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[(u32, String)],
+    ) -> Result<String> {
+        dispatch!(self, client => client.complete_multipart_upload(key, upload_id, parts).await)
+    }
+
+    async fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<()> {
+        dispatch!(self, client => client.abort_multipart_upload(key, upload_id).await)
+    }
+
+    async fn get_object_acl(&self, key: &str) -> Result<ObjectAcl> {
+        dispatch!(self, client => client.get_object_acl(key).await)
+    }
+
+    async fn get_processed(&self, key: &str, rule: &str) -> Result<Bytes> {
+        dispatch!(self, client => client.get_processed(key, rule).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider() {
+        assert_eq!("S3".parse::<Provider>().unwrap(), Provider::S3);
+        assert_eq!(Provider::default().to_string(), "cos");
+        assert_eq!("oss".parse::<Provider>().unwrap(), Provider::Oss);
+        assert!("gcs".parse::<Provider>().is_err());
+
+        let options = ClientOptions {
+            provider: Provider::S3,
+            endpoint: Some("http://minio:9000".to_string()),
+            ..Default::default()
+        };
+        let backend = Backend::new("data".to_string(), String::new(), &options).unwrap();
+        assert!(matches!(backend, Backend::S3(_)));
+        assert_eq!(backend.object_url("a"), "http://minio:9000/data/a");
+    }
+}
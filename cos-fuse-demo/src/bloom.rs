@@ -0,0 +1,103 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// 默认误判率
+pub const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// 简单的布隆过滤器，用于在访问网络前快速判断路径“一定不存在”
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// 按预计元素个数和误判率创建过滤器
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let n = expected_items.max(1) as f64;
+        let p = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+
+        let num_bits = ((-n * p.ln()) / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().clamp(1.0, 16.0) as u32;
+
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    /// 用一组元素构建过滤器
+    #[cfg(test)]
+    pub fn from_items<'a, I>(items: I, false_positive_rate: f64) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let items = items.into_iter();
+        let mut filter = Self::new(items.len(), false_positive_rate);
+        for item in items {
+            filter.insert(item);
+        }
+        filter
+    }
+
+    pub fn insert(&mut self, item: &str) {
+        for bit in self.bit_positions(item) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    /// 返回 false 时元素一定不在集合中；返回 true 时可能存在
+    pub fn might_contain(&self, item: &str) -> bool {
+        self.bit_positions(item)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// 双重哈希生成 k 个位置
+    fn bit_positions(&self, item: &str) -> impl Iterator<Item = u64> {
+        let h1 = hash_with_seed(item, 0);
+        let h2 = hash_with_seed(item, 0x9e37_79b9_7f4a_7c15) | 1;
+        let num_bits = self.num_bits;
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+fn hash_with_seed(item: &str, seed: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    item.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inserted_items_are_found() {
+        let keys: Vec<String> = (0..1000).map(|i| format!("/data/file{}.txt", i)).collect();
+        let filter =
+            BloomFilter::from_items(keys.iter().map(String::as_str), DEFAULT_FALSE_POSITIVE_RATE);
+
+        assert!(keys.iter().all(|k| filter.might_contain(k)));
+    }
+
+    #[test]
+    fn test_false_positive_rate_is_bounded() {
+        let keys: Vec<String> = (0..1000).map(|i| format!("/data/file{}.txt", i)).collect();
+        let filter =
+            BloomFilter::from_items(keys.iter().map(String::as_str), DEFAULT_FALSE_POSITIVE_RATE);
+
+        let false_positives = (0..10000)
+            .filter(|i| filter.might_contain(&format!("/other/missing{}.txt", i)))
+            .count();
+        assert!(
+            false_positives < 500,
+            "too many false positives: {}",
+            false_positives
+        );
+    }
+}
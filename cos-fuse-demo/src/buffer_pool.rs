@@ -0,0 +1,198 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 最小的缓冲区容量，更小的请求也按这个大小分配
+const MIN_CLASS_SHIFT: u32 = 12;
+
+/// 最大的缓冲区容量（16M，与最大的读请求一致），更大的缓冲区用完即释放
+const MAX_CLASS_SHIFT: u32 = 24;
+
+/// 默认最多保留的空闲缓冲区总字节数
+pub const DEFAULT_POOL_BYTES: u64 = 64 << 20;
+
+/// 读请求缓冲区池：释放的缓冲区按容量（4K 起的 2 的幂）分级保留，下一个同级的读请求直接复用，
+/// 高吞吐顺序读时每个 FUSE 读请求不再单独分配和释放一块内存
+#[derive(Debug)]
+pub struct BufferPool {
+    /// 每一级的空闲缓冲区，下标 0 对应 4K
+    free: Mutex<Vec<Vec<Vec<u8>>>>,
+    /// 空闲缓冲区的总容量上限
+    max_bytes: u64,
+    pooled_bytes: AtomicU64,
+    reuses: AtomicU64,
+    allocations: AtomicU64,
+}
+
+/// 缓冲区池的统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// 空闲缓冲区的总容量
+    pub pooled_bytes: u64,
+    /// 复用空闲缓冲区的次数
+    pub reuses: u64,
+    /// 新分配缓冲区的次数
+    pub allocations: u64,
+}
+
+impl BufferPool {
+    pub fn new(max_bytes: u64) -> Arc<Self> {
+        let classes = (MAX_CLASS_SHIFT - MIN_CLASS_SHIFT + 1) as usize;
+        Arc::new(Self {
+            free: Mutex::new(vec![Vec::new(); classes]),
+            max_bytes,
+            pooled_bytes: AtomicU64::new(0),
+            reuses: AtomicU64::new(0),
+            allocations: AtomicU64::new(0),
+        })
+    }
+
+    /// 取一个长度为 `len` 的缓冲区。复用的缓冲区不清零，调用方必须写满整个缓冲区
+    pub fn get(self: &Arc<Self>, len: usize) -> PooledBuf {
+        let Some(class) = class_of(len) else {
+            self.allocations.fetch_add(1, Ordering::Relaxed);
+            return PooledBuf::unpooled(vec![0; len]);
+        };
+
+        let reused = self.free.lock().unwrap()[class].pop();
+        let mut buf = match reused {
+            Some(buf) => {
+                self.pooled_bytes
+                    .fetch_sub(buf.capacity() as u64, Ordering::Relaxed);
+                self.reuses.fetch_add(1, Ordering::Relaxed);
+                buf
+            }
+            None => {
+                self.allocations.fetch_add(1, Ordering::Relaxed);
+                Vec::with_capacity(1 << (class as u32 + MIN_CLASS_SHIFT))
+            }
+        };
+        // 容量已经足够，resize 不会重新分配
+        buf.resize(len, 0);
+        PooledBuf {
+            buf,
+            pool: Some(Arc::clone(self)),
+        }
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            pooled_bytes: self.pooled_bytes.load(Ordering::Relaxed),
+            reuses: self.reuses.load(Ordering::Relaxed),
+            allocations: self.allocations.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 归还缓冲区；池已满或容量不是某一级的大小时直接释放
+    fn put(&self, buf: Vec<u8>) {
+        let capacity = buf.capacity();
+        let Some(class) =
+            class_of(capacity).filter(|&c| capacity == 1 << (c as u32 + MIN_CLASS_SHIFT))
+        else {
+            return;
+        };
+        let mut free = self.free.lock().unwrap();
+        if self.pooled_bytes.load(Ordering::Relaxed) + capacity as u64 > self.max_bytes {
+            return;
+        }
+        self.pooled_bytes
+            .fetch_add(capacity as u64, Ordering::Relaxed);
+        free[class].push(buf);
+    }
+}
+
+/// 能容纳 `len` 字节的最小一级，超过最大一级时为 None
+fn class_of(len: usize) -> Option<usize> {
+    let shift = len
+        .max(1 << MIN_CLASS_SHIFT)
+        .next_power_of_two()
+        .trailing_zeros();
+    (shift <= MAX_CLASS_SHIFT).then(|| (shift - MIN_CLASS_SHIFT) as usize)
+}
+
+/// 从池中取出的缓冲区，丢弃时归还
+#[derive(Debug)]
+pub struct PooledBuf {
+    buf: Vec<u8>,
+    pool: Option<Arc<BufferPool>>,
+}
+
+impl PooledBuf {
+    /// 不属于任何池的缓冲区（归档、图片等已经分配好的内容）
+    pub fn unpooled(buf: Vec<u8>) -> Self {
+        Self { buf, pool: None }
+    }
+
+    /// 取出内部的 Vec，它不再归还到池中
+    pub fn into_vec(mut self) -> Vec<u8> {
+        self.pool = None;
+        std::mem::take(&mut self.buf)
+    }
+}
+
+impl From<Vec<u8>> for PooledBuf {
+    fn from(buf: Vec<u8>) -> Self {
+        Self::unpooled(buf)
+    }
+}
+
+impl Deref for PooledBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+}
+
+impl Drop for PooledBuf {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            pool.put(std::mem::take(&mut self.buf));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffers_are_reused() {
+        let pool = BufferPool::new(1 << 20);
+        let buf = pool.get(100_000);
+        assert_eq!(buf.len(), 100_000);
+        drop(buf);
+        assert_eq!(pool.stats().pooled_bytes, 128 << 10);
+
+        // 同一级的请求复用，不同级的请求重新分配
+        let mut buf = pool.get(70_000);
+        buf[..3].copy_from_slice(&[7, 7, 7]);
+        assert_eq!(&buf[..3], &[7, 7, 7]);
+        let small = pool.get(10);
+        assert_eq!(
+            pool.stats(),
+            PoolStats {
+                pooled_bytes: 0,
+                reuses: 1,
+                allocations: 2,
+            }
+        );
+        drop((buf, small));
+
+        // 超过上限的缓冲区和取出的 Vec 不归还
+        let big = pool.get(1 << 20);
+        assert_eq!(big.len(), 1 << 20);
+        drop(big);
+        assert_eq!(pool.stats().pooled_bytes, (128 << 10) + (4 << 10));
+        let vec = pool.get(4096).into_vec();
+        assert_eq!(vec.len(), 4096);
+        assert_eq!(pool.stats().pooled_bytes, 128 << 10);
+        assert!(class_of((16 << 20) + 1).is_none());
+    }
+}
@@ -0,0 +1,313 @@
+use anyhow::{anyhow, Result};
+use fuser::MountOption;
+use reqwest::header::HeaderMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+use crate::backend::Provider;
+use crate::config::{AttrOptions, CacheOptions, RuntimeOptions};
+use crate::cos_client::ClientOptions;
+use crate::filesystem::CosFilesystem;
+use crate::hooks::OperationHook;
+use crate::limiter::RequestLimits;
+use crate::mount_options::daemon_mount_options;
+use crate::object_store::ObjectStore;
+use crate::recording::Recording;
+use crate::signer::Credentials;
+use crate::spool::SpoolOptions;
+use crate::upload::UploadOptions;
+
+/// 未指定时的内容缓存目录
+pub const DEFAULT_CACHE_DIR: &str = "/tmp/cosfs_cache";
+
+/// 未指定时的写入暂存目录
+pub const DEFAULT_SPOOL_DIR: &str = "/tmp/cosfs_spool";
+
+/// 以代码配置并创建 [`CosFilesystem`]，未设置的选项与命令行的默认值一致。
+///
+/// ```no_run
+/// use cosfs::CosFilesystemBuilder;
+/// use std::time::Duration;
+///
+/// let fs = CosFilesystemBuilder::new("data-1250000000", "ap-beijing")
+///     .prefix("datasets/")
+///     .cache_dir("/var/cache/cosfs")
+///     .metadata_ttl(Duration::from_secs(30))
+///     .build()?;
+/// let _session = fuser::spawn_mount2(fs, "/mnt/datasets", &[])?;
+/// # Ok::<_, anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct CosFilesystemBuilder {
+    bucket: String,
+    region: String,
+    cache_dir: PathBuf,
+    cache_options: CacheOptions,
+    spool_options: SpoolOptions,
+    client_options: ClientOptions,
+    runtime_options: RuntimeOptions,
+    /// 未设置时按 `runtime_options` 新建
+    runtime: Option<Arc<Runtime>>,
+    attr_options: AttrOptions,
+    upload_options: UploadOptions,
+}
+
+impl CosFilesystemBuilder {
+    pub fn new(bucket: impl Into<String>, region: impl Into<String>) -> Self {
+        Self {
+            bucket: bucket.into(),
+            region: region.into(),
+            cache_dir: PathBuf::from(DEFAULT_CACHE_DIR),
+            cache_options: CacheOptions::default(),
+            spool_options: SpoolOptions {
+                dir: PathBuf::from(DEFAULT_SPOOL_DIR),
+                max_size: None,
+                fsync: Default::default(),
+            },
+            client_options: ClientOptions::default(),
+            runtime_options: RuntimeOptions::default(),
+            runtime: None,
+            attr_options: AttrOptions::default(),
+            upload_options: UploadOptions::default(),
+        }
+    }
+
+    /// 对象存储服务商，默认为腾讯云 COS
+    pub fn provider(mut self, provider: Provider) -> Self {
+        self.client_options.provider = provider;
+        self
+    }
+
+    /// 访问域名（不含 bucket），默认为 `cos.<region>.myqcloud.com`；
+    /// S3 兼容服务为服务地址，如 `http://minio:9000`；OSS 为不含 bucket 的访问域名
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.client_options.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// 只挂载桶内的这个前缀（如 `team-a/`）
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.client_options.prefix = prefix.into();
+        self
+    }
+
+    /// 访问密钥，未设置时匿名访问
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.client_options.credentials = Some(credentials);
+        self
+    }
+
+    /// 附加到每个 COS 请求上的头
+    pub fn extra_headers(mut self, headers: HeaderMap) -> Self {
+        self.client_options.extra_headers = headers;
+        self
+    }
+
+    pub fn request_limits(mut self, limits: RequestLimits) -> Self {
+        self.client_options.limits = limits;
+        self
+    }
+
+    /// 与其他客户端共享的 HTTP 客户端（连接池）
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.client_options.http_client = Some(client);
+        self
+    }
+
+    /// 录制所有后端请求与响应，或从录制中回放而不访问网络
+    pub fn recording(mut self, recording: Arc<Recording>) -> Self {
+        self.client_options.recording = Some(recording);
+        self
+    }
+
+    pub fn cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = dir.into();
+        self
+    }
+
+    /// 替换全部缓存选项，之后调用的一致性选项在此基础上修改
+    pub fn cache_options(mut self, options: CacheOptions) -> Self {
+        self.cache_options = options;
+        self
+    }
+
+    /// 元数据缓存有效期
+    pub fn metadata_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_options.metadata_ttl = ttl;
+        self
+    }
+
+    /// 元数据过期后仍直接使用、在后台重新验证的时间窗口
+    pub fn stale_while_revalidate(mut self, window: Option<Duration>) -> Self {
+        self.cache_options.stale_while_revalidate = window;
+        self
+    }
+
+    /// lookup 未命中时先向 COS 确认，其他客户端刚上传的对象立即可见
+    pub fn revalidate_misses(mut self, enabled: bool) -> Self {
+        self.cache_options.revalidate_misses = enabled;
+        self
+    }
+
+    /// 周期性重新列出访问过的目录的间隔（None 表示只在挂载时列出）
+    pub fn refresh_interval(mut self, interval: Option<Duration>) -> Self {
+        self.cache_options.refresh_interval = interval;
+        self
+    }
+
+    pub fn spool_options(mut self, options: SpoolOptions) -> Self {
+        self.spool_options = options;
+        self
+    }
+
+    /// 替换全部运行时选项（包括已注册的钩子）
+    pub fn runtime_options(mut self, options: RuntimeOptions) -> Self {
+        self.runtime_options = options;
+        self
+    }
+
+    /// 注册操作钩子，多个钩子按注册顺序调用
+    pub fn hook(mut self, hook: Arc<dyn OperationHook>) -> Self {
+        self.runtime_options.hooks.push(hook);
+        self
+    }
+
+    /// 在已有的 tokio 运行时上执行，多个挂载可以共用；其线程配置不受 `runtime_options` 影响
+    pub fn runtime(mut self, runtime: Arc<Runtime>) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    pub fn attr_options(mut self, options: AttrOptions) -> Self {
+        self.attr_options = options;
+        self
+    }
+
+    pub fn upload_options(mut self, options: UploadOptions) -> Self {
+        self.upload_options = options;
+        self
+    }
+
+    /// 与 `daemon` 相同的默认 FUSE 挂载选项：只读、fsname=cosfs、与 `max_read` 一致的请求大小等
+    ///
+    /// 需要可写挂载时用 [`merge_mount_options`](crate::mount_options::merge_mount_options)
+    /// 以 `MountOption::RW` 覆盖，与命令行的 `-o rw` 相同
+    pub fn default_mount_options(&self) -> Vec<MountOption> {
+        daemon_mount_options(&self.bucket, self.runtime_options.transfer.max_read)
+    }
+
+    /// 挂载锁的对象：(bucket, 前缀, 缓存目录)
+    pub(crate) fn lock_target(&self) -> (&str, &str, &Path) {
+        (&self.bucket, &self.client_options.prefix, &self.cache_dir)
+    }
+
+    /// 创建文件系统，交给 `fuser::spawn_mount2` 等挂载；不访问网络，需要确认权限时调用
+    /// [`CosFilesystem::probe`]
+    pub fn build(self) -> Result<CosFilesystem> {
+        if self.bucket.is_empty() {
+            return Err(anyhow!("bucket is required"));
+        }
+        // S3 兼容服务未指定地域时使用 us-east-1，OSS 在创建客户端时检查地域与访问域名
+        if self.region.is_empty() && self.client_options.provider == Provider::Cos {
+            return Err(anyhow!("region is required"));
+        }
+        let runtime = self.shared_runtime()?;
+        CosFilesystem::new(
+            self.bucket,
+            self.region,
+            &self.cache_dir,
+            &self.cache_options,
+            &self.spool_options,
+            &self.client_options,
+            &self.runtime_options,
+            runtime,
+            &self.attr_options,
+            &self.upload_options,
+        )
+    }
+
+    /// 使用给定的对象存储后端创建文件系统，bucket、region 与客户端选项（前缀、限速等）
+    /// 由后端自己处理，这里不再使用
+    pub fn build_with_store<S: ObjectStore>(self, store: S) -> Result<CosFilesystem<S>> {
+        let runtime = self.shared_runtime()?;
+        CosFilesystem::with_store(
+            Arc::new(store),
+            &self.cache_dir,
+            &self.cache_options,
+            &self.spool_options,
+            &self.runtime_options,
+            runtime,
+            &self.attr_options,
+            &self.upload_options,
+        )
+    }
+
+    /// 设置的运行时，未设置时按 `runtime_options` 新建
+    fn shared_runtime(&self) -> Result<Arc<Runtime>> {
+        match &self.runtime {
+            Some(runtime) => Ok(Arc::clone(runtime)),
+            None => Ok(Arc::new(
+                self.runtime_options
+                    .build_runtime()
+                    .map_err(|e| anyhow!("Failed to create runtime: {}", e))?,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_backend::LocalBackend;
+
+    #[test]
+    fn test_builder() {
+        let dir = tempfile::tempdir().unwrap();
+        let builder = CosFilesystemBuilder::new("bucket-1250000000", "ap-beijing")
+            .prefix("datasets/")
+            .cache_dir(dir.path().join("cache"))
+            .spool_options(SpoolOptions {
+                dir: dir.path().join("spool"),
+                max_size: None,
+                fsync: Default::default(),
+            })
+            .cache_options(CacheOptions {
+                metadata_cache_size: 10,
+                ..Default::default()
+            })
+            .metadata_ttl(Duration::from_secs(30))
+            .revalidate_misses(true);
+        assert_eq!(builder.cache_options.metadata_cache_size, 10);
+        assert_eq!(builder.cache_options.metadata_ttl, Duration::from_secs(30));
+        assert!(builder.cache_options.revalidate_misses);
+        assert_eq!(builder.client_options.prefix, "datasets/");
+        builder.build().unwrap();
+
+        assert!(CosFilesystemBuilder::new("", "ap-beijing").build().is_err());
+        assert!(CosFilesystemBuilder::new("bucket", "").build().is_err());
+    }
+
+    #[test]
+    fn test_build_with_local_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let bucket = dir.path().join("bucket");
+        std::fs::create_dir(&bucket).unwrap();
+        std::fs::write(bucket.join("hello.txt"), "hello").unwrap();
+
+        let fs = CosFilesystemBuilder::new("demo", "")
+            .cache_dir(dir.path().join("cache"))
+            .spool_options(SpoolOptions {
+                dir: dir.path().join("spool"),
+                max_size: None,
+                fsync: Default::default(),
+            })
+            .build_with_store(LocalBackend::new("demo", &bucket).unwrap())
+            .unwrap();
+        let capabilities = fs.probe(true).unwrap();
+        assert!(capabilities.read && capabilities.write);
+        // 探测写权限的对象已删除
+        assert_eq!(std::fs::read_dir(&bucket).unwrap().count(), 1);
+    }
+}
@@ -1,85 +1,603 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use log::{debug, warn};
 use lru::LruCache;
-use std::collections::HashMap;
-use std::fs;
-use std::io::Write;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io;
 use std::num::NonZeroUsize;
+use std::ops::DerefMut;
+use std::os::unix::fs::FileExt;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use crate::cache_format::{check_cache_dir, prepare_cache_dir, CACHE_FORMAT_FILE};
+use crate::compression::CacheCompression;
+use crate::config::CacheOptions;
 use crate::cos_client::ObjectMeta;
+use crate::eviction::EvictionPolicy;
+use crate::extents::ExtentMap;
+use crate::memory::{string_size, METADATA_ENTRY_SIZE};
+use crate::read_data::MappedRange;
+
+/// 写入中的临时文件后缀；超过宽限期仍存在的视为崩溃残留
+const TEMP_SUFFIX: &str = ".tmp";
+
+/// 孤立文件（临时文件、未被索引的缓存文件）的删除宽限期，避免误删正在写入的文件
+const ORPHAN_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+/// 缓存盘写满时紧急淘汰后保留的内容缓存比例
+const EMERGENCY_EVICTION_KEEP_RATIO: f64 = 0.5;
+
+/// 部分缓存对象的区间记录，与稀疏缓存文件一起持久化为 `<name>.extents`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PartialRecord {
+    /// 缓存内容对应的对象 ETag，不一致时整条记录作废
+    etag: String,
+    extents: ExtentMap,
+    /// 压缩存放的帧：逻辑偏移 -> 帧，未记录的区间原样存放
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    frames: BTreeMap<u64, Frame>,
+}
+
+/// 压缩缓存中的一帧：压缩后的 `stored` 字节存放在帧的逻辑偏移处，其后直到 `len` 为空洞
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Frame {
+    len: u64,
+    stored: u64,
+}
+
+/// 对象内容在本地缓存中的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentState {
+    /// 没有缓存
+    None,
+    /// 只缓存了部分区间
+    Partial,
+    /// 完整缓存
+    Cached,
+}
+
+impl ContentState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContentState::None => "none",
+            ContentState::Partial => "partial",
+            ContentState::Cached => "cached",
+        }
+    }
+}
+
+/// L2 内容缓存的索引：记录每个条目的大小，并交由淘汰策略决定淘汰顺序
+struct ContentIndex {
+    sizes: HashMap<String, u64>,
+    total_size: u64,
+    policy: Box<dyn EvictionPolicy>,
+}
 
 pub struct Cache {
-    /// L1 缓存：内存中的元数据缓存
-    metadata_cache: Mutex<LruCache<String, ObjectMeta>>,
-    
+    /// L1 缓存：内存中的元数据缓存（附带写入时间，用于 TTL 过期）
+    metadata_cache: Mutex<LruCache<String, (ObjectMeta, Instant)>>,
+
+    /// 缓存配置
+    options: CacheOptions,
+
+    /// L2 内容缓存索引
+    content_index: Mutex<ContentIndex>,
+
+    /// 部分缓存对象的区间记录（按需从磁盘加载）
+    partial_records: Mutex<HashMap<String, PartialRecord>>,
+
     /// L2 缓存：本地文件内容缓存
     cache_dir: PathBuf,
+
+    /// 因缓存盘空间不足而改为直接读取 COS 的次数
+    disk_full_fallbacks: AtomicU64,
 }
 
 impl Cache {
-    pub fn new(cache_dir: &Path, metadata_cache_size: usize) -> Result<Self> {
-        // 创建缓存目录
-        fs::create_dir_all(cache_dir)?;
+    pub fn new(cache_dir: &Path, options: &CacheOptions) -> Result<Self> {
+        // 创建缓存目录，并迁移或丢弃旧格式（或压缩方式不同）的缓存
+        prepare_cache_dir(cache_dir, options.compression)?;
         
         Ok(Self {
             metadata_cache: Mutex::new(LruCache::new(
-                NonZeroUsize::new(metadata_cache_size)
+                NonZeroUsize::new(options.metadata_cache_size)
                     .ok_or_else(|| anyhow!("Invalid cache size"))?,
             )),
+            options: options.clone(),
+            content_index: Mutex::new(ContentIndex {
+                sizes: HashMap::new(),
+                total_size: 0,
+                policy: options.eviction_policy.build(),
+            }),
+            partial_records: Mutex::new(HashMap::new()),
             cache_dir: cache_dir.to_path_buf(),
+            disk_full_fallbacks: AtomicU64::new(0),
         })
     }
 
-    /// 获取元数据缓存
-    pub fn get_metadata(&self, key: &str) -> Option<ObjectMeta> {
+    /// 缓存配置
+    pub fn options(&self) -> &CacheOptions {
+        &self.options
+    }
+
+    /// 查询元数据缓存，区分新鲜、可容忍的过期（stale-while-revalidate 窗口内）和未命中
+    pub fn lookup_metadata(&self, key: &str) -> MetadataLookup {
+        let ttl = self.options.metadata_ttl;
+        let stale_window = self.options.stale_while_revalidate.unwrap_or_default();
+
         let mut cache = self.metadata_cache.lock().unwrap();
-        cache.get(key).cloned()
+        match cache.get(key) {
+            Some((meta, cached_at)) if cached_at.elapsed() < ttl => {
+                MetadataLookup::Fresh(meta.clone())
+            }
+            Some((meta, cached_at)) if cached_at.elapsed() < ttl + stale_window => {
+                MetadataLookup::Stale(meta.clone())
+            }
+            Some(_) => {
+                cache.pop(key);
+                MetadataLookup::Miss
+            }
+            None => MetadataLookup::Miss,
+        }
     }
 
     /// 设置元数据缓存
     pub fn set_metadata(&self, key: String, meta: ObjectMeta) {
         let mut cache = self.metadata_cache.lock().unwrap();
-        cache.put(key, meta);
+        cache.put(key, (meta, Instant::now()));
+    }
+
+    /// 对象已删除，丢弃其元数据
+    pub fn remove_metadata(&self, key: &str) {
+        self.metadata_cache.lock().unwrap().pop(key);
+    }
+
+    /// 元数据缓存的内存占用估计
+    pub fn metadata_memory(&self) -> u64 {
+        let cache = self.metadata_cache.lock().unwrap();
+        cache
+            .iter()
+            .map(|(key, _)| string_size(key) + METADATA_ENTRY_SIZE)
+            .sum()
+    }
+
+    /// 按最近最少使用的顺序丢弃元数据，直到释放至少 `bytes` 字节，返回释放的字节数
+    pub fn trim_metadata(&self, bytes: u64) -> u64 {
+        let mut cache = self.metadata_cache.lock().unwrap();
+        let mut freed = 0;
+        while freed < bytes {
+            let Some((key, _)) = cache.pop_lru() else {
+                break;
+            };
+            freed += string_size(&key) + METADATA_ENTRY_SIZE;
+        }
+        freed
     }
 
     /// 获取文件内容缓存路径
     pub fn get_content_cache_path(&self, key: &str) -> PathBuf {
-        // 使用 URL 安全的文件名
-        let safe_key = key.replace('/', "_").replace('\\', "_");
-        self.cache_dir.join(format!("{}.cache", safe_key))
+        self.cache_dir.join(cache_file_name(key))
     }
 
-    /// 检查文件内容是否已缓存
-    pub fn is_content_cached(&self, key: &str) -> bool {
-        let cache_path = self.get_content_cache_path(key);
-        cache_path.exists()
+    /// 部分缓存对象的区间记录文件路径
+    fn get_extents_path(&self, key: &str) -> PathBuf {
+        self.get_content_cache_path(key).with_extension("extents")
     }
 
-    /// 获取缓存的内容
-    pub fn get_cached_content(&self, key: &str) -> Result<Vec<u8>> {
-        let cache_path = self.get_content_cache_path(key);
-        if !cache_path.exists() {
-            return Err(anyhow!("Content not cached for key: {}", key));
+    /// 读取部分缓存记录；ETag 不匹配或不存在时返回 None
+    fn load_partial_record(&self, key: &str, etag: &str) -> Option<PartialRecord> {
+        let mut records = self.partial_records.lock().unwrap();
+        if !records.contains_key(key) {
+            let data = fs::read(self.get_extents_path(key)).ok()?;
+            let record: PartialRecord = serde_json::from_slice(&data).ok()?;
+            records.insert(key.to_string(), record);
         }
-        
-        fs::read(cache_path).map_err(|e| anyhow!("Failed to read cached content: {}", e))
+        records.get(key).filter(|r| r.etag == etag).cloned()
+    }
+
+    /// 打开内容缓存文件；文件已被外部删除（如挂载期间执行 `cache prune`）时记录随之作废，返回 None
+    fn open_content_file(&self, key: &str) -> Result<Option<fs::File>> {
+        match fs::File::open(self.get_content_cache_path(key)) {
+            Ok(file) => Ok(Some(file)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                self.remove_content(key);
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 从部分缓存中读取 [offset, offset + len)，未完全缓存时返回 None；
+    /// 确认区间已缓存后才由 `alloc` 取得长度为 `len` 的缓冲区（如缓冲区池）
+    pub fn read_range<B: DerefMut<Target = [u8]>>(
+        &self,
+        key: &str,
+        etag: &str,
+        offset: u64,
+        len: u64,
+        alloc: impl FnOnce(usize) -> B,
+    ) -> Result<Option<B>> {
+        let Some(record) = self.load_partial_record(key, etag) else {
+            return Ok(None);
+        };
+        if !record.extents.covers(offset, offset + len) {
+            return Ok(None);
+        }
+
+        let Some(file) = self.open_content_file(key)? else {
+            return Ok(None);
+        };
+        let mut buf = alloc(len as usize);
+        if record.frames.is_empty() {
+            file.read_exact_at(&mut buf, offset)
+                .map_err(|e| anyhow!("Failed to read cached range: {}", e))?;
+        } else {
+            self.read_frames(&file, &record, offset, &mut buf)?;
+        }
+
+        self.content_index.lock().unwrap().policy.on_access(key);
+        Ok(Some(buf))
+    }
+
+    /// 把已缓存、原样存放的区间 [offset, offset + len) 映射到内存，答复时不经过用户态缓冲区；
+    /// 未完全缓存或与压缩帧重叠时返回 None
+    pub fn map_range(
+        &self,
+        key: &str,
+        etag: &str,
+        offset: u64,
+        len: u64,
+    ) -> Result<Option<MappedRange>> {
+        let Some(record) = self.load_partial_record(key, etag) else {
+            return Ok(None);
+        };
+        let end = offset + len;
+        // 帧互不重叠，只需检查在区间结束前开始的最后一帧
+        let compressed = record
+            .frames
+            .range(..end)
+            .next_back()
+            .is_some_and(|(start, frame)| start + frame.len > offset);
+        if len == 0 || compressed || !record.extents.covers(offset, end) {
+            return Ok(None);
+        }
+
+        let Some(file) = self.open_content_file(key)? else {
+            return Ok(None);
+        };
+        // 检查记录之后，对象变化时 write_range 可能已把缓存文件换成更短的新文件；
+        // 映射超出文件末尾的部分在访问时会触发 SIGBUS，这种情况改为 pread 读取
+        let file_len = file.metadata()?.len();
+        if end > file_len {
+            debug!(
+                "Cache file of {} is shorter than the recorded range ({} < {}), not mapping",
+                key, file_len, end
+            );
+            return Ok(None);
+        }
+        let range = MappedRange::map(&file, offset, len as usize)
+            .map_err(|e| anyhow!("Failed to map cached range: {}", e))?;
+        self.content_index.lock().unwrap().policy.on_access(key);
+        Ok(Some(range))
+    }
+
+    /// 从压缩缓存文件读取 `buf.len()` 字节：解压与区间重叠的帧，帧之间的内容原样读取
+    fn read_frames(
+        &self,
+        file: &fs::File,
+        record: &PartialRecord,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> Result<()> {
+        let end = offset + buf.len() as u64;
+        let read_raw = |buf: &mut [u8], start: u64, end: u64| {
+            file.read_exact_at(&mut buf[(start - offset) as usize..(end - offset) as usize], start)
+                .map_err(|e| anyhow!("Failed to read cached range: {}", e))
+        };
+
+        // 帧互不重叠，按偏移排序后结束位置也是递增的
+        let mut frames: Vec<(u64, Frame)> = record
+            .frames
+            .range(..end)
+            .rev()
+            .take_while(|(start, frame)| **start + frame.len > offset)
+            .map(|(start, frame)| (*start, *frame))
+            .collect();
+        frames.reverse();
+
+        let mut pos = offset;
+        for (start, frame) in frames {
+            if start > pos {
+                read_raw(buf, pos, start)?;
+            }
+            let mut stored = vec![0u8; frame.stored as usize];
+            file.read_exact_at(&mut stored, start)
+                .map_err(|e| anyhow!("Failed to read cached frame: {}", e))?;
+            let content = self.options.compression.decompress(stored, frame.len)?;
+            if content.len() as u64 != frame.len {
+                return Err(anyhow!("Corrupt cached frame at offset {}", start));
+            }
+            let from = pos.max(start);
+            let to = end.min(start + frame.len);
+            buf[(from - offset) as usize..(to - offset) as usize]
+                .copy_from_slice(&content[(from - start) as usize..(to - start) as usize]);
+            pos = to;
+        }
+        if pos < end {
+            read_raw(buf, pos, end)?;
+        }
+        Ok(())
     }
 
-    /// 缓存文件内容
-    pub fn cache_content(&self, key: &str, content: &[u8]) -> Result<()> {
+    /// [start, end) 中尚未被部分缓存覆盖的区间
+    pub fn missing_ranges(&self, key: &str, etag: &str, start: u64, end: u64) -> Vec<(u64, u64)> {
+        match self.load_partial_record(key, etag) {
+            Some(record) => record.extents.missing(start, end),
+            None => vec![(start, end)],
+        }
+    }
+
+    /// 将一段内容写入稀疏缓存文件并记录区间
+    pub fn write_range(&self, key: &str, etag: &str, offset: u64, data: &[u8]) -> Result<()> {
         let cache_path = self.get_content_cache_path(key);
-        
-        // 确保父目录存在
-        if let Some(parent) = cache_path.parent() {
-            fs::create_dir_all(parent)?;
+        let mut record = match self.load_partial_record(key, etag) {
+            Some(record) => record,
+            None => {
+                // 对象已变化（或首次缓存），丢弃旧内容
+                self.remove_content(key);
+                PartialRecord {
+                    etag: etag.to_string(),
+                    extents: ExtentMap::new(),
+                    frames: BTreeMap::new(),
+                }
+            }
+        };
+
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(&cache_path)?;
+        let added = if self.options.compression == CacheCompression::None {
+            file.write_all_at(data, offset)
+                .context("Failed to cache range")?;
+            record.extents.insert(offset, offset + data.len() as u64)
+        } else {
+            self.write_frames(&file, &mut record, offset, data)?
+        };
+        write_atomic(&self.get_extents_path(key), &serde_json::to_vec(&record)?)?;
+        self.partial_records
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), record);
+
+        let mut index = self.content_index.lock().unwrap();
+        match index.sizes.get_mut(key) {
+            Some(size) => {
+                *size += added;
+                index.policy.on_access(key);
+            }
+            None => {
+                index.sizes.insert(key.to_string(), added);
+                index.policy.on_insert(key, added);
+            }
         }
-        
-        fs::write(&cache_path, content)
-            .map_err(|e| anyhow!("Failed to cache content: {}", e))?;
-        
+        index.total_size += added;
+        self.evict_over_quota(&mut index);
+
         Ok(())
     }
 
+    /// 压缩写入尚未缓存的部分：按块切成独立的帧，各自存放在逻辑偏移处，
+    /// 返回实际写入的字节数（计入缓存配额）
+    fn write_frames(
+        &self,
+        file: &fs::File,
+        record: &mut PartialRecord,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<u64> {
+        let mut written = 0;
+        for (start, end) in record.extents.missing(offset, offset + data.len() as u64) {
+            let mut frame_start = start;
+            while frame_start < end {
+                let frame_end = (frame_start + self.options.block_size).min(end);
+                let chunk = &data[(frame_start - offset) as usize..(frame_end - offset) as usize];
+                let compressed = self.options.compression.compress(chunk)?;
+                if compressed.len() < chunk.len() {
+                    file.write_all_at(&compressed, frame_start)
+                        .context("Failed to cache range")?;
+                    record.frames.insert(
+                        frame_start,
+                        Frame {
+                            len: chunk.len() as u64,
+                            stored: compressed.len() as u64,
+                        },
+                    );
+                    written += compressed.len() as u64;
+                } else {
+                    // 压缩不划算的内容原样存放
+                    file.write_all_at(chunk, frame_start)
+                        .context("Failed to cache range")?;
+                    written += chunk.len() as u64;
+                }
+                record.extents.insert(frame_start, frame_end);
+                frame_start = frame_end;
+            }
+        }
+        Ok(written)
+    }
+
+    /// 删除某个键的缓存文件与索引（对象被覆盖或删除）
+    pub fn remove_content(&self, key: &str) {
+        {
+            let mut index = self.content_index.lock().unwrap();
+            if let Some(size) = index.sizes.remove(key) {
+                index.total_size -= size;
+                index.policy.on_remove(key);
+            }
+        }
+        self.remove_content_files(key);
+    }
+
+    /// 删除某个键的缓存文件（内容与区间记录）
+    fn remove_content_files(&self, key: &str) {
+        self.partial_records.lock().unwrap().remove(key);
+        let _ = fs::remove_file(self.get_content_cache_path(key));
+        let _ = fs::remove_file(self.get_extents_path(key));
+    }
+
+    /// 对象（ETag 为 `etag`、大小为 `size`）的内容缓存状态，区间覆盖整个对象时视为完整缓存。
+    /// 没有记录、记录属于旧版本的对象或缓存文件已被外部删除时为 None
+    pub fn content_state(&self, key: &str, etag: &str, size: u64) -> ContentState {
+        match self.load_partial_record(key, etag) {
+            Some(_) if !self.get_content_cache_path(key).exists() => ContentState::None,
+            Some(record) if record.extents.covers(0, size) => ContentState::Cached,
+            Some(_) => ContentState::Partial,
+            None => ContentState::None,
+        }
+    }
+
+    /// 检查对象（ETag 为 `etag`、大小为 `size`）的内容是否已完整缓存
+    pub fn is_content_cached(&self, key: &str, etag: &str, size: u64) -> bool {
+        self.content_state(key, etag, size) == ContentState::Cached
+    }
+
+    /// 获取完整缓存的内容；没有缓存或缓存属于旧版本的对象时返回 None
+    pub fn get_cached_content(&self, key: &str, etag: &str, size: u64) -> Result<Option<Vec<u8>>> {
+        self.read_range(key, etag, 0, size, |len| vec![0u8; len])
+    }
+
+    /// 缓存整个对象的内容。与部分缓存一样记录 ETag，对象变化后不会再被读到
+    pub fn cache_content(&self, key: &str, etag: &str, content: &[u8]) -> Result<()> {
+        // 整体重写：丢弃旧版本的内容以及同一版本已缓存的区间
+        self.remove_content(key);
+        self.write_range(key, etag, 0, content)
+    }
+
+    /// 按淘汰策略删除条目，直到内容缓存总大小回到配额以内
+    fn evict_over_quota(&self, index: &mut ContentIndex) {
+        let Some(max_size) = self.options.content_cache_max_size else {
+            return;
+        };
+
+        while index.total_size > max_size {
+            let Some(victim) = index.policy.victim() else {
+                break;
+            };
+            if let Some(size) = index.sizes.remove(&victim) {
+                index.total_size -= size;
+            }
+            debug!("Evicting cached content for key: {}", victim);
+            self.remove_content_files(&victim);
+        }
+    }
+
+    /// 缓存盘写满后的处理：计数，并按淘汰策略紧急释放一半的内容缓存
+    pub fn handle_disk_full(&self) -> usize {
+        self.disk_full_fallbacks.fetch_add(1, Ordering::Relaxed);
+
+        let mut index = self.content_index.lock().unwrap();
+        let target = (index.total_size as f64 * EMERGENCY_EVICTION_KEEP_RATIO) as u64;
+        let mut evicted = 0;
+        while index.total_size > target {
+            let Some(victim) = index.policy.victim() else {
+                break;
+            };
+            if let Some(size) = index.sizes.remove(&victim) {
+                index.total_size -= size;
+            }
+            self.remove_content_files(&victim);
+            evicted += 1;
+        }
+
+        warn!(
+            "Cache disk is full, evicted {} entries ({} bytes of content remain)",
+            evicted, index.total_size
+        );
+        evicted
+    }
+
+    /// 后台维护：清理过期元数据、删除残留临时文件和未被索引的缓存文件、
+    /// 修正索引并执行配额淘汰
+    pub fn run_maintenance(&self) -> MaintenanceReport {
+        let mut report = MaintenanceReport::default();
+
+        // 过期元数据（超出 TTL 与 stale-while-revalidate 窗口）
+        {
+            let expiry = self.options.metadata_ttl
+                + self.options.stale_while_revalidate.unwrap_or_default();
+            let mut cache = self.metadata_cache.lock().unwrap();
+            let expired: Vec<String> = cache
+                .iter()
+                .filter(|(_, (_, cached_at))| cached_at.elapsed() >= expiry)
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in expired {
+                cache.pop(&key);
+                report.expired_metadata += 1;
+            }
+        }
+
+        let mut index = self.content_index.lock().unwrap();
+
+        // 文件已不存在的索引条目
+        let missing: Vec<String> = index
+            .sizes
+            .keys()
+            .filter(|key| !self.get_content_cache_path(key).exists())
+            .cloned()
+            .collect();
+        for key in missing {
+            if let Some(size) = index.sizes.remove(&key) {
+                index.total_size -= size;
+            }
+            index.policy.on_remove(&key);
+            self.partial_records.lock().unwrap().remove(&key);
+            report.dropped_index_entries += 1;
+        }
+
+        // 磁盘上的孤立文件
+        let mut known_files = HashSet::new();
+        for key in index.sizes.keys() {
+            known_files.insert(self.get_content_cache_path(key));
+            known_files.insert(self.get_extents_path(key));
+        }
+        if let Ok(entries) = fs::read_dir(&self.cache_dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if known_files.contains(&path)
+                    || !path.is_file()
+                    || entry.file_name() == CACHE_FORMAT_FILE
+                {
+                    continue;
+                }
+                let old_enough = entry
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .and_then(|mtime| SystemTime::now().duration_since(mtime).ok())
+                    .is_some_and(|age| age >= ORPHAN_GRACE_PERIOD);
+                if old_enough && fs::remove_file(&path).is_ok() {
+                    debug!("Removed orphaned cache file: {}", path.display());
+                    report.removed_orphans += 1;
+                }
+            }
+        }
+
+        // 配额淘汰并压缩索引
+        let before = index.sizes.len();
+        self.evict_over_quota(&mut index);
+        report.evicted = before - index.sizes.len();
+        index.sizes.shrink_to_fit();
+        self.partial_records.lock().unwrap().shrink_to_fit();
+
+        report
+    }
+
     /// 清理缓存
     pub fn clear(&self) -> Result<()> {
         // 清理元数据缓存
@@ -89,9 +607,17 @@ impl Cache {
         }
         
         // 清理文件内容缓存
+        {
+            let mut index = self.content_index.lock().unwrap();
+            while index.policy.victim().is_some() {}
+            index.sizes.clear();
+            index.total_size = 0;
+        }
+        self.partial_records.lock().unwrap().clear();
+
         if self.cache_dir.exists() {
             fs::remove_dir_all(&self.cache_dir)?;
-            fs::create_dir_all(&self.cache_dir)?;
+            prepare_cache_dir(&self.cache_dir, self.options.compression)?;
         }
         
         Ok(())
@@ -115,32 +641,212 @@ impl Cache {
         CacheStats {
             metadata_cache_size,
             content_cache_size,
+            disk_full_fallbacks: self.disk_full_fallbacks.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 内容缓存文件的扩展名
+pub const CACHE_FILE_EXTENSION: &str = "cache";
+
+/// 对象键对应的缓存文件名：对 `%`、`/`、`\\` 做百分号转义，文件名可无损还原为键
+fn cache_file_name(key: &str) -> String {
+    let mut name = String::with_capacity(key.len() + 6);
+    for c in key.chars() {
+        match c {
+            '%' => name.push_str("%25"),
+            '/' => name.push_str("%2F"),
+            '\\' => name.push_str("%5C"),
+            _ => name.push(c),
+        }
+    }
+    name.push('.');
+    name.push_str(CACHE_FILE_EXTENSION);
+    name
+}
+
+/// 从缓存文件名还原对象键；不是内容缓存文件时返回 None
+fn key_from_cache_file_name(name: &str) -> Option<String> {
+    let escaped = name.strip_suffix(&format!(".{}", CACHE_FILE_EXTENSION))?;
+    let mut key = String::with_capacity(escaped.len());
+    let mut rest = escaped;
+    while let Some(pos) = rest.find('%') {
+        key.push_str(&rest[..pos]);
+        let code = rest.get(pos + 1..pos + 3)?;
+        key.push(match code {
+            "25" => '%',
+            "2F" => '/',
+            "5C" => '\\',
+            _ => return None,
+        });
+        rest = &rest[pos + 3..];
+    }
+    key.push_str(rest);
+    Some(key)
+}
+
+/// 离线清理缓存的筛选条件
+#[derive(Debug, Default)]
+pub struct PruneOptions {
+    /// 只处理以该前缀开头的对象
+    pub prefix: Option<String>,
+    /// 删除超过该时间未被访问的条目
+    pub older_than: Option<Duration>,
+    /// 筛选范围内的缓存总大小上限，超出时从最久未访问的开始删除
+    pub max_size: Option<u64>,
+    /// 只统计不删除
+    pub dry_run: bool,
+}
+
+/// 离线清理结果
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    pub scanned: usize,
+    pub removed: usize,
+    pub removed_bytes: u64,
+    pub remaining_bytes: u64,
+}
+
+/// 按前缀/时间/大小有选择地清理缓存目录（无需挂载，可在运行中的挂载旁执行）
+pub fn prune_cache_dir(cache_dir: &Path, options: &PruneOptions) -> Result<PruneReport> {
+    check_cache_dir(cache_dir)?;
+    let mut report = PruneReport::default();
+
+    // (最后访问时间, 大小, 内容文件路径)
+    let mut candidates = Vec::new();
+    for entry in fs::read_dir(cache_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let Some(key) = file_name.to_str().and_then(key_from_cache_file_name) else {
+            continue;
+        };
+        if let Some(prefix) = &options.prefix {
+            if !key.starts_with(prefix.as_str()) {
+                continue;
+            }
+        }
+
+        let metadata = entry.metadata()?;
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        let last_used = metadata.accessed().unwrap_or(modified).max(modified);
+        candidates.push((last_used, metadata.len(), entry.path()));
+    }
+    report.scanned = candidates.len();
+
+    // 最久未访问的排在前面
+    candidates.sort_by_key(|(last_used, _, _)| *last_used);
+
+    let now = SystemTime::now();
+    let mut remaining: u64 = candidates.iter().map(|(_, size, _)| size).sum();
+    let filtering = options.older_than.is_some() || options.max_size.is_some();
+
+    for (last_used, size, path) in candidates {
+        let too_old = options
+            .older_than
+            .is_some_and(|age| now.duration_since(last_used).unwrap_or_default() >= age);
+        let over_size = options.max_size.is_some_and(|max| remaining > max);
+        if filtering && !too_old && !over_size {
+            continue;
         }
+
+        if !options.dry_run {
+            fs::remove_file(&path)?;
+            let _ = fs::remove_file(path.with_extension("extents"));
+        }
+        debug!("Pruned cache file: {}", path.display());
+        report.removed += 1;
+        report.removed_bytes += size;
+        remaining -= size;
+    }
+
+    report.remaining_bytes = remaining;
+    Ok(report)
+}
+
+/// 先写入同目录下的临时文件再重命名
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(TEMP_SUFFIX);
+    let tmp_path = PathBuf::from(tmp_name);
+
+    if let Err(e) = fs::write(&tmp_path, data) {
+        // 写到一半失败（例如磁盘已满）时不留下残缺的临时文件
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
     }
+    fs::rename(&tmp_path, path)
+}
+
+/// 判断错误是否由缓存盘空间不足（ENOSPC/EDQUOT）引起
+pub fn is_disk_full(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .and_then(|e| e.raw_os_error())
+            .is_some_and(|code| code == libc::ENOSPC || code == libc::EDQUOT)
+    })
+}
+
+/// 一次后台维护的结果
+#[derive(Debug, Default)]
+pub struct MaintenanceReport {
+    pub expired_metadata: usize,
+    pub dropped_index_entries: usize,
+    pub removed_orphans: usize,
+    pub evicted: usize,
+}
+
+/// 元数据缓存查询结果
+#[derive(Debug)]
+pub enum MetadataLookup {
+    Fresh(ObjectMeta),
+    Stale(ObjectMeta),
+    Miss,
 }
 
 #[derive(Debug)]
 pub struct CacheStats {
     pub metadata_cache_size: usize,
     pub content_cache_size: usize,
+    pub disk_full_fallbacks: u64,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
     use tempfile::TempDir;
 
+    fn vec_buf(len: usize) -> Vec<u8> {
+        vec![0; len]
+    }
+
+    /// 只返回新鲜的元数据缓存
+    fn fresh_metadata(cache: &Cache, key: &str) -> Option<ObjectMeta> {
+        match cache.lookup_metadata(key) {
+            MetadataLookup::Fresh(meta) => Some(meta),
+            _ => None,
+        }
+    }
+
+    fn test_options() -> CacheOptions {
+        CacheOptions {
+            metadata_cache_size: 100,
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_cache_creation() {
         let temp_dir = TempDir::new().unwrap();
-        let cache = Cache::new(temp_dir.path(), 100).unwrap();
+        let cache = Cache::new(temp_dir.path(), &test_options()).unwrap();
         assert!(cache.cache_dir.exists());
     }
 
     #[test]
     fn test_metadata_cache() {
         let temp_dir = TempDir::new().unwrap();
-        let cache = Cache::new(temp_dir.path(), 100).unwrap();
+        let cache = Cache::new(temp_dir.path(), &test_options()).unwrap();
         
         let meta = ObjectMeta {
             key: "test.txt".to_string(),
@@ -148,29 +854,322 @@ mod tests {
             last_modified: std::time::SystemTime::now(),
             etag: "test-etag".to_string(),
             content_type: Some("text/plain".to_string()),
+            server_side_encryption: None,
+            details: Default::default(),
         };
         
         // 测试设置和获取
         cache.set_metadata("test.txt".to_string(), meta.clone());
-        let cached_meta = cache.get_metadata("test.txt");
+        let cached_meta = fresh_metadata(&cache, "test.txt");
         assert!(cached_meta.is_some());
         assert_eq!(cached_meta.unwrap().size, 100);
+
+        // 内存不足时按 LRU 顺序丢弃
+        cache.set_metadata("other.txt".to_string(), meta.clone());
+        let entry = string_size("test.txt") + METADATA_ENTRY_SIZE;
+        assert_eq!(cache.metadata_memory(), 2 * entry + 1);
+        assert_eq!(cache.trim_metadata(1), entry);
+        assert!(fresh_metadata(&cache, "test.txt").is_none());
+        assert!(fresh_metadata(&cache, "other.txt").is_some());
     }
 
     #[test]
     fn test_content_cache() {
         let temp_dir = TempDir::new().unwrap();
-        let cache = Cache::new(temp_dir.path(), 100).unwrap();
+        let cache = Cache::new(temp_dir.path(), &test_options()).unwrap();
         
         let key = "test/file.txt";
         let content = b"Hello, World!";
         
         // 测试缓存内容
-        cache.cache_content(key, content).unwrap();
-        assert!(cache.is_content_cached(key));
+        let size = content.len() as u64;
+        cache.cache_content(key, "e1", content).unwrap();
+        assert!(cache.is_content_cached(key, "e1", size));
         
         // 测试获取缓存内容
-        let cached_content = cache.get_cached_content(key).unwrap();
-        assert_eq!(cached_content, content);
+        let cached_content = cache.get_cached_content(key, "e1", size).unwrap();
+        assert_eq!(cached_content.as_deref(), Some(&content[..]));
+
+        // 对象变化后旧内容不会被读到，重新缓存时整体替换
+        assert!(!cache.is_content_cached(key, "e2", size));
+        assert!(cache.get_cached_content(key, "e2", size).unwrap().is_none());
+        cache.cache_content(key, "e2", b"Bye").unwrap();
+        assert_eq!(cache.get_cached_content(key, "e2", 3).unwrap().unwrap(), b"Bye");
+        assert!(cache.get_cached_content(key, "e1", size).unwrap().is_none());
+        assert_eq!(cache.content_index.lock().unwrap().total_size, 3);
+    }
+
+    #[test]
+    fn test_metadata_ttl_expiry() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = CacheOptions {
+            metadata_ttl: Duration::from_millis(20),
+            ..test_options()
+        };
+        let cache = Cache::new(temp_dir.path(), &options).unwrap();
+
+        let meta = ObjectMeta {
+            key: "test.txt".to_string(),
+            size: 100,
+            last_modified: std::time::SystemTime::now(),
+            etag: "test-etag".to_string(),
+            content_type: None,
+            server_side_encryption: None,
+            details: Default::default(),
+        };
+
+        cache.set_metadata("test.txt".to_string(), meta);
+        assert!(fresh_metadata(&cache, "test.txt").is_some());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(fresh_metadata(&cache, "test.txt").is_none());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_stale_while_revalidate_window() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = CacheOptions {
+            metadata_ttl: Duration::from_millis(20),
+            stale_while_revalidate: Some(Duration::from_millis(200)),
+            ..test_options()
+        };
+        let cache = Cache::new(temp_dir.path(), &options).unwrap();
+
+        let meta = ObjectMeta {
+            key: "test.txt".to_string(),
+            size: 100,
+            last_modified: std::time::SystemTime::now(),
+            etag: "test-etag".to_string(),
+            content_type: None,
+            server_side_encryption: None,
+            details: Default::default(),
+        };
+        cache.set_metadata("test.txt".to_string(), meta);
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(matches!(cache.lookup_metadata("test.txt"), MetadataLookup::Stale(_)));
+
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(matches!(cache.lookup_metadata("test.txt"), MetadataLookup::Miss));
+    }
+
+    #[test]
+    fn test_content_cache_eviction_respects_quota() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = CacheOptions {
+            content_cache_max_size: Some(25),
+            ..test_options()
+        };
+        let cache = Cache::new(temp_dir.path(), &options).unwrap();
+
+        cache.cache_content("a", "e", &[0u8; 10]).unwrap();
+        cache.cache_content("b", "e", &[0u8; 10]).unwrap();
+        cache.get_cached_content("a", "e", 10).unwrap();
+        cache.cache_content("c", "e", &[0u8; 10]).unwrap();
+
+        // 默认 LRU：b 最久未被访问，应被淘汰
+        assert!(cache.is_content_cached("a", "e", 10));
+        assert!(!cache.is_content_cached("b", "e", 10));
+        assert!(cache.is_content_cached("c", "e", 10));
+    }
+
+    #[test]
+    fn test_disk_full_detection_and_emergency_eviction() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = Cache::new(temp_dir.path(), &test_options()).unwrap();
+
+        let enospc = anyhow::Error::new(io::Error::from_raw_os_error(libc::ENOSPC))
+            .context("Failed to cache content");
+        assert!(is_disk_full(&enospc));
+        assert!(!is_disk_full(&anyhow!("Failed to cache content")));
+
+        for key in ["a", "b", "c", "d"] {
+            cache.cache_content(key, "e", &[0u8; 10]).unwrap();
+        }
+        assert_eq!(cache.handle_disk_full(), 2);
+        assert!(!cache.is_content_cached("a", "e", 10));
+        assert!(!cache.is_content_cached("b", "e", 10));
+        assert!(cache.is_content_cached("d", "e", 10));
+        assert_eq!(cache.get_stats().disk_full_fallbacks, 1);
+    }
+
+    #[test]
+    fn test_partial_range_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = Cache::new(temp_dir.path(), &test_options()).unwrap();
+        let key = "videos/big.mp4";
+
+        assert!(cache.read_range(key, "e1", 100, 10, vec_buf).unwrap().is_none());
+
+        cache.write_range(key, "e1", 100, &[7u8; 50]).unwrap();
+        assert_eq!(cache.read_range(key, "e1", 110, 10, vec_buf).unwrap().unwrap(), vec![7u8; 10]);
+        assert!(cache.read_range(key, "e1", 140, 20, vec_buf).unwrap().is_none());
+        assert_eq!(cache.missing_ranges(key, "e1", 0, 200), vec![(0, 100), (150, 200)]);
+        assert_eq!(&cache.map_range(key, "e1", 120, 30).unwrap().unwrap()[..], &[7u8; 30]);
+        assert!(cache.map_range(key, "e1", 90, 20).unwrap().is_none());
+
+        // 文件比记录短时不映射（映射越过文件末尾会在访问时触发 SIGBUS）
+        let path = cache.get_content_cache_path(key);
+        let file = fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(130).unwrap();
+        assert!(cache.map_range(key, "e1", 120, 30).unwrap().is_none());
+        assert!(cache.map_range(key, "e1", 100, 30).unwrap().is_some());
+        file.set_len(150).unwrap();
+        drop(file);
+
+        // 区间记录持久化在磁盘上，新实例也能读到
+        let reopened = Cache::new(temp_dir.path(), &test_options()).unwrap();
+        assert!(reopened.read_range(key, "e1", 100, 50, vec_buf).unwrap().is_some());
+
+        // ETag 变化后旧内容作废
+        assert!(cache.read_range(key, "e2", 100, 10, vec_buf).unwrap().is_none());
+        cache.write_range(key, "e2", 0, &[1u8; 10]).unwrap();
+        assert!(cache.read_range(key, "e2", 100, 10, vec_buf).unwrap().is_none());
+        assert_eq!(cache.read_range(key, "e2", 0, 10, vec_buf).unwrap().unwrap(), vec![1u8; 10]);
+
+        assert_eq!(cache.content_state(key, "e2", 200), ContentState::Partial);
+        assert_eq!(cache.content_state(key, "e2", 10), ContentState::Cached);
+        assert_eq!(cache.content_state(key, "e3", 10), ContentState::None);
+        cache.cache_content("small.txt", "e", b"hi").unwrap();
+        assert_eq!(cache.content_state("small.txt", "e", 2), ContentState::Cached);
+        assert_eq!(cache.content_state("small.txt", "e2", 2), ContentState::None);
+        assert_eq!(cache.content_state("missing.txt", "e", 2), ContentState::None);
+    }
+
+    #[test]
+    fn test_compressed_content_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = CacheOptions {
+            compression: CacheCompression::Zstd,
+            block_size: 64 << 10,
+            ..test_options()
+        };
+        let cache = Cache::new(temp_dir.path(), &options).unwrap();
+
+        // 整个对象压缩存放，配额按压缩后的大小计算
+        let text = b"GET /index.html 200\n".repeat(1000);
+        cache.cache_content("access.log", "e", &text).unwrap();
+        let size = text.len() as u64;
+        assert_eq!(cache.get_cached_content("access.log", "e", size).unwrap().unwrap(), text);
+        let stored = fs::metadata(cache.get_content_cache_path("access.log")).unwrap().len();
+        assert!(stored * 10 < text.len() as u64);
+        assert_eq!(cache.content_index.lock().unwrap().total_size, stored);
+
+        // 分块缓存：按块压缩，跨块和块内的读取都能还原内容
+        let key = "logs/big.log";
+        let data: Vec<u8> = (0..200_000u32).flat_map(|i| (i / 100).to_le_bytes()).collect();
+        cache.write_range(key, "e1", 0, &data[..300_000]).unwrap();
+        // 与已缓存区间重叠的写入只补上缺失部分
+        cache.write_range(key, "e1", 262_144, &data[262_144..]).unwrap();
+        let record = cache.load_partial_record(key, "e1").unwrap();
+        assert_eq!(record.frames.len(), 13);
+        for (offset, len) in [(0, 10), (65_530, 20), (100_000, 500_000), (799_990, 10)] {
+            let got = cache.read_range(key, "e1", offset, len, vec_buf).unwrap().unwrap();
+            assert_eq!(got, &data[offset as usize..(offset + len) as usize]);
+        }
+        // 压缩存放的区间不能映射
+        assert!(cache.map_range(key, "e1", 100_000, 10).unwrap().is_none());
+
+        // 不可压缩的内容原样存放
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let noise: Vec<u8> = (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        cache.write_range("noise.bin", "e", 0, &noise).unwrap();
+        assert!(cache.load_partial_record("noise.bin", "e").unwrap().frames.is_empty());
+        assert_eq!(cache.read_range("noise.bin", "e", 100, 50, vec_buf).unwrap().unwrap(), &noise[100..150]);
+
+        // 关闭压缩后重新挂载时丢弃压缩过的缓存
+        let reopened = Cache::new(temp_dir.path(), &test_options()).unwrap();
+        assert!(!reopened.is_content_cached("access.log", "e", size));
+        assert!(reopened.read_range(key, "e1", 0, 10, vec_buf).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_maintenance_removes_orphans_and_expired_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = CacheOptions {
+            metadata_ttl: Duration::from_millis(10),
+            ..test_options()
+        };
+        let cache = Cache::new(temp_dir.path(), &options).unwrap();
+
+        let meta = ObjectMeta {
+            key: "a.txt".to_string(),
+            size: 1,
+            last_modified: SystemTime::now(),
+            etag: "etag".to_string(),
+            content_type: None,
+            server_side_encryption: None,
+            details: Default::default(),
+        };
+        cache.set_metadata("a.txt".to_string(), meta);
+        cache.cache_content("a.txt", "e", b"a").unwrap();
+
+        // 模拟上次崩溃留下的临时文件和未被索引的缓存文件
+        let stale_time = SystemTime::now() - Duration::from_secs(3600);
+        for name in ["leftover.cache.tmp", "unknown.cache"] {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, b"junk").unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(stale_time)
+                .unwrap();
+        }
+
+        std::thread::sleep(Duration::from_millis(20));
+        let report = cache.run_maintenance();
+
+        assert_eq!(report.expired_metadata, 1);
+        assert_eq!(report.removed_orphans, 2);
+        assert!(cache.is_content_cached("a.txt", "e", 1));
+        assert!(!temp_dir.path().join("unknown.cache").exists());
+    }
+
+    #[test]
+    fn test_cache_file_name_round_trip() {
+        for key in ["logs/2024/app.log", "a%2Fb", "dir\\file", "plain.txt"] {
+            let name = cache_file_name(key);
+            assert!(!name.contains('/'));
+            assert_eq!(key_from_cache_file_name(&name).as_deref(), Some(key));
+        }
+        assert!(key_from_cache_file_name("other.extents").is_none());
+    }
+
+    #[test]
+    fn test_prune_by_prefix_and_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = Cache::new(temp_dir.path(), &test_options()).unwrap();
+        let cached = |key| cache.is_content_cached(key, "e", 100);
+        cache.cache_content("logs/a.log", "e", &[0u8; 100]).unwrap();
+        cache.cache_content("logs/b.log", "e", &[0u8; 100]).unwrap();
+        cache.cache_content("data/c.bin", "e", &[0u8; 100]).unwrap();
+
+        let options = PruneOptions {
+            prefix: Some("logs/".to_string()),
+            max_size: Some(150),
+            ..Default::default()
+        };
+        let report = prune_cache_dir(temp_dir.path(), &options).unwrap();
+        assert_eq!(report.scanned, 2);
+        assert_eq!(report.removed, 1);
+        assert_eq!(report.remaining_bytes, 100);
+        assert!(cached("data/c.bin"));
+
+        let options = PruneOptions {
+            prefix: Some("logs/".to_string()),
+            ..Default::default()
+        };
+        prune_cache_dir(temp_dir.path(), &options).unwrap();
+        assert!(!cached("logs/a.log"));
+        assert!(!cached("logs/b.log"));
+        assert!(cached("data/c.bin"));
+    }
+}
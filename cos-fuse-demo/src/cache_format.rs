@@ -0,0 +1,249 @@
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::cache::CACHE_FILE_EXTENSION;
+use crate::compression::CacheCompression;
+
+/// 缓存目录中记录格式版本的文件
+pub const CACHE_FORMAT_FILE: &str = "FORMAT";
+
+/// 当前缓存格式版本
+///
+/// - v1：没有版本文件，内容文件名为把 `/` 替换成 `_` 的对象键
+/// - v2：内容文件名对 `%`、`/`、`\` 做百分号转义，部分缓存附带 `.extents` 区间记录
+/// - v3：整体缓存的对象也附带 `.extents` 记录，读取前按 ETag 校验
+pub const CACHE_FORMAT_VERSION: u32 = 3;
+
+/// 没有版本文件的缓存目录视为该版本
+const LEGACY_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheFormat {
+    version: u32,
+    /// 内容文件的压缩方式，与挂载选项不一致时丢弃缓存
+    #[serde(default)]
+    compression: CacheCompression,
+}
+
+/// 读取缓存目录的格式；目录为空时返回 None
+fn read_format(cache_dir: &Path) -> Result<Option<CacheFormat>> {
+    let format_path = cache_dir.join(CACHE_FORMAT_FILE);
+    match fs::read(&format_path) {
+        Ok(data) => {
+            let format: CacheFormat = serde_json::from_slice(&data)
+                .with_context(|| format!("Corrupt cache format file {}", format_path.display()))?;
+            Ok(Some(format))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if fs::read_dir(cache_dir)?.next().is_none() {
+                Ok(None)
+            } else {
+                Ok(Some(CacheFormat {
+                    version: LEGACY_FORMAT_VERSION,
+                    compression: CacheCompression::None,
+                }))
+            }
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 读取缓存目录的格式版本；目录为空时返回 None
+fn read_version(cache_dir: &Path) -> Result<Option<u32>> {
+    Ok(read_format(cache_dir)?.map(|format| format.version))
+}
+
+fn write_format(cache_dir: &Path, version: u32, compression: CacheCompression) -> Result<()> {
+    let data = serde_json::to_vec(&CacheFormat {
+        version,
+        compression,
+    })?;
+    fs::write(cache_dir.join(CACHE_FORMAT_FILE), data)?;
+    Ok(())
+}
+
+/// 把 `from` 版本的缓存原地升级到下一个版本；无法升级时返回 false。
+/// 以后的格式变更在这里按版本加入迁移步骤
+fn migrate(cache_dir: &Path, from: u32) -> Result<bool> {
+    match from {
+        // v2 的整体缓存没有记录 ETag，无法判断是否过期，只删除这些文件，部分缓存保留
+        2 => {
+            for entry in fs::read_dir(cache_dir)? {
+                let path = entry?.path();
+                let is_content = path
+                    .extension()
+                    .is_some_and(|ext| ext == CACHE_FILE_EXTENSION);
+                if is_content && !path.with_extension("extents").exists() {
+                    fs::remove_file(&path)?;
+                }
+            }
+            Ok(true)
+        }
+        // v1 的文件名无法还原出对象键（`_` 可能本来就在键里），只能丢弃
+        _ => Ok(false),
+    }
+}
+
+/// 删除缓存目录中的所有内容
+fn discard(cache_dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(cache_dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// 准备缓存目录：新目录写入当前版本，旧版本依次迁移，无法迁移时整体丢弃；
+/// 压缩方式与 `compression` 不同的缓存也整体丢弃。
+/// 由更新版本程序创建的缓存不做任何修改，直接报错
+pub fn prepare_cache_dir(cache_dir: &Path, compression: CacheCompression) -> Result<()> {
+    fs::create_dir_all(cache_dir)?;
+
+    let Some(format) = read_format(cache_dir)? else {
+        return write_format(cache_dir, CACHE_FORMAT_VERSION, compression);
+    };
+    let mut version = format.version;
+
+    if version > CACHE_FORMAT_VERSION {
+        return Err(anyhow!(
+            "Cache directory {} uses format v{}, newer than supported v{}",
+            cache_dir.display(),
+            version,
+            CACHE_FORMAT_VERSION
+        ));
+    }
+
+    while version < CACHE_FORMAT_VERSION {
+        if !migrate(cache_dir, version)? {
+            warn!(
+                "Discarding cache in {}: format v{} cannot be migrated to v{}",
+                cache_dir.display(),
+                version,
+                CACHE_FORMAT_VERSION
+            );
+            discard(cache_dir)?;
+            break;
+        }
+        version += 1;
+        info!(
+            "Migrated cache in {} to format v{}",
+            cache_dir.display(),
+            version
+        );
+        write_format(cache_dir, version, format.compression)?;
+    }
+
+    // 无法迁移的旧缓存在上面已经丢弃，目录为空时不需要再处理
+    if format.compression != compression && read_format(cache_dir)?.is_some() {
+        warn!(
+            "Discarding cache in {}: stored with compression {}, now {}",
+            cache_dir.display(),
+            format.compression,
+            compression
+        );
+        discard(cache_dir)?;
+    }
+
+    write_format(cache_dir, CACHE_FORMAT_VERSION, compression)
+}
+
+/// 离线工具使用：只接受当前版本（或空目录）的缓存，不做迁移
+pub fn check_cache_dir(cache_dir: &Path) -> Result<()> {
+    match read_version(cache_dir)? {
+        None => Ok(()),
+        Some(CACHE_FORMAT_VERSION) => Ok(()),
+        Some(version) => Err(anyhow!(
+            "Cache directory {} uses format v{} (expected v{}); mount it once to migrate",
+            cache_dir.display(),
+            version,
+            CACHE_FORMAT_VERSION
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_new_dir_gets_current_version() {
+        let temp_dir = TempDir::new().unwrap();
+        prepare_cache_dir(temp_dir.path(), CacheCompression::None).unwrap();
+        assert_eq!(
+            read_version(temp_dir.path()).unwrap(),
+            Some(CACHE_FORMAT_VERSION)
+        );
+        check_cache_dir(temp_dir.path()).unwrap();
+    }
+
+    #[test]
+    fn test_legacy_cache_is_discarded() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("dir_file.txt.cache"), b"old").unwrap();
+        assert!(check_cache_dir(temp_dir.path()).is_err());
+
+        prepare_cache_dir(temp_dir.path(), CacheCompression::None).unwrap();
+        assert!(!temp_dir.path().join("dir_file.txt.cache").exists());
+        assert_eq!(
+            read_version(temp_dir.path()).unwrap(),
+            Some(CACHE_FORMAT_VERSION)
+        );
+    }
+
+    #[test]
+    fn test_v2_drops_whole_object_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        write_format(temp_dir.path(), 2, CacheCompression::None).unwrap();
+        for name in ["whole.cache", "partial.cache", "partial.extents"] {
+            fs::write(temp_dir.path().join(name), b"data").unwrap();
+        }
+
+        // 没有区间记录的整体缓存无法校验 ETag，迁移时删除；部分缓存保留
+        prepare_cache_dir(temp_dir.path(), CacheCompression::None).unwrap();
+        assert!(!temp_dir.path().join("whole.cache").exists());
+        assert!(temp_dir.path().join("partial.cache").exists());
+        assert!(temp_dir.path().join("partial.extents").exists());
+        assert_eq!(
+            read_version(temp_dir.path()).unwrap(),
+            Some(CACHE_FORMAT_VERSION)
+        );
+    }
+
+    #[test]
+    fn test_newer_version_is_left_untouched() {
+        let temp_dir = TempDir::new().unwrap();
+        write_format(
+            temp_dir.path(),
+            CACHE_FORMAT_VERSION + 1,
+            CacheCompression::None,
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("a.cache"), b"new").unwrap();
+
+        assert!(prepare_cache_dir(temp_dir.path(), CacheCompression::None).is_err());
+        assert!(temp_dir.path().join("a.cache").exists());
+    }
+
+    #[test]
+    fn test_compression_change_discards_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        prepare_cache_dir(temp_dir.path(), CacheCompression::None).unwrap();
+        fs::write(temp_dir.path().join("a.cache"), b"plain").unwrap();
+
+        prepare_cache_dir(temp_dir.path(), CacheCompression::None).unwrap();
+        assert!(temp_dir.path().join("a.cache").exists());
+
+        prepare_cache_dir(temp_dir.path(), CacheCompression::Zstd).unwrap();
+        assert!(!temp_dir.path().join("a.cache").exists());
+        let format = read_format(temp_dir.path()).unwrap().unwrap();
+        assert_eq!(format.compression, CacheCompression::Zstd);
+    }
+}
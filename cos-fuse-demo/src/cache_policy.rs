@@ -0,0 +1,226 @@
+use anyhow::{anyhow, bail, Result};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::CacheOptions;
+use crate::config_file::{ConfigFile, Section};
+use crate::readahead::AccessPattern;
+
+/// 配置文件中按前缀设置缓存方式的节名：`[cache."prefix/"]`
+pub const CACHE_SECTION: &str = "cache";
+
+/// 顺序扫描时整体下载的对象最多包含的块数，更大的对象按块缓存并预读
+const WHOLE_SCAN_MAX_BLOCKS: u64 = 16;
+
+/// 顺序扫描的对象超过内容缓存上限的 1/N 时直接读取，避免一次扫描把常用对象挤出缓存
+const DIRECT_SCAN_CACHE_FRACTION: u64 = 4;
+
+/// 配置的缓存方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CachePolicy {
+    /// 按文件句柄的访问模式和对象大小自动选择
+    #[default]
+    Auto,
+    /// 第一次读取时下载整个对象
+    Whole,
+    /// 按块下载读到的部分并预读
+    Block,
+    /// 按读取的区间直接请求 COS，不写入内容缓存
+    Direct,
+}
+
+impl FromStr for CachePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(CachePolicy::Auto),
+            "whole" => Ok(CachePolicy::Whole),
+            "block" => Ok(CachePolicy::Block),
+            "direct" => Ok(CachePolicy::Direct),
+            _ => Err(format!(
+                "unknown cache policy: {} (expected auto, whole, block or direct)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CachePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CachePolicy::Auto => "auto",
+            CachePolicy::Whole => "whole",
+            CachePolicy::Block => "block",
+            CachePolicy::Direct => "direct",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 一次读取实际使用的缓存方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    Whole,
+    Block,
+    Direct,
+}
+
+impl CachePolicy {
+    /// 按访问模式和对象大小决定缓存方式：不写入内容缓存的对象总是直接读取；
+    /// `auto` 下不超过一块的对象整体缓存，随机读取按块缓存，顺序扫描的中等对象整体下载，
+    /// 大到会挤掉相当一部分缓存的对象直接读取，其余按块缓存并预读
+    pub fn choose(self, pattern: AccessPattern, size: u64, options: &CacheOptions) -> CacheMode {
+        if !options.should_cache_content(size) {
+            return CacheMode::Direct;
+        }
+        match self {
+            CachePolicy::Whole => CacheMode::Whole,
+            CachePolicy::Block => CacheMode::Block,
+            CachePolicy::Direct => CacheMode::Direct,
+            CachePolicy::Auto if size <= options.block_size => CacheMode::Whole,
+            CachePolicy::Auto if pattern == AccessPattern::Random => CacheMode::Block,
+            CachePolicy::Auto => {
+                if options
+                    .content_cache_max_size
+                    .is_some_and(|max| size > max / DIRECT_SCAN_CACHE_FRACTION)
+                {
+                    CacheMode::Direct
+                } else if size <= options.block_size.saturating_mul(WHOLE_SCAN_MAX_BLOCKS) {
+                    CacheMode::Whole
+                } else {
+                    CacheMode::Block
+                }
+            }
+        }
+    }
+}
+
+/// 按前缀设置的缓存方式，更长的前缀优先
+#[derive(Debug, Clone, Default)]
+pub struct CachePolicies {
+    /// 没有前缀匹配时使用的方式
+    default: CachePolicy,
+    /// 按前缀长度降序排列
+    rules: Vec<(String, CachePolicy)>,
+}
+
+impl CachePolicies {
+    /// 从配置文件的 `[cache."prefix/"]` 节读取规则
+    pub fn from_config(config: &ConfigFile, default: CachePolicy) -> Result<Self> {
+        let mut rules: Vec<(String, CachePolicy)> = Vec::new();
+        for section in config.sections(CACHE_SECTION) {
+            let (prefix, policy) = Self::rule_from_section(section)?;
+            if rules.iter().any(|(p, _)| *p == prefix) {
+                bail!(
+                    "line {}: duplicate cache policy for prefix '{}'",
+                    section.line,
+                    prefix
+                );
+            }
+            rules.push((prefix, policy));
+        }
+        rules.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        Ok(Self { default, rules })
+    }
+
+    fn rule_from_section(section: &Section) -> Result<(String, CachePolicy)> {
+        let prefix = match section.name.as_slice() {
+            [_, prefix] => prefix.trim_start_matches('/').to_string(),
+            _ => bail!(
+                "line {}: expected [{}.\"prefix/\"], got [{}]",
+                section.line,
+                CACHE_SECTION,
+                section.name.join(".")
+            ),
+        };
+        let mut policy = None;
+        for (key, value) in &section.entries {
+            match key.as_str() {
+                "policy" => {
+                    policy = Some(
+                        value
+                            .parse()
+                            .map_err(|e| anyhow!("line {}: {}", section.line, e))?,
+                    )
+                }
+                _ => bail!(
+                    "line {}: unknown key '{}' (expected policy)",
+                    section.line,
+                    key
+                ),
+            }
+        }
+        let policy = policy.ok_or_else(|| anyhow!("line {}: missing policy", section.line))?;
+        Ok((prefix, policy))
+    }
+
+    /// 对象 `key` 的缓存方式
+    pub fn resolve(&self, key: &str) -> CachePolicy {
+        self.rules
+            .iter()
+            .find(|(prefix, _)| key.starts_with(prefix.as_str()))
+            .map_or(self.default, |(_, policy)| *policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1 << 20;
+
+    #[test]
+    fn test_cache_policy() {
+        let config = ConfigFile::parse(
+            "[cache.\"logs/\"] policy = direct\n\
+             [cache.\"logs/index/\"] policy = whole\n",
+        )
+        .unwrap();
+        let policies = CachePolicies::from_config(&config, CachePolicy::Block).unwrap();
+        assert_eq!(policies.resolve("logs/2024.log"), CachePolicy::Direct);
+        assert_eq!(policies.resolve("logs/index/a.idx"), CachePolicy::Whole);
+        assert_eq!(policies.resolve("data/a.bin"), CachePolicy::Block);
+
+        for text in [
+            "[cache] policy = auto",
+            "[cache.\"a/\"] policy = sometimes",
+            "[cache.\"a/\"] mode = auto",
+            "[cache.\"a/\"]",
+            "[cache.\"a/\"] policy = auto\n[cache.\"a/\"] policy = block",
+        ] {
+            let config = ConfigFile::parse(text).unwrap();
+            assert!(
+                CachePolicies::from_config(&config, CachePolicy::Auto).is_err(),
+                "{}",
+                text
+            );
+        }
+
+        // auto：按访问模式和大小选择
+        let options = CacheOptions {
+            content_cache_max_size: Some(1024 * MIB),
+            ..Default::default()
+        };
+        let auto = |pattern, size| CachePolicy::Auto.choose(pattern, size, &options);
+        assert_eq!(auto(AccessPattern::Random, MIB / 2), CacheMode::Whole);
+        assert_eq!(auto(AccessPattern::Random, 100 * MIB), CacheMode::Block);
+        assert_eq!(auto(AccessPattern::Sequential, 8 * MIB), CacheMode::Whole);
+        assert_eq!(auto(AccessPattern::Sequential, 100 * MIB), CacheMode::Block);
+        assert_eq!(auto(AccessPattern::Backward, 512 * MIB), CacheMode::Direct);
+
+        // 不写入内容缓存的对象总是直接读取
+        let options = CacheOptions {
+            content_cache_max_object_size: Some(10 * MIB),
+            ..Default::default()
+        };
+        assert_eq!(
+            CachePolicy::Whole.choose(AccessPattern::Sequential, 20 * MIB, &options),
+            CacheMode::Direct
+        );
+        assert_eq!(
+            CachePolicy::Block.choose(AccessPattern::Sequential, 5 * MIB, &options),
+            CacheMode::Block
+        );
+    }
+}
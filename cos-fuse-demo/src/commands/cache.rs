@@ -0,0 +1,84 @@
+use anyhow::{anyhow, Result};
+use clap::{Arg, ArgMatches, Command};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::cache::{prune_cache_dir, PruneOptions};
+use crate::config::{parse_duration, parse_size};
+
+/// `cache` 子命令定义
+pub fn command() -> Command {
+    Command::new("cache")
+        .about("Inspect and maintain the local content cache")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("prune")
+                .about("Selectively remove cached content without wiping the whole cache")
+                .arg(
+                    Arg::new("older-than")
+                        .long("older-than")
+                        .value_name("DURATION")
+                        .help("Remove entries not accessed within this duration (e.g. 7d)")
+                        .value_parser(parse_duration),
+                )
+                .arg(
+                    Arg::new("prefix")
+                        .long("prefix")
+                        .value_name("PREFIX")
+                        .help("Only consider objects whose key starts with this prefix"),
+                )
+                .arg(
+                    Arg::new("max-size")
+                        .long("max-size")
+                        .value_name("SIZE")
+                        .help("Shrink the selected entries to at most this size, oldest first (e.g. 5G)")
+                        .value_parser(parse_size),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Only report what would be removed")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+}
+
+/// 执行 `cache` 子命令
+pub fn run(matches: &ArgMatches, cache_dir: &Path) -> Result<()> {
+    match matches.subcommand() {
+        Some(("prune", sub)) => prune(sub, cache_dir),
+        Some((name, _)) => Err(anyhow!("Unknown cache subcommand: {}", name)),
+        None => Err(anyhow!("Missing cache subcommand")),
+    }
+}
+
+fn prune(matches: &ArgMatches, cache_dir: &Path) -> Result<()> {
+    if !cache_dir.is_dir() {
+        return Err(anyhow!(
+            "Cache directory does not exist: {}",
+            cache_dir.display()
+        ));
+    }
+
+    let options = PruneOptions {
+        prefix: matches.get_one::<String>("prefix").cloned(),
+        older_than: matches.get_one::<Duration>("older-than").copied(),
+        max_size: matches.get_one::<u64>("max-size").copied(),
+        dry_run: matches.get_flag("dry-run"),
+    };
+
+    let report = prune_cache_dir(cache_dir, &options)?;
+    println!(
+        "{} {} of {} cached objects ({} bytes), {} bytes remain",
+        if options.dry_run {
+            "Would remove"
+        } else {
+            "Removed"
+        },
+        report.removed,
+        report.scanned,
+        report.removed_bytes,
+        report.remaining_bytes
+    );
+    Ok(())
+}
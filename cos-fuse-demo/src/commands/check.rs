@@ -0,0 +1,330 @@
+use anyhow::{anyhow, Result};
+use clap::{Arg, ArgMatches, Command};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, SystemTime};
+use tokio::runtime::Runtime;
+
+use crate::cos_client::{error_status, ClientOptions, CosClient};
+use crate::object_store::ObjectStore;
+use crate::signer::{Credentials, SECRET_ID_ENV, SECRET_KEY_ENV};
+
+/// 单项检查的超时，避免网络不通时长时间无输出
+const STEP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 时钟偏差超过该值时提示
+const CLOCK_SKEW_WARN: Duration = Duration::from_secs(30);
+
+/// 时钟偏差超过该值时签名会被拒绝
+const CLOCK_SKEW_FAIL: Duration = Duration::from_secs(15 * 60);
+
+/// 抽样读取的字节数
+const SAMPLE_READ_SIZE: u64 = 4096;
+
+/// `check` 子命令定义
+pub fn command() -> Command {
+    Command::new("check")
+        .about("Diagnose why a bucket cannot be mounted (DNS, TLS, credentials, clock, listing, reads)")
+        .arg(
+            Arg::new("bucket")
+                .short('b')
+                .long("bucket")
+                .value_name("BUCKET")
+                .help("Tencent Cloud COS bucket name")
+                .required(true),
+        )
+        .arg(
+            Arg::new("region")
+                .short('r')
+                .long("region")
+                .value_name("REGION")
+                .help("Tencent Cloud COS region (e.g., ap-beijing)")
+                .required(true),
+        )
+        .arg(
+            Arg::new("key")
+                .long("key")
+                .value_name("KEY")
+                .help("Object to read for the sample GET (default: the first listed object)"),
+        )
+}
+
+/// 单项检查的结论
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Ok,
+    Warn,
+    Fail,
+    Skip,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Outcome::Ok => " OK ",
+            Outcome::Warn => "WARN",
+            Outcome::Fail => "FAIL",
+            Outcome::Skip => "SKIP",
+        };
+        write!(f, "[{}]", label)
+    }
+}
+
+/// 逐项打印检查结果并汇总
+#[derive(Debug, Default)]
+struct Report {
+    warnings: usize,
+    failures: usize,
+}
+
+impl Report {
+    fn record(&mut self, step: &str, outcome: Outcome, detail: impl fmt::Display) {
+        match outcome {
+            Outcome::Warn => self.warnings += 1,
+            Outcome::Fail => self.failures += 1,
+            Outcome::Ok | Outcome::Skip => {}
+        }
+        println!("{} {:<12} {}", outcome, step, detail);
+    }
+}
+
+/// 执行 `check` 子命令，`extra_headers` 附加到每个 COS 请求上
+pub fn run(matches: &ArgMatches, extra_headers: HeaderMap) -> Result<()> {
+    let bucket = matches.get_one::<String>("bucket").unwrap().clone();
+    let region = matches.get_one::<String>("region").unwrap().clone();
+    let key = matches.get_one::<String>("key").cloned();
+
+    let options = ClientOptions {
+        extra_headers,
+        credentials: Credentials::from_env(),
+        ..Default::default()
+    };
+    let client = CosClient::with_options(bucket, region, &options)?;
+
+    let mut report = Report::default();
+    Runtime::new()?.block_on(diagnose(&client, key, &mut report));
+
+    println!(
+        "\n{} failure(s), {} warning(s)",
+        report.failures, report.warnings
+    );
+    if report.failures > 0 {
+        return Err(anyhow!("{} check(s) failed", report.failures));
+    }
+    Ok(())
+}
+
+/// 给单步加上超时
+async fn step<T>(fut: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::time::timeout(STEP_TIMEOUT, fut)
+        .await
+        .unwrap_or_else(|_| Err(anyhow!("timed out after {:?}", STEP_TIMEOUT)))
+}
+
+async fn diagnose(client: &CosClient, key: Option<String>, report: &mut Report) {
+    // DNS
+    let host = client.host();
+    match step(async {
+        Ok(tokio::net::lookup_host((host, 443))
+            .await?
+            .collect::<Vec<_>>())
+    })
+    .await
+    {
+        Ok(addrs) if !addrs.is_empty() => {
+            let addrs: Vec<String> = addrs.iter().map(|a| a.ip().to_string()).collect();
+            report.record(
+                "dns",
+                Outcome::Ok,
+                format!("{} -> {}", host, addrs.join(", ")),
+            );
+        }
+        Ok(_) => {
+            report.record("dns", Outcome::Fail, format!("{} has no addresses", host));
+            return;
+        }
+        Err(e) => {
+            report.record(
+                "dns",
+                Outcome::Fail,
+                format!(
+                    "cannot resolve {}: {} (check bucket name, region and DNS)",
+                    host, e
+                ),
+            );
+            return;
+        }
+    }
+
+    // TLS 与桶：任何 HTTP 响应都说明连接和证书没有问题
+    let ping = match step(client.ping(false)).await {
+        Ok(ping) => {
+            report.record("tls", Outcome::Ok, "HTTPS connection established");
+            ping
+        }
+        Err(e) => {
+            report.record(
+                "tls",
+                Outcome::Fail,
+                format!(
+                    "{:#} (check proxy settings, firewall and CA certificates)",
+                    e
+                ),
+            );
+            return;
+        }
+    };
+    match ping.status.as_u16() {
+        200..=299 => report.record(
+            "bucket",
+            Outcome::Ok,
+            "bucket exists and allows anonymous HEAD",
+        ),
+        404 => {
+            report.record(
+                "bucket",
+                Outcome::Fail,
+                format!(
+                    "bucket {} does not exist in region {}{}",
+                    client.bucket(),
+                    client.region(),
+                    ping.ids
+                ),
+            );
+            return;
+        }
+        status => report.record(
+            "bucket",
+            Outcome::Ok,
+            format!("bucket exists (anonymous HEAD returned {})", status),
+        ),
+    }
+
+    // 时钟偏差：签名中的时间与服务端相差太大时请求会被拒绝
+    match ping.date {
+        Some(server_time) => {
+            let (outcome, detail) = classify_clock_skew(SystemTime::now(), server_time);
+            report.record("clock", outcome, detail);
+        }
+        None => report.record("clock", Outcome::Skip, "response has no Date header"),
+    }
+
+    // 访问密钥
+    if client.has_credentials() {
+        match step(client.ping(true)).await {
+            Ok(ping) if ping.status.is_success() => {
+                report.record("credentials", Outcome::Ok, "signed HEAD Bucket accepted")
+            }
+            Ok(ping) => report.record(
+                "credentials",
+                Outcome::Fail,
+                format!(
+                    "signed HEAD Bucket returned {}{} (wrong SecretId/SecretKey, expired session \
+                     token or missing cos:HeadBucket)",
+                    ping.status, ping.ids
+                ),
+            ),
+            Err(e) => report.record("credentials", Outcome::Fail, format!("{:#}", e)),
+        }
+    } else {
+        report.record(
+            "credentials",
+            Outcome::Warn,
+            format!(
+                "{} and {} are not set; only public-read buckets can be mounted",
+                SECRET_ID_ENV, SECRET_KEY_ENV
+            ),
+        );
+    }
+
+    // 列表
+    let first_key = match step(client.list_first_key()).await {
+        Ok(first_key) => {
+            let detail = match &first_key {
+                Some(key) => format!("first object: {}", key),
+                None => "bucket is empty".to_string(),
+            };
+            report.record("list", Outcome::Ok, detail);
+            first_key
+        }
+        Err(e) => {
+            let hint = if error_status(&e) == Some(StatusCode::FORBIDDEN) {
+                " (missing cos:GetBucket)"
+            } else {
+                ""
+            };
+            report.record("list", Outcome::Fail, format!("{:#}{}", e, hint));
+            None
+        }
+    };
+
+    // 抽样读取
+    let Some(key) = key.or(first_key) else {
+        report.record("get", Outcome::Skip, "no object to read (use --key)");
+        return;
+    };
+    let sample = async {
+        let meta = client.head_object(&key).await?;
+        let data = client
+            .get_object_range(&key, 0, meta.size.min(SAMPLE_READ_SIZE))
+            .await?;
+        Ok(data.len())
+    };
+    match step(sample).await {
+        Ok(len) => report.record("get", Outcome::Ok, format!("read {} bytes of {}", len, key)),
+        Err(e) => {
+            let hint = if error_status(&e) == Some(StatusCode::FORBIDDEN) {
+                " (missing cos:GetObject)"
+            } else {
+                ""
+            };
+            report.record("get", Outcome::Fail, format!("{}: {:#}{}", key, e, hint));
+        }
+    }
+}
+
+/// 比较本机与服务端时间
+fn classify_clock_skew(local: SystemTime, server: SystemTime) -> (Outcome, String) {
+    let (skew, direction) = match local.duration_since(server) {
+        Ok(ahead) => (ahead, "ahead of"),
+        Err(e) => (e.duration(), "behind"),
+    };
+    // Date 头只精确到秒
+    let detail = format!("local clock is {}s {} COS", skew.as_secs(), direction);
+    if skew > CLOCK_SKEW_FAIL {
+        (
+            Outcome::Fail,
+            format!(
+                "{}; signed requests will be rejected, sync the clock (NTP)",
+                detail
+            ),
+        )
+    } else if skew > CLOCK_SKEW_WARN {
+        (Outcome::Warn, detail)
+    } else {
+        (Outcome::Ok, detail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_clock_skew() {
+        let server = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        let (outcome, detail) = classify_clock_skew(server + Duration::from_secs(2), server);
+        assert_eq!(outcome, Outcome::Ok);
+        assert_eq!(detail, "local clock is 2s ahead of COS");
+
+        let (outcome, _) = classify_clock_skew(server - Duration::from_secs(120), server);
+        assert_eq!(outcome, Outcome::Warn);
+
+        let (outcome, detail) = classify_clock_skew(server - Duration::from_secs(3600), server);
+        assert_eq!(outcome, Outcome::Fail);
+        assert!(detail.starts_with("local clock is 3600s behind COS"));
+    }
+}
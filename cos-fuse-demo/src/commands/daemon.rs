@@ -0,0 +1,338 @@
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Arg, ArgMatches, Command};
+use fuser::spawn_mount2;
+use log::info;
+use reqwest::header::HeaderMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::builder::DEFAULT_SPOOL_DIR;
+use crate::config::{
+    headers_from_config, AttrOptions, CacheOptions, RuntimeOptions, HEADERS_SECTION,
+};
+use crate::config_file::{ConfigFile, Section};
+use crate::cos_client::{build_http_client, ClientOptions};
+use crate::filesystem::CosFilesystem;
+use crate::idmap::{IdMap, IDMAP_SECTION};
+use crate::metrics::{MetricLabels, StatsdOptions};
+use crate::mount::check_mount_point;
+use crate::mount_lock::MountLock;
+use crate::mount_options::{daemon_mount_options, merge_mount_options, parse_fuse_option};
+use crate::permissions::{PermissionOverrides, PERM_SECTION};
+use crate::profile::{default_config_path, PROFILE_SECTION};
+use crate::sd_notify;
+use crate::signer::Credentials;
+use crate::spool::SpoolOptions;
+use crate::upload::{UploadOptions, UPLOAD_SECTION};
+
+/// 配置文件中挂载节的名字：`[mount."datasets"]`
+pub const MOUNT_SECTION: &str = "mount";
+
+/// `daemon` 子命令定义
+pub fn command() -> Command {
+    Command::new("daemon")
+        .about("Serve every [mount.\"name\"] in the config file from one process")
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("FILE")
+                .help("Config file declaring the mounts (default: ~/.config/cosfs/config)")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .help("Mount even if another instance holds the lock on a cache directory or mount point")
+                .action(clap::ArgAction::SetTrue),
+        )
+}
+
+/// 配置文件中的一个挂载
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MountSpec {
+    name: String,
+    bucket: String,
+    region: String,
+    endpoint: Option<String>,
+    /// 挂载桶内的这个前缀，为空时挂载整个桶
+    prefix: String,
+    mount_point: PathBuf,
+    /// 未设置时为 `<cache-dir>/<name>`
+    cache_dir: Option<PathBuf>,
+    /// 未设置时为 `/tmp/cosfs_spool/<name>`
+    spool_dir: Option<PathBuf>,
+    /// 逗号分隔的 FUSE 挂载选项，覆盖默认值
+    options: Vec<String>,
+}
+
+impl MountSpec {
+    fn from_section(section: &Section) -> Result<Self> {
+        let [_, name] = section.name.as_slice() else {
+            bail!(
+                "line {}: expected [{}.\"name\"], got [{}]",
+                section.line,
+                MOUNT_SECTION,
+                section.name.join(".")
+            );
+        };
+
+        let (mut bucket, mut region, mut mount_point) = (None, None, None);
+        let mut spec = MountSpec {
+            name: name.clone(),
+            bucket: String::new(),
+            region: String::new(),
+            endpoint: None,
+            prefix: String::new(),
+            mount_point: PathBuf::new(),
+            cache_dir: None,
+            spool_dir: None,
+            options: Vec::new(),
+        };
+        for (key, value) in &section.entries {
+            match key.as_str() {
+                "bucket" => bucket = Some(value.clone()),
+                "region" => region = Some(value.clone()),
+                "mountpoint" => mount_point = Some(PathBuf::from(value)),
+                "endpoint" => spec.endpoint = Some(value.clone()),
+                "prefix" => spec.prefix = value.clone(),
+                "cache_dir" => spec.cache_dir = Some(PathBuf::from(value)),
+                "spool_dir" => spec.spool_dir = Some(PathBuf::from(value)),
+                "options" => {
+                    spec.options = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|option| !option.is_empty())
+                        .map(String::from)
+                        .collect()
+                }
+                _ => bail!(
+                    "line {}: unknown key '{}' (expected bucket, region, mountpoint, endpoint, \
+                     prefix, cache_dir, spool_dir or options)",
+                    section.line,
+                    key
+                ),
+            }
+        }
+
+        let missing = |key: &str| anyhow!("line {}: mount '{}' has no {}", section.line, name, key);
+        spec.bucket = bucket.ok_or_else(|| missing("bucket"))?;
+        spec.region = region.ok_or_else(|| missing("region"))?;
+        spec.mount_point = mount_point.ok_or_else(|| missing("mountpoint"))?;
+        Ok(spec)
+    }
+}
+
+/// 读取配置文件中的所有挂载，名字和挂载点都不能重复
+fn mounts_from_config(config: &ConfigFile) -> Result<Vec<MountSpec>> {
+    let mut mounts: Vec<MountSpec> = Vec::new();
+    for section in config.sections(MOUNT_SECTION) {
+        let spec = MountSpec::from_section(section)?;
+        if mounts.iter().any(|other| other.name == spec.name) {
+            bail!("line {}: duplicate mount '{}'", section.line, spec.name);
+        }
+        if mounts
+            .iter()
+            .any(|other| other.mount_point == spec.mount_point)
+        {
+            bail!(
+                "line {}: mount point {} is used twice",
+                section.line,
+                spec.mount_point.display()
+            );
+        }
+        mounts.push(spec);
+    }
+    if mounts.is_empty() {
+        bail!(
+            "no [{}.\"name\"] sections in the config file",
+            MOUNT_SECTION
+        );
+    }
+    Ok(mounts)
+}
+
+/// 执行 `daemon` 子命令：挂载配置文件中的所有挂载，直到收到 Ctrl+C。
+/// 各挂载共用访问密钥、异步运行时和 HTTP 连接池，`[headers]`、`[perm]`、`[idmap]`、`[upload]` 对所有挂载生效；
+/// 指定了 `statsd` 时每个挂载分别推送指标，并带上各自的 bucket、prefix 与挂载点
+pub fn run(
+    matches: &ArgMatches,
+    cache_dir: &Path,
+    extra_headers: HeaderMap,
+    statsd: Option<StatsdOptions>,
+) -> Result<()> {
+    let config_path = matches
+        .get_one::<PathBuf>("config")
+        .cloned()
+        .or_else(default_config_path)
+        .ok_or_else(|| anyhow!("--config is required when HOME is not set"))?;
+    let config = ConfigFile::load(&config_path)?;
+    config.check_sections(&[
+        HEADERS_SECTION,
+        PERM_SECTION,
+        IDMAP_SECTION,
+        UPLOAD_SECTION,
+        PROFILE_SECTION,
+        MOUNT_SECTION,
+    ])?;
+    let mounts = mounts_from_config(&config)?;
+
+    let mut headers = headers_from_config(&config).context("Invalid extra headers")?;
+    headers.extend(extra_headers);
+    let attr_options = AttrOptions {
+        permissions: PermissionOverrides::from_config(&config)
+            .context("Invalid permission rules")?,
+        id_map: IdMap::from_config(&config).context("Invalid id mapping")?,
+        ..Default::default()
+    };
+    let upload_options = UploadOptions::from_config(&config).context("Invalid upload settings")?;
+    let runtime_options = RuntimeOptions::default();
+    let runtime = Arc::new(
+        runtime_options
+            .build_runtime()
+            .map_err(|e| anyhow!("Failed to create runtime: {}", e))?,
+    );
+    let client_options = ClientOptions {
+        http_client: Some(build_http_client(&headers)?),
+        extra_headers: headers,
+        credentials: Credentials::from_env(),
+        ..Default::default()
+    };
+
+    // 任一挂载失败时已挂载的会话随 sessions 一起卸载
+    let mut sessions = Vec::new();
+    let mut locks = Vec::new();
+    for spec in &mounts {
+        check_mount_point(&spec.mount_point)?;
+        let mount_cache_dir = spec
+            .cache_dir
+            .clone()
+            .unwrap_or_else(|| cache_dir.join(&spec.name));
+        locks.push(MountLock::acquire(
+            &spec.bucket,
+            &spec.prefix,
+            &mount_cache_dir,
+            &spec.mount_point,
+            matches.get_flag("force"),
+        )?);
+        let spool_options = SpoolOptions {
+            dir: spec
+                .spool_dir
+                .clone()
+                .unwrap_or_else(|| Path::new(DEFAULT_SPOOL_DIR).join(&spec.name)),
+            max_size: None,
+            fsync: Default::default(),
+        };
+        let fs = CosFilesystem::new(
+            spec.bucket.clone(),
+            spec.region.clone(),
+            &mount_cache_dir,
+            &CacheOptions::default(),
+            &spool_options,
+            &ClientOptions {
+                endpoint: spec.endpoint.clone(),
+                prefix: spec.prefix.clone(),
+                ..client_options.clone()
+            },
+            &RuntimeOptions {
+                statsd: statsd.clone().map(|options| StatsdOptions {
+                    labels: MetricLabels {
+                        bucket: spec.bucket.clone(),
+                        prefix: spec.prefix.clone(),
+                        mountpoint: spec.mount_point.display().to_string(),
+                    },
+                    ..options
+                }),
+                ..runtime_options.clone()
+            },
+            Arc::clone(&runtime),
+            &attr_options,
+            &upload_options,
+        )
+        .with_context(|| format!("Failed to create filesystem for mount '{}'", spec.name))?;
+        fs.probe(false)
+            .with_context(|| format!("Bucket permission check failed for mount '{}'", spec.name))?;
+
+        let options = merge_mount_options(
+            daemon_mount_options(&spec.name, runtime_options.transfer.max_read),
+            spec.options
+                .iter()
+                .map(|option| parse_fuse_option(option))
+                .collect(),
+        );
+        let session = spawn_mount2(fs, &spec.mount_point, &options).with_context(|| {
+            format!(
+                "Failed to mount '{}' at {}",
+                spec.name,
+                spec.mount_point.display()
+            )
+        })?;
+        info!(
+            "Mounted {} ({}/{}) at {}",
+            spec.name,
+            spec.bucket,
+            spec.prefix,
+            spec.mount_point.display()
+        );
+        sessions.push(session);
+    }
+
+    info!(
+        "Serving {} mount(s). Press Ctrl+C to unmount.",
+        sessions.len()
+    );
+    let mount_points: Vec<PathBuf> = mounts.iter().map(|spec| spec.mount_point.clone()).collect();
+    sd_notify::notify_ready(&mount_points);
+    let _watchdog = sd_notify::spawn_watchdog(mount_points);
+    let (tx, rx) = std::sync::mpsc::channel();
+    ctrlc::set_handler(move || {
+        let _ = tx.send(());
+    })
+    .map_err(|e| anyhow!("Failed to set Ctrl-C handler: {}", e))?;
+    let _ = rx.recv();
+
+    info!("Unmounting {} filesystem(s)...", sessions.len());
+    let _ = sd_notify::notify("STOPPING=1");
+    drop(sessions);
+    drop(locks);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mounts_from_config() {
+        let config = ConfigFile::parse(
+            r#"
+[mount."datasets"]
+bucket = "data-1250000000"
+region = "ap-beijing"
+prefix = "datasets/"
+mountpoint = "/mnt/datasets"
+options = "allow_other, noatime"
+
+[mount."logs"] bucket=logs-1250000000 region=ap-shanghai mountpoint=/mnt/logs
+"#,
+        )
+        .unwrap();
+        let mounts = mounts_from_config(&config).unwrap();
+        assert_eq!(mounts.len(), 2);
+        assert_eq!(mounts[0].prefix, "datasets/");
+        assert_eq!(mounts[0].options, vec!["allow_other", "noatime"]);
+        assert_eq!(mounts[1].name, "logs");
+        assert_eq!(mounts[1].mount_point, PathBuf::from("/mnt/logs"));
+        assert_eq!(mounts[1].cache_dir, None);
+
+        for text in [
+            "",
+            "[mount.\"a\"] bucket=b region=r",
+            "[mount.\"a\"] bucket=b region=r mountpoint=/m mount_point=/m",
+            "[mount] bucket=b region=r mountpoint=/m",
+            "[mount.\"a\"] bucket=b region=r mountpoint=/m\n[mount.\"b\"] bucket=b region=r mountpoint=/m",
+        ] {
+            let config = ConfigFile::parse(text).unwrap();
+            assert!(mounts_from_config(&config).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use clap::{Arg, ArgMatches, Command};
+use log::info;
+use reqwest::header::HeaderMap;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::{Child, Stdio};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+
+use super::parse_cos_url;
+use crate::config::parse_size;
+use crate::cos_client::{ClientOptions, CosClient, ObjectMeta};
+use crate::object_store::ObjectStore;
+use crate::tar::TarWriter;
+
+/// 每个 Range GET 的默认大小
+const DEFAULT_CHUNK_SIZE: &str = "8M";
+
+/// 默认同时进行的 Range GET 数
+const DEFAULT_PARALLEL: &str = "4";
+
+/// `export` 子命令定义
+pub fn command() -> Command {
+    Command::new("export")
+        .about("Stream a COS prefix into a tar archive without going through the cache")
+        .arg(
+            Arg::new("source")
+                .value_name("SOURCE")
+                .help("Source, e.g. cos://bucket/prefix")
+                .required(true),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FILE")
+                .help("Output archive; a .zst suffix compresses with zstd, - writes to stdout")
+                .required(true),
+        )
+        .arg(
+            Arg::new("region")
+                .short('r')
+                .long("region")
+                .value_name("REGION")
+                .help("Tencent Cloud COS region (e.g., ap-beijing)")
+                .required(true),
+        )
+        .arg(
+            Arg::new("chunk-size")
+                .long("chunk-size")
+                .value_name("SIZE")
+                .help("Size of each ranged GET; memory use is about chunk-size x parallel")
+                .value_parser(parse_size)
+                .default_value(DEFAULT_CHUNK_SIZE),
+        )
+        .arg(
+            Arg::new("parallel")
+                .short('j')
+                .long("parallel")
+                .value_name("N")
+                .help("Number of ranged GETs in flight")
+                .value_parser(clap::value_parser!(usize))
+                .default_value(DEFAULT_PARALLEL),
+        )
+}
+
+/// 执行 `export` 子命令，`extra_headers` 附加到每个 COS 请求上
+pub fn run(matches: &ArgMatches, extra_headers: HeaderMap) -> Result<()> {
+    let source = matches.get_one::<String>("source").unwrap();
+    let output = matches.get_one::<String>("output").unwrap();
+    let region = matches.get_one::<String>("region").unwrap().clone();
+    let chunk_size = (*matches.get_one::<u64>("chunk-size").unwrap()).max(1);
+    let parallel = (*matches.get_one::<usize>("parallel").unwrap()).max(1);
+
+    let Some((bucket, prefix)) = parse_cos_url(source) else {
+        return Err(anyhow!(
+            "Invalid source {}: expected cos://bucket/prefix",
+            source
+        ));
+    };
+
+    let options = ClientOptions {
+        extra_headers,
+        ..Default::default()
+    };
+    let client = Arc::new(CosClient::with_options(bucket, region, &options)?);
+    let (writer, archive) = Archive::create(output)?;
+    let runtime = Runtime::new()?;
+    let result = runtime.block_on(export(client, &prefix, writer, chunk_size, parallel));
+    match result {
+        Ok((objects, bytes)) => {
+            archive.close()?;
+            let summary = format!(
+                "Exported {} objects ({} bytes) to {}",
+                objects, bytes, output
+            );
+            if output == "-" {
+                eprintln!("{}", summary);
+            } else {
+                println!("{}", summary);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            // 不留下看起来完整的半截归档
+            archive.discard();
+            Err(e)
+        }
+    }
+}
+
+/// 输出的归档：文件、标准输出，或者经由 `zstd` 进程压缩后写入文件
+struct Archive {
+    path: Option<String>,
+    zstd: Option<Child>,
+}
+
+impl Archive {
+    /// 返回写入归档内容的 writer，写完后丢弃它再调用 `close`
+    fn create(output: &str) -> Result<(Box<dyn Write + Send>, Self)> {
+        if output == "-" {
+            let archive = Self {
+                path: None,
+                zstd: None,
+            };
+            return Ok((Box::new(BufWriter::new(io::stdout())), archive));
+        }
+
+        if Path::new(output)
+            .extension()
+            .is_some_and(|ext| ext == "zst")
+        {
+            let mut child = std::process::Command::new("zstd")
+                .args(["-q", "-f", "-o", output])
+                .stdin(Stdio::piped())
+                .spawn()
+                .context("Failed to run zstd (is it installed?)")?;
+            let stdin = child.stdin.take().unwrap();
+            let archive = Self {
+                path: Some(output.to_string()),
+                zstd: Some(child),
+            };
+            return Ok((Box::new(BufWriter::new(stdin)), archive));
+        }
+
+        let file = File::create(output).with_context(|| format!("Failed to create {}", output))?;
+        let archive = Self {
+            path: Some(output.to_string()),
+            zstd: None,
+        };
+        Ok((Box::new(BufWriter::new(file)), archive))
+    }
+
+    /// 等待压缩进程写完
+    fn close(mut self) -> Result<()> {
+        if let Some(mut child) = self.zstd.take() {
+            let status = child.wait()?;
+            if !status.success() {
+                return Err(anyhow!("zstd exited with {}", status));
+            }
+        }
+        Ok(())
+    }
+
+    fn discard(mut self) {
+        if let Some(mut child) = self.zstd.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        if let Some(path) = &self.path {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// 按键的顺序把前缀下的对象写入归档，返回 (对象数, 字节数)
+async fn export(
+    client: Arc<CosClient>,
+    prefix: &str,
+    writer: Box<dyn Write + Send>,
+    chunk_size: u64,
+    parallel: usize,
+) -> Result<(usize, u64)> {
+    let objects: Vec<ObjectMeta> = client
+        .list_objects()
+        .await?
+        .into_iter()
+        .filter(|meta| meta.key.starts_with(prefix))
+        .filter(|meta| !relative_path(prefix, &meta.key).is_empty())
+        .collect();
+    info!(
+        "Exporting {} objects under prefix '{}'",
+        objects.len(),
+        prefix
+    );
+
+    // 所有文件内容按顺序切成 Range，最多 `parallel` 个同时下载，按顺序写入
+    let mut ranges = objects
+        .iter()
+        .filter(|meta| !meta.key.ends_with('/'))
+        .flat_map(|meta| {
+            chunk_ranges(meta.size, chunk_size).map(|(offset, len)| (meta.key.clone(), offset, len))
+        });
+    let mut pending: VecDeque<JoinHandle<Result<Bytes>>> = VecDeque::new();
+
+    let mut tar = TarWriter::new(writer);
+    let mut bytes = 0;
+    for meta in &objects {
+        let path = relative_path(prefix, &meta.key);
+        if meta.key.ends_with('/') {
+            tar.append_dir(path.trim_end_matches('/'), meta.last_modified)?;
+            continue;
+        }
+
+        tar.begin_file(path, meta.size, meta.last_modified)?;
+        let mut written = 0;
+        while written < meta.size {
+            while pending.len() < parallel {
+                let Some((key, offset, len)) = ranges.next() else {
+                    break;
+                };
+                let client = Arc::clone(&client);
+                pending.push_back(tokio::spawn(async move {
+                    client.get_object_range(&key, offset, len).await
+                }));
+            }
+            let data = pending
+                .pop_front()
+                .ok_or_else(|| anyhow!("Missing data for {}", meta.key))?
+                .await??;
+            let expected = chunk_size.min(meta.size - written);
+            if data.len() as u64 != expected {
+                // 对象在列出之后被修改
+                return Err(anyhow!(
+                    "Size mismatch for {} at offset {}: expected {} bytes, got {}",
+                    meta.key,
+                    written,
+                    expected,
+                    data.len()
+                ));
+            }
+            tar.write_data(&data)?;
+            written += expected;
+        }
+        bytes += meta.size;
+    }
+    tar.finish()?;
+    Ok((objects.len(), bytes))
+}
+
+/// 对象在归档中的路径：相对于导出前缀
+fn relative_path<'a>(prefix: &str, key: &'a str) -> &'a str {
+    key[prefix.len()..].trim_start_matches('/')
+}
+
+/// 把 `size` 字节的对象切成 (offset, len)，空对象没有 Range
+fn chunk_ranges(size: u64, chunk_size: u64) -> impl Iterator<Item = (u64, u64)> {
+    (0..size)
+        .step_by(chunk_size as usize)
+        .map(move |offset| (offset, chunk_size.min(size - offset)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_ranges() {
+        assert_eq!(chunk_ranges(0, 4).count(), 0);
+        assert_eq!(
+            chunk_ranges(10, 4).collect::<Vec<_>>(),
+            [(0, 4), (4, 4), (8, 2)]
+        );
+        assert_eq!(chunk_ranges(8, 4).collect::<Vec<_>>(), [(0, 4), (4, 4)]);
+
+        assert_eq!(relative_path("datasets/", "datasets/a/b.bin"), "a/b.bin");
+        assert_eq!(relative_path("datasets", "datasets/a/"), "a/");
+        assert_eq!(relative_path("", "top.txt"), "top.txt");
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::{anyhow, Result};
+use clap::{Arg, ArgMatches, Command};
+use log::info;
+use reqwest::header::HeaderMap;
+use std::io::{self, BufWriter, Write};
+use tokio::runtime::Runtime;
+
+use super::parse_cos_url;
+use crate::cos_client::{normalize_prefix, ClientOptions, CosClient};
+use crate::manifest::{Manifest, ManifestEntry};
+
+/// CSV 输出的表头，列的顺序与清单条目的字段一致
+const CSV_HEADER: &str = "key,size,etag,last_modified,storage_class";
+
+/// `ls` 子命令定义
+pub fn command() -> Command {
+    Command::new("ls")
+        .about("List a COS prefix as a manifest (keys, sizes, ETags, mtimes, storage classes)")
+        .arg(
+            Arg::new("source")
+                .value_name("SOURCE")
+                .help("Source, e.g. cos://bucket/prefix")
+                .required(true),
+        )
+        .arg(
+            Arg::new("region")
+                .short('r')
+                .long("region")
+                .value_name("REGION")
+                .help("Tencent Cloud COS region (e.g., ap-beijing)")
+                .required(true),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output format; json can be mounted with --manifest or used as a prefetch manifest")
+                .value_parser(["json", "csv"])
+                .default_value("json"),
+        )
+        .arg(
+            Arg::new("recursive")
+                .short('R')
+                .long("recursive")
+                .help("List everything under the prefix instead of only its top level")
+                .action(clap::ArgAction::SetTrue),
+        )
+}
+
+/// 执行 `ls` 子命令，清单写到标准输出，`extra_headers` 附加到每个 COS 请求上
+pub fn run(matches: &ArgMatches, extra_headers: HeaderMap) -> Result<()> {
+    let source = matches.get_one::<String>("source").unwrap();
+    let region = matches.get_one::<String>("region").unwrap().clone();
+    let format = matches.get_one::<String>("format").unwrap();
+    let recursive = matches.get_flag("recursive");
+
+    let Some((bucket, prefix)) = parse_cos_url(source) else {
+        return Err(anyhow!(
+            "Invalid source {}: expected cos://bucket/prefix",
+            source
+        ));
+    };
+
+    // 键相对于前缀，与挂载同一前缀时看到的路径一致
+    let prefix = normalize_prefix(&prefix);
+    let options = ClientOptions {
+        prefix: prefix.clone(),
+        extra_headers,
+        ..Default::default()
+    };
+    let client = CosClient::with_options(bucket.clone(), region, &options)?;
+    let runtime = Runtime::new()?;
+    let (objects, skipped_dirs) = runtime.block_on(list(&client, recursive))?;
+
+    let bytes: u64 = objects.iter().map(|entry| entry.size).sum();
+    let count = objects.len();
+    let manifest = Manifest {
+        bucket,
+        prefix,
+        objects,
+    };
+    let mut out = BufWriter::new(io::stdout().lock());
+    match format.as_str() {
+        "csv" => write_csv(&mut out, &manifest.objects)?,
+        _ => {
+            serde_json::to_writer_pretty(&mut out, &manifest)?;
+            writeln!(out)?;
+        }
+    }
+    out.flush()?;
+
+    // 摘要写到标准错误，不混进重定向的清单
+    eprintln!("Listed {} objects ({} bytes)", count, bytes);
+    if skipped_dirs > 0 {
+        eprintln!(
+            "Skipped {} subdirectories; use --recursive to include them",
+            skipped_dirs
+        );
+    }
+    Ok(())
+}
+
+/// 逐页列出前缀下的对象，返回 (清单条目, 没有展开的子目录数)
+async fn list(client: &CosClient, recursive: bool) -> Result<(Vec<ManifestEntry>, usize)> {
+    let mut objects = Vec::new();
+    let mut skipped_dirs = 0;
+    let mut marker: Option<String> = None;
+    loop {
+        let page = client.list_page("", !recursive, marker.as_deref()).await?;
+        objects.extend(page.objects.iter().map(ManifestEntry::from_meta));
+        skipped_dirs += page.prefixes.len();
+        match page.next_marker {
+            Some(next) => {
+                info!("Listed {} objects so far", objects.len());
+                marker = Some(next);
+            }
+            None => break,
+        }
+    }
+    Ok((objects, skipped_dirs))
+}
+
+/// 每个对象一行；ETag 去掉引号，含逗号、引号或换行的字段按 RFC 4180 加引号
+fn write_csv(out: &mut impl Write, objects: &[ManifestEntry]) -> io::Result<()> {
+    writeln!(out, "{}", CSV_HEADER)?;
+    for entry in objects {
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            csv_field(&entry.key),
+            entry.size,
+            csv_field(entry.etag.trim_matches('"')),
+            entry.last_modified,
+            csv_field(entry.storage_class.as_deref().unwrap_or(""))
+        )?;
+    }
+    Ok(())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_csv() {
+        let entry = |key: &str, storage_class: Option<&str>| ManifestEntry {
+            key: key.to_string(),
+            size: 7,
+            etag: "\"0123\"".to_string(),
+            last_modified: "2019-05-24T10:56:40.000Z".to_string(),
+            storage_class: storage_class.map(str::to_string),
+        };
+        let mut out = Vec::new();
+        write_csv(
+            &mut out,
+            &[entry("a.txt", Some("STANDARD")), entry("b,\"c\".txt", None)],
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "key,size,etag,last_modified,storage_class\n\
+             a.txt,7,0123,2019-05-24T10:56:40.000Z,STANDARD\n\
+             \"b,\"\"c\"\".txt\",7,0123,2019-05-24T10:56:40.000Z,\n"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! 挂载以外的子命令
+
+use anyhow::{anyhow, Result};
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use crate::cos_client::parse_object_url;
+use crate::filesystem::OBJECT_URL_XATTR;
+
+pub mod cache;
+pub mod check;
+pub mod daemon;
+pub mod export;
+pub mod ls;
+pub mod presign;
+pub mod sync;
+pub mod trash;
+pub mod verify;
+
+/// 解析 `cos://bucket/prefix`，返回 (bucket, prefix)
+pub fn parse_cos_url(url: &str) -> Option<(String, String)> {
+    let rest = url.strip_prefix("cos://")?;
+    let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+    if bucket.is_empty() {
+        return None;
+    }
+    Some((bucket.to_string(), prefix.to_string()))
+}
+
+/// 校验内容的 MD5 与 ETag 一致。分块上传的对象 ETag 不是内容 MD5，跳过校验
+pub fn verify_etag(key: &str, etag: &str, data: &[u8]) -> Result<()> {
+    let etag = etag.trim_matches('"');
+    if etag.len() != 32 || !etag.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(());
+    }
+
+    let digest = format!("{:x}", md5::compute(data));
+    if !digest.eq_ignore_ascii_case(etag) {
+        return Err(anyhow!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            key,
+            etag,
+            digest
+        ));
+    }
+    Ok(())
+}
+
+/// 通过挂载提供的扩展属性找到文件对应的 (bucket, 访问域名, key)
+pub fn resolve_mounted_path(path: &Path) -> Result<(String, String, String)> {
+    let value = read_xattr(path, OBJECT_URL_XATTR).map_err(|e| {
+        anyhow!(
+            "Failed to read {} of {}: {} (is it a file inside a cosfs mount?)",
+            OBJECT_URL_XATTR,
+            path.display(),
+            e
+        )
+    })?;
+    let url = String::from_utf8(value)?;
+    parse_object_url(&url).ok_or_else(|| anyhow!("Unexpected object URL: {}", url))
+}
+
+fn read_xattr(path: &Path, name: &str) -> io::Result<Vec<u8>> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let name = CString::new(name)?;
+    let mut buf = vec![0u8; 4096];
+    // SAFETY: 两个 CString 在调用期间有效，缓冲区长度与传入的 size 一致
+    #[cfg(target_os = "macos")]
+    let len = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+            0,
+            0,
+        )
+    };
+    #[cfg(not(target_os = "macos"))]
+    let len = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+        )
+    };
+    if len < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    buf.truncate(len as usize);
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cos_url() {
+        assert_eq!(
+            parse_cos_url("cos://bucket-1250000000/data/2024/"),
+            Some(("bucket-1250000000".to_string(), "data/2024/".to_string()))
+        );
+        assert_eq!(
+            parse_cos_url("cos://bucket"),
+            Some(("bucket".to_string(), String::new()))
+        );
+        assert_eq!(parse_cos_url("/local/dir"), None);
+        assert_eq!(parse_cos_url("cos:///prefix"), None);
+    }
+
+    #[test]
+    fn test_verify_etag() {
+        let data = b"hello";
+        assert!(verify_etag("k", "\"5d41402abc4b2a76b9719d911017c592\"", data).is_ok());
+        assert!(verify_etag("k", "\"00000000000000000000000000000000\"", data).is_err());
+        // 分块上传的 ETag 不校验
+        assert!(verify_etag("k", "\"abc-3\"", data).is_ok());
+    }
+}
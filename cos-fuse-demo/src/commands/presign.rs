@@ -0,0 +1,65 @@
+use anyhow::{anyhow, Result};
+use clap::{Arg, ArgMatches, Command};
+use std::path::Path;
+use std::time::Duration;
+
+use super::{parse_cos_url, resolve_mounted_path};
+use crate::config::parse_duration;
+use crate::cos_client::{default_endpoint, ClientOptions, CosClient};
+use crate::signer::Credentials;
+
+/// `presign` 子命令定义
+pub fn command() -> Command {
+    Command::new("presign")
+        .about("Print a time-limited signed download URL for a file")
+        .arg(
+            Arg::new("path")
+                .value_name("PATH")
+                .help("A file inside a cosfs mount, or cos://bucket/key")
+                .required(true),
+        )
+        .arg(
+            Arg::new("expires")
+                .long("expires")
+                .value_name("DURATION")
+                .help("How long the URL stays valid (e.g. 30m, 1h, 7d)")
+                .value_parser(parse_duration)
+                .default_value("1h"),
+        )
+        .arg(
+            Arg::new("region")
+                .short('r')
+                .long("region")
+                .value_name("REGION")
+                .help("Tencent Cloud COS region, required for cos:// paths"),
+        )
+}
+
+/// 执行 `presign` 子命令：密钥来自 `COS_SECRET_ID`/`COS_SECRET_KEY` 环境变量
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let path = matches.get_one::<String>("path").unwrap();
+    let expires = *matches.get_one::<Duration>("expires").unwrap();
+
+    let (bucket, endpoint, key) = match parse_cos_url(path) {
+        Some((bucket, key)) => {
+            let region = matches
+                .get_one::<String>("region")
+                .ok_or_else(|| anyhow!("--region is required for cos:// paths"))?;
+            (bucket, default_endpoint(region), key)
+        }
+        None => resolve_mounted_path(Path::new(path))?,
+    };
+    if key.is_empty() || key.ends_with('/') {
+        return Err(anyhow!("{} is not a file", path));
+    }
+
+    let options = ClientOptions {
+        credentials: Credentials::from_env(),
+        endpoint: Some(endpoint),
+        ..Default::default()
+    };
+    // 访问域名已经确定，不需要地域
+    let client = CosClient::with_options(bucket, String::new(), &options)?;
+    println!("{}", client.presign_url(&key, expires)?);
+    Ok(())
+}
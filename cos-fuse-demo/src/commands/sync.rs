@@ -0,0 +1,205 @@
+use anyhow::{anyhow, Result};
+use clap::{Arg, ArgMatches, Command};
+use log::{debug, info, warn};
+use reqwest::header::HeaderMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use super::{parse_cos_url, verify_etag};
+use crate::cos_client::{ClientOptions, CosClient, ObjectMeta};
+use crate::object_store::ObjectStore;
+
+/// 默认并行传输数
+const DEFAULT_PARALLEL: &str = "8";
+
+/// `sync` 子命令定义
+pub fn command() -> Command {
+    Command::new("sync")
+        .about("One-shot synchronization of a COS prefix to a local directory")
+        .arg(
+            Arg::new("source")
+                .value_name("SOURCE")
+                .help("Source, e.g. cos://bucket/prefix")
+                .required(true),
+        )
+        .arg(
+            Arg::new("destination")
+                .value_name("DEST")
+                .help("Local destination directory")
+                .required(true),
+        )
+        .arg(
+            Arg::new("region")
+                .short('r')
+                .long("region")
+                .value_name("REGION")
+                .help("Tencent Cloud COS region (e.g., ap-beijing)")
+                .required(true),
+        )
+        .arg(
+            Arg::new("parallel")
+                .short('j')
+                .long("parallel")
+                .value_name("N")
+                .help("Number of concurrent transfers")
+                .value_parser(clap::value_parser!(usize))
+                .default_value(DEFAULT_PARALLEL),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Only report what would be transferred")
+                .action(clap::ArgAction::SetTrue),
+        )
+}
+
+/// 执行 `sync` 子命令，`extra_headers` 附加到每个 COS 请求上
+pub fn run(matches: &ArgMatches, extra_headers: HeaderMap) -> Result<()> {
+    let source = matches.get_one::<String>("source").unwrap();
+    let destination = PathBuf::from(matches.get_one::<String>("destination").unwrap());
+    let region = matches.get_one::<String>("region").unwrap().clone();
+    let parallel = *matches.get_one::<usize>("parallel").unwrap();
+    let dry_run = matches.get_flag("dry-run");
+
+    let Some((bucket, prefix)) = parse_cos_url(source) else {
+        // 本地 -> COS 需要上传支持
+        return Err(anyhow!(
+            "Unsupported source {}: only cos://bucket/prefix -> local directory is supported",
+            source
+        ));
+    };
+
+    let runtime = Runtime::new()?;
+    let options = ClientOptions {
+        extra_headers,
+        ..Default::default()
+    };
+    let client = Arc::new(CosClient::with_options(bucket, region, &options)?);
+    runtime.block_on(sync_to_local(
+        client,
+        &prefix,
+        &destination,
+        parallel,
+        dry_run,
+    ))
+}
+
+/// 将前缀下的对象同步到本地目录，大小和修改时间一致的文件跳过
+async fn sync_to_local(
+    client: Arc<CosClient>,
+    prefix: &str,
+    destination: &Path,
+    parallel: usize,
+    dry_run: bool,
+) -> Result<()> {
+    let objects: Vec<ObjectMeta> = client
+        .list_objects()
+        .await?
+        .into_iter()
+        .filter(|meta| meta.key.starts_with(prefix) && !meta.key.ends_with('/'))
+        .collect();
+    info!("Found {} objects under prefix '{}'", objects.len(), prefix);
+
+    let permits = Arc::new(Semaphore::new(parallel.max(1)));
+    let mut tasks = JoinSet::new();
+    let mut skipped = 0;
+
+    for meta in objects {
+        let relative = meta.key[prefix.len()..].trim_start_matches('/');
+        let local_path = destination.join(relative);
+
+        if is_up_to_date(&local_path, &meta) {
+            debug!("Up to date: {}", local_path.display());
+            skipped += 1;
+            continue;
+        }
+
+        if dry_run {
+            println!("{} -> {}", meta.key, local_path.display());
+            continue;
+        }
+
+        let client = Arc::clone(&client);
+        let permits = Arc::clone(&permits);
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await?;
+            download(&client, &meta, &local_path).await?;
+            Ok::<_, anyhow::Error>(meta.size)
+        });
+    }
+
+    let mut transferred = 0;
+    let mut bytes = 0;
+    let mut failed = 0;
+    while let Some(result) = tasks.join_next().await {
+        match result? {
+            Ok(size) => {
+                transferred += 1;
+                bytes += size;
+            }
+            Err(e) => {
+                warn!("Transfer failed: {:#}", e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "Transferred {} objects ({} bytes), {} up to date, {} failed",
+        transferred, bytes, skipped, failed
+    );
+    if failed > 0 {
+        return Err(anyhow!("{} transfers failed", failed));
+    }
+    Ok(())
+}
+
+/// 本地文件与对象大小、修改时间（秒级）一致时视为最新
+fn is_up_to_date(local_path: &Path, meta: &ObjectMeta) -> bool {
+    let Ok(local) = fs::metadata(local_path) else {
+        return false;
+    };
+    let same_mtime = local
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok())
+        .zip(
+            meta.last_modified
+                .duration_since(std::time::UNIX_EPOCH)
+                .ok(),
+        )
+        .is_some_and(|(local, remote)| local.as_secs() == remote.as_secs());
+    local.len() == meta.size && same_mtime
+}
+
+/// 下载单个对象：校验后写入临时文件再重命名，并设置修改时间
+async fn download(client: &CosClient, meta: &ObjectMeta, local_path: &Path) -> Result<()> {
+    let data = client.get_object(&meta.key).await?;
+    if data.len() as u64 != meta.size {
+        return Err(anyhow!(
+            "Size mismatch for {}: expected {}, got {}",
+            meta.key,
+            meta.size,
+            data.len()
+        ));
+    }
+    verify_etag(&meta.key, &meta.etag, &data)?;
+
+    if let Some(parent) = local_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = local_path.with_extension("cosfs-partial");
+    fs::write(&tmp_path, &data)?;
+    fs::File::options()
+        .write(true)
+        .open(&tmp_path)?
+        .set_modified(meta.last_modified)?;
+    fs::rename(&tmp_path, local_path)?;
+
+    debug!("Downloaded {} -> {}", meta.key, local_path.display());
+    Ok(())
+}
@@ -0,0 +1,325 @@
+use anyhow::{anyhow, Result};
+use clap::{Arg, ArgMatches, Command};
+use log::warn;
+use reqwest::header::HeaderMap;
+use std::collections::{BTreeMap, HashSet};
+use std::time::{Duration, SystemTime};
+use tokio::runtime::Runtime;
+
+use super::parse_cos_url;
+use crate::config::parse_duration;
+use crate::cos_client::{normalize_prefix, ClientOptions, CosClient};
+use crate::object_store::ObjectStore;
+use crate::signer::Credentials;
+use crate::trash::{Trash, TrashEntry};
+
+/// 默认回收站前缀，与挂载时常用的 `--trash-prefix .trash/` 一致
+const DEFAULT_TRASH_PREFIX: &str = ".trash/";
+
+/// `trash` 子命令定义
+pub fn command() -> Command {
+    Command::new("trash")
+        .about("List, restore or empty objects moved aside by --trash-prefix")
+        .subcommand_required(true)
+        .subcommand(
+            with_common_args(Command::new("list"))
+                .about("List trashed objects with their deletion time and original key"),
+        )
+        .subcommand(
+            with_common_args(Command::new("restore"))
+                .about("Copy trashed objects back to their original keys")
+                .arg(
+                    Arg::new("path")
+                        .value_name("PATH")
+                        .help("Original key or directory to restore (e.g. data/report.csv, data/)")
+                        .required(true)
+                        .num_args(1..),
+                )
+                .arg(
+                    Arg::new("deleted-at")
+                        .long("deleted-at")
+                        .value_name("TIMESTAMP")
+                        .help("Only restore objects deleted at this timestamp (as shown by `trash list`); default: the latest deletion of each key"),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .help("Overwrite objects that exist again at the original key")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Only report what would be restored")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            with_common_args(Command::new("empty"))
+                .about("Permanently delete trashed objects")
+                .arg(
+                    Arg::new("older-than")
+                        .long("older-than")
+                        .value_name("DURATION")
+                        .help("Only delete objects trashed longer than this ago (e.g. 7d)")
+                        .value_parser(parse_duration),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Only report what would be deleted")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+}
+
+/// 各个子命令共用的 bucket、地域和回收站前缀参数
+fn with_common_args(command: Command) -> Command {
+    command
+        .arg(
+            Arg::new("source")
+                .value_name("SOURCE")
+                .help("Bucket and mount prefix, e.g. cos://bucket or cos://bucket/team-a/")
+                .required(true),
+        )
+        .arg(
+            Arg::new("region")
+                .short('r')
+                .long("region")
+                .value_name("REGION")
+                .help("Tencent Cloud COS region (e.g., ap-beijing)")
+                .required(true),
+        )
+        .arg(
+            Arg::new("trash-prefix")
+                .long("trash-prefix")
+                .value_name("PREFIX")
+                .help("Trash prefix used by the mount, relative to the mount prefix")
+                .default_value(DEFAULT_TRASH_PREFIX),
+        )
+}
+
+/// 执行 `trash` 子命令，`extra_headers` 附加到每个 COS 请求上
+pub fn run(matches: &ArgMatches, extra_headers: HeaderMap) -> Result<()> {
+    let Some((name, sub)) = matches.subcommand() else {
+        return Err(anyhow!("Missing trash subcommand"));
+    };
+
+    let source = sub.get_one::<String>("source").unwrap();
+    let Some((bucket, prefix)) = parse_cos_url(source) else {
+        return Err(anyhow!(
+            "Invalid source {}: expected cos://bucket[/prefix]",
+            source
+        ));
+    };
+    let region = sub.get_one::<String>("region").unwrap().clone();
+    let trash = Trash::new(sub.get_one::<String>("trash-prefix").unwrap());
+    if trash.prefix().is_empty() {
+        return Err(anyhow!("--trash-prefix must not be empty"));
+    }
+
+    let options = ClientOptions {
+        extra_headers,
+        credentials: Credentials::from_env(),
+        prefix: normalize_prefix(&prefix),
+        ..Default::default()
+    };
+    let client = CosClient::with_options(bucket, region, &options)?;
+    let runtime = Runtime::new()?;
+    match name {
+        "list" => runtime.block_on(list(&client, &trash)),
+        "restore" => runtime.block_on(restore(sub, &client, &trash)),
+        "empty" => runtime.block_on(empty(sub, &client, &trash)),
+        _ => Err(anyhow!("Unknown trash subcommand: {}", name)),
+    }
+}
+
+async fn list(client: &CosClient, trash: &Trash) -> Result<()> {
+    let objects = client.list_objects().await?;
+    let entries = trash.entries(&objects);
+    for entry in &entries {
+        println!(
+            "{}  {:>12}  {}",
+            entry.deleted_at, entry.size, entry.original
+        );
+    }
+    let bytes: u64 = entries.iter().map(|entry| entry.size).sum();
+    println!(
+        "{} objects ({} bytes) in {}",
+        entries.len(),
+        bytes,
+        trash.prefix()
+    );
+    Ok(())
+}
+
+async fn restore(matches: &ArgMatches, client: &CosClient, trash: &Trash) -> Result<()> {
+    let paths: Vec<&String> = matches.get_many::<String>("path").unwrap().collect();
+    let deleted_at = matches.get_one::<String>("deleted-at").map(String::as_str);
+    let force = matches.get_flag("force");
+    let dry_run = matches.get_flag("dry-run");
+
+    let objects = client.list_objects().await?;
+    let entries = trash.entries(&objects);
+    let selected = select_restores(&entries, &paths, deleted_at);
+    if selected.is_empty() {
+        return Err(anyhow!("No trashed objects match {:?}", paths));
+    }
+
+    // 原位置已经有同名对象（重新创建过）时默认不覆盖
+    let existing: HashSet<&str> = objects
+        .iter()
+        .filter(|meta| !trash.contains(&meta.key))
+        .map(|meta| meta.key.as_str())
+        .collect();
+
+    let mut restored = 0;
+    let mut skipped = 0;
+    let mut failed = 0;
+    for entry in selected {
+        if !force && existing.contains(entry.original.as_str()) {
+            warn!(
+                "{} exists, skipping (use --force to overwrite)",
+                entry.original
+            );
+            skipped += 1;
+            continue;
+        }
+        if dry_run {
+            println!("{} -> {}", entry.key, entry.original);
+            continue;
+        }
+        match trash.restore(client, entry).await {
+            Ok(()) => restored += 1,
+            Err(e) => {
+                warn!("Failed to restore {}: {:#}", entry.original, e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "Restored {} objects, {} skipped, {} failed",
+        restored, skipped, failed
+    );
+    if failed > 0 {
+        return Err(anyhow!("{} restores failed", failed));
+    }
+    Ok(())
+}
+
+/// 选出要恢复的对象：原对象键等于 `paths` 之一或位于其下；
+/// 未指定 `deleted_at` 时每个原对象键只取最近一次删除
+fn select_restores<'a>(
+    entries: &'a [TrashEntry],
+    paths: &[&String],
+    deleted_at: Option<&str>,
+) -> Vec<&'a TrashEntry> {
+    let paths: Vec<&str> = paths.iter().map(|path| path.trim_matches('/')).collect();
+    let matches_path = |key: &str| {
+        paths.iter().any(|path| {
+            path.is_empty()
+                || key == *path
+                || key
+                    .strip_prefix(path)
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    };
+
+    // entries 按删除时间升序，后面的覆盖前面的
+    let mut latest: BTreeMap<&str, &TrashEntry> = BTreeMap::new();
+    for entry in entries {
+        if deleted_at.is_some_and(|at| at != entry.deleted_at) || !matches_path(&entry.original) {
+            continue;
+        }
+        latest.insert(&entry.original, entry);
+    }
+    latest.into_values().collect()
+}
+
+async fn empty(matches: &ArgMatches, client: &CosClient, trash: &Trash) -> Result<()> {
+    let older_than = matches.get_one::<Duration>("older-than").copied();
+    let dry_run = matches.get_flag("dry-run");
+
+    let objects = client.list_objects().await?;
+    let now = SystemTime::now();
+    let mut deleted = 0;
+    let mut bytes = 0;
+    let mut failed = 0;
+    // 不符合回收站布局的对象也在回收站前缀下，一并清理
+    for meta in objects.iter().filter(|meta| trash.contains(&meta.key)) {
+        let age = now.duration_since(meta.last_modified).unwrap_or_default();
+        if older_than.is_some_and(|older_than| age < older_than) {
+            continue;
+        }
+        if dry_run {
+            println!("{}", meta.key);
+        } else if let Err(e) = client.delete_object(&meta.key).await {
+            warn!("Failed to delete {}: {:#}", meta.key, e);
+            failed += 1;
+            continue;
+        }
+        deleted += 1;
+        bytes += meta.size;
+    }
+
+    println!(
+        "{} {} objects ({} bytes) from {}",
+        if dry_run { "Would delete" } else { "Deleted" },
+        deleted,
+        bytes,
+        trash.prefix()
+    );
+    if failed > 0 {
+        return Err(anyhow!("{} deletions failed", failed));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_restores() {
+        let entry = |deleted_at: &str, original: &str| TrashEntry {
+            key: format!(".trash/{}/{}", deleted_at, original),
+            deleted_at: deleted_at.to_string(),
+            original: original.to_string(),
+            size: 0,
+        };
+        let entries = vec![
+            entry("20240101T000000Z", "data/a.txt"),
+            entry("20240101T000000Z", "database/b.txt"),
+            entry("20240102T000000Z", "data/a.txt"),
+            entry("20240102T000000Z", "data/sub/c.txt"),
+        ];
+        let keys = |selected: Vec<&TrashEntry>| -> Vec<String> {
+            selected.iter().map(|entry| entry.key.clone()).collect()
+        };
+
+        let data = "data/".to_string();
+        assert_eq!(
+            keys(select_restores(&entries, &[&data], None)),
+            [
+                ".trash/20240102T000000Z/data/a.txt",
+                ".trash/20240102T000000Z/data/sub/c.txt"
+            ]
+        );
+        assert_eq!(
+            keys(select_restores(
+                &entries,
+                &[&data],
+                Some("20240101T000000Z")
+            )),
+            [".trash/20240101T000000Z/data/a.txt"]
+        );
+
+        let file = "/database/b.txt".to_string();
+        assert_eq!(
+            keys(select_restores(&entries, &[&file], None)),
+            [".trash/20240101T000000Z/database/b.txt"]
+        );
+        assert!(select_restores(&entries, &[&"logs".to_string()], None).is_empty());
+    }
+}
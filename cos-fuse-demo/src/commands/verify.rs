@@ -0,0 +1,258 @@
+use anyhow::{anyhow, Result};
+use clap::{Arg, ArgMatches, Command};
+use log::{debug, info, warn};
+use reqwest::header::HeaderMap;
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::BuildHasher;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use super::{parse_cos_url, resolve_mounted_path};
+use crate::config::parse_size;
+use crate::cos_client::{ClientOptions, CosClient};
+use crate::crc64::Crc64;
+use crate::object_store::ObjectStore;
+use crate::signer::Credentials;
+
+/// 默认并行校验的对象数
+const DEFAULT_PARALLEL: &str = "4";
+
+/// 每个 Range GET 的默认大小
+const DEFAULT_CHUNK_SIZE: &str = "8M";
+
+/// `verify` 子命令定义
+pub fn command() -> Command {
+    Command::new("verify")
+        .about("Re-download objects and check them against their stored CRC64")
+        .arg(
+            Arg::new("target")
+                .value_name("PATH")
+                .help("A file or directory inside a cosfs mount, or cos://bucket/prefix")
+                .required(true),
+        )
+        .arg(
+            Arg::new("region")
+                .short('r')
+                .long("region")
+                .value_name("REGION")
+                .help("Tencent Cloud COS region, required for cos:// paths"),
+        )
+        .arg(
+            Arg::new("sample")
+                .long("sample")
+                .value_name("PERCENT")
+                .help("Only verify a random subset of about this percentage of the objects")
+                .value_parser(clap::value_parser!(u8).range(1..=100)),
+        )
+        .arg(
+            Arg::new("parallel")
+                .short('j')
+                .long("parallel")
+                .value_name("N")
+                .help("Number of objects verified concurrently")
+                .value_parser(clap::value_parser!(usize))
+                .default_value(DEFAULT_PARALLEL),
+        )
+        .arg(
+            Arg::new("chunk-size")
+                .long("chunk-size")
+                .value_name("SIZE")
+                .help("Size of each ranged GET")
+                .value_parser(parse_size)
+                .default_value(DEFAULT_CHUNK_SIZE),
+        )
+}
+
+/// 执行 `verify` 子命令，`extra_headers` 附加到每个 COS 请求上
+pub fn run(matches: &ArgMatches, extra_headers: HeaderMap) -> Result<()> {
+    let target = matches.get_one::<String>("target").unwrap();
+    let sample = matches.get_one::<u8>("sample").copied();
+    let parallel = (*matches.get_one::<usize>("parallel").unwrap()).max(1);
+    let chunk_size = (*matches.get_one::<u64>("chunk-size").unwrap()).max(1);
+
+    let mut options = ClientOptions {
+        extra_headers,
+        credentials: Credentials::from_env(),
+        ..Default::default()
+    };
+    let runtime = Runtime::new()?;
+    let (client, keys) = match parse_cos_url(target) {
+        Some((bucket, prefix)) => {
+            let region = matches
+                .get_one::<String>("region")
+                .ok_or_else(|| anyhow!("--region is required for cos:// paths"))?;
+            let client = CosClient::with_options(bucket, region.clone(), &options)?;
+            let keys: Vec<String> = runtime
+                .block_on(client.list_objects())?
+                .into_iter()
+                .filter(|meta| meta.key.starts_with(&prefix) && !meta.key.ends_with('/'))
+                .map(|meta| meta.key)
+                .collect();
+            (client, keys)
+        }
+        None => {
+            let (bucket, endpoint, keys) = mounted_objects(Path::new(target))?;
+            options.endpoint = Some(endpoint);
+            // 访问域名已经确定，不需要地域
+            (
+                CosClient::with_options(bucket, String::new(), &options)?,
+                keys,
+            )
+        }
+    };
+
+    let keys = match sample {
+        Some(percent) => {
+            // 每次运行随机选取不同的对象
+            let hasher = RandomState::new();
+            keys.into_iter()
+                .filter(|key| hasher.hash_one(key) % 100 < percent as u64)
+                .collect()
+        }
+        None => keys,
+    };
+    info!("Verifying {} objects", keys.len());
+    runtime.block_on(verify_all(Arc::new(client), keys, parallel, chunk_size))
+}
+
+/// 挂载中的文件或目录下所有文件对应的 (bucket, 访问域名, 对象键列表)
+fn mounted_objects(path: &Path) -> Result<(String, String, Vec<String>)> {
+    let mut files = Vec::new();
+    collect_files(path, &mut files)?;
+    let mut bucket = None;
+    let mut keys = Vec::with_capacity(files.len());
+    for file in files {
+        let (file_bucket, endpoint, key) = resolve_mounted_path(&file)?;
+        match &bucket {
+            None => bucket = Some((file_bucket, endpoint)),
+            Some(first) if *first != (file_bucket, endpoint) => {
+                return Err(anyhow!("{} spans more than one mount", path.display()));
+            }
+            Some(_) => {}
+        }
+        keys.push(key);
+    }
+    let (bucket, endpoint) = bucket.ok_or_else(|| anyhow!("No files under {}", path.display()))?;
+    Ok((bucket, endpoint, keys))
+}
+
+fn collect_files(path: &Path, files: &mut Vec<std::path::PathBuf>) -> Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_file() {
+        files.push(path.to_path_buf());
+    } else if metadata.is_dir() {
+        for entry in fs::read_dir(path)? {
+            collect_files(&entry?.path(), files)?;
+        }
+    }
+    Ok(())
+}
+
+/// 单个对象的校验结果
+enum Outcome {
+    Ok(u64),
+    Mismatch {
+        expected: u64,
+        actual: u64,
+    },
+    /// 对象没有记录 CRC64（如早期上传的对象）
+    NoChecksum,
+}
+
+async fn verify_all(
+    client: Arc<CosClient>,
+    keys: Vec<String>,
+    parallel: usize,
+    chunk_size: u64,
+) -> Result<()> {
+    let permits = Arc::new(Semaphore::new(parallel));
+    let mut tasks = JoinSet::new();
+    for key in keys {
+        let client = Arc::clone(&client);
+        let permits = Arc::clone(&permits);
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await?;
+            let outcome = verify_object(&client, &key, chunk_size).await;
+            Ok::<_, anyhow::Error>((key, outcome))
+        });
+    }
+
+    let (mut verified, mut bytes, mut mismatched, mut unchecked, mut failed) = (0, 0, 0, 0, 0);
+    while let Some(result) = tasks.join_next().await {
+        let (key, outcome) = result??;
+        match outcome {
+            Ok(Outcome::Ok(size)) => {
+                debug!("OK {}", key);
+                verified += 1;
+                bytes += size;
+            }
+            Ok(Outcome::Mismatch { expected, actual }) => {
+                println!(
+                    "MISMATCH {}: expected crc64 {}, got {}",
+                    key, expected, actual
+                );
+                mismatched += 1;
+            }
+            Ok(Outcome::NoChecksum) => {
+                debug!("No CRC64 recorded for {}", key);
+                unchecked += 1;
+            }
+            Err(e) => {
+                warn!("Failed to verify {}: {:#}", key, e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "Verified {} objects ({} bytes): {} mismatched, {} without checksum, {} failed",
+        verified, bytes, mismatched, unchecked, failed
+    );
+    if mismatched > 0 || failed > 0 {
+        return Err(anyhow!(
+            "{} objects mismatched, {} could not be verified",
+            mismatched,
+            failed
+        ));
+    }
+    Ok(())
+}
+
+/// 按 Range 分段下载对象并计算 CRC64，与 HEAD 返回的 `x-cos-hash-crc64ecma` 比较
+async fn verify_object(client: &CosClient, key: &str, chunk_size: u64) -> Result<Outcome> {
+    let meta = client.head_object(key).await?;
+    let Some(expected) = meta.details.crc64.as_deref() else {
+        return Ok(Outcome::NoChecksum);
+    };
+    let expected: u64 = expected
+        .parse()
+        .map_err(|_| anyhow!("Invalid CRC64 '{}' for {}", expected, key))?;
+
+    let mut crc = Crc64::new();
+    let mut offset = 0;
+    while offset < meta.size {
+        let len = chunk_size.min(meta.size - offset);
+        let data = client.get_object_range(key, offset, len).await?;
+        if data.len() as u64 != len {
+            return Err(anyhow!(
+                "Size mismatch for {} at offset {}: expected {} bytes, got {}",
+                key,
+                offset,
+                len,
+                data.len()
+            ));
+        }
+        crc.update(&data);
+        offset += len;
+    }
+
+    let actual = crc.finish();
+    if actual != expected {
+        return Ok(Outcome::Mismatch { expected, actual });
+    }
+    Ok(Outcome::Ok(meta.size))
+}
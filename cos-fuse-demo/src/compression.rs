@@ -0,0 +1,141 @@
+use anyhow::{anyhow, Context, Result};
+use flate2::read::DeflateDecoder;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+
+/// zstd 的默认压缩级别，压缩速度足以跟上下载速度
+const ZSTD_LEVEL: i32 = 3;
+
+/// 本地内容缓存的压缩方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheCompression {
+    /// 原样存放
+    #[default]
+    None,
+    /// zstd 压缩，读取时解压
+    Zstd,
+}
+
+impl CacheCompression {
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            CacheCompression::None => Ok(data.to_vec()),
+            CacheCompression::Zstd => {
+                zstd::bulk::compress(data, ZSTD_LEVEL).context("zstd compression failed")
+            }
+        }
+    }
+
+    /// 解压 `compress` 的结果；内容超过 `max_size` 时报错，不按帧头声明的大小分配内存
+    pub fn decompress(self, data: Vec<u8>, max_size: u64) -> Result<Vec<u8>> {
+        match self {
+            CacheCompression::None => Ok(data),
+            CacheCompression::Zstd => {
+                let decoder = zstd::stream::read::Decoder::with_buffer(data.as_slice())
+                    .context("zstd: invalid frame")?;
+                read_limited(decoder, max_size).context("zstd decompression failed")
+            }
+        }
+    }
+}
+
+impl FromStr for CacheCompression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(CacheCompression::None),
+            "zstd" => Ok(CacheCompression::Zstd),
+            _ => Err(format!(
+                "unknown cache compression: {} (expected none or zstd)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CacheCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CacheCompression::None => "none",
+            CacheCompression::Zstd => "zstd",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 解压 raw deflate 数据（zip 条目使用的格式），`size` 为解压后的大小
+pub fn inflate_raw(data: &[u8], size: u64) -> Result<Vec<u8>> {
+    let content =
+        read_limited(DeflateDecoder::new(data), size).context("corrupt deflate stream")?;
+    if content.len() as u64 != size {
+        return Err(anyhow!(
+            "corrupt deflate stream ({} of {} bytes)",
+            content.len(),
+            size
+        ));
+    }
+    Ok(content)
+}
+
+/// 读出解压后的全部内容，超过 `max_size` 字节时报错
+fn read_limited(reader: impl Read, max_size: u64) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    reader
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut out)?;
+    if out.len() as u64 > max_size {
+        return Err(anyhow!("content exceeds {} bytes", max_size));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zstd_round_trip() {
+        let text = b"2024-01-01 INFO request served\n".repeat(1000);
+        let compressed = CacheCompression::Zstd.compress(&text).unwrap();
+        assert!(compressed.len() * 10 < text.len());
+        assert_eq!(
+            CacheCompression::Zstd
+                .decompress(compressed.clone(), text.len() as u64)
+                .unwrap(),
+            text
+        );
+        // 超过上限的内容不会整体解压
+        assert!(CacheCompression::Zstd
+            .decompress(compressed, text.len() as u64 - 1)
+            .is_err());
+
+        let empty = CacheCompression::Zstd.compress(b"").unwrap();
+        assert!(CacheCompression::Zstd
+            .decompress(empty, 0)
+            .unwrap()
+            .is_empty());
+        assert!(CacheCompression::Zstd
+            .decompress(b"not zstd".to_vec(), 100)
+            .is_err());
+
+        assert_eq!("ZSTD".parse(), Ok(CacheCompression::Zstd));
+        assert_eq!(CacheCompression::None.to_string(), "none");
+        assert!("gzip".parse::<CacheCompression>().is_err());
+    }
+
+    #[test]
+    fn test_inflate_raw() {
+        // zlib.compressobj(wbits=-15) 压缩 b"hello hello hello\n"
+        let deflated = [
+            0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x5c, 0x00,
+        ];
+        assert_eq!(inflate_raw(&deflated, 18).unwrap(), b"hello hello hello\n");
+        assert!(inflate_raw(&deflated, 10).is_err());
+        assert!(inflate_raw(&deflated, 30).is_err());
+        assert!(inflate_raw(b"garbage", 10).is_err());
+    }
+}
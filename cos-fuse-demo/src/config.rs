@@ -0,0 +1,456 @@
+use anyhow::{anyhow, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+use crate::atime::AtimeMode;
+use crate::cache_policy::CachePolicies;
+use crate::compression::CacheCompression;
+use crate::config_file::ConfigFile;
+use crate::eviction::EvictionPolicyKind;
+use crate::hooks::Hooks;
+use crate::idmap::IdMap;
+use crate::manifest::Snapshot;
+use crate::metrics::StatsdOptions;
+use crate::permissions::PermissionOverrides;
+use crate::readahead::{DEFAULT_READAHEAD_MAX, DEFAULT_READAHEAD_MIN};
+use crate::system_xattrs::SystemXattrs;
+
+/// 分块缓存的默认块大小：部分缓存按块对齐下载
+pub const DEFAULT_CACHE_BLOCK_SIZE: u64 = 1 << 20;
+
+/// 允许的最小块大小，更小的块会让请求数量和区间记录膨胀
+pub const MIN_CACHE_BLOCK_SIZE: u64 = 64 << 10;
+
+/// 允许的最大块大小，更大的块会让随机小读放大成大量无用下载
+pub const MAX_CACHE_BLOCK_SIZE: u64 = 64 << 20;
+
+/// 与内核协商的单个读写请求默认大小上限，内核默认的 128K 让大文件顺序读写的请求数多出数倍
+pub const DEFAULT_MAX_TRANSFER: u32 = 1 << 20;
+
+/// 单个读写请求大小的下限（一页）
+pub const MIN_TRANSFER: u32 = 4 << 10;
+
+/// 单个读写请求大小的上限，与 fuser 的请求缓冲区一致
+pub const MAX_TRANSFER: u32 = 16 << 20;
+
+/// 缓存相关配置
+#[derive(Debug, Clone)]
+pub struct CacheOptions {
+    /// L1 元数据缓存的最大条目数
+    pub metadata_cache_size: usize,
+
+    /// 元数据缓存有效期，过期后重新 HEAD
+    pub metadata_ttl: Duration,
+
+    /// 元数据过期后仍可直接使用的时间窗口，期间在后台重新验证（None 表示关闭）
+    pub stale_while_revalidate: Option<Duration>,
+
+    /// 小于该大小的对象不写入内容缓存
+    pub content_cache_min_object_size: u64,
+
+    /// 大于该大小的对象不写入内容缓存（None 表示不限制）
+    pub content_cache_max_object_size: Option<u64>,
+
+    /// 内容缓存总大小上限（None 表示不限制）
+    pub content_cache_max_size: Option<u64>,
+
+    /// 分块缓存的块大小（2 的幂）：不超过一块的对象整体缓存，更大的对象按块对齐下载
+    pub block_size: u64,
+
+    /// 自适应预读的初始窗口
+    pub readahead_min: u64,
+
+    /// 自适应预读的最大窗口（0 表示不预读）
+    pub readahead_max: u64,
+
+    /// 按前缀设置的缓存方式（整体、分块或直接读取）
+    pub cache_policies: CachePolicies,
+
+    /// 内容缓存淘汰策略
+    pub eviction_policy: EvictionPolicyKind,
+
+    /// 内容缓存在磁盘上的压缩方式，配额按压缩后的大小计算
+    pub compression: CacheCompression,
+
+    /// 后台缓存清理任务的执行间隔
+    pub janitor_interval: Duration,
+
+    /// 本地预取清单；未设置时使用 bucket 根目录下的 `.cosfs-prefetch` 对象（如果存在）
+    pub prefetch_manifest: Option<PathBuf>,
+
+    /// lookup 未命中对象列表时先 HEAD/按前缀列出确认，使其他客户端刚上传的对象无需刷新即可见
+    pub revalidate_misses: bool,
+
+    /// 周期性重新列出访问过的目录的间隔（None 表示只在挂载时列出）
+    pub refresh_interval: Option<Duration>,
+
+    /// 快照挂载的对象清单：列表与元数据取自清单，从不列出实时的桶（None 表示正常挂载）
+    pub snapshot: Option<Arc<Snapshot>>,
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        Self {
+            metadata_cache_size: 1000,
+            metadata_ttl: Duration::from_secs(300),
+            stale_while_revalidate: None,
+            content_cache_min_object_size: 0,
+            content_cache_max_object_size: None,
+            content_cache_max_size: None,
+            block_size: DEFAULT_CACHE_BLOCK_SIZE,
+            readahead_min: DEFAULT_READAHEAD_MIN,
+            readahead_max: DEFAULT_READAHEAD_MAX,
+            cache_policies: CachePolicies::default(),
+            eviction_policy: EvictionPolicyKind::default(),
+            compression: CacheCompression::default(),
+            janitor_interval: Duration::from_secs(60),
+            prefetch_manifest: None,
+            revalidate_misses: false,
+            refresh_interval: None,
+            snapshot: None,
+        }
+    }
+}
+
+/// 预签名 URL 的默认有效期
+pub const DEFAULT_PRESIGN_EXPIRES: Duration = Duration::from_secs(3600);
+
+/// 文件属性相关配置
+#[derive(Debug, Clone)]
+pub struct AttrOptions {
+    /// 读取时是否更新（仅在本地记录的）atime
+    pub atime: AtimeMode,
+
+    /// 按前缀覆盖的属主与权限
+    pub permissions: PermissionOverrides,
+
+    /// 请求者 uid/gid 映射与白名单
+    pub id_map: IdMap,
+
+    /// `user.cos.presigned-url` 扩展属性生成的链接有效期
+    pub presign_expires: Duration,
+
+    /// 按对象 ACL 推导文件权限位（需要访问密钥）
+    pub acl_permissions: bool,
+
+    /// mtime 以 `x-cos-meta-mtime` 为准：列表只有秒级的 Last-Modified，不再用于填充元数据缓存
+    pub hires_mtime: bool,
+
+    /// 文件的属主与权限位取自 s3fs 记录的 `x-cos-meta-uid/gid/mode`（每个文件一次 HEAD）
+    pub s3fs_compat: bool,
+
+    /// `.zip`/`.tar` 对象显示为目录，其中的文件按需从归档中读取
+    pub browse_archives: bool,
+
+    /// 图片对象旁提供 `photo.jpg@w=200` 等虚拟文件，读取经数据万象处理后的图片
+    pub image_variants: bool,
+
+    /// `security.*`、`system.*`、`trusted.*` 扩展属性的答复方式
+    pub system_xattrs: SystemXattrs,
+}
+
+impl Default for AttrOptions {
+    fn default() -> Self {
+        Self {
+            atime: AtimeMode::default(),
+            permissions: PermissionOverrides::default(),
+            id_map: IdMap::default(),
+            presign_expires: DEFAULT_PRESIGN_EXPIRES,
+            acl_permissions: false,
+            hires_mtime: false,
+            s3fs_compat: false,
+            browse_archives: false,
+            image_variants: false,
+            system_xattrs: SystemXattrs::default(),
+        }
+    }
+}
+
+/// 内部 tokio 运行时的线程配置（None 表示使用 tokio 默认值）
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeOptions {
+    /// 异步工作线程数
+    pub io_threads: Option<usize>,
+
+    /// 阻塞任务线程池上限（缓存文件读写、后台维护）
+    pub blocking_threads: Option<usize>,
+
+    /// 单个 FUSE 操作（含续传与重试）的最长执行时间，超时答复 EIO（None 表示不限制）
+    pub op_timeout: Option<Duration>,
+
+    /// 周期性推送指标的 StatsD 服务（None 表示不推送）
+    pub statsd: Option<StatsdOptions>,
+
+    /// 每个请求者 uid 每秒的读写字节数上限（None 表示不限速，只统计）
+    pub uid_rate_limit: Option<u64>,
+
+    /// 内存中状态（对象列表、inode 映射、目录与元数据缓存、内存中的内容）的上限（None 表示不限制）
+    pub memory_limit: Option<u64>,
+
+    /// 与内核协商的单个读写请求大小
+    pub transfer: TransferSizes,
+
+    /// 库使用者注册的操作钩子
+    pub hooks: Hooks,
+}
+
+/// 单个 FUSE 读写请求的大小上限：越大则大文件顺序读写需要的请求越少
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferSizes {
+    /// 单个读请求的最大字节数（`max_read` 挂载选项）
+    pub max_read: u32,
+    /// 单个写请求的最大字节数
+    pub max_write: u32,
+}
+
+impl Default for TransferSizes {
+    fn default() -> Self {
+        Self {
+            max_read: DEFAULT_MAX_TRANSFER,
+            max_write: DEFAULT_MAX_TRANSFER,
+        }
+    }
+}
+
+impl TransferSizes {
+    /// 单个请求的页数上限须覆盖读写两者，内核再按自己的上限截断
+    pub fn max_pages(&self, page_size: u32) -> u16 {
+        let pages = self.max_read.max(self.max_write).div_ceil(page_size);
+        pages.min(u32::from(u16::MAX)) as u16
+    }
+}
+
+impl RuntimeOptions {
+    /// 按配置创建多线程运行时
+    pub fn build_runtime(&self) -> std::io::Result<Runtime> {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.enable_all().thread_name("cosfs-io");
+        if let Some(threads) = self.io_threads {
+            builder.worker_threads(threads);
+        }
+        if let Some(threads) = self.blocking_threads {
+            builder.max_blocking_threads(threads);
+        }
+        builder.build()
+    }
+}
+
+/// 解析线程数，必须大于 0
+pub fn parse_thread_count(s: &str) -> Result<usize, String> {
+    match s.trim().parse::<usize>() {
+        Ok(0) | Err(_) => Err(format!("invalid thread count: {}", s)),
+        Ok(n) => Ok(n),
+    }
+}
+
+/// 解析 `Name: value` 形式的请求头
+pub fn parse_header(s: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("invalid header (expected 'Name: value'): {}", s))?;
+    let name = HeaderName::from_bytes(name.trim().as_bytes())
+        .map_err(|_| format!("invalid header name: {}", name.trim()))?;
+    let value = HeaderValue::from_str(value.trim())
+        .map_err(|_| format!("invalid value for header {}", name))?;
+    Ok((name, value))
+}
+
+/// 配置文件中附加请求头节的名字：`[headers]`
+pub const HEADERS_SECTION: &str = "headers";
+
+/// 读取配置文件 `[headers]` 节中附加到每个 COS 请求上的头
+pub fn headers_from_config(config: &ConfigFile) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for section in config.sections(HEADERS_SECTION) {
+        for (name, value) in &section.entries {
+            let (name, value) = parse_header(&format!("{}: {}", name, value))
+                .map_err(|e| anyhow!("line {}: {}", section.line, e))?;
+            headers.insert(name, value);
+        }
+    }
+    Ok(headers)
+}
+
+impl CacheOptions {
+    /// 判断该大小的对象是否应写入内容缓存
+    pub fn should_cache_content(&self, size: u64) -> bool {
+        size >= self.content_cache_min_object_size
+            && self
+                .content_cache_max_object_size
+                .is_none_or(|max| size <= max)
+    }
+}
+
+/// 解析时长，支持 `30`（秒）、`500ms`、`30s`、`5m`、`2h`、`7d`
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration: {}", s))?;
+
+    let duration = match unit {
+        "ms" => Duration::from_millis(value),
+        "" | "s" => Duration::from_secs(value),
+        "m" => Duration::from_secs(value * 60),
+        "h" => Duration::from_secs(value * 3600),
+        "d" => Duration::from_secs(value * 86400),
+        _ => return Err(format!("invalid duration unit in: {}", s)),
+    };
+    Ok(duration)
+}
+
+/// 解析周期任务的间隔，必须大于 0
+pub fn parse_interval(s: &str) -> Result<Duration, String> {
+    match parse_duration(s)? {
+        interval if interval.is_zero() => Err(format!("interval must be positive: {}", s)),
+        interval => Ok(interval),
+    }
+}
+
+/// 解析字节大小，支持 `4096`、`64K`、`16M`、`5G`、`1T`（1024 进制，可带 `B`/`iB` 后缀）
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: u64 = number.parse().map_err(|_| format!("invalid size: {}", s))?;
+
+    let unit = unit.to_ascii_uppercase();
+    let multiplier: u64 = match unit.trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(format!("invalid size unit in: {}", s)),
+    };
+
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size too large: {}", s))
+}
+
+/// 解析单个读写请求的大小上限：在 4K 到 16M 之间
+pub fn parse_transfer_size(s: &str) -> Result<u32, String> {
+    let size = parse_size(s)?;
+    if !(u64::from(MIN_TRANSFER)..=u64::from(MAX_TRANSFER)).contains(&size) {
+        return Err(format!("request size must be between 4K and 16M: {}", s));
+    }
+    Ok(size as u32)
+}
+
+/// 解析缓存块大小：必须是 2 的幂，且在 64K 到 64M 之间
+pub fn parse_block_size(s: &str) -> Result<u64, String> {
+    let size = parse_size(s)?;
+    if !size.is_power_of_two() {
+        return Err(format!("block size must be a power of two: {}", s));
+    }
+    if !(MIN_CACHE_BLOCK_SIZE..=MAX_CACHE_BLOCK_SIZE).contains(&size) {
+        return Err(format!("block size must be between 64K and 64M: {}", s));
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_header() {
+        let (name, value) = parse_header("X-Traffic-Tag: batch ").unwrap();
+        assert_eq!(name, "x-traffic-tag");
+        assert_eq!(value, "batch");
+        assert!(parse_header("X-Traffic-Tag").is_err());
+        assert!(parse_header("Bad Name: 1").is_err());
+
+        let config = ConfigFile::parse("[headers]\nx-route = \"gw-2\"").unwrap();
+        assert_eq!(headers_from_config(&config).unwrap()["x-route"], "gw-2");
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(
+            parse_duration("7d").unwrap(),
+            Duration::from_secs(7 * 86400)
+        );
+        assert!(parse_duration("5x").is_err());
+        assert!(parse_duration("").is_err());
+        assert_eq!(parse_interval("10s").unwrap(), Duration::from_secs(10));
+        assert!(parse_interval("0").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("64K").unwrap(), 64 * 1024);
+        assert_eq!(parse_size("5G").unwrap(), 5 << 30);
+        assert_eq!(parse_size("16MiB").unwrap(), 16 << 20);
+        assert_eq!(parse_size("1mb").unwrap(), 1 << 20);
+        assert!(parse_size("12Q").is_err());
+    }
+
+    #[test]
+    fn test_parse_block_size() {
+        assert_eq!(parse_block_size("4M").unwrap(), 4 << 20);
+        assert_eq!(parse_block_size("64K").unwrap(), MIN_CACHE_BLOCK_SIZE);
+        assert!(parse_block_size("3M").is_err());
+        assert!(parse_block_size("4K").is_err());
+        assert!(parse_block_size("128M").is_err());
+    }
+
+    #[test]
+    fn test_runtime_options() {
+        assert_eq!(parse_thread_count("4").unwrap(), 4);
+        assert!(parse_thread_count("0").is_err());
+
+        let options = RuntimeOptions {
+            io_threads: Some(2),
+            blocking_threads: Some(4),
+            op_timeout: None,
+            statsd: None,
+            uid_rate_limit: None,
+            memory_limit: None,
+            transfer: TransferSizes::default(),
+            hooks: Hooks::default(),
+        };
+        let runtime = options.build_runtime().unwrap();
+        assert_eq!(runtime.block_on(async { 1 + 1 }), 2);
+    }
+
+    #[test]
+    fn test_transfer_sizes() {
+        assert_eq!(parse_transfer_size("1M").unwrap(), 1 << 20);
+        assert_eq!(parse_transfer_size("4K").unwrap(), MIN_TRANSFER);
+        assert!(parse_transfer_size("1K").is_err());
+        assert!(parse_transfer_size("32M").is_err());
+
+        assert_eq!(TransferSizes::default().max_pages(4096), 256);
+        let sizes = TransferSizes {
+            max_read: 4 << 20,
+            max_write: 128 << 10,
+        };
+        assert_eq!(sizes.max_pages(4096), 1024);
+        assert_eq!(sizes.max_pages(64 << 10), 64);
+    }
+
+    #[test]
+    fn test_should_cache_content() {
+        let options = CacheOptions {
+            content_cache_min_object_size: 10,
+            content_cache_max_object_size: Some(100),
+            ..Default::default()
+        };
+        assert!(!options.should_cache_content(5));
+        assert!(options.should_cache_content(10));
+        assert!(options.should_cache_content(100));
+        assert!(!options.should_cache_content(101));
+        assert!(CacheOptions::default().should_cache_content(u64::MAX));
+    }
+}
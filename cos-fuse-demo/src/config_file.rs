@@ -0,0 +1,243 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// 配置文件中的一节，例如 `[perm."datasets/raw/"]` 的名字是 `["perm", "datasets/raw/"]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: Vec<String>,
+    pub entries: Vec<(String, String)>,
+    /// 节头所在行号（从 1 开始），用于报错
+    pub line: usize,
+}
+
+impl Section {
+    /// 节名的第一段
+    pub fn kind(&self) -> &str {
+        self.name.first().map_or("", String::as_str)
+    }
+}
+
+/// 挂载配置文件
+///
+/// 语法是 TOML 的一个子集：`[a."b"]` 节头、`key = value` 键值对（值可以是裸词或带引号的字符串）、
+/// `#` 注释。为了方便写在一行里，节头后面和同一行内也可以跟多个以空白分隔的键值对
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigFile {
+    pub sections: Vec<Section>,
+}
+
+impl ConfigFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut sections: Vec<Section> = Vec::new();
+
+        for (index, raw) in text.lines().enumerate() {
+            let line_no = index + 1;
+            let mut scanner = Scanner::new(raw);
+            scanner.skip_whitespace();
+
+            if scanner.eat('[') {
+                let name = scanner
+                    .section_name()
+                    .map_err(|e| anyhow!("line {}: {}", line_no, e))?;
+                sections.push(Section {
+                    name,
+                    entries: Vec::new(),
+                    line: line_no,
+                });
+            }
+
+            let pairs = scanner
+                .pairs()
+                .map_err(|e| anyhow!("line {}: {}", line_no, e))?;
+            if pairs.is_empty() {
+                continue;
+            }
+            match sections.last_mut() {
+                Some(section) => section.entries.extend(pairs),
+                None => bail!("line {}: key/value pair outside of a section", line_no),
+            }
+        }
+
+        Ok(Self { sections })
+    }
+
+    /// 拒绝未知的节，避免拼错的节名被悄悄忽略
+    pub fn check_sections(&self, known: &[&str]) -> Result<()> {
+        for section in &self.sections {
+            if !known.contains(&section.kind()) {
+                bail!(
+                    "line {}: unknown section [{}] (expected one of: {})",
+                    section.line,
+                    section.name.join("."),
+                    known.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// 第一段名为 `kind` 的所有节
+    pub fn sections<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'a Section> + 'a {
+        self.sections.iter().filter(move |s| s.kind() == kind)
+    }
+}
+
+/// 单行扫描器
+struct Scanner<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(line: &'a str) -> Self {
+        Self { rest: line }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    fn at_end(&self) -> bool {
+        self.rest.is_empty() || self.rest.starts_with('#')
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 解析 `[` 之后的节名，直到 `]`
+    fn section_name(&mut self) -> Result<Vec<String>> {
+        let mut name = Vec::new();
+        loop {
+            self.skip_whitespace();
+            name.push(self.word(&['.', ']'])?);
+            self.skip_whitespace();
+            if self.eat(']') {
+                return Ok(name);
+            }
+            if !self.eat('.') {
+                bail!("expected '.' or ']' in section header");
+            }
+        }
+    }
+
+    /// 解析剩余的 `key = value` 键值对
+    fn pairs(&mut self) -> Result<Vec<(String, String)>> {
+        let mut pairs = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.at_end() {
+                return Ok(pairs);
+            }
+            let key = self.word(&['=', '#'])?;
+            self.skip_whitespace();
+            if !self.eat('=') {
+                bail!("expected '=' after key '{}'", key);
+            }
+            self.skip_whitespace();
+            let value = self.word(&['#'])?;
+            pairs.push((key, value));
+        }
+    }
+
+    /// 带引号的字符串，或遇到空白和 `stops` 为止的裸词
+    fn word(&mut self, stops: &[char]) -> Result<String> {
+        if self.eat('"') {
+            return self.quoted();
+        }
+        let end = self
+            .rest
+            .find(|c: char| c.is_whitespace() || stops.contains(&c))
+            .unwrap_or(self.rest.len());
+        if end == 0 {
+            bail!("expected a name or value");
+        }
+        let (word, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Ok(word.to_string())
+    }
+
+    fn quoted(&mut self) -> Result<String> {
+        let mut value = String::new();
+        let mut chars = self.rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &self.rest[i + 1..];
+                    return Ok(value);
+                }
+                '\\' => match chars.next() {
+                    Some((_, '"')) => value.push('"'),
+                    Some((_, '\\')) => value.push('\\'),
+                    Some((_, other)) => bail!("unsupported escape '\\{}'", other),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        bail!("unterminated string")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sections_and_pairs() {
+        let config = ConfigFile::parse(
+            r#"
+# 团队目录
+[perm."datasets/raw/"] uid=1000 mode=0555
+
+[perm."models/"]
+uid = 2000   # 模型组
+gid = "2000"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.sections,
+            vec![
+                Section {
+                    name: vec!["perm".to_string(), "datasets/raw/".to_string()],
+                    entries: vec![
+                        ("uid".to_string(), "1000".to_string()),
+                        ("mode".to_string(), "0555".to_string()),
+                    ],
+                    line: 3,
+                },
+                Section {
+                    name: vec!["perm".to_string(), "models/".to_string()],
+                    entries: vec![
+                        ("uid".to_string(), "2000".to_string()),
+                        ("gid".to_string(), "2000".to_string()),
+                    ],
+                    line: 5,
+                },
+            ]
+        );
+        assert!(config.check_sections(&["perm"]).is_ok());
+        assert!(config.check_sections(&["profile"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(ConfigFile::parse("uid = 1").is_err());
+        assert!(ConfigFile::parse("[perm.\"a/").is_err());
+        assert!(ConfigFile::parse("[perm]\nuid 1").is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use std::path::Path;
+
+/// 无法识别时使用的类型
+pub const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// 按扩展名识别的常见类型（扩展名小写）
+const EXTENSION_TYPES: &[(&str, &str)] = &[
+    // 网页与文本
+    ("html", "text/html; charset=utf-8"),
+    ("htm", "text/html; charset=utf-8"),
+    ("css", "text/css; charset=utf-8"),
+    ("js", "text/javascript; charset=utf-8"),
+    ("mjs", "text/javascript; charset=utf-8"),
+    ("json", "application/json"),
+    ("map", "application/json"),
+    ("xml", "application/xml"),
+    ("txt", "text/plain; charset=utf-8"),
+    ("md", "text/markdown; charset=utf-8"),
+    ("csv", "text/csv; charset=utf-8"),
+    ("yaml", "application/yaml"),
+    ("yml", "application/yaml"),
+    ("wasm", "application/wasm"),
+    // 图片
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("svg", "image/svg+xml"),
+    ("ico", "image/x-icon"),
+    ("bmp", "image/bmp"),
+    // 字体
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+    // 音视频
+    ("mp3", "audio/mpeg"),
+    ("wav", "audio/wav"),
+    ("ogg", "audio/ogg"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+    ("m3u8", "application/vnd.apple.mpegurl"),
+    ("ts", "video/mp2t"),
+    // 文档与压缩包
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tgz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("zst", "application/zstd"),
+    ("7z", "application/x-7z-compressed"),
+];
+
+/// 按开头字节识别的类型
+const MAGIC_TYPES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (b"\0asm", "application/wasm"),
+    (b"wOFF", "font/woff"),
+    (b"wOF2", "font/woff2"),
+];
+
+/// 上传时使用的 Content-Type：先看扩展名；识别不了且提供了内容开头（`head`）时再嗅探
+pub fn guess_content_type(key: &str, head: Option<&[u8]>) -> &'static str {
+    let by_extension = Path::new(key)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(|ext| {
+            EXTENSION_TYPES
+                .iter()
+                .find(|(known, _)| known.eq_ignore_ascii_case(ext))
+        })
+        .map(|(_, content_type)| *content_type);
+
+    by_extension
+        .or_else(|| head.and_then(sniff_content_type))
+        .unwrap_or(DEFAULT_CONTENT_TYPE)
+}
+
+/// 根据内容开头猜测类型
+fn sniff_content_type(head: &[u8]) -> Option<&'static str> {
+    if let Some((_, content_type)) = MAGIC_TYPES
+        .iter()
+        .find(|(magic, _)| head.starts_with(magic))
+    {
+        return Some(content_type);
+    }
+    if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+
+    // 没有 NUL 的 UTF-8 视为文本；截断处可能切开一个多字节字符，只检查到最后一个完整字符
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+    if text.contains('\0') {
+        return None;
+    }
+    let trimmed = text.trim_start();
+    let lower = trimmed
+        .get(..trimmed.len().min(15))
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+        Some("text/html; charset=utf-8")
+    } else if lower.starts_with("<?xml") {
+        Some("application/xml")
+    } else {
+        Some("text/plain; charset=utf-8")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess_by_extension() {
+        assert_eq!(
+            guess_content_type("site/index.HTML", None),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(
+            guess_content_type("img/logo.png", Some(b"not a png")),
+            "image/png"
+        );
+        assert_eq!(guess_content_type("data/blob", None), DEFAULT_CONTENT_TYPE);
+    }
+
+    #[test]
+    fn test_sniff_without_extension() {
+        assert_eq!(
+            guess_content_type("a", Some(b"\x89PNG\r\n\x1a\n....")),
+            "image/png"
+        );
+        assert_eq!(
+            guess_content_type("b", Some(b"RIFF\0\0\0\0WEBPVP8 ")),
+            "image/webp"
+        );
+        assert_eq!(
+            guess_content_type("c", Some(b"  <!DOCTYPE html><html>")),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(
+            guess_content_type("d", Some("日志\n".as_bytes())),
+            "text/plain; charset=utf-8"
+        );
+        // 截断在多字节字符中间仍视为文本
+        assert_eq!(
+            guess_content_type("e", Some(&"日志".as_bytes()[..4])),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(
+            guess_content_type("f", Some(b"\0\x01\x02")),
+            DEFAULT_CONTENT_TYPE
+        );
+    }
+}
@@ -1,9 +1,145 @@
 use anyhow::{anyhow, Result};
 use bytes::Bytes;
+use log::warn;
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
-use url::Url;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Range;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::acl::ObjectAcl;
+use crate::backend::Provider;
+use crate::limiter::{RequestClass, RequestLimiter, RequestLimits};
+use crate::object_store::ObjectStore;
+use crate::recording::Recording;
+use crate::signer::{self, Credentials};
+
+/// 服务端加密方式的请求/响应头
+pub const SSE_HEADER: &str = "x-cos-server-side-encryption";
+
+/// SSE-KMS 使用的 KMS 密钥 ID 请求头
+pub const SSE_KMS_KEY_ID_HEADER: &str = "x-cos-server-side-encryption-cos-kms-key-id";
+
+/// 服务端返回的请求 ID 响应头
+pub const REQUEST_ID_HEADER: &str = "x-cos-request-id";
+
+/// 服务端返回的链路追踪 ID 响应头
+pub const TRACE_ID_HEADER: &str = "x-cos-trace-id";
+
+/// 服务端为请求分配的标识，向腾讯云提交工单时用来定位具体请求
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestIds {
+    pub request_id: Option<String>,
+    pub trace_id: Option<String>,
+}
+
+impl RequestIds {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(String::from)
+        };
+        Self {
+            request_id: header(REQUEST_ID_HEADER),
+            trace_id: header(TRACE_ID_HEADER),
+        }
+    }
+}
+
+/// 有标识时输出 ` (request id: ..., trace id: ...)`，否则为空
+impl fmt::Display for RequestIds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids: Vec<String> = [("request id", &self.request_id), ("trace id", &self.trace_id)]
+            .into_iter()
+            .filter_map(|(name, id)| id.as_ref().map(|id| format!("{}: {}", name, id)))
+            .collect();
+        if ids.is_empty() {
+            return Ok(());
+        }
+        write!(f, " ({})", ids.join(", "))
+    }
+}
+
+/// 请求返回了非成功状态码，调用方可以 downcast 出状态码区分权限不足等情况
+#[derive(Debug, Clone)]
+pub struct StatusError {
+    pub request: &'static str,
+    pub status: StatusCode,
+    pub ids: RequestIds,
+}
+
+impl StatusError {
+    pub fn new(request: &'static str, status: StatusCode) -> Self {
+        Self {
+            request,
+            status,
+            ids: RequestIds::default(),
+        }
+    }
+
+    /// 由失败的响应构造，保留响应头中的请求 ID
+    pub fn from_response(request: &'static str, response: &reqwest::Response) -> Self {
+        Self {
+            request,
+            status: response.status(),
+            ids: RequestIds::from_headers(response.headers()),
+        }
+    }
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} request failed with status: {}{}",
+            self.request, self.status, self.ids
+        )
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+/// 对象不存在 (404)，保留状态码以便区分桶被删除等情况
+pub(crate) fn not_found(request: &'static str, response: &reqwest::Response, key: &str) -> anyhow::Error {
+    anyhow::Error::from(StatusError::from_response(request, response))
+        .context(format!("Object not found: {}", key))
+}
+
+/// 错误对应的 HTTP 状态码（不是状态码错误时返回 None）
+pub fn error_status(error: &anyhow::Error) -> Option<StatusCode> {
+    error.downcast_ref::<StatusError>().map(|e| e.status)
+}
+
+/// 下载中断后续传的最大次数
+pub(crate) const MAX_RESUME_ATTEMPTS: u32 = 3;
+
+/// 用户自定义元数据请求/响应头的前缀
+pub const USER_META_PREFIX: &str = "x-cos-meta-";
+
+/// 修改元数据时沿用的标准头；元数据指令为 Replaced 时不带上的头会被清除
+pub(crate) const PRESERVED_HEADERS: &[&str] = &[
+    "content-type",
+    "cache-control",
+    "content-disposition",
+    "content-encoding",
+    "content-language",
+    "expires",
+    "x-cos-storage-class",
+    SSE_HEADER,
+    SSE_KMS_KEY_ID_HEADER,
+];
+
+/// 单次 PUT Object - Copy 支持的最大对象
+pub const MAX_COPY_SIZE: u64 = 5 << 30;
+
+/// 记录纳秒精度 mtime 的自定义元数据（对应 s3fs 的 `x-amz-meta-mtime`），值为 `秒[.小数]`
+pub const MTIME_META_HEADER: &str = "x-cos-meta-mtime";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ObjectMeta {
@@ -12,6 +148,202 @@ pub struct ObjectMeta {
     pub last_modified: SystemTime,
     pub etag: String,
     pub content_type: Option<String>,
+    /// 服务端加密方式（`AES256` 为 SSE-COS，`cos/kms` 为 SSE-KMS）；列表结果不包含该信息
+    #[serde(default)]
+    pub server_side_encryption: Option<String>,
+    /// 只有 HEAD/GET 响应头才有的信息（列表结果中只有存储类型）
+    #[serde(default)]
+    pub details: ObjectDetails,
+}
+
+/// 对象的版本、存储类型、校验值等附加信息，调试和 `user.cosfs.info` 使用
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ObjectDetails {
+    pub version_id: Option<String>,
+    pub storage_class: Option<String>,
+    /// 服务端计算的 CRC64-ECMA 校验值
+    pub crc64: Option<String>,
+    /// 取得这些信息的请求的 ID，向腾讯云提交工单时使用
+    pub request_id: Option<String>,
+    /// 用户自定义元数据（`x-cos-meta-*`，键不含前缀）
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub user_meta: BTreeMap<String, String>,
+}
+
+/// 按目录列出的一层结果
+#[derive(Debug, Clone, Default)]
+pub struct DirListing {
+    /// 目录下的对象（含目录自身的标记对象）
+    pub objects: Vec<ObjectMeta>,
+    /// 子目录，以 `/` 结尾
+    pub prefixes: Vec<String>,
+}
+
+/// 分页列出的一页结果
+#[derive(Debug, Clone, Default)]
+pub struct ListPage {
+    pub objects: Vec<ObjectMeta>,
+    /// 指定分隔符时折叠的子目录，以 `/` 结尾
+    pub prefixes: Vec<String>,
+    /// 下一页的起点（完整的对象键，ListObjectsV2 为续列令牌），列完时为 None
+    pub next_marker: Option<String>,
+}
+
+/// GET Bucket 响应体
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListBucketResult {
+    #[serde(default)]
+    contents: Vec<ListContents>,
+    #[serde(default)]
+    common_prefixes: Vec<CommonPrefix>,
+    #[serde(default)]
+    is_truncated: bool,
+    #[serde(default)]
+    next_marker: Option<String>,
+    /// ListObjectsV2 的续列令牌
+    #[serde(default)]
+    next_continuation_token: Option<String>,
+}
+
+/// 指定分隔符时 GET Bucket 响应中折叠的子目录
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CommonPrefix {
+    prefix: String,
+}
+
+/// GET Bucket 响应中的单个对象条目
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListContents {
+    key: String,
+    last_modified: String,
+    #[serde(rename = "ETag", default)]
+    etag: String,
+    size: u64,
+    #[serde(default)]
+    storage_class: Option<String>,
+}
+
+/// Initiate Multipart Upload 响应体
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct InitiateMultipartUploadResult {
+    pub upload_id: String,
+}
+
+/// Upload Part - Copy 与 Complete Multipart Upload 响应体中的 ETag
+#[derive(Debug, Deserialize)]
+pub(crate) struct EtagResult {
+    #[serde(rename = "ETag")]
+    pub etag: String,
+}
+
+/// 可续传下载的进度：已收到的数据和下一次请求的起点
+#[derive(Debug)]
+pub(crate) struct PartialDownload {
+    offset: u64,
+    len: Option<u64>,
+    data: Vec<u8>,
+    /// 服务端返回完整对象时需要丢弃的前缀字节数
+    skip: u64,
+}
+
+impl PartialDownload {
+    pub fn new(offset: u64, len: Option<u64>) -> Self {
+        Self {
+            offset,
+            len,
+            // 区间下载的长度已知，一次分配到位，避免接收过程中反复扩容
+            data: Vec::with_capacity(len.unwrap_or(0) as usize),
+            skip: 0,
+        }
+    }
+
+    pub fn received(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    /// 下一次请求的 Range 头；从头下载整个对象时为 None
+    pub fn range(&self) -> Option<String> {
+        let start = self.offset + self.received();
+        match self.len {
+            Some(len) => Some(format!("bytes={}-{}", start, self.offset + len - 1)),
+            None if start > 0 => Some(format!("bytes={}-", start)),
+            None => None,
+        }
+    }
+
+    /// 响应是完整对象而不是请求的区间：跳过断点之前的内容
+    pub fn restart_from_zero(&mut self) {
+        self.skip = self.offset + self.received();
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        let skip = self.skip.min(chunk.len() as u64);
+        self.skip -= skip;
+        self.data.extend_from_slice(&chunk[skip as usize..]);
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.len.is_some_and(|len| self.received() >= len)
+    }
+
+    pub fn finish(mut self) -> Bytes {
+        if let Some(len) = self.len {
+            self.data.truncate(len as usize);
+        }
+        Bytes::from(self.data)
+    }
+}
+
+/// HEAD Bucket 的原始结果
+#[derive(Debug, Clone)]
+pub struct BucketPing {
+    pub status: StatusCode,
+    /// 响应的 Date 头（服务端时间）
+    pub date: Option<SystemTime>,
+    /// 响应中的请求标识
+    pub ids: RequestIds,
+}
+
+/// 本机与服务端时间相差超过该值时，签名可能被拒绝
+const MAX_CLOCK_SKEW: Duration = Duration::from_secs(60);
+
+/// 请求签名的有效期，只需覆盖请求在途的时间
+const SIGNATURE_TTL: Duration = Duration::from_secs(600);
+
+/// 对象存储客户端配置
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    /// 对象存储服务商，[`Backend`](crate::backend::Backend) 据此选择客户端
+    pub provider: Provider,
+
+    /// 并发请求上限
+    pub limits: RequestLimits,
+
+    /// 附加到每个请求上的头（流量标记、网关路由等）
+    pub extra_headers: HeaderMap,
+
+    /// 访问密钥，生成预签名 URL 时需要
+    pub credentials: Option<Credentials>,
+
+    /// 访问域名（不含 bucket），未设置时为 `cos.<region>.myqcloud.com`；
+    /// S3 兼容服务为服务地址（如 `http://minio:9000`）
+    pub endpoint: Option<String>,
+
+    /// 只访问该前缀下的对象，键相对于前缀（如 `team-a/`，为空时访问整个桶）
+    pub prefix: String,
+
+    /// 多个客户端共享的 HTTP 客户端（连接池），未设置时按 `extra_headers` 新建
+    pub http_client: Option<reqwest::Client>,
+
+    /// 快照挂载时每个对象固定的 ETag，下载时以 If-Match 发送，对象已被改写时失败而不是读到新内容
+    pub pinned_etags: Option<Arc<HashMap<String, String>>>,
+
+    /// 录制所有请求与响应，或从录制中回放而不访问网络
+    pub recording: Option<Arc<Recording>>,
 }
 
 #[derive(Debug)]
@@ -19,113 +351,1222 @@ pub struct CosClient {
     bucket: String,
     region: String,
     base_url: String,
+    /// 对象键前缀，空或以 `/` 结尾
+    prefix: String,
     client: reqwest::Client,
+    limiter: RequestLimiter,
+    credentials: Option<Credentials>,
+    /// 服务端时间减本机时间（秒），签名被拒绝时校正
+    clock_offset: AtomicI64,
+    /// 下载时要求的 ETag（快照挂载）
+    pinned_etags: Option<Arc<HashMap<String, String>>>,
+    /// 请求的录制或回放
+    recording: Option<Arc<Recording>>,
 }
 
 impl CosClient {
+    #[cfg(test)]
     pub fn new(bucket: String, region: String) -> Self {
-        let base_url = format!("https://{}.cos.{}.myqcloud.com", bucket, region);
-        
-        Self {
+        Self::with_options(bucket, region, &ClientOptions::default())
+            .expect("Failed to create HTTP client")
+    }
+
+    /// 创建客户端：按 `options.limits` 限制同时进行的请求数，每个请求都带上 `options.extra_headers`
+    pub fn with_options(bucket: String, region: String, options: &ClientOptions) -> Result<Self> {
+        let endpoint = options.endpoint.clone()
+            .unwrap_or_else(|| default_endpoint(&region));
+        let base_url = format!("https://{}.{}", bucket, endpoint);
+        let client = match &options.http_client {
+            Some(client) => client.clone(),
+            None => build_http_client(&options.extra_headers)?,
+        };
+
+        Ok(Self {
             bucket,
             region,
             base_url,
-            client: reqwest::Client::new(),
+            prefix: normalize_prefix(&options.prefix),
+            client,
+            limiter: RequestLimiter::new(&options.limits),
+            credentials: options.credentials.clone(),
+            clock_offset: AtomicI64::new(0),
+            pinned_etags: options.pinned_etags.clone(),
+            recording: options.recording.clone(),
+        })
+    }
+
+    /// 发送请求，有访问密钥时签名。服务端以 403 拒绝且 Date 头显示本机时钟偏差过大时，
+    /// 记下偏差、校正之后所有签名的时间并重试一次（请求体是流、无法重发时不重试）
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let mut request = request.build()?;
+        let credentials = match &self.credentials {
+            Some(credentials) => credentials,
+            None => return self.execute(request).await,
+        };
+        let mut corrected = false;
+
+        loop {
+            let retry = if corrected { None } else { request.try_clone() };
+            self.sign(credentials, &mut request)?;
+            let url = request.url().clone();
+            let response = self.execute(request).await?;
+
+            if let Some(retry) = retry {
+                if response.status() == StatusCode::FORBIDDEN {
+                    if let Some(offset) = response_date(&response)
+                        .and_then(|server| clock_offset(SystemTime::now(), server))
+                        .filter(|offset| offset.abs_diff(self.clock_offset()) > MAX_CLOCK_SKEW.as_secs())
+                    {
+                        warn!(
+                            "Request to {} rejected, COS time is {:+}s from the local clock; correcting signature time",
+                            url, offset
+                        );
+                        self.clock_offset.store(offset, Ordering::Relaxed);
+                        corrected = true;
+                        request = retry;
+                        continue;
+                    }
+                }
+            }
+            return Ok(response);
+        }
+    }
+
+    /// 不签名地发送，诊断匿名访问时使用
+    async fn send_anonymous(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        self.execute(request.build()?).await
+    }
+
+    /// 发送必须签名的请求，没有访问密钥时返回错误
+    async fn send_signed(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        if self.credentials.is_none() {
+            return Err(anyhow!("Signed requests require credentials"));
+        }
+        self.send(request).await
+    }
+
+    /// 录制或回放时经过 [`Recording`]，否则直接发出
+    async fn execute(&self, request: reqwest::Request) -> Result<reqwest::Response> {
+        match &self.recording {
+            Some(recording) => recording.send(&self.client, request).await,
+            None => Ok(self.client.execute(request).await?),
+        }
+    }
+
+    /// 加上 `Authorization` 头（临时密钥另加 `x-cos-security-token`），
+    /// 签名覆盖方法、对象键与全部查询参数
+    fn sign(&self, credentials: &Credentials, request: &mut reqwest::Request) -> Result<()> {
+        let url = request.url();
+        let key = signer::uri_decode(url.path().trim_start_matches('/'))
+            .ok_or_else(|| anyhow!("Invalid request path: {}", url.path()))?;
+        let params: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        let params: Vec<(&str, &str)> = params.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+        let authorization = signer::authorization(
+            credentials, request.method().as_str(), &key, &params, SIGNATURE_TTL, self.signing_time(),
+        );
+
+        let headers = request.headers_mut();
+        headers.insert(reqwest::header::AUTHORIZATION, HeaderValue::from_str(&authorization)?);
+        if let Some(token) = &credentials.session_token {
+            headers.insert("x-cos-security-token", HeaderValue::from_str(token)?);
+        }
+        Ok(())
+    }
+
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    pub fn region(&self) -> &str {
+        &self.region
+    }
+
+    /// 服务端域名
+    pub fn host(&self) -> &str {
+        self.base_url.trim_start_matches("https://")
+    }
+
+    /// 加上前缀的完整对象键
+    fn full_key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+
+    /// 请求对象使用的地址
+    fn key_url(&self, key: &str) -> String {
+        format!("{}/{}", self.base_url, signer::encode_path(&self.full_key(key)))
+    }
+
+    /// 列表请求的地址，只列出挂载前缀加上 `sub_prefix` 之下的对象
+    fn list_url(&self, sub_prefix: &str, query: &str) -> String {
+        let mut params = Vec::new();
+        let prefix = self.full_key(sub_prefix);
+        if !prefix.is_empty() {
+            params.push(format!("prefix={}", signer::uri_encode(&prefix)));
         }
+        if !query.is_empty() {
+            params.push(query.to_string());
+        }
+        if params.is_empty() {
+            format!("{}/", self.base_url)
+        } else {
+            format!("{}/?{}", self.base_url, params.join("&"))
+        }
+    }
+
+    /// 去掉列表结果中的前缀，前缀本身（目录标记）被丢弃
+    fn strip_prefix(&self, objects: Vec<ObjectMeta>) -> Vec<ObjectMeta> {
+        strip_key_prefix(&self.prefix, objects)
+    }
+
+    /// 是否配置了访问密钥
+    pub fn has_credentials(&self) -> bool {
+        self.credentials.is_some()
+    }
+
+    /// 对象的（未签名）访问地址
+    pub fn object_url(&self, key: &str) -> String {
+        format!("{}/{}", self.base_url, signer::encode_path(&self.full_key(key)))
+    }
+
+    /// 生成在 `expires` 内有效的预签名下载地址
+    pub fn presign_url(&self, key: &str, expires: Duration) -> Result<String> {
+        let credentials = self.credentials.as_ref().ok_or_else(|| {
+            anyhow!("Presigning requires {} and {}", signer::SECRET_ID_ENV, signer::SECRET_KEY_ENV)
+        })?;
+        Ok(signer::presign_url(
+            credentials, &self.base_url, &self.full_key(key), expires, self.signing_time(),
+        ))
     }
 
     /// 获取对象元数据 (HEAD 请求)
     pub async fn head_object(&self, key: &str) -> Result<ObjectMeta> {
-        let url = format!("{}/{}", self.base_url, key);
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = self.key_url(key);
         
-        let response = self.client
-            .head(&url)
-            .send()
+        let response = self
+            .send(self.client.head(&url))
             .await?;
 
         if response.status() == 404 {
-            return Err(anyhow!("Object not found: {}", key));
+            return Err(not_found("HEAD", &response, key));
         }
 
         if !response.status().is_success() {
-            return Err(anyhow!("HEAD request failed with status: {}", response.status()));
+            return Err(StatusError::from_response("HEAD", &response).into());
         }
 
-        let headers = response.headers();
-        
-        let size = headers
-            .get("content-length")
-            .and_then(|v| v.to_str().ok())
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(0);
+        Ok(meta_from_headers(key, response.headers()))
+    }
 
-        let last_modified = headers
-            .get("last-modified")
-            .and_then(|v| v.to_str().ok())
-            .and_then(|v| parse_http_date(v))
-            .unwrap_or_else(SystemTime::now);
+    /// 获取对象内容 (GET 请求)
+    pub async fn get_object(&self, key: &str) -> Result<Bytes> {
+        self.get_resumable(key, 0, None, None).await
+    }
+
+    /// 获取对象的一段内容 (带 Range 头的 GET 请求)
+    pub async fn get_object_range(&self, key: &str, offset: u64, len: u64) -> Result<Bytes> {
+        if len == 0 {
+            return Ok(Bytes::new());
+        }
+
+        self.get_resumable(key, offset, Some(len), None).await
+    }
+
+    /// 获取版本为 `etag` 的对象的一段内容 (带 Range 与 If-Match 头的 GET 请求)
+    pub async fn get_object_range_if_match(
+        &self,
+        key: &str,
+        etag: &str,
+        offset: u64,
+        len: u64,
+    ) -> Result<Bytes> {
+        if len == 0 {
+            return Ok(Bytes::new());
+        }
+
+        self.get_resumable(key, offset, Some(len), Some(etag)).await
+    }
+
+    /// 获取经数据万象处理后的对象内容，`rule` 为处理参数（如 `imageMogr2/thumbnail/200x`）
+    pub async fn get_processed(&self, key: &str, rule: &str) -> Result<Bytes> {
+        let _permit = self.limiter.acquire(RequestClass::Data).await?;
+        let url = format!("{}?{}", self.key_url(key), rule);
+
+        let response = self.send(self.client.get(&url)).await?;
+
+        if response.status() == 404 {
+            return Err(not_found("GET", &response, key));
+        }
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("Image processing", &response).into());
+        }
+
+        Ok(response.bytes().await?)
+    }
+
+    /// 可续传的 GET：响应体中途断开时保留已收到的数据，
+    /// 用 Range 请求从断点继续（If-Match 保证续传的是同一版本的对象）
+    /// `if_match` 不为空时只读取该版本，对象已变化时以 412 失败
+    async fn get_resumable(
+        &self,
+        key: &str,
+        offset: u64,
+        len: Option<u64>,
+        if_match: Option<&str>,
+    ) -> Result<Bytes> {
+        // 续传复用同一个名额
+        let _permit = self.limiter.acquire(RequestClass::Data).await?;
+        let url = self.key_url(key);
+        let mut download = PartialDownload::new(offset, len);
+        let pinned = self.pinned_etags.as_ref().and_then(|etags| etags.get(key));
+        let mut etag: Option<String> = if_match.map(String::from).or_else(|| pinned.cloned());
+        let mut attempts = 0;
+
+        'request: loop {
+            let range = download.range();
+            let mut request = self.client.get(&url);
+            if let Some(range) = &range {
+                request = request.header(reqwest::header::RANGE, range.as_str());
+            }
+            if let Some(etag) = &etag {
+                request = request.header(reqwest::header::IF_MATCH, etag.as_str());
+            }
+            let mut response = self.send(request).await?;
+
+            if response.status() == 404 {
+                return Err(not_found("GET", &response, key));
+            }
+
+            if response.status() == reqwest::StatusCode::PRECONDITION_FAILED {
+                if if_match.is_some() {
+                    return Err(StatusError::from_response("GET", &response).into());
+                }
+                if pinned.is_some() {
+                    return Err(anyhow!("Object changed since the snapshot manifest: {}", key));
+                }
+                return Err(anyhow!("Object changed while downloading: {}", key));
+            }
+
+            if !response.status().is_success() {
+                return Err(StatusError::from_response("GET", &response).into());
+            }
+
+            // 服务端忽略 Range 时会返回完整对象，需要跳过断点之前的内容
+            if range.is_some() && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+                download.restart_from_zero();
+            }
+
+            if etag.is_none() {
+                etag = response
+                    .headers()
+                    .get(reqwest::header::ETAG)
+                    .and_then(|v| v.to_str().ok())
+                    .map(String::from);
+            }
+
+            loop {
+                match response.chunk().await {
+                    Ok(Some(chunk)) => {
+                        download.push(&chunk);
+                        if download.is_complete() {
+                            return Ok(download.finish());
+                        }
+                    }
+                    Ok(None) => return Ok(download.finish()),
+                    Err(e) if attempts < MAX_RESUME_ATTEMPTS => {
+                        attempts += 1;
+                        warn!(
+                            "Download of {} interrupted after {} bytes, resuming ({}/{}): {}",
+                            key,
+                            download.received(),
+                            attempts,
+                            MAX_RESUME_ATTEMPTS,
+                            e
+                        );
+                        continue 'request;
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+    }
+
+    /// 条件 GET (If-None-Match)：对象未变化时返回 None (304)。
+    /// 对象已变化时返回新元数据；不超过 `max_body` 字节的对象同时返回内容
+    pub async fn get_object_if_changed(
+        &self,
+        key: &str,
+        etag: &str,
+        max_body: u64,
+    ) -> Result<Option<(ObjectMeta, Option<Bytes>)>> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = self.key_url(key);
+
+        let response = self
+            .send(self.client.get(&url).header(reqwest::header::IF_NONE_MATCH, etag))
+            .await?;
+
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+
+        if response.status() == 404 {
+            return Err(not_found("Conditional GET", &response, key));
+        }
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("Conditional GET", &response).into());
+        }
+
+        let meta = meta_from_headers(key, response.headers());
+        // 大对象只需要新的元数据，不读取响应体
+        let body = if meta.size <= max_body {
+            Some(response.bytes().await?)
+        } else {
+            None
+        };
+        Ok(Some((meta, body)))
+    }
+
+    /// 从 `marker`（完整的对象键）之后列出 `prefix` 下的一页，最多 1000 个对象；
+    /// `delimiter` 为 true 时只列一层，子目录放在 `prefixes` 中
+    pub async fn list_page(&self, prefix: &str, delimiter: bool, marker: Option<&str>) -> Result<ListPage> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let mut query = Vec::new();
+        if delimiter {
+            query.push("delimiter=%2F".to_string());
+        }
+        if let Some(marker) = marker {
+            query.push(format!("marker={}", signer::uri_encode(marker)));
+        }
+        let url = self.list_url(prefix, &query.join("&"));
+
+        let response = self
+            .send(self.client.get(&url))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("LIST", &response).into());
+        }
+
+        let body = response.text().await?;
+        let (objects, prefixes, next_marker) = parse_list_page(&body)?;
+        let prefixes = prefixes
+            .into_iter()
+            .filter_map(|p| p.strip_prefix(&self.prefix).filter(|p| !p.is_empty()).map(str::to_string))
+            .collect();
+        Ok(ListPage { objects: self.strip_prefix(objects), prefixes, next_marker })
+    }
+
+    /// 检查桶是否存在且可以访问 (HEAD Bucket 请求)
+    pub async fn head_bucket(&self) -> Result<()> {
+        let ping = self.ping(self.has_credentials()).await?;
+        if !ping.status.is_success() {
+            return Err(StatusError {
+                ids: ping.ids,
+                ..StatusError::new("HEAD Bucket", ping.status)
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// 发送 HEAD Bucket 请求并原样返回状态码和服务端时间，诊断用；
+    /// `signed` 为 true 时用访问密钥签名（没有密钥时返回错误）
+    pub async fn ping(&self, signed: bool) -> Result<BucketPing> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = format!("{}/", self.base_url);
+
+        let response = if signed {
+            self.send_signed(self.client.head(&url)).await?
+        } else {
+            self.send_anonymous(self.client.head(&url)).await?
+        };
+
+        Ok(BucketPing {
+            status: response.status(),
+            date: response_date(&response),
+            ids: RequestIds::from_headers(response.headers()),
+        })
+    }
+
+    /// 列出以 `prefix` 开头的第一个对象，用于确认某个目录是否存在
+    pub async fn list_first_key_under(&self, prefix: &str) -> Result<Option<String>> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        // 挂载前缀本身可能是目录标记，多取一个
+        let url = self.list_url(prefix, "max-keys=2");
+
+        let response = self
+            .send(self.client.get(&url))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("LIST", &response).into());
+        }
 
-        let etag = headers
+        let body = response.text().await?;
+        Ok(self.strip_prefix(parse_list_response(&body)?).into_iter().next().map(|meta| meta.key))
+    }
+
+    /// 获取对象 ACL (GET Object ACL 请求)，需要访问密钥
+    pub async fn get_object_acl(&self, key: &str) -> Result<ObjectAcl> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = format!("{}/{}?acl", self.base_url, signer::encode_path(&self.full_key(key)));
+        let response = self.send_signed(self.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("GET ACL", &response).into());
+        }
+
+        let body = response.text().await?;
+        ObjectAcl::parse(&body)
+    }
+
+    /// 当前记录的时钟偏差（服务端时间减本机时间，秒）
+    fn clock_offset(&self) -> i64 {
+        self.clock_offset.load(Ordering::Relaxed)
+    }
+
+    /// 签名使用的时间：本机时间加上记录的时钟偏差
+    fn signing_time(&self) -> SystemTime {
+        apply_clock_offset(SystemTime::now(), self.clock_offset())
+    }
+
+    /// 上传对象 (PUT 请求)，`headers` 为 Content-Type 等附加头，返回新对象的 ETag
+    pub async fn put_object(&self, key: &str, body: Bytes, headers: HeaderMap) -> Result<String> {
+        let _permit = self.limiter.acquire(RequestClass::Data).await?;
+        let url = self.key_url(key);
+
+        let response = self
+            .send(self.client.put(&url).headers(headers).body(body))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("PUT", &response).into());
+        }
+
+        let etag = response
+            .headers()
             .get("etag")
             .and_then(|v| v.to_str().ok())
             .unwrap_or("")
             .to_string();
+        Ok(etag)
+    }
 
-        let content_type = headers
-            .get("content-type")
-            .and_then(|v| v.to_str().ok())
-            .map(String::from);
+    /// 服务端复制对象 (PUT Object - Copy)，数据不经过本机
+    pub async fn copy_object(&self, source_key: &str, dest_key: &str) -> Result<()> {
+        let _permit = self.limiter.acquire(RequestClass::Data).await?;
+        let url = self.key_url(dest_key);
+        let source = format!("{}/{}", self.host(), signer::encode_path(&self.full_key(source_key)));
 
-        Ok(ObjectMeta {
-            key: key.to_string(),
-            size,
-            last_modified,
-            etag,
-            content_type,
-        })
+        let response = self
+            .send(self.client.put(&url).header("x-cos-copy-source", source))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("PUT Copy", &response).into());
+        }
+        Ok(())
     }
 
-    /// 获取对象内容 (GET 请求)
-    pub async fn get_object(&self, key: &str) -> Result<Bytes> {
-        let url = format!("{}/{}", self.base_url, key);
-        
-        let response = self.client
-            .get(&url)
-            .send()
+    /// 把对象的用户自定义元数据整体替换为 `user_meta`（复制到自身并使用 Replaced 元数据指令），
+    /// 内容类型、存储类型、加密方式等标准头沿用原值；对象已不是 `etag` 时失败
+    pub async fn replace_metadata(
+        &self,
+        key: &str,
+        etag: &str,
+        user_meta: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = self.key_url(key);
+
+        let current = self.send(self.client.head(&url)).await?;
+        if current.status() == 404 {
+            return Err(not_found("HEAD", &current, key));
+        }
+        if !current.status().is_success() {
+            return Err(StatusError::from_response("HEAD", &current).into());
+        }
+
+        let mut headers = HeaderMap::new();
+        for name in PRESERVED_HEADERS {
+            if let Some(value) = current.headers().get(*name) {
+                headers.insert(*name, value.clone());
+            }
+        }
+        for (name, value) in user_meta {
+            headers.insert(
+                reqwest::header::HeaderName::from_bytes(format!("{}{}", USER_META_PREFIX, name).as_bytes())?,
+                reqwest::header::HeaderValue::from_str(value)?,
+            );
+        }
+        let source = format!("{}/{}", self.host(), signer::encode_path(&self.full_key(key)));
+
+        let response = self
+            .send(
+                self.client
+                .put(&url)
+                .headers(headers)
+                .header("x-cos-copy-source", source)
+                .header("x-cos-copy-source-if-match", etag)
+                .header("x-cos-metadata-directive", "Replaced")
+            )
             .await?;
 
-        if response.status() == 404 {
-            return Err(anyhow!("Object not found: {}", key));
+        if response.status() == reqwest::StatusCode::PRECONDITION_FAILED {
+            return Err(anyhow!("Object changed while updating metadata: {}", key));
+        }
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("PUT Copy", &response).into());
+        }
+        Ok(())
+    }
+
+    /// 删除对象 (DELETE 请求)，对象不存在也视为成功
+    pub async fn delete_object(&self, key: &str) -> Result<()> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = self.key_url(key);
+
+        let response = self
+            .send(self.client.delete(&url))
+            .await?;
+
+        if !response.status().is_success() && response.status() != 404 {
+            return Err(StatusError::from_response("DELETE", &response).into());
+        }
+        Ok(())
+    }
+
+    /// 分块上传某个分块使用的地址
+    fn part_url(&self, key: &str, upload_id: &str, part_number: u32) -> String {
+        format!(
+            "{}?partNumber={}&uploadId={}",
+            self.key_url(key),
+            part_number,
+            signer::uri_encode(upload_id)
+        )
+    }
+
+    /// 开始分块上传 (Initiate Multipart Upload)，`headers` 为最终对象的 Content-Type 等头，返回 UploadId
+    pub async fn initiate_multipart_upload(&self, key: &str, headers: HeaderMap) -> Result<String> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = format!("{}?uploads", self.key_url(key));
+
+        let response = self
+            .send(self.client.post(&url).headers(headers))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("Initiate Multipart Upload", &response).into());
+        }
+
+        let body = response.text().await?;
+        let result: InitiateMultipartUploadResult = quick_xml::de::from_str(&body)
+            .map_err(|e| anyhow!("Failed to parse initiate multipart upload response: {}", e))?;
+        Ok(result.upload_id)
+    }
+
+    /// 上传一个分块 (Upload Part)，返回分块的 ETag
+    pub async fn upload_part(&self, key: &str, upload_id: &str, part_number: u32, body: Bytes) -> Result<String> {
+        let _permit = self.limiter.acquire(RequestClass::Data).await?;
+        let url = self.part_url(key, upload_id, part_number);
+
+        let response = self
+            .send(self.client.put(&url).body(body))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("Upload Part", &response).into());
         }
 
+        Ok(response
+            .headers()
+            .get("etag")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string())
+    }
+
+    /// 从已有对象的 `range` 复制一个分块 (Upload Part - Copy)，数据不经过本机。
+    /// `source_etag` 保证复制的是修改前读到的那个版本，对象已被他人改写时返回 412
+    pub async fn upload_part_copy(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        source_key: &str,
+        source_etag: &str,
+        range: Range<u64>,
+    ) -> Result<String> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = self.part_url(key, upload_id, part_number);
+        let source = format!("{}/{}", self.host(), signer::encode_path(&self.full_key(source_key)));
+
+        let response = self
+            .send(
+                self.client
+                .put(&url)
+                .header("x-cos-copy-source", source)
+                .header("x-cos-copy-source-range", format!("bytes={}-{}", range.start, range.end - 1))
+                .header("x-cos-copy-source-if-match", source_etag)
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("Upload Part - Copy", &response).into());
+        }
+
+        let body = response.text().await?;
+        let result: EtagResult = quick_xml::de::from_str(&body)
+            .map_err(|e| anyhow!("Failed to parse upload part copy response: {}", e))?;
+        Ok(result.etag)
+    }
+
+    /// 按分块编号和 ETag 合并分块 (Complete Multipart Upload)，返回新对象的 ETag
+    pub async fn complete_multipart_upload(&self, key: &str, upload_id: &str, parts: &[(u32, String)]) -> Result<String> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = format!("{}?uploadId={}", self.key_url(key), signer::uri_encode(upload_id));
+
+        let response = self
+            .send(self.client.post(&url).body(complete_multipart_body(parts)))
+            .await?;
+
         if !response.status().is_success() {
-            return Err(anyhow!("GET request failed with status: {}", response.status()));
+            return Err(StatusError::from_response("Complete Multipart Upload", &response).into());
         }
 
-        let bytes = response.bytes().await?;
-        Ok(bytes)
+        // 合并失败时也可能返回 200，错误在响应体中
+        let body = response.text().await?;
+        let result: EtagResult = quick_xml::de::from_str(&body)
+            .map_err(|_| anyhow!("Complete multipart upload of {} failed: {}", key, body))?;
+        Ok(result.etag)
+    }
+
+    /// 放弃分块上传 (Abort Multipart Upload)，释放已上传的分块
+    pub async fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<()> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = format!("{}?uploadId={}", self.key_url(key), signer::uri_encode(upload_id));
+
+        let response = self
+            .send(self.client.delete(&url))
+            .await?;
+
+        if !response.status().is_success() && response.status() != 404 {
+            return Err(StatusError::from_response("Abort Multipart Upload", &response).into());
+        }
+        Ok(())
+    }
+}
+
+impl ObjectStore for CosClient {
+    fn bucket(&self) -> &str {
+        CosClient::bucket(self)
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        CosClient::object_url(self, key)
+    }
+
+    async fn list_page(&self, prefix: &str, delimiter: bool, marker: Option<&str>) -> Result<ListPage> {
+        CosClient::list_page(self, prefix, delimiter, marker).await
+    }
+
+    async fn head_object(&self, key: &str) -> Result<ObjectMeta> {
+        CosClient::head_object(self, key).await
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        CosClient::get_object(self, key).await
+    }
+
+    async fn get_object_range(&self, key: &str, offset: u64, len: u64) -> Result<Bytes> {
+        CosClient::get_object_range(self, key, offset, len).await
+    }
+
+    async fn get_object_range_if_match(&self, key: &str, etag: &str, offset: u64, len: u64) -> Result<Bytes> {
+        CosClient::get_object_range_if_match(self, key, etag, offset, len).await
+    }
+
+    async fn put_object(&self, key: &str, body: Bytes, headers: HeaderMap) -> Result<String> {
+        CosClient::put_object(self, key, body, headers).await
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        CosClient::delete_object(self, key).await
+    }
+
+    async fn copy_object(&self, source_key: &str, dest_key: &str) -> Result<()> {
+        CosClient::copy_object(self, source_key, dest_key).await
+    }
+
+    fn region(&self) -> &str {
+        CosClient::region(self)
+    }
+
+    fn has_credentials(&self) -> bool {
+        CosClient::has_credentials(self)
+    }
+
+    fn presign_url(&self, key: &str, expires: Duration) -> Result<String> {
+        CosClient::presign_url(self, key, expires)
+    }
+
+    async fn list_first_key_under(&self, prefix: &str) -> Result<Option<String>> {
+        CosClient::list_first_key_under(self, prefix).await
+    }
+
+    async fn head_bucket(&self) -> Result<()> {
+        CosClient::head_bucket(self).await
+    }
+
+    async fn get_object_if_changed(
+        &self,
+        key: &str,
+        etag: &str,
+        max_body: u64,
+    ) -> Result<Option<(ObjectMeta, Option<Bytes>)>> {
+        CosClient::get_object_if_changed(self, key, etag, max_body).await
+    }
+
+    async fn replace_metadata(&self, key: &str, etag: &str, user_meta: &BTreeMap<String, String>) -> Result<()> {
+        CosClient::replace_metadata(self, key, etag, user_meta).await
+    }
+
+    async fn initiate_multipart_upload(&self, key: &str, headers: HeaderMap) -> Result<String> {
+        CosClient::initiate_multipart_upload(self, key, headers).await
+    }
+
+    async fn upload_part(&self, key: &str, upload_id: &str, part_number: u32, body: Bytes) -> Result<String> {
+        CosClient::upload_part(self, key, upload_id, part_number, body).await
+    }
+
+    async fn upload_part_copy(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        source_key: &str,
+        source_etag: &str,
+        range: Range<u64>,
+    ) -> Result<String> {
+        CosClient::upload_part_copy(self, key, upload_id, part_number, source_key, source_etag, range).await
+    }
+
+    async fn complete_multipart_upload(&self, key: &str, upload_id: &str, parts: &[(u32, String)]) -> Result<String> {
+        CosClient::complete_multipart_upload(self, key, upload_id, parts).await
+    }
+
+    async fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<()> {
+        CosClient::abort_multipart_upload(self, key, upload_id).await
+    }
+
+    async fn get_object_acl(&self, key: &str) -> Result<ObjectAcl> {
+        CosClient::get_object_acl(self, key).await
+    }
+
+    async fn get_processed(&self, key: &str, rule: &str) -> Result<Bytes> {
+        CosClient::get_processed(self, key, rule).await
+    }
+}
+
+/// Complete Multipart Upload 的请求体
+pub(crate) fn complete_multipart_body(parts: &[(u32, String)]) -> String {
+    let mut body = String::from("<CompleteMultipartUpload>");
+    for (part_number, etag) in parts {
+        body.push_str(&format!(
+            "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+            part_number,
+            etag.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+        ));
+    }
+    body.push_str("</CompleteMultipartUpload>");
+    body
+}
+
+/// 按附加请求头创建 HTTP 客户端
+pub fn build_http_client(extra_headers: &HeaderMap) -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .default_headers(extra_headers.clone())
+        .build()
+        .map_err(|e| anyhow!("Failed to create HTTP client: {}", e))
+}
+
+/// 去掉列表结果中的挂载前缀，前缀本身（目录标记）被丢弃
+pub(crate) fn strip_key_prefix(prefix: &str, objects: Vec<ObjectMeta>) -> Vec<ObjectMeta> {
+    if prefix.is_empty() {
+        return objects;
+    }
+    objects
+        .into_iter()
+        .filter_map(|mut meta| {
+            let key = meta.key.strip_prefix(prefix)?;
+            if key.is_empty() {
+                return None;
+            }
+            meta.key = key.to_string();
+            Some(meta)
+        })
+        .collect()
+}
+
+/// 规范化对象键前缀：去掉开头的 `/`，非空时以 `/` 结尾
+pub fn normalize_prefix(prefix: &str) -> String {
+    let prefix = prefix.trim_start_matches('/');
+    if prefix.is_empty() || prefix.ends_with('/') {
+        prefix.to_string()
+    } else {
+        format!("{}/", prefix)
+    }
+}
+
+/// 地域的默认访问域名
+pub fn default_endpoint(region: &str) -> String {
+    format!("cos.{}.myqcloud.com", region)
+}
+
+/// 从 `object_url` 生成的地址还原出 (bucket, 访问域名, key)
+pub fn parse_object_url(url: &str) -> Option<(String, String, String)> {
+    let rest = url.strip_prefix("https://")?;
+    let (host, path) = rest.split_once('/')?;
+    let (bucket, endpoint) = host.split_once('.')?;
+    if bucket.is_empty() || endpoint.is_empty() || path.is_empty() {
+        return None;
     }
+    Some((bucket.to_string(), endpoint.to_string(), signer::uri_decode(path)?))
+}
+
+/// 响应的 Date 头（服务端时间）
+fn response_date(response: &reqwest::Response) -> Option<SystemTime> {
+    response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_http_date)
+}
+
+/// 服务端时间减本机时间（秒），早于 1970 的时间返回 None
+fn clock_offset(local: SystemTime, server: SystemTime) -> Option<i64> {
+    let local = local.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let server = server.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(server as i64 - local as i64)
+}
+
+/// 把时钟偏差加到本机时间上
+fn apply_clock_offset(now: SystemTime, offset: i64) -> SystemTime {
+    if offset >= 0 {
+        now + Duration::from_secs(offset as u64)
+    } else {
+        now - Duration::from_secs(offset.unsigned_abs())
+    }
+}
+
+/// 从 HEAD/GET 响应头中提取对象元数据
+pub(crate) fn meta_from_headers(key: &str, headers: &reqwest::header::HeaderMap) -> ObjectMeta {
+    let size = headers
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+
+    // 上传时记录的高精度 mtime 优先于只精确到秒的 Last-Modified
+    let last_modified = headers
+        .get(MTIME_META_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_mtime_meta)
+        .or_else(|| {
+            headers
+                .get("last-modified")
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_http_date)
+        })
+        // 缺失或无法解析时用固定值，不能让每次 HEAD 得到不同的 mtime
+        .unwrap_or(UNIX_EPOCH);
+
+    let etag = headers
+        .get("etag")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+
+    let content_type = headers
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(String::from);
+
+    let server_side_encryption = headers
+        .get(SSE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from);
+
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(String::from);
+    let details = ObjectDetails {
+        version_id: header("x-cos-version-id"),
+        // 标准存储不返回该头
+        storage_class: header("x-cos-storage-class").or_else(|| Some("STANDARD".to_string())),
+        crc64: header("x-cos-hash-crc64ecma"),
+        request_id: header(REQUEST_ID_HEADER),
+        user_meta: headers
+            .iter()
+            .filter_map(|(name, value)| {
+                let key = name.as_str().strip_prefix(USER_META_PREFIX)?;
+                Some((key.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+            })
+            .collect(),
+    };
+
+    ObjectMeta {
+        key: key.to_string(),
+        size,
+        last_modified,
+        etag,
+        content_type,
+        server_side_encryption,
+        details,
+    }
+}
+
+/// 解析 GET Bucket 的 XML 响应
+pub(crate) fn parse_list_response(xml: &str) -> Result<Vec<ObjectMeta>> {
+    let result: ListBucketResult = quick_xml::de::from_str(xml)
+        .map_err(|e| anyhow!("Failed to parse list response: {}", e))?;
+    Ok(list_contents_to_meta(result.contents))
+}
+
+/// 解析分页列表的一页，返回 (对象, 子目录前缀, 下一页的起点)；
+/// 不带分隔符时响应中没有 NextMarker，以本页最后一个键续列；ListObjectsV2 的响应以 NextContinuationToken 续列
+pub(crate) fn parse_list_page(xml: &str) -> Result<(Vec<ObjectMeta>, Vec<String>, Option<String>)> {
+    let result: ListBucketResult = quick_xml::de::from_str(xml)
+        .map_err(|e| anyhow!("Failed to parse list response: {}", e))?;
+    let next_marker = if result.is_truncated {
+        let last = result
+            .contents
+            .last()
+            .map(|item| item.key.clone())
+            .into_iter()
+            .chain(result.common_prefixes.last().map(|p| p.prefix.clone()))
+            .max();
+        let marker = result
+            .next_marker
+            .or(result.next_continuation_token)
+            .filter(|m| !m.is_empty())
+            .or(last);
+        Some(marker.ok_or_else(|| anyhow!("Truncated list response without a marker"))?)
+    } else {
+        None
+    };
+    let prefixes = result.common_prefixes.into_iter().map(|p| p.prefix).collect();
+    Ok((list_contents_to_meta(result.contents), prefixes, next_marker))
+}
+
+fn list_contents_to_meta(contents: Vec<ListContents>) -> Vec<ObjectMeta> {
+    contents
+        .into_iter()
+        .map(|item| ObjectMeta {
+            last_modified: parse_iso8601(&item.last_modified).unwrap_or(UNIX_EPOCH),
+            key: item.key,
+            size: item.size,
+            etag: item.etag,
+            content_type: None,
+            server_side_encryption: None,
+            details: ObjectDetails { storage_class: item.storage_class, ..Default::default() },
+        })
+        .collect()
+}
+
+/// 解析 ISO 8601 时间 (例如 2019-05-24T10:56:40.000Z)，只支持 UTC
+pub fn parse_iso8601(s: &str) -> Option<SystemTime> {
+    let s = s.strip_suffix('Z')?;
+    let (date, time) = s.split_once('T')?;
+
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+
+    let (hms, frac) = time.split_once('.').unwrap_or((time, ""));
+    let mut time_parts = hms.splitn(3, ':');
+    let hour: u64 = time_parts.next()?.parse().ok()?;
+    let minute: u64 = time_parts.next()?.parse().ok()?;
+    let second: u64 = time_parts.next()?.parse().ok()?;
+
+    let nanos = if frac.is_empty() {
+        0
+    } else {
+        // 小数部分补齐到 9 位纳秒
+        let digits: String = frac.chars().take(9).collect();
+        format!("{:0<9}", digits).parse::<u32>().ok()?
+    };
+
+    utc_time(year, month, day, hour, minute, second, nanos)
+}
+
+/// 月份缩写，HTTP 日期使用
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// 解析 IMF-fixdate 格式的 HTTP 日期 (例如 Sun, 06 Nov 1994 08:49:37 GMT)，COS 的响应头都使用该格式
+fn parse_imf_fixdate(s: &str) -> Option<SystemTime> {
+    let (_, rest) = s.split_once(", ")?;
+    let mut parts = rest.split(' ');
+    let day: u32 = parts.next()?.parse().ok()?;
+    let month = month_number(parts.next()?)?;
+    let year: i64 = parts.next()?.parse().ok()?;
+    let time = parts.next()?;
+    if parts.next()? != "GMT" || parts.next().is_some() {
+        return None;
+    }
+
+    let (hour, minute, second) = parse_hms(time)?;
+    utc_time(year, month, day, hour, minute, second, 0)
+}
+
+/// 解析已废弃的 RFC 850 格式 (例如 Sunday, 06-Nov-94 08:49:37 GMT)，两位年份 70 以前视为 20xx
+fn parse_rfc850_date(s: &str) -> Option<SystemTime> {
+    let (_, rest) = s.split_once(", ")?;
+    let mut parts = rest.split(' ');
+    let mut date_parts = parts.next()?.splitn(3, '-');
+    let day: u32 = date_parts.next()?.parse().ok()?;
+    let month = month_number(date_parts.next()?)?;
+    let year = date_parts.next()?;
+    if year.len() != 2 {
+        return None;
+    }
+    let year: i64 = year.parse().ok()?;
+    let year = if year < 70 { 2000 + year } else { 1900 + year };
+    let (hour, minute, second) = parse_hms(parts.next()?)?;
+    if parts.next()? != "GMT" || parts.next().is_some() {
+        return None;
+    }
+
+    utc_time(year, month, day, hour, minute, second, 0)
+}
+
+/// 解析 C asctime() 格式 (例如 Sun Nov  6 08:49:37 1994)，一位数的日期前补空格
+fn parse_asctime_date(s: &str) -> Option<SystemTime> {
+    let mut parts = s.split_whitespace();
+    let _weekday = parts.next()?;
+    let month = month_number(parts.next()?)?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    let (hour, minute, second) = parse_hms(parts.next()?)?;
+    let year: i64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    utc_time(year, month, day, hour, minute, second, 0)
+}
 
-    /// 列出所有对象 (简化版本，实际应该使用 COS 的 ListObjects API)
-    /// 对于 demo，我们假设有一个预定义的对象列表
-    pub async fn list_objects(&self) -> Result<Vec<String>> {
-        // 这里应该调用 COS 的 ListObjects API
-        // 为了 demo 简化，我们返回一些示例对象
-        // 在实际使用中，你需要实现完整的 COS API 调用
-        Ok(vec![
-            "data/file1.txt".to_string(),
-            "data/file2.jpg".to_string(),
-            "data/subdir/file3.txt".to_string(),
-            "README.md".to_string(),
-        ])
+/// 月份缩写对应的月份 (1-12)
+fn month_number(name: &str) -> Option<u32> {
+    Some(MONTH_NAMES.iter().position(|m| *m == name)? as u32 + 1)
+}
+
+/// 解析 HH:MM:SS
+fn parse_hms(time: &str) -> Option<(u64, u64, u64)> {
+    let mut time_parts = time.splitn(3, ':');
+    let hour: u64 = time_parts.next()?.parse().ok()?;
+    let minute: u64 = time_parts.next()?.parse().ok()?;
+    let second: u64 = time_parts.next()?.parse().ok()?;
+    Some((hour, minute, second))
+}
+
+/// 校验各字段范围并转换为 SystemTime
+fn utc_time(
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u64,
+    minute: u64,
+    second: u64,
+    nanos: u32,
+) -> Option<SystemTime> {
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
     }
+
+    let days = days_from_civil(year, month, day);
+    let secs = u64::try_from(days).ok()? * 86400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::new(secs, nanos))
+}
+
+/// 公历日期到 1970-01-01 的天数 (Howard Hinnant 的 days_from_civil 算法)
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// 1970-01-01 起的天数到公历日期 (Howard Hinnant 的 civil_from_days 算法)
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// 格式化为列表结果中的 ISO 8601 UTC 时间，精确到毫秒，如 `2019-05-24T10:56:40.000Z`
+pub fn format_iso8601(at: SystemTime) -> String {
+    let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let rem = secs % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// 格式化为 [`MTIME_META_HEADER`] 的值：`秒.纳秒`
+pub fn format_mtime_meta(mtime: SystemTime) -> String {
+    let since_epoch = mtime.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("{}.{:09}", since_epoch.as_secs(), since_epoch.subsec_nanos())
+}
+
+/// 解析 [`MTIME_META_HEADER`] 的值，兼容 s3fs 写入的整数秒
+fn parse_mtime_meta(value: &str) -> Option<SystemTime> {
+    let (secs, frac) = value.trim().split_once('.').unwrap_or((value.trim(), ""));
+    let secs: u64 = secs.parse().ok()?;
+    let nanos = if frac.is_empty() {
+        0
+    } else {
+        if frac.len() > 9 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        format!("{:0<9}", frac).parse::<u32>().ok()?
+    };
+    Some(UNIX_EPOCH + Duration::new(secs, nanos))
 }
 
-/// 简单的 HTTP 日期解析器
-fn parse_http_date(_date_str: &str) -> Option<SystemTime> {
-    // 这里应该实现完整的 HTTP 日期解析
-    // 为了简化，返回当前时间
-    Some(SystemTime::now())
+/// 解析 HTTP 日期（RFC 7231 7.1.1.1）：接收方必须同时接受 IMF-fixdate 和两种旧格式
+fn parse_http_date(date_str: &str) -> Option<SystemTime> {
+    let date_str = date_str.trim();
+    parse_imf_fixdate(date_str)
+        .or_else(|| parse_rfc850_date(date_str))
+        .or_else(|| parse_asctime_date(date_str))
 }
 
 #[cfg(test)]
@@ -138,4 +1579,371 @@ mod tests {
         assert_eq!(client.bucket, "test-bucket");
         assert_eq!(client.region, "ap-beijing");
     }
+
+    #[test]
+    fn test_object_url_round_trip() {
+        let client = CosClient::new("b-125".to_string(), "ap-beijing".to_string());
+        let url = client.object_url("日志/a b.txt");
+        assert_eq!(url, "https://b-125.cos.ap-beijing.myqcloud.com/%E6%97%A5%E5%BF%97/a%20b.txt");
+        let (bucket, endpoint, key) = parse_object_url(&url).unwrap();
+        assert_eq!((bucket.as_str(), key.as_str()), ("b-125", "日志/a b.txt"));
+        assert_eq!(endpoint, default_endpoint("ap-beijing"));
+        assert_eq!(parse_object_url("https://localhost/a"), None);
+
+        // 没有密钥时不能签名
+        assert!(client.presign_url("a", Duration::from_secs(60)).is_err());
+    }
+
+    #[test]
+    fn test_multipart_bodies() {
+        let parts = vec![(1, "\"a1\"".to_string()), (2, "\"b2\"".to_string())];
+        assert_eq!(
+            complete_multipart_body(&parts),
+            "<CompleteMultipartUpload>\
+             <Part><PartNumber>1</PartNumber><ETag>\"a1\"</ETag></Part>\
+             <Part><PartNumber>2</PartNumber><ETag>\"b2\"</ETag></Part>\
+             </CompleteMultipartUpload>"
+        );
+
+        let xml = "<InitiateMultipartUploadResult><Bucket>b</Bucket><Key>k</Key>\
+                   <UploadId>1585130821cbb7df1d1</UploadId></InitiateMultipartUploadResult>";
+        let result: InitiateMultipartUploadResult = quick_xml::de::from_str(xml).unwrap();
+        assert_eq!(result.upload_id, "1585130821cbb7df1d1");
+
+        let xml = "<CopyPartResult><ETag>\"ba82b57cfdfda8bd17ad4e5879ebb4fe\"</ETag>\
+                   <LastModified>2024-05-24T10:56:40Z</LastModified></CopyPartResult>";
+        let result: EtagResult = quick_xml::de::from_str(xml).unwrap();
+        assert_eq!(result.etag, "\"ba82b57cfdfda8bd17ad4e5879ebb4fe\"");
+        assert!(quick_xml::de::from_str::<EtagResult>("<Error><Code>InternalError</Code></Error>").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sign_request() {
+        let options = ClientOptions {
+            prefix: "team-a".to_string(),
+            credentials: Some(Credentials {
+                secret_id: "AKIDtest".to_string(),
+                secret_key: "secret".to_string(),
+                session_token: Some("token".to_string()),
+            }),
+            ..Default::default()
+        };
+        let client = CosClient::with_options("b-125".to_string(), "ap-beijing".to_string(), &options)
+            .unwrap();
+        let credentials = client.credentials.as_ref().unwrap();
+
+        // 对象键按解码后的路径签名，查询参数全部列入签名
+        let url = format!("{}?versionId=v1", client.key_url("a b+c.txt"));
+        assert_eq!(url, "https://b-125.cos.ap-beijing.myqcloud.com/team-a/a%20b%2Bc.txt?versionId=v1");
+        let mut request = client.client.get(&url).build().unwrap();
+        client.sign(credentials, &mut request).unwrap();
+        let authorization = request.headers()[reqwest::header::AUTHORIZATION].to_str().unwrap();
+        assert!(authorization.contains("q-ak=AKIDtest"));
+        assert!(authorization.contains("q-url-param-list=versionid"));
+        let start: u64 = authorization
+            .split("q-sign-time=")
+            .nth(1)
+            .and_then(|time| time.split(';').next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        let expected = signer::authorization(
+            credentials,
+            "GET",
+            "team-a/a b+c.txt",
+            &[("versionId", "v1")],
+            SIGNATURE_TTL,
+            UNIX_EPOCH + Duration::from_secs(start),
+        );
+        assert_eq!(authorization, expected);
+        assert_eq!(request.headers()["x-cos-security-token"], "token");
+    }
+
+    #[tokio::test]
+    async fn test_key_prefix() {
+        assert_eq!(normalize_prefix("/team-a"), "team-a/");
+        assert_eq!(normalize_prefix("team-a/"), "team-a/");
+        assert_eq!(normalize_prefix(""), "");
+
+        let options = ClientOptions { prefix: "team-a".to_string(), ..Default::default() };
+        let client = CosClient::with_options("b-125".to_string(), "ap-beijing".to_string(), &options)
+            .unwrap();
+        assert_eq!(client.key_url("x.txt"), "https://b-125.cos.ap-beijing.myqcloud.com/team-a/x.txt");
+        assert_eq!(
+            client.list_url("", "max-keys=2"),
+            "https://b-125.cos.ap-beijing.myqcloud.com/?prefix=team-a%2F&max-keys=2"
+        );
+        assert_eq!(
+            client.list_url("logs/", "max-keys=2"),
+            "https://b-125.cos.ap-beijing.myqcloud.com/?prefix=team-a%2Flogs%2F&max-keys=2"
+        );
+
+        let meta = |key: &str| ObjectMeta {
+            key: key.to_string(),
+            size: 0,
+            last_modified: UNIX_EPOCH,
+            etag: String::new(),
+            content_type: None,
+            server_side_encryption: None,
+            details: ObjectDetails::default(),
+        };
+        let keys: Vec<String> = client
+            .strip_prefix(vec![meta("team-a/"), meta("team-a/x.txt"), meta("team-b/y")])
+            .into_iter()
+            .map(|meta| meta.key)
+            .collect();
+        assert_eq!(keys, vec!["x.txt"]);
+    }
+
+    #[test]
+    fn test_parse_iso8601() {
+        let t = parse_iso8601("2019-05-24T10:56:40.000Z").unwrap();
+        assert_eq!(t.duration_since(UNIX_EPOCH).unwrap().as_secs(), 1558695400);
+
+        let t = parse_iso8601("1970-01-01T00:00:01.5Z").unwrap();
+        assert_eq!(t.duration_since(UNIX_EPOCH).unwrap(), Duration::from_millis(1500));
+
+        assert!(parse_iso8601("2019-13-24T10:56:40Z").is_none());
+        assert!(parse_iso8601("not a date").is_none());
+
+        let t = parse_iso8601("2024-02-29T23:59:58.250Z").unwrap();
+        assert_eq!(format_iso8601(t), "2024-02-29T23:59:58.250Z");
+        assert_eq!(format_iso8601(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn test_parse_imf_fixdate() {
+        let t = parse_imf_fixdate("Fri, 24 May 2019 10:56:40 GMT").unwrap();
+        assert_eq!(t.duration_since(UNIX_EPOCH).unwrap().as_secs(), 1558695400);
+
+        assert!(parse_imf_fixdate("Fri, 24 Foo 2019 10:56:40 GMT").is_none());
+        assert!(parse_imf_fixdate("Fri, 24 May 2019 10:56:40 UTC").is_none());
+        assert!(parse_imf_fixdate("2019-05-24T10:56:40Z").is_none());
+    }
+
+    #[test]
+    fn test_parse_http_date() {
+        // RFC 7231 中的三种格式表示同一时刻
+        let expected = UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(expected));
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), Some(expected));
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), Some(expected));
+
+        let t = parse_http_date("Thursday, 01-Jan-26 00:00:00 GMT").unwrap();
+        assert_eq!(t.duration_since(UNIX_EPOCH).unwrap().as_secs(), 1767225600);
+
+        assert!(parse_http_date("Sunday, 06-Nov-1994 08:49:37 GMT").is_none());
+        assert!(parse_http_date("Sun Nov  6 08:49:37").is_none());
+        assert!(parse_http_date("Sun Nov 32 08:49:37 1994").is_none());
+        assert!(parse_http_date("").is_none());
+    }
+
+    #[test]
+    fn test_meta_from_headers_last_modified() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("last-modified", "Fri, 24 May 2019 10:56:40 GMT".parse().unwrap());
+        let meta = meta_from_headers("a.txt", &headers);
+        assert_eq!(meta.last_modified, UNIX_EPOCH + Duration::from_secs(1558695400));
+
+        headers.insert(MTIME_META_HEADER, "1558695400.123456789".parse().unwrap());
+        let meta = meta_from_headers("a.txt", &headers);
+        assert_eq!(meta.last_modified, UNIX_EPOCH + Duration::new(1558695400, 123456789));
+        assert_eq!(meta.details.storage_class.as_deref(), Some("STANDARD"));
+
+        headers.insert("x-cos-storage-class", "ARCHIVE".parse().unwrap());
+        headers.insert("x-cos-hash-crc64ecma", "1234567890".parse().unwrap());
+        headers.insert("x-cos-request-id", "NjQ1ZjYx".parse().unwrap());
+        let details = meta_from_headers("a.txt", &headers).details;
+        assert_eq!(details.storage_class.as_deref(), Some("ARCHIVE"));
+        assert_eq!(details.crc64.as_deref(), Some("1234567890"));
+        assert_eq!(details.request_id.as_deref(), Some("NjQ1ZjYx"));
+        assert!(details.version_id.is_none());
+        assert_eq!(details.user_meta.len(), 1);
+
+        headers.insert("x-cos-meta-project", "atlas".parse().unwrap());
+        let details = meta_from_headers("a.txt", &headers).details;
+        assert_eq!(details.user_meta.len(), 2);
+        assert_eq!(details.user_meta["project"], "atlas");
+        assert_eq!(details.user_meta["mtime"], "1558695400.123456789");
+    }
+
+    #[test]
+    fn test_request_ids() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        let error = StatusError::new("HEAD", StatusCode::FORBIDDEN);
+        assert_eq!(error.to_string(), "HEAD request failed with status: 403 Forbidden");
+
+        headers.insert(REQUEST_ID_HEADER, "NjQ1ZjYx".parse().unwrap());
+        let error = StatusError {
+            ids: RequestIds::from_headers(&headers),
+            ..error
+        };
+        assert_eq!(
+            error.to_string(),
+            "HEAD request failed with status: 403 Forbidden (request id: NjQ1ZjYx)"
+        );
+    }
+
+    #[test]
+    fn test_mtime_meta() {
+        let mtime = UNIX_EPOCH + Duration::new(1700000000, 5);
+        assert_eq!(format_mtime_meta(mtime), "1700000000.000000005");
+        assert_eq!(parse_mtime_meta(&format_mtime_meta(mtime)), Some(mtime));
+        // s3fs 写入整数秒
+        assert_eq!(parse_mtime_meta("1700000000"), Some(UNIX_EPOCH + Duration::from_secs(1700000000)));
+        assert_eq!(parse_mtime_meta("1.5"), Some(UNIX_EPOCH + Duration::from_millis(1500)));
+        assert!(parse_mtime_meta("-1").is_none());
+        assert!(parse_mtime_meta("1.-5").is_none());
+        assert!(parse_mtime_meta("abc").is_none());
+    }
+
+    #[test]
+    fn test_clock_offset() {
+        let local = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let server = local + Duration::from_secs(1800);
+        assert_eq!(clock_offset(local, server), Some(1800));
+        assert_eq!(clock_offset(server, local), Some(-1800));
+
+        assert_eq!(apply_clock_offset(local, 1800), server);
+        assert_eq!(apply_clock_offset(server, -1800), local);
+    }
+
+    #[test]
+    fn test_partial_download_resume() {
+        let mut download = PartialDownload::new(0, None);
+        assert_eq!(download.range(), None);
+        download.push(b"hello ");
+        assert_eq!(download.range().as_deref(), Some("bytes=6-"));
+
+        // 续传请求被忽略、返回了完整对象
+        download.restart_from_zero();
+        download.push(b"hel");
+        download.push(b"lo world");
+        assert_eq!(&download.finish()[..], b"hello world");
+
+        let mut download = PartialDownload::new(100, Some(10));
+        assert_eq!(download.range().as_deref(), Some("bytes=100-109"));
+        download.push(&[1; 4]);
+        assert_eq!(download.range().as_deref(), Some("bytes=104-109"));
+        assert!(!download.is_complete());
+        download.push(&[2; 8]);
+        assert!(download.is_complete());
+        assert_eq!(download.finish().len(), 10);
+    }
+
+    #[test]
+    fn test_parse_list_response() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult>
+    <Name>examplebucket-1250000000</Name>
+    <Prefix></Prefix>
+    <Marker></Marker>
+    <MaxKeys>1000</MaxKeys>
+    <IsTruncated>false</IsTruncated>
+    <Contents>
+        <Key>data/file1.txt</Key>
+        <LastModified>2019-05-24T10:56:40.000Z</LastModified>
+        <ETag>"5a6fd7a2e9e1c6b2f6e4c3a1b2d3e4f5"</ETag>
+        <Size>1024</Size>
+        <StorageClass>STANDARD</StorageClass>
+    </Contents>
+    <Contents>
+        <Key>README.md</Key>
+        <LastModified>2019-05-24T10:56:40.000Z</LastModified>
+        <ETag>"0123"</ETag>
+        <Size>7</Size>
+    </Contents>
+</ListBucketResult>"#;
+
+        let objects = parse_list_response(xml).unwrap();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].key, "data/file1.txt");
+        assert_eq!(objects[0].size, 1024);
+        assert_eq!(objects[0].etag, "\"5a6fd7a2e9e1c6b2f6e4c3a1b2d3e4f5\"");
+        assert_eq!(objects[1].key, "README.md");
+        assert_eq!(objects[0].details.storage_class.as_deref(), Some("STANDARD"));
+        assert!(objects[1].details.storage_class.is_none());
+
+        let empty = parse_list_response(
+            "<ListBucketResult><IsTruncated>false</IsTruncated></ListBucketResult>",
+        )
+        .unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_parse_dir_listing() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult>
+    <Prefix>data/</Prefix>
+    <Delimiter>/</Delimiter>
+    <IsTruncated>false</IsTruncated>
+    <Contents>
+        <Key>data/file1.txt</Key>
+        <LastModified>2019-05-24T10:56:40.000Z</LastModified>
+        <ETag>"0123"</ETag>
+        <Size>7</Size>
+    </Contents>
+    <CommonPrefixes>
+        <Prefix>data/logs/</Prefix>
+    </CommonPrefixes>
+    <CommonPrefixes>
+        <Prefix>data/raw/</Prefix>
+    </CommonPrefixes>
+</ListBucketResult>"#;
+
+        let (objects, prefixes, _) = parse_list_page(xml).unwrap();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].key, "data/file1.txt");
+        assert_eq!(prefixes, ["data/logs/", "data/raw/"]);
+
+        // 不指定分隔符时没有 CommonPrefixes
+        let (_, prefixes, _) = parse_list_page("<ListBucketResult></ListBucketResult>").unwrap();
+        assert!(prefixes.is_empty());
+    }
+
+    #[test]
+    fn test_parse_list_page() {
+        let page = |truncated: bool, next_marker: &str| {
+            format!(
+                r#"<ListBucketResult>
+    <IsTruncated>{}</IsTruncated>{}
+    <Contents>
+        <Key>data/a</Key>
+        <LastModified>2019-05-24T10:56:40.000Z</LastModified>
+        <ETag>"0123"</ETag>
+        <Size>1</Size>
+    </Contents>
+    <Contents>
+        <Key>data/b</Key>
+        <LastModified>2019-05-24T10:56:40.000Z</LastModified>
+        <ETag>"4567"</ETag>
+        <Size>2</Size>
+        <StorageClass>STANDARD_IA</StorageClass>
+    </Contents>
+</ListBucketResult>"#,
+                truncated, next_marker
+            )
+        };
+
+        let (objects, _, marker) = parse_list_page(&page(false, "")).unwrap();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[1].details.storage_class.as_deref(), Some("STANDARD_IA"));
+        assert_eq!(marker, None);
+
+        // 没有 NextMarker 时从本页最后一个键续列
+        let (_, _, marker) = parse_list_page(&page(true, "")).unwrap();
+        assert_eq!(marker.as_deref(), Some("data/b"));
+        let (_, _, marker) =
+            parse_list_page(&page(true, "\n    <NextMarker>data/c/</NextMarker>")).unwrap();
+        assert_eq!(marker.as_deref(), Some("data/c/"));
+        let (_, _, marker) = parse_list_page(&page(
+            true,
+            "\n    <NextContinuationToken>ChRkYXRhL2M=</NextContinuationToken>",
+        ))
+        .unwrap();
+        assert_eq!(marker.as_deref(), Some("ChRkYXRhL2M="));
+
+        assert!(parse_list_page("<ListBucketResult><IsTruncated>true</IsTruncated></ListBucketResult>").is_err());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,101 @@
+use anyhow::{bail, Result};
+use bytes::Bytes;
+use log::debug;
+use std::sync::Arc;
+
+use crate::cache::{is_disk_full, Cache};
+use crate::inflight::InFlight;
+use crate::object_store::ObjectStore;
+
+/// 内容下载器：合并同一对象（或同一区间）的并发下载，
+/// 下载结果只写入一次内容缓存，其余调用方等待同一次传输
+pub struct Downloader<S> {
+    store: Arc<S>,
+    cache: Arc<Cache>,
+    objects: InFlight<Bytes>,
+    ranges: InFlight<bool>,
+}
+
+impl<S: ObjectStore> Downloader<S> {
+    pub fn new(store: Arc<S>, cache: Arc<Cache>) -> Self {
+        Self {
+            store,
+            cache,
+            objects: InFlight::new(),
+            ranges: InFlight::new(),
+        }
+    }
+
+    pub fn store(&self) -> &Arc<S> {
+        &self.store
+    }
+
+    pub fn cache(&self) -> &Arc<Cache> {
+        &self.cache
+    }
+
+    /// 下载整个对象并按配置写入内容缓存，缓存记录为 ETag `etag`；同一个键的并发调用只会发出一次 GET。
+    /// 长度与 `size` 不符（响应提前结束或对象已变化）时返回错误且不写入缓存
+    pub async fn fetch_object(&self, key: &str, etag: &str, size: u64) -> Result<Bytes> {
+        self.objects
+            .run(key, || async {
+                debug!("Downloading object: {}", key);
+                let content = self.store.get_object(key).await?;
+                if content.len() as u64 != size {
+                    bail!(
+                        "Downloaded {} bytes of {}, expected {}",
+                        content.len(),
+                        key,
+                        size
+                    );
+                }
+
+                // 超出大小阈值的对象直接返回，不落盘
+                if !self
+                    .cache
+                    .options()
+                    .should_cache_content(content.len() as u64)
+                {
+                    debug!(
+                        "Skipping content cache for key: {} ({} bytes)",
+                        key,
+                        content.len()
+                    );
+                    return Ok(content);
+                }
+
+                if let Err(e) = self.cache.cache_content(key, etag, &content) {
+                    // 缓存盘写满不影响本次读取，直接返回已下载的内容
+                    if !is_disk_full(&e) {
+                        return Err(e);
+                    }
+                    self.cache.handle_disk_full();
+                }
+                Ok(content)
+            })
+            .await
+    }
+
+    /// 下载 [start, stop) 并写入部分缓存；同一区间的并发调用只会发出一次 GET。
+    /// 返回 false 表示缓存盘已满、内容未能写入缓存
+    pub async fn fetch_range(&self, key: &str, etag: &str, start: u64, stop: u64) -> Result<bool> {
+        let id = format!("{}\0{}\0{}-{}", key, etag, start, stop);
+        self.ranges
+            .run(&id, || async {
+                debug!("Downloading range of key: {} [{}, {})", key, start, stop);
+                let data = self
+                    .store
+                    .get_object_range(key, start, stop - start)
+                    .await?;
+                match self.cache.write_range(key, etag, start, &data) {
+                    Ok(()) => Ok(true),
+                    Err(e) if is_disk_full(&e) => {
+                        self.cache.handle_disk_full();
+                        Ok(false)
+                    }
+                    Err(e) => Err(e),
+                }
+            })
+            .await
+    }
+}
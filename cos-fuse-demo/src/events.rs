@@ -0,0 +1,165 @@
+use fuser::PollHandle;
+use log::debug;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::health::BucketState;
+use crate::trash::format_timestamp;
+
+/// 保留的最近事件数，打开事件文件时从最早保留的事件开始读
+const MAX_EVENTS: usize = 256;
+
+/// 管理工具关心的挂载事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// 对象列表刷新完成
+    Refreshed { objects: usize },
+    /// 桶状态变化：进入错误状态后所有操作直接失败，恢复后回到 `ok`
+    BucketState(BucketState),
+    /// 上传失败
+    UploadFailed { key: String, error: String },
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Refreshed { objects } => write!(f, "refresh-complete objects={}", objects),
+            Event::BucketState(state) => write!(f, "bucket-state {}", state),
+            Event::UploadFailed { key, error } => write!(f, "upload-failed {}: {}", key, error),
+        }
+    }
+}
+
+struct State {
+    /// 下一个事件的序号
+    next_seq: u64,
+    /// (序号, 以换行结尾的一行)
+    events: VecDeque<(u64, String)>,
+    /// 等待新事件的 poll 句柄，下一个事件发生时通知内核
+    waiters: Vec<PollHandle>,
+}
+
+/// 控制目录中事件文件的内容：每个打开的句柄按自己的游标读取新事件，
+/// 并可以 `select()`/`poll()` 等待下一个事件，而不必轮询统计信息
+pub struct EventLog {
+    state: Mutex<State>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                next_seq: 0,
+                events: VecDeque::new(),
+                waiters: Vec::new(),
+            }),
+        }
+    }
+
+    /// 记录一个事件并唤醒等待的 poll
+    pub fn publish(&self, event: Event) {
+        let line = format!("{} {}\n", format_timestamp(SystemTime::now()), event);
+        let waiters = {
+            let mut state = self.state.lock().unwrap();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.events.push_back((seq, line));
+            if state.events.len() > MAX_EVENTS {
+                state.events.pop_front();
+            }
+            std::mem::take(&mut state.waiters)
+        };
+        for waiter in waiters {
+            // 文件已关闭或挂载正在卸载时内核会拒绝通知
+            if let Err(e) = waiter.notify() {
+                debug!("Failed to notify poll waiter: {}", e);
+            }
+        }
+    }
+
+    /// 新打开的句柄的游标：最早保留的事件
+    pub fn first_cursor(&self) -> u64 {
+        let state = self.state.lock().unwrap();
+        state.events.front().map_or(state.next_seq, |(seq, _)| *seq)
+    }
+
+    /// 读取游标 `cursor` 之后、总长不超过 `max` 字节的完整事件行，返回内容与新游标；
+    /// 已被丢弃的事件直接跳过。一行超过 `max` 时单独返回（截断）
+    pub fn read(&self, cursor: u64, max: usize) -> (Vec<u8>, u64) {
+        let state = self.state.lock().unwrap();
+        let mut data = Vec::new();
+        let mut next = cursor;
+        for (seq, line) in state.events.iter().filter(|(seq, _)| *seq >= cursor) {
+            if data.len() + line.len() > max {
+                if data.is_empty() {
+                    data.extend_from_slice(&line.as_bytes()[..max]);
+                    next = seq + 1;
+                }
+                break;
+            }
+            data.extend_from_slice(line.as_bytes());
+            next = seq + 1;
+        }
+        (data, next.max(cursor))
+    }
+
+    /// 游标之后有没有可读的事件；没有时登记 `handle`（如果有），下一个事件发生时通知内核
+    pub fn poll(&self, cursor: u64, handle: Option<PollHandle>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.next_seq > cursor {
+            return true;
+        }
+        if let Some(handle) = handle {
+            state.waiters.push(handle);
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_log() {
+        let events = EventLog::new();
+        let cursor = events.first_cursor();
+        assert!(!events.poll(cursor, None));
+        assert_eq!(events.read(cursor, 4096), (Vec::new(), cursor));
+
+        events.publish(Event::Refreshed { objects: 3 });
+        events.publish(Event::BucketState(BucketState::AccessDenied));
+        assert!(events.poll(cursor, None));
+
+        let (data, cursor) = events.read(cursor, 4096);
+        let text = String::from_utf8(data).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(" refresh-complete objects=3"));
+        assert!(lines[1].ends_with(" bucket-state access-denied"));
+        assert!(!events.poll(cursor, None));
+
+        // 一次只返回放得下的完整行
+        events.publish(Event::UploadFailed {
+            key: "a.txt".to_string(),
+            error: "timed out".to_string(),
+        });
+        events.publish(Event::BucketState(BucketState::Ok));
+        let (data, next) = events.read(cursor, 60);
+        assert!(String::from_utf8(data)
+            .unwrap()
+            .ends_with(" upload-failed a.txt: timed out\n"));
+        assert_eq!(next, cursor + 1);
+
+        // 只保留最近的事件
+        for objects in 0..MAX_EVENTS {
+            events.publish(Event::Refreshed { objects });
+        }
+        let first = events.first_cursor();
+        assert!(first > next);
+        let (data, _) = events.read(next, usize::MAX);
+        assert_eq!(String::from_utf8(data).unwrap().lines().count(), MAX_EVENTS);
+    }
+}
@@ -0,0 +1,308 @@
+use lru::LruCache;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+/// 内容缓存淘汰策略
+pub trait EvictionPolicy: Send {
+    /// 新条目写入缓存
+    fn on_insert(&mut self, key: &str, size: u64);
+
+    /// 条目被读取
+    fn on_access(&mut self, key: &str);
+
+    /// 条目被移除（淘汰或显式删除）
+    fn on_remove(&mut self, key: &str);
+
+    /// 选出下一个应被淘汰的条目并将其从策略中移除
+    fn victim(&mut self) -> Option<String>;
+}
+
+/// 可通过配置选择的淘汰策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicyKind {
+    /// 最近最少使用
+    #[default]
+    Lru,
+    /// 最不经常使用
+    Lfu,
+    /// Greedy-Dual-Size-Frequency：兼顾访问频率与对象大小，优先淘汰冷的大文件
+    Gdsf,
+}
+
+impl EvictionPolicyKind {
+    pub fn build(self) -> Box<dyn EvictionPolicy> {
+        match self {
+            EvictionPolicyKind::Lru => Box::new(LruPolicy::new()),
+            EvictionPolicyKind::Lfu => Box::new(LfuPolicy::new()),
+            EvictionPolicyKind::Gdsf => Box::new(GdsfPolicy::new()),
+        }
+    }
+}
+
+impl FromStr for EvictionPolicyKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "lru" => Ok(EvictionPolicyKind::Lru),
+            "lfu" => Ok(EvictionPolicyKind::Lfu),
+            "gdsf" => Ok(EvictionPolicyKind::Gdsf),
+            _ => Err(format!(
+                "unknown eviction policy: {} (expected lru, lfu or gdsf)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for EvictionPolicyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EvictionPolicyKind::Lru => "lru",
+            EvictionPolicyKind::Lfu => "lfu",
+            EvictionPolicyKind::Gdsf => "gdsf",
+        };
+        f.write_str(name)
+    }
+}
+
+/// LRU 策略
+pub struct LruPolicy {
+    order: LruCache<String, ()>,
+}
+
+impl Default for LruPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LruPolicy {
+    pub fn new() -> Self {
+        Self {
+            order: LruCache::unbounded(),
+        }
+    }
+}
+
+impl EvictionPolicy for LruPolicy {
+    fn on_insert(&mut self, key: &str, _size: u64) {
+        self.order.put(key.to_string(), ());
+    }
+
+    fn on_access(&mut self, key: &str) {
+        self.order.get(key);
+    }
+
+    fn on_remove(&mut self, key: &str) {
+        self.order.pop(key);
+    }
+
+    fn victim(&mut self) -> Option<String> {
+        self.order.pop_lru().map(|(key, _)| key)
+    }
+}
+
+/// 按优先级排序的条目集合，优先级相同时先插入者先淘汰
+struct PriorityQueue {
+    /// (优先级, 序号, 键)
+    queue: BTreeSet<(u64, u64, String)>,
+    /// 键 -> (优先级, 序号)
+    index: HashMap<String, (u64, u64)>,
+    next_seq: u64,
+}
+
+impl PriorityQueue {
+    fn new() -> Self {
+        Self {
+            queue: BTreeSet::new(),
+            index: HashMap::new(),
+            next_seq: 0,
+        }
+    }
+
+    fn set(&mut self, key: &str, priority: u64) {
+        self.remove(key);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.queue.insert((priority, seq, key.to_string()));
+        self.index.insert(key.to_string(), (priority, seq));
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some((priority, seq)) = self.index.remove(key) {
+            self.queue.remove(&(priority, seq, key.to_string()));
+        }
+    }
+
+    fn pop_min(&mut self) -> Option<(u64, String)> {
+        let (priority, _, key) = self.queue.pop_first()?;
+        self.index.remove(&key);
+        Some((priority, key))
+    }
+}
+
+/// LFU 策略
+pub struct LfuPolicy {
+    frequencies: HashMap<String, u64>,
+    queue: PriorityQueue,
+}
+
+impl Default for LfuPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LfuPolicy {
+    pub fn new() -> Self {
+        Self {
+            frequencies: HashMap::new(),
+            queue: PriorityQueue::new(),
+        }
+    }
+}
+
+impl EvictionPolicy for LfuPolicy {
+    fn on_insert(&mut self, key: &str, _size: u64) {
+        self.frequencies.insert(key.to_string(), 1);
+        self.queue.set(key, 1);
+    }
+
+    fn on_access(&mut self, key: &str) {
+        if let Some(freq) = self.frequencies.get_mut(key) {
+            *freq += 1;
+            self.queue.set(key, *freq);
+        }
+    }
+
+    fn on_remove(&mut self, key: &str) {
+        self.frequencies.remove(key);
+        self.queue.remove(key);
+    }
+
+    fn victim(&mut self) -> Option<String> {
+        let (_, key) = self.queue.pop_min()?;
+        self.frequencies.remove(&key);
+        Some(key)
+    }
+}
+
+/// GDSF 策略：priority = L + frequency / size，L 为最近一次被淘汰条目的优先级
+pub struct GdsfPolicy {
+    entries: HashMap<String, (u64, u64)>,
+    queue: PriorityQueue,
+    inflation: f64,
+}
+
+impl Default for GdsfPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GdsfPolicy {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            queue: PriorityQueue::new(),
+            inflation: 0.0,
+        }
+    }
+
+    fn priority(&self, frequency: u64, size: u64) -> u64 {
+        // 优先级均为正数，f64 的位模式与数值大小同序，可直接作为排序键
+        let value = self.inflation + frequency as f64 / size.max(1) as f64;
+        value.to_bits()
+    }
+}
+
+impl EvictionPolicy for GdsfPolicy {
+    fn on_insert(&mut self, key: &str, size: u64) {
+        self.entries.insert(key.to_string(), (1, size));
+        let priority = self.priority(1, size);
+        self.queue.set(key, priority);
+    }
+
+    fn on_access(&mut self, key: &str) {
+        if let Some(&(frequency, size)) = self.entries.get(key) {
+            self.entries.insert(key.to_string(), (frequency + 1, size));
+            let priority = self.priority(frequency + 1, size);
+            self.queue.set(key, priority);
+        }
+    }
+
+    fn on_remove(&mut self, key: &str) {
+        self.entries.remove(key);
+        self.queue.remove(key);
+    }
+
+    fn victim(&mut self) -> Option<String> {
+        let (priority, key) = self.queue.pop_min()?;
+        self.inflation = f64::from_bits(priority);
+        self.entries.remove(&key);
+        Some(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_evicts_least_recently_used() {
+        let mut policy = LruPolicy::new();
+        policy.on_insert("a", 10);
+        policy.on_insert("b", 10);
+        policy.on_insert("c", 10);
+        policy.on_access("a");
+
+        assert_eq!(policy.victim().as_deref(), Some("b"));
+        assert_eq!(policy.victim().as_deref(), Some("c"));
+        assert_eq!(policy.victim().as_deref(), Some("a"));
+        assert_eq!(policy.victim(), None);
+    }
+
+    #[test]
+    fn test_lfu_evicts_least_frequently_used() {
+        let mut policy = LfuPolicy::new();
+        policy.on_insert("a", 10);
+        policy.on_insert("b", 10);
+        policy.on_access("a");
+        policy.on_access("a");
+        policy.on_access("b");
+        policy.on_insert("c", 10);
+
+        assert_eq!(policy.victim().as_deref(), Some("c"));
+        assert_eq!(policy.victim().as_deref(), Some("b"));
+        policy.on_remove("a");
+        assert_eq!(policy.victim(), None);
+    }
+
+    #[test]
+    fn test_gdsf_prefers_evicting_large_cold_objects() {
+        let mut policy = GdsfPolicy::new();
+        policy.on_insert("small-hot", 1024);
+        policy.on_insert("huge-scan", 1 << 30);
+        policy.on_insert("small-cold", 1024);
+        policy.on_access("small-hot");
+
+        assert_eq!(policy.victim().as_deref(), Some("huge-scan"));
+        assert_eq!(policy.victim().as_deref(), Some("small-cold"));
+        assert_eq!(policy.victim().as_deref(), Some("small-hot"));
+    }
+
+    #[test]
+    fn test_policy_kind_from_str() {
+        assert_eq!(
+            "LRU".parse::<EvictionPolicyKind>().unwrap(),
+            EvictionPolicyKind::Lru
+        );
+        assert_eq!(
+            "gdsf".parse::<EvictionPolicyKind>().unwrap(),
+            EvictionPolicyKind::Gdsf
+        );
+        assert!("fifo".parse::<EvictionPolicyKind>().is_err());
+    }
+}
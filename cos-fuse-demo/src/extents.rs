@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+/// 已缓存字节区间的集合（左闭右开，按起点排序且互不重叠/相邻）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtentMap {
+    extents: Vec<(u64, u64)>,
+}
+
+impl ExtentMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 标记 [start, end) 已缓存，返回新增覆盖的字节数
+    pub fn insert(&mut self, start: u64, end: u64) -> u64 {
+        if start >= end {
+            return 0;
+        }
+
+        let before = self.covered_bytes();
+
+        let mut merged_start = start;
+        let mut merged_end = end;
+        let mut result = Vec::with_capacity(self.extents.len() + 1);
+        let mut inserted = false;
+
+        for &(s, e) in &self.extents {
+            if e < merged_start {
+                result.push((s, e));
+            } else if s > merged_end {
+                if !inserted {
+                    result.push((merged_start, merged_end));
+                    inserted = true;
+                }
+                result.push((s, e));
+            } else {
+                // 重叠或相邻，合并
+                merged_start = merged_start.min(s);
+                merged_end = merged_end.max(e);
+            }
+        }
+        if !inserted {
+            result.push((merged_start, merged_end));
+        }
+
+        self.extents = result;
+        self.covered_bytes() - before
+    }
+
+    /// [start, end) 是否已完全缓存
+    pub fn covers(&self, start: u64, end: u64) -> bool {
+        if start >= end {
+            return true;
+        }
+        self.extents.iter().any(|&(s, e)| s <= start && end <= e)
+    }
+
+    /// [start, end) 中尚未缓存的区间
+    pub fn missing(&self, start: u64, end: u64) -> Vec<(u64, u64)> {
+        let mut gaps = Vec::new();
+        let mut cursor = start;
+        for &(s, e) in &self.extents {
+            if e <= cursor {
+                continue;
+            }
+            if s >= end {
+                break;
+            }
+            if s > cursor {
+                gaps.push((cursor, s.min(end)));
+            }
+            cursor = cursor.max(e);
+            if cursor >= end {
+                break;
+            }
+        }
+        if cursor < end {
+            gaps.push((cursor, end));
+        }
+        gaps
+    }
+
+    /// [start, end) 中已缓存的区间（截取到该范围内）
+    pub fn overlapping(&self, start: u64, end: u64) -> Vec<(u64, u64)> {
+        self.extents
+            .iter()
+            .filter(|&&(s, e)| s < end && e > start)
+            .map(|&(s, e)| (s.max(start), e.min(end)))
+            .collect()
+    }
+
+    /// 丢弃 `size` 之后的部分（对象被截断）
+    pub fn truncate(&mut self, size: u64) {
+        self.extents.retain(|&(s, _)| s < size);
+        if let Some(last) = self.extents.last_mut() {
+            last.1 = last.1.min(size);
+        }
+    }
+
+    /// 已缓存的总字节数
+    pub fn covered_bytes(&self) -> u64 {
+        self.extents.iter().map(|&(s, e)| e - s).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_merges_overlapping_and_adjacent() {
+        let mut map = ExtentMap::new();
+        assert_eq!(map.insert(0, 10), 10);
+        assert_eq!(map.insert(20, 30), 10);
+        assert_eq!(map.insert(5, 15), 5);
+        assert_eq!(map.insert(15, 20), 5);
+        assert_eq!(map.extents, vec![(0, 30)]);
+        assert_eq!(map.insert(0, 30), 0);
+    }
+
+    #[test]
+    fn test_covers_and_missing() {
+        let mut map = ExtentMap::new();
+        map.insert(0, 10);
+        map.insert(20, 30);
+
+        assert!(map.covers(2, 8));
+        assert!(!map.covers(5, 25));
+        assert_eq!(map.missing(5, 25), vec![(10, 20)]);
+        assert_eq!(map.missing(0, 40), vec![(10, 20), (30, 40)]);
+        assert_eq!(map.missing(40, 50), vec![(40, 50)]);
+        assert!(map.missing(20, 30).is_empty());
+        assert_eq!(map.overlapping(5, 25), vec![(5, 10), (20, 25)]);
+        assert!(map.overlapping(10, 20).is_empty());
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut map = ExtentMap::new();
+        map.insert(0, 10);
+        map.insert(20, 30);
+        map.truncate(25);
+        assert_eq!(map.extents, vec![(0, 10), (20, 25)]);
+        map.truncate(15);
+        assert_eq!(map.extents, vec![(0, 10)]);
+        map.truncate(0);
+        assert_eq!(map.covered_bytes(), 0);
+    }
+}
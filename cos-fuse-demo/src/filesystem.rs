@@ -1,85 +1,351 @@
 use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use fuser::consts::{FOPEN_DIRECT_IO, FOPEN_NONSEEKABLE, FUSE_POLL_SCHEDULE_NOTIFY};
 use fuser::{
-    FileAttr, FileType, Filesystem, KernelConfig, ReplyAttr, ReplyData, ReplyDirectory, ReplyEmpty,
-    ReplyEntry, ReplyOpen, ReplyXattr, Request,
+    FileAttr, FileType, Filesystem, KernelConfig, PollHandle, ReplyAttr, ReplyCreate, ReplyData,
+    ReplyDirectory, ReplyEmpty, ReplyEntry, ReplyOpen, ReplyPoll, ReplyWrite, ReplyXattr, Request,
+    TimeOrNow,
 };
-use libc::{EACCES, EIO, ENODATA, ENOENT, ENOTDIR, EPERM};
+use libc::{
+    EACCES, EBADF, EBUSY, EEXIST, EFBIG, EINTR, EINVAL, EIO, EISDIR, ENOENT, ENOTDIR, ENOTEMPTY,
+    EOPNOTSUPP, EPERM, ERANGE, EROFS, O_ACCMODE, O_APPEND, O_RDONLY, O_TRUNC, O_WRONLY, POLLIN,
+    POLLOUT, POLLRDNORM, POLLWRNORM, XATTR_CREATE, XATTR_REPLACE,
+};
+// 扩展属性不存在：Linux 上是 ENODATA，macOS 上是 ENOATTR
+#[cfg(not(target_os = "linux"))]
+use libc::ENOATTR;
+#[cfg(target_os = "linux")]
+use libc::ENODATA as ENOATTR;
+// renameat2 的标志：macOS 上对应 renamex_np 的 RENAME_EXCL 与 RENAME_SWAP
+#[cfg(target_os = "linux")]
+use libc::{RENAME_EXCHANGE, RENAME_NOREPLACE};
+#[cfg(not(target_os = "linux"))]
+use libc::{RENAME_EXCL as RENAME_NOREPLACE, RENAME_SWAP as RENAME_EXCHANGE};
 use log::{debug, error, info, warn};
+use serde::Serialize;
 use std::backtrace::Backtrace;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::OsStr;
-use std::fs;
+use std::future::Future;
 use std::path::Path;
-use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::runtime::Runtime;
+use tokio::sync::watch;
+use tokio::task::{JoinHandle, JoinSet};
 
-use crate::cache::Cache;
-use crate::cos_client::{CosClient, ObjectMeta};
+use crate::accounting::UidAccounting;
+use crate::acl::AclPermissions;
+use crate::archive::{ArchiveFile, ArchiveIndex, ArchiveKind, ArchiveNode, Archives};
+use crate::atime::AccessTimes;
+use crate::backend::Backend;
+use crate::bloom::{BloomFilter, DEFAULT_FALSE_POSITIVE_RATE};
+use crate::buffer_pool::{BufferPool, DEFAULT_POOL_BYTES};
+use crate::cache::{Cache, MetadataLookup};
+use crate::cache_policy::CacheMode;
+use crate::config::{AttrOptions, CacheOptions, RuntimeOptions, TransferSizes};
+use crate::cos_client::{
+    error_status, format_mtime_meta, ClientOptions, ObjectDetails, ObjectMeta, MAX_COPY_SIZE,
+    MTIME_META_HEADER, USER_META_PREFIX,
+};
+use crate::downloader::Downloader;
+use crate::events::{Event, EventLog};
+use crate::head_resolver::{HeadResolver, DEFAULT_MAX_CONCURRENT_HEADS};
+use crate::health::BucketHealth;
+use crate::hooks::{Hooks, Requester};
+use crate::idmap::{Credentials, IdMap};
+use crate::image_variants::{parse_variant, ImageVariants};
+use crate::intern::Interner;
+use crate::interrupt::PendingOps;
+use crate::janitor::spawn_janitor;
+use crate::last_error::LastErrors;
+use crate::limiter::background;
+use crate::manifest::Snapshot;
+use crate::memory::{
+    string_size, MemoryBudget, MemoryUsage, DIR_ENTRY_SIZE, DIR_KEY_SIZE, INODE_ENTRY_SIZE,
+    LISTING_ENTRY_SIZE,
+};
+use crate::metrics::{spawn_statsd, Metrics, StatsdOptions};
+use crate::mutations::PendingMutations;
+use crate::object_store::ObjectStore;
+use crate::permissions::{
+    Ownership, PermissionOverrides, DEFAULT_FILE_MODE, DEFAULT_GID, DEFAULT_UID,
+};
+use crate::prefetch::{
+    glob_match, parse_manifest, run_prefetch, PrefetchStats, PREFETCH_MANIFEST_KEY,
+};
+use crate::probe::{probe, Capabilities};
+use crate::read_data::{ReadData, MIN_MAPPED_LEN};
+use crate::readahead::{AccessPattern, Readahead};
+use crate::revalidate::Revalidator;
+use crate::s3fs_compat::{self, MetaOwnership};
+use crate::spool::{Spool, SpoolOptions};
+use crate::staging::{StagedFiles, WriteHandle};
+use crate::system_xattrs::{SystemXattr, SystemXattrs};
+use crate::upload::UploadOptions;
 
 /// 文件系统 inode 分配器
 const ROOT_INODE: u64 = 1;
-const FIRST_DYNAMIC_INODE: u64 = 2;
+const CONTROL_DIR_INODE: u64 = 2;
+const EVENTS_INODE: u64 = 3;
+const FIRST_DYNAMIC_INODE: u64 = 4;
+
+/// 挂载根目录下的控制目录：只能按名字访问，不出现在 readdir 中，桶中同名的前缀被遮蔽
+const CONTROL_DIR_NAME: &str = ".cosfs";
+
+/// 控制目录中的事件文件，每行一个事件，可以 poll 等待新事件
+const EVENTS_FILE_NAME: &str = "events";
+
+/// 目录缓存的默认有效期
+const DEFAULT_DIR_CACHE_TTL: Duration = Duration::from_secs(30);
 
-/// 目录条目
+/// 向 COS 确认过不存在的路径在这段时间内直接答复 ENOENT，不重复请求
+const MISS_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// 一次预读中同时下载的块数
+const READAHEAD_CONCURRENCY: usize = 4;
+
+/// 关闭时上传失败的文件在后台重试的间隔
+const UPLOAD_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 一次 LIST 请求最多返回的对象数：访问过的目录多到逐个列出比完整列表还贵时，周期刷新改为完整列表
+const LIST_PAGE_KEYS: usize = 1000;
+
+/// 目录条目（inode 在 readdir 时按路径解析，刷新后不会失效）
 #[derive(Debug, Clone)]
 struct DirEntry {
-    name: String,
-    ino: u64,
+    name: Arc<str>,
     file_type: FileType,
 }
 
-/// COS 文件系统实现
-pub struct CosFilesystem {
-    /// COS 客户端
-    cos_client: CosClient,
+/// 目录句柄打开时拍下的完整目录项（含 `.` 与 `..`），readdir 的偏移量是其中的下标
+type DirSnapshot = Vec<(u64, FileType, String)>;
 
-    /// 缓存系统
-    cache: Cache,
+/// 以写方式打开的对象的暂存写入，同一对象的多个句柄共用一份；跨 await 持有的是它自己的锁
+type SharedWriteHandle = Arc<tokio::sync::Mutex<WriteHandle>>;
+
+/// 带时间戳的目录缓存
+#[derive(Debug, Clone)]
+struct CachedDir {
+    entries: Vec<DirEntry>,
+    cached_at: Instant,
+}
+
+impl CachedDir {
+    fn new(entries: Vec<DirEntry>) -> Self {
+        Self {
+            entries,
+            cached_at: Instant::now(),
+        }
+    }
+
+    /// 是否仍在有效期内
+    fn is_fresh(&self, ttl: Duration) -> bool {
+        self.cached_at.elapsed() < ttl
+    }
+}
+
+/// 可以答复错误码的 FUSE reply，异步操作在出错或被中断时统一使用
+trait ErrorReply: Send + 'static {
+    fn error(self, errno: i32);
+}
+
+macro_rules! impl_error_reply {
+    ($($reply:ty),*) => {
+        $(impl ErrorReply for $reply {
+            fn error(self, errno: i32) {
+                <$reply>::error(self, errno)
+            }
+        })*
+    };
+}
+
+impl_error_reply!(
+    ReplyEntry,
+    ReplyAttr,
+    ReplyData,
+    ReplyXattr,
+    ReplyEmpty,
+    ReplyOpen,
+    ReplyWrite,
+    ReplyCreate
+);
+
+/// 对象服务端加密方式的只读扩展属性，值为 `AES256`（SSE-COS）、`cos/kms`（SSE-KMS）或 `none`
+const SSE_XATTR: &str = "user.cos.server-side-encryption";
+
+/// 对象 CRC64-ECMA 校验值（十进制）的只读扩展属性；上传时没有记录校验值的对象没有该属性
+const CRC64_XATTR: &str = "user.cos.crc64";
+
+/// 对象（未签名）访问地址的只读扩展属性，`cosfs presign` 据此找到挂载点对应的 bucket
+pub const OBJECT_URL_XATTR: &str = "user.cos.object-url";
+
+/// 限时预签名下载地址的只读扩展属性，每次读取重新签名；只在配置了访问密钥时提供
+const PRESIGNED_URL_XATTR: &str = "user.cos.presigned-url";
+
+/// 文件的调试信息扩展属性：HEAD 得到的版本、存储类型、CRC64、请求 ID 与本地缓存状态（JSON）
+const INFO_XATTR: &str = "user.cosfs.info";
+
+/// `user.cosfs.info` 的内容
+#[derive(Serialize)]
+struct ObjectInfo<'a> {
+    key: &'a str,
+    size: u64,
+    etag: &'a str,
+    /// `秒.纳秒`
+    mtime: String,
+    server_side_encryption: Option<&'a str>,
+    #[serde(flatten)]
+    details: &'a ObjectDetails,
+    /// none、partial 或 cached
+    cache: &'static str,
+}
+
+/// 挂载根目录上的只读扩展属性：桶的运行状态（ok、bucket-deleted、access-denied）
+const HEALTH_XATTR: &str = "user.cosfs.health";
+
+/// 最近一次失败的时间与错误（含 COS 请求 ID）：文件上是该对象的，根目录上是整个挂载的
+const LAST_ERROR_XATTR: &str = "user.cosfs.last-error";
+
+/// 挂载根目录上的只读扩展属性：按请求者 uid 统计的读写字节数与限速等待时间（JSON）
+const USAGE_XATTR: &str = "user.cosfs.usage";
+
+/// 挂载根目录上的只读扩展属性：内存上限与各部分状态的内存占用估计（JSON）
+const MEMORY_XATTR: &str = "user.cosfs.memory";
+
+/// 写入即刷新的扩展属性：根目录上重新列出整个桶，其他目录上只重新列出该目录
+const REFRESH_XATTR: &str = "user.cosfs.refresh";
+
+/// 对象用户自定义元数据 `x-cos-meta-<键>` 对应的扩展属性前缀，可读写
+const USER_META_XATTR_PREFIX: &str = "user.cos.meta.";
+
+/// 只读的扩展属性，setxattr/removexattr 答复 EPERM
+const READ_ONLY_XATTRS: &[&str] = &[
+    SSE_XATTR,
+    CRC64_XATTR,
+    OBJECT_URL_XATTR,
+    PRESIGNED_URL_XATTR,
+    INFO_XATTR,
+    HEALTH_XATTR,
+    LAST_ERROR_XATTR,
+    USAGE_XATTR,
+    MEMORY_XATTR,
+];
+
+/// 用户自定义元数据的键：小写字母、数字与 `-`、`_`、`.`（HTTP 头名不区分大小写，COS 以小写保存）
+fn is_valid_meta_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b"-_.".contains(&b))
+}
+
+/// open 的标志是否要求写入：写方式打开，或者要截断、追加
+fn wants_write(flags: i32) -> bool {
+    flags & O_ACCMODE != O_RDONLY || flags & (O_TRUNC | O_APPEND) != 0
+}
+
+/// 写入路径上失败时暂存区的本地错误码（如配额用尽的 ENOSPC），COS 请求失败时为 None
+fn io_errno(error: &anyhow::Error) -> Option<i32> {
+    error
+        .downcast_ref::<std::io::Error>()
+        .and_then(std::io::Error::raw_os_error)
+}
+
+/// 按 getxattr/listxattr 的约定答复：`size` 为 0 时只返回所需大小，缓冲区不够时返回 ERANGE
+fn reply_xattr(reply: ReplyXattr, size: u32, value: &[u8]) {
+    if size == 0 {
+        reply.size(value.len() as u32);
+    } else if value.len() > size as usize {
+        reply.error(ERANGE);
+    } else {
+        reply.data(value);
+    }
+}
+
+/// listxattr 的答复格式：以 NUL 结尾的属性名依次排列
+fn xattr_name_list<S: AsRef<str>>(names: &[S]) -> Vec<u8> {
+    names
+        .iter()
+        .flat_map(|name| name.as_ref().bytes().chain(Some(0)))
+        .collect()
+}
+
+/// 目录下对象键的公共前缀：根目录下对象键本身即为相对路径，子目录为 "dir/"
+fn dir_prefix(dir: &str) -> String {
+    if dir == "/" {
+        String::new()
+    } else {
+        format!("{}/", dir.trim_start_matches('/'))
+    }
+}
+
+/// 命名空间状态：inode 映射、对象列表与目录缓存
+struct Namespace {
+    /// 对象键、路径与目录项名字的驻留池，下面各个映射共享其中的字符串
+    strings: Interner,
 
     /// inode 到路径的映射
-    inode_to_path: HashMap<u64, String>,
+    inode_to_path: HashMap<u64, Arc<str>>,
 
     /// 路径到 inode 的映射
-    path_to_inode: HashMap<String, u64>,
+    path_to_inode: HashMap<Arc<str>, u64>,
 
     /// 下一个可用的 inode 号
     next_inode: u64,
 
     /// 对象列表缓存（用于构建虚拟目录结构）
-    object_list: Vec<String>,
+    object_list: Vec<Arc<str>>,
 
-    /// 目录条目缓存（减少重复的readdir调用）
-    dir_cache: HashMap<String, Vec<DirEntry>>,
+    /// 目录条目缓存（减少重复的readdir调用），每个目录独立过期
+    dir_cache: HashMap<Arc<str>, CachedDir>,
 
-    /// 共享的异步运行时
-    runtime: Arc<Runtime>,
-}
+    /// 路径是否为目录的判定缓存，随目录缓存一起失效
+    is_dir_cache: HashMap<Arc<str>, bool>,
 
-impl CosFilesystem {
-    pub fn new(bucket: String, region: String, cache_dir: &Path) -> Result<Self> {
-        let cos_client = CosClient::new(bucket, region);
-        let cache = Cache::new(cache_dir, 1000)?;
+    /// 已知路径（对象及其父目录）的布隆过滤器，每次列表刷新时重建，
+    /// 用于在任何网络请求之前快速给出“不存在”的答复
+    known_paths: Option<BloomFilter>,
 
-        // 创建共享的运行时
-        let runtime = Runtime::new().map_err(|e| anyhow!("Failed to create runtime: {}", e))?;
+    /// 向 COS 确认过不存在的路径及确认时间，列表刷新时清空
+    recent_misses: HashMap<String, Instant>,
 
-        let mut fs = Self {
-            cos_client,
-            cache,
+    /// 已删除对象的墓碑（对象键及删除时间），在下一次完整列表之前不让它们重新出现
+    tombstones: HashMap<String, Instant>,
+
+    /// 列出过或在其中查找过的目录，周期刷新只重新列出这些目录
+    listed_dirs: HashSet<Arc<str>>,
+
+    /// listxattr 已确认没有自身扩展属性的 inode（目录、归档与图片虚拟文件），
+    /// 之后直接答复；inode 重新映射或路径变化时失效
+    xattr_free: HashSet<u64>,
+}
+
+impl Namespace {
+    fn new() -> Self {
+        let mut namespace = Self {
+            strings: Interner::default(),
             inode_to_path: HashMap::new(),
             path_to_inode: HashMap::new(),
             next_inode: FIRST_DYNAMIC_INODE,
             object_list: Vec::new(),
             dir_cache: HashMap::new(),
-            runtime: Arc::new(runtime),
+            is_dir_cache: HashMap::new(),
+            known_paths: None,
+            recent_misses: HashMap::new(),
+            tombstones: HashMap::new(),
+            listed_dirs: HashSet::new(),
+            xattr_free: HashSet::new(),
         };
 
         // 初始化根目录
-        fs.inode_to_path.insert(ROOT_INODE, "/".to_string());
-        fs.path_to_inode.insert("/".to_string(), ROOT_INODE);
-
-        Ok(fs)
+        let root = namespace.strings.intern("/");
+        namespace
+            .inode_to_path
+            .insert(ROOT_INODE, Arc::clone(&root));
+        namespace.path_to_inode.insert(root, ROOT_INODE);
+        namespace
     }
 
     /// 分配新的 inode
@@ -96,13 +362,14 @@ impl CosFilesystem {
         }
 
         let ino = self.allocate_inode();
-        self.inode_to_path.insert(ino, path.to_string());
-        self.path_to_inode.insert(path.to_string(), ino);
+        let path = self.strings.intern(path);
+        self.inode_to_path.insert(ino, Arc::clone(&path));
+        self.path_to_inode.insert(path, ino);
         ino
     }
 
     /// 获取 inode 对应的路径
-    fn get_path(&self, ino: u64) -> Option<&String> {
+    fn get_path(&self, ino: u64) -> Option<&str> {
         // 捕获调用栈用于调试
         let backtrace = Backtrace::force_capture();
         info!(
@@ -110,237 +377,472 @@ impl CosFilesystem {
             ino, backtrace
         );
 
-        self.inode_to_path.get(&ino)
+        self.inode_to_path.get(&ino).map(|path| &**path)
     }
 
-    /// 刷新对象列表（非借用版本）
-    async fn refresh_object_list_async(&mut self) -> Result<()> {
-        info!("Refreshing object list from COS");
-        self.object_list = self.cos_client.list_objects().await?;
+    /// 使单个目录的缓存失效
+    fn invalidate_dir(&mut self, dir: &str) {
+        if self.dir_cache.remove(dir).is_some() {
+            debug!("Invalidated dir cache for {}", dir);
+        }
+    }
 
-        // 清理旧的 inode 映射（保留根目录）
-        self.inode_to_path.clear();
-        self.path_to_inode.clear();
-        self.next_inode = FIRST_DYNAMIC_INODE;
+    /// 使路径所有祖先目录的缓存失效（在其下写入、删除、重命名后调用）
+    fn invalidate_ancestors(&mut self, path: &str) {
+        // 路径本身及其祖先的文件/目录判定都可能随之改变
+        self.is_dir_cache.remove(path);
+        if let Some(ino) = self.path_to_inode.get(path) {
+            self.xattr_free.remove(ino);
+        }
 
-        // 清空目录缓存
-        self.dir_cache.clear();
+        let mut current = Path::new(path).parent();
+        while let Some(dir) = current {
+            let dir_str = dir.to_string_lossy();
+            self.is_dir_cache.remove(dir_str.as_ref());
+            self.invalidate_dir(&dir_str);
+            current = dir.parent();
+        }
+    }
 
-        // 重新添加根目录
-        self.inode_to_path.insert(ROOT_INODE, "/".to_string());
-        self.path_to_inode.insert("/".to_string(), ROOT_INODE);
+    /// 用 `listed_at` 时开始的列表结果替换当前列表并重建布隆过滤器。
+    /// 仍然存在的路径保留原来的 inode，打开的文件句柄和内核持有的 inode 号在刷新后继续有效；
+    /// 只释放已消失路径的映射。inode 在 lookup/readdir 时按需分配，没有访问过的对象不占用映射
+    fn replace_object_list(&mut self, new_list: Vec<String>, listed_at: Instant) {
+        // 列表开始之后才删除的对象可能仍在结果中，继续隐藏；更早的墓碑已由这次列表确认
+        self.tombstones
+            .retain(|_, deleted_at| *deleted_at > listed_at);
+        let new_list: Vec<Arc<str>> = new_list
+            .iter()
+            .filter(|key| !self.tombstones.contains_key(*key))
+            .map(|key| self.strings.intern(key))
+            .collect();
 
-        // 为所有对象路径创建 inode 映射
-        for object_key in self.object_list.clone() {
-            let path = format!("/{}", object_key);
-            self.get_or_create_inode(&path);
+        // 只让发生变化的对象所在目录失效，未变化的目录缓存继续有效
+        let old_keys: HashSet<&str> = self.object_list.iter().map(|key| &**key).collect();
+        let new_keys: HashSet<&str> = new_list.iter().map(|key| &**key).collect();
+        let changed: Vec<String> = old_keys
+            .symmetric_difference(&new_keys)
+            .map(|key| format!("/{}", key))
+            .collect();
+        let removed: Vec<String> = old_keys
+            .difference(&new_keys)
+            .map(|key| key.trim_end_matches('/').to_string())
+            .collect();
+        for path in &changed {
+            self.invalidate_ancestors(path);
+        }
+        self.object_list = new_list;
 
-            // 为所有父目录创建 inode
-            let mut current_path = Path::new(&path).parent().unwrap_or(Path::new("/"));
-            while current_path != Path::new("/") {
-                let current_path_str = current_path.to_string_lossy();
-                self.get_or_create_inode(&current_path_str);
-                current_path = current_path.parent().unwrap_or(Path::new("/"));
+        // 布隆过滤器包含所有对象路径（"dir/" 形式的目录标记对象即目录本身）及其父目录
+        let mut dirs: HashSet<&str> = HashSet::new();
+        for object_key in &self.object_list {
+            let mut current = object_key.trim_end_matches('/');
+            while let Some((parent, _)) = current.rsplit_once('/') {
+                if !dirs.insert(parent) {
+                    break;
+                }
+                current = parent;
             }
         }
 
-        info!("Loaded {} objects from COS", self.object_list.len());
-        Ok(())
-    }
+        // 消失的路径：已删除的对象，以及其下不再有任何对象的父目录
+        let mut gone: HashSet<&str> = HashSet::new();
+        for key in &removed {
+            let mut current = key.as_str();
+            while !dirs.contains(current) && gone.insert(current) {
+                match current.rsplit_once('/') {
+                    Some((parent, _)) => current = parent,
+                    None => break,
+                }
+            }
+        }
+        // 文件变成了同名的目录标记对象（或反过来）时路径仍然存在
+        if !gone.is_empty() {
+            for key in &self.object_list {
+                gone.remove(key.trim_end_matches('/'));
+            }
+        }
+        let gone: Vec<String> = gone.into_iter().map(|path| format!("/{}", path)).collect();
+
+        let mut filter = BloomFilter::new(
+            self.object_list.len() + dirs.len() + 1,
+            DEFAULT_FALSE_POSITIVE_RATE,
+        );
+        filter.insert("/");
+        for path in self
+            .object_list
+            .iter()
+            .map(|key| key.trim_end_matches('/'))
+            .chain(dirs)
+        {
+            filter.insert(&format!("/{}", path));
+        }
+        self.known_paths = Some(filter);
+        self.recent_misses.clear();
 
-    /// 刷新对象列表
-    async fn refresh_object_list(&mut self) -> Result<()> {
-        self.refresh_object_list_async().await
+        for path in &gone {
+            self.forget_path(path);
+        }
+        self.strings.purge();
     }
 
-    /// 获取对象的元数据
-    async fn get_object_metadata(&self, key: &str) -> Result<ObjectMeta> {
-        // 先检查缓存
-        if let Some(meta) = self.cache.get_metadata(key) {
-            debug!("Metadata cache hit for key: {}", key);
-            return Ok(meta);
+    /// 路径已不存在：释放它的 inode 映射
+    fn forget_path(&mut self, path: &str) {
+        if let Some(ino) = self.path_to_inode.remove(path) {
+            self.inode_to_path.remove(&ino);
+            self.xattr_free.remove(&ino);
         }
+        self.listed_dirs.remove(path);
+    }
 
-        debug!("Metadata cache miss for key: {}, fetching from COS", key);
-        let meta = self.cos_client.head_object(key).await?;
+    /// 目录已整个消失：释放其下所有路径的 inode 映射与缓存
+    fn forget_subtree(&mut self, dir: &str) {
+        let prefix = format!("{}/", dir);
+        let paths: Vec<Arc<str>> = self
+            .path_to_inode
+            .keys()
+            .filter(|path| path.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for path in paths {
+            self.forget_path(&path);
+        }
+        self.invalidate_dir(dir);
+        self.dir_cache.retain(|path, _| !path.starts_with(&prefix));
+        self.is_dir_cache
+            .retain(|path, _| !path.starts_with(&prefix));
+        self.listed_dirs.retain(|path| !path.starts_with(&prefix));
+    }
 
-        // 缓存元数据
-        self.cache.set_metadata(key.to_string(), meta.clone());
+    /// 记下访问过的目录，之后的周期刷新会重新列出它
+    fn mark_listed(&mut self, dir: &str) {
+        if !self.listed_dirs.contains(dir) {
+            let dir = self.strings.intern(dir);
+            self.listed_dirs.insert(dir);
+        }
+    }
 
-        Ok(meta)
+    fn listed_dirs(&self) -> Vec<String> {
+        self.listed_dirs.iter().map(|dir| dir.to_string()).collect()
     }
 
-    /// 获取对象的元数据并转换为FileAttr
-    async fn get_object_meta(&mut self, path: &str) -> Result<FileAttr> {
-        // 先检查缓存
-        if let Some(meta) = self.cache.get_metadata(path) {
-            let ino = self.get_or_create_inode(path);
-            return Ok(self.meta_to_attr(&meta, ino));
+    /// 用 `listed_at` 时开始的单层列表结果更新目录 `dir` 的直接子项：消失的对象移出列表，
+    /// 消失的子目录连同其下所有对象一起移除，新出现的子目录以目录标记对象加入，其中的内容在被访问后再列出。
+    /// 其他目录不受影响，仍然存在的路径保留原来的 inode
+    fn merge_dir_listing(
+        &mut self,
+        dir: &str,
+        objects: Vec<String>,
+        prefixes: Vec<String>,
+        listed_at: Instant,
+    ) {
+        let dir_prefix = dir_prefix(dir);
+        // 目录下一层的对象（不含子目录中的对象与目录自身的标记）
+        let is_child = |key: &str| {
+            key.strip_prefix(dir_prefix.as_str())
+                .is_some_and(|name| !name.is_empty() && !name.contains('/'))
+        };
+
+        // 列表开始之后才删除的对象可能仍在结果中，继续隐藏；该目录下更早的墓碑已由这次列表确认
+        self.tombstones
+            .retain(|key, deleted_at| *deleted_at > listed_at || !is_child(key));
+        let new_files: HashSet<String> = objects
+            .into_iter()
+            .filter(|key| is_child(key) && !self.tombstones.contains_key(key))
+            .collect();
+        let new_dirs: HashSet<String> = prefixes
+            .iter()
+            .filter(|prefix| !self.tombstones.contains_key(*prefix))
+            .filter_map(|prefix| prefix.strip_prefix(dir_prefix.as_str())?.strip_suffix('/'))
+            .filter(|name| !name.is_empty() && !name.contains('/'))
+            .map(str::to_string)
+            .collect();
+
+        let mut old_files: HashSet<String> = HashSet::new();
+        let mut old_dirs: HashSet<String> = HashSet::new();
+        for key in &self.object_list {
+            let Some(rest) = key.strip_prefix(dir_prefix.as_str()) else {
+                continue;
+            };
+            match rest.split_once('/') {
+                Some((name, _)) if !name.is_empty() => {
+                    old_dirs.insert(name.to_string());
+                }
+                None if !rest.is_empty() => {
+                    old_files.insert(key.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        let removed_files: HashSet<&String> = old_files.difference(&new_files).collect();
+        let added_files: Vec<&String> = new_files.difference(&old_files).collect();
+        let removed_dirs: Vec<String> = old_dirs
+            .difference(&new_dirs)
+            .map(|name| format!("{}{}/", dir_prefix, name))
+            .collect();
+        let added_dirs: Vec<String> = new_dirs
+            .difference(&old_dirs)
+            .map(|name| format!("{}{}/", dir_prefix, name))
+            .collect();
+        if removed_files.is_empty()
+            && added_files.is_empty()
+            && removed_dirs.is_empty()
+            && added_dirs.is_empty()
+        {
+            return;
         }
 
-        // 从 COS 获取元数据
-        let meta = self.cos_client.head_object(&path[1..]).await?; // 去掉开头的 '/'
+        self.object_list.retain(|key| {
+            !removed_files.contains(&key.to_string())
+                && !removed_dirs
+                    .iter()
+                    .any(|prefix| key.starts_with(prefix.as_str()))
+        });
+        for key in added_files.iter().copied().chain(&added_dirs) {
+            let path = format!("/{}", key.trim_end_matches('/'));
+            self.recent_misses.remove(&path);
+            if let Some(filter) = &mut self.known_paths {
+                filter.insert(&path);
+            }
+            let key = self.strings.intern(key);
+            self.object_list.push(key);
+        }
 
-        // 缓存元数据
-        self.cache.set_metadata(path.to_string(), meta.clone());
+        let changed: Vec<String> = removed_files
+            .iter()
+            .copied()
+            .chain(added_files.iter().copied())
+            .chain(&removed_dirs)
+            .chain(&added_dirs)
+            .map(|key| format!("/{}", key.trim_end_matches('/')))
+            .collect();
+        for path in &changed {
+            self.invalidate_ancestors(path);
+        }
 
-        let ino = self.get_or_create_inode(path);
-        Ok(self.meta_to_attr(&meta, ino))
+        // 消失的路径：文件变成了同名目录（或反过来）时路径仍然存在
+        for key in &removed_files {
+            if !new_dirs.contains(&key[dir_prefix.len()..]) {
+                self.forget_path(&format!("/{}", key));
+            }
+        }
+        for prefix in &removed_dirs {
+            let key = prefix.trim_end_matches('/');
+            let path = format!("/{}", key);
+            if !new_files.contains(key) {
+                self.forget_path(&path);
+            }
+            self.forget_subtree(&path);
+        }
+        self.strings.purge();
     }
 
-    /// 获取对象内容
-    async fn get_object_content(&self, key: &str) -> Result<Vec<u8>> {
-        // 先检查 L2 缓存
-        if self.cache.is_content_cached(key) {
-            debug!("Content cache hit for key: {}", key);
-            return self.cache.get_cached_content(key);
+    /// 内存不足时丢弃可以按需重建的目录缓存、目录判定缓存与不存在路径的缓存，
+    /// 连同只被它们引用的驻留字符串，返回释放的字节数
+    fn trim_caches(&mut self) -> u64 {
+        let freed = self.cache_bytes();
+        self.dir_cache.clear();
+        self.is_dir_cache.clear();
+        self.recent_misses.clear();
+        freed + self.strings.purge()
+    }
+
+    /// 目录缓存、目录判定缓存与不存在路径的缓存占用的内存估计（不含驻留的字符串）
+    fn cache_bytes(&self) -> u64 {
+        let dirs: u64 = self
+            .dir_cache
+            .values()
+            .map(|cached| DIR_KEY_SIZE + cached.entries.len() as u64 * DIR_ENTRY_SIZE)
+            .sum();
+        let misses: u64 = self
+            .recent_misses
+            .keys()
+            .map(|path| string_size(path) + 16)
+            .sum();
+        dirs + self.is_dir_cache.len() as u64 * DIR_KEY_SIZE + misses
+    }
+
+    /// 驻留字符串、对象列表、inode 映射与目录缓存占用的内存估计
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            strings: self.strings.bytes(),
+            listing: self.object_list.len() as u64 * LISTING_ENTRY_SIZE,
+            inodes: self.path_to_inode.len() as u64 * INODE_ENTRY_SIZE
+                + self.listed_dirs.len() as u64 * DIR_KEY_SIZE,
+            dir_cache: self.cache_bytes(),
+            ..Default::default()
         }
+    }
 
-        debug!("Content cache miss for key: {}, downloading from COS", key);
-        let content = self.cos_client.get_object(key).await?;
+    /// 列表中是否有对象 `key`
+    fn contains_object(&self, key: &str) -> bool {
+        self.object_list.iter().any(|k| **k == *key)
+    }
 
-        // 缓存内容
-        self.cache.cache_content(key, &content)?;
+    /// 对象已删除：移出列表并记下墓碑，目录缓存随之失效
+    fn remove_object(&mut self, object_key: &str) {
+        self.object_list.retain(|key| **key != *object_key);
+        self.tombstones
+            .insert(object_key.to_string(), Instant::now());
+        self.invalidate_ancestors(&format!("/{}", object_key.trim_end_matches('/')));
+    }
 
-        Ok(content.to_vec())
+    /// 目录已删除：移出其标记与其下残留的对象并记下标记的墓碑，释放其下所有路径
+    fn remove_dir(&mut self, path: &str) {
+        let prefix = dir_prefix(path);
+        self.object_list
+            .retain(|key| !key.starts_with(prefix.as_str()));
+        self.tombstones.insert(prefix, Instant::now());
+        self.invalidate_ancestors(path);
+        self.forget_path(path);
+        self.forget_subtree(path);
     }
 
-    /// 将 ObjectMeta 转换为 FileAttr
-    fn meta_to_attr(&self, meta: &ObjectMeta, ino: u64) -> FileAttr {
-        FileAttr {
-            ino,
-            size: meta.size,
-            blocks: (meta.size + 511) / 512, // 块大小为 512 字节
-            atime: meta.last_modified,
-            mtime: meta.last_modified,
-            ctime: meta.last_modified,
-            crtime: meta.last_modified,
-            kind: FileType::RegularFile,
-            perm: 0o644, // 默认文件权限
-            nlink: 1,
-            uid: 501, // 默认用户 ID
-            gid: 20,  // 默认组 ID
-            rdev: 0,
-            blksize: 4096,
-            flags: 0,
+    /// 路径 `from`（文件或目录）已改名为 `to`，`moved` 为复制过去的 (原对象键, 新对象键)。
+    /// 原路径及其下路径的 inode 随之改名（内核在改名后仍使用原来的 inode），目标上原有的路径被替换
+    fn rename_tree(&mut self, from: &str, to: &str, moved: &[(String, String)]) {
+        for (old_key, new_key) in moved {
+            self.object_list.retain(|key| **key != *old_key);
+            self.tombstones.insert(old_key.clone(), Instant::now());
+            self.tombstones.remove(new_key);
+            if !self.contains_object(new_key) {
+                let key = self.strings.intern(new_key);
+                self.object_list.push(key);
+            }
+            let path = format!("/{}", new_key.trim_end_matches('/'));
+            self.recent_misses.remove(&path);
+            if let Some(filter) = &mut self.known_paths {
+                filter.insert(&path);
+            }
+        }
+        self.invalidate_ancestors(from);
+        self.invalidate_ancestors(to);
+        self.forget_path(to);
+        self.forget_subtree(to);
+
+        let from_prefix = format!("{}/", from);
+        let paths: Vec<(Arc<str>, u64)> = self
+            .path_to_inode
+            .iter()
+            .filter(|(path, _)| ***path == *from || path.starts_with(&from_prefix))
+            .map(|(path, &ino)| (Arc::clone(path), ino))
+            .collect();
+        for (path, ino) in paths {
+            self.path_to_inode.remove(&path);
+            self.xattr_free.remove(&ino);
+            let renamed = self
+                .strings
+                .intern(&format!("{}{}", to, &path[from.len()..]));
+            self.inode_to_path.insert(ino, Arc::clone(&renamed));
+            self.path_to_inode.insert(renamed, ino);
         }
+        self.forget_path(from);
+        self.forget_subtree(from);
+        self.strings.purge();
     }
 
-    /// 创建目录属性
-    fn create_dir_attr(&self, ino: u64) -> FileAttr {
-        let now = SystemTime::now();
-        FileAttr {
-            ino,
-            size: 0,
-            blocks: 0,
-            atime: now,
-            mtime: now,
-            ctime: now,
-            crtime: now,
-            kind: FileType::Directory,
-            perm: 0o755, // 默认目录权限
-            nlink: 2,
-            uid: 501,
-            gid: 20,
-            rdev: 0,
-            blksize: 4096,
-            flags: 0,
+    /// 把列表之外新发现的对象加入列表（lookup 未命中后在 COS 上找到），返回其路径的 inode
+    fn insert_object(&mut self, object_key: String) -> u64 {
+        let path = format!("/{}", object_key.trim_end_matches('/'));
+        self.tombstones.remove(&object_key);
+        if !self.contains_object(&object_key) {
+            self.invalidate_ancestors(&path);
+            let key = self.strings.intern(&object_key);
+            self.object_list.push(key);
+        }
+
+        let mut current = Path::new(&path);
+        while current != Path::new("/") {
+            let current_str = current.to_string_lossy();
+            self.recent_misses.remove(current_str.as_ref());
+            if let Some(filter) = &mut self.known_paths {
+                filter.insert(&current_str);
+            }
+            self.get_or_create_inode(&current_str);
+            current = current.parent().unwrap_or(Path::new("/"));
+        }
+        self.get_or_create_inode(&path)
+    }
+
+    /// 路径是否刚向 COS 确认过不存在
+    fn recently_missed(&mut self, path: &str) -> bool {
+        match self.recent_misses.get(path) {
+            Some(at) if at.elapsed() < MISS_CACHE_TTL => true,
+            Some(_) => {
+                self.recent_misses.remove(path);
+                false
+            }
+            None => false,
         }
     }
 
-    /// 判断路径是否是目录
-    fn is_directory(&self, path: &str) -> bool {
+    /// 判断路径是否是目录（结果会被缓存，直到该路径所在目录失效）
+    fn is_directory(&mut self, path: &str) -> bool {
         if path == "/" {
             return true;
         }
 
+        if let Some(&is_dir) = self.is_dir_cache.get(path) {
+            return is_dir;
+        }
+
         // 检查是否有任何对象以该路径为前缀（后面跟着'/'）
-        let path_with_slash = if path == "/" {
-            "/".to_string()
-        } else {
-            format!("{}/", path.trim_start_matches('/'))
-        };
+        let path_with_slash = format!("{}/", path.trim_start_matches('/'));
+        let is_dir = self
+            .object_list
+            .iter()
+            .any(|obj| obj.starts_with(&path_with_slash));
 
-        self.object_list.iter().any(|obj| {
-            let obj_path = format!("/{}", obj);
-            obj_path.starts_with(&path_with_slash) && obj_path != path
-        })
+        let path = self.strings.intern(path);
+        self.is_dir_cache.insert(path, is_dir);
+        is_dir
+    }
+
+    /// 路径是归档对象或位于归档对象之中时返回 (归档对象键, 归档内路径)
+    fn split_archive_path(&self, path: &str) -> Option<(String, String)> {
+        let key = path.trim_start_matches('/');
+        let ends = key
+            .match_indices('/')
+            .map(|(i, _)| i)
+            .chain(Some(key.len()));
+        for end in ends {
+            let candidate = &key[..end];
+            if ArchiveKind::from_key(candidate).is_some() && self.contains_object(candidate) {
+                let inner_path = key[end..].trim_start_matches('/');
+                return Some((candidate.to_string(), inner_path.to_string()));
+            }
+        }
+        None
     }
 
-    /// 列出目录内容
-    fn list_directory(&self, path: &str) -> Vec<DirEntry> {
+    /// 列出目录内容，条目名字取自驻留池
+    fn list_directory(&mut self, path: &str) -> Vec<DirEntry> {
         let mut entries = Vec::new();
-        let path_prefix = path.trim_start_matches('/');
+        let mut seen_names = HashSet::new();
 
-        if path == "/" {
-            // 根目录，列出第一级目录和文件
-            let mut seen_names = std::collections::HashSet::new();
-
-            for object_key in &self.object_list {
-                let parts: Vec<&str> = object_key.split('/').collect();
-                if parts.len() >= 1 {
-                    let name = parts[0];
-                    if !seen_names.contains(name) {
-                        seen_names.insert(name);
-
-                        let full_path = format!("/{}", name);
-                        let ino = *self.path_to_inode.get(&full_path).unwrap();
-
-                        if parts.len() > 1 {
-                            // 这是一个目录
-                            entries.push(DirEntry {
-                                name: name.to_string(),
-                                ino,
-                                file_type: FileType::Directory,
-                            });
-                        } else {
-                            // 这是一个文件
-                            entries.push(DirEntry {
-                                name: name.to_string(),
-                                ino,
-                                file_type: FileType::RegularFile,
-                            });
-                        }
-                    }
-                }
-            }
-        } else {
-            // 子目录
-            let mut seen_names = std::collections::HashSet::new();
-
-            for object_key in &self.object_list {
-                if object_key.starts_with(path_prefix) {
-                    let relative_path = &object_key[path_prefix.len()..];
-                    let relative_path = relative_path.trim_start_matches('/');
-
-                    if let Some(slash_pos) = relative_path.find('/') {
-                        // 这是一个子目录
-                        let dir_name = &relative_path[..slash_pos];
-                        if !seen_names.contains(dir_name) {
-                            seen_names.insert(dir_name);
-
-                            let full_path = format!("{}/{}", path, dir_name);
-                            let ino = *self.path_to_inode.get(&full_path).unwrap();
-
-                            entries.push(DirEntry {
-                                name: dir_name.to_string(),
-                                ino,
-                                file_type: FileType::Directory,
-                            });
-                        }
-                    } else if !relative_path.is_empty() {
-                        // 这是一个文件
-                        let full_path = format!("/{}", object_key);
-                        let ino = *self.path_to_inode.get(&full_path).unwrap();
-
-                        entries.push(DirEntry {
-                            name: relative_path.to_string(),
-                            ino,
-                            file_type: FileType::RegularFile,
-                        });
-                    }
-                }
+        let dir_prefix = dir_prefix(path);
+
+        for object_key in &self.object_list {
+            let Some(relative_path) = object_key.strip_prefix(dir_prefix.as_str()) else {
+                continue;
+            };
+
+            let (name, file_type) = match relative_path.find('/') {
+                // 这是一个子目录
+                Some(slash_pos) => (&relative_path[..slash_pos], FileType::Directory),
+                // 这是一个文件
+                None => (relative_path, FileType::RegularFile),
+            };
+
+            if name.is_empty() || !seen_names.insert(name) {
+                continue;
             }
+
+            entries.push(DirEntry {
+                name: self.strings.intern(name),
+                file_type,
+            });
         }
 
         entries.sort_by(|a, b| a.name.cmp(&b.name));
@@ -348,310 +850,4522 @@ impl CosFilesystem {
     }
 }
 
-impl Filesystem for CosFilesystem {
-    fn init(&mut self, _req: &Request<'_>, _config: &mut KernelConfig) -> Result<(), i32> {
-        info!("Initializing COS filesystem");
+/// 各个 FUSE 操作任务共享的文件系统状态
+struct Inner<S> {
+    /// 对象存储后端
+    store: Arc<S>,
 
-        // 在初始化时刷新对象列表
-        let rt = Arc::clone(&self.runtime);
+    /// HEAD 请求解析器（合并并发请求、限制并发数）
+    head_resolver: HeadResolver<S>,
 
-        if let Err(e) = rt.block_on(self.refresh_object_list_async()) {
-            error!("Failed to initialize object list: {}", e);
-            return Err(EIO);
-        }
+    /// 缓存系统
+    cache: Arc<Cache>,
 
-        info!("COS filesystem initialized successfully");
-        Ok(())
-    }
+    /// 过期缓存的后台重新验证
+    revalidator: Revalidator<S>,
 
-    fn destroy(&mut self) {
-        info!("Destroying COS filesystem");
+    /// 合并并发下载的内容下载器
+    downloader: Arc<Downloader<S>>,
 
-        // 清理缓存
-        if let Err(e) = self.cache.clear() {
-            warn!("Failed to clear cache: {}", e);
-        }
+    /// 写入暂存区，与读缓存分开存放和计算配额
+    spool: Arc<Spool>,
 
-        info!("COS filesystem destroyed");
-    }
+    /// inode 映射、对象列表与目录缓存（不跨 await 持有）
+    namespace: Mutex<Namespace>,
 
-    fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
-        info!("Lookup: parent={}, name={}", parent, name.display());
+    /// 目录缓存有效期
+    dir_cache_ttl: Duration,
 
-        let name_str = match name.to_str() {
-            Some(s) => s,
-            None => {
-                reply.error(ENOENT);
-                return;
-            }
-        };
+    /// 挂载时后台预取的进度
+    prefetch_stats: Arc<PrefetchStats>,
 
-        let parent_path = match self.get_path(parent) {
-            Some(p) => p.clone(),
-            None => {
-                reply.error(ENOENT);
-                return;
-            }
-        };
+    /// 本地记录的 atime
+    access_times: AccessTimes,
 
-        let target_path = if parent_path == "/" {
-            format!("/{}", name_str)
-        } else {
-            format!("{}/{}", parent_path, name_str)
-        };
+    /// 按前缀覆盖的属主与权限
+    permissions: PermissionOverrides,
 
-        debug!(
-            "Lookup: parent={}, name={}, target_path={}",
-            parent, name_str, target_path
-        );
+    /// 请求者 uid/gid 映射与白名单
+    id_map: IdMap,
 
-        // 检查是否是目录
-        if self.is_directory(&target_path) {
-            let ino = self.get_or_create_inode(&target_path);
-            let attr = self.create_dir_attr(ino);
-            reply.entry(&Duration::from_secs(1), &attr, 0);
-            return;
-        }
+    /// 预签名 URL 扩展属性的有效期
+    presign_expires: Duration,
 
-        // 检查是否是文件
-        let object_key = target_path.trim_start_matches('/');
-        if self.object_list.contains(&object_key.to_string()) {
-            let ino = self.get_or_create_inode(&target_path);
+    /// 按对象 ACL 推导权限位（未开启时为 None）
+    acl: Option<AclPermissions<S>>,
 
-            let rt = Arc::clone(&self.runtime);
+    /// 挂载前探测到的访问能力（没有探测时为空）
+    capabilities: OnceLock<Capabilities>,
 
-            match rt.block_on(self.get_object_metadata(object_key)) {
-                Ok(meta) => {
-                    let attr = self.meta_to_attr(&meta, ino);
-                    reply.entry(&Duration::from_secs(1), &attr, 0);
-                }
-                Err(e) => {
-                    error!("Failed to get metadata for {}: {}", object_key, e);
-                    reply.error(EIO);
-                }
-            }
-        } else {
-            reply.error(ENOENT);
-        }
-    }
+    /// 运行中桶被删除或权限被收回时进入错误状态
+    health: BucketHealth<S>,
 
-    fn getattr(&mut self, _req: &Request<'_>, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
-        info!("Getattr: ino={}", ino);
+    /// 尚未在 COS 上完成的上传、删除与重命名，fsyncdir 等待它们完成
+    mutations: Arc<PendingMutations>,
 
-        let path = match self.get_path(ino) {
-            Some(p) => p,
-            None => {
-                reply.error(ENOENT);
-                return;
-            }
-        };
+    /// 以写方式打开的文件句柄：fh -> (对象键, 暂存写入)；打开期间文件被删除后键为 None
+    write_handles: Mutex<HashMap<u64, (Option<String>, SharedWriteHandle)>>,
 
-        debug!("Getattr: ino={}, path={}", ino, path);
+    /// 关闭时上传失败的暂存：对象键 -> (最后关闭它的请求者, 暂存写入)，后台重试直到上传成功
+    failed_uploads: Mutex<HashMap<String, (Requester, SharedWriteHandle)>>,
 
-        if self.is_directory(path) {
-            let attr = self.create_dir_attr(ino);
-            reply.attr(&Duration::from_secs(1), &attr);
-        } else {
-            let object_key = path.trim_start_matches('/');
+    /// 单次上传的最长时间（`--op-timeout`）；超时放弃本次上传，改动留在暂存中重试
+    upload_timeout: Option<Duration>,
 
-            let rt = Arc::clone(&self.runtime);
+    /// 以写方式打开着的对象，上传之前 lookup/getattr 报告暂存的大小与 mtime
+    staged: StagedFiles,
 
-            match rt.block_on(self.get_object_metadata(object_key)) {
-                Ok(meta) => {
-                    let attr = self.meta_to_attr(&meta, ino);
-                    reply.attr(&Duration::from_secs(1), &attr);
-                }
-                Err(e) => {
-                    error!("Failed to get metadata for {}: {}", object_key, e);
-                    reply.error(EIO);
-                }
-            }
-        }
-    }
+    /// 挂载前探测确认没有写权限
+    write_denied: AtomicBool,
 
-    fn readdir(
-        &mut self,
-        _req: &Request<'_>,
-        ino: u64,
-        _fh: u64,
+    /// lookup 未命中列表时先向 COS 确认再答复 ENOENT
+    revalidate_misses: bool,
+
+    /// mtime 以 `x-cos-meta-mtime` 为准，列表结果不写入元数据缓存
+    hires_mtime: bool,
+
+    /// 文件的属主与权限位取自 s3fs 记录的 `x-cos-meta-uid/gid/mode`，列表结果不写入元数据缓存
+    s3fs_compat: bool,
+
+    /// 上传时附加的头
+    upload: UploadOptions,
+
+    /// `.zip`/`.tar` 对象显示为目录时的归档索引（未开启时为 None）
+    archives: Option<Archives>,
+
+    /// 图片的 `@w=200` 等虚拟文件经数据万象处理后的内容（未开启时为 None）
+    images: Option<ImageVariants>,
+
+    /// 推送到 StatsD 的计数器与计时器
+    metrics: Arc<Metrics>,
+
+    /// 最近的对象操作失败，`user.cosfs.last-error` 使用
+    last_errors: LastErrors,
+
+    /// `security.*` 等扩展属性的答复方式
+    system_xattrs: SystemXattrs,
+
+    /// 按请求者 uid 的用量统计与限速
+    accounting: UidAccounting,
+
+    /// 内存中状态的上限
+    memory: MemoryBudget,
+
+    /// 读请求答复使用的缓冲区池
+    buffers: Arc<BufferPool>,
+
+    /// 快照挂载的对象清单（正常挂载时为 None）
+    snapshot: Option<Arc<Snapshot>>,
+
+    /// 丢弃所有缓存后仍超出内存上限（只在进入该状态时告警一次）
+    memory_exhausted: AtomicBool,
+
+    /// 控制目录事件文件的内容
+    events: Arc<EventLog>,
+
+    /// 库使用者注册的操作钩子
+    hooks: Hooks,
+}
+
+impl<S: ObjectStore> Inner<S> {
+    fn namespace(&self) -> MutexGuard<'_, Namespace> {
+        self.namespace.lock().unwrap()
+    }
+
+    /// 从 COS 刷新对象列表
+    async fn refresh_object_list(&self) -> Result<()> {
+        let listed_at = Instant::now();
+        let listing = match &self.snapshot {
+            Some(snapshot) => {
+                info!("Loading object list from the snapshot manifest");
+                snapshot.objects().cloned().collect()
+            }
+            None => {
+                info!("Refreshing object list from COS");
+                self.store.list_objects().await?
+            }
+        };
+
+        // 列表已经带有 size/mtime/etag，直接写入元数据缓存，lookup/getattr 无需再发 HEAD
+        let mut new_list = Vec::with_capacity(listing.len());
+        for meta in listing {
+            // 回收站不出现在挂载中
+            if self.in_trash(&meta.key) {
+                continue;
+            }
+            // 其他工具的 `dir_$folder$` 目录标记按 `dir/` 处理
+            let key = s3fs_compat::normalize_key(&meta.key);
+            if key != meta.key {
+                new_list.push(key.into_owned());
+                continue;
+            }
+            new_list.push(meta.key.clone());
+            if self.listing_is_complete() {
+                self.cache.set_metadata(meta.key.clone(), meta);
+            }
+        }
+
+        // 还没有上传完成的新文件不在列表中，继续保留
+        for key in self.staged.keys() {
+            if !new_list.contains(&key) {
+                new_list.push(key);
+            }
+        }
+
+        let count = new_list.len();
+        self.namespace().replace_object_list(new_list, listed_at);
+        self.enforce_memory_budget();
+        self.events.publish(Event::Refreshed { objects: count });
+
+        info!("Loaded {} objects from COS", count);
+        Ok(())
+    }
+
+    /// 周期刷新：只重新列出访问过的目录（每个一层）并合并变化，挂载大桶中的一个子目录时
+    /// 不必每次重新列出整个桶；访问过的目录多到逐个列出更贵时改为完整列表
+    async fn refresh_listed_dirs(&self) -> Result<()> {
+        let (dirs, objects) = {
+            let namespace = self.namespace();
+            (namespace.listed_dirs(), namespace.object_list.len())
+        };
+        if dirs.is_empty() {
+            return Ok(());
+        }
+        if dirs.len() * LIST_PAGE_KEYS >= objects {
+            return self.refresh_object_list().await;
+        }
+
+        debug!("Refreshing {} listed directories", dirs.len());
+        for dir in &dirs {
+            if let Err(e) = self.refresh_dir(dir).await {
+                warn!("Failed to refresh directory {}: {:#}", dir, e);
+            }
+        }
+        self.enforce_memory_budget();
+        Ok(())
+    }
+
+    /// 重新列出目录 `dir` 的一层并合并进对象列表
+    async fn refresh_dir(&self, dir: &str) -> Result<()> {
+        // 快照挂载的内容不变
+        if self.snapshot.is_some() {
+            return Ok(());
+        }
+        let listed_at = Instant::now();
+        let listing = self.store.list_dir(&dir_prefix(dir)).await?;
+        self.metrics.incr("refresh.dirs", 1);
+
+        let mut objects = Vec::with_capacity(listing.objects.len());
+        let mut prefixes: Vec<String> = listing
+            .prefixes
+            .into_iter()
+            .filter(|prefix| !self.in_trash(prefix))
+            .collect();
+        for meta in listing.objects {
+            if self.in_trash(&meta.key) {
+                continue;
+            }
+            // `sub_$folder$` 标记的是这一层的子目录
+            let key = s3fs_compat::normalize_key(&meta.key);
+            if key != meta.key {
+                prefixes.push(key.into_owned());
+                continue;
+            }
+            objects.push(meta.key.clone());
+            if self.listing_is_complete() {
+                self.cache.set_metadata(meta.key.clone(), meta);
+            }
+        }
+        // 不在这一层的键由合并时过滤
+        objects.extend(self.staged.keys());
+        self.namespace()
+            .merge_dir_listing(dir, objects, prefixes, listed_at);
+        Ok(())
+    }
+
+    /// 列表结果是否足以填充元数据缓存：列表不带自定义元数据，
+    /// 需要其中的 mtime 或属主时由 HEAD 取得
+    fn listing_is_complete(&self) -> bool {
+        !self.hires_mtime && !self.s3fs_compat
+    }
+
+    /// 内存中各部分状态的占用估计
+    fn memory_usage(&self) -> MemoryUsage {
+        let namespace = self.namespace().memory_usage();
+        let content = self.archives.as_ref().map_or(0, Archives::memory_usage)
+            + self.images.as_ref().map_or(0, ImageVariants::memory_usage);
+        MemoryUsage {
+            metadata: self.cache.metadata_memory(),
+            content,
+            ..namespace
+        }
+    }
+
+    /// 超出内存上限时依次丢弃目录缓存、内存中的内容和最久未用的元数据，直到回到上限以内
+    fn enforce_memory_budget(&self) {
+        let usage = self.memory_usage();
+        let Some(mut excess) = self.memory.excess(&usage) else {
+            self.memory_exhausted.store(false, Ordering::Relaxed);
+            return;
+        };
+        self.metrics.incr("memory.trims", 1);
+        excess = excess.saturating_sub(self.namespace().trim_caches());
+        if excess > 0 && usage.content > 0 {
+            if let Some(archives) = &self.archives {
+                archives.clear_inflated();
+            }
+            if let Some(images) = &self.images {
+                images.clear();
+            }
+            excess = excess.saturating_sub(usage.content);
+        }
+        if excess > 0 {
+            self.cache.trim_metadata(excess);
+        }
+
+        if !self.memory.is_exhausted(&usage) {
+            debug!(
+                "Trimmed in-memory caches from {} bytes to stay under the memory limit",
+                usage.total()
+            );
+        } else if !self.memory_exhausted.swap(true, Ordering::Relaxed) {
+            warn!(
+                "Object listing and inode maps alone use {} bytes, over the memory limit of {} bytes; \
+                 mount a narrower prefix or raise --memory-limit",
+                usage.strings + usage.listing + usage.inodes,
+                self.memory.limit().unwrap_or_default()
+            );
+        }
+    }
+
+    /// 对象是否位于回收站中
+    fn in_trash(&self, key: &str) -> bool {
+        self.upload
+            .trash
+            .as_ref()
+            .is_some_and(|trash| trash.contains(key))
+    }
+
+    /// 获取对象的元数据
+    async fn get_object_metadata(&self, key: &str) -> Result<ObjectMeta> {
+        // 快照挂载的元数据固定为清单中的值，不向 COS 确认
+        if let Some(snapshot) = &self.snapshot {
+            return snapshot
+                .get(key)
+                .cloned()
+                .ok_or_else(|| anyhow!("{} is not in the snapshot manifest", key));
+        }
+
+        // 先检查缓存
+        match self.cache.lookup_metadata(key) {
+            MetadataLookup::Fresh(meta) => {
+                debug!("Metadata cache hit for key: {}", key);
+                self.metrics.incr("cache.metadata.hits", 1);
+                return Ok(meta);
+            }
+            MetadataLookup::Stale(meta) => {
+                // 先返回过期结果，后台重新验证
+                debug!("Serving stale metadata for key: {}", key);
+                self.metrics.incr("cache.metadata.stale", 1);
+                self.revalidator.schedule(meta.clone());
+                return Ok(meta);
+            }
+            MetadataLookup::Miss => {}
+        }
+
+        // 只有列表中没有（或已被 LRU 淘汰）的对象才会走到 HEAD
+        debug!("Metadata cache miss for key: {}, fetching from COS", key);
+        self.metrics.incr("cache.metadata.misses", 1);
+        let started = Instant::now();
+        let meta = self.head_resolver.resolve(key).await?;
+        self.metrics.time("cos.head", started.elapsed());
+
+        // 缓存元数据
+        self.cache.set_metadata(key.to_string(), meta.clone());
+
+        Ok(meta)
+    }
+
+    /// 对象操作失败时答复的错误码。桶正常而对象返回 404，说明对象已被其他客户端删除：
+    /// 移出列表并记下墓碑，答复 ENOENT 而不是 EIO
+    async fn object_errno(&self, key: &str, error: &anyhow::Error) -> i32 {
+        self.last_errors.record(key, error);
+        let errno = self.health.errno(error).await;
+        if errno == EIO && error_status(error) == Some(reqwest::StatusCode::NOT_FOUND) {
+            info!("{} was deleted from COS, removing it from the listing", key);
+            self.namespace().remove_object(key);
+            self.cache.remove_metadata(key);
+            return ENOENT;
+        }
+        errno
+    }
+
+    /// lookup 未命中列表时向 COS 确认：先 HEAD 同名对象，再列出 `key/` 之下的对象判断是否是目录。
+    /// 找到的对象加入列表，确认不存在的路径在 [`MISS_CACHE_TTL`] 内不再重复请求
+    async fn revalidate_miss(&self, path: &str) -> Result<Option<FileAttr>> {
+        let key = path.trim_start_matches('/');
+        match self.head_resolver.resolve(key).await {
+            Ok(meta) => {
+                debug!("Lookup miss for {} found on COS", path);
+                self.cache.set_metadata(key.to_string(), meta);
+                let ino = self.namespace().insert_object(key.to_string());
+                return self.object_attr(key, ino).await.map(Some);
+            }
+            Err(e) if error_status(&e) == Some(reqwest::StatusCode::NOT_FOUND) => {}
+            Err(e) => return Err(e),
+        }
+
+        let dir_prefix = format!("{}/", key);
+        if let Some(found) = self.store.list_first_key_under(&dir_prefix).await? {
+            debug!("Lookup miss for {} found on COS as a directory", path);
+            let mut namespace = self.namespace();
+            namespace.insert_object(found);
+            let ino = namespace.get_or_create_inode(path);
+            return Ok(Some(self.create_dir_attr(ino, path)));
+        }
+
+        self.namespace()
+            .recent_misses
+            .insert(path.to_string(), Instant::now());
+        Ok(None)
+    }
+
+    /// `user.cosfs.info` 的值：重新 HEAD 一次，请求 ID 等信息总是最新的
+    async fn object_info(&self, key: &str) -> Result<String> {
+        let meta = self.head_resolver.resolve(key).await?;
+        let info = ObjectInfo {
+            key,
+            size: meta.size,
+            etag: &meta.etag,
+            mtime: format_mtime_meta(meta.last_modified),
+            server_side_encryption: meta.server_side_encryption.as_deref(),
+            details: &meta.details,
+            cache: self
+                .cache
+                .content_state(key, &meta.etag, meta.size)
+                .as_str(),
+        };
+        Ok(serde_json::to_string(&info)?)
+    }
+
+    /// 修改对象的用户自定义元数据：`update` 在对象当前的元数据上修改，返回错误码时不修改。
+    /// COS 只能通过复制自身替换元数据，ETag 不符（期间被其他客户端修改）时失败
+    async fn update_user_meta(
+        &self,
+        requester: Requester,
+        key: &str,
+        update: impl FnOnce(&mut BTreeMap<String, String>) -> Result<(), i32>,
+    ) -> Result<(), i32> {
+        let meta = match self.head_resolver.resolve(key).await {
+            Ok(meta) => meta,
+            Err(e) => {
+                error!("Failed to get metadata for {}: {:#}", key, e);
+                return Err(self.object_errno(key, &e).await);
+            }
+        };
+        if meta.size > MAX_COPY_SIZE {
+            return Err(EFBIG);
+        }
+        let mut user_meta = meta.details.user_meta.clone();
+        update(&mut user_meta)?;
+        if let Err(e) = self
+            .store
+            .replace_metadata(key, &meta.etag, &user_meta)
+            .await
+        {
+            error!("Failed to update metadata of {}: {:#}", key, e);
+            return Err(self.object_errno(key, &e).await);
+        }
+        self.cache.remove_metadata(key);
+        self.hooks.on_write_complete(&requester, key, meta.size);
+        Ok(())
+    }
+
+    /// 句柄 `fh` 的暂存写入，只读打开的句柄没有
+    fn write_handle(&self, fh: u64) -> Option<SharedWriteHandle> {
+        self.write_handles
+            .lock()
+            .unwrap()
+            .get(&fh)
+            .map(|(_, handle)| Arc::clone(handle))
+    }
+
+    /// 对象 `key` 已有的暂存写入（包括关闭时上传失败的），再次以写方式打开或 setattr 时共用
+    fn write_handle_for(&self, key: &str) -> Option<SharedWriteHandle> {
+        self.open_write_handle_for(key).or_else(|| {
+            self.failed_uploads
+                .lock()
+                .unwrap()
+                .get(key)
+                .map(|(_, handle)| Arc::clone(handle))
+        })
+    }
+
+    /// 对象 `key` 还打开着的句柄的暂存写入
+    fn open_write_handle_for(&self, key: &str) -> Option<SharedWriteHandle> {
+        self.write_handles
+            .lock()
+            .unwrap()
+            .values()
+            .find(|(k, _)| k.as_deref() == Some(key))
+            .map(|(_, handle)| Arc::clone(handle))
+    }
+
+    /// 登记以写方式打开的句柄，`meta` 为暂存的当前元数据。重新打开上传失败的暂存时
+    /// 改由新句柄持有，关闭时再上传
+    fn register_write_handle(&self, fh: u64, handle: SharedWriteHandle, meta: ObjectMeta) {
+        let key = meta.key.clone();
+        self.staged.open(meta);
+        if self.failed_uploads.lock().unwrap().remove(&key).is_some() {
+            self.staged.close(&key);
+        }
+        self.write_handles
+            .lock()
+            .unwrap()
+            .insert(fh, (Some(key), handle));
+    }
+
+    /// 以写方式打开已有对象。对象已经以写方式打开时共用其暂存，否则 HEAD 取得最新的元数据
+    /// （带 CRC64，内容没有改动时可以跳过上传）
+    async fn open_for_write(&self, fh: u64, key: &str, truncate: bool) -> Result<()> {
+        let handle = match self.write_handle_for(key) {
+            Some(handle) => handle,
+            None => {
+                let meta = self.head_resolver.resolve(key).await?;
+                let file = self.spool.create()?;
+                Arc::new(tokio::sync::Mutex::new(WriteHandle::open(
+                    file, meta, truncate,
+                )?))
+            }
+        };
+        let meta = {
+            let mut staged = handle.lock().await;
+            if truncate {
+                staged.truncate(0)?;
+            }
+            staged.meta()
+        };
+        self.register_write_handle(fh, handle, meta);
+        Ok(())
+    }
+
+    /// 上传暂存的写入，没有改动时什么也不做。失败时发布事件并记录错误，暂存保留到句柄关闭，
+    /// 之后的 flush 或关闭会再次上传
+    async fn upload(&self, requester: &Requester, handle: &SharedWriteHandle) -> Result<(), i32> {
+        let mut handle = handle.lock().await;
+        if !handle.is_dirty() {
+            return Ok(());
+        }
+        let key = handle.key().to_string();
+        let _mutation = self.mutations.begin(&format!("/{}", key));
+        let started = Instant::now();
+        let upload = handle.upload(self.store.as_ref(), &self.upload);
+        let result = match self.upload_timeout {
+            // 卡住的请求不会无限期占住句柄：放弃后改动仍在暂存中，由下一次 flush、关闭或后台重试上传
+            Some(limit) => tokio::time::timeout(limit, upload)
+                .await
+                .unwrap_or_else(|_| Err(anyhow!("Upload timed out after {:?}", limit))),
+            None => upload.await,
+        };
+        match result {
+            Ok(Some(meta)) => {
+                self.metrics.time("cos.upload", started.elapsed());
+                self.metrics.incr("upload.bytes", meta.size);
+                info!("Uploaded {} ({} bytes)", key, meta.size);
+                self.cache.remove_content(&key);
+                self.cache.set_metadata(key.clone(), meta.clone());
+                self.staged.update(meta.clone());
+                self.namespace().insert_object(key.clone());
+                self.hooks.on_write_complete(requester, &key, meta.size);
+                Ok(())
+            }
+            Ok(None) => Ok(()),
+            Err(e) => {
+                error!("Failed to upload {}: {:#}", key, e);
+                self.events.publish(Event::UploadFailed {
+                    key: key.clone(),
+                    error: format!("{:#}", e),
+                });
+                self.last_errors.record(&key, &e);
+                match io_errno(&e) {
+                    Some(errno) => Err(errno),
+                    None => Err(self.health.errno(&e).await),
+                }
+            }
+        }
+    }
+
+    /// 关闭以写方式打开的句柄：还有没上传的改动时上传（flush 失败后再试一次）。
+    /// 最后一个句柄关闭后暂存随之删除，从未上传成功的新文件从列表中移除。
+    /// 最后一个句柄上传失败时保留暂存，由后台重试，重新打开时继续使用
+    async fn release_write_handle(
+        &self,
+        requester: &Requester,
+        key: &str,
+        handle: SharedWriteHandle,
+    ) -> Result<(), i32> {
+        let result = self.upload(requester, &handle).await;
+        if result.is_err()
+            && handle.lock().await.is_dirty()
+            && self.open_write_handle_for(key).is_none()
+        {
+            warn!("Keeping staged changes of {} to retry the upload", key);
+            self.failed_uploads
+                .lock()
+                .unwrap()
+                .insert(key.to_string(), (*requester, handle));
+            return result;
+        }
+        self.staged.close(key);
+        if self.staged.get(key).is_none() && handle.lock().await.is_new() {
+            warn!("{} was never uploaded, removing it from the listing", key);
+            self.namespace().remove_object(key);
+            self.cache.remove_metadata(key);
+        }
+        result
+    }
+
+    /// 重试关闭时上传失败的文件，上传成功后丢弃暂存
+    async fn retry_failed_uploads(&self) {
+        let failed: Vec<(String, Requester, SharedWriteHandle)> = self
+            .failed_uploads
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, (requester, handle))| (key.clone(), *requester, Arc::clone(handle)))
+            .collect();
+        for (key, requester, handle) in failed {
+            if self.upload(&requester, &handle).await.is_err() {
+                continue;
+            }
+            let mut failed_uploads = self.failed_uploads.lock().unwrap();
+            // 其间被重新打开的暂存由新句柄持有
+            if failed_uploads
+                .get(&key)
+                .is_some_and(|(_, kept)| Arc::ptr_eq(kept, &handle))
+            {
+                failed_uploads.remove(&key);
+                drop(failed_uploads);
+                info!("Uploaded {} after an earlier failure", key);
+                self.staged.close(&key);
+            }
+        }
+    }
+
+    /// setattr 修改大小或 mtime。以写方式打开着的对象改在暂存中，随下一次上传生效；
+    /// 没有打开的对象截断时立即上传（没有改动的分块在服务端复制），只改 mtime 时
+    /// 开启了 `--hires-mtime` 才改写元数据，否则 COS 无法记录而忽略
+    async fn set_attr(
+        &self,
+        requester: &Requester,
+        key: &str,
+        handle: Option<SharedWriteHandle>,
+        size: Option<u64>,
+        mtime: Option<SystemTime>,
+    ) -> Result<(), i32> {
+        if let Some(handle) = handle {
+            let meta = {
+                let mut handle = handle.lock().await;
+                if let Some(size) = size {
+                    handle.truncate(size).map_err(|e| {
+                        error!("Failed to truncate {}: {}", key, e);
+                        e.raw_os_error().unwrap_or(EIO)
+                    })?;
+                }
+                if let Some(mtime) = mtime {
+                    handle.set_mtime(mtime);
+                }
+                (!handle.is_unlinked()).then(|| handle.meta())
+            };
+            if let Some(meta) = meta {
+                self.staged.update(meta);
+            }
+            return Ok(());
+        }
+
+        if let Some(size) = size {
+            let staged = async {
+                let meta = self.head_resolver.resolve(key).await?;
+                let mut staged = WriteHandle::open(self.spool.create()?, meta, size == 0)?;
+                staged.truncate(size)?;
+                if let Some(mtime) = mtime {
+                    staged.set_mtime(mtime);
+                }
+                Ok::<_, anyhow::Error>(Arc::new(tokio::sync::Mutex::new(staged)))
+            };
+            return match staged.await {
+                Ok(handle) => self.upload(requester, &handle).await,
+                Err(e) => {
+                    error!("Failed to truncate {}: {:#}", key, e);
+                    match io_errno(&e) {
+                        Some(errno) => Err(errno),
+                        None => Err(self.object_errno(key, &e).await),
+                    }
+                }
+            };
+        }
+
+        match mtime {
+            Some(mtime) if self.upload.store_mtime => {
+                let name = MTIME_META_HEADER.trim_start_matches(USER_META_PREFIX);
+                self.update_user_meta(*requester, key, |user_meta| {
+                    user_meta.insert(name.to_string(), format_mtime_meta(mtime));
+                    Ok(())
+                })
+                .await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// mkdir 的前置检查：同名的文件或目录已存在时答复 EEXIST
+    fn check_mkdir(&self, path: &str) -> Result<(), i32> {
+        let mut namespace = self.namespace();
+        let key = path.trim_start_matches('/');
+        if namespace.contains_object(key) || self.is_directory(&mut namespace, path) {
+            return Err(EEXIST);
+        }
+        Ok(())
+    }
+
+    /// rmdir 的前置检查：`path` 不是目录时答复 ENOTDIR 或 ENOENT，是否为空由 `remove_dir` 确认
+    fn check_rmdir(&self, path: &str) -> Result<(), i32> {
+        let mut namespace = self.namespace();
+        if self.is_directory(&mut namespace, path) {
+            return Ok(());
+        }
+        let key = path.trim_start_matches('/');
+        Err(if namespace.contains_object(key) {
+            ENOTDIR
+        } else {
+            ENOENT
+        })
+    }
+
+    /// unlink 的前置检查：目录答复 EISDIR，不存在的文件答复 ENOENT
+    fn check_unlink(&self, path: &str) -> Result<(), i32> {
+        let mut namespace = self.namespace();
+        if self.is_directory(&mut namespace, path) {
+            return Err(EISDIR);
+        }
+        if !namespace.contains_object(path.trim_start_matches('/')) {
+            return Err(ENOENT);
+        }
+        Ok(())
+    }
+
+    /// rename 的前置检查，返回 `from` 是否为目录。`from` 与 `to` 相同时只确认 `from` 存在，
+    /// 调用方随后直接答复成功
+    fn check_rename(&self, from: &str, to: &str, flags: u32) -> Result<bool, i32> {
+        // 两个对象无法原子地交换
+        if flags & RENAME_EXCHANGE != 0 {
+            return Err(EINVAL);
+        }
+        let mut namespace = self.namespace();
+        let is_dir = self.is_directory(&mut namespace, from);
+        if !is_dir && !namespace.contains_object(from.trim_start_matches('/')) {
+            return Err(ENOENT);
+        }
+        if from == to {
+            return Ok(is_dir);
+        }
+        let to_is_dir = self.is_directory(&mut namespace, to);
+        let to_exists = to_is_dir || namespace.contains_object(to.trim_start_matches('/'));
+        if to_exists && flags & RENAME_NOREPLACE != 0 {
+            return Err(EEXIST);
+        }
+        if is_dir && to.starts_with(&format!("{}/", from)) {
+            // 目录不能移到自己之下
+            return Err(EINVAL);
+        }
+        if is_dir && to_exists && !to_is_dir {
+            return Err(ENOTDIR);
+        }
+        if !is_dir && to_is_dir {
+            return Err(EISDIR);
+        }
+        if to_is_dir && !namespace.list_directory(to).is_empty() {
+            return Err(ENOTEMPTY);
+        }
+        Ok(is_dir)
+    }
+
+    /// 创建目录 `path` 的标记对象 `dir/`（与 s3fs、ossfs 的约定相同），返回目录的 inode
+    async fn make_dir(&self, path: &str, owner: Ownership) -> Result<u64, i32> {
+        let key = dir_prefix(path);
+        let _mutation = self.mutations.begin(path);
+        let mut headers = self.upload.headers_for(&key, &[]);
+        self.upload
+            .insert_ownership(&mut headers, owner.uid, owner.gid, owner.perm, true);
+        if let Err(e) = self.store.put_object(&key, Bytes::new(), headers).await {
+            error!("Failed to create directory marker {}: {:#}", key, e);
+            self.last_errors.record(&key, &e);
+            return Err(self.health.errno(&e).await);
+        }
+        info!("Created directory {}", path);
+        Ok(self.namespace().insert_object(key))
+    }
+
+    /// 删除空目录 `path`：向 COS 确认其下没有其他对象后删除目录标记，其他工具的
+    /// `dir_$folder$` 标记一并删除，否则目录会在下一次列表后重新出现
+    async fn remove_dir(&self, path: &str) -> Result<(), i32> {
+        let prefix = dir_prefix(path);
+        // 还没有上传的新文件不在 COS 上
+        if self
+            .staged
+            .keys()
+            .iter()
+            .any(|key| key.starts_with(&prefix))
+        {
+            return Err(ENOTEMPTY);
+        }
+        let _mutation = self.mutations.begin(path);
+        let result = async {
+            let page = self.store.list_page(&prefix, true, None).await?;
+            let empty = page.prefixes.is_empty()
+                && page
+                    .objects
+                    .iter()
+                    .all(|meta| meta.key == prefix || self.in_trash(&meta.key));
+            if !empty {
+                return Ok(false);
+            }
+            self.store.delete_object(&prefix).await?;
+            let folder_marker = format!(
+                "{}{}",
+                prefix.trim_end_matches('/'),
+                s3fs_compat::FOLDER_MARKER_SUFFIX
+            );
+            self.store.delete_object(&folder_marker).await?;
+            Ok::<_, anyhow::Error>(true)
+        };
+        match result.await {
+            Ok(true) => {
+                info!("Removed directory {}", path);
+                self.namespace().remove_dir(path);
+                Ok(())
+            }
+            Ok(false) => Err(ENOTEMPTY),
+            Err(e) => {
+                error!("Failed to remove directory {}: {:#}", path, e);
+                self.last_errors.record(&prefix, &e);
+                Err(self.health.errno(&e).await)
+            }
+        }
+    }
+
+    /// 删除文件 `key`。以写方式打开着时先取回全部旧内容，删除后句柄与键解除关联：
+    /// 句柄仍可以读写暂存，但不再上传，关闭时丢弃暂存
+    async fn unlink_file(&self, key: &str) -> Result<(), i32> {
+        let Some(handle) = self.write_handle_for(key) else {
+            return self.remove_file(key).await;
+        };
+        // 持有暂存的锁直到解除关联，其间的 flush 不会把文件重新上传
+        let mut staged = handle.lock().await;
+        if let Err(e) = staged.load(self.store.as_ref()).await {
+            error!("Failed to load {} before deleting it: {:#}", key, e);
+            return Err(self.object_errno(key, &e).await);
+        }
+        self.remove_file(key).await?;
+        staged.unlink();
+        self.failed_uploads.lock().unwrap().remove(key);
+        for (k, _) in self.write_handles.lock().unwrap().values_mut() {
+            if k.as_deref() == Some(key) {
+                *k = None;
+            }
+        }
+        self.staged.remove(key);
+        Ok(())
+    }
+
+    /// 删除对象 `key`：配置了回收站时先在服务端复制进回收站再删除，
+    /// 之后从列表、元数据缓存与内容缓存中移除
+    async fn remove_file(&self, key: &str) -> Result<(), i32> {
+        let _mutation = self.mutations.begin(&format!("/{}", key));
+        let result = match &self.upload.trash {
+            Some(trash) => trash
+                .move_to_trash(self.store.as_ref(), key)
+                .await
+                .map(|_| ()),
+            None => self.store.delete_object(key).await,
+        };
+        if let Err(e) = result {
+            error!("Failed to delete {}: {:#}", key, e);
+            self.last_errors.record(key, &e);
+            return Err(self.object_errno(key, &e).await);
+        }
+        info!("Deleted {}", key);
+        self.namespace().remove_object(key);
+        self.cache.remove_metadata(key);
+        self.cache.remove_content(key);
+        Ok(())
+    }
+
+    /// 把文件或目录 `from` 改名为 `to`：COS 没有改名操作，逐个在服务端复制到新键后删除原对象。
+    /// 目录改名时复制其下所有对象（连同目录标记），全部复制成功后才开始删除原对象。
+    /// 改名不是原子的：复制时失败，原处完整、目标处多出已复制的对象；删除时失败，目标处完整、
+    /// 原处只剩部分对象。失败后两处都重新列出，如实反映 COS 上的状态。
+    /// 改名以 `spawn_mutation` 执行，不会因超时或中断停在中途
+    async fn rename_path(&self, from: &str, to: &str, is_dir: bool) -> Result<(), i32> {
+        let from_prefix = if is_dir {
+            dir_prefix(from)
+        } else {
+            from.trim_start_matches('/').to_string()
+        };
+        let to_prefix = if is_dir {
+            dir_prefix(to)
+        } else {
+            to.trim_start_matches('/').to_string()
+        };
+        // 以写方式打开着的文件关闭时会按原来的键上传
+        if self
+            .staged
+            .keys()
+            .iter()
+            .any(|key| key.starts_with(&from_prefix) || (!is_dir && *key == to_prefix))
+        {
+            return Err(EBUSY);
+        }
+        let _from = self.mutations.begin(from);
+        let _to = self.mutations.begin(to);
+
+        let objects = if is_dir {
+            match self.store.list_all(&from_prefix, false).await {
+                Ok(listing) => listing.objects,
+                Err(e) => {
+                    error!("Failed to list {}: {:#}", from, e);
+                    self.last_errors.record(&from_prefix, &e);
+                    return Err(self.health.errno(&e).await);
+                }
+            }
+        } else {
+            match self.head_resolver.resolve(&from_prefix).await {
+                Ok(meta) => vec![meta],
+                Err(e) => {
+                    error!("Failed to get metadata for {}: {:#}", from_prefix, e);
+                    return Err(self.object_errno(&from_prefix, &e).await);
+                }
+            }
+        };
+        let objects: Vec<ObjectMeta> = objects
+            .into_iter()
+            .filter(|meta| !self.in_trash(&meta.key))
+            .collect();
+        if objects.iter().any(|meta| meta.size > MAX_COPY_SIZE) {
+            return Err(EFBIG);
+        }
+
+        let moved: Vec<(String, String)> = objects
+            .iter()
+            .map(|meta| {
+                let new_key = format!("{}{}", to_prefix, &meta.key[from_prefix.len()..]);
+                (meta.key.clone(), new_key)
+            })
+            .collect();
+        let result = async {
+            for (old_key, new_key) in &moved {
+                self.store.copy_object(old_key, new_key).await?;
+            }
+            for (old_key, _) in &moved {
+                self.store.delete_object(old_key).await?;
+            }
+            if is_dir {
+                let folder_marker = format!(
+                    "{}{}",
+                    from_prefix.trim_end_matches('/'),
+                    s3fs_compat::FOLDER_MARKER_SUFFIX
+                );
+                self.store.delete_object(&folder_marker).await?;
+            }
+            Ok::<_, anyhow::Error>(())
+        };
+        if let Err(e) = result.await {
+            error!("Failed to rename {} to {}: {:#}", from, to, e);
+            self.last_errors.record(&from_prefix, &e);
+            for (old_key, new_key) in &moved {
+                self.cache.remove_metadata(old_key);
+                self.cache.remove_metadata(new_key);
+            }
+            {
+                let mut namespace = self.namespace();
+                namespace.invalidate_ancestors(from);
+                namespace.invalidate_ancestors(to);
+            }
+            let parent = |path: &str| {
+                Path::new(path)
+                    .parent()
+                    .map_or_else(|| "/".to_string(), |dir| dir.to_string_lossy().into_owned())
+            };
+            let mut dirs = vec![parent(from), parent(to)];
+            if is_dir {
+                dirs.extend([from.to_string(), to.to_string()]);
+            }
+            for dir in &dirs {
+                if let Err(e) = self.refresh_dir(dir).await {
+                    warn!("Failed to refresh directory {}: {:#}", dir, e);
+                }
+            }
+            return Err(self.health.errno(&e).await);
+        }
+
+        info!("Renamed {} to {} ({} objects)", from, to, moved.len());
+        for (old_key, new_key) in &moved {
+            self.cache.remove_metadata(old_key);
+            self.cache.remove_content(old_key);
+            self.cache.remove_metadata(new_key);
+            self.cache.remove_content(new_key);
+        }
+        self.namespace().rename_tree(from, to, &moved);
+        Ok(())
+    }
+
+    /// 开启归档浏览时，路径落在归档中则返回 (归档对象键, 归档内路径)
+    fn archive_path(&self, namespace: &Namespace, path: &str) -> Option<(String, String)> {
+        self.archives.as_ref()?;
+        namespace.split_archive_path(path)
+    }
+
+    /// 路径是否为目录；开启归档浏览时归档对象本身和（已加载索引的）归档中的目录也是目录
+    fn is_directory(&self, namespace: &mut Namespace, path: &str) -> bool {
+        let Some((key, inner_path)) = self.archive_path(namespace, path) else {
+            return namespace.is_directory(path);
+        };
+        inner_path.is_empty()
+            || self
+                .archives
+                .as_ref()
+                .and_then(|archives| archives.loaded(&key))
+                .is_some_and(|index| matches!(index.node(&inner_path), Some(ArchiveNode::Dir)))
+    }
+
+    /// 归档对象的当前索引，对象变化后重新读取
+    async fn archive_index(&self, key: &str) -> Result<Arc<ArchiveIndex>> {
+        let archives = self
+            .archives
+            .as_ref()
+            .ok_or_else(|| anyhow!("Archive browsing is disabled"))?;
+        let meta = self.get_object_metadata(key).await?;
+        archives.index(self.store.as_ref(), &meta).await
+    }
+
+    /// 归档中路径的属性，路径不存在时答复 ENOENT
+    async fn archive_attr(&self, path: &str, key: &str, inner_path: &str) -> Result<FileAttr, i32> {
+        let index = match self.archive_index(key).await {
+            Ok(index) => index,
+            Err(e) => {
+                error!("Failed to index archive {}: {:#}", key, e);
+                return Err(self.object_errno(key, &e).await);
+            }
+        };
+        let ino = self.namespace().get_or_create_inode(path);
+        match index.node(inner_path) {
+            None => Err(ENOENT),
+            Some(ArchiveNode::Dir) => {
+                // 归档中的目录使用归档对象的修改时间
+                let mut attr = self.create_dir_attr(ino, path);
+                attr.atime = index.mtime;
+                attr.mtime = index.mtime;
+                attr.ctime = index.mtime;
+                attr.crtime = index.mtime;
+                Ok(attr)
+            }
+            Some(ArchiveNode::File(file)) => Ok(self.archive_file_attr(key, file, ino).await),
+        }
+    }
+
+    /// 归档中文件的属性，属主与权限沿用归档对象
+    async fn archive_file_attr(&self, key: &str, file: &ArchiveFile, ino: u64) -> FileAttr {
+        let owner = self.file_ownership(key).await;
+        FileAttr {
+            ino,
+            size: file.size,
+            blocks: file.size.div_ceil(512),
+            atime: file.mtime,
+            mtime: file.mtime,
+            ctime: file.mtime,
+            crtime: file.mtime,
+            kind: FileType::RegularFile,
+            perm: owner.perm,
+            nlink: 1,
+            uid: owner.uid,
+            gid: owner.gid,
+            rdev: 0,
+            blksize: 4096,
+            flags: 0,
+        }
+    }
+
+    /// 读取归档中的文件：原样存放的内容按偏移直接读取归档对象（经过内容缓存），
+    /// 压缩的内容读出后解压
+    async fn read_archive_file(
+        &self,
+        key: &str,
+        inner_path: &str,
+        offset: u64,
+        size: u64,
+    ) -> Result<Vec<u8>> {
+        let archives = self
+            .archives
+            .as_ref()
+            .ok_or_else(|| anyhow!("Archive browsing is disabled"))?;
+        let index = self.archive_index(key).await?;
+        archives
+            .read(
+                key,
+                &index,
+                inner_path,
+                offset,
+                size,
+                |offset, len| async move {
+                    self.read_object(key, offset, len, AccessPattern::Random)
+                        .await
+                        .map(ReadData::into_vec)
+                },
+            )
+            .await
+    }
+
+    /// 开启图片虚拟文件时，路径是已存在图片的虚拟文件则返回 (源图片对象键, 处理参数)；
+    /// 同名的真实对象优先
+    fn image_variant(&self, namespace: &Namespace, path: &str) -> Option<(String, String)> {
+        self.images.as_ref()?;
+        let key = path.trim_start_matches('/');
+        if namespace.contains_object(key) {
+            return None;
+        }
+        let (source, rule) = parse_variant(key)?;
+        namespace
+            .contains_object(source)
+            .then(|| (source.to_string(), rule))
+    }
+
+    /// 图片 `key` 按 `rule` 处理后的内容
+    async fn render_image(&self, key: &str, rule: &str) -> Result<Bytes> {
+        let images = self
+            .images
+            .as_ref()
+            .ok_or_else(|| anyhow!("Image variants are disabled"))?;
+        let meta = self.get_object_metadata(key).await?;
+        images.render(self.store.as_ref(), &meta, rule).await
+    }
+
+    /// 图片虚拟文件的属性：大小为处理结果的大小，其余沿用源图片
+    async fn image_variant_attr(&self, path: &str, key: &str, rule: &str) -> Result<FileAttr, i32> {
+        let result = async {
+            let data = self.render_image(key, rule).await?;
+            let ino = self.namespace().get_or_create_inode(path);
+            let mut attr = self.object_attr(key, ino).await?;
+            attr.size = data.len() as u64;
+            attr.blocks = attr.size.div_ceil(512);
+            Ok::<_, anyhow::Error>(attr)
+        };
+        match result.await {
+            Ok(attr) => Ok(attr),
+            Err(e) => {
+                error!("Failed to process image {} with {}: {:#}", key, rule, e);
+                Err(self.object_errno(key, &e).await)
+            }
+        }
+    }
+
+    /// 读取图片虚拟文件
+    async fn read_image_variant(
+        &self,
+        key: &str,
+        rule: &str,
+        offset: u64,
+        size: u64,
+    ) -> Result<ReadData> {
+        let data = self.render_image(key, rule).await?;
+        let start = (offset as usize).min(data.len());
+        let end = start.saturating_add(size as usize).min(data.len());
+        Ok(ReadData::Shared(data.slice(start..end)))
+    }
+
+    /// 获取对象内容，`etag` 与 `size` 为对象当前的 ETag 和大小
+    async fn get_object_content(&self, key: &str, etag: &str, size: u64) -> Result<Vec<u8>> {
+        // 先检查 L2 缓存，只使用同一版本的内容；旧版本的缓存在重新下载时被替换
+        if let Some(content) = self.cache.get_cached_content(key, etag, size)? {
+            debug!("Content cache hit for key: {}", key);
+            self.metrics.incr("cache.content.hits", 1);
+            return Ok(content);
+        }
+
+        debug!("Content cache miss for key: {}, downloading from COS", key);
+        self.metrics.incr("cache.content.misses", 1);
+        let started = Instant::now();
+        let content = self.downloader.fetch_object(key, etag, size).await?;
+        self.metrics.time("cos.get", started.elapsed());
+        Ok(content.to_vec())
+    }
+
+    /// 对象 `key` 在访问模式 `pattern` 下的缓存方式
+    fn cache_mode(&self, key: &str, size: u64, pattern: AccessPattern) -> CacheMode {
+        let options = self.cache.options();
+        options
+            .cache_policies
+            .resolve(key)
+            .choose(pattern, size, options)
+    }
+
+    /// 读取对象的 [offset, offset + size)：按缓存方式整体缓存、按块下载写入稀疏部分缓存，或直接读取 COS。
+    /// 较大的已缓存区间映射到内存答复，下载到内存的内容直接引用，都不再复制到缓冲区
+    async fn read_object(
+        &self,
+        key: &str,
+        offset: u64,
+        size: u64,
+        pattern: AccessPattern,
+    ) -> Result<ReadData> {
+        let meta = self.get_object_metadata(key).await?;
+        self.access_times.record_read(key, meta.last_modified);
+        let end = offset.saturating_add(size).min(meta.size);
+        if offset >= end {
+            return Ok(ReadData::empty());
+        }
+
+        let block_size = self.cache.options().block_size;
+        let mode = self.cache_mode(key, meta.size, pattern);
+        if meta.size <= block_size && mode != CacheMode::Direct {
+            let content = self.get_object_content(key, &meta.etag, meta.size).await?;
+            let content = Bytes::from(content);
+            let start = (offset as usize).min(content.len());
+            let end = (end as usize).min(content.len());
+            return Ok(ReadData::Shared(content.slice(start..end)));
+        }
+
+        if let Some(data) = self.read_cached_range(key, &meta.etag, offset, end)? {
+            debug!("Partial cache hit for key: {} [{}, {})", key, offset, end);
+            self.metrics.incr("cache.content.hits", 1);
+            return Ok(data);
+        }
+        self.metrics.incr("cache.content.misses", 1);
+        let started = Instant::now();
+
+        if mode == CacheMode::Direct {
+            let data = self
+                .store
+                .get_object_range(key, offset, end - offset)
+                .await?;
+            self.metrics.time("cos.get", started.elapsed());
+            return Ok(ReadData::Shared(data));
+        }
+
+        // 整体缓存时一次下载整个对象，否则按块对齐；只下载尚未缓存的区间
+        let (block_start, block_end) = match mode {
+            CacheMode::Whole => (0, meta.size),
+            _ => (
+                offset / block_size * block_size,
+                (end.div_ceil(block_size) * block_size).min(meta.size),
+            ),
+        };
+        for (start, stop) in self
+            .cache
+            .missing_ranges(key, &meta.etag, block_start, block_end)
+        {
+            if !self
+                .downloader
+                .fetch_range(key, &meta.etag, start, stop)
+                .await?
+            {
+                // 缓存盘写满：本次改为直接读取 COS
+                let data = self
+                    .store
+                    .get_object_range(key, offset, end - offset)
+                    .await?;
+                self.metrics.time("cos.get", started.elapsed());
+                return Ok(ReadData::Shared(data));
+            }
+        }
+        self.metrics.time("cos.get", started.elapsed());
+
+        self.read_cached_range(key, &meta.etag, offset, end)?
+            .ok_or_else(|| {
+                anyhow!(
+                    "Range [{}, {}) of {} missing after download",
+                    offset,
+                    end,
+                    key
+                )
+            })
+    }
+
+    /// 读取已缓存的 [offset, end)：不小于 [`MIN_MAPPED_LEN`] 的原样存放区间映射到内存，
+    /// 其余读入池中的缓冲区；区间未完全缓存时返回 None
+    fn read_cached_range(
+        &self,
+        key: &str,
+        etag: &str,
+        offset: u64,
+        end: u64,
+    ) -> Result<Option<ReadData>> {
+        let len = end - offset;
+        if len >= MIN_MAPPED_LEN {
+            if let Some(range) = self.cache.map_range(key, etag, offset, len)? {
+                self.metrics.incr("read.mapped_bytes", len);
+                return Ok(Some(ReadData::Mapped(range)));
+            }
+        }
+        Ok(self
+            .cache
+            .read_range(key, etag, offset, len, |len| self.buffers.get(len))?
+            .map(ReadData::from))
+    }
+
+    /// 预读对象的 [start, stop)：按块下载尚未缓存的部分，与前台读取同一块的下载合并。
+    /// 只有按块缓存的对象需要预读
+    async fn readahead(&self, key: &str, start: u64, stop: u64, pattern: AccessPattern) {
+        let meta = match self.get_object_metadata(key).await {
+            Ok(meta) => meta,
+            Err(e) => {
+                debug!("Skipping readahead of {}: {:#}", key, e);
+                return;
+            }
+        };
+        if self.cache_mode(key, meta.size, pattern) != CacheMode::Block {
+            return;
+        }
+        let block_size = self.cache.options().block_size;
+        let stop = stop.min(meta.size);
+        if start >= stop {
+            return;
+        }
+
+        let block_start = start / block_size * block_size;
+        let block_end = stop
+            .div_ceil(block_size)
+            .saturating_mul(block_size)
+            .min(meta.size);
+        let mut blocks = Vec::new();
+        for (start, stop) in self
+            .cache
+            .missing_ranges(key, &meta.etag, block_start, block_end)
+        {
+            let mut offset = start;
+            while offset < stop {
+                let end = (offset + block_size).min(stop);
+                blocks.push((offset, end));
+                offset = end;
+            }
+        }
+        if blocks.is_empty() {
+            return;
+        }
+        debug!(
+            "Readahead of {} [{}, {}): {} blocks",
+            key,
+            block_start,
+            block_end,
+            blocks.len()
+        );
+
+        let mut tasks = JoinSet::new();
+        let mut blocks = blocks.into_iter();
+        loop {
+            while tasks.len() < READAHEAD_CONCURRENCY {
+                let Some((start, stop)) = blocks.next() else {
+                    break;
+                };
+                let downloader = Arc::clone(&self.downloader);
+                let key = key.to_string();
+                let etag = meta.etag.clone();
+                tasks.spawn(background(async move {
+                    let result = downloader.fetch_range(&key, &etag, start, stop).await;
+                    (stop - start, result)
+                }));
+            }
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+            match joined {
+                Ok((len, Ok(true))) => self.metrics.incr("readahead.bytes", len),
+                // 缓存盘已满，放弃剩余的预读
+                Ok((_, Ok(false))) => return,
+                Ok((_, Err(e))) => debug!("Readahead of {} failed: {:#}", key, e),
+                Err(_) => {}
+            }
+        }
+    }
+
+    /// 读取预取清单：优先使用配置的本地文件，其次是 bucket 根目录下的 `.cosfs-prefetch`
+    async fn load_prefetch_manifest(&self) -> Result<Option<String>> {
+        if let Some(path) = &self.cache.options().prefetch_manifest {
+            return Ok(Some(std::fs::read_to_string(path)?));
+        }
+
+        let has_manifest = self
+            .namespace()
+            .object_list
+            .iter()
+            .any(|k| &**k == PREFETCH_MANIFEST_KEY);
+        if !has_manifest {
+            return Ok(None);
+        }
+
+        let data = self.store.get_object(PREFETCH_MANIFEST_KEY).await?;
+        Ok(Some(String::from_utf8_lossy(&data).into_owned()))
+    }
+
+    /// 按预取清单把匹配的对象下载到缓存
+    async fn prefetch(&self) {
+        let manifest = match self.load_prefetch_manifest().await {
+            Ok(Some(manifest)) => manifest,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("Failed to load prefetch manifest: {:#}", e);
+                return;
+            }
+        };
+
+        let patterns = parse_manifest(&manifest);
+        let keys: Vec<String> = self
+            .namespace()
+            .object_list
+            .iter()
+            .filter(|key| !key.ends_with('/'))
+            .filter(|key| patterns.iter().any(|p| glob_match(p, key)))
+            .map(|key| key.to_string())
+            .collect();
+        if keys.is_empty() {
+            return;
+        }
+
+        run_prefetch(
+            Arc::clone(&self.downloader),
+            keys,
+            Arc::clone(&self.prefetch_stats),
+        )
+        .await;
+    }
+
+    /// 挂载时探测到可以读取对象（没有探测时按可读处理）
+    fn can_read(&self) -> bool {
+        self.capabilities.get().is_none_or(|c| c.read)
+    }
+
+    /// 拒绝写入时答复的错误码：快照挂载只读（EROFS），挂载时探测确认没有写权限的答复 EACCES。
+    /// 不带 `-o rw` 的只读挂载由内核直接答复 EROFS，不会走到这里
+    fn write_denied(&self) -> Option<i32> {
+        if self.snapshot.is_some() {
+            Some(EROFS)
+        } else if self.write_denied.load(Ordering::Relaxed) {
+            Some(EACCES)
+        } else {
+            None
+        }
+    }
+
+    /// 请求者（映射后）是否在白名单中
+    fn is_allowed(&self, req: &Request<'_>) -> bool {
+        let allowed = self.id_map.credentials(req.uid(), req.gid()).is_some();
+        if !allowed {
+            debug!("Rejecting request from uid={} gid={}", req.uid(), req.gid());
+        }
+        allowed
+    }
+
+    /// 文件报告的属主与权限；开启了 ACL 权限时需要查询 ACL，其余只看前缀规则，不访问网络
+    async fn file_ownership(&self, key: &str) -> Ownership {
+        let acl_mode = match &self.acl {
+            Some(acl) => acl.mode(key).await,
+            None => None,
+        };
+        self.permissions
+            .resolve_with_default(key, false, acl_mode.unwrap_or(DEFAULT_FILE_MODE))
+    }
+
+    /// 获取对象的元数据并转换为 FileAttr，开启了 ACL 权限时一并查询 ACL
+    async fn object_attr(&self, key: &str, ino: u64) -> Result<FileAttr> {
+        // 以写方式打开着的对象报告暂存的大小与 mtime
+        let meta = match self.staged.get(key) {
+            Some(meta) => meta,
+            None => self.get_object_metadata(key).await?,
+        };
+        let acl_mode = match &self.acl {
+            Some(acl) => acl.mode(key).await,
+            None => None,
+        };
+        Ok(self.meta_to_attr(&meta, ino, acl_mode))
+    }
+
+    /// 将 ObjectMeta 转换为 FileAttr，`acl_mode` 为由 ACL 推导的权限位
+    fn meta_to_attr(&self, meta: &ObjectMeta, ino: u64, acl_mode: Option<u16>) -> FileAttr {
+        let perm = acl_mode.unwrap_or(DEFAULT_FILE_MODE);
+        let owner = if self.s3fs_compat {
+            // 元数据中的属主优先于默认值，前缀规则仍然覆盖它们
+            let recorded = MetaOwnership::from_user_meta(&meta.details.user_meta);
+            let default = Ownership {
+                uid: recorded.uid.unwrap_or(DEFAULT_UID),
+                gid: recorded.gid.unwrap_or(DEFAULT_GID),
+                perm: recorded.perm.unwrap_or(perm),
+            };
+            self.permissions.resolve_from(&meta.key, false, default)
+        } else {
+            self.permissions
+                .resolve_with_default(&meta.key, false, perm)
+        };
+        FileAttr {
+            ino,
+            size: meta.size,
+            blocks: meta.size.div_ceil(512), // 块大小为 512 字节
+            atime: self.access_times.atime(&meta.key, meta.last_modified),
+            mtime: meta.last_modified,
+            ctime: meta.last_modified,
+            crtime: meta.last_modified,
+            kind: FileType::RegularFile,
+            perm: owner.perm,
+            nlink: 1,
+            uid: owner.uid,
+            gid: owner.gid,
+            rdev: 0,
+            blksize: 4096,
+            flags: 0,
+        }
+    }
+
+    /// 目录报告的属主与权限
+    fn dir_ownership(&self, path: &str) -> Ownership {
+        let dir_key = match path.trim_matches('/') {
+            "" => String::new(),
+            key => format!("{}/", key),
+        };
+        self.permissions.resolve(&dir_key, true)
+    }
+
+    /// 推送指标时采集的瞬时值
+    fn gauges(&self) -> Vec<(&'static str, u64)> {
+        let stats = self.cache.get_stats();
+        let memory = self.memory_usage();
+        let buffers = self.buffers.stats();
+        vec![
+            ("cache.metadata.entries", stats.metadata_cache_size as u64),
+            ("cache.content.files", stats.content_cache_size as u64),
+            ("cache.disk_full_fallbacks", stats.disk_full_fallbacks),
+            ("spool.used_bytes", self.spool.used_bytes()),
+            ("memory.total", memory.total()),
+            ("memory.strings", memory.strings),
+            ("memory.listing", memory.listing),
+            ("memory.inodes", memory.inodes),
+            ("memory.dir_cache", memory.dir_cache),
+            ("memory.metadata", memory.metadata),
+            ("memory.content", memory.content),
+            ("buffers.pooled_bytes", buffers.pooled_bytes),
+            ("buffers.allocations", buffers.allocations),
+            ("buffers.reuses", buffers.reuses),
+        ]
+    }
+
+    /// 创建目录属性
+    /// 控制目录及其中文件的属性：属主与挂载根目录相同，只读
+    fn control_attr(&self, ino: u64) -> Option<FileAttr> {
+        let mut attr = self.create_dir_attr(ino, "/");
+        match ino {
+            CONTROL_DIR_INODE => attr.perm &= 0o555,
+            EVENTS_INODE => {
+                attr.kind = FileType::RegularFile;
+                attr.perm &= 0o444;
+                attr.nlink = 1;
+            }
+            _ => return None,
+        }
+        Some(attr)
+    }
+
+    fn create_dir_attr(&self, ino: u64, path: &str) -> FileAttr {
+        let now = SystemTime::now();
+        let owner = self.dir_ownership(path);
+        FileAttr {
+            ino,
+            size: 0,
+            blocks: 0,
+            atime: now,
+            mtime: now,
+            ctime: now,
+            crtime: now,
+            kind: FileType::Directory,
+            perm: owner.perm,
+            nlink: 2,
+            uid: owner.uid,
+            gid: owner.gid,
+            rdev: 0,
+            blksize: 4096,
+            flags: 0,
+        }
+    }
+}
+
+/// 异步操作 `name` 的计时器与失败计数器名
+fn op_metric_names(name: &'static str) -> (&'static str, &'static str) {
+    match name {
+        "lookup" => ("fuse.lookup", "fuse.lookup.errors"),
+        "getattr" => ("fuse.getattr", "fuse.getattr.errors"),
+        "read" => ("fuse.read", "fuse.read.errors"),
+        "access" => ("fuse.access", "fuse.access.errors"),
+        "getxattr" => ("fuse.getxattr", "fuse.getxattr.errors"),
+        "listxattr" => ("fuse.listxattr", "fuse.listxattr.errors"),
+        "setxattr" => ("fuse.setxattr", "fuse.setxattr.errors"),
+        "removexattr" => ("fuse.removexattr", "fuse.removexattr.errors"),
+        "fsyncdir" => ("fuse.fsyncdir", "fuse.fsyncdir.errors"),
+        "open" => ("fuse.open", "fuse.open.errors"),
+        "write" => ("fuse.write", "fuse.write.errors"),
+        "flush" => ("fuse.flush", "fuse.flush.errors"),
+        "fsync" => ("fuse.fsync", "fuse.fsync.errors"),
+        "release" => ("fuse.release", "fuse.release.errors"),
+        "setattr" => ("fuse.setattr", "fuse.setattr.errors"),
+        "mkdir" => ("fuse.mkdir", "fuse.mkdir.errors"),
+        "unlink" => ("fuse.unlink", "fuse.unlink.errors"),
+        "rename" => ("fuse.rename", "fuse.rename.errors"),
+        "rmdir" => ("fuse.rmdir", "fuse.rmdir.errors"),
+        _ => ("fuse.other", "fuse.other.errors"),
+    }
+}
+
+/// COS 文件系统实现
+///
+/// 需要访问网络的操作（lookup、getattr、read）在运行时上作为独立任务执行并在任务内答复，
+/// FUSE 会话线程不会阻塞在网络请求上，多个请求的等待可以相互重叠
+pub struct CosFilesystem<S: ObjectStore = Backend> {
+    /// 各操作任务共享的状态
+    inner: Arc<Inner<S>>,
+
+    /// 后台缓存清理任务
+    janitor: Option<JoinHandle<()>>,
+
+    /// 挂载时的后台预取任务
+    prefetch: Option<JoinHandle<()>>,
+
+    /// 周期性重新列出访问过的目录的任务
+    refresher: Option<JoinHandle<()>>,
+
+    /// 重试关闭时上传失败的文件的任务
+    uploader: Option<JoinHandle<()>>,
+
+    /// StatsD 推送配置与推送任务
+    statsd_options: Option<StatsdOptions>,
+    statsd: Option<JoinHandle<()>>,
+
+    /// 进行中的异步操作，用于响应 FUSE_INTERRUPT
+    pending: Arc<PendingOps>,
+
+    /// 单个操作的最长执行时间
+    op_timeout: Option<Duration>,
+
+    /// init 时与内核协商的单个读写请求大小
+    transfer: TransferSizes,
+
+    /// 共享的异步运行时，`daemon` 中由多个挂载共用
+    runtime: Arc<Runtime>,
+
+    /// 打开的目录句柄及其目录项快照：列出途中刷新或变更不会让 `ls` 跳过或重复条目
+    dir_handles: HashMap<u64, DirSnapshot>,
+
+    /// 下一个目录句柄号（0 保留给没有经过 opendir 的 readdir）
+    next_dir_handle: u64,
+
+    /// 打开的事件文件句柄及其读取游标
+    event_handles: HashMap<u64, u64>,
+
+    /// 打开的对象文件句柄的预读状态
+    readahead: HashMap<u64, Readahead>,
+
+    /// 下一个文件句柄号
+    next_file_handle: u64,
+
+    /// 就绪状态，init 完成首次列表后更新
+    ready: watch::Sender<Readiness>,
+}
+
+/// 挂载的就绪状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Readiness {
+    Pending,
+    Ready,
+    /// init 失败，挂载不可用
+    Failed(String),
+}
+
+/// 挂载后从外部访问文件系统状态的句柄，文件系统交给 fuser 后仍然有效
+#[derive(Clone)]
+pub(crate) struct FsControl<S = Backend> {
+    inner: Arc<Inner<S>>,
+    runtime: Arc<Runtime>,
+    ready: watch::Receiver<Readiness>,
+}
+
+impl<S: ObjectStore> FsControl<S> {
+    /// 当前的瞬时指标，名字与推送的 StatsD 瞬时值相同
+    pub fn stats(&self) -> BTreeMap<&'static str, u64> {
+        self.inner.gauges().into_iter().collect()
+    }
+
+    /// 重新列出整个桶，与在根目录上写入 `user.cosfs.refresh` 相同
+    pub async fn refresh(&self) -> Result<()> {
+        let inner = Arc::clone(&self.inner);
+        self.runtime
+            .spawn(async move {
+                inner.refresh_object_list().await?;
+                inner.enforce_memory_budget();
+                Ok(())
+            })
+            .await?
+    }
+
+    /// 等到首次列表完成、挂载可用；init 失败或会话在此之前结束时返回错误
+    pub async fn ready(&self) -> Result<()> {
+        let mut ready = self.ready.clone();
+        let state = ready
+            .wait_for(|state| *state != Readiness::Pending)
+            .await
+            .map_err(|_| anyhow!("Filesystem stopped before it became ready"))?
+            .clone();
+        match state {
+            Readiness::Failed(reason) => Err(anyhow!("Filesystem failed to start: {}", reason)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl CosFilesystem<Backend> {
+    /// 按 `client_options.provider` 创建对象存储客户端，再创建文件系统
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        bucket: String,
+        region: String,
+        cache_dir: &Path,
+        cache_options: &CacheOptions,
+        spool_options: &SpoolOptions,
+        client_options: &ClientOptions,
+        runtime_options: &RuntimeOptions,
+        runtime: Arc<Runtime>,
+        attr_options: &AttrOptions,
+        upload_options: &UploadOptions,
+    ) -> Result<Self> {
+        // 快照挂载的下载固定到清单中的版本
+        let client_options = match &cache_options.snapshot {
+            Some(snapshot) => ClientOptions {
+                pinned_etags: Some(Arc::new(snapshot.etags())),
+                ..client_options.clone()
+            },
+            None => client_options.clone(),
+        };
+        let store = Arc::new(Backend::new(bucket, region, &client_options)?);
+        Self::with_store(
+            store,
+            cache_dir,
+            cache_options,
+            spool_options,
+            runtime_options,
+            runtime,
+            attr_options,
+            upload_options,
+        )
+    }
+}
+
+impl<S: ObjectStore> CosFilesystem<S> {
+    /// 使用给定的对象存储后端创建文件系统
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_store(
+        store: Arc<S>,
+        cache_dir: &Path,
+        cache_options: &CacheOptions,
+        spool_options: &SpoolOptions,
+        runtime_options: &RuntimeOptions,
+        runtime: Arc<Runtime>,
+        attr_options: &AttrOptions,
+        upload_options: &UploadOptions,
+    ) -> Result<Self> {
+        let head_resolver = HeadResolver::new(Arc::clone(&store), DEFAULT_MAX_CONCURRENT_HEADS);
+        let cache = Arc::new(Cache::new(cache_dir, cache_options)?);
+        let revalidator = Revalidator::new(Arc::clone(&store), Arc::clone(&cache));
+        let downloader = Arc::new(Downloader::new(Arc::clone(&store), Arc::clone(&cache)));
+        let spool = Arc::new(Spool::open(spool_options, cache_dir)?);
+        let events = Arc::new(EventLog::new());
+        let health = BucketHealth::new(Arc::clone(&store), Arc::clone(&events));
+        let acl = if attr_options.acl_permissions {
+            Some(AclPermissions::new(
+                Arc::clone(&store),
+                cache_options.metadata_ttl,
+                cache_options.metadata_cache_size,
+            )?)
+        } else {
+            None
+        };
+
+        let inner = Inner {
+            store,
+            head_resolver,
+            cache,
+            revalidator,
+            downloader,
+            spool,
+            namespace: Mutex::new(Namespace::new()),
+            dir_cache_ttl: DEFAULT_DIR_CACHE_TTL,
+            prefetch_stats: Arc::new(PrefetchStats::default()),
+            access_times: AccessTimes::new(attr_options.atime),
+            permissions: attr_options.permissions.clone(),
+            id_map: attr_options.id_map.clone(),
+            presign_expires: attr_options.presign_expires,
+            acl,
+            capabilities: OnceLock::new(),
+            health,
+            mutations: Arc::new(PendingMutations::new()),
+            write_handles: Mutex::new(HashMap::new()),
+            failed_uploads: Mutex::new(HashMap::new()),
+            upload_timeout: runtime_options.op_timeout,
+            staged: StagedFiles::new(),
+            write_denied: AtomicBool::new(false),
+            revalidate_misses: cache_options.revalidate_misses,
+            hires_mtime: attr_options.hires_mtime,
+            s3fs_compat: attr_options.s3fs_compat,
+            upload: upload_options.clone(),
+            archives: attr_options.browse_archives.then(Archives::new),
+            images: attr_options.image_variants.then(ImageVariants::new),
+            metrics: Arc::new(Metrics::new()),
+            last_errors: LastErrors::new(),
+            system_xattrs: attr_options.system_xattrs.clone(),
+            accounting: UidAccounting::new(runtime_options.uid_rate_limit),
+            memory: MemoryBudget::new(runtime_options.memory_limit),
+            buffers: BufferPool::new(DEFAULT_POOL_BYTES),
+            snapshot: cache_options.snapshot.clone(),
+            memory_exhausted: AtomicBool::new(false),
+            events,
+            hooks: runtime_options.hooks.clone(),
+        };
+
+        Ok(Self {
+            inner: Arc::new(inner),
+            janitor: None,
+            prefetch: None,
+            refresher: None,
+            uploader: None,
+            statsd_options: runtime_options.statsd.clone(),
+            statsd: None,
+            pending: Arc::new(PendingOps::new()),
+            op_timeout: runtime_options.op_timeout,
+            transfer: runtime_options.transfer,
+            runtime,
+            dir_handles: HashMap::new(),
+            next_dir_handle: 1,
+            event_handles: HashMap::new(),
+            readahead: HashMap::new(),
+            next_file_handle: 1,
+            ready: watch::channel(Readiness::Pending).0,
+        })
+    }
+
+    /// 挂载后访问状态的句柄，需在把文件系统交给 fuser 之前取得
+    pub(crate) fn control(&self) -> FsControl<S> {
+        FsControl {
+            inner: Arc::clone(&self.inner),
+            runtime: Arc::clone(&self.runtime),
+            ready: self.ready.subscribe(),
+        }
+    }
+
+    /// 协商单个读写请求的大小：读请求由 `max_read` 挂载选项与请求页数上限共同决定，
+    /// 内核不支持时保持默认值
+    fn negotiate_transfer(&self, config: &mut KernelConfig) {
+        let TransferSizes {
+            max_read,
+            max_write,
+        } = self.transfer;
+        if let Err(nearest) = config.set_max_write(max_write) {
+            warn!(
+                "Kernel rejected max_write of {} bytes, using {}",
+                max_write, nearest
+            );
+            let _ = config.set_max_write(nearest);
+        }
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u32;
+        if let Err(nearest) = config.set_max_pages(self.transfer.max_pages(page_size)) {
+            warn!("Kernel rejected request size, using {} pages", nearest);
+        }
+        info!(
+            "Negotiated FUSE request sizes: max_read {} bytes, max_write {} bytes",
+            max_read, max_write
+        );
+    }
+
+    /// 挂载前探测对桶的访问权限并记录；`write` 为 true 时上传并删除一个空对象检查写权限
+    pub fn probe(&self, write: bool) -> Result<Capabilities> {
+        let capabilities = self.runtime.block_on(async {
+            let probe = probe(self.inner.store.as_ref(), write);
+            match self.op_timeout {
+                Some(limit) => tokio::time::timeout(limit, probe)
+                    .await
+                    .unwrap_or_else(|_| Err(anyhow!("timed out after {:?}", limit))),
+                None => probe.await,
+            }
+        })?;
+        let _ = self.inner.capabilities.set(capabilities);
+        if write && !capabilities.write {
+            self.inner.write_denied.store(true, Ordering::Relaxed);
+        }
+        Ok(capabilities)
+    }
+
+    /// setxattr/removexattr 的目标：只有普通文件上的 `user.cos.meta.*` 可以修改，
+    /// 返回 (对象键, 元数据键)，否则返回应答复的错误码
+    fn user_meta_target(
+        &self,
+        req: &Request<'_>,
+        ino: u64,
+        name: &OsStr,
+    ) -> Result<(String, String), i32> {
+        if !self.inner.is_allowed(req) {
+            return Err(EACCES);
+        }
+        let name = name.to_str().ok_or(EOPNOTSUPP)?;
+        let Some(meta_key) = name.strip_prefix(USER_META_XATTR_PREFIX) else {
+            return Err(if READ_ONLY_XATTRS.contains(&name) {
+                EPERM
+            } else {
+                EOPNOTSUPP
+            });
+        };
+        if !is_valid_meta_key(meta_key) {
+            return Err(EINVAL);
+        }
+        if self.inner.snapshot.is_some() {
+            return Err(EROFS);
+        }
+        if self.inner.control_attr(ino).is_some() {
+            return Err(EOPNOTSUPP);
+        }
+
+        let mut namespace = self.inner.namespace();
+        let path = namespace.get_path(ino).map(str::to_string).ok_or(ENOENT)?;
+        if ino == ROOT_INODE
+            || self.inner.archive_path(&namespace, &path).is_some()
+            || self.inner.image_variant(&namespace, &path).is_some()
+            || namespace.is_directory(&path)
+        {
+            return Err(EOPNOTSUPP);
+        }
+        drop(namespace);
+        if let Some(errno) = self.unhealthy() {
+            return Err(errno);
+        }
+        Ok((
+            path.trim_start_matches('/').to_string(),
+            meta_key.to_string(),
+        ))
+    }
+
+    /// 写入 `user.cosfs.refresh`：根目录上重新列出整个桶，其他目录上只重新列出该目录，
+    /// 并在之后的周期刷新中继续列出它
+    fn refresh_on_demand(&mut self, req: &Request<'_>, ino: u64, reply: ReplyEmpty) {
+        if !self.inner.is_allowed(req) {
+            reply.error(EACCES);
+            return;
+        }
+        if self.inner.control_attr(ino).is_some() {
+            reply.error(EOPNOTSUPP);
+            return;
+        }
+        let mut namespace = self.inner.namespace();
+        let Some(path) = namespace.get_path(ino).map(str::to_string) else {
+            reply.error(ENOENT);
+            return;
+        };
+        if ino != ROOT_INODE {
+            if self.inner.archive_path(&namespace, &path).is_some()
+                || !namespace.is_directory(&path)
+            {
+                reply.error(ENOTDIR);
+                return;
+            }
+            namespace.mark_listed(&path);
+        }
+        drop(namespace);
+        if let Some(errno) = self.unhealthy() {
+            reply.error(errno);
+            return;
+        }
+
+        let inner = Arc::clone(&self.inner);
+        self.spawn_op(
+            "refresh",
+            req,
+            reply,
+            async move {
+                let result = if ino == ROOT_INODE {
+                    inner.refresh_object_list().await
+                } else {
+                    inner.refresh_dir(&path).await
+                };
+                match result {
+                    Ok(()) => {
+                        inner.enforce_memory_budget();
+                        Ok(())
+                    }
+                    Err(e) => {
+                        warn!("Failed to refresh {}: {:#}", path, e);
+                        Err(inner.health.errno(&e).await)
+                    }
+                }
+            },
+            |reply, ()| reply.ok(),
+        );
+    }
+
+    /// 打开控制目录中的文件：事件文件的每个句柄从最早保留的事件开始读，
+    /// 绕过页缓存且不可定位，读取总是取到最新内容
+    fn open_control(&mut self, req: &Request<'_>, ino: u64, flags: i32, reply: ReplyOpen) {
+        if !self.inner.is_allowed(req) {
+            reply.error(EACCES);
+            return;
+        }
+        if ino == CONTROL_DIR_INODE {
+            reply.error(EPERM);
+            return;
+        }
+        if wants_write(flags) {
+            reply.error(EACCES);
+            return;
+        }
+        let fh = self.next_file_handle;
+        self.next_file_handle += 1;
+        self.event_handles
+            .insert(fh, self.inner.events.first_cursor());
+        reply.opened(fh, FOPEN_DIRECT_IO | FOPEN_NONSEEKABLE);
+    }
+
+    /// 桶处于错误状态时返回操作应答复的错误码，到了重试间隔时在后台重新检查
+    fn unhealthy(&self) -> Option<i32> {
+        let errno = self.inner.health.state().errno()?;
+        if self.inner.health.check_due() {
+            let inner = Arc::clone(&self.inner);
+            self.runtime.spawn(async move {
+                inner.health.check().await;
+            });
+        }
+        debug!(
+            "Rejecting request, bucket state is {}",
+            self.inner.health.state()
+        );
+        Some(errno)
+    }
+
+    /// 在目录 `parent` 中新建或删除条目 `name` 之前的检查，返回条目的路径与映射后的请求者身份。
+    /// 控制目录中答复 EACCES，归档与图片虚拟目录中答复 EROFS
+    fn entry_to_change(
+        &self,
+        req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+    ) -> Result<(String, Credentials), i32> {
+        if self.inner.control_attr(parent).is_some() {
+            return Err(EACCES);
+        }
+        if let Some(errno) = self.unhealthy() {
+            return Err(errno);
+        }
+        let Some(credentials) = self.inner.id_map.credentials(req.uid(), req.gid()) else {
+            debug!("Rejecting request from uid={} gid={}", req.uid(), req.gid());
+            return Err(EACCES);
+        };
+        if let Some(errno) = self.inner.write_denied() {
+            return Err(errno);
+        }
+        // 对象键以 UTF-8 保存
+        let name = name.to_str().ok_or(EINVAL)?;
+
+        let namespace = self.inner.namespace();
+        let parent_path = namespace.get_path(parent).ok_or(ENOENT)?;
+        let path = if parent_path == "/" {
+            format!("/{}", name)
+        } else {
+            format!("{}/{}", parent_path, name)
+        };
+        if self.inner.archive_path(&namespace, &path).is_some()
+            || self.inner.image_variant(&namespace, &path).is_some()
+        {
+            return Err(EROFS);
+        }
+        Ok((path, credentials))
+    }
+
+    /// lookup 在本地列表中没有找到路径：开启 `--revalidate-misses` 时先向 COS 确认，
+    /// 否则（或刚确认过不存在时）直接答复 ENOENT
+    fn lookup_miss(&self, req: &Request<'_>, path: String, reply: ReplyEntry) {
+        let skip = {
+            let mut namespace = self.inner.namespace();
+            let object_key = path.trim_start_matches('/');
+            // 自己删除的对象在下一次列表之前不再向 COS 确认
+            namespace.tombstones.contains_key(object_key)
+                || namespace.recently_missed(&path)
+                || self.inner.in_trash(object_key)
+        };
+        if !self.inner.revalidate_misses || skip {
+            reply.error(ENOENT);
+            return;
+        }
+
+        let inner = Arc::clone(&self.inner);
+        self.spawn_op(
+            "lookup",
+            req,
+            reply,
+            async move {
+                match inner.revalidate_miss(&path).await {
+                    Ok(Some(attr)) => Ok(attr),
+                    Ok(None) => Err(ENOENT),
+                    Err(e) => {
+                        error!("Failed to revalidate lookup miss for {}: {:#}", path, e);
+                        Err(inner.health.errno(&e).await)
+                    }
+                }
+            },
+            |reply, attr| reply.entry(&Duration::from_secs(1), &attr, 0),
+        );
+    }
+
+    /// 列出目录的完整目录项（含 `.` 与 `..`）
+    fn dir_snapshot(&self, ino: u64) -> Result<DirSnapshot, i32> {
+        if ino == CONTROL_DIR_INODE {
+            return Ok(vec![
+                (CONTROL_DIR_INODE, FileType::Directory, ".".to_string()),
+                (ROOT_INODE, FileType::Directory, "..".to_string()),
+                (
+                    EVENTS_INODE,
+                    FileType::RegularFile,
+                    EVENTS_FILE_NAME.to_string(),
+                ),
+            ]);
+        }
+        let mut namespace = self.inner.namespace();
+        let path = namespace.get_path(ino).map(str::to_string).ok_or(ENOENT)?;
+        let entries = match self.inner.archive_path(&namespace, &path) {
+            Some((key, inner_path)) => self.archive_dir_entries(&key, &inner_path)?,
+            None => {
+                if !namespace.is_directory(&path) {
+                    return Err(ENOTDIR);
+                }
+                namespace.mark_listed(&path);
+
+                // 目录缓存过期后重新从对象列表构建
+                match namespace.dir_cache.get(path.as_str()) {
+                    Some(cached) if cached.is_fresh(self.inner.dir_cache_ttl) => {
+                        cached.entries.clone()
+                    }
+                    _ => {
+                        let listed = namespace.list_directory(&path);
+                        let key = namespace.strings.intern(&path);
+                        namespace
+                            .dir_cache
+                            .insert(key, CachedDir::new(listed.clone()));
+                        listed
+                    }
+                }
+            }
+        };
+
+        // 构建完整 entry 列表
+        let mut all_entries = Vec::with_capacity(entries.len() + 2);
+
+        // "."
+        all_entries.push((ino, FileType::Directory, ".".to_string()));
+
+        // ".."
+        let parent_ino = if path == "/" {
+            ino
+        } else {
+            let parent_path = Path::new(&path).parent().unwrap_or(Path::new("/"));
+            let parent_path_str = parent_path.to_string_lossy().to_string();
+            *namespace
+                .path_to_inode
+                .get(parent_path_str.as_str())
+                .unwrap_or(&ROOT_INODE)
+        };
+        all_entries.push((parent_ino, FileType::Directory, "..".to_string()));
+
+        // 真实条目
+        for entry in entries {
+            let child_path = if path == "/" {
+                format!("/{}", entry.name)
+            } else {
+                format!("{}/{}", path, entry.name)
+            };
+            let child_ino = namespace.get_or_create_inode(&child_path);
+            // 开启归档浏览时归档对象显示为目录
+            let file_type = if self.inner.archives.is_some()
+                && entry.file_type == FileType::RegularFile
+                && ArchiveKind::from_key(&entry.name).is_some()
+            {
+                FileType::Directory
+            } else {
+                entry.file_type
+            };
+            all_entries.push((child_ino, file_type, entry.name.to_string()));
+        }
+        Ok(all_entries)
+    }
+
+    /// 归档中目录的条目；索引在 lookup 归档时已经加载
+    fn archive_dir_entries(&self, key: &str, inner_path: &str) -> Result<Vec<DirEntry>, i32> {
+        let Some(index) = self
+            .inner
+            .archives
+            .as_ref()
+            .and_then(|archives| archives.loaded(key))
+        else {
+            warn!("Archive {} listed before its index was loaded", key);
+            return Err(EIO);
+        };
+        if !matches!(index.node(inner_path), Some(ArchiveNode::Dir)) {
+            return Err(ENOTDIR);
+        }
+        Ok(index
+            .list(inner_path)
+            .into_iter()
+            .map(|(name, is_dir)| DirEntry {
+                name: name.into(),
+                file_type: if is_dir {
+                    FileType::Directory
+                } else {
+                    FileType::RegularFile
+                },
+            })
+            .collect())
+    }
+
+    /// 在运行时上执行一个需要等待网络的操作，并在任务内答复。
+    /// `op` 失败时返回错误码；请求被中断时放弃等待并答复 EINTR，超时答复 EIO。
+    /// 耗时记入计时器 `fuse.<name>`，失败次数记入 `fuse.<name>.errors`
+    fn spawn_op<R, T, Fut, F>(
+        &self,
+        name: &'static str,
+        req: &Request<'_>,
+        reply: R,
+        op: Fut,
+        respond: F,
+    ) where
+        R: ErrorReply,
+        T: Send + 'static,
+        Fut: Future<Output = Result<T, i32>> + Send + 'static,
+        F: FnOnce(R, T) + Send + 'static,
+    {
+        self.spawn_request(name, req, reply, op, respond, true);
+    }
+
+    /// 与 [`Self::spawn_op`] 相同，但不响应 FUSE_INTERRUPT，也不在中途放弃：删除与改名中途放弃
+    /// 会留下复制了一半的目录，而列表来不及按失败处理。超过 `--op-timeout` 时先答复 EIO，
+    /// 操作在后台继续执行到结束；上传本身另按同样的时限放弃，改动留在暂存中重试
+    fn spawn_mutation<R, T, Fut, F>(
+        &self,
+        name: &'static str,
+        req: &Request<'_>,
+        reply: R,
+        op: Fut,
+        respond: F,
+    ) where
+        R: ErrorReply,
+        T: Send + 'static,
+        Fut: Future<Output = Result<T, i32>> + Send + 'static,
+        F: FnOnce(R, T) + Send + 'static,
+    {
+        self.spawn_request(name, req, reply, op, respond, false);
+    }
+
+    fn spawn_request<R, T, Fut, F>(
+        &self,
+        name: &'static str,
+        req: &Request<'_>,
+        reply: R,
+        op: Fut,
+        respond: F,
+        cancellable: bool,
+    ) where
+        R: ErrorReply,
+        T: Send + 'static,
+        Fut: Future<Output = Result<T, i32>> + Send + 'static,
+        F: FnOnce(R, T) + Send + 'static,
+    {
+        let unique = req.unique();
+        // 不可取消的操作不登记，FUSE_INTERRUPT 找不到它而被忽略
+        let interrupted = cancellable.then(|| self.pending.register(unique));
+        let pending = Arc::clone(&self.pending);
+        let op_timeout = self.op_timeout;
+        let metrics = Arc::clone(&self.inner.metrics);
+        let (timer, errors) = op_metric_names(name);
+        let started = Instant::now();
+        let hooks = (!self.inner.hooks.is_empty())
+            .then(|| (self.inner.hooks.clone(), Requester::from_request(req)));
+
+        self.runtime.spawn(async move {
+            let op = async move {
+                let Some(limit) = op_timeout else {
+                    return op.await;
+                };
+                if cancellable {
+                    return tokio::time::timeout(limit, op).await.unwrap_or_else(|_| {
+                        warn!("Request {} timed out after {:?}", unique, limit);
+                        Err(EIO)
+                    });
+                }
+                let mut task = tokio::spawn(op);
+                match tokio::time::timeout(limit, &mut task).await {
+                    Ok(result) => result.unwrap_or(Err(EIO)),
+                    Err(_) => {
+                        warn!(
+                            "Request {} timed out after {:?}, finishing it in the background",
+                            unique, limit
+                        );
+                        Err(EIO)
+                    }
+                }
+            };
+            let result = match interrupted {
+                Some(interrupted) => {
+                    let result = tokio::select! {
+                        result = op => Some(result),
+                        Ok(()) = interrupted => None,
+                    };
+                    pending.complete(unique);
+                    result
+                }
+                None => Some(op.await),
+            };
+            if let Some(result) = &result {
+                metrics.time(timer, started.elapsed());
+                if let Err(errno) = result {
+                    metrics.incr(errors, 1);
+                    if let Some((hooks, requester)) = &hooks {
+                        hooks.on_error(requester, name, *errno);
+                    }
+                }
+            }
+
+            match result {
+                Some(Ok(value)) => respond(reply, value),
+                Some(Err(errno)) => reply.error(errno),
+                None => {
+                    debug!("Request {} interrupted", unique);
+                    reply.error(EINTR);
+                }
+            }
+        });
+    }
+}
+
+impl<S: ObjectStore> Filesystem for CosFilesystem<S> {
+    fn init(&mut self, _req: &Request<'_>, config: &mut KernelConfig) -> Result<(), i32> {
+        info!(
+            "Initializing COS filesystem for bucket {} ({})",
+            self.inner.store.bucket(),
+            self.inner.store.region()
+        );
+        self.negotiate_transfer(config);
+
+        // 挂载前必须拿到对象列表，这里是唯一等待网络请求的地方
+        let refresh = self.runtime.block_on(async {
+            match self.op_timeout {
+                Some(limit) => tokio::time::timeout(limit, self.inner.refresh_object_list())
+                    .await
+                    .unwrap_or_else(|_| Err(anyhow!("timed out after {:?}", limit))),
+                None => self.inner.refresh_object_list().await,
+            }
+        });
+        if let Err(e) = refresh {
+            error!("Failed to initialize object list: {:#}", e);
+            self.ready.send_replace(Readiness::Failed(format!("{:#}", e)));
+            return Err(EIO);
+        }
+
+        let inner = Arc::clone(&self.inner);
+        self.prefetch = Some(
+            self.runtime
+                .spawn(background(async move { inner.prefetch().await })),
+        );
+
+        if let Some(interval) = self.inner.cache.options().refresh_interval {
+            let inner = Arc::clone(&self.inner);
+            self.refresher = Some(self.runtime.spawn(background(async move {
+                let mut ticker = tokio::time::interval(interval);
+                ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                // 第一次 tick 立即返回，挂载时刚列出过
+                ticker.tick().await;
+                loop {
+                    ticker.tick().await;
+                    if let Err(e) = inner.refresh_listed_dirs().await {
+                        warn!("Periodic refresh failed: {:#}", e);
+                    }
+                }
+            })));
+        }
+
+        let inner = Arc::clone(&self.inner);
+        self.uploader = Some(self.runtime.spawn(background(async move {
+            let mut ticker = tokio::time::interval(UPLOAD_RETRY_INTERVAL);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                inner.retry_failed_uploads().await;
+            }
+        })));
+
+        self.janitor = Some(spawn_janitor(
+            &self.runtime,
+            Arc::clone(&self.inner.cache),
+            self.inner.cache.options().janitor_interval,
+            {
+                let inner = Arc::clone(&self.inner);
+                move || inner.enforce_memory_budget()
+            },
+        ));
+
+        if let Some(options) = self.statsd_options.clone() {
+            let inner = Arc::clone(&self.inner);
+            self.statsd = Some(spawn_statsd(
+                &self.runtime,
+                Arc::clone(&self.inner.metrics),
+                options,
+                move || inner.gauges(),
+            ));
+        }
+
+        info!("COS filesystem initialized successfully");
+        self.ready.send_replace(Readiness::Ready);
+        Ok(())
+    }
+
+    fn destroy(&mut self) {
+        info!("Destroying COS filesystem");
+        if let Some(janitor) = self.janitor.take() {
+            janitor.abort();
+        }
+        if let Some(prefetch) = self.prefetch.take() {
+            prefetch.abort();
+        }
+        if let Some(refresher) = self.refresher.take() {
+            refresher.abort();
+        }
+        if let Some(uploader) = self.uploader.take() {
+            uploader.abort();
+        }
+        // 卸载前最后重试一次，仍然失败的改动随暂存丢弃
+        self.runtime.block_on(self.inner.retry_failed_uploads());
+        for key in self.inner.failed_uploads.lock().unwrap().keys() {
+            error!("{} was never uploaded, its changes are lost", key);
+        }
+        if let Some(statsd) = self.statsd.take() {
+            statsd.abort();
+        }
+        info!("Prefetch: {}", self.inner.prefetch_stats.summary());
+        info!(
+            "Spool usage at shutdown: {} bytes",
+            self.inner.spool.used_bytes()
+        );
+        for (uid, usage) in self.inner.accounting.usage() {
+            info!(
+                "Usage of uid {}: {} bytes read, {} bytes written, throttled for {} ms",
+                uid, usage.read_bytes, usage.write_bytes, usage.throttled_ms
+            );
+        }
+        let stats = self.inner.cache.get_stats();
+        info!(
+            "Cache stats at shutdown: {} metadata entries, {} cached files, {} disk-full fallbacks",
+            stats.metadata_cache_size, stats.content_cache_size, stats.disk_full_fallbacks
+        );
+
+        // 清理缓存
+        if let Err(e) = self.inner.cache.clear() {
+            warn!("Failed to clear cache: {}", e);
+        }
+
+        info!("COS filesystem destroyed");
+    }
+
+    fn interrupt(&mut self, _req: &Request<'_>, unique: u64) {
+        // 已经答复的请求不需要处理，内核会忽略迟到的中断
+        if self.pending.interrupt(unique) {
+            debug!("Interrupting request {}", unique);
+        }
+    }
+
+    fn lookup(&mut self, req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        info!("Lookup: parent={}, name={}", parent, name.display());
+        // 控制目录在桶不可用时也能访问
+        let control = match parent {
+            ROOT_INODE if name == CONTROL_DIR_NAME => Some(CONTROL_DIR_INODE),
+            CONTROL_DIR_INODE if name == EVENTS_FILE_NAME => Some(EVENTS_INODE),
+            CONTROL_DIR_INODE => {
+                reply.error(ENOENT);
+                return;
+            }
+            _ => None,
+        };
+        if let Some(attr) = control.and_then(|ino| self.inner.control_attr(ino)) {
+            reply.entry(&Duration::from_secs(1), &attr, 0);
+            return;
+        }
+        if let Some(errno) = self.unhealthy() {
+            reply.error(errno);
+            return;
+        }
+
+        let name_str = match name.to_str() {
+            Some(s) => s,
+            None => {
+                reply.error(ENOENT);
+                return;
+            }
+        };
+
+        let mut namespace = self.inner.namespace();
+        let parent_path = match namespace.get_path(parent) {
+            Some(p) => p.to_string(),
+            None => {
+                reply.error(ENOENT);
+                return;
+            }
+        };
+
+        let target_path = if parent_path == "/" {
+            format!("/{}", name_str)
+        } else {
+            format!("{}/{}", parent_path, name_str)
+        };
+
+        debug!(
+            "Lookup: parent={}, name={}, target_path={}",
+            parent, name_str, target_path
+        );
+
+        // 归档对象及其中的路径由归档索引答复
+        if let Some((archive_key, inner_path)) = self.inner.archive_path(&namespace, &target_path) {
+            drop(namespace);
+            let inner = Arc::clone(&self.inner);
+            self.spawn_op(
+                "lookup",
+                req,
+                reply,
+                async move {
+                    inner
+                        .archive_attr(&target_path, &archive_key, &inner_path)
+                        .await
+                },
+                |reply, attr| reply.entry(&Duration::from_secs(1), &attr, 0),
+            );
+            return;
+        }
+
+        // 图片的虚拟文件不在列表中，先于布隆过滤器判断
+        if let Some((image_key, rule)) = self.inner.image_variant(&namespace, &target_path) {
+            drop(namespace);
+            let inner = Arc::clone(&self.inner);
+            self.spawn_op(
+                "lookup",
+                req,
+                reply,
+                async move {
+                    inner
+                        .image_variant_attr(&target_path, &image_key, &rule)
+                        .await
+                },
+                |reply, attr| reply.entry(&Duration::from_secs(1), &attr, 0),
+            );
+            return;
+        }
+
+        namespace.mark_listed(&parent_path);
+
+        // 布隆过滤器判定一定不存在的路径直接返回（shell 补全、IDE 索引的大量探测）
+        if let Some(filter) = &namespace.known_paths {
+            if !filter.might_contain(&target_path) {
+                debug!("Lookup: {} rejected by bloom filter", target_path);
+                drop(namespace);
+                self.lookup_miss(req, target_path, reply);
+                return;
+            }
+        }
+
+        // 检查是否是目录
+        if namespace.is_directory(&target_path) {
+            let ino = namespace.get_or_create_inode(&target_path);
+            let attr = self.inner.create_dir_attr(ino, &target_path);
+            reply.entry(&Duration::from_secs(1), &attr, 0);
+            return;
+        }
+
+        // 检查是否是文件
+        let object_key = target_path.trim_start_matches('/').to_string();
+        if !namespace.contains_object(&object_key) {
+            drop(namespace);
+            self.lookup_miss(req, target_path, reply);
+            return;
+        }
+        let ino = namespace.get_or_create_inode(&target_path);
+        drop(namespace);
+
+        let inner = Arc::clone(&self.inner);
+        self.spawn_op(
+            "lookup",
+            req,
+            reply,
+            async move {
+                match inner.object_attr(&object_key, ino).await {
+                    Ok(attr) => Ok(attr),
+                    Err(e) => {
+                        error!("Failed to get metadata for {}: {:#}", object_key, e);
+                        Err(inner.object_errno(&object_key, &e).await)
+                    }
+                }
+            },
+            |reply, attr| reply.entry(&Duration::from_secs(1), &attr, 0),
+        );
+    }
+
+    fn getattr(&mut self, req: &Request<'_>, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
+        info!("Getattr: ino={}", ino);
+        if let Some(attr) = self.inner.control_attr(ino) {
+            reply.attr(&Duration::from_secs(1), &attr);
+            return;
+        }
+        // 根目录不受影响，仍可读取 user.cosfs.health
+        if ino != ROOT_INODE {
+            if let Some(errno) = self.unhealthy() {
+                reply.error(errno);
+                return;
+            }
+        }
+
+        let mut namespace = self.inner.namespace();
+        let path = match namespace.get_path(ino) {
+            Some(p) => p.to_string(),
+            None => {
+                reply.error(ENOENT);
+                return;
+            }
+        };
+
+        debug!("Getattr: ino={}, path={}", ino, path);
+
+        if let Some((archive_key, inner_path)) = self.inner.archive_path(&namespace, &path) {
+            drop(namespace);
+            let inner = Arc::clone(&self.inner);
+            self.spawn_op(
+                "getattr",
+                req,
+                reply,
+                async move { inner.archive_attr(&path, &archive_key, &inner_path).await },
+                |reply, attr| reply.attr(&Duration::from_secs(1), &attr),
+            );
+            return;
+        }
+
+        if let Some((image_key, rule)) = self.inner.image_variant(&namespace, &path) {
+            drop(namespace);
+            let inner = Arc::clone(&self.inner);
+            self.spawn_op(
+                "getattr",
+                req,
+                reply,
+                async move { inner.image_variant_attr(&path, &image_key, &rule).await },
+                |reply, attr| reply.attr(&Duration::from_secs(1), &attr),
+            );
+            return;
+        }
+
+        if namespace.is_directory(&path) {
+            let attr = self.inner.create_dir_attr(ino, &path);
+            reply.attr(&Duration::from_secs(1), &attr);
+            return;
+        }
+        drop(namespace);
+
+        let inner = Arc::clone(&self.inner);
+        self.spawn_op(
+            "getattr",
+            req,
+            reply,
+            async move {
+                let object_key = path.trim_start_matches('/');
+                match inner.object_attr(object_key, ino).await {
+                    Ok(attr) => Ok(attr),
+                    Err(e) => {
+                        error!("Failed to get metadata for {}: {:#}", object_key, e);
+                        Err(inner.object_errno(object_key, &e).await)
+                    }
+                }
+            },
+            |reply, attr| reply.attr(&Duration::from_secs(1), &attr),
+        );
+    }
+
+    fn opendir(&mut self, _req: &Request<'_>, ino: u64, _flags: i32, reply: ReplyOpen) {
+        if ino != CONTROL_DIR_INODE {
+            if let Some(errno) = self.unhealthy() {
+                reply.error(errno);
+                return;
+            }
+        }
+        let snapshot = match self.dir_snapshot(ino) {
+            Ok(snapshot) => snapshot,
+            Err(errno) => {
+                reply.error(errno);
+                return;
+            }
+        };
+
+        let fh = self.next_dir_handle;
+        self.next_dir_handle += 1;
+        debug!(
+            "Opendir: ino={}, fh={}, {} entries",
+            ino,
+            fh,
+            snapshot.len()
+        );
+        self.dir_handles.insert(fh, snapshot);
+        reply.opened(fh, 0);
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        if ino != CONTROL_DIR_INODE {
+            if let Some(errno) = self.unhealthy() {
+                reply.error(errno);
+                return;
+            }
+        }
+
+        // 没有经过 opendir 的句柄每次重新列出
+        let fresh;
+        let entries = match self.dir_handles.get(&fh) {
+            Some(snapshot) => snapshot,
+            None => match self.dir_snapshot(ino) {
+                Ok(snapshot) => {
+                    fresh = snapshot;
+                    &fresh
+                }
+                Err(errno) => {
+                    reply.error(errno);
+                    return;
+                }
+            },
+        };
+
+        // 发送目录项
+        let start = usize::try_from(offset).unwrap_or(0);
+        for (index, (ino, kind, name)) in entries.iter().enumerate().skip(start) {
+            let next_offset = (index + 1) as i64;
+            if reply.add(*ino, next_offset, *kind, name) {
+                break; // buffer full
+            }
+        }
+
+        reply.ok();
+    }
+
+    fn fsyncdir(
+        &mut self,
+        req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        _datasync: bool,
+        reply: ReplyEmpty,
+    ) {
+        let Some(path) = self.inner.namespace().get_path(ino).map(str::to_string) else {
+            reply.error(ENOENT);
+            return;
+        };
+        debug!("Fsyncdir: ino={}, path={}", ino, path);
+
+        // 数据库、归档工具把 fsyncdir 当作持久化屏障：等目录下进行中的变更都落到 COS 上
+        let inner = Arc::clone(&self.inner);
+        self.spawn_op(
+            "fsyncdir",
+            req,
+            reply,
+            async move {
+                inner.mutations.wait_under(&path).await;
+                Ok(())
+            },
+            |reply, ()| reply.ok(),
+        );
+    }
+
+    fn releasedir(
+        &mut self,
+        _req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
+        _flags: i32,
+        reply: ReplyEmpty,
+    ) {
+        self.dir_handles.remove(&fh);
+        reply.ok();
+    }
+
+    fn open(&mut self, req: &Request<'_>, ino: u64, flags: i32, reply: ReplyOpen) {
+        info!("Open: ino={}, flags={:#o}", ino, flags);
+        if ino == CONTROL_DIR_INODE || ino == EVENTS_INODE {
+            self.open_control(req, ino, flags, reply);
+            return;
+        }
+        if let Some(errno) = self.unhealthy() {
+            reply.error(errno);
+            return;
+        }
+
+        if !self.inner.is_allowed(req) {
+            reply.error(EACCES);
+            return;
+        }
+
+        let mut namespace = self.inner.namespace();
+        let path = match namespace.get_path(ino) {
+            Some(p) => p.to_string(),
+            None => {
+                reply.error(ENOENT);
+                return;
+            }
+        };
+
+        debug!("Open: ino={}, path={}", ino, path);
+
+        // 只允许打开文件，不允许打开目录
+        if self.inner.is_directory(&mut namespace, &path) {
+            reply.error(EPERM);
+            return;
+        }
+
+        // 不能写入时在打开阶段就拒绝，不让应用以为拿到了可写的句柄
+        let write = wants_write(flags);
+        if let Some(errno) = self.inner.write_denied().filter(|_| write) {
+            debug!("Open: rejecting write access to {}", path);
+            reply.error(errno);
+            return;
+        }
+
+        // 挂载时已确认没有读权限，直接拒绝而不是在读取时返回 EIO
+        if flags & O_ACCMODE != O_WRONLY && !self.inner.can_read() {
+            reply.error(EACCES);
+            return;
+        }
+        let is_virtual = self.inner.archive_path(&namespace, &path).is_some()
+            || self.inner.image_variant(&namespace, &path).is_some();
+        drop(namespace);
+        // 归档中的文件和图片的虚拟文件只读
+        if write && is_virtual {
+            reply.error(EROFS);
+            return;
+        }
+        if let Err(errno) = self.inner.hooks.on_open(
+            &Requester::from_request(req),
+            path.trim_start_matches('/'),
+            flags,
+        ) {
+            reply.error(errno);
+            return;
+        }
+
+        let fh = self.next_file_handle;
+        self.next_file_handle += 1;
+
+        // 写入先落在暂存文件中，读取也经过暂存，关闭时上传
+        if write {
+            let inner = Arc::clone(&self.inner);
+            let key = path.trim_start_matches('/').to_string();
+            self.spawn_op(
+                "open",
+                req,
+                reply,
+                async move {
+                    match inner.open_for_write(fh, &key, flags & O_TRUNC != 0).await {
+                        Ok(()) => Ok(()),
+                        Err(e) => {
+                            error!("Failed to open {} for writing: {:#}", key, e);
+                            match io_errno(&e) {
+                                Some(errno) => Err(errno),
+                                None => Err(inner.object_errno(&key, &e).await),
+                            }
+                        }
+                    }
+                },
+                move |reply, ()| reply.opened(fh, 0),
+            );
+            return;
+        }
+
+        // 对象文件按句柄跟踪访问模式，用于选择缓存方式和自适应预读；归档中的文件和图片的虚拟文件不跟踪
+        let options = self.inner.cache.options();
+        if !is_virtual {
+            self.readahead.insert(
+                fh,
+                Readahead::new(
+                    options.readahead_min,
+                    options.readahead_max.max(options.readahead_min),
+                ),
+            );
+        }
+        reply.opened(fh, 0);
+    }
+
+    fn read(
+        &mut self,
+        req: &Request<'_>,
+        ino: u64,
+        fh: u64,
         offset: i64,
-        mut reply: ReplyDirectory,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        // 事件文件不可定位：忽略 offset，按句柄的游标读取新事件，没有新事件时读到 0 字节
+        if ino == EVENTS_INODE {
+            let Some(cursor) = self.event_handles.get_mut(&fh) else {
+                reply.error(EBADF);
+                return;
+            };
+            let (data, next) = self.inner.events.read(*cursor, size as usize);
+            *cursor = next;
+            reply.data(&data);
+            return;
+        }
+        if let Some(errno) = self.unhealthy() {
+            reply.error(errno);
+            return;
+        }
+        // 以写方式打开的句柄读取暂存的内容，没有写入过的部分从 COS 取回
+        if let Some(handle) = self.inner.write_handle(fh) {
+            let inner = Arc::clone(&self.inner);
+            self.spawn_op(
+                "read",
+                req,
+                reply,
+                async move {
+                    let mut handle = handle.lock().await;
+                    match handle
+                        .read(inner.store.as_ref(), offset as u64, size as u64)
+                        .await
+                    {
+                        Ok(data) => {
+                            inner.metrics.incr("read.bytes", data.len() as u64);
+                            Ok(data)
+                        }
+                        Err(e) => {
+                            let key = handle.key().to_string();
+                            drop(handle);
+                            error!("Failed to read object {}: {:#}", key, e);
+                            Err(inner.object_errno(&key, &e).await)
+                        }
+                    }
+                },
+                |reply, data| reply.data(&data),
+            );
+            return;
+        }
+        let mut namespace = self.inner.namespace();
+        let path = match namespace.get_path(ino) {
+            Some(p) => p.to_string(),
+            None => {
+                reply.error(ENOENT);
+                return;
+            }
+        };
+
+        debug!(
+            "Read: ino={}, path={}, offset={}, size={}",
+            ino, path, offset, size
+        );
+
+        if self.inner.is_directory(&mut namespace, &path) {
+            reply.error(EPERM);
+            return;
+        }
+        let archive = self.inner.archive_path(&namespace, &path);
+        let image = self.inner.image_variant(&namespace, &path);
+        drop(namespace);
+        let requester = Requester::from_request(req);
+        let (pattern, readahead) = match (&archive, &image, self.readahead.get_mut(&fh)) {
+            (None, None, Some(state)) => {
+                let range = state.observe(offset as u64, size as u64);
+                (state.pattern(), range)
+            }
+            _ => (AccessPattern::Random, None),
+        };
+        let readahead = readahead.filter(|_| self.inner.cache.options().readahead_max > 0);
+
+        let inner = Arc::clone(&self.inner);
+        self.spawn_op(
+            "read",
+            req,
+            reply,
+            async move {
+                let result = match (&archive, &image) {
+                    (Some((key, inner_path)), _) => inner
+                        .read_archive_file(key, inner_path, offset as u64, size as u64)
+                        .await
+                        .map(ReadData::from),
+                    (None, Some((key, rule))) => inner
+                        .read_image_variant(key, rule, offset as u64, size as u64)
+                        .await,
+                    (None, None) => {
+                        let object_key = path.trim_start_matches('/');
+                        inner
+                            .read_object(object_key, offset as u64, size as u64, pattern)
+                            .await
+                    }
+                };
+                let object_key = match (&archive, &image) {
+                    (Some((key, _)), _) | (None, Some((key, _))) => key.as_str(),
+                    (None, None) => path.trim_start_matches('/'),
+                };
+                match result {
+                    Ok(data) => {
+                        if let Some((start, stop)) = readahead {
+                            let inner = Arc::clone(&inner);
+                            let key = object_key.to_string();
+                            tokio::spawn(background(async move {
+                                inner.readahead(&key, start, stop, pattern).await
+                            }));
+                        }
+                        inner.metrics.incr("read.bytes", data.len() as u64);
+                        inner
+                            .accounting
+                            .record_read(requester.uid, data.len() as u64)
+                            .await;
+                        inner.hooks.on_read(
+                            &requester,
+                            object_key,
+                            offset as u64,
+                            data.len() as u64,
+                        );
+                        Ok(data)
+                    }
+                    Err(e) => {
+                        error!("Failed to read object {}: {:#}", object_key, e);
+                        Err(inner.object_errno(object_key, &e).await)
+                    }
+                }
+            },
+            |reply, data| reply.data(&data),
+        );
+    }
+
+    fn access(&mut self, req: &Request<'_>, ino: u64, mask: i32, reply: ReplyEmpty) {
+        debug!("Access: ino={}, mask={}", ino, mask);
+        if self.inner.control_attr(ino).is_some() {
+            if !self.inner.is_allowed(req) || mask & libc::W_OK != 0 {
+                reply.error(EACCES);
+            } else {
+                reply.ok();
+            }
+            return;
+        }
+        if ino != ROOT_INODE {
+            if let Some(errno) = self.unhealthy() {
+                reply.error(errno);
+                return;
+            }
+        }
+
+        // 检查文件/目录是否存在
+        let mut namespace = self.inner.namespace();
+        let path = match namespace.get_path(ino) {
+            Some(p) => p.to_string(),
+            None => {
+                reply.error(ENOENT);
+                return;
+            }
+        };
+        let is_dir = self.inner.is_directory(&mut namespace, &path);
+        // 归档中的文件沿用归档对象的属主与权限，图片的虚拟文件沿用源图片
+        let archive_key = self
+            .inner
+            .archive_path(&namespace, &path)
+            .or_else(|| self.inner.image_variant(&namespace, &path))
+            .map(|(key, _)| key);
+        drop(namespace);
+
+        let Some(credentials) = self.inner.id_map.credentials(req.uid(), req.gid()) else {
+            debug!("Rejecting request from uid={} gid={}", req.uid(), req.gid());
+            reply.error(EACCES);
+            return;
+        };
+        if mask == libc::F_OK {
+            reply.ok();
+            return;
+        }
+        // 与 open 一致：不能写入时先于权限位返回
+        if mask & libc::W_OK != 0 {
+            if let Some(errno) = self.inner.write_denied() {
+                reply.error(errno);
+                return;
+            }
+            if archive_key.is_some() {
+                reply.error(EROFS);
+                return;
+            }
+        }
+
+        // 按 getattr 报告的属主与权限位判断，结果与 ls -l 看到的一致
+        let check = move |owner: Ownership| {
+            if owner.permits(credentials.uid, credentials.gid, mask, is_dir) {
+                Ok(())
+            } else {
+                Err(EACCES)
+            }
+        };
+        if is_dir {
+            match check(self.inner.dir_ownership(&path)) {
+                Ok(()) => reply.ok(),
+                Err(errno) => reply.error(errno),
+            }
+            return;
+        }
+
+        let inner = Arc::clone(&self.inner);
+        self.spawn_op(
+            "access",
+            req,
+            reply,
+            async move {
+                let object_key = archive_key
+                    .as_deref()
+                    .unwrap_or(path.trim_start_matches('/'));
+                check(inner.file_ownership(object_key).await)
+            },
+            |reply, ()| reply.ok(),
+        );
+    }
+
+    fn listxattr(&mut self, req: &Request<'_>, ino: u64, size: u32, reply: ReplyXattr) {
+        let mut names: Vec<&str> = self.inner.system_xattrs.listed().to_vec();
+        let mut namespace = self.inner.namespace();
+        if namespace.xattr_free.contains(&ino) || self.inner.control_attr(ino).is_some() {
+            drop(namespace);
+            reply_xattr(reply, size, &xattr_name_list(&names));
+            return;
+        }
+        let path = match namespace.get_path(ino) {
+            Some(p) => p.to_string(),
+            None => {
+                reply.error(ENOENT);
+                return;
+            }
+        };
+
+        // 根目录提供挂载状态；其他目录没有扩展属性；文件提供只读的服务端加密方式、校验值、访问地址、
+        // 调试信息以及对象上的用户自定义元数据
+        let is_virtual = self.inner.archive_path(&namespace, &path).is_some()
+            || self.inner.image_variant(&namespace, &path).is_some();
+        if ino == ROOT_INODE {
+            names.extend_from_slice(&[HEALTH_XATTR, USAGE_XATTR, MEMORY_XATTR]);
+            if self.inner.last_errors.latest().is_some() {
+                names.push(LAST_ERROR_XATTR);
+            }
+        } else if !is_virtual && !namespace.is_directory(&path) {
+            names.extend_from_slice(&[SSE_XATTR, CRC64_XATTR, OBJECT_URL_XATTR, INFO_XATTR]);
+            if self.inner.store.has_credentials() {
+                names.push(PRESIGNED_URL_XATTR);
+            }
+            if self
+                .inner
+                .last_errors
+                .get(path.trim_start_matches('/'))
+                .is_some()
+            {
+                names.push(LAST_ERROR_XATTR);
+            }
+            drop(namespace);
+
+            // 用户自定义元数据只在 HEAD 响应中，列出时取对象当前的键
+            let mut names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+            let object_key = path.trim_start_matches('/').to_string();
+            let inner = Arc::clone(&self.inner);
+            self.spawn_op(
+                "listxattr",
+                req,
+                reply,
+                async move {
+                    match inner.head_resolver.resolve(&object_key).await {
+                        Ok(meta) => {
+                            names.extend(
+                                meta.details
+                                    .user_meta
+                                    .keys()
+                                    .map(|key| format!("{}{}", USER_META_XATTR_PREFIX, key)),
+                            );
+                            Ok(names)
+                        }
+                        Err(e) => {
+                            error!("Failed to get metadata for {}: {:#}", object_key, e);
+                            Err(inner.object_errno(&object_key, &e).await)
+                        }
+                    }
+                },
+                move |reply, names| reply_xattr(reply, size, &xattr_name_list(&names)),
+            );
+            return;
+        } else {
+            namespace.xattr_free.insert(ino);
+        }
+        drop(namespace);
+
+        reply_xattr(reply, size, &xattr_name_list(&names));
+    }
+
+    fn getxattr(
+        &mut self,
+        req: &Request<'_>,
+        ino: u64,
+        name: &OsStr,
+        size: u32,
+        reply: ReplyXattr,
+    ) {
+        // 内核每次访问文件都会探测 security.capability 等属性：不加锁、不记录日志、不访问 COS
+        match self.inner.system_xattrs.lookup(name) {
+            SystemXattr::NotSystem => {}
+            SystemXattr::Absent => {
+                reply.error(ENOATTR);
+                return;
+            }
+            SystemXattr::Unsupported => {
+                reply.error(EOPNOTSUPP);
+                return;
+            }
+            SystemXattr::Value(value) => {
+                reply_xattr(reply, size, value.as_bytes());
+                return;
+            }
+        }
+        if self.inner.control_attr(ino).is_some() {
+            reply.error(ENOATTR);
+            return;
+        }
+
+        let mut namespace = self.inner.namespace();
+        let path = match namespace.get_path(ino) {
+            Some(p) => p.to_string(),
+            None => {
+                reply.error(ENOENT);
+                return;
+            }
+        };
+
+        if ino == ROOT_INODE && name == HEALTH_XATTR {
+            let state = self.inner.health.state().to_string();
+            reply_xattr(reply, size, state.as_bytes());
+            return;
+        }
+        if ino == ROOT_INODE && name == MEMORY_XATTR {
+            let report = self.inner.memory.report(self.inner.memory_usage());
+            match serde_json::to_string(&report) {
+                Ok(report) => reply_xattr(reply, size, report.as_bytes()),
+                Err(_) => reply.error(EIO),
+            }
+            return;
+        }
+        if ino == ROOT_INODE && name == USAGE_XATTR {
+            match serde_json::to_string(&self.inner.accounting.usage()) {
+                Ok(usage) => reply_xattr(reply, size, usage.as_bytes()),
+                Err(_) => reply.error(EIO),
+            }
+            return;
+        }
+        // 失败记录在本地，桶不可用时也能读取
+        if name == LAST_ERROR_XATTR {
+            let last_error = if ino == ROOT_INODE {
+                self.inner.last_errors.latest()
+            } else {
+                self.inner.last_errors.get(path.trim_start_matches('/'))
+            };
+            match last_error {
+                Some(message) => reply_xattr(reply, size, message.as_bytes()),
+                None => reply.error(ENOATTR),
+            }
+            return;
+        }
+        // 归档对象及其中的文件、图片的虚拟文件没有扩展属性
+        if self.inner.archive_path(&namespace, &path).is_some()
+            || self.inner.image_variant(&namespace, &path).is_some()
+            || namespace.is_directory(&path)
+        {
+            reply.error(ENOATTR);
+            return;
+        }
+        drop(namespace);
+        if let Some(errno) = self.unhealthy() {
+            reply.error(errno);
+            return;
+        }
+
+        let object_key = path.trim_start_matches('/');
+        let client = &self.inner.store;
+        if name == OBJECT_URL_XATTR {
+            reply_xattr(reply, size, client.object_url(object_key).as_bytes());
+            return;
+        }
+        if name == PRESIGNED_URL_XATTR {
+            match client.presign_url(object_key, self.inner.presign_expires) {
+                Ok(url) => reply_xattr(reply, size, url.as_bytes()),
+                Err(_) => reply.error(ENOATTR),
+            }
+            return;
+        }
+        if name == INFO_XATTR {
+            let inner = Arc::clone(&self.inner);
+            let object_key = object_key.to_string();
+            self.spawn_op(
+                "getxattr",
+                req,
+                reply,
+                async move {
+                    match inner.object_info(&object_key).await {
+                        Ok(info) => Ok(info),
+                        Err(e) => {
+                            error!("Failed to get object info for {}: {:#}", object_key, e);
+                            Err(inner.health.errno(&e).await)
+                        }
+                    }
+                },
+                move |reply, value| reply_xattr(reply, size, value.as_bytes()),
+            );
+            return;
+        }
+        if let Some(meta_key) = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(USER_META_XATTR_PREFIX))
+        {
+            let inner = Arc::clone(&self.inner);
+            let object_key = object_key.to_string();
+            let meta_key = meta_key.to_string();
+            self.spawn_op(
+                "getxattr",
+                req,
+                reply,
+                async move {
+                    match inner.head_resolver.resolve(&object_key).await {
+                        Ok(meta) => meta
+                            .details
+                            .user_meta
+                            .get(&meta_key)
+                            .cloned()
+                            .ok_or(ENOATTR),
+                        Err(e) => {
+                            error!("Failed to get metadata for {}: {:#}", object_key, e);
+                            Err(inner.object_errno(&object_key, &e).await)
+                        }
+                    }
+                },
+                move |reply, value| reply_xattr(reply, size, value.as_bytes()),
+            );
+            return;
+        }
+        let crc64 = name == CRC64_XATTR;
+        if name != SSE_XATTR && !crc64 {
+            reply.error(ENOATTR);
+            return;
+        }
+
+        let inner = Arc::clone(&self.inner);
+        let object_key = object_key.to_string();
+        self.spawn_op(
+            "getxattr",
+            req,
+            reply,
+            async move {
+                // 列表结果不带加密信息和校验值，直接 HEAD 取对象当前的值
+                match inner.head_resolver.resolve(&object_key).await {
+                    Ok(meta) if crc64 => meta.details.crc64.ok_or(ENOATTR),
+                    Ok(meta) => Ok(meta
+                        .server_side_encryption
+                        .unwrap_or_else(|| "none".to_string())),
+                    Err(e) => {
+                        error!("Failed to get metadata for {}: {:#}", object_key, e);
+                        Err(inner.object_errno(&object_key, &e).await)
+                    }
+                }
+            },
+            move |reply, value| reply_xattr(reply, size, value.as_bytes()),
+        );
+    }
+
+    fn setxattr(
+        &mut self,
+        req: &Request<'_>,
+        ino: u64,
+        name: &OsStr,
+        value: &[u8],
+        flags: i32,
+        position: u32,
+        reply: ReplyEmpty,
+    ) {
+        info!("Setxattr: ino={}, name={:?}", ino, name);
+        if name == REFRESH_XATTR {
+            self.refresh_on_demand(req, ino, reply);
+            return;
+        }
+        let (object_key, meta_key) = match self.user_meta_target(req, ino, name) {
+            Ok(target) => target,
+            Err(errno) => {
+                reply.error(errno);
+                return;
+            }
+        };
+        // 值以 HTTP 头发送：须为不含控制字符的 UTF-8
+        let value = match std::str::from_utf8(value) {
+            Ok(value) if position == 0 && !value.chars().any(|c| c.is_control()) => {
+                value.to_string()
+            }
+            _ => {
+                reply.error(EINVAL);
+                return;
+            }
+        };
+
+        let inner = Arc::clone(&self.inner);
+        let requester = Requester::from_request(req);
+        self.spawn_mutation(
+            "setxattr",
+            req,
+            reply,
+            async move {
+                inner
+                    .update_user_meta(requester, &object_key, |user_meta| {
+                        let exists = user_meta.contains_key(&meta_key);
+                        if flags & XATTR_CREATE != 0 && exists {
+                            return Err(EEXIST);
+                        }
+                        if flags & XATTR_REPLACE != 0 && !exists {
+                            return Err(ENOATTR);
+                        }
+                        user_meta.insert(meta_key, value);
+                        Ok(())
+                    })
+                    .await
+            },
+            |reply, ()| reply.ok(),
+        );
+    }
+
+    fn removexattr(&mut self, req: &Request<'_>, ino: u64, name: &OsStr, reply: ReplyEmpty) {
+        info!("Removexattr: ino={}, name={:?}", ino, name);
+        let (object_key, meta_key) = match self.user_meta_target(req, ino, name) {
+            Ok(target) => target,
+            Err(errno) => {
+                reply.error(errno);
+                return;
+            }
+        };
+
+        let inner = Arc::clone(&self.inner);
+        let requester = Requester::from_request(req);
+        self.spawn_mutation(
+            "removexattr",
+            req,
+            reply,
+            async move {
+                inner
+                    .update_user_meta(requester, &object_key, |user_meta| {
+                        user_meta.remove(&meta_key).map(|_| ()).ok_or(ENOATTR)
+                    })
+                    .await
+            },
+            |reply, ()| reply.ok(),
+        );
+    }
+
+    fn release(
+        &mut self,
+        req: &Request<'_>,
+        ino: u64,
+        fh: u64,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        _flush: bool,
+        reply: ReplyEmpty,
+    ) {
+        if ino == EVENTS_INODE {
+            self.event_handles.remove(&fh);
+        }
+        self.readahead.remove(&fh);
+        let Some((key, handle)) = self.inner.write_handles.lock().unwrap().remove(&fh) else {
+            reply.ok();
+            return;
+        };
+        // 打开期间文件已被删除：不再上传，暂存随句柄丢弃
+        let Some(key) = key else {
+            reply.ok();
+            return;
+        };
+
+        let inner = Arc::clone(&self.inner);
+        let requester = Requester::from_request(req);
+        self.spawn_mutation(
+            "release",
+            req,
+            reply,
+            async move { inner.release_write_handle(&requester, &key, handle).await },
+            |reply, ()| reply.ok(),
+        );
+    }
+
+    fn create(
+        &mut self,
+        req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        mode: u32,
+        umask: u32,
+        flags: i32,
+        reply: ReplyCreate,
+    ) {
+        info!("Create: parent={}, name={}", parent, name.display());
+        let (path, credentials) = match self.entry_to_change(req, parent, name) {
+            Ok(entry) => entry,
+            Err(errno) => {
+                reply.error(errno);
+                return;
+            }
+        };
+
+        let key = path.trim_start_matches('/').to_string();
+        if let Err(errno) = self
+            .inner
+            .hooks
+            .on_open(&Requester::from_request(req), &key, flags)
+        {
+            reply.error(errno);
+            return;
+        }
+        let file = match self.inner.spool.create() {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Failed to create spool file for {}: {}", key, e);
+                reply.error(e.raw_os_error().unwrap_or(EIO));
+                return;
+            }
+        };
+        // 属主与权限只在 `--s3fs-compat` 时记录到对象上，报告的属性仍按前缀规则
+        let owner = Ownership {
+            uid: credentials.uid,
+            gid: credentials.gid,
+            perm: (mode & !umask & 0o7777) as u16,
+        };
+        let handle = WriteHandle::create(key.clone(), file, owner);
+        let meta = handle.meta();
+
+        let fh = self.next_file_handle;
+        self.next_file_handle += 1;
+        let ino = self.inner.namespace().insert_object(key);
+        self.inner.register_write_handle(
+            fh,
+            Arc::new(tokio::sync::Mutex::new(handle)),
+            meta.clone(),
+        );
+        let attr = self.inner.meta_to_attr(&meta, ino, None);
+        reply.created(&Duration::from_secs(1), &attr, 0, fh, 0);
+    }
+
+    fn mkdir(
+        &mut self,
+        req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        mode: u32,
+        umask: u32,
+        reply: ReplyEntry,
     ) {
-        let path = match self.get_path(ino) {
-            Some(p) => p.clone(),
-            None => {
-                reply.error(ENOENT);
+        info!("Mkdir: parent={}, name={}", parent, name.display());
+        let (path, credentials) = match self.entry_to_change(req, parent, name) {
+            Ok(entry) => entry,
+            Err(errno) => {
+                reply.error(errno);
                 return;
             }
         };
-
-        if !self.is_directory(&path) {
-            reply.error(ENOTDIR);
+        if let Err(errno) = self.inner.check_mkdir(&path) {
+            reply.error(errno);
             return;
         }
 
-        // --- 修复点：避免在 or_insert_with 中捕获 self ---
-        let entries = if let Some(cached) = self.dir_cache.get(&path) {
-            cached.clone()
-        } else {
-            let listed = self.list_directory(&path);
-            self.dir_cache.insert(path.clone(), listed.clone());
-            listed
+        // 属主与权限只在 `--s3fs-compat` 时记录到标记对象上，报告的属性仍按前缀规则
+        let owner = Ownership {
+            uid: credentials.uid,
+            gid: credentials.gid,
+            perm: (mode & !umask & 0o7777) as u16,
         };
+        let inner = Arc::clone(&self.inner);
+        self.spawn_mutation(
+            "mkdir",
+            req,
+            reply,
+            async move {
+                let ino = inner.make_dir(&path, owner).await?;
+                Ok(inner.create_dir_attr(ino, &path))
+            },
+            |reply, attr| reply.entry(&Duration::from_secs(1), &attr, 0),
+        );
+    }
 
-        // 构建完整 entry 列表
-        let mut all_entries = Vec::with_capacity(entries.len() + 2);
-
-        // "."
-        all_entries.push((ino, FileType::Directory, ".".to_string()));
-
-        // ".."
-        let parent_ino = if path == "/" {
-            ino
-        } else {
-            let parent_path = Path::new(&path).parent().unwrap_or(Path::new("/"));
-            let parent_path_str = parent_path.to_string_lossy().to_string();
-            *self
-                .path_to_inode
-                .get(&parent_path_str)
-                .unwrap_or(&ROOT_INODE)
+    fn unlink(&mut self, req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        info!("Unlink: parent={}, name={}", parent, name.display());
+        let path = match self.entry_to_change(req, parent, name) {
+            Ok((path, _)) => path,
+            Err(errno) => {
+                reply.error(errno);
+                return;
+            }
         };
-        all_entries.push((parent_ino, FileType::Directory, "..".to_string()));
+        if let Err(errno) = self.inner.check_unlink(&path) {
+            reply.error(errno);
+            return;
+        }
 
-        // 真实条目
-        all_entries.extend(entries.into_iter().map(|e| (e.ino, e.file_type, e.name)));
+        let key = path.trim_start_matches('/').to_string();
+        let inner = Arc::clone(&self.inner);
+        self.spawn_mutation(
+            "unlink",
+            req,
+            reply,
+            async move { inner.unlink_file(&key).await },
+            |reply, ()| reply.ok(),
+        );
+    }
 
-        // 发送目录项
-        for (index, (ino, kind, name)) in all_entries.into_iter().enumerate() {
-            let next_offset = (index + 1) as i64;
-            if (index as i64) >= offset {
-                if reply.add(ino, next_offset, kind, &name) {
-                    break; // buffer full
-                }
+    fn rename(
+        &mut self,
+        req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        newparent: u64,
+        newname: &OsStr,
+        flags: u32,
+        reply: ReplyEmpty,
+    ) {
+        info!(
+            "Rename: parent={}, name={}, newparent={}, newname={}",
+            parent,
+            name.display(),
+            newparent,
+            newname.display()
+        );
+        let entries = self
+            .entry_to_change(req, parent, name)
+            .and_then(|(from, _)| Ok((from, self.entry_to_change(req, newparent, newname)?.0)));
+        let (from, to) = match entries {
+            Ok(entries) => entries,
+            Err(errno) => {
+                reply.error(errno);
+                return;
+            }
+        };
+        let is_dir = match self.inner.check_rename(&from, &to, flags) {
+            Ok(is_dir) => is_dir,
+            Err(errno) => {
+                reply.error(errno);
+                return;
             }
+        };
+        if from == to {
+            reply.ok();
+            return;
         }
 
-        reply.ok();
+        let inner = Arc::clone(&self.inner);
+        self.spawn_mutation(
+            "rename",
+            req,
+            reply,
+            async move { inner.rename_path(&from, &to, is_dir).await },
+            |reply, ()| reply.ok(),
+        );
     }
 
-    fn open(&mut self, _req: &Request<'_>, ino: u64, _flags: i32, reply: ReplyOpen) {
-        info!("Open: ino={}", ino);
-
-        let path = match self.get_path(ino) {
-            Some(p) => p,
-            None => {
-                reply.error(ENOENT);
+    fn rmdir(&mut self, req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        info!("Rmdir: parent={}, name={}", parent, name.display());
+        let path = match self.entry_to_change(req, parent, name) {
+            Ok((path, _)) => path,
+            Err(errno) => {
+                reply.error(errno);
                 return;
             }
         };
-
-        debug!("Open: ino={}, path={}", ino, path);
-
-        // 只允许打开文件，不允许打开目录
-        if self.is_directory(path) {
-            reply.error(EPERM);
+        if let Err(errno) = self.inner.check_rmdir(&path) {
+            reply.error(errno);
             return;
         }
 
-        reply.opened(0, 0);
+        let inner = Arc::clone(&self.inner);
+        self.spawn_mutation(
+            "rmdir",
+            req,
+            reply,
+            async move { inner.remove_dir(&path).await },
+            |reply, ()| reply.ok(),
+        );
     }
 
-    fn read(
+    fn write(
         &mut self,
-        _req: &Request<'_>,
-        ino: u64,
-        _fh: u64,
+        req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
         offset: i64,
-        size: u32,
+        data: &[u8],
+        _write_flags: u32,
         _flags: i32,
         _lock_owner: Option<u64>,
-        reply: ReplyData,
+        reply: ReplyWrite,
     ) {
-        let path = match self.get_path(ino) {
-            Some(p) => p,
-            None => {
-                reply.error(ENOENT);
-                return;
-            }
+        let Some(handle) = self.inner.write_handle(fh) else {
+            reply.error(EBADF);
+            return;
+        };
+        let Ok(offset) = u64::try_from(offset) else {
+            reply.error(EINVAL);
+            return;
         };
 
-        debug!(
-            "Read: ino={}, path={}, offset={}, size={}",
-            ino, path, offset, size
+        let data = data.to_vec();
+        let inner = Arc::clone(&self.inner);
+        let requester = Requester::from_request(req);
+        self.spawn_mutation(
+            "write",
+            req,
+            reply,
+            async move {
+                let len = data.len() as u64;
+                let meta = {
+                    let mut handle = handle.lock().await;
+                    if let Err(e) = handle.write(offset, &data) {
+                        error!("Failed to write to {}: {}", handle.key(), e);
+                        return Err(e.raw_os_error().unwrap_or(EIO));
+                    }
+                    // 已删除的文件不再登记，同名的新文件不受影响
+                    (!handle.is_unlinked()).then(|| handle.meta())
+                };
+                if let Some(meta) = meta {
+                    inner.staged.update(meta);
+                }
+                inner.metrics.incr("write.bytes", len);
+                inner.accounting.record_write(requester.uid, len).await;
+                Ok(len as u32)
+            },
+            |reply, written| reply.written(written),
+        );
+    }
+
+    fn flush(
+        &mut self,
+        req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
+        _lock_owner: u64,
+        reply: ReplyEmpty,
+    ) {
+        // 每次 close() 都会 flush：在这里上传，上传失败时 close() 返回错误
+        let Some(handle) = self.inner.write_handle(fh) else {
+            reply.ok();
+            return;
+        };
+        let inner = Arc::clone(&self.inner);
+        let requester = Requester::from_request(req);
+        self.spawn_mutation(
+            "flush",
+            req,
+            reply,
+            async move { inner.upload(&requester, &handle).await },
+            |reply, ()| reply.ok(),
+        );
+    }
+
+    fn fsync(&mut self, req: &Request<'_>, _ino: u64, fh: u64, _datasync: bool, reply: ReplyEmpty) {
+        let Some(handle) = self.inner.write_handle(fh) else {
+            reply.ok();
+            return;
+        };
+        let inner = Arc::clone(&self.inner);
+        let requester = Requester::from_request(req);
+        self.spawn_mutation(
+            "fsync",
+            req,
+            reply,
+            async move { inner.upload(&requester, &handle).await },
+            |reply, ()| reply.ok(),
         );
+    }
 
-        if self.is_directory(path) {
+    fn setattr(
+        &mut self,
+        req: &Request<'_>,
+        ino: u64,
+        mode: Option<u32>,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        size: Option<u64>,
+        _atime: Option<TimeOrNow>,
+        mtime: Option<TimeOrNow>,
+        _ctime: Option<SystemTime>,
+        fh: Option<u64>,
+        _crtime: Option<SystemTime>,
+        _chgtime: Option<SystemTime>,
+        _bkuptime: Option<SystemTime>,
+        _flags: Option<u32>,
+        reply: ReplyAttr,
+    ) {
+        info!("Setattr: ino={}, size={:?}, mtime={:?}", ino, size, mtime);
+        if self.inner.control_attr(ino).is_some() {
+            reply.error(EPERM);
+            return;
+        }
+        if let Some(errno) = self.unhealthy() {
+            reply.error(errno);
+            return;
+        }
+        if !self.inner.is_allowed(req) {
+            reply.error(EACCES);
+            return;
+        }
+        // 属主与权限位由前缀规则决定，不能逐个文件修改
+        if mode.is_some() || uid.is_some() || gid.is_some() {
             reply.error(EPERM);
             return;
         }
 
-        let object_key = path.trim_start_matches('/');
+        let mut namespace = self.inner.namespace();
+        let Some(path) = namespace.get_path(ino).map(str::to_string) else {
+            reply.error(ENOENT);
+            return;
+        };
+        // 目录没有可以修改的属性，atime 只在本地记录
+        if self.inner.is_directory(&mut namespace, &path) {
+            if size.is_some() {
+                reply.error(EISDIR);
+            } else {
+                reply.attr(
+                    &Duration::from_secs(1),
+                    &self.inner.create_dir_attr(ino, &path),
+                );
+            }
+            return;
+        }
+        let is_virtual = self.inner.archive_path(&namespace, &path).is_some()
+            || self.inner.image_variant(&namespace, &path).is_some();
+        drop(namespace);
+        if is_virtual {
+            reply.error(EROFS);
+            return;
+        }
+        if let Some(errno) = self
+            .inner
+            .write_denied()
+            .filter(|_| size.is_some() || mtime.is_some())
+        {
+            reply.error(errno);
+            return;
+        }
 
-        let rt = Arc::clone(&self.runtime);
+        let key = path.trim_start_matches('/').to_string();
+        let handle = fh
+            .and_then(|fh| self.inner.write_handle(fh))
+            .or_else(|| self.inner.write_handle_for(&key));
+        let mtime = mtime.map(|mtime| match mtime {
+            TimeOrNow::SpecificTime(time) => time,
+            TimeOrNow::Now => SystemTime::now(),
+        });
+        let inner = Arc::clone(&self.inner);
+        let requester = Requester::from_request(req);
+        self.spawn_mutation(
+            "setattr",
+            req,
+            reply,
+            async move {
+                inner
+                    .set_attr(&requester, &key, handle, size, mtime)
+                    .await?;
+                match inner.object_attr(&key, ino).await {
+                    Ok(attr) => Ok(attr),
+                    Err(e) => {
+                        error!("Failed to get metadata for {}: {:#}", key, e);
+                        Err(inner.object_errno(&key, &e).await)
+                    }
+                }
+            },
+            |reply, attr| reply.attr(&Duration::from_secs(1), &attr),
+        );
+    }
 
-        match rt.block_on(self.get_object_content(object_key)) {
-            Ok(content) => {
-                let start = offset as usize;
-                let end = std::cmp::min(start + size as usize, content.len());
+    fn poll(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        fh: u64,
+        ph: PollHandle,
+        _events: u32,
+        flags: u32,
+        reply: ReplyPoll,
+    ) {
+        // 普通文件总是可读写。不能答复 ENOSYS：内核会因此对整个挂载停用 poll
+        if ino != EVENTS_INODE {
+            reply.poll((POLLIN | POLLOUT | POLLRDNORM | POLLWRNORM) as u32);
+            return;
+        }
+        let Some(cursor) = self.event_handles.get(&fh) else {
+            reply.error(EBADF);
+            return;
+        };
+        // 没有新事件时登记句柄，下一个事件发生时由内核唤醒 select()/poll()
+        let handle = (flags & FUSE_POLL_SCHEDULE_NOTIFY != 0).then_some(ph);
+        if self.inner.events.poll(*cursor, handle) {
+            reply.poll((POLLIN | POLLRDNORM) as u32);
+        } else {
+            reply.poll(0);
+        }
+    }
+}
 
-                if start >= content.len() {
-                    reply.data(&[]);
-                } else {
-                    reply.data(&content[start..end]);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::CosFilesystemBuilder;
+    use crate::compression::CacheCompression;
+    use crate::local_backend::LocalBackend;
+    use crate::mock_backend::{Fault, MockBackend, Op};
+    use crate::spool::SpoolOptions;
+    use reqwest::StatusCode;
+    use std::cell::Cell;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const REQUESTER: Requester = Requester {
+        uid: 1000,
+        gid: 1000,
+        pid: 1,
+    };
+
+    const OWNER: Ownership = Ownership {
+        uid: 1000,
+        gid: 1000,
+        perm: 0o644,
+    };
+
+    /// 不经过内核驱动文件系统：按各个 FUSE 回调同样的方式解析路径，再调用同样的内部操作
+    struct TestMount<S: ObjectStore> {
+        fs: CosFilesystem<S>,
+        next_fh: Cell<u64>,
+        _dir: TempDir,
+    }
+
+    fn mount<S: ObjectStore>(store: S) -> TestMount<S> {
+        mount_with(store, |builder| builder)
+    }
+
+    /// 由 `configure` 修改挂载选项
+    fn mount_with<S: ObjectStore>(
+        store: S,
+        configure: impl FnOnce(CosFilesystemBuilder) -> CosFilesystemBuilder,
+    ) -> TestMount<S> {
+        let dir = TempDir::new().unwrap();
+        let builder = CosFilesystemBuilder::new("demo", "")
+            .cache_dir(dir.path().join("cache"))
+            .spool_options(SpoolOptions {
+                dir: dir.path().join("spool"),
+                max_size: None,
+                fsync: Default::default(),
+            });
+        let fs = configure(builder).build_with_store(store).unwrap();
+        fs.runtime.block_on(fs.inner.refresh_object_list()).unwrap();
+        TestMount {
+            fs,
+            next_fh: Cell::new(1),
+            _dir: dir,
+        }
+    }
+
+    /// 以临时目录为桶挂载，`files` 为初始的对象
+    fn mount_local(bucket: &TempDir, files: &[(&str, &str)]) -> TestMount<LocalBackend> {
+        for (key, body) in files {
+            let path = bucket.path().join(key);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, body).unwrap();
+        }
+        mount(LocalBackend::new("demo", bucket.path()).unwrap())
+    }
+
+    impl<S: ObjectStore> TestMount<S> {
+        fn inner(&self) -> &Inner<S> {
+            &self.fs.inner
+        }
+
+        fn block_on<F: Future>(&self, future: F) -> F::Output {
+            self.fs.runtime.block_on(future)
+        }
+
+        fn lookup(&self, path: &str) -> Result<FileAttr, i32> {
+            let inner = self.inner();
+            let key = path.trim_start_matches('/');
+            let ino = {
+                let mut namespace = inner.namespace();
+                if namespace.is_directory(path) {
+                    let ino = namespace.get_or_create_inode(path);
+                    return Ok(inner.create_dir_attr(ino, path));
+                }
+                if !namespace.contains_object(key) {
+                    return Err(ENOENT);
+                }
+                namespace.get_or_create_inode(path)
+            };
+            self.block_on(async {
+                match inner.object_attr(key, ino).await {
+                    Ok(attr) => Ok(attr),
+                    Err(e) => Err(inner.object_errno(key, &e).await),
+                }
+            })
+        }
+
+        /// 目录中的名字（不含 `.` 与 `..`），按名字排序
+        fn readdir(&self, path: &str) -> Result<Vec<String>, i32> {
+            let ino = self.inner().namespace().get_or_create_inode(path);
+            let mut names: Vec<String> = self
+                .fs
+                .dir_snapshot(ino)?
+                .into_iter()
+                .skip(2)
+                .map(|(_, _, name)| name)
+                .collect();
+            names.sort();
+            Ok(names)
+        }
+
+        fn read(&self, path: &str, offset: u64, size: u64) -> Result<Vec<u8>, i32> {
+            let inner = self.inner();
+            let key = path.trim_start_matches('/');
+            self.block_on(async {
+                match inner
+                    .read_object(key, offset, size, AccessPattern::Random)
+                    .await
+                {
+                    Ok(data) => Ok(data.to_vec()),
+                    Err(e) => Err(inner.object_errno(key, &e).await),
+                }
+            })
+        }
+
+        fn next_fh(&self) -> u64 {
+            let fh = self.next_fh.get();
+            self.next_fh.set(fh + 1);
+            fh
+        }
+
+        fn handle(&self, fh: u64) -> SharedWriteHandle {
+            self.inner().write_handle(fh).expect("not open for writing")
+        }
+
+        fn create(&self, path: &str) -> u64 {
+            let inner = self.inner();
+            let key = path.trim_start_matches('/').to_string();
+            let handle = WriteHandle::create(key.clone(), inner.spool.create().unwrap(), OWNER);
+            let meta = handle.meta();
+            let fh = self.next_fh();
+            inner.namespace().insert_object(key);
+            inner.register_write_handle(fh, Arc::new(tokio::sync::Mutex::new(handle)), meta);
+            fh
+        }
+
+        fn open_write(&self, path: &str, truncate: bool) -> Result<u64, i32> {
+            let inner = self.inner();
+            let key = path.trim_start_matches('/');
+            let fh = self.next_fh();
+            self.block_on(async {
+                match inner.open_for_write(fh, key, truncate).await {
+                    Ok(()) => Ok(fh),
+                    Err(e) => Err(inner.object_errno(key, &e).await),
                 }
+            })
+        }
+
+        fn write(&self, fh: u64, offset: u64, data: &[u8]) {
+            let handle = self.handle(fh);
+            let meta = self.block_on(async {
+                let mut handle = handle.lock().await;
+                handle.write(offset, data).unwrap();
+                (!handle.is_unlinked()).then(|| handle.meta())
+            });
+            if let Some(meta) = meta {
+                self.inner().staged.update(meta);
             }
-            Err(e) => {
-                error!("Failed to read object {}: {}", object_key, e);
-                reply.error(EIO);
+        }
+
+        /// 以写方式打开的句柄读取暂存的内容
+        fn read_handle(&self, fh: u64, offset: u64, size: u64) -> Vec<u8> {
+            let handle = self.handle(fh);
+            let store = self.inner().store.as_ref();
+            self.block_on(async { handle.lock().await.read(store, offset, size).await })
+                .unwrap()
+        }
+
+        fn flush(&self, fh: u64) -> Result<(), i32> {
+            let handle = self.handle(fh);
+            self.block_on(self.inner().upload(&REQUESTER, &handle))
+        }
+
+        fn release(&self, fh: u64) -> Result<(), i32> {
+            let inner = self.inner();
+            let Some((key, handle)) = inner.write_handles.lock().unwrap().remove(&fh) else {
+                return Ok(());
+            };
+            let Some(key) = key else {
+                return Ok(());
+            };
+            self.block_on(inner.release_write_handle(&REQUESTER, &key, handle))
+        }
+
+        fn mkdir(&self, path: &str) -> Result<FileAttr, i32> {
+            let inner = self.inner();
+            inner.check_mkdir(path)?;
+            let ino = self.block_on(inner.make_dir(path, OWNER))?;
+            Ok(inner.create_dir_attr(ino, path))
+        }
+
+        fn rmdir(&self, path: &str) -> Result<(), i32> {
+            self.inner().check_rmdir(path)?;
+            self.block_on(self.inner().remove_dir(path))
+        }
+
+        fn unlink(&self, path: &str) -> Result<(), i32> {
+            self.inner().check_unlink(path)?;
+            self.block_on(self.inner().unlink_file(path.trim_start_matches('/')))
+        }
+
+        fn rename(&self, from: &str, to: &str) -> Result<(), i32> {
+            self.rename_with_flags(from, to, 0)
+        }
+
+        fn rename_with_flags(&self, from: &str, to: &str, flags: u32) -> Result<(), i32> {
+            let is_dir = self.inner().check_rename(from, to, flags)?;
+            if from == to {
+                return Ok(());
             }
+            self.block_on(self.inner().rename_path(from, to, is_dir))
         }
     }
 
-    fn access(&mut self, _req: &Request<'_>, ino: u64, mask: i32, reply: ReplyEmpty) {
-        debug!("Access: ino={}, mask={}", ino, mask);
+    #[test]
+    fn test_lookup_and_readdir() {
+        let bucket = TempDir::new().unwrap();
+        let t = mount_local(
+            &bucket,
+            &[("a.txt", "hello"), ("docs/b.md", "bb"), ("docs/sub/c", "")],
+        );
 
-        // 检查文件/目录是否存在
-        if self.get_path(ino).is_none() {
-            reply.error(ENOENT);
-            return;
+        let attr = t.lookup("/a.txt").unwrap();
+        assert_eq!((attr.kind, attr.size), (FileType::RegularFile, 5));
+        assert_eq!(t.lookup("/docs").unwrap().kind, FileType::Directory);
+        assert_eq!(t.lookup("/docs/sub/c").unwrap().size, 0);
+        assert_eq!(t.lookup("/missing").unwrap_err(), ENOENT);
+        assert_eq!(t.lookup("/docs/b").unwrap_err(), ENOENT);
+
+        assert_eq!(t.readdir("/").unwrap(), ["a.txt", "docs"]);
+        assert_eq!(t.readdir("/docs").unwrap(), ["b.md", "sub"]);
+        assert_eq!(t.readdir("/docs/sub").unwrap(), ["c"]);
+        assert_eq!(t.readdir("/a.txt").unwrap_err(), ENOTDIR);
+    }
+
+    #[test]
+    fn test_read() {
+        let bucket = TempDir::new().unwrap();
+        let t = mount_local(&bucket, &[("a.txt", "hello world")]);
+
+        assert_eq!(t.read("/a.txt", 0, 5).unwrap(), b"hello");
+        assert_eq!(t.read("/a.txt", 6, 100).unwrap(), b"world");
+        assert!(t.read("/a.txt", 11, 10).unwrap().is_empty());
+        assert!(t.read("/a.txt", 100, 10).unwrap().is_empty());
+
+        // 内容已缓存：之后的读取不再访问后端
+        fs::write(bucket.path().join("a.txt"), "HELLO WORLD").unwrap();
+        assert_eq!(t.read("/a.txt", 0, 11).unwrap(), b"hello world");
+        assert_eq!(t.read("/missing", 0, 10).unwrap_err(), ENOENT);
+    }
+
+    #[test]
+    fn test_read_after_remote_overwrite() {
+        for compression in [CacheCompression::None, CacheCompression::Zstd] {
+            let mock = MockBackend::new("demo");
+            mock.insert("a", vec![7u8; 3 << 19]);
+            mock.insert("b", "old!");
+            let t = mount_with(mock, |builder| {
+                builder
+                    .cache_options(CacheOptions {
+                        compression,
+                        ..Default::default()
+                    })
+                    .metadata_ttl(Duration::from_millis(200))
+            });
+            let store = &t.inner().store;
+
+            // 大对象只缓存了第一块
+            assert_eq!(t.read("/a", 0, 10).unwrap(), [7u8; 10]);
+            assert_eq!(t.read("/b", 0, 10).unwrap(), b"old!");
+
+            // 其他客户端覆盖：大对象换成小对象，小对象换成同样大小的新内容
+            store.insert("a", "small now");
+            store.insert("b", "new!");
+            // 元数据过期之前仍使用缓存的内容，过期后重新 HEAD 发现变化，读到新内容
+            assert_eq!(t.read("/b", 0, 10).unwrap(), b"old!");
+            std::thread::sleep(Duration::from_millis(250));
+            assert_eq!(t.read("/a", 0, 100).unwrap(), b"small now");
+            assert_eq!(t.read("/b", 0, 10).unwrap(), b"new!");
+            let gets = store.requests(Op::Get);
+            assert_eq!(t.read("/a", 0, 100).unwrap(), b"small now");
+            assert_eq!(store.requests(Op::Get), gets);
         }
+    }
 
-        // 对于COS文件系统，我们假设所有文件都有读权限
-        // 写权限暂时不支持，因为COS是只读的
-        if mask & libc::W_OK != 0 {
-            // 拒绝写权限
-            reply.error(EACCES);
-        } else {
-            // 允许读和执行权限
-            reply.ok();
+    #[test]
+    fn test_create_write_flush_release() {
+        let bucket = TempDir::new().unwrap();
+        let t = mount_local(&bucket, &[]);
+        let on_disk = |key: &str| fs::read(bucket.path().join(key)).ok();
+
+        let fh = t.create("/new.txt");
+        t.write(fh, 0, b"hello");
+        // 上传之前 lookup 与 readdir 看到暂存的文件
+        assert_eq!(t.lookup("/new.txt").unwrap().size, 5);
+        assert_eq!(t.readdir("/").unwrap(), ["new.txt"]);
+        assert_eq!(on_disk("new.txt"), None);
+
+        t.flush(fh).unwrap();
+        assert_eq!(on_disk("new.txt").unwrap(), b"hello");
+        t.write(fh, 5, b" world");
+        assert_eq!(t.read_handle(fh, 0, 100), b"hello world");
+        t.release(fh).unwrap();
+        assert_eq!(on_disk("new.txt").unwrap(), b"hello world");
+        assert_eq!(t.lookup("/new.txt").unwrap().size, 11);
+        assert_eq!(t.read("/new.txt", 0, 100).unwrap(), b"hello world");
+
+        // 改写已有文件的一部分，没有写入的部分从后端取回
+        let fh = t.open_write("/new.txt", false).unwrap();
+        t.write(fh, 0, b"HE");
+        assert_eq!(t.read_handle(fh, 0, 5), b"HEllo");
+        t.release(fh).unwrap();
+        assert_eq!(on_disk("new.txt").unwrap(), b"HEllo world");
+        assert_eq!(t.read("/new.txt", 0, 100).unwrap(), b"HEllo world");
+
+        // O_TRUNC 从空文件开始
+        let fh = t.open_write("/new.txt", true).unwrap();
+        assert_eq!(t.lookup("/new.txt").unwrap().size, 0);
+        t.release(fh).unwrap();
+        assert_eq!(on_disk("new.txt").unwrap(), b"");
+        assert_eq!(t.open_write("/missing", false).unwrap_err(), ENOENT);
+    }
+
+    #[test]
+    fn test_mkdir_rmdir() {
+        let bucket = TempDir::new().unwrap();
+        let t = mount_local(&bucket, &[("a.txt", "a")]);
+
+        assert_eq!(t.mkdir("/d").unwrap().kind, FileType::Directory);
+        assert!(bucket.path().join("d").is_dir());
+        assert_eq!(t.readdir("/").unwrap(), ["a.txt", "d"]);
+        assert_eq!(t.mkdir("/d").unwrap_err(), EEXIST);
+        assert_eq!(t.mkdir("/a.txt").unwrap_err(), EEXIST);
+
+        let fh = t.create("/d/f");
+        // 还没有上传的新文件也算在目录中
+        assert_eq!(t.rmdir("/d").unwrap_err(), ENOTEMPTY);
+        t.release(fh).unwrap();
+        assert_eq!(t.readdir("/d").unwrap(), ["f"]);
+        assert_eq!(t.rmdir("/d").unwrap_err(), ENOTEMPTY);
+
+        t.unlink("/d/f").unwrap();
+        t.rmdir("/d").unwrap();
+        assert_eq!(t.lookup("/d").unwrap_err(), ENOENT);
+        assert!(!bucket.path().join("d").exists());
+        assert_eq!(t.rmdir("/a.txt").unwrap_err(), ENOTDIR);
+        assert_eq!(t.rmdir("/missing").unwrap_err(), ENOENT);
+    }
+
+    #[test]
+    fn test_unlink() {
+        let bucket = TempDir::new().unwrap();
+        let t = mount_local(&bucket, &[("a.txt", "hello"), ("docs/b", "b")]);
+
+        assert_eq!(t.read("/a.txt", 0, 5).unwrap(), b"hello");
+        t.unlink("/a.txt").unwrap();
+        assert_eq!(t.lookup("/a.txt").unwrap_err(), ENOENT);
+        assert_eq!(t.readdir("/").unwrap(), ["docs"]);
+        assert!(!bucket.path().join("a.txt").exists());
+        assert_eq!(t.unlink("/a.txt").unwrap_err(), ENOENT);
+        assert_eq!(t.unlink("/docs").unwrap_err(), EISDIR);
+
+        // 以写方式打开着的文件：名字立即消失，句柄仍可读写，关闭时不再上传
+        let fh = t.open_write("/docs/b", false).unwrap();
+        t.write(fh, 1, b"+");
+        t.unlink("/docs/b").unwrap();
+        assert_eq!(t.lookup("/docs/b").unwrap_err(), ENOENT);
+        assert!(!bucket.path().join("docs/b").exists());
+        t.write(fh, 2, b"!");
+        assert_eq!(t.read_handle(fh, 0, 10), b"b+!");
+        t.flush(fh).unwrap();
+        t.release(fh).unwrap();
+        assert!(!bucket.path().join("docs/b").exists());
+        assert_eq!(t.lookup("/docs/b").unwrap_err(), ENOENT);
+
+        // 同名的新文件不受已删除句柄的影响
+        let old = t.create("/c");
+        t.write(old, 0, b"old");
+        t.unlink("/c").unwrap();
+        let new = t.create("/c");
+        t.write(new, 0, b"new!");
+        t.write(old, 0, b"OLD");
+        assert_eq!(t.lookup("/c").unwrap().size, 4);
+        t.release(old).unwrap();
+        t.release(new).unwrap();
+        assert_eq!(fs::read(bucket.path().join("c")).unwrap(), b"new!");
+    }
+
+    #[test]
+    fn test_rename_file() {
+        let bucket = TempDir::new().unwrap();
+        let t = mount_local(&bucket, &[("a.txt", "hello"), ("docs/b.txt", "world")]);
+        assert_eq!(t.read("/a.txt", 0, 100).unwrap(), b"hello");
+
+        t.rename("/a.txt", "/docs/c.txt").unwrap();
+        assert_eq!(t.lookup("/a.txt").unwrap_err(), ENOENT);
+        assert_eq!(t.lookup("/docs/c.txt").unwrap().size, 5);
+        // 旧键缓存的内容不会被新键读到
+        assert_eq!(t.read("/docs/c.txt", 0, 100).unwrap(), b"hello");
+        assert_eq!(t.readdir("/").unwrap(), ["docs"]);
+        assert_eq!(t.readdir("/docs").unwrap(), ["b.txt", "c.txt"]);
+        let on_disk = |key: &str| fs::read(bucket.path().join(key)).ok();
+        assert_eq!(on_disk("a.txt"), None);
+        assert_eq!(on_disk("docs/c.txt").unwrap(), b"hello");
+
+        // 覆盖已有的文件
+        t.rename("/docs/c.txt", "/docs/b.txt").unwrap();
+        assert_eq!(t.read("/docs/b.txt", 0, 100).unwrap(), b"hello");
+        assert_eq!(t.readdir("/docs").unwrap(), ["b.txt"]);
+        assert_eq!(t.rename("/missing", "/x").unwrap_err(), ENOENT);
+    }
+
+    #[test]
+    fn test_rename_dir() {
+        let bucket = TempDir::new().unwrap();
+        let t = mount_local(
+            &bucket,
+            &[("src/a", "1"), ("src/sub/b", "22"), ("other", "3")],
+        );
+
+        t.rename("/src", "/dst").unwrap();
+        assert_eq!(t.readdir("/").unwrap(), ["dst", "other"]);
+        assert_eq!(t.readdir("/dst").unwrap(), ["a", "sub"]);
+        assert_eq!(t.readdir("/dst/sub").unwrap(), ["b"]);
+        assert_eq!(t.read("/dst/sub/b", 0, 10).unwrap(), b"22");
+        assert_eq!(t.lookup("/src").unwrap_err(), ENOENT);
+        assert_eq!(t.lookup("/src/a").unwrap_err(), ENOENT);
+        assert_eq!(fs::read(bucket.path().join("dst/a")).unwrap(), b"1");
+        assert!(!bucket.path().join("src/a").exists());
+    }
+
+    #[test]
+    fn test_rename_errors() {
+        let bucket = TempDir::new().unwrap();
+        let t = mount_local(
+            &bucket,
+            &[("a", "1"), ("b", "2"), ("d/x", "3"), ("e/y", "4")],
+        );
+        t.mkdir("/empty").unwrap();
+
+        assert_eq!(t.rename("/missing", "/z").unwrap_err(), ENOENT);
+        assert_eq!(t.rename("/missing", "/missing").unwrap_err(), ENOENT);
+        // 改名为自身不做任何事，目录不为空也一样
+        t.rename("/a", "/a").unwrap();
+        t.rename("/d", "/d").unwrap();
+        let noreplace = t.rename_with_flags("/a", "/b", RENAME_NOREPLACE);
+        assert_eq!(noreplace.unwrap_err(), EEXIST);
+        let exchange = t.rename_with_flags("/a", "/z", RENAME_EXCHANGE);
+        assert_eq!(exchange.unwrap_err(), EINVAL);
+        assert_eq!(t.rename("/d", "/d/sub").unwrap_err(), EINVAL);
+        assert_eq!(t.rename("/d", "/a").unwrap_err(), ENOTDIR);
+        assert_eq!(t.rename("/a", "/d").unwrap_err(), EISDIR);
+        assert_eq!(t.rename("/d", "/e").unwrap_err(), ENOTEMPTY);
+
+        // 失败的检查不改动任何对象
+        assert_eq!(t.readdir("/").unwrap(), ["a", "b", "d", "e", "empty"]);
+        assert_eq!(fs::read(bucket.path().join("b")).unwrap(), b"2");
+
+        // 目标是空目录时可以替换
+        t.rename("/d", "/empty").unwrap();
+        assert_eq!(t.readdir("/empty").unwrap(), ["x"]);
+        t.rename_with_flags("/a", "/z", RENAME_NOREPLACE).unwrap();
+        assert_eq!(t.read("/z", 0, 10).unwrap(), b"1");
+    }
+
+    #[test]
+    fn test_rename_dir_fails_while_deleting() {
+        let mock = MockBackend::new("demo");
+        for key in ["d/1", "d/2", "d/3"] {
+            mock.insert(key, key);
+        }
+        let t = mount(mock);
+        let store = &t.inner().store;
+        store.inject_for(Op::Delete, "d/2", Fault::Status(StatusCode::FORBIDDEN), 1);
+
+        // 复制全部完成后才删除：目标完整，原处只剩没有删掉的对象，列表随之更新
+        assert_eq!(t.rename("/d", "/e").unwrap_err(), EIO);
+        for key in ["e/1", "e/2", "e/3"] {
+            assert_eq!(store.object(key).unwrap(), key.replace('e', "d"));
         }
+        assert!(store.object("d/1").is_none());
+        assert!(store.object("d/2").is_some() && store.object("d/3").is_some());
+        assert_eq!(t.readdir("/").unwrap(), ["d", "e"]);
+        assert_eq!(t.readdir("/d").unwrap(), ["2", "3"]);
+        assert_eq!(t.readdir("/e").unwrap(), ["1", "2", "3"]);
+        assert_eq!(t.read("/e/2", 0, 10).unwrap(), b"d/2");
     }
 
-    fn listxattr(&mut self, _req: &Request<'_>, _ino: u64, size: u32, reply: ReplyXattr) {
-        // 不支持扩展属性：返回空列表
-        if size == 0 {
-            reply.size(0); // 只需返回所需 buffer 大小（0）
-        } else {
-            reply.data(&[]); // 实际返回空数据
+    #[test]
+    fn test_read_faults() {
+        let mock = MockBackend::new("demo");
+        let etag = mock.insert("small", "hello world");
+        let big: Vec<u8> = (0..3 << 19).map(|i| (i % 251) as u8).collect();
+        mock.insert("big", big.clone());
+        mock.insert("gone", "x");
+        let t = mount(mock);
+        let (cache, store) = (&t.inner().cache, &t.inner().store);
+
+        // 失败的读取答复 EIO，不留下缓存；下一次读取重新请求并得到完整的内容
+        let faults = [
+            Fault::Status(StatusCode::INTERNAL_SERVER_ERROR),
+            Fault::SlowDown,
+            Fault::Truncate(5),
+        ];
+        for fault in faults.clone() {
+            store.inject(Op::Get, fault, 1);
+            let gets = store.requests(Op::Get);
+            assert_eq!(t.read("/small", 0, 100).unwrap_err(), EIO);
+            assert!(!cache.is_content_cached("small", &etag, 11));
+            assert_eq!(t.read("/small", 0, 100).unwrap(), b"hello world");
+            assert_eq!(store.requests(Op::Get), gets + 2);
+            cache.remove_content("small");
+        }
+
+        // 大对象按块下载：跨越两个块的读取，其中一块失败
+        let (offset, size) = ((1 << 20) - 10, 20);
+        let expected = &big[offset as usize..(offset + size) as usize];
+        for fault in faults {
+            store.inject(Op::Get, fault, 1);
+            assert_eq!(t.read("/big", offset, size).unwrap_err(), EIO);
+            assert_eq!(t.read("/big", offset, size).unwrap(), expected);
+            assert_eq!(t.read("/big", 0, 3 << 19).unwrap(), big);
+            cache.remove_content("big");
         }
+
+        // 对象在 COS 上已被删除
+        t.block_on(store.delete_object("gone")).unwrap();
+        assert_eq!(t.read("/gone", 0, 10).unwrap_err(), ENOENT);
+        assert_eq!(t.readdir("/").unwrap(), ["big", "small"]);
     }
 
-    fn getxattr(
-        &mut self,
-        _req: &Request<'_>,
-        _ino: u64,
-        _name: &std::ffi::OsStr,
-        size: u32,
-        reply: ReplyXattr,
-    ) {
-        // 不支持任何扩展属性
-        if size == 0 {
-            // 应用程序只查询值的大小（通常用于分配 buffer）
-            // 因为属性不存在，返回 0 或错误均可，但标准做法是返回错误
-            reply.error(ENODATA);
-        } else {
-            // 尝试读取不存在的属性
-            reply.error(ENODATA);
+    #[test]
+    fn test_lookup_faults() {
+        let mock = MockBackend::new("demo");
+        mock.insert("a", "hello");
+        mock.insert("b", "b");
+        let t = mount(mock);
+        let (cache, store) = (&t.inner().cache, &t.inner().store);
+
+        // 元数据不在缓存中时 lookup 发出 HEAD；失败不会被记住，下一次重新请求
+        for fault in [
+            Fault::Status(StatusCode::INTERNAL_SERVER_ERROR),
+            Fault::Status(StatusCode::BAD_GATEWAY),
+            Fault::SlowDown,
+            Fault::Status(StatusCode::FORBIDDEN),
+        ] {
+            cache.remove_metadata("a");
+            store.inject(Op::Head, fault, 1);
+            let heads = store.requests(Op::Head);
+            assert_eq!(t.lookup("/a").unwrap_err(), EIO);
+            assert!(matches!(cache.lookup_metadata("a"), MetadataLookup::Miss));
+            assert_eq!(t.lookup("/a").unwrap().size, 5);
+            assert_eq!(store.requests(Op::Head), heads + 2);
+        }
+
+        cache.remove_metadata("b");
+        t.block_on(store.delete_object("b")).unwrap();
+        assert_eq!(t.lookup("/b").unwrap_err(), ENOENT);
+        assert_eq!(t.readdir("/").unwrap(), ["a"]);
+    }
+
+    #[test]
+    fn test_upload_faults() {
+        let t = mount(MockBackend::new("demo"));
+        let store = &t.inner().store;
+
+        // flush 失败答复 EIO，暂存保留，下一次 flush 重新上传
+        let fh = t.create("/a");
+        t.write(fh, 0, b"hello");
+        for fault in [
+            Fault::Status(StatusCode::INTERNAL_SERVER_ERROR),
+            Fault::SlowDown,
+        ] {
+            store.inject(Op::Put, fault, 1);
+            assert_eq!(t.flush(fh).unwrap_err(), EIO);
+            assert!(store.object("a").is_none());
+            assert_eq!(t.lookup("/a").unwrap().size, 5);
         }
+        let puts = store.requests(Op::Put);
+        t.flush(fh).unwrap();
+        assert_eq!(store.requests(Op::Put), puts + 1);
+        assert_eq!(store.object("a").unwrap(), "hello");
+
+        // 关闭时上传失败：暂存留待之后重试，期间仍能看到改动
+        t.write(fh, 5, b" world");
+        store.inject(Op::Put, Fault::SlowDown, u32::MAX);
+        assert_eq!(t.release(fh).unwrap_err(), EIO);
+        assert_eq!(store.object("a").unwrap(), "hello");
+        assert_eq!(t.lookup("/a").unwrap().size, 11);
+        t.block_on(t.inner().retry_failed_uploads());
+        assert_eq!(store.object("a").unwrap(), "hello");
+        assert!(t.inner().failed_uploads.lock().unwrap().contains_key("a"));
+
+        store.clear_faults();
+        t.block_on(t.inner().retry_failed_uploads());
+        assert_eq!(store.object("a").unwrap(), "hello world");
+        assert!(t.inner().failed_uploads.lock().unwrap().is_empty());
+        assert!(t.inner().staged.get("a").is_none());
+        assert_eq!(t.read("/a", 0, 100).unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn test_upload_timeout() {
+        let t = mount_with(MockBackend::new("demo"), |builder| {
+            builder.runtime_options(RuntimeOptions {
+                op_timeout: Some(Duration::from_millis(100)),
+                ..Default::default()
+            })
+        });
+        let store = &t.inner().store;
+
+        // 卡住的 PUT 到时限后放弃：flush 与关闭答复 EIO 而不是一直等下去，改动留在暂存中
+        let fh = t.create("/a");
+        t.write(fh, 0, b"hello");
+        store.set_latency(Duration::from_secs(3600));
+        let started = Instant::now();
+        assert_eq!(t.flush(fh).unwrap_err(), EIO);
+        assert_eq!(t.release(fh).unwrap_err(), EIO);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(store.object("a").is_none());
+        assert_eq!(t.lookup("/a").unwrap().size, 5);
+
+        // 后端恢复后由后台重试上传
+        store.set_latency(Duration::ZERO);
+        t.block_on(t.inner().retry_failed_uploads());
+        assert_eq!(store.object("a").unwrap(), "hello");
+        assert!(t.inner().failed_uploads.lock().unwrap().is_empty());
     }
 }
@@ -0,0 +1,44 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+use crate::cos_client::ObjectMeta;
+use crate::inflight::InFlight;
+use crate::object_store::ObjectStore;
+
+/// 默认允许同时进行的 HEAD 请求数
+pub const DEFAULT_MAX_CONCURRENT_HEADS: usize = 16;
+
+/// HEAD 请求解析器：合并同一个键的并发请求，并限制同时打开的连接数，
+/// 避免一批 stat 同时打出成百上千个请求
+pub struct HeadResolver<S> {
+    store: Arc<S>,
+    permits: Semaphore,
+    inflight: InFlight<ObjectMeta>,
+}
+
+impl<S: ObjectStore> HeadResolver<S> {
+    pub fn new(store: Arc<S>, max_concurrent: usize) -> Self {
+        Self {
+            store,
+            permits: Semaphore::new(max_concurrent.max(1)),
+            inflight: InFlight::new(),
+        }
+    }
+
+    /// 获取对象元数据；同一个键的并发调用只会发出一次 HEAD
+    pub async fn resolve(&self, key: &str) -> Result<ObjectMeta> {
+        self.inflight
+            .run(key, || async {
+                let _permit = self
+                    .permits
+                    .acquire()
+                    .await
+                    .map_err(|e| anyhow!("HEAD resolver closed: {}", e))?;
+                debug!("Issuing HEAD for key: {}", key);
+                self.store.head_object(key).await
+            })
+            .await
+    }
+}
@@ -0,0 +1,198 @@
+use libc::{EACCES, EIO, ENOENT};
+use log::{error, info, warn};
+use reqwest::StatusCode;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::cos_client::error_status;
+use crate::events::{Event, EventLog};
+use crate::object_store::ObjectStore;
+
+/// 两次检查桶状态的最短间隔，处于错误状态时也按这个间隔重试
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 运行中观察到的桶状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BucketState {
+    Ok,
+    /// 桶已被删除（NoSuchBucket）
+    Deleted,
+    /// 访问权限被收回（AccessDenied）
+    AccessDenied,
+}
+
+impl BucketState {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => BucketState::Deleted,
+            2 => BucketState::AccessDenied,
+            _ => BucketState::Ok,
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            BucketState::Ok => 0,
+            BucketState::Deleted => 1,
+            BucketState::AccessDenied => 2,
+        }
+    }
+
+    /// 处于错误状态时所有操作答复的错误码
+    pub fn errno(self) -> Option<i32> {
+        match self {
+            BucketState::Ok => None,
+            BucketState::Deleted => Some(ENOENT),
+            BucketState::AccessDenied => Some(EACCES),
+        }
+    }
+}
+
+impl fmt::Display for BucketState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BucketState::Ok => "ok",
+            BucketState::Deleted => "bucket-deleted",
+            BucketState::AccessDenied => "access-denied",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 跟踪桶在挂载期间是否被删除或收回权限。
+///
+/// 操作以 403/404 失败时列一次桶确认原因；确认后整个挂载进入错误状态，
+/// 所有操作直接答复 ENOENT/EACCES 而不再访问 COS，只在状态变化时记录一行日志，
+/// 之后每隔 [`CHECK_INTERVAL`] 重新检查，桶恢复后自动回到正常状态。状态变化同时记入事件文件
+pub struct BucketHealth<S> {
+    client: Arc<S>,
+    events: Arc<EventLog>,
+    state: AtomicU8,
+    /// 上次检查的时间
+    last_check: Mutex<Option<Instant>>,
+}
+
+impl<S: ObjectStore> BucketHealth<S> {
+    pub fn new(client: Arc<S>, events: Arc<EventLog>) -> Self {
+        Self {
+            client,
+            events,
+            state: AtomicU8::new(BucketState::Ok.as_u8()),
+            last_check: Mutex::new(None),
+        }
+    }
+
+    pub fn state(&self) -> BucketState {
+        BucketState::from_u8(self.state.load(Ordering::Relaxed))
+    }
+
+    /// 距上次检查已超过间隔
+    pub fn check_due(&self) -> bool {
+        self.last_check
+            .lock()
+            .unwrap()
+            .is_none_or(|at| at.elapsed() >= CHECK_INTERVAL)
+    }
+
+    /// 操作失败时应答复的错误码：可能意味着桶被删除或权限被收回的错误先检查桶的状态
+    pub async fn errno(&self, error: &anyhow::Error) -> i32 {
+        let suspicious = matches!(
+            error_status(error),
+            Some(StatusCode::NOT_FOUND | StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED)
+        );
+        if !suspicious {
+            return EIO;
+        }
+        self.check().await.errno().unwrap_or(EIO)
+    }
+
+    /// 列一个对象确认桶的状态；距上次检查不足间隔时直接返回当前状态
+    pub async fn check(&self) -> BucketState {
+        {
+            let mut last_check = self.last_check.lock().unwrap();
+            if last_check.is_some_and(|at| at.elapsed() < CHECK_INTERVAL) {
+                return self.state();
+            }
+            *last_check = Some(Instant::now());
+        }
+
+        let state = match self.client.list_first_key().await {
+            Ok(_) => BucketState::Ok,
+            Err(e) => match classify(&e) {
+                Some(state) => state,
+                // 网络错误等不能说明桶的状态
+                None => {
+                    warn!("Failed to check bucket {}: {:#}", self.client.bucket(), e);
+                    return self.state();
+                }
+            },
+        };
+        self.transition(state);
+        state
+    }
+
+    fn transition(&self, state: BucketState) {
+        let previous = BucketState::from_u8(self.state.swap(state.as_u8(), Ordering::Relaxed));
+        if previous == state {
+            return;
+        }
+        self.events.publish(Event::BucketState(state));
+        let bucket = self.client.bucket();
+        match state {
+            BucketState::Ok => info!("Bucket {} is accessible again, resuming", bucket),
+            BucketState::Deleted => error!(
+                "Bucket {} no longer exists; all operations fail with ENOENT until it is recreated",
+                bucket
+            ),
+            BucketState::AccessDenied => error!(
+                "Access to bucket {} was revoked; all operations fail with EACCES until it is restored",
+                bucket
+            ),
+        }
+    }
+}
+
+/// 列表失败的状态码对应的桶状态
+fn classify(error: &anyhow::Error) -> Option<BucketState> {
+    match error_status(error)? {
+        StatusCode::NOT_FOUND => Some(BucketState::Deleted),
+        StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED => Some(BucketState::AccessDenied),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cos_client::{CosClient, StatusError};
+
+    #[tokio::test]
+    async fn test_state_transitions() {
+        let client = Arc::new(CosClient::new("b".to_string(), "ap-beijing".to_string()));
+        let events = Arc::new(EventLog::new());
+        let health = BucketHealth::new(client, Arc::clone(&events));
+        assert_eq!(health.state(), BucketState::Ok);
+        assert!(health.check_due());
+
+        health.transition(BucketState::AccessDenied);
+        assert_eq!(health.state().errno(), Some(EACCES));
+        assert_eq!(health.state().to_string(), "access-denied");
+        health.transition(BucketState::Ok);
+        assert_eq!(health.state().errno(), None);
+        // 只有状态变化才记入事件
+        health.transition(BucketState::Ok);
+        let (data, _) = events.read(events.first_cursor(), 4096);
+        assert_eq!(String::from_utf8(data).unwrap().lines().count(), 2);
+
+        // 与桶状态无关的错误不触发检查
+        assert_eq!(health.errno(&anyhow::anyhow!("timed out")).await, EIO);
+        assert!(health.check_due());
+
+        let error = StatusError::new("LIST", StatusCode::NOT_FOUND).into();
+        assert_eq!(classify(&error), Some(BucketState::Deleted));
+        let error = StatusError::new("LIST", StatusCode::SERVICE_UNAVAILABLE).into();
+        assert_eq!(classify(&error), None);
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+use std::sync::Arc;
+
+/// 发起操作的请求者（内核报告的原始 id，未经 `[idmap]` 映射）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Requester {
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: u32,
+}
+
+impl Requester {
+    pub fn from_request(req: &fuser::Request<'_>) -> Self {
+        Self {
+            uid: req.uid(),
+            gid: req.gid(),
+            pid: req.pid(),
+        }
+    }
+}
+
+/// 库使用者注册的操作钩子（审计、上传后的病毒扫描、请求标记等）。
+///
+/// 钩子在答复内核之前同步调用：`on_open` 在 FUSE 会话线程上，其余在运行时的工作线程上，
+/// 都应尽快返回，耗时的工作交给自己的线程或任务。键相对于挂载的前缀
+pub trait OperationHook: Send + Sync {
+    /// 打开对象文件，返回错误码时拒绝打开
+    fn on_open(&self, _requester: &Requester, _key: &str, _flags: i32) -> Result<(), i32> {
+        Ok(())
+    }
+
+    /// 读取对象的 [offset, offset + len) 成功
+    fn on_read(&self, _requester: &Requester, _key: &str, _offset: u64, _len: u64) {}
+
+    /// 对象在 COS 上写入完成（上传或修改元数据），`size` 为对象大小
+    fn on_write_complete(&self, _requester: &Requester, _key: &str, _size: u64) {}
+
+    /// 需要访问网络的操作 `op`（如 read、getattr）以错误码 `errno` 答复
+    fn on_error(&self, _requester: &Requester, _op: &'static str, _errno: i32) {}
+}
+
+/// 按注册顺序调用的钩子列表；没有注册钩子时每个调用点只多一次判空
+#[derive(Clone, Default)]
+pub struct Hooks {
+    hooks: Vec<Arc<dyn OperationHook>>,
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Hooks({})", self.hooks.len())
+    }
+}
+
+impl Hooks {
+    pub fn push(&mut self, hook: Arc<dyn OperationHook>) {
+        self.hooks.push(hook);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// 依次调用 `on_open`，第一个拒绝的钩子的错误码作为结果，之后的钩子不再调用
+    pub fn on_open(&self, requester: &Requester, key: &str, flags: i32) -> Result<(), i32> {
+        self.hooks
+            .iter()
+            .try_for_each(|hook| hook.on_open(requester, key, flags))
+    }
+
+    pub fn on_read(&self, requester: &Requester, key: &str, offset: u64, len: u64) {
+        for hook in &self.hooks {
+            hook.on_read(requester, key, offset, len);
+        }
+    }
+
+    pub fn on_write_complete(&self, requester: &Requester, key: &str, size: u64) {
+        for hook in &self.hooks {
+            hook.on_write_complete(requester, key, size);
+        }
+    }
+
+    pub fn on_error(&self, requester: &Requester, op: &'static str, errno: i32) {
+        for hook in &self.hooks {
+            hook.on_error(requester, op, errno);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Audit {
+        log: Mutex<Vec<String>>,
+        deny: Option<&'static str>,
+    }
+
+    impl OperationHook for Audit {
+        fn on_open(&self, requester: &Requester, key: &str, _flags: i32) -> Result<(), i32> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("open {} {}", requester.uid, key));
+            match self.deny {
+                Some(denied) if key.starts_with(denied) => Err(libc::EACCES),
+                _ => Ok(()),
+            }
+        }
+
+        fn on_read(&self, _requester: &Requester, key: &str, offset: u64, len: u64) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("read {} {}+{}", key, offset, len));
+        }
+    }
+
+    #[test]
+    fn test_hooks() {
+        let requester = Requester {
+            uid: 1000,
+            gid: 1000,
+            pid: 42,
+        };
+        let first = Arc::new(Audit {
+            deny: Some("secret/"),
+            ..Default::default()
+        });
+        let second = Arc::new(Audit::default());
+        let mut hooks = Hooks::default();
+        assert!(hooks.is_empty());
+        hooks.push(first.clone());
+        hooks.push(second.clone());
+
+        assert_eq!(hooks.on_open(&requester, "data/a", 0), Ok(()));
+        hooks.on_read(&requester, "data/a", 0, 10);
+        // 被拒绝后不再调用之后的钩子；没有实现的方法什么也不做
+        assert_eq!(hooks.on_open(&requester, "secret/b", 0), Err(libc::EACCES));
+        hooks.on_error(&requester, "read", libc::EIO);
+
+        assert_eq!(
+            *first.log.lock().unwrap(),
+            vec!["open 1000 data/a", "read data/a 0+10", "open 1000 secret/b"]
+        );
+        assert_eq!(
+            *second.log.lock().unwrap(),
+            vec!["open 1000 data/a", "read data/a 0+10"]
+        );
+    }
+}
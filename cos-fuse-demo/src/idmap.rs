@@ -0,0 +1,129 @@
+use anyhow::{anyhow, bail, Result};
+use std::collections::{HashMap, HashSet};
+
+use crate::config_file::ConfigFile;
+
+/// 配置文件中 id 映射节的名字：`[idmap]`
+pub const IDMAP_SECTION: &str = "idmap";
+
+/// 映射后的请求者身份
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Credentials {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// 请求者 uid/gid 的映射表（类似 NFS idmapping）
+///
+/// 多个租户的 uid 可以映射（压缩）到挂载的规范属主上；设置了白名单时，
+/// 映射后不在白名单中的 uid 在 `access()`/`open()` 时被拒绝
+#[derive(Debug, Clone, Default)]
+pub struct IdMap {
+    uids: HashMap<u32, u32>,
+    gids: HashMap<u32, u32>,
+    allowed_uids: Option<HashSet<u32>>,
+}
+
+impl IdMap {
+    /// 从配置文件的 `[idmap]` 节读取：
+    /// `uid.<请求 uid> = <映射 uid>`、`gid.<请求 gid> = <映射 gid>`、`allowed_uids = "1000,1001"`
+    pub fn from_config(config: &ConfigFile) -> Result<Self> {
+        let mut map = IdMap::default();
+        for section in config.sections(IDMAP_SECTION) {
+            if section.name.len() != 1 {
+                bail!(
+                    "line {}: expected [{}], got [{}]",
+                    section.line,
+                    IDMAP_SECTION,
+                    section.name.join(".")
+                );
+            }
+            for (key, value) in &section.entries {
+                let invalid = || anyhow!("line {}: invalid {} '{}'", section.line, key, value);
+                if key == "allowed_uids" {
+                    let uids = value
+                        .split(',')
+                        .map(|uid| uid.trim().parse())
+                        .collect::<Result<HashSet<u32>, _>>()
+                        .map_err(|_| invalid())?;
+                    map.allowed_uids = Some(uids);
+                    continue;
+                }
+
+                let (table, from) = match key.split_once('.') {
+                    Some(("uid", from)) => (&mut map.uids, from),
+                    Some(("gid", from)) => (&mut map.gids, from),
+                    _ => bail!(
+                        "line {}: unknown key '{}' (expected uid.<id>, gid.<id> or allowed_uids)",
+                        section.line,
+                        key
+                    ),
+                };
+                let from = from.parse().map_err(|_| invalid())?;
+                let to = value.parse().map_err(|_| invalid())?;
+                table.insert(from, to);
+            }
+        }
+        Ok(map)
+    }
+
+    /// 映射请求者身份；映射后的 uid 不在白名单中时返回 None
+    pub fn credentials(&self, uid: u32, gid: u32) -> Option<Credentials> {
+        let uid = self.uids.get(&uid).copied().unwrap_or(uid);
+        let gid = self.gids.get(&gid).copied().unwrap_or(gid);
+        match &self.allowed_uids {
+            Some(allowed) if !allowed.contains(&uid) => None,
+            _ => Some(Credentials { uid, gid }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_and_allowlist() {
+        let config = ConfigFile::parse(
+            r#"
+[idmap]
+uid.1001 = 1000
+uid.1002 = 1000
+gid.2001 = 1000
+allowed_uids = "1000, 0"
+"#,
+        )
+        .unwrap();
+        let map = IdMap::from_config(&config).unwrap();
+
+        assert_eq!(
+            map.credentials(1002, 2001),
+            Some(Credentials {
+                uid: 1000,
+                gid: 1000
+            })
+        );
+        assert_eq!(map.credentials(0, 0), Some(Credentials { uid: 0, gid: 0 }));
+        assert_eq!(map.credentials(1003, 2001), None);
+
+        // 没有配置时原样放行
+        assert_eq!(
+            IdMap::default().credentials(1003, 7),
+            Some(Credentials { uid: 1003, gid: 7 })
+        );
+    }
+
+    #[test]
+    fn test_invalid_entries_are_rejected() {
+        for text in [
+            "[idmap] uid.x=1",
+            "[idmap] uid.1=-1",
+            "[idmap] user.1=1",
+            "[idmap] allowed_uids=\"1,a\"",
+            "[idmap.extra] uid.1=1",
+        ] {
+            let config = ConfigFile::parse(text).unwrap();
+            assert!(IdMap::from_config(&config).is_err(), "{}", text);
+        }
+    }
+}
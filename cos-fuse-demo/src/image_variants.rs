@@ -0,0 +1,167 @@
+use anyhow::Result;
+use bytes::Bytes;
+use log::info;
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+use crate::content_type::guess_content_type;
+use crate::cos_client::ObjectMeta;
+use crate::inflight::InFlight;
+use crate::object_store::ObjectStore;
+
+/// 内存中保留的处理结果个数
+const RENDERED_CACHE_ENTRIES: usize = 64;
+
+/// 数据万象支持输出的格式
+const FORMATS: &[&str] = &["jpg", "png", "webp", "gif", "bmp", "heif", "avif", "tpg"];
+
+/// 把 `photo.jpg@w=200,q=80` 这样的虚拟文件名拆成 (源图片对象键, 数据万象处理参数)。
+/// 源对象须为图片；参数为 `w`/`h`（缩放后的宽高，只给一个时等比缩放）、
+/// `q`（质量 1-100）和 `fmt`（输出格式），未知或重复的参数不构成虚拟文件
+pub fn parse_variant(key: &str) -> Option<(&str, String)> {
+    let (source, params) = key.rsplit_once('@')?;
+    if source.is_empty() || source.ends_with('/') {
+        return None;
+    }
+    if !guess_content_type(source, None).starts_with("image/") {
+        return None;
+    }
+
+    let mut width = None;
+    let mut height = None;
+    let mut quality = None;
+    let mut format = None;
+    for param in params.split(',') {
+        let (name, value) = param.split_once('=')?;
+        let slot = match name {
+            "w" => &mut width,
+            "h" => &mut height,
+            "q" => &mut quality,
+            "fmt" => &mut format,
+            _ => return None,
+        };
+        if slot.is_some() {
+            return None;
+        }
+        *slot = Some(value);
+    }
+
+    let dimension = |value: Option<&str>| -> Option<Option<u32>> {
+        match value {
+            None => Some(None),
+            Some(value) => value.parse::<u32>().ok().filter(|v| *v > 0).map(Some),
+        }
+    };
+    let width = dimension(width)?;
+    let height = dimension(height)?;
+
+    let mut rule = String::from("imageMogr2");
+    match (width, height) {
+        (None, None) => {}
+        (Some(w), None) => rule.push_str(&format!("/thumbnail/{}x", w)),
+        (None, Some(h)) => rule.push_str(&format!("/thumbnail/x{}", h)),
+        (Some(w), Some(h)) => rule.push_str(&format!("/thumbnail/{}x{}", w, h)),
+    }
+    if let Some(format) = format {
+        if !FORMATS.contains(&format) {
+            return None;
+        }
+        rule.push_str(&format!("/format/{}", format));
+    }
+    if let Some(quality) = quality {
+        let quality = quality
+            .parse::<u8>()
+            .ok()
+            .filter(|q| (1..=100).contains(q))?;
+        rule.push_str(&format!("/quality/{}", quality));
+    }
+    Some((source, rule))
+}
+
+/// 数据万象处理结果：按 (对象键, ETag, 处理参数) 缓存在内存中，并发的相同请求只执行一次
+pub struct ImageVariants {
+    loading: InFlight<Bytes>,
+    rendered: Mutex<LruCache<String, Bytes>>,
+}
+
+impl ImageVariants {
+    pub fn new() -> Self {
+        Self {
+            loading: InFlight::new(),
+            rendered: Mutex::new(LruCache::new(
+                NonZeroUsize::new(RENDERED_CACHE_ENTRIES).unwrap(),
+            )),
+        }
+    }
+
+    /// 图片 `meta` 按 `rule` 处理后的内容，源对象变化后重新处理
+    pub async fn render<S: ObjectStore>(
+        &self,
+        client: &S,
+        meta: &ObjectMeta,
+        rule: &str,
+    ) -> Result<Bytes> {
+        let cache_key = format!("{}\0{}\0{}", meta.key, meta.etag, rule);
+        if let Some(data) = self.rendered.lock().unwrap().get(&cache_key) {
+            return Ok(data.clone());
+        }
+        let data = self
+            .loading
+            .run(&cache_key, || async {
+                let data = client.get_processed(&meta.key, rule).await?;
+                info!(
+                    "Processed image {} with {} ({} bytes)",
+                    meta.key,
+                    rule,
+                    data.len()
+                );
+                Ok(data)
+            })
+            .await?;
+        self.rendered.lock().unwrap().put(cache_key, data.clone());
+        Ok(data)
+    }
+
+    /// 内存中处理结果占用的字节数
+    pub fn memory_usage(&self) -> u64 {
+        let rendered = self.rendered.lock().unwrap();
+        rendered
+            .iter()
+            .map(|(key, data)| (key.len() + data.len()) as u64)
+            .sum()
+    }
+
+    /// 内存不足时丢弃所有处理结果
+    pub fn clear(&self) {
+        self.rendered.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_variant() {
+        assert_eq!(
+            parse_variant("photos/a.jpg@w=200"),
+            Some(("photos/a.jpg", "imageMogr2/thumbnail/200x".to_string()))
+        );
+        assert_eq!(
+            parse_variant("a.PNG@h=100,w=50,q=80,fmt=webp"),
+            Some((
+                "a.PNG",
+                "imageMogr2/thumbnail/50x100/format/webp/quality/80".to_string()
+            ))
+        );
+        // 非图片、未知参数、非法取值与重复参数都不是虚拟文件
+        assert_eq!(parse_variant("notes.txt@w=200"), None);
+        assert_eq!(parse_variant("a.jpg@x=1"), None);
+        assert_eq!(parse_variant("a.jpg@w=0"), None);
+        assert_eq!(parse_variant("a.jpg@q=101"), None);
+        assert_eq!(parse_variant("a.jpg@fmt=exe"), None);
+        assert_eq!(parse_variant("a.jpg@w=1,w=2"), None);
+        assert_eq!(parse_variant("a.jpg"), None);
+    }
+}
@@ -0,0 +1,142 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+use crate::cos_client::StatusError;
+
+/// 单个进行中请求的共享结果
+type Slot<T> = Arc<OnceCell<std::result::Result<T, SharedError>>>;
+
+/// 错误以字符串保存，便于在多个等待者之间克隆；状态码单独保留，调用方仍能区分权限不足等情况
+#[derive(Clone)]
+struct SharedError {
+    message: String,
+    status: Option<StatusError>,
+}
+
+impl SharedError {
+    fn new(error: &anyhow::Error) -> Self {
+        Self {
+            message: error.to_string(),
+            status: error.downcast_ref::<StatusError>().cloned(),
+        }
+    }
+
+    fn into_error(self) -> anyhow::Error {
+        match self.status {
+            Some(status) if status.to_string() == self.message => status.into(),
+            Some(status) => anyhow::Error::from(status).context(self.message),
+            None => anyhow!(self.message),
+        }
+    }
+}
+
+/// 进行中请求登记表：同一个键的并发请求只会真正执行一次，
+/// 其余调用方等待并共享同一个结果
+pub struct InFlight<T> {
+    slots: Mutex<HashMap<String, Slot<T>>>,
+}
+
+impl<T: Clone> InFlight<T> {
+    pub fn new() -> Self {
+        Self {
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 执行 `f`，若同一个 `key` 已有请求在进行中则等待它的结果
+    pub async fn run<F, Fut>(&self, key: &str, f: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let slot = {
+            let mut slots = self.slots.lock().unwrap();
+            slots
+                .entry(key.to_string())
+                .or_insert_with(|| Arc::new(OnceCell::new()))
+                .clone()
+        };
+
+        let result = slot
+            .get_or_init(|| async { f().await.map_err(|e| SharedError::new(&e)) })
+            .await
+            .clone();
+
+        // 请求完成后移除登记，后续调用会重新发起请求
+        {
+            let mut slots = self.slots.lock().unwrap();
+            if slots.get(key).is_some_and(|s| Arc::ptr_eq(s, &slot)) {
+                slots.remove(key);
+            }
+        }
+
+        result.map_err(SharedError::into_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cos_client::error_status;
+    use reqwest::StatusCode;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_requests_are_coalesced() {
+        let inflight = Arc::new(InFlight::<u64>::new());
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let mut handles = Vec::new();
+        for _ in 0..10 {
+            let inflight = Arc::clone(&inflight);
+            let calls = Arc::clone(&calls);
+            handles.push(tokio::spawn(async move {
+                inflight
+                    .run("same-key", || async {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        Ok(42)
+                    })
+                    .await
+            }));
+        }
+
+        for handle in handles {
+            assert_eq!(handle.await.unwrap().unwrap(), 42);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(inflight.slots.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_shared_and_not_cached() {
+        let inflight = InFlight::<u64>::new();
+
+        let err = inflight
+            .run("key", || async { Err(anyhow!("boom")) })
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "boom");
+
+        // 失败结果不会被保留，下一次调用会重新执行
+        let value = inflight.run("key", || async { Ok(7) }).await.unwrap();
+        assert_eq!(value, 7);
+
+        // 状态码随错误一起共享
+        let err = inflight
+            .run("key", || async {
+                Err(
+                    anyhow::Error::from(StatusError::new("HEAD", StatusCode::FORBIDDEN))
+                        .context("Failed to get key"),
+                )
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Failed to get key");
+        assert_eq!(error_status(&err), Some(StatusCode::FORBIDDEN));
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::memory::interned_size;
+
+/// 字符串驻留池：相同的对象键、路径与目录项名字只保存一份，
+/// 对象列表、inode 映射与目录缓存共享同一个 `Arc<str>`
+#[derive(Debug, Default)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+    /// 驻留的字符串占用的内存估计
+    bytes: u64,
+}
+
+impl Interner {
+    /// 返回与 `s` 相同的共享字符串，第一次出现时加入驻留池
+    pub fn intern(&mut self, s: &str) -> Arc<str> {
+        if let Some(existing) = self.strings.get(s) {
+            return Arc::clone(existing);
+        }
+        let interned: Arc<str> = Arc::from(s);
+        self.bytes += interned_size(s);
+        self.strings.insert(Arc::clone(&interned));
+        interned
+    }
+
+    /// 丢弃只剩驻留池自己引用的字符串，返回释放的字节数
+    pub fn purge(&mut self) -> u64 {
+        let before = self.bytes;
+        let mut freed = 0;
+        self.strings.retain(|s| {
+            let used = Arc::strong_count(s) > 1;
+            if !used {
+                freed += interned_size(s);
+            }
+            used
+        });
+        self.bytes = before - freed;
+        freed
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interner() {
+        let mut interner = Interner::default();
+        let a = interner.intern("photos/a.jpg");
+        let b = interner.intern("photos/a.jpg");
+        assert!(Arc::ptr_eq(&a, &b));
+        let c = interner.intern("b.txt");
+        assert_eq!(
+            interner.bytes(),
+            interned_size("photos/a.jpg") + interned_size("b.txt")
+        );
+
+        // 仍被引用的字符串保留
+        drop(c);
+        assert_eq!(interner.purge(), interned_size("b.txt"));
+        drop(a);
+        assert_eq!(interner.purge(), 0);
+        drop(b);
+        assert_eq!(interner.purge(), interned_size("photos/a.jpg"));
+        assert_eq!(interner.bytes(), 0);
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+/// 进行中的 FUSE 请求登记表：按请求的 unique ID 记录取消信号，
+/// 收到 FUSE_INTERRUPT 时通知对应的任务放弃等待并答复 EINTR
+#[derive(Debug, Default)]
+pub struct PendingOps {
+    ops: Mutex<HashMap<u64, oneshot::Sender<()>>>,
+}
+
+impl PendingOps {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记一个请求，返回的接收端在请求被中断时完成
+    pub fn register(&self, unique: u64) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        self.ops.lock().unwrap().insert(unique, tx);
+        rx
+    }
+
+    /// 请求已答复，移除登记
+    pub fn complete(&self, unique: u64) {
+        self.ops.lock().unwrap().remove(&unique);
+    }
+
+    /// 中断一个请求；请求已经完成（或从未登记）时返回 false
+    pub fn interrupt(&self, unique: u64) -> bool {
+        match self.ops.lock().unwrap().remove(&unique) {
+            Some(tx) => tx.send(()).is_ok(),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_interrupt_wakes_registered_op() {
+        let pending = PendingOps::new();
+        let rx = pending.register(7);
+
+        assert!(!pending.interrupt(8));
+        assert!(pending.interrupt(7));
+        assert!(rx.await.is_ok());
+
+        // 已完成的请求不会再被中断
+        let _rx = pending.register(9);
+        pending.complete(9);
+        assert!(!pending.interrupt(9));
+    }
+}
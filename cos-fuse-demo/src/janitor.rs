@@ -0,0 +1,43 @@
+use log::{debug, info};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+
+use crate::cache::Cache;
+
+/// 启动周期性的缓存维护任务，每次维护后调用 `trim_memory` 检查内存上限；
+/// 返回的句柄在卸载时用于停止任务
+pub fn spawn_janitor<F>(
+    runtime: &Runtime,
+    cache: Arc<Cache>,
+    interval: Duration,
+    trim_memory: F,
+) -> JoinHandle<()>
+where
+    F: Fn() + Send + 'static,
+{
+    runtime.spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // 第一次 tick 立即返回，跳过它以免与挂载初始化抢资源
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+
+            let cache = Arc::clone(&cache);
+            let report = match tokio::task::spawn_blocking(move || cache.run_maintenance()).await {
+                Ok(report) => report,
+                Err(_) => continue,
+            };
+
+            if report.removed_orphans > 0 || report.evicted > 0 || report.dropped_index_entries > 0
+            {
+                info!("Cache janitor: {:?}", report);
+            } else {
+                debug!("Cache janitor: {:?}", report);
+            }
+            trim_memory();
+        }
+    })
+}
@@ -0,0 +1,81 @@
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::trash::format_timestamp;
+
+/// 记录最近出错的对象个数
+const MAX_TRACKED_KEYS: usize = 1024;
+
+/// 最近一次失败的说明：UTC 时间与完整的错误链（含服务端请求 ID），
+/// 通过 `user.cosfs.last-error` 提供给排查问题和提交工单的人
+pub struct LastErrors {
+    latest: Mutex<Option<String>>,
+    by_key: Mutex<LruCache<String, String>>,
+}
+
+impl LastErrors {
+    pub fn new() -> Self {
+        Self {
+            latest: Mutex::new(None),
+            by_key: Mutex::new(LruCache::new(NonZeroUsize::new(MAX_TRACKED_KEYS).unwrap())),
+        }
+    }
+
+    /// 记录对象 `key` 的一次失败
+    pub fn record(&self, key: &str, error: &anyhow::Error) {
+        let message = format!(
+            "{} {}: {:#}",
+            format_timestamp(SystemTime::now()),
+            key,
+            error
+        );
+        self.by_key
+            .lock()
+            .unwrap()
+            .put(key.to_string(), message.clone());
+        *self.latest.lock().unwrap() = Some(message);
+    }
+
+    /// 对象 `key` 最近一次失败
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.by_key.lock().unwrap().get(key).cloned()
+    }
+
+    /// 整个挂载最近一次失败
+    pub fn latest(&self) -> Option<String> {
+        self.latest.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cos_client::{RequestIds, StatusError};
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_last_errors() {
+        let errors = LastErrors::new();
+        assert!(errors.latest().is_none());
+
+        let error = anyhow::Error::from(StatusError {
+            ids: RequestIds {
+                request_id: Some("NjQ1ZjYx".to_string()),
+                trace_id: Some("OGVmYzZiMmQz".to_string()),
+            },
+            ..StatusError::new("GET", StatusCode::FORBIDDEN)
+        })
+        .context("Failed to read a.txt");
+        errors.record("a.txt", &error);
+
+        let message = errors.get("a.txt").unwrap();
+        assert!(message.ends_with(
+            "a.txt: Failed to read a.txt: GET request failed with status: 403 Forbidden \
+             (request id: NjQ1ZjYx, trace id: OGVmYzZiMmQz)"
+        ));
+        assert_eq!(errors.latest(), Some(message));
+        assert!(errors.get("b.txt").is_none());
+    }
+}
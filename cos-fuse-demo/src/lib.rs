@@ -0,0 +1,86 @@
+//! 把腾讯云 COS 存储桶挂载为本地文件系统。
+//!
+//! [`CosFilesystemBuilder`] 以代码配置并创建 [`CosFilesystem`]，交给 fuser 挂载，
+//! 或由 [`Mount::spawn`] 挂载并返回控制挂载的 [`MountHandle`]；
+//! `cos-fuse-demo` 命令行只负责解析参数并调用这里的接口。
+
+mod accounting;
+mod acl;
+mod archive;
+pub mod atime;
+pub mod backend;
+mod bloom;
+mod buffer_pool;
+pub mod builder;
+mod cache;
+mod cache_format;
+pub mod cache_policy;
+#[doc(hidden)]
+pub mod commands;
+pub mod compression;
+pub mod config;
+#[doc(hidden)]
+pub mod config_file;
+mod content_type;
+pub mod cos_client;
+mod crc64;
+mod downloader;
+mod events;
+pub mod eviction;
+mod extents;
+pub mod filesystem;
+mod head_resolver;
+mod health;
+pub mod hooks;
+pub mod idmap;
+mod image_variants;
+mod inflight;
+mod intern;
+mod interrupt;
+mod janitor;
+mod last_error;
+pub mod limiter;
+pub mod local_backend;
+pub mod manifest;
+mod memory;
+pub mod metrics;
+pub mod mock_backend;
+pub mod mount;
+#[doc(hidden)]
+pub mod mount_lock;
+#[doc(hidden)]
+pub mod mount_options;
+mod mutations;
+pub mod object_store;
+pub mod oss_client;
+pub mod oss_signer;
+mod partial_upload;
+pub mod permissions;
+mod prefetch;
+mod probe;
+#[doc(hidden)]
+pub mod profile;
+mod read_data;
+mod readahead;
+pub mod recording;
+mod revalidate;
+pub mod s3_client;
+mod s3fs_compat;
+#[doc(hidden)]
+pub mod sd_notify;
+pub mod signer;
+pub mod sigv4;
+pub mod spool;
+mod staging;
+#[doc(hidden)]
+pub mod supervise;
+pub mod system_xattrs;
+mod tar;
+pub mod trash;
+pub mod upload;
+
+pub use builder::CosFilesystemBuilder;
+pub use filesystem::CosFilesystem;
+pub use hooks::{OperationHook, Requester};
+pub use mount::{Mount, MountConfig, MountHandle};
+pub use object_store::ObjectStore;
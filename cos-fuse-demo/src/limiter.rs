@@ -0,0 +1,251 @@
+use anyhow::{anyhow, Result};
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::{Notify, Semaphore, SemaphorePermit};
+
+/// COS 请求的类别，各自有独立的并发上限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestClass {
+    /// HEAD、LIST 和条件 GET 等元数据请求
+    Metadata,
+    /// 读取对象内容的 GET
+    Data,
+}
+
+/// 请求的优先级：FUSE 操作触发的请求为交互请求，预取、后台重新验证等为后台请求
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Interactive,
+    Background,
+}
+
+tokio::task_local! {
+    static PRIORITY: Priority;
+}
+
+/// 以后台优先级执行 `future`：其中发出的 COS 请求让位于交互请求。
+/// 优先级不会传给 `future` 中新建的任务，这些任务需要各自包装
+pub async fn background<F: Future>(future: F) -> F::Output {
+    PRIORITY.scope(Priority::Background, future).await
+}
+
+/// 当前任务的优先级，未经 [`background`] 包装的都是交互请求
+pub fn current_priority() -> Priority {
+    PRIORITY.try_with(|p| *p).unwrap_or(Priority::Interactive)
+}
+
+/// 并发请求上限
+#[derive(Debug, Clone)]
+pub struct RequestLimits {
+    /// 所有类别合计
+    pub max_requests: usize,
+    pub max_metadata_requests: usize,
+    pub max_data_requests: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_requests: 64,
+            max_metadata_requests: 32,
+            max_data_requests: 32,
+        }
+    }
+}
+
+/// 全局请求限流器
+///
+/// 请求先取得所属类别的许可，再排队取得全局许可。两级信号量都是先进先出的，
+/// 因此全局队列里每个类别最多只有其上限个请求：大量数据读取（例如递归 grep）
+/// 占满数据类别后，新来的 `ls` 最多排在这些请求之后，而不会被无限饿死。
+///
+/// 后台请求最多占用全局上限的一半，并且在有交互请求排队时暂停发出，
+/// 预热缓存等后台任务不会明显拖慢交互的 `ls`
+#[derive(Debug)]
+pub struct RequestLimiter {
+    global: Semaphore,
+    metadata: Semaphore,
+    data: Semaphore,
+    background: Semaphore,
+    /// 正在排队的交互请求数
+    interactive_waiting: AtomicUsize,
+    /// 交互请求全部拿到名额时唤醒暂停的后台请求
+    interactive_idle: Notify,
+}
+
+/// 持有期间占用一个请求名额
+pub struct RequestPermit<'a> {
+    _background: Option<SemaphorePermit<'a>>,
+    _class: SemaphorePermit<'a>,
+    _global: SemaphorePermit<'a>,
+}
+
+/// 交互请求排队期间计数
+struct Waiting<'a>(&'a RequestLimiter);
+
+impl<'a> Waiting<'a> {
+    fn new(limiter: &'a RequestLimiter) -> Self {
+        limiter.interactive_waiting.fetch_add(1, Ordering::SeqCst);
+        Self(limiter)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        if self.0.interactive_waiting.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.interactive_idle.notify_waiters();
+        }
+    }
+}
+
+impl RequestLimiter {
+    pub fn new(limits: &RequestLimits) -> Self {
+        Self {
+            global: Semaphore::new(limits.max_requests.max(1)),
+            metadata: Semaphore::new(limits.max_metadata_requests.max(1)),
+            data: Semaphore::new(limits.max_data_requests.max(1)),
+            background: Semaphore::new((limits.max_requests / 2).max(1)),
+            interactive_waiting: AtomicUsize::new(0),
+            interactive_idle: Notify::new(),
+        }
+    }
+
+    /// 等待一个 `class` 类别的请求名额，优先级取自当前任务
+    pub async fn acquire(&self, class: RequestClass) -> Result<RequestPermit<'_>> {
+        let mut background_permit = None;
+        let mut waiting = None;
+        match current_priority() {
+            Priority::Background => {
+                self.wait_interactive_idle().await;
+                background_permit = Some(
+                    self.background
+                        .acquire()
+                        .await
+                        .map_err(|e| anyhow!("Request limiter closed: {}", e))?,
+                );
+            }
+            Priority::Interactive => waiting = Some(Waiting::new(self)),
+        }
+
+        let semaphore = match class {
+            RequestClass::Metadata => &self.metadata,
+            RequestClass::Data => &self.data,
+        };
+        let class_permit = semaphore
+            .acquire()
+            .await
+            .map_err(|e| anyhow!("Request limiter closed: {}", e))?;
+        let global_permit = self
+            .global
+            .acquire()
+            .await
+            .map_err(|e| anyhow!("Request limiter closed: {}", e))?;
+
+        drop(waiting);
+
+        Ok(RequestPermit {
+            _background: background_permit,
+            _class: class_permit,
+            _global: global_permit,
+        })
+    }
+
+    /// 等到没有交互请求排队
+    async fn wait_interactive_idle(&self) {
+        loop {
+            let idle = self.interactive_idle.notified();
+            tokio::pin!(idle);
+            idle.as_mut().enable();
+            if self.interactive_waiting.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_data_requests_cannot_starve_metadata() {
+        let limiter = Arc::new(RequestLimiter::new(&RequestLimits {
+            max_requests: 3,
+            max_metadata_requests: 2,
+            max_data_requests: 2,
+        }));
+
+        // 占满数据类别，并让更多数据请求排队
+        let _d1 = limiter.acquire(RequestClass::Data).await.unwrap();
+        let _d2 = limiter.acquire(RequestClass::Data).await.unwrap();
+        let queued = {
+            let limiter = Arc::clone(&limiter);
+            tokio::spawn(async move {
+                let _permit = limiter.acquire(RequestClass::Data).await.unwrap();
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        // 元数据请求仍能立即拿到名额
+        let metadata = tokio::time::timeout(
+            Duration::from_millis(100),
+            limiter.acquire(RequestClass::Metadata),
+        )
+        .await;
+        assert!(metadata.is_ok());
+        assert!(!queued.is_finished());
+    }
+
+    #[tokio::test]
+    async fn test_background_yields_to_interactive() {
+        let limiter = Arc::new(RequestLimiter::new(&RequestLimits {
+            max_requests: 2,
+            max_metadata_requests: 2,
+            max_data_requests: 2,
+        }));
+        let spawn = |priority: Priority, class: RequestClass| {
+            let limiter = Arc::clone(&limiter);
+            let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+            let acquire = async move {
+                let _permit = limiter.acquire(class).await.unwrap();
+                let _ = rx.await;
+            };
+            let task = match priority {
+                Priority::Background => tokio::spawn(background(acquire)),
+                Priority::Interactive => tokio::spawn(acquire),
+            };
+            (task, tx)
+        };
+        let settle = || tokio::time::sleep(Duration::from_millis(10));
+
+        // 后台请求最多占用一半名额
+        let (b1, b1_done) = spawn(Priority::Background, RequestClass::Data);
+        let (b2, _b2_done) = spawn(Priority::Background, RequestClass::Data);
+        settle().await;
+        assert!(!b1.is_finished());
+        assert_eq!(limiter.global.available_permits(), 1);
+
+        // 名额占满后交互请求排队，排队期间新的后台请求暂停
+        let (_i1, _i1_done) = spawn(Priority::Interactive, RequestClass::Metadata);
+        let (i2, i2_done) = spawn(Priority::Interactive, RequestClass::Metadata);
+        settle().await;
+        assert_eq!(limiter.interactive_waiting.load(Ordering::SeqCst), 1);
+        let (b3, _b3_done) = spawn(Priority::Background, RequestClass::Metadata);
+        settle().await;
+
+        // 后台请求结束后名额先给排队的交互请求
+        b1_done.send(()).unwrap();
+        settle().await;
+        assert!(b1.is_finished());
+        assert_eq!(limiter.interactive_waiting.load(Ordering::SeqCst), 0);
+        assert!(!i2.is_finished());
+        assert!(!b2.is_finished() && !b3.is_finished());
+        assert_eq!(limiter.global.available_permits(), 0);
+        i2_done.send(()).unwrap();
+        settle().await;
+        assert!(i2.is_finished());
+    }
+}
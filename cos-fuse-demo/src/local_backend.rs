@@ -0,0 +1,585 @@
+//! 以本地目录充当桶的后端：目录下的文件即对象，键为以 `/` 分隔的相对路径，
+//! 空目录对应目录标记（`dir/`）。不需要网络与访问密钥，用于离线演示，
+//! 也让测试可以在真实的文件系统与缓存之上运行
+
+use anyhow::{anyhow, bail, Result};
+use bytes::Bytes;
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, Metadata};
+use std::io::{self, ErrorKind, SeekFrom};
+use std::ops::Range;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::cos_client::{normalize_prefix, ClientOptions, ListPage, ObjectMeta, StatusError};
+use crate::object_store::{list_sorted, ObjectStore};
+use crate::signer;
+
+/// 每页列出的条目数，与 COS 相同
+const PAGE_SIZE: usize = 1000;
+
+/// 写入中的临时文件名前缀；列出时跳过，也不能用作对象键
+const TMP_PREFIX: &str = ".cosfs-tmp-";
+
+#[derive(Debug)]
+pub struct LocalBackend {
+    bucket: String,
+    root: PathBuf,
+    /// 进行中的分块上传，按 UploadId 索引
+    uploads: Mutex<HashMap<String, Upload>>,
+    /// 临时文件名与 UploadId 的序号
+    next_id: AtomicU64,
+}
+
+/// 分块上传已收到的分块，合并前保存在内存中
+#[derive(Debug)]
+struct Upload {
+    key: String,
+    parts: BTreeMap<u32, Bytes>,
+}
+
+impl LocalBackend {
+    /// 以已存在的目录 `root` 为桶
+    pub fn new(bucket: impl Into<String>, root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        if !root.is_dir() {
+            bail!("{} is not a directory", root.display());
+        }
+        Ok(Self {
+            bucket: bucket.into(),
+            root,
+            uploads: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        })
+    }
+
+    /// `options.endpoint` 为目录，`options.prefix` 对应其中的子目录（不存在时创建）
+    pub fn with_options(bucket: String, options: &ClientOptions) -> Result<Self> {
+        let Some(dir) = &options.endpoint else {
+            bail!("The local provider requires a directory as the endpoint");
+        };
+        let backend = Self::new(bucket, dir)?;
+        let root = backend.root.join(normalize_prefix(&options.prefix));
+        fs::create_dir_all(&root)?;
+        Ok(Self { root, ..backend })
+    }
+
+    /// 对象在目录中的路径；拒绝 `..`、空路径段等会逃出目录或无法往返的键
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        let relative = key.strip_suffix('/').unwrap_or(key);
+        let invalid = relative.is_empty()
+            || relative.split('/').any(|component| {
+                component.is_empty()
+                    || component == "."
+                    || component == ".."
+                    || component.starts_with(TMP_PREFIX)
+            });
+        if invalid {
+            return Err(anyhow!("Invalid object key: {:?}", key));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    /// 与 `path` 同目录的临时文件，写完后改名，读者不会看到写了一半的文件
+    fn temp_path(&self, path: &Path) -> PathBuf {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        path.with_file_name(format!("{}{}-{}", TMP_PREFIX, std::process::id(), id))
+    }
+
+    /// 写入对象内容，返回新的 ETag
+    async fn write(&self, request: &'static str, key: &str, body: &[u8]) -> Result<String> {
+        let path = self.path(key)?;
+        let io = async {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            if key.ends_with('/') {
+                tokio::fs::create_dir_all(&path).await?;
+            } else {
+                let temp = self.temp_path(&path);
+                tokio::fs::write(&temp, body).await?;
+                if let Err(e) = tokio::fs::rename(&temp, &path).await {
+                    let _ = tokio::fs::remove_file(&temp).await;
+                    return Err(e);
+                }
+            }
+            tokio::fs::metadata(&path).await
+        };
+        let metadata = io.await.map_err(|e| io_error(request, key, e))?;
+        Ok(object_meta(key.to_string(), &metadata).etag)
+    }
+}
+
+impl ObjectStore for LocalBackend {
+    fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        format!(
+            "file://{}/{}",
+            self.root.display(),
+            signer::encode_path(key)
+        )
+    }
+
+    /// 每次都重新遍历前缀所在的目录，适合测试与演示规模的目录树
+    async fn list_page(
+        &self,
+        prefix: &str,
+        delimiter: bool,
+        marker: Option<&str>,
+    ) -> Result<ListPage> {
+        let root = self.root.clone();
+        let scan_prefix = prefix.to_string();
+        let objects = tokio::task::spawn_blocking(move || scan(&root, &scan_prefix))
+            .await?
+            .map_err(|e| io_error("LIST", prefix, e))?;
+        Ok(list_sorted(
+            objects.values(),
+            prefix,
+            delimiter,
+            marker,
+            PAGE_SIZE,
+        ))
+    }
+
+    async fn head_object(&self, key: &str) -> Result<ObjectMeta> {
+        let path = self.path(key)?;
+        let metadata = tokio::fs::metadata(&path)
+            .await
+            .map_err(|e| io_error("HEAD", key, e))?;
+        // 目录只能以目录标记访问，文件不能
+        if metadata.is_dir() != key.ends_with('/') {
+            return Err(io_error("HEAD", key, ErrorKind::NotFound.into()));
+        }
+        Ok(object_meta(key.to_string(), &metadata))
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        if key.ends_with('/') {
+            self.head_object(key).await?;
+            return Ok(Bytes::new());
+        }
+        let body = tokio::fs::read(self.path(key)?)
+            .await
+            .map_err(|e| io_error("GET", key, e))?;
+        Ok(Bytes::from(body))
+    }
+
+    async fn get_object_range(&self, key: &str, offset: u64, len: u64) -> Result<Bytes> {
+        if key.ends_with('/') {
+            self.head_object(key).await?;
+            return Ok(Bytes::new());
+        }
+        let path = self.path(key)?;
+        let io = async {
+            let mut file = tokio::fs::File::open(&path).await?;
+            file.seek(SeekFrom::Start(offset)).await?;
+            let mut body = Vec::new();
+            file.take(len).read_to_end(&mut body).await?;
+            Ok::<_, io::Error>(body)
+        };
+        let body = io.await.map_err(|e| io_error("GET", key, e))?;
+        Ok(Bytes::from(body))
+    }
+
+    /// 附加头（Content-Type、自定义元数据等）没有地方保存，忽略
+    async fn put_object(&self, key: &str, body: Bytes, _headers: HeaderMap) -> Result<String> {
+        self.write("PUT", key, &body).await
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        let path = self.path(key)?;
+        let result = if key.ends_with('/') {
+            tokio::fs::remove_dir(&path).await
+        } else {
+            tokio::fs::remove_file(&path).await
+        };
+        match result {
+            // 目录标记之下还有对象时目录本身保留，与对象存储上删除标记的效果一致
+            Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::DirectoryNotEmpty) => {
+                Ok(())
+            }
+            result => result.map_err(|e| io_error("DELETE", key, e)),
+        }
+    }
+
+    async fn copy_object(&self, source_key: &str, dest_key: &str) -> Result<()> {
+        if source_key.ends_with('/') {
+            self.head_object(source_key).await?;
+            self.write("Copy", dest_key, &[]).await?;
+            return Ok(());
+        }
+        let source = self.path(source_key)?;
+        let dest = self.path(dest_key)?;
+        let io = async {
+            if let Some(parent) = dest.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let temp = self.temp_path(&dest);
+            let result = match tokio::fs::copy(&source, &temp).await {
+                Ok(_) => tokio::fs::rename(&temp, &dest).await,
+                Err(e) => Err(e),
+            };
+            if result.is_err() {
+                let _ = tokio::fs::remove_file(&temp).await;
+            }
+            result
+        };
+        io.await.map_err(|e| io_error("Copy", source_key, e))
+    }
+
+    async fn head_bucket(&self) -> Result<()> {
+        match tokio::fs::metadata(&self.root).await {
+            Ok(metadata) if metadata.is_dir() => Ok(()),
+            Ok(_) => Err(StatusError::new("HEAD Bucket", StatusCode::NOT_FOUND).into()),
+            Err(e) => Err(io_error("HEAD Bucket", "", e)),
+        }
+    }
+
+    async fn initiate_multipart_upload(&self, key: &str, _headers: HeaderMap) -> Result<String> {
+        self.path(key)?;
+        let upload_id = format!("{:016x}", self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.uploads.lock().unwrap().insert(
+            upload_id.clone(),
+            Upload {
+                key: key.to_string(),
+                parts: BTreeMap::new(),
+            },
+        );
+        Ok(upload_id)
+    }
+
+    async fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        body: Bytes,
+    ) -> Result<String> {
+        let etag = part_etag(&body);
+        let mut uploads = self.uploads.lock().unwrap();
+        let upload = find_upload(&mut uploads, "Upload Part", key, upload_id)?;
+        upload.parts.insert(part_number, body);
+        Ok(etag)
+    }
+
+    async fn upload_part_copy(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        source_key: &str,
+        source_etag: &str,
+        range: Range<u64>,
+    ) -> Result<String> {
+        let request = "Upload Part - Copy";
+        if self.head_object(source_key).await?.etag != source_etag {
+            return Err(StatusError::new(request, StatusCode::PRECONDITION_FAILED).into());
+        }
+        let body = self
+            .get_object_range(source_key, range.start, range.end - range.start)
+            .await?;
+        let etag = part_etag(&body);
+        let mut uploads = self.uploads.lock().unwrap();
+        let upload = find_upload(&mut uploads, request, key, upload_id)?;
+        upload.parts.insert(part_number, body);
+        Ok(etag)
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[(u32, String)],
+    ) -> Result<String> {
+        let request = "Complete Multipart Upload";
+        let body = {
+            let mut uploads = self.uploads.lock().unwrap();
+            let upload = find_upload(&mut uploads, request, key, upload_id)?;
+            let mut body = Vec::new();
+            for (part_number, etag) in parts {
+                match upload.parts.get(part_number) {
+                    Some(part) if part_etag(part) == *etag => body.extend_from_slice(part),
+                    _ => {
+                        return Err(anyhow::Error::from(StatusError::new(
+                            request,
+                            StatusCode::BAD_REQUEST,
+                        ))
+                        .context(format!("Invalid part {} of {}", part_number, key)))
+                    }
+                }
+            }
+            uploads.remove(upload_id);
+            body
+        };
+        self.write(request, key, &body).await
+    }
+
+    async fn abort_multipart_upload(&self, _key: &str, upload_id: &str) -> Result<()> {
+        self.uploads.lock().unwrap().remove(upload_id);
+        Ok(())
+    }
+}
+
+/// 列出 `root` 下键以 `prefix` 开头的对象，只遍历前缀可能涉及的目录
+fn scan(root: &Path, prefix: &str) -> io::Result<BTreeMap<String, ObjectMeta>> {
+    let mut objects = BTreeMap::new();
+    let start = &prefix[..prefix.rfind('/').map_or(0, |i| i + 1)];
+    let mut pending = vec![start.to_string()];
+    while let Some(dir_key) = pending.pop() {
+        let entries = match fs::read_dir(root.join(&dir_key)) {
+            Ok(entries) => entries,
+            Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory) => {
+                continue
+            }
+            Err(e) => return Err(e),
+        };
+        let mut empty = true;
+        for entry in entries {
+            let entry = entry?;
+            empty = false;
+            // 不是 UTF-8 的名字无法作为对象键
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            if name.starts_with(TMP_PREFIX) {
+                continue;
+            }
+            // 跟随符号链接，悬空的链接跳过
+            let Ok(metadata) = fs::metadata(entry.path()) else {
+                continue;
+            };
+            let key = format!("{}{}", dir_key, name);
+            if metadata.is_dir() {
+                let dir = format!("{}/", key);
+                if dir.starts_with(prefix) || prefix.starts_with(&dir) {
+                    pending.push(dir);
+                }
+            } else if metadata.is_file() && key.starts_with(prefix) {
+                objects.insert(key.clone(), object_meta(key, &metadata));
+            }
+        }
+        if empty && !dir_key.is_empty() && dir_key.starts_with(prefix) {
+            let metadata = fs::metadata(root.join(&dir_key))?;
+            objects.insert(dir_key.clone(), object_meta(dir_key, &metadata));
+        }
+    }
+    Ok(objects)
+}
+
+/// 文件的元数据；ETag 由 inode、修改时间与大小组成，文件被替换或修改后随之变化
+fn object_meta(key: String, metadata: &Metadata) -> ObjectMeta {
+    let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+    let nanos = modified
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let size = if metadata.is_dir() { 0 } else { metadata.len() };
+    ObjectMeta {
+        key,
+        size,
+        last_modified: modified,
+        etag: format!("\"{:x}-{:x}-{:x}\"", metadata.ino(), nanos, size),
+        content_type: None,
+        server_side_encryption: None,
+        details: Default::default(),
+    }
+}
+
+/// 分块的 ETag：内容的 MD5，与对象存储一致
+fn part_etag(body: &[u8]) -> String {
+    format!("\"{:x}\"", md5::compute(body))
+}
+
+/// 属于 `key` 的分块上传，不存在时答复 404
+fn find_upload<'a>(
+    uploads: &'a mut HashMap<String, Upload>,
+    request: &'static str,
+    key: &str,
+    upload_id: &str,
+) -> Result<&'a mut Upload> {
+    match uploads.get_mut(upload_id) {
+        Some(upload) if upload.key == key => Ok(upload),
+        _ => Err(
+            anyhow::Error::from(StatusError::new(request, StatusCode::NOT_FOUND))
+                .context(format!("No such upload {} for {}", upload_id, key)),
+        ),
+    }
+}
+
+/// 本地文件操作的错误换成对象存储的状态码：不存在为 404，没有权限为 403
+fn io_error(request: &'static str, key: &str, error: io::Error) -> anyhow::Error {
+    let status = match error.kind() {
+        ErrorKind::NotFound | ErrorKind::NotADirectory | ErrorKind::IsADirectory => {
+            StatusCode::NOT_FOUND
+        }
+        ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+        _ => return anyhow::Error::from(error).context(format!("{} {} failed", request, key)),
+    };
+    let context = match status {
+        StatusCode::NOT_FOUND => format!("Object not found: {}", key),
+        _ => format!("{}: {}", key, error),
+    };
+    anyhow::Error::from(StatusError::new(request, status)).context(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cos_client::error_status;
+
+    async fn put(backend: &LocalBackend, key: &str, body: &str) -> String {
+        backend
+            .put_object(key, Bytes::from(body.to_string()), HeaderMap::new())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = LocalBackend::new("demo", dir.path()).unwrap();
+        backend.head_bucket().await.unwrap();
+
+        let etag = put(&backend, "docs/readme.txt", "hello world").await;
+        assert_eq!(
+            fs::read_to_string(dir.path().join("docs/readme.txt")).unwrap(),
+            "hello world"
+        );
+        let meta = backend.head_object("docs/readme.txt").await.unwrap();
+        assert_eq!((meta.size, meta.etag.as_str()), (11, etag.as_str()));
+        assert_eq!(
+            backend
+                .get_object_range("docs/readme.txt", 6, 100)
+                .await
+                .unwrap(),
+            "world"
+        );
+        assert!(backend
+            .get_object_range("docs/readme.txt", 20, 5)
+            .await
+            .unwrap()
+            .is_empty());
+        // 覆盖后 ETag 变化
+        assert_ne!(put(&backend, "docs/readme.txt", "hello").await, etag);
+
+        backend
+            .copy_object("docs/readme.txt", "backup/readme.txt")
+            .await
+            .unwrap();
+        assert_eq!(
+            backend.get_object("backup/readme.txt").await.unwrap(),
+            "hello"
+        );
+
+        // 不存在的对象、目录当作文件访问都答复 404，删除不存在的对象视为成功
+        for key in ["missing", "docs", "docs/readme.txt/"] {
+            let e = backend.head_object(key).await.unwrap_err();
+            assert_eq!(error_status(&e), Some(StatusCode::NOT_FOUND), "{}", key);
+        }
+        let e = backend.get_object("missing").await.unwrap_err();
+        assert_eq!(error_status(&e), Some(StatusCode::NOT_FOUND));
+        backend.delete_object("backup/readme.txt").await.unwrap();
+        backend.delete_object("backup/readme.txt").await.unwrap();
+        assert!(!dir.path().join("backup/readme.txt").exists());
+
+        for key in ["../escape", "a//b", "./a", ""] {
+            assert!(backend.path(key).is_err(), "{}", key);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = LocalBackend::new("demo", dir.path()).unwrap();
+        for key in ["a.txt", "logs/1.log", "logs/2.log", "logs/old/0.log"] {
+            put(&backend, key, key).await;
+        }
+        put(&backend, "empty/", "").await;
+        fs::write(dir.path().join(format!("{}1", TMP_PREFIX)), "partial").unwrap();
+
+        let keys: Vec<_> = backend
+            .list_objects()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.key)
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "a.txt",
+                "empty/",
+                "logs/1.log",
+                "logs/2.log",
+                "logs/old/0.log"
+            ]
+        );
+
+        let listing = backend.list_dir("logs/").await.unwrap();
+        let keys: Vec<_> = listing.objects.iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(keys, ["logs/1.log", "logs/2.log"]);
+        assert_eq!(listing.prefixes, ["logs/old/"]);
+        assert_eq!(
+            backend.list_first_key_under("logs/o").await.unwrap(),
+            Some("logs/old/0.log".to_string())
+        );
+        assert!(backend
+            .list_dir("nothing/")
+            .await
+            .unwrap()
+            .objects
+            .is_empty());
+
+        // 目录标记之下还有对象时删除标记不影响对象
+        backend.delete_object("logs/").await.unwrap();
+        assert!(backend.head_object("logs/1.log").await.is_ok());
+        backend.delete_object("empty/").await.unwrap();
+        assert!(!dir.path().join("empty").exists());
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = LocalBackend::new("demo", dir.path()).unwrap();
+        let source_etag = put(&backend, "source", "0123456789").await;
+
+        let upload_id = backend
+            .initiate_multipart_upload("big", HeaderMap::new())
+            .await
+            .unwrap();
+        let first = backend
+            .upload_part("big", &upload_id, 1, Bytes::from("abc"))
+            .await
+            .unwrap();
+        let second = backend
+            .upload_part_copy("big", &upload_id, 2, "source", &source_etag, 2..5)
+            .await
+            .unwrap();
+        let e = backend
+            .upload_part_copy("big", &upload_id, 3, "source", "\"stale\"", 0..1)
+            .await
+            .unwrap_err();
+        assert_eq!(error_status(&e), Some(StatusCode::PRECONDITION_FAILED));
+
+        backend
+            .complete_multipart_upload("big", &upload_id, &[(1, first), (2, second)])
+            .await
+            .unwrap();
+        assert_eq!(backend.get_object("big").await.unwrap(), "abc234");
+        // 合并后上传不再存在
+        let e = backend
+            .upload_part("big", &upload_id, 3, Bytes::new())
+            .await
+            .unwrap_err();
+        assert_eq!(error_status(&e), Some(StatusCode::NOT_FOUND));
+    }
+}
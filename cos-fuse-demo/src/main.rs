@@ -1,15 +1,45 @@
+use clap::parser::ValueSource;
 use clap::{Arg, Command};
 use fuser::{spawn_mount2, MountOption};
 use log::{error, info};
-use std::path::PathBuf;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
-mod cache;
-mod cos_client;
-mod filesystem;
-
-use filesystem::CosFilesystem;
+use cosfs::atime::AtimeMode;
+use cosfs::backend::Provider;
+use cosfs::builder::{DEFAULT_CACHE_DIR, DEFAULT_SPOOL_DIR};
+use cosfs::cache_policy::{CachePolicies, CachePolicy, CACHE_SECTION};
+use cosfs::commands;
+use cosfs::compression::CacheCompression;
+use cosfs::config::{
+    headers_from_config, parse_block_size, parse_duration, parse_header, parse_interval,
+    parse_size, parse_thread_count, parse_transfer_size, AttrOptions, CacheOptions, RuntimeOptions,
+    TransferSizes, HEADERS_SECTION,
+};
+use cosfs::config_file::ConfigFile;
+use cosfs::eviction::EvictionPolicyKind;
+use cosfs::idmap::{IdMap, IDMAP_SECTION};
+use cosfs::limiter::RequestLimits;
+use cosfs::manifest::{Manifest, Snapshot};
+use cosfs::metrics::{MetricLabels, StatsdOptions, DEFAULT_STATSD_PREFIX};
+use cosfs::mount_lock::MountLock;
+use cosfs::mount_options::{
+    default_mount_options, expand_mount_options, merge_mount_options, parse_fuse_option,
+    platform_args, FUSE_OPTION_ARG,
+};
+use cosfs::oss_signer;
+use cosfs::permissions::{PermissionOverrides, PERM_SECTION};
+use cosfs::profile::{default_config_path, Profile, PROFILE_SECTION};
+use cosfs::recording::{BodyMode, Recording};
+use cosfs::signer::{self, Credentials};
+use cosfs::sigv4;
+use cosfs::spool::{FsyncPolicy, SpoolOptions};
+use cosfs::system_xattrs::SystemXattrs;
+use cosfs::trash::Trash;
+use cosfs::upload::{UploadOptions, UPLOAD_SECTION};
+use cosfs::{sd_notify, supervise, CosFilesystemBuilder};
 
 fn main() {
     // 初始化日志
@@ -17,73 +47,353 @@ fn main() {
         .filter_level(log::LevelFilter::Info)
         .init();
 
-    let matches = Command::new("cos-fuse-demo")
-        .version("0.1.0")
-        .about("A demo FUSE filesystem that mounts Tencent Cloud COS as a local filesystem")
-        .arg(
-            Arg::new("bucket")
-                .short('b')
-                .long("bucket")
-                .value_name("BUCKET")
-                .help("Tencent Cloud COS bucket name")
-                .required(true),
-        )
-        .arg(
-            Arg::new("region")
-                .short('r')
-                .long("region")
-                .value_name("REGION")
-                .help("Tencent Cloud COS region (e.g., ap-beijing)")
-                .required(true),
-        )
-        .arg(
-            Arg::new("mount-point")
-                .short('m')
-                .long("mount-point")
-                .value_name("MOUNT_POINT")
-                .help("Directory to mount the filesystem")
-                .required(true),
-        )
-        .arg(
-            Arg::new("cache-dir")
-                .short('c')
-                .long("cache-dir")
-                .value_name("CACHE_DIR")
-                .help("Directory for file content cache")
-                .default_value("/tmp/cosfs_cache"),
-        )
-        .arg(
-            Arg::new("foreground")
-                .short('f')
-                .long("foreground")
-                .help("Run in foreground")
-                .action(clap::ArgAction::SetTrue),
-        )
-        .arg(
-            Arg::new("debug")
-                .short('d')
-                .long("debug")
-                .help("Enable debug logging")
-                .action(clap::ArgAction::SetTrue),
-        )
-        .get_matches();
+    let command = cli();
+    let args = expand_mount_options(&command, std::env::args_os());
+    let matches = command.get_matches_from(args.clone());
 
     // 设置日志级别
     if matches.get_flag("debug") {
         log::set_max_level(log::LevelFilter::Debug);
     }
 
-    let bucket = matches.get_one::<String>("bucket").unwrap().clone();
-    let region = matches.get_one::<String>("region").unwrap().clone();
-    let mount_point = matches.get_one::<String>("mount-point").unwrap().clone();
-    let cache_dir = matches.get_one::<String>("cache-dir").unwrap().clone();
+    if let Some((name, sub)) = matches.subcommand() {
+        let cache_dir = PathBuf::from(matches.get_one::<String>("cache-dir").unwrap());
+        let result = match name {
+            "cache" => commands::cache::run(sub, &cache_dir),
+            "check" => commands::check::run(sub, cli_headers(sub)),
+            "daemon" => commands::daemon::run(
+                sub,
+                &cache_dir,
+                cli_headers(sub),
+                statsd_options(sub, MetricLabels::default()),
+            ),
+            "export" => commands::export::run(sub, cli_headers(sub)),
+            "ls" => commands::ls::run(sub, cli_headers(sub)),
+            "presign" => commands::presign::run(sub),
+            "sync" => commands::sync::run(sub, cli_headers(sub)),
+            "trash" => commands::trash::run(sub, cli_headers(sub)),
+            "verify" => commands::verify::run(sub, cli_headers(sub)),
+            _ => unreachable!("unknown subcommand: {}", name),
+        };
+        if let Err(e) = result {
+            error!("{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if matches.get_flag("supervise") {
+        let mount_point = matches
+            .get_one::<String>("mount-point")
+            .or_else(|| matches.get_one::<String>("target"))
+            .unwrap();
+        let interval = *matches.get_one::<Duration>("supervise-interval").unwrap();
+        if let Err(e) = supervise::run(args, Path::new(mount_point), interval) {
+            error!("{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // 指定了 --profile 而没有 --config 时读取默认配置文件
+    let profile_name = matches.get_one::<String>("profile");
+    let config_path = matches
+        .get_one::<PathBuf>("config")
+        .cloned()
+        .or_else(|| profile_name.and_then(|_| default_config_path()));
+    let config_file = match config_path {
+        Some(path) => match ConfigFile::load(&path).and_then(|config| {
+            config.check_sections(&[
+                HEADERS_SECTION,
+                PERM_SECTION,
+                IDMAP_SECTION,
+                UPLOAD_SECTION,
+                PROFILE_SECTION,
+                CACHE_SECTION,
+            ])?;
+            Ok(config)
+        }) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("{:#}", e);
+                std::process::exit(1);
+            }
+        },
+        None => ConfigFile::default(),
+    };
+    let profile = match profile_name {
+        Some(name) => match Profile::from_config(&config_file, name) {
+            Ok(profile) => profile,
+            Err(e) => {
+                error!("Invalid profile: {:#}", e);
+                std::process::exit(1);
+            }
+        },
+        None => Profile::default(),
+    };
+
+    let snapshot = match matches.get_one::<PathBuf>("manifest") {
+        Some(path) => match Manifest::load(path).and_then(Snapshot::from_manifest) {
+            Ok(snapshot) => Some(Arc::new(snapshot)),
+            Err(e) => {
+                error!("{:#}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // 命令行显式指定的参数优先于 profile，快照挂载默认使用清单中的 bucket
+    let Some(bucket) = matches
+        .get_one::<String>("bucket")
+        .or_else(|| matches.get_one::<String>("source"))
+        .or(profile.bucket.as_ref())
+        .cloned()
+        .or_else(|| {
+            snapshot
+                .as_ref()
+                .map(|snapshot| snapshot.bucket().to_string())
+        })
+    else {
+        error!("--bucket is required (or set bucket in the profile)");
+        std::process::exit(1);
+    };
+    if let Some(snapshot) = &snapshot {
+        if snapshot.bucket() != bucket {
+            error!(
+                "Manifest is for bucket {}, not {}",
+                snapshot.bucket(),
+                bucket
+            );
+            std::process::exit(1);
+        }
+    }
+    let prefix = snapshot
+        .as_ref()
+        .map_or_else(String::new, |snapshot| snapshot.prefix().to_string());
+    let provider = match (matches.value_source("provider"), profile.provider) {
+        (Some(ValueSource::CommandLine), _) | (_, None) => {
+            *matches.get_one::<Provider>("provider").unwrap()
+        }
+        (_, Some(profile_provider)) => profile_provider,
+    };
+    let endpoint = matches
+        .get_one::<String>("endpoint")
+        .or(profile.endpoint.as_ref())
+        .cloned();
+    // S3 兼容服务可以不指定地域，由客户端使用默认地域签名；OSS 指定访问域名后不需要地域
+    let Some(region) = matches
+        .get_one::<String>("region")
+        .or(profile.region.as_ref())
+        .cloned()
+        .or_else(|| match provider {
+            Provider::S3 | Provider::Local => Some(String::new()),
+            Provider::Oss => endpoint.as_ref().map(|_| String::new()),
+            Provider::Cos => None,
+        })
+    else {
+        error!("--region is required (or set region in the profile)");
+        std::process::exit(1);
+    };
+    let mount_point = matches
+        .get_one::<String>("mount-point")
+        .or_else(|| matches.get_one::<String>("target"))
+        .unwrap()
+        .clone();
+    let cache_dir = match (matches.value_source("cache-dir"), &profile.cache_dir) {
+        (Some(ValueSource::CommandLine), _) | (_, None) => {
+            matches.get_one::<String>("cache-dir").unwrap().clone()
+        }
+        (_, Some(profile_cache_dir)) => profile_cache_dir.clone(),
+    };
     let foreground = matches.get_flag("foreground");
+    let cache_policies = match CachePolicies::from_config(
+        &config_file,
+        *matches.get_one::<CachePolicy>("cache-policy").unwrap(),
+    ) {
+        Ok(policies) => policies,
+        Err(e) => {
+            error!("Invalid cache policies: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let cache_options = CacheOptions {
+        metadata_cache_size: *matches.get_one::<usize>("metadata-cache-size").unwrap(),
+        metadata_ttl: *matches.get_one::<Duration>("metadata-ttl").unwrap(),
+        stale_while_revalidate: matches
+            .get_one::<Duration>("stale-while-revalidate")
+            .copied(),
+        content_cache_min_object_size: *matches
+            .get_one::<u64>("content-cache-min-object-size")
+            .unwrap(),
+        content_cache_max_object_size: matches
+            .get_one::<u64>("content-cache-max-object-size")
+            .copied(),
+        content_cache_max_size: matches.get_one::<u64>("cache-max-size").copied(),
+        block_size: *matches.get_one::<u64>("cache-block-size").unwrap(),
+        readahead_min: *matches.get_one::<u64>("readahead-min").unwrap(),
+        readahead_max: *matches.get_one::<u64>("readahead-max").unwrap(),
+        cache_policies,
+        eviction_policy: *matches
+            .get_one::<EvictionPolicyKind>("cache-eviction")
+            .unwrap(),
+        compression: *matches
+            .get_one::<CacheCompression>("cache-compression")
+            .unwrap(),
+        janitor_interval: *matches
+            .get_one::<Duration>("cache-janitor-interval")
+            .unwrap(),
+        prefetch_manifest: matches.get_one::<PathBuf>("prefetch-manifest").cloned(),
+        revalidate_misses: matches.get_flag("revalidate-misses"),
+        refresh_interval: matches
+            .get_one::<Duration>("refresh-interval")
+            .copied()
+            .filter(|interval| !interval.is_zero()),
+        snapshot: snapshot.clone(),
+    };
+    let request_limits = RequestLimits {
+        max_requests: *matches.get_one::<usize>("max-requests").unwrap(),
+        max_metadata_requests: *matches.get_one::<usize>("max-metadata-requests").unwrap(),
+        max_data_requests: *matches.get_one::<usize>("max-data-requests").unwrap(),
+    };
+    let runtime_options = RuntimeOptions {
+        io_threads: matches.get_one::<usize>("io-threads").copied(),
+        blocking_threads: matches.get_one::<usize>("blocking-threads").copied(),
+        op_timeout: matches
+            .get_one::<Duration>("op-timeout")
+            .copied()
+            .filter(|timeout| !timeout.is_zero()),
+        statsd: statsd_options(
+            &matches,
+            MetricLabels {
+                bucket: bucket.clone(),
+                prefix: String::new(),
+                mountpoint: mount_point.clone(),
+            },
+        ),
+        uid_rate_limit: matches.get_one::<u64>("uid-rate-limit").copied(),
+        memory_limit: matches.get_one::<u64>("memory-limit").copied(),
+        transfer: TransferSizes {
+            max_read: *matches.get_one::<u32>("max-read").unwrap(),
+            max_write: *matches.get_one::<u32>("max-write").unwrap(),
+        },
+        ..Default::default()
+    };
+    let permissions = match PermissionOverrides::from_config(&config_file) {
+        Ok(permissions) => permissions,
+        Err(e) => {
+            error!("Invalid permission rules: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let id_map = match IdMap::from_config(&config_file) {
+        Ok(id_map) => id_map,
+        Err(e) => {
+            error!("Invalid id mapping: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let hires_mtime = matches.get_flag("hires-mtime");
+    let mut upload_options = match UploadOptions::from_config(&config_file) {
+        Ok(upload_options) => upload_options,
+        Err(e) => {
+            error!("Invalid upload settings: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let s3fs_compat = matches.get_flag("s3fs-compat");
+    upload_options.store_mtime = hires_mtime;
+    upload_options.s3fs_compat = s3fs_compat;
+    if let Some(prefix) = matches.get_one::<String>("trash-prefix") {
+        upload_options.trash = Some(Trash::new(prefix));
+    }
+    if let Some(trash) = &upload_options.trash {
+        info!("Deleted objects will be moved to {}", trash.prefix());
+    }
+    let mut extra_headers = match headers_from_config(&config_file) {
+        Ok(headers) => headers,
+        Err(e) => {
+            error!("Invalid extra headers: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    extra_headers.extend(cli_headers(&matches));
+    // 命令行的密钥优先于配置，配置优先于环境变量
+    let credentials = match (
+        matches.get_one::<String>("secret-id"),
+        matches.get_one::<String>("secret-key"),
+    ) {
+        (Some(secret_id), Some(secret_key)) => Some(Credentials {
+            secret_id: secret_id.clone(),
+            secret_key: secret_key.clone(),
+            session_token: matches
+                .get_one::<String>("session-token")
+                .cloned()
+                .or_else(|| std::env::var(signer::SESSION_TOKEN_ENV).ok())
+                .filter(|token| !token.is_empty()),
+        }),
+        _ => profile
+            .credentials
+            .clone()
+            .or_else(Credentials::from_env)
+            .or_else(|| match provider {
+                Provider::S3 => sigv4::credentials_from_env(),
+                Provider::Oss => oss_signer::credentials_from_env(),
+                Provider::Cos | Provider::Local => None,
+            }),
+    };
+    match &credentials {
+        Some(credentials) => info!("Signing requests with SecretId {}", credentials.secret_id),
+        None => info!("No credentials given, accessing the bucket anonymously"),
+    }
+    let acl_permissions = matches.get_flag("acl-permissions");
+    for flag in ["acl-permissions", "image-variants"] {
+        if matches.get_flag(flag) && provider != Provider::Cos {
+            error!("--{} is only supported with --provider cos", flag);
+            std::process::exit(1);
+        }
+    }
+    if acl_permissions && credentials.is_none() {
+        error!(
+            "--acl-permissions requires {} and {}",
+            signer::SECRET_ID_ENV,
+            signer::SECRET_KEY_ENV
+        );
+        std::process::exit(1);
+    }
+    let attr_options = AttrOptions {
+        atime: *matches.get_one::<AtimeMode>("atime").unwrap(),
+        permissions,
+        id_map,
+        presign_expires: *matches.get_one::<Duration>("presign-expires").unwrap(),
+        acl_permissions,
+        hires_mtime,
+        s3fs_compat,
+        browse_archives: matches.get_flag("browse-archives"),
+        image_variants: matches.get_flag("image-variants"),
+        system_xattrs: matches
+            .get_one::<SystemXattrs>("system-xattrs")
+            .unwrap()
+            .clone(),
+    };
+    let spool_options = SpoolOptions {
+        dir: PathBuf::from(matches.get_one::<String>("spool-dir").unwrap()),
+        max_size: matches.get_one::<u64>("spool-max-size").copied(),
+        fsync: *matches.get_one::<FsyncPolicy>("spool-fsync").unwrap(),
+    };
 
     info!("Starting COS FUSE filesystem");
+    info!("Provider: {}", provider);
     info!("Bucket: {}", bucket);
     info!("Region: {}", region);
     info!("Mount point: {}", mount_point);
     info!("Cache directory: {}", cache_dir);
+    info!("Cache options: {:?}", cache_options);
+    info!("Spool options: {:?}", spool_options);
+    info!("Request limits: {:?}", request_limits);
+    info!("Extra request headers: {:?}", extra_headers);
+    info!("Runtime options: {:?}", runtime_options);
+    info!("Attr options: {:?}", attr_options);
 
     // 验证挂载点
     let mount_path = PathBuf::from(&mount_point);
@@ -97,16 +407,83 @@ fn main() {
         std::process::exit(1);
     }
 
+    let default_options = default_mount_options(&bucket, &matches);
+
+    // 挂载期间持有，防止另一个实例共用缓存目录或重复挂载
+    let _mount_lock = match MountLock::acquire(
+        &bucket,
+        &prefix,
+        Path::new(&cache_dir),
+        &mount_path,
+        matches.get_flag("force"),
+    ) {
+        Ok(lock) => lock,
+        Err(e) => {
+            error!("{:#}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let recording = match (
+        matches.get_one::<PathBuf>("record"),
+        matches.get_one::<PathBuf>("replay"),
+    ) {
+        (Some(path), _) => Some(Recording::record(
+            path,
+            *matches.get_one::<BodyMode>("record-bodies").unwrap(),
+        )),
+        (None, Some(path)) => Some(Recording::replay(path)),
+        (None, None) => None,
+    };
+    let recording = match recording.transpose() {
+        Ok(recording) => recording,
+        Err(e) => {
+            error!("{:#}", e);
+            std::process::exit(1);
+        }
+    };
+
     // 创建文件系统实例
-    let cache_path = PathBuf::from(cache_dir);
-    let fs = match CosFilesystem::new(bucket, region, &cache_path) {
+    let mut builder = CosFilesystemBuilder::new(bucket, region)
+        .provider(provider)
+        .prefix(prefix)
+        .extra_headers(extra_headers)
+        .request_limits(request_limits)
+        .cache_dir(cache_dir)
+        .cache_options(cache_options)
+        .spool_options(spool_options)
+        .runtime_options(runtime_options)
+        .attr_options(attr_options)
+        .upload_options(upload_options);
+    if let Some(endpoint) = endpoint {
+        builder = builder.endpoint(endpoint);
+    }
+    if let Some(credentials) = credentials {
+        builder = builder.credentials(credentials);
+    }
+    if let Some(recording) = recording {
+        builder = builder.recording(Arc::new(recording));
+    }
+    let fs = match builder.build() {
         Ok(fs) => fs,
         Err(e) => {
-            error!("Failed to create filesystem: {}", e);
+            error!("Failed to create filesystem: {:#}", e);
             std::process::exit(1);
         }
     };
 
+    // 挂载前确认权限，缺少的权限在这里说明，而不是挂载后以 EIO 失败；
+    // 快照挂载不列出实时的桶，读取权限在打开文件时才检查
+    if let Some(snapshot) = &snapshot {
+        info!(
+            "Mounting a read-only snapshot of {} objects from the manifest",
+            snapshot.object_count()
+        );
+    } else if let Err(e) = fs.probe(matches.get_flag("probe-write")) {
+        error!("Bucket permission check failed: {:#}", e);
+        std::process::exit(1);
+    }
+
     // 检查挂载点是否为空目录
     let is_empty = match mount_path.read_dir() {
         Ok(mut entries) => entries.next().is_none(),
@@ -123,15 +500,17 @@ fn main() {
 
     info!("Mounting filesystem...");
 
-    // 设置挂载选项
-    let options = vec![
-        MountOption::RO,                          // 只读模式（COS是只读的）
-        MountOption::FSName("cosfs".to_string()), // 文件系统名称
-        MountOption::AutoUnmount,                 // 自动卸载
-        MountOption::NoDev,                       // 禁用设备文件
-        MountOption::NoSuid,                      // 禁用SUID
-        MountOption::NoExec,                      // 禁用执行权限
-    ];
+    // 设置挂载选项，-o 透传的 FUSE 选项覆盖默认值
+    let fuse_options = matches
+        .get_many::<String>(FUSE_OPTION_ARG)
+        .unwrap_or_default()
+        .map(|option| parse_fuse_option(option))
+        .collect();
+    let mut options = merge_mount_options(default_options, fuse_options);
+    if snapshot.is_some() {
+        options = merge_mount_options(options, vec![MountOption::RO]);
+    }
+    info!("Mount options: {:?}", options);
 
     // 挂载文件系统
     match spawn_mount2(fs, &mount_path, &options) {
@@ -146,6 +525,11 @@ fn main() {
             // 前台模式：等待信号
             info!("Running in foreground mode. Press Ctrl+C to unmount.");
 
+            // 由 systemd 启动时，首次列表完成、挂载可用后才报告就绪
+            let mount_points = vec![mount_path.clone()];
+            sd_notify::notify_ready(&mount_points);
+            let _watchdog = sd_notify::spawn_watchdog(mount_points);
+
             // 设置信号处理
             let (tx, rx) = std::sync::mpsc::channel();
 
@@ -158,6 +542,7 @@ fn main() {
             // 等待信号
             if rx.recv().is_ok() {
                 info!("Unmounting filesystem...");
+                let _ = sd_notify::notify("STOPPING=1");
                 // session 会在 drop 时自动卸载
             }
         }
@@ -168,14 +553,643 @@ fn main() {
     }
 }
 
+/// `--statsd` 等参数指定的推送配置，`labels` 为指标所属的挂载
+fn statsd_options(matches: &clap::ArgMatches, labels: MetricLabels) -> Option<StatsdOptions> {
+    matches
+        .get_one::<String>("statsd")
+        .map(|addr| StatsdOptions {
+            addr: addr.clone(),
+            prefix: matches.get_one::<String>("statsd-prefix").unwrap().clone(),
+            interval: *matches.get_one::<Duration>("statsd-interval").unwrap(),
+            tags: matches.get_flag("statsd-tags"),
+            labels,
+        })
+}
+
+/// `--header` 指定的附加请求头
+fn cli_headers(matches: &clap::ArgMatches) -> HeaderMap {
+    matches
+        .get_many::<(HeaderName, HeaderValue)>("header")
+        .unwrap_or_default()
+        .cloned()
+        .collect()
+}
+
+fn cli() -> Command {
+    Command::new("cos-fuse-demo")
+        .version("0.1.0")
+        .about("A demo FUSE filesystem that mounts Tencent Cloud COS as a local filesystem")
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
+        .subcommand(commands::cache::command())
+        .subcommand(commands::check::command())
+        .subcommand(commands::daemon::command())
+        .subcommand(commands::export::command())
+        .subcommand(commands::ls::command())
+        .subcommand(commands::presign::command())
+        .subcommand(commands::sync::command())
+        .subcommand(commands::trash::command())
+        .subcommand(commands::verify::command())
+        .arg(
+            Arg::new("bucket")
+                .short('b')
+                .long("bucket")
+                .value_name("BUCKET")
+                .help("Tencent Cloud COS bucket name")
+                .required_unless_present_any(["source", "profile", "manifest"])
+                .conflicts_with("source"),
+        )
+        .arg(
+            Arg::new("provider")
+                .long("provider")
+                .value_name("PROVIDER")
+                .help("Object storage provider: cos, s3 (Amazon S3 and S3-compatible services such as MinIO) oss (Aliyun OSS) or local (the directory given with --endpoint, for offline demos)")
+                .value_parser(clap::value_parser!(Provider))
+                .default_value("cos"),
+        )
+        .arg(
+            Arg::new("region")
+                .short('r')
+                .long("region")
+                .value_name("REGION")
+                .help("Tencent Cloud COS region (e.g., ap-beijing); optional for --provider s3 (default: us-east-1); cn-hangzhou etc. for --provider oss"),
+        )
+        .arg(
+            Arg::new("endpoint")
+                .long("endpoint")
+                .value_name("HOST")
+                .help("Endpoint without the bucket, e.g. cos.accelerate.myqcloud.com (default: cos.<region>.myqcloud.com); for --provider s3 the service URL, e.g. http://minio:9000; for --provider oss e.g. oss-cn-hangzhou-internal.aliyuncs.com; for --provider local the directory"),
+        )
+        .arg(
+            Arg::new("secret-id")
+                .long("secret-id")
+                .value_name("ID")
+                .help("SecretId for signing requests; visible to other users in the process list, prefer COS_SECRET_ID or a profile")
+                .requires("secret-key"),
+        )
+        .arg(
+            Arg::new("secret-key")
+                .long("secret-key")
+                .value_name("KEY")
+                .help("SecretKey for signing requests (default: from the profile, then COS_SECRET_KEY, then AWS_SECRET_ACCESS_KEY for --provider s3 or OSS_ACCESS_KEY_SECRET for --provider oss)")
+                .requires("secret-id"),
+        )
+        .arg(
+            Arg::new("session-token")
+                .long("session-token")
+                .value_name("TOKEN")
+                .help("Session token of temporary credentials given with --secret-id/--secret-key")
+                .requires("secret-id"),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .value_name("NAME")
+                .help("Use provider, bucket, region, endpoint, cache dir and credentials from [profile.\"NAME\"] in the config file (default file: ~/.config/cosfs/config)"),
+        )
+        .arg(
+            Arg::new("mount-point")
+                .short('m')
+                .long("mount-point")
+                .value_name("MOUNT_POINT")
+                .help("Directory to mount the filesystem")
+                .required_unless_present("target")
+                .conflicts_with("target"),
+        )
+        .arg(
+            Arg::new("source")
+                .value_name("BUCKET")
+                .help("Bucket name, as passed by mount(8) / fstab (alternative to --bucket)")
+                .index(1),
+        )
+        .arg(
+            Arg::new("target")
+                .value_name("MOUNT_POINT")
+                .help("Mount point, as passed by mount(8) / fstab (alternative to --mount-point)")
+                .index(2),
+        )
+        .arg(
+            Arg::new("options")
+                .short('o')
+                .value_name("OPTIONS")
+                .help(
+                    "Comma-separated mount options: keys matching a long option (e.g. \
+                     region=ap-beijing,cache_dir=/var/cache/cosfs) set it, others are passed to FUSE",
+                ),
+        )
+        .arg(
+            Arg::new(FUSE_OPTION_ARG)
+                .long(FUSE_OPTION_ARG)
+                .value_name("OPTION")
+                .hide(true)
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("cache-dir")
+                .short('c')
+                .long("cache-dir")
+                .value_name("CACHE_DIR")
+                .help("Directory for file content cache")
+                .global(true)
+                .default_value(DEFAULT_CACHE_DIR),
+        )
+        .arg(
+            Arg::new("header")
+                .long("header")
+                .value_name("NAME: VALUE")
+                .help("Extra header added to every COS request (e.g. traffic tagging); repeatable")
+                .global(true)
+                .value_parser(parse_header)
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("FILE")
+                .help("Mount config file, e.g. per-prefix [perm.\"datasets/\"] uid/gid/mode overrides, [idmap] uid mapping and [upload] headers")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("metadata-cache-size")
+                .long("metadata-cache-size")
+                .value_name("ENTRIES")
+                .help("Maximum number of entries in the metadata cache")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("1000"),
+        )
+        .arg(
+            Arg::new("metadata-ttl")
+                .long("metadata-ttl")
+                .value_name("DURATION")
+                .help("How long cached metadata is trusted (e.g. 30s, 5m)")
+                .value_parser(parse_duration)
+                .default_value("5m"),
+        )
+        .arg(
+            Arg::new("stale-while-revalidate")
+                .long("stale-while-revalidate")
+                .value_name("DURATION")
+                .help(
+                    "Serve cached data up to this long past --metadata-ttl while \
+                     revalidating it in the background (e.g. 10m)",
+                )
+                .value_parser(parse_duration),
+        )
+        .arg(
+            Arg::new("revalidate-misses")
+                .long("revalidate-misses")
+                .help(
+                    "Check COS for names missing from the cached listing before \
+                     returning ENOENT, so objects uploaded elsewhere appear immediately",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("refresh-interval")
+                .long("refresh-interval")
+                .value_name("DURATION")
+                .help(
+                    "Re-list the directories that have been listed or looked into this often, \
+                     one level each (0 disables; the listing is otherwise only loaded at mount)",
+                )
+                .value_parser(parse_duration)
+                .default_value("0s"),
+        )
+        .arg(
+            Arg::new("content-cache-min-object-size")
+                .long("content-cache-min-object-size")
+                .value_name("SIZE")
+                .help("Objects smaller than this are not stored in the content cache (e.g. 4K)")
+                .value_parser(parse_size)
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("content-cache-max-object-size")
+                .long("content-cache-max-object-size")
+                .value_name("SIZE")
+                .help("Objects larger than this are not stored in the content cache (e.g. 1G)")
+                .value_parser(parse_size),
+        )
+        .arg(
+            Arg::new("cache-block-size")
+                .long("cache-block-size")
+                .value_name("SIZE")
+                .help("Block size of the chunked content cache; a power of two between 64K and 64M")
+                .value_parser(parse_block_size)
+                .default_value("1M"),
+        )
+        .arg(
+            Arg::new("readahead-min")
+                .long("readahead-min")
+                .value_name("SIZE")
+                .help("Initial readahead window for sequential or backward reads of a file handle")
+                .value_parser(parse_size)
+                .default_value("1M"),
+        )
+        .arg(
+            Arg::new("readahead-max")
+                .long("readahead-max")
+                .value_name("SIZE")
+                .help("Largest readahead window; the window doubles while a handle keeps streaming (0 disables readahead)")
+                .value_parser(parse_size)
+                .default_value("64M"),
+        )
+        .arg(
+            Arg::new("cache-policy")
+                .long("cache-policy")
+                .value_name("POLICY")
+                .help("How object content is cached where no [cache.\"prefix/\"] rule applies: auto, whole, block or direct")
+                .value_parser(clap::value_parser!(CachePolicy))
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::new("cache-max-size")
+                .long("cache-max-size")
+                .value_name("SIZE")
+                .help("Maximum total size of the content cache (e.g. 10G)")
+                .value_parser(parse_size),
+        )
+        .arg(
+            Arg::new("cache-eviction")
+                .long("cache-eviction")
+                .value_name("POLICY")
+                .help("Content cache eviction policy: lru, lfu or gdsf")
+                .value_parser(clap::value_parser!(EvictionPolicyKind))
+                .default_value("lru"),
+        )
+        .arg(
+            Arg::new("cache-compression")
+                .long("cache-compression")
+                .value_name("ALGORITHM")
+                .help("Compress cached content on disk: none or zstd")
+                .value_parser(clap::value_parser!(CacheCompression))
+                .default_value("none"),
+        )
+        .arg(
+            Arg::new("cache-janitor-interval")
+                .long("cache-janitor-interval")
+                .value_name("DURATION")
+                .help("How often the background task enforces the cache quota and removes stale files")
+                .value_parser(parse_duration)
+                .default_value("60s"),
+        )
+        .arg(
+            Arg::new("max-requests")
+                .long("max-requests")
+                .value_name("N")
+                .help("Maximum number of concurrent COS requests")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("64"),
+        )
+        .arg(
+            Arg::new("max-metadata-requests")
+                .long("max-metadata-requests")
+                .value_name("N")
+                .help("Maximum number of concurrent HEAD/LIST requests")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("32"),
+        )
+        .arg(
+            Arg::new("max-data-requests")
+                .long("max-data-requests")
+                .value_name("N")
+                .help("Maximum number of concurrent object downloads")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("32"),
+        )
+        .arg(
+            Arg::new("io-threads")
+                .long("io-threads")
+                .value_name("N")
+                .help("Number of async worker threads for network I/O (defaults to the number of CPUs)")
+                .value_parser(parse_thread_count),
+        )
+        .arg(
+            Arg::new("blocking-threads")
+                .long("blocking-threads")
+                .value_name("N")
+                .help("Maximum number of threads for blocking cache work (defaults to 512)")
+                .value_parser(parse_thread_count),
+        )
+        .arg(
+            Arg::new("op-timeout")
+                .long("op-timeout")
+                .value_name("DURATION")
+                .help("Fail any filesystem operation with EIO after this long, including retries; uploads are abandoned and retried, other changes finish in the background (0 disables)")
+                .value_parser(parse_duration)
+                .default_value("60s"),
+        )
+        .arg(
+            Arg::new("uid-rate-limit")
+                .long("uid-rate-limit")
+                .value_name("SIZE")
+                .help(
+                    "Limit each requesting uid to this many bytes per second of reads and writes \
+                     (e.g. 20M); usage per uid is always tracked",
+                )
+                .value_parser(parse_size),
+        )
+        .arg(
+            Arg::new("memory-limit")
+                .long("memory-limit")
+                .value_name("SIZE")
+                .help(
+                    "Cap the memory used by the object listing, inode maps, directory and metadata \
+                     caches and in-memory content (e.g. 1G); caches are trimmed under pressure",
+                )
+                .value_parser(parse_size),
+        )
+        .arg(
+            Arg::new("max-read")
+                .long("max-read")
+                .value_name("SIZE")
+                .help("Largest read the kernel sends in one FUSE request, between 4K and 16M")
+                .value_parser(parse_transfer_size)
+                .default_value("1M"),
+        )
+        .arg(
+            Arg::new("max-write")
+                .long("max-write")
+                .value_name("SIZE")
+                .help("Largest write the kernel sends in one FUSE request, between 4K and 16M")
+                .value_parser(parse_transfer_size)
+                .default_value("1M"),
+        )
+        .arg(
+            Arg::new("statsd")
+                .long("statsd")
+                .value_name("HOST:PORT")
+                .help("Push counters, timers and gauges to a StatsD/DogStatsD server over UDP")
+                .global(true),
+        )
+        .arg(
+            Arg::new("statsd-prefix")
+                .long("statsd-prefix")
+                .value_name("PREFIX")
+                .help("Prefix for metric names pushed with --statsd (empty for none)")
+                .global(true)
+                .default_value(DEFAULT_STATSD_PREFIX),
+        )
+        .arg(
+            Arg::new("statsd-interval")
+                .long("statsd-interval")
+                .value_name("DURATION")
+                .help("How often metrics are pushed with --statsd")
+                .global(true)
+                .value_parser(parse_interval)
+                .default_value("10s"),
+        )
+        .arg(
+            Arg::new("statsd-tags")
+                .long("statsd-tags")
+                .help("Tag metrics pushed with --statsd with bucket, prefix and mountpoint (DogStatsD tag syntax)")
+                .global(true)
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("atime")
+                .long("atime")
+                .value_name("MODE")
+                .help("Whether reads update atime (tracked locally, never written to COS): noatime, relatime or strictatime")
+                .value_parser(clap::value_parser!(AtimeMode))
+                .default_value("noatime"),
+        )
+        .arg(
+            Arg::new("probe-write")
+                .long("probe-write")
+                .help("At mount time, upload and delete an empty object to check write permission")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("acl-permissions")
+                .long("acl-permissions")
+                .help("Derive file permission bits from object ACLs (needs COS_SECRET_ID/COS_SECRET_KEY)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("hires-mtime")
+                .long("hires-mtime")
+                .help(
+                    "Store nanosecond mtimes in x-cos-meta-mtime on upload and prefer them \
+                     over Last-Modified (s3fs-compatible; one HEAD per file)",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("s3fs-compat")
+                .long("s3fs-compat")
+                .help(
+                    "Report file owners and modes recorded by s3fs in x-cos-meta-uid/gid/mode \
+                     and record them on upload (one HEAD per file)",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("browse-archives")
+                .long("browse-archives")
+                .help(
+                    "Show .zip and .tar objects as directories; files inside are read with \
+                     ranged GETs of the archive instead of downloading all of it",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("image-variants")
+                .long("image-variants")
+                .help(
+                    "Serve virtual files such as photo.jpg@w=200 next to image objects; \
+                     reads return the image processed by Tencent Cloud Infinite (CI)",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("system-xattrs")
+                .long("system-xattrs")
+                .value_name("MODE")
+                .help(
+                    "How security.*, system.* and trusted.* xattr probes are answered: absent \
+                     (ENODATA), unsupported (EOPNOTSUPP) or selinux-label=CONTEXT to report a \
+                     fixed security.selinux label",
+                )
+                .value_parser(clap::value_parser!(SystemXattrs))
+                .default_value("absent"),
+        )
+        .arg(
+            Arg::new("trash-prefix")
+                .long("trash-prefix")
+                .value_name("PREFIX")
+                .help(
+                    "Move deleted objects into <PREFIX><UTC timestamp>/ (server-side copy + delete) \
+                     instead of deleting them; the trash is hidden from the mount",
+                ),
+        )
+        .arg(
+            Arg::new("presign-expires")
+                .long("presign-expires")
+                .value_name("DURATION")
+                .help("Validity of URLs returned by the user.cos.presigned-url xattr (needs COS_SECRET_ID/COS_SECRET_KEY)")
+                .value_parser(parse_duration)
+                .default_value("1h"),
+        )
+        .arg(
+            Arg::new("manifest")
+                .long("manifest")
+                .value_name("FILE")
+                .help(
+                    "Mount a read-only snapshot built from a saved manifest (JSON with keys, sizes, \
+                     etags and mtimes); the live bucket is never listed and reads fail if an object changed",
+                )
+                .value_parser(clap::value_parser!(PathBuf))
+                .conflicts_with_all(["revalidate-misses", "refresh-interval"]),
+        )
+        .arg(
+            Arg::new("prefetch-manifest")
+                .long("prefetch-manifest")
+                .value_name("FILE")
+                .help("File listing keys/globs to prefetch into the cache on mount (defaults to the bucket's .cosfs-prefetch object)")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("record")
+                .long("record")
+                .value_name("FILE")
+                .help("Record every COS request and response of this mount to FILE for offline debugging")
+                .value_parser(clap::value_parser!(PathBuf))
+                .conflicts_with("replay"),
+        )
+        .arg(
+            Arg::new("record-bodies")
+                .long("record-bodies")
+                .value_name("MODE")
+                .help(
+                    "How --record stores response bodies: full, truncate=SIZE (keep the first SIZE bytes) \
+                     or hash (length and MD5 only); replayed bodies are zero-filled past what was kept",
+                )
+                .value_parser(clap::value_parser!(BodyMode))
+                .default_value("full")
+                .requires("record"),
+        )
+        .arg(
+            Arg::new("replay")
+                .long("replay")
+                .value_name("FILE")
+                .help("Serve the mount from a --record session without any network access")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("spool-dir")
+                .long("spool-dir")
+                .value_name("SPOOL_DIR")
+                .help("Directory for dirty write spool files, kept apart from the read cache")
+                .default_value(DEFAULT_SPOOL_DIR),
+        )
+        .arg(
+            Arg::new("spool-max-size")
+                .long("spool-max-size")
+                .value_name("SIZE")
+                .help("Maximum total size of the write spool; writes beyond it fail with ENOSPC")
+                .value_parser(parse_size),
+        )
+        .arg(
+            Arg::new("spool-fsync")
+                .long("spool-fsync")
+                .value_name("POLICY")
+                .help("When spool files are fsynced: never, close or always")
+                .value_parser(clap::value_parser!(FsyncPolicy))
+                .default_value("close"),
+        )
+        .arg(
+            Arg::new("foreground")
+                .short('f')
+                .long("foreground")
+                .help("Run in foreground")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .help("Mount even if another instance holds the lock on the cache directory or mount point")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("supervise")
+                .long("supervise")
+                .help("Run the mount in a child process and remount it after a crash or kernel disconnect")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("supervise-interval")
+                .long("supervise-interval")
+                .value_name("DURATION")
+                .help("How often --supervise checks the mount point with statfs")
+                .value_parser(parse_duration)
+                .default_value("5s"),
+        )
+        .arg(
+            Arg::new("debug")
+                .short('d')
+                .long("debug")
+                .help("Enable debug logging")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .args(platform_args())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_command_line_parsing() {
-        use clap::Parser;
+        cli().debug_assert();
+
+        // mount(8) 的调用形式：位置参数加 -o 选项
+        let args = [
+            "cos-fuse-demo",
+            "mybucket",
+            "/mnt/cos",
+            "-o",
+            "region=ap-beijing,_netdev,allow_other,metadata_ttl=30s",
+        ]
+        .map(std::ffi::OsString::from);
+        let command = cli();
+        let matches = command
+            .clone()
+            .try_get_matches_from(expand_mount_options(&command, args))
+            .unwrap();
 
-        // 这里可以添加命令行解析的测试
+        assert_eq!(matches.get_one::<String>("source").unwrap(), "mybucket");
+        assert_eq!(matches.get_one::<String>("target").unwrap(), "/mnt/cos");
+        assert_eq!(matches.get_one::<String>("region").unwrap(), "ap-beijing");
+        assert_eq!(
+            *matches.get_one::<Duration>("metadata-ttl").unwrap(),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            matches
+                .get_many::<String>(FUSE_OPTION_ARG)
+                .unwrap()
+                .collect::<Vec<_>>(),
+            vec!["allow_other"]
+        );
+
+        let matches = cli()
+            .try_get_matches_from([
+                "cos-fuse-demo",
+                "--provider",
+                "s3",
+                "--endpoint",
+                "http://minio:9000",
+                "--bucket",
+                "data",
+                "--mount-point",
+                "/mnt/minio",
+            ])
+            .unwrap();
+        assert_eq!(
+            *matches.get_one::<Provider>("provider").unwrap(),
+            Provider::S3
+        );
+        assert!(matches.get_one::<String>("region").is_none());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,182 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::cos_client::{format_iso8601, parse_iso8601, ObjectDetails, ObjectMeta};
+
+/// 对象清单：某一时刻桶中一个前缀下所有对象的键、大小、ETag 与修改时间（JSON）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub bucket: String,
+    /// 对象键相对于该前缀（空表示整个桶）
+    #[serde(default)]
+    pub prefix: String,
+    pub objects: Vec<ManifestEntry>,
+}
+
+/// 清单中的一个对象
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub key: String,
+    pub size: u64,
+    pub etag: String,
+    /// ISO 8601 UTC 时间，与列表结果相同（如 `2019-05-24T10:56:40.000Z`）
+    pub last_modified: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_class: Option<String>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid manifest {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(serde_json::from_str(text)?)
+    }
+}
+
+impl ManifestEntry {
+    /// 由列表结果生成，键已经相对于清单的前缀
+    pub fn from_meta(meta: &ObjectMeta) -> Self {
+        Self {
+            key: meta.key.clone(),
+            size: meta.size,
+            etag: meta.etag.clone(),
+            last_modified: format_iso8601(meta.last_modified),
+            storage_class: meta.details.storage_class.clone(),
+        }
+    }
+}
+
+/// 快照挂载：对象列表与元数据固定为清单中的值，从不列出实时的桶
+pub struct Snapshot {
+    bucket: String,
+    prefix: String,
+    objects: HashMap<String, ObjectMeta>,
+}
+
+impl Snapshot {
+    pub fn from_manifest(manifest: Manifest) -> Result<Self> {
+        let mut objects = HashMap::with_capacity(manifest.objects.len());
+        for entry in manifest.objects {
+            if entry.key.is_empty() {
+                bail!("object with an empty key");
+            }
+            let last_modified = parse_iso8601(&entry.last_modified).ok_or_else(|| {
+                anyhow!(
+                    "invalid last_modified for {}: {}",
+                    entry.key,
+                    entry.last_modified
+                )
+            })?;
+            // If-Match 需要带引号的 ETag，与列表结果一致
+            let etag = if entry.etag.starts_with('"') {
+                entry.etag
+            } else {
+                format!("\"{}\"", entry.etag)
+            };
+            let meta = ObjectMeta {
+                key: entry.key.clone(),
+                size: entry.size,
+                last_modified,
+                etag,
+                content_type: None,
+                server_side_encryption: None,
+                details: ObjectDetails {
+                    storage_class: entry.storage_class,
+                    ..Default::default()
+                },
+            };
+            if objects.insert(entry.key.clone(), meta).is_some() {
+                bail!("duplicate key {}", entry.key);
+            }
+        }
+        Ok(Self {
+            bucket: manifest.bucket,
+            prefix: manifest.prefix,
+            objects,
+        })
+    }
+
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn object_count(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ObjectMeta> {
+        self.objects.get(key)
+    }
+
+    pub fn objects(&self) -> impl Iterator<Item = &ObjectMeta> {
+        self.objects.values()
+    }
+
+    /// 每个对象的 ETag，下载时以 If-Match 固定到清单中的版本
+    pub fn etags(&self) -> HashMap<String, String> {
+        self.objects
+            .iter()
+            .map(|(key, meta)| (key.clone(), meta.etag.clone()))
+            .collect()
+    }
+}
+
+/// 日志中只打印概要，不展开所有对象
+impl fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Snapshot")
+            .field("bucket", &self.bucket)
+            .field("prefix", &self.prefix)
+            .field("objects", &self.objects.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_from_manifest() {
+        let manifest = Manifest::parse(
+            r#"{"bucket": "b-125", "prefix": "data/", "objects": [
+                {"key": "a.txt", "size": 7, "etag": "\"0123\"", "last_modified": "2019-05-24T10:56:40.000Z"},
+                {"key": "logs/b.log", "size": 0, "etag": "4567", "last_modified": "2019-05-24T10:56:41Z",
+                 "storage_class": "ARCHIVE"}
+            ]}"#,
+        )
+        .unwrap();
+        let snapshot = Snapshot::from_manifest(manifest).unwrap();
+        assert_eq!((snapshot.bucket(), snapshot.prefix()), ("b-125", "data/"));
+        assert_eq!(snapshot.object_count(), 2);
+        assert_eq!(snapshot.get("a.txt").unwrap().size, 7);
+        let log = snapshot.get("logs/b.log").unwrap();
+        assert_eq!(log.etag, "\"4567\"");
+        assert_eq!(log.details.storage_class.as_deref(), Some("ARCHIVE"));
+        assert_eq!(snapshot.etags()["a.txt"], "\"0123\"");
+
+        for objects in [
+            r#"[{"key": "a", "size": 1, "etag": "1", "last_modified": "yesterday"}]"#,
+            r#"[{"key": "", "size": 1, "etag": "1", "last_modified": "2019-05-24T10:56:40Z"}]"#,
+            r#"[{"key": "a", "size": 1, "etag": "1", "last_modified": "2019-05-24T10:56:40Z"},
+                {"key": "a", "size": 2, "etag": "2", "last_modified": "2019-05-24T10:56:40Z"}]"#,
+        ] {
+            let text = format!(r#"{{"bucket": "b-125", "objects": {}}}"#, objects);
+            let manifest = Manifest::parse(&text).unwrap();
+            assert!(Snapshot::from_manifest(manifest).is_err(), "{}", objects);
+        }
+        assert!(Manifest::parse(r#"{"objects": []}"#).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use serde::Serialize;
+
+/// `String` 本身（指针、长度、容量）占用的字节数
+const STRING_HEADER: u64 = 24;
+
+/// 哈希表每个条目的额外开销估计（控制字节、空槽与负载因子）
+const HASH_ENTRY_OVERHEAD: u64 = 16;
+
+/// `Arc<str>` 的引用（胖指针）占用的字节数
+const SHARED_REF: u64 = 16;
+
+/// `Arc<str>` 堆上的引用计数
+const SHARED_HEADER: u64 = 16;
+
+/// 元数据缓存每个条目除对象键以外的占用估计（ETag、时间、LRU 链表节点）
+pub const METADATA_ENTRY_SIZE: u64 = 192;
+
+/// 堆上字符串占用的字节数
+pub fn string_size(s: &str) -> u64 {
+    STRING_HEADER + s.len() as u64
+}
+
+/// 驻留池中一个共享字符串的占用（字符串本身只计一次）
+pub fn interned_size(s: &str) -> u64 {
+    SHARED_HEADER + s.len() as u64 + SHARED_REF + HASH_ENTRY_OVERHEAD
+}
+
+/// 对象列表中一个条目的占用（键本身计入驻留池）
+pub const LISTING_ENTRY_SIZE: u64 = SHARED_REF;
+
+/// inode 映射中一个路径的占用：两个方向的映射各持有一个共享路径的引用
+pub const INODE_ENTRY_SIZE: u64 = 2 * (SHARED_REF + 8 + HASH_ENTRY_OVERHEAD);
+
+/// 目录缓存或目录判定缓存中一个路径键的占用
+pub const DIR_KEY_SIZE: u64 = SHARED_REF + 8 + HASH_ENTRY_OVERHEAD;
+
+/// 目录缓存中一个条目的占用（名字本身计入驻留池）
+pub const DIR_ENTRY_SIZE: u64 = SHARED_REF + 8;
+
+/// 内存中各部分状态的占用估计（字节）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryUsage {
+    /// 驻留的对象键、路径与名字，由下面几部分共享
+    pub strings: u64,
+    /// 对象列表
+    pub listing: u64,
+    /// inode 与路径的双向映射
+    pub inodes: u64,
+    /// 目录缓存、目录判定缓存与不存在路径的缓存
+    pub dir_cache: u64,
+    /// L1 元数据缓存
+    pub metadata: u64,
+    /// 内存中的内容（解压后的归档条目、处理后的图片）
+    pub content: u64,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> u64 {
+        self.strings + self.listing + self.inodes + self.dir_cache + self.metadata + self.content
+    }
+
+    /// 可以丢弃后按需重建的部分
+    pub fn trimmable(&self) -> u64 {
+        self.dir_cache + self.metadata + self.content
+    }
+}
+
+/// `user.cosfs.memory` 扩展属性的内容
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MemoryReport {
+    pub limit: Option<u64>,
+    pub total: u64,
+    #[serde(flatten)]
+    pub usage: MemoryUsage,
+}
+
+/// 内存上限。超出时按重建代价从低到高依次丢弃目录缓存、内存中的内容和元数据缓存；
+/// 对象列表与已分配的 inode 是挂载的状态本身，只计入、不丢弃；只被丢弃的缓存引用的驻留字符串随之释放
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryBudget {
+    limit: Option<u64>,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: Option<u64>) -> Self {
+        Self { limit }
+    }
+
+    pub fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+
+    pub fn report(&self, usage: MemoryUsage) -> MemoryReport {
+        MemoryReport {
+            limit: self.limit,
+            total: usage.total(),
+            usage,
+        }
+    }
+
+    /// 超出上限的字节数；未超出或没有上限时为 None
+    pub fn excess(&self, usage: &MemoryUsage) -> Option<u64> {
+        let limit = self.limit?;
+        usage
+            .total()
+            .checked_sub(limit)
+            .filter(|excess| *excess > 0)
+    }
+
+    /// 丢弃所有可重建的部分之后仍然超出上限：对象列表本身放不下，应缩小挂载的前缀或提高上限
+    pub fn is_exhausted(&self, usage: &MemoryUsage) -> bool {
+        self.excess(usage)
+            .is_some_and(|excess| excess > usage.trimmable())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_budget() {
+        let usage = MemoryUsage {
+            strings: 600,
+            listing: 400,
+            inodes: 500,
+            dir_cache: 200,
+            metadata: 300,
+            content: 0,
+        };
+        assert_eq!(usage.total(), 2000);
+        assert_eq!(usage.trimmable(), 500);
+
+        assert_eq!(MemoryBudget::new(None).excess(&usage), None);
+        assert_eq!(MemoryBudget::new(Some(2000)).excess(&usage), None);
+        let budget = MemoryBudget::new(Some(1800));
+        assert_eq!(budget.excess(&usage), Some(200));
+        assert!(!budget.is_exhausted(&usage));
+        // 只丢弃可重建的部分也回不到上限以内
+        assert!(MemoryBudget::new(Some(1000)).is_exhausted(&usage));
+
+        assert_eq!(string_size("abc"), 27);
+        assert_eq!(interned_size("abc"), 16 + 3 + 16 + 16);
+    }
+}
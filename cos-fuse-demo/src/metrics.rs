@@ -0,0 +1,293 @@
+use log::{debug, info, warn};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+
+/// 默认的指标名前缀
+pub const DEFAULT_STATSD_PREFIX: &str = "cosfs";
+
+/// 每个计时器在一个推送周期内最多保留的样本数，超出的样本丢弃（计数仍然准确）
+const MAX_TIMER_SAMPLES: usize = 1000;
+
+/// 单个 UDP 包的最大长度，避免在常见 MTU 下分片
+const MAX_DATAGRAM_SIZE: usize = 1432;
+
+/// 运行中累计的计数器与计时器，推送后清零
+#[derive(Default)]
+pub struct Metrics {
+    counters: Mutex<BTreeMap<&'static str, u64>>,
+    timers: Mutex<BTreeMap<&'static str, Vec<Duration>>>,
+}
+
+/// 一个推送周期内的数据
+#[derive(Debug, Default)]
+struct Snapshot {
+    counters: BTreeMap<&'static str, u64>,
+    timers: BTreeMap<&'static str, Vec<Duration>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 计数器 `name` 加 `n`
+    pub fn incr(&self, name: &'static str, n: u64) {
+        *self.counters.lock().unwrap().entry(name).or_default() += n;
+    }
+
+    /// 记录计时器 `name` 的一个样本
+    pub fn time(&self, name: &'static str, elapsed: Duration) {
+        let mut timers = self.timers.lock().unwrap();
+        let samples = timers.entry(name).or_default();
+        if samples.len() < MAX_TIMER_SAMPLES {
+            samples.push(elapsed);
+        }
+    }
+
+    /// 取出并清零当前累计的数据
+    fn drain(&self) -> Snapshot {
+        Snapshot {
+            counters: std::mem::take(&mut *self.counters.lock().unwrap()),
+            timers: std::mem::take(&mut *self.timers.lock().unwrap()),
+        }
+    }
+}
+
+/// StatsD 推送配置
+#[derive(Debug, Clone)]
+pub struct StatsdOptions {
+    /// StatsD/DogStatsD 服务地址（`host:port`）
+    pub addr: String,
+
+    /// 指标名前缀，为空时不加前缀
+    pub prefix: String,
+
+    /// 推送间隔
+    pub interval: Duration,
+
+    /// 以 DogStatsD 标签（`|#bucket:...,mountpoint:...`）附加挂载的标识；普通 StatsD 不支持标签
+    pub tags: bool,
+
+    /// 指标所属的挂载
+    pub labels: MetricLabels,
+}
+
+/// 指标所属挂载的标识，多个挂载推送到同一服务时用于聚合与对比
+#[derive(Debug, Clone, Default)]
+pub struct MetricLabels {
+    pub bucket: String,
+    /// 挂载桶内的前缀，为空时不附加
+    pub prefix: String,
+    pub mountpoint: String,
+}
+
+impl MetricLabels {
+    /// DogStatsD 标签后缀；标签值中的 `,`、`|`、`#` 与空白替换为 `_`
+    fn dogstatsd_suffix(&self) -> String {
+        let tags: Vec<String> = [
+            ("bucket", &self.bucket),
+            ("prefix", &self.prefix),
+            ("mountpoint", &self.mountpoint),
+        ]
+        .iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(name, value)| {
+            let value: String = value
+                .chars()
+                .map(|c| {
+                    if matches!(c, ',' | '|' | '#') || c.is_whitespace() {
+                        '_'
+                    } else {
+                        c
+                    }
+                })
+                .collect();
+            format!("{}:{}", name, value)
+        })
+        .collect();
+        if tags.is_empty() {
+            String::new()
+        } else {
+            format!("|#{}", tags.join(","))
+        }
+    }
+}
+
+/// 按 StatsD 文本协议格式化：计数器 `|c`、计时器 `|ms`、瞬时值 `|g`，每行以 `suffix`（标签）结尾
+fn format_lines(
+    prefix: &str,
+    suffix: &str,
+    snapshot: &Snapshot,
+    gauges: &[(&'static str, u64)],
+) -> Vec<String> {
+    let name = |metric: &str| {
+        if prefix.is_empty() {
+            metric.to_string()
+        } else {
+            format!("{}.{}", prefix, metric)
+        }
+    };
+    let mut lines = Vec::new();
+    for (metric, value) in &snapshot.counters {
+        lines.push(format!("{}:{}|c{}", name(metric), value, suffix));
+    }
+    for (metric, samples) in &snapshot.timers {
+        for sample in samples {
+            lines.push(format!(
+                "{}:{:.3}|ms{}",
+                name(metric),
+                sample.as_secs_f64() * 1000.0,
+                suffix
+            ));
+        }
+    }
+    for (metric, value) in gauges {
+        lines.push(format!("{}:{}|g{}", name(metric), value, suffix));
+    }
+    lines
+}
+
+/// 把多行合并成不超过 [`MAX_DATAGRAM_SIZE`] 的 UDP 包，行之间以换行分隔
+fn pack_datagrams(lines: &[String]) -> Vec<String> {
+    let mut datagrams = Vec::new();
+    let mut current = String::new();
+    for line in lines {
+        if !current.is_empty() && current.len() + 1 + line.len() > MAX_DATAGRAM_SIZE {
+            datagrams.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        datagrams.push(current);
+    }
+    datagrams
+}
+
+/// 解析地址并创建同一地址族的 UDP 套接字
+async fn connect(addr: &str) -> std::io::Result<(UdpSocket, SocketAddr)> {
+    let target = tokio::net::lookup_host(addr).await?.next().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("no address for {}", addr),
+        )
+    })?;
+    let local = if target.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    Ok((UdpSocket::bind(local).await?, target))
+}
+
+/// 启动周期性的 StatsD 推送任务，`gauges` 在每次推送时（在阻塞线程池上）采集瞬时值；
+/// 返回的句柄在卸载时用于停止任务
+pub fn spawn_statsd<G>(
+    runtime: &Runtime,
+    metrics: Arc<Metrics>,
+    options: StatsdOptions,
+    gauges: G,
+) -> JoinHandle<()>
+where
+    G: Fn() -> Vec<(&'static str, u64)> + Send + Sync + 'static,
+{
+    info!(
+        "Pushing metrics to StatsD at {} every {:?}",
+        options.addr, options.interval
+    );
+    let suffix = if options.tags {
+        options.labels.dogstatsd_suffix()
+    } else {
+        String::new()
+    };
+    let gauges = Arc::new(gauges);
+    runtime.spawn(async move {
+        let mut ticker = tokio::time::interval(options.interval);
+        ticker.tick().await;
+        let mut socket = None;
+
+        loop {
+            ticker.tick().await;
+
+            let snapshot = metrics.drain();
+            let gauges = Arc::clone(&gauges);
+            let gauges = tokio::task::spawn_blocking(move || gauges())
+                .await
+                .unwrap_or_default();
+            let lines = format_lines(&options.prefix, &suffix, &snapshot, &gauges);
+
+            // 地址暂时无法解析时下个周期重试，期间的数据丢弃
+            if socket.is_none() {
+                match connect(&options.addr).await {
+                    Ok(connected) => socket = Some(connected),
+                    Err(e) => {
+                        warn!("Failed to resolve StatsD address {}: {}", options.addr, e);
+                        continue;
+                    }
+                }
+            }
+            let Some((socket, target)) = &socket else {
+                continue;
+            };
+            for datagram in pack_datagrams(&lines) {
+                if let Err(e) = socket.send_to(datagram.as_bytes(), target).await {
+                    debug!("Failed to send metrics to {}: {}", target, e);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statsd_lines() {
+        let metrics = Metrics::new();
+        metrics.incr("read.bytes", 2);
+        metrics.incr("read.bytes", 1);
+        metrics.time("fuse.read", Duration::from_micros(1500));
+        let lines = format_lines("cosfs", "", &metrics.drain(), &[("spool.used_bytes", 42)]);
+        assert_eq!(
+            lines,
+            [
+                "cosfs.read.bytes:3|c",
+                "cosfs.fuse.read:1.500|ms",
+                "cosfs.spool.used_bytes:42|g"
+            ]
+        );
+        // 推送后清零
+        assert!(format_lines("", "", &metrics.drain(), &[]).is_empty());
+
+        // 挂载标识作为 DogStatsD 标签附加在每一行
+        let labels = MetricLabels {
+            bucket: "data-1250000000".to_string(),
+            prefix: String::new(),
+            mountpoint: "/mnt/my data".to_string(),
+        };
+        let suffix = labels.dogstatsd_suffix();
+        assert_eq!(suffix, "|#bucket:data-1250000000,mountpoint:/mnt/my_data");
+        metrics.incr("read.bytes", 1);
+        assert_eq!(
+            format_lines("cosfs", &suffix, &metrics.drain(), &[]),
+            ["cosfs.read.bytes:1|c|#bucket:data-1250000000,mountpoint:/mnt/my_data"]
+        );
+        assert_eq!(MetricLabels::default().dogstatsd_suffix(), "");
+
+        let lines: Vec<String> = (0..100)
+            .map(|i| format!("cosfs.metric.{}:1|c", i))
+            .collect();
+        let datagrams = pack_datagrams(&lines);
+        assert!(datagrams.len() > 1);
+        assert!(datagrams.iter().all(|d| d.len() <= MAX_DATAGRAM_SIZE));
+        assert_eq!(datagrams.join("\n").lines().count(), 100);
+    }
+}
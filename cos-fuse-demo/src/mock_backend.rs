@@ -0,0 +1,432 @@
+//! 内存中的桶，可以按操作与键注入延迟、错误状态码、限流与截断的响应。
+//! 不需要网络，测试可以确定地触发读取、查找与写入的各种失败路径
+
+use anyhow::Result;
+use bytes::Bytes;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use reqwest::StatusCode;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use crate::cos_client::{ListPage, ObjectMeta, StatusError};
+use crate::object_store::{list_sorted, ObjectStore};
+
+/// 每页列出的条目数，与 COS 相同
+const PAGE_SIZE: usize = 1000;
+
+/// 可以注入故障的操作；整体与区间 GET 都属于 `Get`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    List,
+    Head,
+    Get,
+    Put,
+    Delete,
+    Copy,
+}
+
+impl Op {
+    fn request(self) -> &'static str {
+        match self {
+            Op::List => "LIST",
+            Op::Head => "HEAD",
+            Op::Get => "GET",
+            Op::Put => "PUT",
+            Op::Delete => "DELETE",
+            Op::Copy => "Copy",
+        }
+    }
+}
+
+/// 注入的故障
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// 以该状态码失败（如 500、503、403）
+    Status(StatusCode),
+    /// 限流：与 COS/S3 一样答复 503 SlowDown
+    SlowDown,
+    /// 在常规延迟之外再等待这么久，然后正常处理
+    Delay(Duration),
+    /// GET 只返回前这么多字节，模拟提前结束的响应；对其他操作无效
+    Truncate(u64),
+}
+
+/// 一条注入规则：`key` 为 None 时对该操作的所有键生效，列出时键为前缀
+#[derive(Debug)]
+struct Rule {
+    op: Op,
+    key: Option<String>,
+    fault: Fault,
+    /// 剩余生效次数，`u32::MAX` 表示一直生效
+    remaining: u32,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    objects: BTreeMap<String, (ObjectMeta, Bytes)>,
+    rules: Vec<Rule>,
+    latency: Duration,
+    requests: HashMap<Op, u64>,
+}
+
+#[derive(Debug)]
+pub struct MockBackend {
+    bucket: String,
+    state: Mutex<State>,
+}
+
+impl MockBackend {
+    pub fn new(bucket: impl Into<String>) -> Self {
+        Self {
+            bucket: bucket.into(),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// 直接放入对象（不计入请求数，也不受故障影响），返回 ETag
+    pub fn insert(&self, key: &str, body: impl Into<Bytes>) -> String {
+        self.store(key, body.into(), None)
+    }
+
+    /// 对象的当前内容
+    pub fn object(&self, key: &str) -> Option<Bytes> {
+        let state = self.state.lock().unwrap();
+        state.objects.get(key).map(|(_, body)| body.clone())
+    }
+
+    /// 之后每个请求处理前都等待 `latency`
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// 让接下来 `times` 次 `op` 请求（不论键）出现 `fault`；`u32::MAX` 表示一直生效
+    pub fn inject(&self, op: Op, fault: Fault, times: u32) {
+        self.push_rule(op, None, fault, times);
+    }
+
+    /// 同 [`inject`](Self::inject)，只对 `key` 生效
+    pub fn inject_for(&self, op: Op, key: &str, fault: Fault, times: u32) {
+        self.push_rule(op, Some(key.to_string()), fault, times);
+    }
+
+    /// 清除所有未用完的注入规则与延迟
+    pub fn clear_faults(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.rules.clear();
+        state.latency = Duration::ZERO;
+    }
+
+    /// 已收到的 `op` 请求数，包括注入失败的请求
+    pub fn requests(&self, op: Op) -> u64 {
+        let state = self.state.lock().unwrap();
+        state.requests.get(&op).copied().unwrap_or(0)
+    }
+
+    fn push_rule(&self, op: Op, key: Option<String>, fault: Fault, times: u32) {
+        if times == 0 {
+            return;
+        }
+        self.state.lock().unwrap().rules.push(Rule {
+            op,
+            key,
+            fault,
+            remaining: times,
+        });
+    }
+
+    /// 每个请求的入口：计数、等待延迟并应用第一条匹配的规则。
+    /// 返回 GET 应截断到的长度
+    async fn begin(&self, op: Op, key: &str) -> Result<Option<u64>> {
+        let (latency, fault) = {
+            let mut state = self.state.lock().unwrap();
+            *state.requests.entry(op).or_default() += 1;
+            let index = state
+                .rules
+                .iter()
+                .position(|rule| rule.op == op && rule.key.as_deref().is_none_or(|k| k == key));
+            let fault = index.map(|index| {
+                let rule = &mut state.rules[index];
+                let fault = rule.fault.clone();
+                if rule.remaining != u32::MAX {
+                    rule.remaining -= 1;
+                    if rule.remaining == 0 {
+                        state.rules.remove(index);
+                    }
+                }
+                fault
+            });
+            (state.latency, fault)
+        };
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+        match fault {
+            None => Ok(None),
+            Some(Fault::Status(status)) => {
+                Err(anyhow::Error::from(StatusError::new(op.request(), status))
+                    .context(format!("Injected fault for {}", key)))
+            }
+            Some(Fault::SlowDown) => Err(anyhow::Error::from(StatusError::new(
+                op.request(),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ))
+            .context("SlowDown: Please reduce your request rate")),
+            Some(Fault::Delay(delay)) => {
+                tokio::time::sleep(delay).await;
+                Ok(None)
+            }
+            Some(Fault::Truncate(len)) => Ok(Some(len)),
+        }
+    }
+
+    /// 写入对象，返回 ETag（内容的 MD5）
+    fn store(&self, key: &str, body: Bytes, content_type: Option<String>) -> String {
+        let etag = format!("\"{:x}\"", md5::compute(&body));
+        let meta = ObjectMeta {
+            key: key.to_string(),
+            size: body.len() as u64,
+            last_modified: SystemTime::now(),
+            etag: etag.clone(),
+            content_type,
+            server_side_encryption: None,
+            details: Default::default(),
+        };
+        let mut state = self.state.lock().unwrap();
+        state.objects.insert(key.to_string(), (meta, body));
+        etag
+    }
+
+    fn get(&self, op: Op, key: &str) -> Result<(ObjectMeta, Bytes)> {
+        let state = self.state.lock().unwrap();
+        match state.objects.get(key) {
+            Some((meta, body)) => Ok((meta.clone(), body.clone())),
+            None => Err(
+                anyhow::Error::from(StatusError::new(op.request(), StatusCode::NOT_FOUND))
+                    .context(format!("Object not found: {}", key)),
+            ),
+        }
+    }
+}
+
+impl ObjectStore for MockBackend {
+    fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        format!("mock://{}/{}", self.bucket, key)
+    }
+
+    async fn list_page(
+        &self,
+        prefix: &str,
+        delimiter: bool,
+        marker: Option<&str>,
+    ) -> Result<ListPage> {
+        self.begin(Op::List, prefix).await?;
+        let state = self.state.lock().unwrap();
+        Ok(list_sorted(
+            state.objects.values().map(|(meta, _)| meta),
+            prefix,
+            delimiter,
+            marker,
+            PAGE_SIZE,
+        ))
+    }
+
+    async fn head_object(&self, key: &str) -> Result<ObjectMeta> {
+        self.begin(Op::Head, key).await?;
+        Ok(self.get(Op::Head, key)?.0)
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        let truncate = self.begin(Op::Get, key).await?;
+        let body = self.get(Op::Get, key)?.1;
+        Ok(truncated(body, truncate))
+    }
+
+    async fn get_object_range(&self, key: &str, offset: u64, len: u64) -> Result<Bytes> {
+        let truncate = self.begin(Op::Get, key).await?;
+        let body = self.get(Op::Get, key)?.1;
+        let start = offset.min(body.len() as u64);
+        let end = offset.saturating_add(len).min(body.len() as u64);
+        Ok(truncated(
+            body.slice(start as usize..end as usize),
+            truncate,
+        ))
+    }
+
+    async fn get_object_range_if_match(
+        &self,
+        key: &str,
+        etag: &str,
+        offset: u64,
+        len: u64,
+    ) -> Result<Bytes> {
+        let truncate = self.begin(Op::Get, key).await?;
+        let (meta, body) = self.get(Op::Get, key)?;
+        if meta.etag != etag {
+            return Err(
+                StatusError::new(Op::Get.request(), StatusCode::PRECONDITION_FAILED).into(),
+            );
+        }
+        let start = offset.min(body.len() as u64);
+        let end = offset.saturating_add(len).min(body.len() as u64);
+        Ok(truncated(
+            body.slice(start as usize..end as usize),
+            truncate,
+        ))
+    }
+
+    /// 只保留 Content-Type，其余附加头忽略
+    async fn put_object(&self, key: &str, body: Bytes, headers: HeaderMap) -> Result<String> {
+        self.begin(Op::Put, key).await?;
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        Ok(self.store(key, body, content_type))
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        self.begin(Op::Delete, key).await?;
+        self.state.lock().unwrap().objects.remove(key);
+        Ok(())
+    }
+
+    async fn copy_object(&self, source_key: &str, dest_key: &str) -> Result<()> {
+        self.begin(Op::Copy, source_key).await?;
+        let (meta, body) = self.get(Op::Copy, source_key)?;
+        self.store(dest_key, body, meta.content_type);
+        Ok(())
+    }
+}
+
+fn truncated(body: Bytes, len: Option<u64>) -> Bytes {
+    match len {
+        Some(len) if len < body.len() as u64 => body.slice(..len as usize),
+        _ => body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Cache;
+    use crate::config::CacheOptions;
+    use crate::cos_client::error_status;
+    use crate::downloader::Downloader;
+    use crate::head_resolver::HeadResolver;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_objects() {
+        let mock = MockBackend::new("mock");
+        let etag = mock.insert("a/b.txt", "hello");
+        assert_eq!(etag, format!("\"{:x}\"", md5::compute("hello")));
+        mock.insert("c.txt", "world");
+
+        let meta = mock.head_object("a/b.txt").await.unwrap();
+        assert_eq!((meta.size, meta.etag), (5, etag));
+        assert_eq!(
+            mock.get_object_range("a/b.txt", 1, 10).await.unwrap(),
+            "ello"
+        );
+        let listing = mock.list_dir("").await.unwrap();
+        assert_eq!(listing.objects.len(), 1);
+        assert_eq!(listing.prefixes, vec!["a/".to_string()]);
+
+        mock.copy_object("a/b.txt", "d.txt").await.unwrap();
+        mock.delete_object("a/b.txt").await.unwrap();
+        let error = mock.get_object("a/b.txt").await.unwrap_err();
+        assert_eq!(error_status(&error), Some(StatusCode::NOT_FOUND));
+        assert_eq!(mock.object("d.txt").unwrap(), "hello");
+        assert_eq!(mock.requests(Op::Get), 2);
+        assert_eq!(mock.requests(Op::Put), 0);
+    }
+
+    #[tokio::test]
+    async fn test_faults() {
+        let mock = MockBackend::new("mock");
+        mock.insert("a", "0123456789");
+        mock.insert("b", "b");
+
+        mock.inject(Op::Get, Fault::Status(StatusCode::INTERNAL_SERVER_ERROR), 2);
+        for _ in 0..2 {
+            let error = mock.get_object("a").await.unwrap_err();
+            assert_eq!(
+                error_status(&error),
+                Some(StatusCode::INTERNAL_SERVER_ERROR)
+            );
+        }
+        assert_eq!(mock.get_object("a").await.unwrap(), "0123456789");
+
+        // 只对指定的键生效
+        mock.inject_for(Op::Head, "b", Fault::SlowDown, u32::MAX);
+        mock.head_object("a").await.unwrap();
+        for _ in 0..3 {
+            let error = mock.head_object("b").await.unwrap_err();
+            assert_eq!(error_status(&error), Some(StatusCode::SERVICE_UNAVAILABLE));
+            assert!(format!("{:#}", error).contains("SlowDown"));
+        }
+
+        mock.inject(Op::Get, Fault::Truncate(4), 1);
+        assert_eq!(mock.get_object_range("a", 2, 8).await.unwrap(), "2345");
+        assert_eq!(mock.get_object_range("a", 2, 8).await.unwrap(), "23456789");
+
+        mock.inject(Op::Put, Fault::Delay(Duration::from_millis(20)), 1);
+        let start = Instant::now();
+        mock.put_object("c", Bytes::new(), HeaderMap::new())
+            .await
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        mock.clear_faults();
+        mock.head_object("b").await.unwrap();
+        assert_eq!(mock.requests(Op::Head), 5);
+    }
+
+    /// 查找与读取在失败后不会留下错误的结果，故障消失后的请求正常完成
+    #[tokio::test]
+    async fn test_lookup_and_read_errors() {
+        let mock = Arc::new(MockBackend::new("mock"));
+        mock.insert("data.bin", vec![7u8; 64]);
+
+        let resolver = HeadResolver::new(mock.clone(), 4);
+        mock.inject(Op::Head, Fault::Status(StatusCode::FORBIDDEN), 1);
+        let error = resolver.resolve("data.bin").await.unwrap_err();
+        assert_eq!(error_status(&error), Some(StatusCode::FORBIDDEN));
+        assert_eq!(resolver.resolve("data.bin").await.unwrap().size, 64);
+        let error = resolver.resolve("missing").await.unwrap_err();
+        assert_eq!(error_status(&error), Some(StatusCode::NOT_FOUND));
+
+        // 延迟期间的并发查找合并为一次 HEAD
+        mock.set_latency(Duration::from_millis(20));
+        let (a, b) = tokio::join!(resolver.resolve("data.bin"), resolver.resolve("data.bin"));
+        assert_eq!(a.unwrap().etag, b.unwrap().etag);
+        assert_eq!(mock.requests(Op::Head), 4);
+        mock.clear_faults();
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Arc::new(Cache::new(dir.path(), &CacheOptions::default()).unwrap());
+        let downloader = Downloader::new(mock.clone(), cache.clone());
+        mock.inject(Op::Get, Fault::SlowDown, 1);
+        let error = downloader
+            .fetch_object("data.bin", "e", 64)
+            .await
+            .unwrap_err();
+        assert_eq!(error_status(&error), Some(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!cache.get_content_cache_path("data.bin").exists());
+        assert_eq!(
+            downloader
+                .fetch_object("data.bin", "e", 64)
+                .await
+                .unwrap()
+                .len(),
+            64
+        );
+        assert!(cache.get_content_cache_path("data.bin").exists());
+    }
+}
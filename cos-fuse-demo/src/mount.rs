@@ -0,0 +1,140 @@
+use anyhow::{bail, Context, Result};
+use fuser::{spawn_mount2, BackgroundSession, MountOption};
+use log::info;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::builder::CosFilesystemBuilder;
+use crate::filesystem::FsControl;
+use crate::mount_lock::MountLock;
+
+/// 一个挂载的配置
+#[derive(Debug, Clone)]
+pub struct MountConfig {
+    pub filesystem: CosFilesystemBuilder,
+
+    /// 挂载点，必须是已存在的空目录
+    pub mount_point: PathBuf,
+
+    /// FUSE 挂载选项（None 表示与 `daemon` 的默认值相同：只读、fsname=cosfs 等）
+    pub options: Option<Vec<MountOption>>,
+
+    /// 挂载前检查对桶的读权限，缺少权限时在这里失败，而不是挂载后以 EIO 失败
+    pub probe: bool,
+
+    /// 缓存目录或挂载点被其他实例锁定时仍然挂载
+    pub force: bool,
+}
+
+impl MountConfig {
+    pub fn new(filesystem: CosFilesystemBuilder, mount_point: impl Into<PathBuf>) -> Self {
+        Self {
+            filesystem,
+            mount_point: mount_point.into(),
+            options: None,
+            probe: true,
+            force: false,
+        }
+    }
+}
+
+/// 在进程内挂载文件系统，嵌入代理、测试工具或编排服务时不必调用命令行
+pub struct Mount;
+
+impl Mount {
+    /// 创建文件系统并在后台线程中挂载，返回控制挂载的句柄。会阻塞到挂载完成（检查权限时还要等待
+    /// 一次 COS 请求），在异步代码中应放到 `spawn_blocking` 中调用；首次列表在此之后完成，
+    /// 需要时等待 [`MountHandle::ready`]
+    pub fn spawn(config: MountConfig) -> Result<MountHandle> {
+        let mount_point = config.mount_point;
+        check_mount_point(&mount_point)?;
+        let (bucket, prefix, cache_dir) = config.filesystem.lock_target();
+        let lock = MountLock::acquire(bucket, prefix, cache_dir, &mount_point, config.force)?;
+
+        let options = config
+            .options
+            .unwrap_or_else(|| config.filesystem.default_mount_options());
+        let fs = config
+            .filesystem
+            .build()
+            .context("Failed to create filesystem")?;
+        if config.probe {
+            fs.probe(false).context("Bucket permission check failed")?;
+        }
+        let control = fs.control();
+        let session = spawn_mount2(fs, &mount_point, &options).with_context(|| {
+            format!("Failed to mount filesystem at {}", mount_point.display())
+        })?;
+        info!("Filesystem mounted at {}", mount_point.display());
+        Ok(MountHandle {
+            session,
+            control,
+            mount_point,
+            _lock: lock,
+        })
+    }
+}
+
+/// 运行中的挂载，drop 时卸载
+pub struct MountHandle {
+    session: BackgroundSession,
+    control: FsControl,
+    mount_point: PathBuf,
+    _lock: MountLock,
+}
+
+impl MountHandle {
+    pub fn mount_point(&self) -> &Path {
+        &self.mount_point
+    }
+
+    /// 等到首次列表完成、挂载可用；挂载初始化失败时返回错误
+    pub async fn ready(&self) -> Result<()> {
+        self.control.ready().await
+    }
+
+    /// 当前的瞬时指标（缓存条目数、暂存区与内存占用等），名字与 `--statsd` 推送的瞬时值相同
+    pub fn stats(&self) -> BTreeMap<&'static str, u64> {
+        self.control.stats()
+    }
+
+    /// 重新列出整个桶，使其他客户端的改动立即可见
+    pub async fn refresh(&self) -> Result<()> {
+        self.control.refresh().await
+    }
+
+    /// 卸载并等待会话线程退出（含文件系统的清理）
+    pub fn unmount(self) {
+        info!("Unmounting {}", self.mount_point.display());
+        self.session.join();
+    }
+}
+
+/// 挂载点必须是已存在的空目录
+pub(crate) fn check_mount_point(path: &Path) -> Result<()> {
+    if !path.is_dir() {
+        bail!("Mount point is not a directory: {}", path.display());
+    }
+    let mut entries = path
+        .read_dir()
+        .with_context(|| format!("Failed to read mount point {}", path.display()))?;
+    if entries.next().is_some() {
+        bail!("Mount point {} is not empty", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_mount_point() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(check_mount_point(dir.path()).is_ok());
+        std::fs::write(dir.path().join("file"), b"").unwrap();
+        assert!(check_mount_point(dir.path()).is_err());
+        assert!(check_mount_point(&dir.path().join("file")).is_err());
+        assert!(check_mount_point(&dir.path().join("missing")).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::{bail, Context, Result};
+use log::warn;
+use std::env;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// 挂载期间持有的咨询锁：缓存目录和挂载点各一把，进程退出（包括崩溃）时由系统释放。
+/// 防止两个实例共用同一个缓存目录（互相淘汰、覆盖文件）或重复挂载同一个挂载点
+pub struct MountLock {
+    _files: Vec<File>,
+}
+
+impl MountLock {
+    /// 获取锁并在锁文件中记下持有者；`force` 为 true 时被占用只记录警告
+    pub fn acquire(
+        bucket: &str,
+        prefix: &str,
+        cache_dir: &Path,
+        mount_point: &Path,
+        force: bool,
+    ) -> Result<Self> {
+        let owner = format!(
+            "pid {}: {}/{} at {} (cache {})",
+            std::process::id(),
+            bucket,
+            prefix,
+            mount_point.display(),
+            cache_dir.display()
+        );
+        let mut files = Vec::new();
+        for (what, path) in [
+            ("Cache directory", cache_lock_path(cache_dir)),
+            ("Mount point", mount_point_lock_path(mount_point)),
+        ] {
+            match lock(&path, &owner)? {
+                Ok(file) => files.push(file),
+                Err(holder) if force => warn!(
+                    "{} is in use by {}; continuing because of --force",
+                    what, holder
+                ),
+                Err(holder) => bail!(
+                    "{} is in use by {} (lock file {}); use --force to mount anyway",
+                    what,
+                    holder,
+                    path.display()
+                ),
+            }
+        }
+        Ok(Self { _files: files })
+    }
+}
+
+/// 缓存目录的锁文件放在目录旁边，缓存清理和格式迁移不会碰到它
+fn cache_lock_path(cache_dir: &Path) -> PathBuf {
+    let mut name = cache_dir
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_else(|| "cosfs_cache".into());
+    name.push(".lock");
+    cache_dir.with_file_name(name)
+}
+
+/// 挂载点的锁文件按规范化路径放在运行时目录中
+fn mount_point_lock_path(mount_point: &Path) -> PathBuf {
+    let canonical = mount_point
+        .canonicalize()
+        .unwrap_or_else(|_| mount_point.to_path_buf());
+    let mut hasher = DefaultHasher::new();
+    canonical.hash(&mut hasher);
+    let runtime_dir = env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir);
+    runtime_dir.join(format!("cosfs-mount-{:016x}.lock", hasher.finish()))
+}
+
+/// 以非阻塞方式加排他锁；被占用时返回锁文件中记录的持有者
+fn lock(path: &Path, owner: &str) -> Result<Result<File, String>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let holder = fs::read_to_string(path).unwrap_or_default();
+            let holder = match holder.trim() {
+                "" => "another process".to_string(),
+                holder => holder.to_string(),
+            };
+            return Ok(Err(holder));
+        }
+        Err(TryLockError::Error(e)) => {
+            return Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+        }
+    }
+
+    file.set_len(0)?;
+    file.rewind()?;
+    writeln!(file, "{}", owner)?;
+    Ok(Ok(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_lock_is_refused() {
+        let dir = env::temp_dir().join(format!("cosfs-lock-test-{}", std::process::id()));
+        let cache_dir = dir.join("cache");
+        let mount_point = dir.join("mnt");
+        fs::create_dir_all(&mount_point).unwrap();
+
+        let lock = MountLock::acquire("b", "", &cache_dir, &mount_point, false).unwrap();
+        let error = MountLock::acquire("b", "", &cache_dir, &mount_point, false)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("b/ at"), "{}", error);
+        assert!(MountLock::acquire("b", "", &cache_dir, &mount_point, true).is_ok());
+
+        drop(lock);
+        assert!(MountLock::acquire("b", "", &cache_dir, &mount_point, false).is_ok());
+        assert_eq!(cache_lock_path(&cache_dir), dir.join("cache.lock"));
+        let _ = fs::remove_file(mount_point_lock_path(&mount_point));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
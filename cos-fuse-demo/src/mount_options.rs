@@ -0,0 +1,349 @@
+use clap::{Arg, ArgMatches, Command};
+use fuser::MountOption;
+use std::ffi::OsString;
+use std::mem::discriminant;
+
+use crate::atime::AtimeMode;
+
+/// 只对 mount(8)/fstab 有意义的选项，不传给内核
+const FSTAB_ONLY_OPTIONS: &[&str] = &[
+    "defaults", "auto", "noauto", "user", "users", "nouser", "nofail", "_netdev",
+];
+
+/// 设置 `--atime` 的选项名
+const ATIME_OPTIONS: &[&str] = &["noatime", "relatime", "strictatime", "atime"];
+
+/// 承载透传 FUSE 选项的内部参数名
+pub const FUSE_OPTION_ARG: &str = "fuse-option";
+
+/// 把 `-o key=value,flag` 展开成等价的命令行参数：
+/// 与长参数同名的键（`_` 与 `-` 等价）转换为对应的长参数，其余作为 FUSE 挂载选项透传给内核
+pub fn expand_mount_options<I>(command: &Command, args: I) -> Vec<OsString>
+where
+    I: IntoIterator<Item = OsString>,
+{
+    let mut expanded = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            expanded.push(arg);
+            expanded.extend(args);
+            break;
+        }
+
+        let options = if arg == "-o" {
+            match args.next() {
+                Some(options) => options,
+                None => {
+                    expanded.push(arg);
+                    continue;
+                }
+            }
+        } else {
+            match arg.to_str().and_then(|a| a.strip_prefix("-o")) {
+                Some(options) if !options.is_empty() => options.into(),
+                _ => {
+                    expanded.push(arg);
+                    continue;
+                }
+            }
+        };
+
+        for option in options.to_string_lossy().split(',') {
+            let option = option.trim();
+            if option.is_empty() || FSTAB_ONLY_OPTIONS.contains(&option) {
+                continue;
+            }
+            expanded.push(translate_option(command, option));
+        }
+    }
+
+    expanded
+}
+
+/// 把单个 `-o` 选项翻译成长参数
+fn translate_option(command: &Command, option: &str) -> OsString {
+    let (key, value) = match option.split_once('=') {
+        Some((key, value)) => (key, Some(value)),
+        None => (option, None),
+    };
+    if value.is_none() && ATIME_OPTIONS.contains(&key) {
+        return format!("--atime={}", key).into();
+    }
+    let name = key.replace('_', "-");
+
+    let known = command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(name.as_str()) && name != FUSE_OPTION_ARG);
+    // 缺少值或多余的值原样交给 clap 报错
+    match (known, value) {
+        (Some(_), Some(value)) => format!("--{}={}", name, value).into(),
+        (Some(_), None) => format!("--{}", name).into(),
+        (None, _) => format!("--{}={}", FUSE_OPTION_ARG, option).into(),
+    }
+}
+
+/// 把透传的选项字符串转换为 fuser 的挂载选项
+pub fn parse_fuse_option(option: &str) -> MountOption {
+    match option {
+        "auto_unmount" => MountOption::AutoUnmount,
+        "allow_other" => MountOption::AllowOther,
+        "allow_root" => MountOption::AllowRoot,
+        "default_permissions" => MountOption::DefaultPermissions,
+        "dev" => MountOption::Dev,
+        "nodev" => MountOption::NoDev,
+        "suid" => MountOption::Suid,
+        "nosuid" => MountOption::NoSuid,
+        "ro" => MountOption::RO,
+        "rw" => MountOption::RW,
+        "exec" => MountOption::Exec,
+        "noexec" => MountOption::NoExec,
+        "atime" => MountOption::Atime,
+        "noatime" => MountOption::NoAtime,
+        "dirsync" => MountOption::DirSync,
+        "sync" => MountOption::Sync,
+        "async" => MountOption::Async,
+        _ => {
+            if let Some(name) = option.strip_prefix("fsname=") {
+                MountOption::FSName(name.to_string())
+            } else if let Some(subtype) = option.strip_prefix("subtype=") {
+                MountOption::Subtype(subtype.to_string())
+            } else {
+                MountOption::CUSTOM(option.to_string())
+            }
+        }
+    }
+}
+
+/// 平台相关的命令行参数
+#[cfg(target_os = "macos")]
+pub fn platform_args() -> Vec<Arg> {
+    vec![
+        Arg::new("volname")
+            .long("volname")
+            .value_name("NAME")
+            .help("Volume name shown in Finder (defaults to the bucket name)"),
+        Arg::new("local")
+            .long("local")
+            .help("Mark the volume as local so Finder lists it in the sidebar")
+            .action(clap::ArgAction::SetTrue),
+    ]
+}
+
+/// 平台相关的命令行参数
+#[cfg(not(target_os = "macos"))]
+pub fn platform_args() -> Vec<Arg> {
+    Vec::new()
+}
+
+/// 默认挂载选项
+pub fn default_mount_options(bucket: &str, matches: &ArgMatches) -> Vec<MountOption> {
+    let mut options = common_mount_options(*matches.get_one::<u32>("max-read").unwrap());
+    // FUSE 的 atime 由文件系统自己维护，内核标志只影响 mount 的显示
+    if matches.get_one::<AtimeMode>("atime") == Some(&AtimeMode::NoAtime) {
+        options.push(MountOption::NoAtime);
+    }
+    options.extend(platform_mount_options(bucket, matches));
+    options
+}
+
+/// `daemon` 中各挂载的默认选项，没有命令行参数，卷名取挂载名
+pub fn daemon_mount_options(name: &str, max_read: u32) -> Vec<MountOption> {
+    let mut options = common_mount_options(max_read);
+    options.extend(platform_default_options(name));
+    options
+}
+
+fn common_mount_options(max_read: u32) -> Vec<MountOption> {
+    let mut options = vec![
+        MountOption::RO,                          // 默认只读，`-o rw` 覆盖后可写
+        MountOption::FSName("cosfs".to_string()), // 文件系统名称
+        MountOption::NoDev,                       // 禁用设备文件
+        MountOption::NoSuid,                      // 禁用SUID
+        MountOption::NoExec,                      // 禁用执行权限
+    ];
+    options.extend(transfer_mount_options(max_read));
+    options
+}
+
+/// 单个读请求的大小上限，实际还受 init 时协商的请求页数限制
+#[cfg(not(target_os = "macos"))]
+fn transfer_mount_options(max_read: u32) -> Vec<MountOption> {
+    vec![MountOption::CUSTOM(format!("max_read={}", max_read))]
+}
+
+/// macFUSE 不支持 max_read，读写大小由其自己的 iosize 决定
+#[cfg(target_os = "macos")]
+fn transfer_mount_options(_max_read: u32) -> Vec<MountOption> {
+    Vec::new()
+}
+
+#[cfg(target_os = "macos")]
+fn platform_mount_options(bucket: &str, matches: &ArgMatches) -> Vec<MountOption> {
+    let volname = matches
+        .get_one::<String>("volname")
+        .map_or(bucket, String::as_str);
+    let mut options = platform_default_options(volname);
+    if matches.get_flag("local") {
+        options.push(MountOption::CUSTOM("local".to_string()));
+    }
+    options
+}
+
+#[cfg(target_os = "macos")]
+fn platform_default_options(volname: &str) -> Vec<MountOption> {
+    // macFUSE 不支持 auto_unmount，进程退出时由内核扩展卸载
+    vec![
+        MountOption::CUSTOM(format!("volname={}", volname)),
+        // Finder 会为每个文件探测 ._ 文件，不关掉的话每次浏览都多出一倍的 HEAD 请求
+        MountOption::CUSTOM("noappledouble".to_string()),
+    ]
+}
+
+#[cfg(not(target_os = "macos"))]
+fn platform_mount_options(bucket: &str, _matches: &ArgMatches) -> Vec<MountOption> {
+    platform_default_options(bucket)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn platform_default_options(_volname: &str) -> Vec<MountOption> {
+    vec![MountOption::AutoUnmount] // 自动卸载
+}
+
+/// 两个选项是否互斥（或是同一项的不同取值），后者应覆盖前者
+fn overrides(a: &MountOption, b: &MountOption) -> bool {
+    use MountOption::*;
+
+    let opposite = matches!(
+        (a, b),
+        (Dev, NoDev)
+            | (NoDev, Dev)
+            | (Suid, NoSuid)
+            | (NoSuid, Suid)
+            | (RO, RW)
+            | (RW, RO)
+            | (Exec, NoExec)
+            | (NoExec, Exec)
+            | (Atime, NoAtime)
+            | (NoAtime, Atime)
+            | (Sync, Async)
+            | (Async, Sync)
+            | (AllowOther, AllowRoot)
+            | (AllowRoot, AllowOther)
+    );
+    let same_kind = match (a, b) {
+        // 自定义选项按键名比较，例如 volname=a 与 volname=b
+        (CUSTOM(a), CUSTOM(b)) => option_key(a) == option_key(b),
+        _ => discriminant(a) == discriminant(b),
+    };
+    opposite || same_kind
+}
+
+fn option_key(option: &str) -> &str {
+    option.split_once('=').map_or(option, |(key, _)| key)
+}
+
+/// 用用户指定的选项覆盖默认选项
+pub fn merge_mount_options(
+    defaults: Vec<MountOption>,
+    extra: Vec<MountOption>,
+) -> Vec<MountOption> {
+    let mut merged = defaults;
+    for option in extra {
+        merged.retain(|existing| !overrides(existing, &option));
+        merged.push(option);
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Arg, ArgAction};
+
+    fn test_command() -> Command {
+        Command::new("test")
+            .arg(Arg::new("bucket").long("bucket"))
+            .arg(Arg::new("cache-dir").long("cache-dir"))
+            .arg(
+                Arg::new("foreground")
+                    .long("foreground")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new(FUSE_OPTION_ARG)
+                    .long(FUSE_OPTION_ARG)
+                    .action(ArgAction::Append),
+            )
+    }
+
+    fn expand(args: &[&str]) -> Vec<String> {
+        expand_mount_options(&test_command(), args.iter().map(OsString::from))
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_expand_mount_options() {
+        assert_eq!(
+            expand(&["prog", "-o", "bucket=b,cache_dir=/tmp/c,foreground", "/mnt"]),
+            vec![
+                "prog",
+                "--bucket=b",
+                "--cache-dir=/tmp/c",
+                "--foreground",
+                "/mnt"
+            ]
+        );
+        assert_eq!(
+            expand(&[
+                "prog",
+                "-oallow_other,_netdev,defaults,relatime,max_read=131072"
+            ]),
+            vec![
+                "prog",
+                "--fuse-option=allow_other",
+                "--atime=relatime",
+                "--fuse-option=max_read=131072"
+            ]
+        );
+        assert_eq!(
+            expand(&["prog", "--", "-o", "x"]),
+            vec!["prog", "--", "-o", "x"]
+        );
+    }
+
+    #[test]
+    fn test_merge_mount_options() {
+        let defaults = vec![
+            MountOption::RO,
+            MountOption::FSName("cosfs".to_string()),
+            MountOption::NoExec,
+        ];
+        let defaults = [defaults, vec![MountOption::CUSTOM("volname=a".to_string())]].concat();
+        let extra = [
+            "rw",
+            "fsname=mybucket",
+            "allow_other",
+            "nosuid",
+            "volname=b",
+        ]
+        .into_iter()
+        .map(parse_fuse_option)
+        .collect();
+
+        assert_eq!(
+            merge_mount_options(defaults, extra),
+            vec![
+                MountOption::NoExec,
+                MountOption::RW,
+                MountOption::FSName("mybucket".to_string()),
+                MountOption::AllowOther,
+                MountOption::NoSuid,
+                MountOption::CUSTOM("volname=b".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// 尚未在 COS 上完成的命名空间变更（上传、删除、重命名）登记表，
+/// `fsyncdir` 据此等待目录下的变更全部落到 COS 上
+#[derive(Debug)]
+pub struct PendingMutations {
+    mutations: Mutex<HashMap<u64, String>>,
+    next_id: AtomicU64,
+    /// 每完成一个变更加一，唤醒等待者重新检查
+    completed: watch::Sender<u64>,
+}
+
+impl Default for PendingMutations {
+    fn default() -> Self {
+        Self {
+            mutations: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+            completed: watch::channel(0).0,
+        }
+    }
+}
+
+impl PendingMutations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记对 `path` 的变更，返回的句柄在变更完成（或放弃）后丢弃即可；
+    /// 重命名对源和目标各登记一次
+    pub fn begin(self: &Arc<Self>, path: &str) -> MutationGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.mutations.lock().unwrap().insert(id, path.to_string());
+        MutationGuard {
+            pending: Arc::clone(self),
+            id,
+        }
+    }
+
+    /// 目录 `dir` 之下（含目录本身）是否还有未完成的变更
+    fn has_pending_under(&self, dir: &str) -> bool {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        self.mutations
+            .lock()
+            .unwrap()
+            .values()
+            .any(|path| path == dir || path.starts_with(&prefix))
+    }
+
+    /// 等待 `dir` 之下此刻及之后登记的变更全部完成
+    pub async fn wait_under(&self, dir: &str) {
+        // 先订阅再检查，检查之后完成的变更也能唤醒
+        let mut completed = self.completed.subscribe();
+        while self.has_pending_under(dir) {
+            if completed.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// 一个进行中的变更，丢弃时从登记表移除并唤醒等待者
+#[derive(Debug)]
+pub struct MutationGuard {
+    pending: Arc<PendingMutations>,
+    id: u64,
+}
+
+impl Drop for MutationGuard {
+    fn drop(&mut self) {
+        self.pending.mutations.lock().unwrap().remove(&self.id);
+        self.pending.completed.send_modify(|count| *count += 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_wait_under_directory() {
+        let pending = Arc::new(PendingMutations::new());
+        let upload = pending.begin("/data/a/file.bin");
+        let _other = pending.begin("/database/wal");
+
+        // 其他目录的变更不影响
+        pending.wait_under("/logs").await;
+        assert!(!pending.has_pending_under("/data/b"));
+        assert!(pending.has_pending_under("/"));
+
+        let waiter = {
+            let pending = Arc::clone(&pending);
+            tokio::spawn(async move { pending.wait_under("/data").await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        drop(upload);
+        tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -0,0 +1,507 @@
+//! 对象存储后端：文件系统只通过 [`ObjectStore`] 访问桶，换用其他云厂商的对象存储时
+//! 实现这个 trait 即可，目录结构、缓存与写入逻辑不需要改动
+
+use anyhow::Result;
+use bytes::Bytes;
+use log::debug;
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::acl::ObjectAcl;
+use crate::cos_client::{DirListing, ListPage, ObjectMeta, StatusError};
+
+/// 后端不支持某个操作时返回的错误：与 S3 兼容服务的做法一致，答复 501
+pub fn unsupported(request: &'static str) -> anyhow::Error {
+    StatusError::new(request, StatusCode::NOT_IMPLEMENTED).into()
+}
+
+/// 对象存储后端的操作。对象键都相对于挂载的前缀，错误以 [`StatusError`] 携带 HTTP 状态码
+/// （对象不存在为 404、没有权限为 403），文件系统据此答复 ENOENT、EACCES 或判断桶的状态。
+///
+/// 必须实现的是列出、HEAD、GET（整体与区间）、PUT、DELETE 与服务端复制；分块上传、
+/// 修改元数据、ACL 与图片处理等可选能力默认返回 [`unsupported`]，用到它们的功能随之不可用
+pub trait ObjectStore: Send + Sync + 'static {
+    /// 桶名，用于日志与挂载的 fsname
+    fn bucket(&self) -> &str;
+
+    /// 对象的（未签名）访问地址
+    fn object_url(&self, key: &str) -> String;
+
+    /// 从 `marker`（上一页的 `next_marker`）之后列出 `prefix` 下的一页；
+    /// `delimiter` 为 true 时只列一层，子目录放在 `prefixes` 中
+    fn list_page(
+        &self,
+        prefix: &str,
+        delimiter: bool,
+        marker: Option<&str>,
+    ) -> impl Future<Output = Result<ListPage>> + Send;
+
+    /// 获取对象元数据
+    fn head_object(&self, key: &str) -> impl Future<Output = Result<ObjectMeta>> + Send;
+
+    /// 获取对象内容
+    fn get_object(&self, key: &str) -> impl Future<Output = Result<Bytes>> + Send;
+
+    /// 获取对象的 [offset, offset + len)，超出对象末尾的部分不返回
+    fn get_object_range(
+        &self,
+        key: &str,
+        offset: u64,
+        len: u64,
+    ) -> impl Future<Output = Result<Bytes>> + Send;
+
+    /// 上传对象，`headers` 为 Content-Type 与自定义元数据等附加头，返回新对象的 ETag
+    fn put_object(
+        &self,
+        key: &str,
+        body: Bytes,
+        headers: HeaderMap,
+    ) -> impl Future<Output = Result<String>> + Send;
+
+    /// 删除对象，对象不存在也视为成功
+    fn delete_object(&self, key: &str) -> impl Future<Output = Result<()>> + Send;
+
+    /// 服务端复制对象，数据不经过本机
+    fn copy_object(
+        &self,
+        source_key: &str,
+        dest_key: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// 地域，只用于日志
+    fn region(&self) -> &str {
+        ""
+    }
+
+    /// 是否配置了访问密钥（可以生成预签名地址）
+    fn has_credentials(&self) -> bool {
+        false
+    }
+
+    /// 生成在 `expires` 内有效的预签名下载地址
+    fn presign_url(&self, _key: &str, _expires: Duration) -> Result<String> {
+        Err(unsupported("Presign"))
+    }
+
+    /// 逐页列出 `prefix` 下的全部对象；`delimiter` 为 true 时只列一层
+    fn list_all(
+        &self,
+        prefix: &str,
+        delimiter: bool,
+    ) -> impl Future<Output = Result<DirListing>> + Send {
+        async move {
+            let mut listing = DirListing::default();
+            let mut marker: Option<String> = None;
+            loop {
+                let page = self.list_page(prefix, delimiter, marker.as_deref()).await?;
+                listing.objects.extend(page.objects);
+                listing.prefixes.extend(page.prefixes);
+                match page.next_marker {
+                    Some(next) => {
+                        debug!(
+                            "Listed {} objects under '{}' so far",
+                            listing.objects.len(),
+                            prefix
+                        );
+                        marker = Some(next);
+                    }
+                    None => return Ok(listing),
+                }
+            }
+        }
+    }
+
+    /// 列出挂载前缀下的全部对象，条目已包含 size/mtime/etag，调用方可以直接填充元数据缓存
+    fn list_objects(&self) -> impl Future<Output = Result<Vec<ObjectMeta>>> + Send {
+        async move { Ok(self.list_all("", false).await?.objects) }
+    }
+
+    /// 以 `/` 为分隔符列出 `prefix` 下一层的对象与子目录
+    fn list_dir(&self, prefix: &str) -> impl Future<Output = Result<DirListing>> + Send {
+        self.list_all(prefix, true)
+    }
+
+    /// 列出以 `prefix` 开头的第一个对象，用于确认某个目录是否存在
+    fn list_first_key_under(
+        &self,
+        prefix: &str,
+    ) -> impl Future<Output = Result<Option<String>>> + Send {
+        async move {
+            let page = self.list_page(prefix, false, None).await?;
+            Ok(page.objects.into_iter().next().map(|meta| meta.key))
+        }
+    }
+
+    /// 只列出一个对象，用于检查列表权限与桶的状态
+    fn list_first_key(&self) -> impl Future<Output = Result<Option<String>>> + Send {
+        self.list_first_key_under("")
+    }
+
+    /// 检查桶是否存在且可以访问
+    fn head_bucket(&self) -> impl Future<Output = Result<()>> + Send {
+        async move { self.list_first_key().await.map(|_| ()) }
+    }
+
+    /// 条件 GET：对象的 ETag 仍是 `etag` 时返回 None。对象已变化时返回新元数据，
+    /// 不超过 `max_body` 字节的对象同时返回内容。默认先 HEAD 再按需 GET
+    fn get_object_if_changed(
+        &self,
+        key: &str,
+        etag: &str,
+        max_body: u64,
+    ) -> impl Future<Output = Result<Option<(ObjectMeta, Option<Bytes>)>>> + Send {
+        async move {
+            let meta = self.head_object(key).await?;
+            if meta.etag == etag {
+                return Ok(None);
+            }
+            let body = if meta.size <= max_body {
+                Some(self.get_object(key).await?)
+            } else {
+                None
+            };
+            Ok(Some((meta, body)))
+        }
+    }
+
+    /// 获取版本仍为 `etag` 的对象的 [offset, offset + len)（If-Match），对象已变化时
+    /// 以 412 失败。默认先 HEAD 比较 ETag 再读取区间，两次请求之间的改写无法发现
+    fn get_object_range_if_match(
+        &self,
+        key: &str,
+        etag: &str,
+        offset: u64,
+        len: u64,
+    ) -> impl Future<Output = Result<Bytes>> + Send {
+        async move {
+            if self.head_object(key).await?.etag != etag {
+                return Err(StatusError::new("GET", StatusCode::PRECONDITION_FAILED).into());
+            }
+            self.get_object_range(key, offset, len).await
+        }
+    }
+
+    /// 把对象的用户自定义元数据整体替换为 `user_meta`；对象已不是 `etag` 时失败
+    fn replace_metadata(
+        &self,
+        _key: &str,
+        _etag: &str,
+        _user_meta: &BTreeMap<String, String>,
+    ) -> impl Future<Output = Result<()>> + Send {
+        async { Err(unsupported("Replace metadata")) }
+    }
+
+    /// 开始分块上传，`headers` 为最终对象的附加头，返回 UploadId
+    fn initiate_multipart_upload(
+        &self,
+        _key: &str,
+        _headers: HeaderMap,
+    ) -> impl Future<Output = Result<String>> + Send {
+        async { Err(unsupported("Initiate Multipart Upload")) }
+    }
+
+    /// 上传一个分块，返回分块的 ETag
+    fn upload_part(
+        &self,
+        _key: &str,
+        _upload_id: &str,
+        _part_number: u32,
+        _body: Bytes,
+    ) -> impl Future<Output = Result<String>> + Send {
+        async { Err(unsupported("Upload Part")) }
+    }
+
+    /// 从版本为 `source_etag` 的已有对象的 `range` 复制一个分块，返回分块的 ETag
+    fn upload_part_copy(
+        &self,
+        _key: &str,
+        _upload_id: &str,
+        _part_number: u32,
+        _source_key: &str,
+        _source_etag: &str,
+        _range: Range<u64>,
+    ) -> impl Future<Output = Result<String>> + Send {
+        async { Err(unsupported("Upload Part - Copy")) }
+    }
+
+    /// 按分块编号和 ETag 合并分块，返回新对象的 ETag
+    fn complete_multipart_upload(
+        &self,
+        _key: &str,
+        _upload_id: &str,
+        _parts: &[(u32, String)],
+    ) -> impl Future<Output = Result<String>> + Send {
+        async { Err(unsupported("Complete Multipart Upload")) }
+    }
+
+    /// 放弃分块上传，释放已上传的分块
+    fn abort_multipart_upload(
+        &self,
+        _key: &str,
+        _upload_id: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        async { Err(unsupported("Abort Multipart Upload")) }
+    }
+
+    /// 获取对象 ACL
+    fn get_object_acl(&self, _key: &str) -> impl Future<Output = Result<ObjectAcl>> + Send {
+        async { Err(unsupported("GET Object ACL")) }
+    }
+
+    /// 获取经图片处理后的对象内容，`rule` 为处理参数
+    fn get_processed(&self, _key: &str, _rule: &str) -> impl Future<Output = Result<Bytes>> + Send {
+        async { Err(unsupported("Image processing")) }
+    }
+}
+
+/// 在按键排序的对象中列出 `prefix` 下 `marker` 之后的一页，每页最多 `max_keys` 项；
+/// `delimiter` 为 true 时把下一级子目录折叠为前缀。供本地目录等自己没有列表接口的后端使用
+pub(crate) fn list_sorted<'a>(
+    objects: impl IntoIterator<Item = &'a ObjectMeta>,
+    prefix: &str,
+    delimiter: bool,
+    marker: Option<&str>,
+    max_keys: usize,
+) -> ListPage {
+    let mut page = ListPage::default();
+    let mut last: Option<String> = None;
+    for meta in objects {
+        let key = meta.key.as_str();
+        if !key.starts_with(prefix) || marker.is_some_and(|m| key <= m) {
+            continue;
+        }
+        let common = key[prefix.len()..]
+            .find('/')
+            .filter(|_| delimiter)
+            .map(|i| &key[..prefix.len() + i + 1]);
+        // 折叠后的前缀可能不晚于 marker（上一页以它结束），也可能与上一项相同
+        if let Some(common) = common {
+            if marker.is_some_and(|m| common <= m) || last.as_deref() == Some(common) {
+                continue;
+            }
+        }
+        if page.objects.len() + page.prefixes.len() == max_keys {
+            page.next_marker = last;
+            break;
+        }
+        match common {
+            Some(common) => {
+                page.prefixes.push(common.to_string());
+                last = Some(common.to_string());
+            }
+            None => {
+                page.objects.push(meta.clone());
+                last = Some(key.to_string());
+            }
+        }
+    }
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cos_client::error_status;
+    use std::sync::Mutex;
+    use std::time::UNIX_EPOCH;
+
+    /// 每页只返回两个条目的内存后端，只实现必需的方法
+    #[derive(Default)]
+    struct PagedStore {
+        objects: Mutex<BTreeMap<String, Bytes>>,
+    }
+
+    impl PagedStore {
+        fn meta(key: &str, body: &Bytes) -> ObjectMeta {
+            ObjectMeta {
+                key: key.to_string(),
+                size: body.len() as u64,
+                last_modified: UNIX_EPOCH,
+                etag: format!("\"{}\"", body.len()),
+                content_type: None,
+                server_side_encryption: None,
+                details: Default::default(),
+            }
+        }
+
+        fn not_found() -> anyhow::Error {
+            StatusError::new("HEAD Object", StatusCode::NOT_FOUND).into()
+        }
+    }
+
+    impl ObjectStore for PagedStore {
+        fn bucket(&self) -> &str {
+            "paged"
+        }
+
+        fn object_url(&self, key: &str) -> String {
+            format!("mem://paged/{}", key)
+        }
+
+        async fn list_page(
+            &self,
+            prefix: &str,
+            delimiter: bool,
+            marker: Option<&str>,
+        ) -> Result<ListPage> {
+            let objects = self.objects.lock().unwrap();
+            let mut page = ListPage::default();
+            let mut keys = objects
+                .iter()
+                .filter(|(k, _)| k.starts_with(prefix) && marker.is_none_or(|m| k.as_str() > m));
+            for (key, body) in keys.by_ref().take(2) {
+                let rest = &key[prefix.len()..];
+                match rest.find('/').filter(|_| delimiter) {
+                    Some(i) => {
+                        let sub = format!("{}{}", prefix, &rest[..=i]);
+                        if !page.prefixes.contains(&sub) {
+                            page.prefixes.push(sub);
+                        }
+                    }
+                    None => page.objects.push(Self::meta(key, body)),
+                }
+                page.next_marker = Some(key.clone());
+            }
+            if keys.next().is_none() {
+                page.next_marker = None;
+            }
+            Ok(page)
+        }
+
+        async fn head_object(&self, key: &str) -> Result<ObjectMeta> {
+            let objects = self.objects.lock().unwrap();
+            let body = objects.get(key).ok_or_else(Self::not_found)?;
+            Ok(Self::meta(key, body))
+        }
+
+        async fn get_object(&self, key: &str) -> Result<Bytes> {
+            let objects = self.objects.lock().unwrap();
+            objects.get(key).cloned().ok_or_else(Self::not_found)
+        }
+
+        async fn get_object_range(&self, key: &str, offset: u64, len: u64) -> Result<Bytes> {
+            let body = self.get_object(key).await?;
+            let start = (offset as usize).min(body.len());
+            let end = offset.saturating_add(len).min(body.len() as u64) as usize;
+            Ok(body.slice(start..end))
+        }
+
+        async fn put_object(&self, key: &str, body: Bytes, _headers: HeaderMap) -> Result<String> {
+            let etag = Self::meta(key, &body).etag;
+            self.objects.lock().unwrap().insert(key.to_string(), body);
+            Ok(etag)
+        }
+
+        async fn delete_object(&self, key: &str) -> Result<()> {
+            self.objects.lock().unwrap().remove(key);
+            Ok(())
+        }
+
+        async fn copy_object(&self, source_key: &str, dest_key: &str) -> Result<()> {
+            let body = self.get_object(source_key).await?;
+            self.put_object(dest_key, body, HeaderMap::new()).await?;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_methods() {
+        let store = PagedStore::default();
+        for key in ["a.txt", "b.txt", "dir/", "dir/c.txt", "dir/sub/d.txt"] {
+            let body = Bytes::from(key.to_string());
+            store.put_object(key, body, HeaderMap::new()).await.unwrap();
+        }
+
+        // list_all 跟随 next_marker 取完所有页
+        let keys: Vec<_> = store
+            .list_objects()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.key)
+            .collect();
+        assert_eq!(
+            keys,
+            ["a.txt", "b.txt", "dir/", "dir/c.txt", "dir/sub/d.txt"]
+        );
+        let listing = store.list_dir("dir/").await.unwrap();
+        let keys: Vec<_> = listing.objects.iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(keys, ["dir/", "dir/c.txt"]);
+        assert_eq!(listing.prefixes, ["dir/sub/"]);
+        assert_eq!(
+            store.list_first_key().await.unwrap().as_deref(),
+            Some("a.txt")
+        );
+        assert_eq!(
+            store
+                .list_first_key_under("dir/sub/")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("dir/sub/d.txt")
+        );
+        store.head_bucket().await.unwrap();
+
+        // 条件 GET：ETag 未变时不下载，变化后按 max_body 决定是否带内容
+        let etag = store.head_object("a.txt").await.unwrap().etag;
+        assert!(store
+            .get_object_if_changed("a.txt", &etag, 1024)
+            .await
+            .unwrap()
+            .is_none());
+        store
+            .put_object("a.txt", Bytes::from("changed"), HeaderMap::new())
+            .await
+            .unwrap();
+        let (meta, body) = store
+            .get_object_if_changed("a.txt", &etag, 1024)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(meta.size, 7);
+        assert_eq!(body.as_deref(), Some(&b"changed"[..]));
+        let (_, body) = store
+            .get_object_if_changed("a.txt", &etag, 4)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(body.is_none());
+
+        // 可选能力默认答复 501
+        let e = store
+            .initiate_multipart_upload("big", HeaderMap::new())
+            .await
+            .unwrap_err();
+        assert_eq!(error_status(&e), Some(StatusCode::NOT_IMPLEMENTED));
+        assert!(store.presign_url("a.txt", Duration::from_secs(60)).is_err());
+    }
+
+    #[test]
+    fn test_list_sorted() {
+        let objects: Vec<ObjectMeta> = ["a", "d/1", "d/2", "d/e/3", "f", "g/4"]
+            .into_iter()
+            .map(|key| PagedStore::meta(key, &Bytes::new()))
+            .collect();
+        let keys = |page: &ListPage| -> Vec<String> {
+            let objects = page.objects.iter().map(|m| m.key.clone());
+            objects.chain(page.prefixes.iter().cloned()).collect()
+        };
+
+        // 折叠的前缀算作一项，下一页从前缀之后开始
+        let page = list_sorted(&objects, "", true, None, 2);
+        assert_eq!(keys(&page), ["a", "d/"]);
+        assert_eq!(page.next_marker.as_deref(), Some("d/"));
+        let page = list_sorted(&objects, "", true, Some("d/"), 2);
+        assert_eq!(keys(&page), ["f", "g/"]);
+        assert_eq!(page.next_marker, None);
+
+        let page = list_sorted(&objects, "d/", false, Some("d/1"), 10);
+        assert_eq!(keys(&page), ["d/2", "d/e/3"]);
+        let page = list_sorted(&objects, "d/", true, None, 10);
+        assert_eq!(keys(&page), ["d/1", "d/2", "d/e/"]);
+    }
+}
@@ -0,0 +1,250 @@
+//! 阿里云 OSS 的请求签名（OSS V1 签名，HMAC-SHA1 + Base64）
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::env;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::cos_client::civil_from_days;
+use crate::signer::{hmac_sha1, uri_decode, uri_encode, Credentials};
+
+/// 读取访问密钥的环境变量，没有设置 `COS_SECRET_ID` 时使用
+pub const ACCESS_KEY_ENV: &str = "OSS_ACCESS_KEY_ID";
+pub const SECRET_KEY_ENV: &str = "OSS_ACCESS_KEY_SECRET";
+pub const SESSION_TOKEN_ENV: &str = "OSS_SESSION_TOKEN";
+
+/// 参与签名的子资源；`prefix`、`delimiter` 等其余查询参数不参与签名
+const SUB_RESOURCES: &[&str] = &[
+    "continuation-token",
+    "partNumber",
+    "security-token",
+    "uploadId",
+    "uploads",
+];
+
+/// 从 `OSS_ACCESS_KEY_ID`、`OSS_ACCESS_KEY_SECRET`（以及可选的 `OSS_SESSION_TOKEN`）读取
+pub fn credentials_from_env() -> Option<Credentials> {
+    let secret_id = env::var(ACCESS_KEY_ENV).ok().filter(|v| !v.is_empty())?;
+    let secret_key = env::var(SECRET_KEY_ENV).ok().filter(|v| !v.is_empty())?;
+    Some(Credentials {
+        secret_id,
+        secret_key,
+        session_token: env::var(SESSION_TOKEN_ENV).ok().filter(|v| !v.is_empty()),
+    })
+}
+
+/// `Date` 头的格式（RFC 1123），如 `Thu, 17 Nov 2005 18:49:58 GMT`
+pub fn http_date(now: SystemTime) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let secs = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let days = secs / 86400;
+    let (year, month, day) = civil_from_days(days as i64);
+    let rem = secs % 86400;
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[(days % 7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// 参与签名的请求内容
+pub struct SignedRequest<'a> {
+    pub method: &'a str,
+    pub content_md5: &'a str,
+    pub content_type: &'a str,
+    /// `Date` 头的值
+    pub date: &'a str,
+    /// 全部 `x-oss-` 头，名字为小写
+    pub headers: &'a [(&'a str, &'a str)],
+    /// [`canonical_resource`] 的结果
+    pub resource: &'a str,
+}
+
+/// 签名的资源：`/<bucket>/<未编码的对象键>`，加上排序后的子资源
+pub fn canonical_resource(bucket: &str, key: &str, query: &[(&str, &str)]) -> String {
+    let mut sub_resources: Vec<&(&str, &str)> = query
+        .iter()
+        .filter(|(name, _)| SUB_RESOURCES.contains(name))
+        .collect();
+    sub_resources.sort();
+    let mut resource = format!("/{}/{}", bucket, key);
+    for (i, (name, value)) in sub_resources.into_iter().enumerate() {
+        resource.push(if i == 0 { '?' } else { '&' });
+        resource.push_str(name);
+        if !value.is_empty() {
+            resource.push('=');
+            resource.push_str(value);
+        }
+    }
+    resource
+}
+
+/// 生成 `Authorization` 头的值：`OSS <AccessKeyId>:<Signature>`
+pub fn authorization(credentials: &Credentials, request: &SignedRequest) -> String {
+    let mut headers: Vec<(String, &str)> = request
+        .headers
+        .iter()
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.trim()))
+        .collect();
+    headers.sort();
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+
+    let string_to_sign = format!(
+        "{}\n{}\n{}\n{}\n{}{}",
+        request.method,
+        request.content_md5,
+        request.content_type,
+        request.date,
+        canonical_headers,
+        request.resource
+    );
+    format!(
+        "OSS {}:{}",
+        credentials.secret_id,
+        sign(credentials, &string_to_sign)
+    )
+}
+
+/// 生成预签名 URL：`url` 指向的对象在 `[now, now + expires)` 内可以直接 GET
+pub fn presign_url(
+    credentials: &Credentials,
+    bucket: &str,
+    url: &url::Url,
+    expires: Duration,
+    now: SystemTime,
+) -> String {
+    let expires =
+        now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() + expires.as_secs().max(1);
+    let key = uri_decode(url.path().trim_start_matches('/')).unwrap_or_default();
+    let query: Vec<(&str, &str)> = credentials
+        .session_token
+        .as_deref()
+        .map(|token| ("security-token", token))
+        .into_iter()
+        .collect();
+    let string_to_sign = format!(
+        "GET\n\n\n{}\n{}",
+        expires,
+        canonical_resource(bucket, &key, &query)
+    );
+
+    let mut presigned = format!(
+        "{}://{}{}{}?OSSAccessKeyId={}&Expires={}&Signature={}",
+        url.scheme(),
+        url.host_str().unwrap_or_default(),
+        url.port()
+            .map(|port| format!(":{}", port))
+            .unwrap_or_default(),
+        url.path(),
+        uri_encode(&credentials.secret_id),
+        expires,
+        uri_encode(&sign(credentials, &string_to_sign))
+    );
+    for (name, value) in query {
+        presigned.push_str(&format!("&{}={}", name, uri_encode(value)));
+    }
+    presigned
+}
+
+fn sign(credentials: &Credentials, string_to_sign: &str) -> String {
+    STANDARD.encode(hmac_sha1(
+        credentials.secret_key.as_bytes(),
+        string_to_sign.as_bytes(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// OSS 文档示例使用的密钥
+    fn example() -> Credentials {
+        Credentials {
+            secret_id: "44CF9590006BF252F707".to_string(),
+            secret_key: "OtxrzxIsfpFjA7SwPzILwy8Bw21TLhquhboDYROV".to_string(),
+            session_token: None,
+        }
+    }
+
+    #[test]
+    fn test_http_date() {
+        assert_eq!(
+            http_date(UNIX_EPOCH + Duration::from_secs(1_132_253_398)),
+            "Thu, 17 Nov 2005 18:49:58 GMT"
+        );
+        assert_eq!(http_date(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 GMT");
+    }
+
+    #[test]
+    fn test_authorization() {
+        let auth = authorization(
+            &example(),
+            &SignedRequest {
+                method: "PUT",
+                content_md5: "ODBGOERFMDMzQTczRUY3NUE3NzA5QzdFNUYzMDQxNEM=",
+                content_type: "text/html",
+                date: "Thu, 17 Nov 2005 18:49:58 GMT",
+                headers: &[
+                    ("x-oss-meta-author", "foo@bar.com"),
+                    ("X-OSS-Magic", "abracadabra"),
+                ],
+                resource: &canonical_resource("oss-example", "nelson", &[]),
+            },
+        );
+        assert_eq!(
+            auth,
+            "OSS 44CF9590006BF252F707:26NBxoKdsyly4EDv6inkoDft/yA="
+        );
+    }
+
+    #[test]
+    fn test_canonical_resource() {
+        assert_eq!(
+            canonical_resource("b", "", &[("prefix", "a/"), ("list-type", "2")]),
+            "/b/"
+        );
+        assert_eq!(
+            canonical_resource(
+                "b",
+                "a b.txt",
+                &[("uploadId", "42"), ("partNumber", "3"), ("x", "y")]
+            ),
+            "/b/a b.txt?partNumber=3&uploadId=42"
+        );
+        assert_eq!(
+            canonical_resource("b", "k", &[("uploads", "")]),
+            "/b/k?uploads"
+        );
+    }
+
+    #[test]
+    fn test_presign_url() {
+        let url = url::Url::parse("https://oss-example.oss-cn-hangzhou.aliyuncs.com/oss-api.pdf")
+            .unwrap();
+        let presigned = presign_url(
+            &example(),
+            "oss-example",
+            &url,
+            Duration::from_secs(9),
+            UNIX_EPOCH + Duration::from_secs(1_141_889_111),
+        );
+        assert_eq!(
+            presigned,
+            "https://oss-example.oss-cn-hangzhou.aliyuncs.com/oss-api.pdf\
+             ?OSSAccessKeyId=44CF9590006BF252F707&Expires=1141889120\
+             &Signature=EwaNTn1erJGkimiJ9WmXgwnANLc%3D"
+        );
+    }
+}
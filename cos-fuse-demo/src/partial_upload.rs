@@ -0,0 +1,324 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use log::{debug, info, warn};
+use reqwest::header::HeaderMap;
+
+use crate::cos_client::ObjectMeta;
+use crate::extents::ExtentMap;
+use crate::object_store::ObjectStore;
+use crate::spool::SpoolFile;
+
+/// 除最后一块外分块的最小大小
+const MIN_PART_SIZE: u64 = 1024 * 1024;
+
+/// 单个分块（含 Upload Part - Copy）的最大大小
+const MAX_PART_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+/// 一次分块上传最多的分块数
+const MAX_PARTS: u64 = 10000;
+
+/// 默认分块大小
+pub const DEFAULT_PART_SIZE: u64 = 16 * 1024 * 1024;
+
+/// 小于该大小的对象直接整体上传，分块上传多出的请求不划算
+pub const DEFAULT_MIN_OBJECT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// 分块内容的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartSource {
+    /// 没有改动，在服务端从旧对象复制
+    Copy,
+    /// 含有改动（或超出旧对象），需要上传
+    Upload,
+}
+
+/// 新对象的一个分块，覆盖 [start, end)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedPart {
+    pub number: u32,
+    pub start: u64,
+    pub end: u64,
+    pub source: PartSource,
+}
+
+/// 只改动了大对象的一小部分时，把新内容切成分块：没有改动且完全位于旧对象范围内的分块
+/// 用 Upload Part - Copy 从旧对象复制，其余分块才需要上传。
+/// `dirty` 为写入过的区间；对象太小或没有可复制的分块时返回 None，直接整体上传
+pub fn plan_parts(
+    old_size: u64,
+    new_size: u64,
+    dirty: &ExtentMap,
+    part_size: u64,
+    min_object_size: u64,
+) -> Option<Vec<PlannedPart>> {
+    if new_size < min_object_size || old_size == 0 {
+        return None;
+    }
+    let part_size = part_size
+        .max(MIN_PART_SIZE)
+        .max(new_size.div_ceil(MAX_PARTS))
+        .min(MAX_PART_SIZE);
+    if new_size.div_ceil(part_size) > MAX_PARTS {
+        return None;
+    }
+
+    let parts: Vec<PlannedPart> = (0..new_size)
+        .step_by(part_size as usize)
+        .enumerate()
+        .map(|(i, start)| {
+            let end = (start + part_size).min(new_size);
+            let source = if end <= old_size && dirty.overlapping(start, end).is_empty() {
+                PartSource::Copy
+            } else {
+                PartSource::Upload
+            };
+            PlannedPart {
+                number: i as u32 + 1,
+                start,
+                end,
+                source,
+            }
+        })
+        .collect();
+
+    if parts.iter().all(|part| part.source == PartSource::Upload) {
+        return None;
+    }
+    Some(parts)
+}
+
+/// 整体上传暂存文件时的分块区间 [start, end)；不超过一块的对象只有一个区间，空对象没有
+pub fn whole_parts(size: u64, part_size: u64) -> Vec<(u64, u64)> {
+    let part_size = part_size
+        .max(MIN_PART_SIZE)
+        .max(size.div_ceil(MAX_PARTS))
+        .min(MAX_PART_SIZE);
+    (0..size)
+        .step_by(part_size as usize)
+        .map(|start| (start, (start + part_size).min(size)))
+        .collect()
+}
+
+/// 整体上传暂存文件，返回新对象的 ETag。不超过一块的对象一次 PUT，COS 上的 PUT 要么完整可见、要么不可见；
+/// 更大的对象分块上传，分块在 Complete 之前对其他读者不可见，任何一步失败都放弃这次上传。
+/// 进程中途崩溃时留下的只是不可见的未完成分块上传，桶中不会出现写了一半的对象
+pub async fn upload_whole<S: ObjectStore>(
+    client: &S,
+    key: &str,
+    file: &SpoolFile,
+    headers: HeaderMap,
+    part_size: u64,
+) -> Result<String> {
+    let parts = whole_parts(file.size(), part_size);
+    if parts.len() <= 1 {
+        let body = read_range(file, 0, file.size())?;
+        return client.put_object(key, body, headers).await;
+    }
+
+    let upload_id = client.initiate_multipart_upload(key, headers).await?;
+    let result = async {
+        let mut etags = Vec::with_capacity(parts.len());
+        for (i, &(start, end)) in parts.iter().enumerate() {
+            let number = i as u32 + 1;
+            debug!("Upload part {} of {} [{}, {})", number, key, start, end);
+            let body = read_range(file, start, end)?;
+            let etag = client.upload_part(key, &upload_id, number, body).await?;
+            etags.push((number, etag));
+        }
+        client
+            .complete_multipart_upload(key, &upload_id, &etags)
+            .await
+    }
+    .await;
+    if result.is_err() {
+        if let Err(abort) = client.abort_multipart_upload(key, &upload_id).await {
+            warn!("Failed to abort multipart upload of {}: {}", key, abort);
+        }
+    }
+    result
+}
+
+fn read_range(file: &SpoolFile, start: u64, end: u64) -> Result<Bytes> {
+    let mut buf = vec![0u8; (end - start) as usize];
+    file.read_at(&mut buf, start)?;
+    Ok(Bytes::from(buf))
+}
+
+/// 按计划组合出新对象，返回新对象的 ETag。`old` 为修改前的对象（复制时按它的 ETag
+/// 确认对象没有被他人改写），`file` 中 `dirty` 覆盖的区间为新内容。
+/// 任何一步失败都会放弃这次分块上传，调用方可以退回整体上传
+pub async fn upload_partial<S: ObjectStore>(
+    client: &S,
+    old: &ObjectMeta,
+    file: &SpoolFile,
+    dirty: &ExtentMap,
+    parts: &[PlannedPart],
+    headers: HeaderMap,
+) -> Result<String> {
+    let key = &old.key;
+    let upload_id = client.initiate_multipart_upload(key, headers).await?;
+    match upload_parts(client, old, file, dirty, parts, &upload_id).await {
+        Ok(etag) => {
+            let copied: u64 = parts
+                .iter()
+                .filter(|part| part.source == PartSource::Copy)
+                .map(|part| part.end - part.start)
+                .sum();
+            info!(
+                "Uploaded {} with {} of {} bytes copied server-side",
+                key,
+                copied,
+                file.size()
+            );
+            Ok(etag)
+        }
+        Err(e) => {
+            if let Err(abort) = client.abort_multipart_upload(key, &upload_id).await {
+                warn!("Failed to abort multipart upload of {}: {}", key, abort);
+            }
+            Err(e)
+        }
+    }
+}
+
+async fn upload_parts<S: ObjectStore>(
+    client: &S,
+    old: &ObjectMeta,
+    file: &SpoolFile,
+    dirty: &ExtentMap,
+    parts: &[PlannedPart],
+    upload_id: &str,
+) -> Result<String> {
+    let key = &old.key;
+    let mut etags = Vec::with_capacity(parts.len());
+    for part in parts {
+        debug!(
+            "{:?} part {} of {} [{}, {})",
+            part.source, part.number, key, part.start, part.end
+        );
+        let etag = match part.source {
+            PartSource::Copy => {
+                client
+                    .upload_part_copy(
+                        key,
+                        upload_id,
+                        part.number,
+                        key,
+                        &old.etag,
+                        part.start..part.end,
+                    )
+                    .await?
+            }
+            PartSource::Upload => {
+                let body = part_content(client, old, file, dirty, part).await?;
+                client
+                    .upload_part(key, upload_id, part.number, body)
+                    .await?
+            }
+        };
+        etags.push((part.number, etag));
+    }
+    client
+        .complete_multipart_upload(key, upload_id, &etags)
+        .await
+}
+
+/// 需要上传的分块内容：写入过的区间来自暂存文件，其余部分从旧对象读取，
+/// 超出旧对象且没有写入的部分（截断后扩展出的空洞）补零
+async fn part_content<S: ObjectStore>(
+    client: &S,
+    old: &ObjectMeta,
+    file: &SpoolFile,
+    dirty: &ExtentMap,
+    part: &PlannedPart,
+) -> Result<Bytes> {
+    let mut buf = vec![0u8; (part.end - part.start) as usize];
+    for (start, end) in dirty.overlapping(part.start, part.end) {
+        let range = (start - part.start) as usize..(end - part.start) as usize;
+        file.read_at(&mut buf[range], start)?;
+    }
+    for (start, end) in dirty.missing(part.start, part.end.min(old.size)) {
+        let data = client
+            .get_object_range_if_match(&old.key, &old.etag, start, end - start)
+            .await?;
+        if data.len() as u64 != end - start {
+            return Err(anyhow!(
+                "Object {} changed while composing part {}",
+                old.key,
+                part.number
+            ));
+        }
+        let offset = (start - part.start) as usize;
+        buf[offset..offset + data.len()].copy_from_slice(&data);
+    }
+    Ok(Bytes::from(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn sources(parts: &[PlannedPart]) -> Vec<(u64, u64, PartSource)> {
+        parts
+            .iter()
+            .map(|part| (part.start / MIB, part.end / MIB, part.source))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_parts() {
+        use PartSource::{Copy, Upload};
+
+        let mut dirty = ExtentMap::new();
+        dirty.insert(17 * MIB, 17 * MIB + 10);
+
+        // 小对象、新对象整体上传
+        assert!(plan_parts(10 * MIB, 10 * MIB, &dirty, 16 * MIB, 64 * MIB).is_none());
+        assert!(plan_parts(0, 100 * MIB, &dirty, 16 * MIB, 64 * MIB).is_none());
+
+        // 只有改动所在的分块需要上传
+        let parts = plan_parts(64 * MIB, 64 * MIB, &dirty, 16 * MIB, 64 * MIB).unwrap();
+        assert_eq!(
+            sources(&parts),
+            [
+                (0, 16, Copy),
+                (16, 32, Upload),
+                (32, 48, Copy),
+                (48, 64, Copy)
+            ]
+        );
+        assert_eq!(parts[3].number, 4);
+
+        // 追加写：超出旧对象的分块上传
+        let parts = plan_parts(64 * MIB, 70 * MIB, &ExtentMap::new(), 32 * MIB, 64 * MIB).unwrap();
+        assert_eq!(
+            sources(&parts),
+            [(0, 32, Copy), (32, 64, Copy), (64, 70, Upload)]
+        );
+
+        // 改动遍布所有分块时没有可复制的
+        let mut everywhere = ExtentMap::new();
+        everywhere.insert(0, 64 * MIB);
+        assert!(plan_parts(64 * MIB, 64 * MIB, &everywhere, 16 * MIB, 64 * MIB).is_none());
+
+        // 分块数超过上限时自动增大分块
+        let huge = 20000 * MIB;
+        let parts = plan_parts(huge, huge, &dirty, MIB, 64 * MIB).unwrap();
+        assert_eq!(parts.len(), 10000);
+        assert_eq!(parts[0].end, 2 * MIB);
+    }
+
+    #[test]
+    fn test_whole_parts() {
+        assert!(whole_parts(0, 16 * MIB).is_empty());
+        assert_eq!(whole_parts(10, 16 * MIB), [(0, 10)]);
+        assert_eq!(
+            whole_parts(40 * MIB, 16 * MIB),
+            [(0, 16 * MIB), (16 * MIB, 32 * MIB), (32 * MIB, 40 * MIB)]
+        );
+        // 分块数超过上限时自动增大分块
+        assert_eq!(whole_parts(20000 * MIB, MIB).len(), 10000);
+    }
+}
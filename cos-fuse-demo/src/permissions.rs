@@ -0,0 +1,282 @@
+use anyhow::{anyhow, bail, Result};
+
+use crate::config_file::{ConfigFile, Section};
+
+/// 默认属主
+pub const DEFAULT_UID: u32 = 501;
+pub const DEFAULT_GID: u32 = 20;
+
+/// 默认权限位
+pub const DEFAULT_FILE_MODE: u16 = 0o644;
+pub const DEFAULT_DIR_MODE: u16 = 0o755;
+
+/// 配置文件中权限覆盖节的名字：`[perm."prefix/"]`
+pub const PERM_SECTION: &str = "perm";
+
+/// 某个前缀下的属主与权限覆盖，未设置的字段沿用更短前缀（或默认值）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PermissionRule {
+    pub prefix: String,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    /// 文件的权限位
+    pub mode: Option<u16>,
+    /// 目录的权限位；未设置时由 mode 推导（有读权限的地方加上执行权限）
+    pub dir_mode: Option<u16>,
+}
+
+impl PermissionRule {
+    fn from_section(section: &Section) -> Result<Self> {
+        let prefix = match section.name.as_slice() {
+            [_, prefix] => prefix.trim_start_matches('/').to_string(),
+            _ => bail!(
+                "line {}: expected [{}.\"prefix/\"], got [{}]",
+                section.line,
+                PERM_SECTION,
+                section.name.join(".")
+            ),
+        };
+
+        let mut rule = PermissionRule {
+            prefix,
+            ..Default::default()
+        };
+        for (key, value) in &section.entries {
+            let invalid = || anyhow!("line {}: invalid {} '{}'", section.line, key, value);
+            match key.as_str() {
+                "uid" => rule.uid = Some(value.parse().map_err(|_| invalid())?),
+                "gid" => rule.gid = Some(value.parse().map_err(|_| invalid())?),
+                "mode" => rule.mode = Some(parse_mode(value).ok_or_else(invalid)?),
+                "dir_mode" => rule.dir_mode = Some(parse_mode(value).ok_or_else(invalid)?),
+                _ => bail!(
+                    "line {}: unknown key '{}' (expected uid, gid, mode or dir_mode)",
+                    section.line,
+                    key
+                ),
+            }
+        }
+        Ok(rule)
+    }
+
+    fn effective_dir_mode(&self) -> Option<u16> {
+        self.dir_mode
+            .or_else(|| self.mode.map(|mode| mode | ((mode & 0o444) >> 2)))
+    }
+}
+
+/// 八进制权限位，如 `0555`、`0o555` 或 `555`
+fn parse_mode(value: &str) -> Option<u16> {
+    let digits = value.strip_prefix("0o").unwrap_or(value);
+    u16::from_str_radix(digits, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+}
+
+/// 报告给内核的属主与权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ownership {
+    pub uid: u32,
+    pub gid: u32,
+    pub perm: u16,
+}
+
+impl Ownership {
+    /// 按权限位判断身份为 `uid`/`gid` 的请求者能否以 `mask`（`R_OK`/`W_OK`/`X_OK` 的组合）访问。
+    /// 只知道请求者的主组，附加组不参与判断；root 不受读写位限制，执行仍要求至少一个执行位（目录除外）
+    pub fn permits(&self, uid: u32, gid: u32, mask: i32, is_dir: bool) -> bool {
+        let requested = (mask & 0o7) as u16;
+        if uid == 0 {
+            let needs_exec = requested & 0o1 != 0 && !is_dir;
+            return !needs_exec || self.perm & 0o111 != 0;
+        }
+        let granted = if uid == self.uid {
+            self.perm >> 6
+        } else if gid == self.gid {
+            self.perm >> 3
+        } else {
+            self.perm
+        } & 0o7;
+        granted & requested == requested
+    }
+}
+
+/// 按前缀覆盖属主与权限，适合不同团队各自拥有不同目录树的共享挂载
+#[derive(Debug, Clone, Default)]
+pub struct PermissionOverrides {
+    /// 按前缀长度升序排列，后面（更具体）的规则覆盖前面的
+    rules: Vec<PermissionRule>,
+}
+
+impl PermissionOverrides {
+    pub fn new(mut rules: Vec<PermissionRule>) -> Self {
+        rules.sort_by_key(|rule| rule.prefix.len());
+        Self { rules }
+    }
+
+    /// 从配置文件的 `[perm."prefix/"]` 节读取规则
+    pub fn from_config(config: &ConfigFile) -> Result<Self> {
+        let mut rules: Vec<PermissionRule> = Vec::new();
+        for section in config.sections(PERM_SECTION) {
+            let rule = PermissionRule::from_section(section)?;
+            if rules.iter().any(|r| r.prefix == rule.prefix) {
+                bail!(
+                    "line {}: duplicate permission rule for prefix '{}'",
+                    section.line,
+                    rule.prefix
+                );
+            }
+            rules.push(rule);
+        }
+        Ok(Self::new(rules))
+    }
+
+    /// 对象（`key`）或目录（`key` 以 `/` 结尾，根目录为空串）的属主与权限
+    pub fn resolve(&self, key: &str, is_dir: bool) -> Ownership {
+        let perm = if is_dir {
+            DEFAULT_DIR_MODE
+        } else {
+            DEFAULT_FILE_MODE
+        };
+        self.resolve_with_default(key, is_dir, perm)
+    }
+
+    /// 同 `resolve`，但没有规则设置权限位时使用 `default_perm`（如由对象 ACL 推导的值）
+    pub fn resolve_with_default(&self, key: &str, is_dir: bool, default_perm: u16) -> Ownership {
+        let default = Ownership {
+            uid: DEFAULT_UID,
+            gid: DEFAULT_GID,
+            perm: default_perm,
+        };
+        self.resolve_from(key, is_dir, default)
+    }
+
+    /// 同 `resolve`，但规则没有设置的字段使用 `default`（如对象元数据中记录的属主）
+    pub fn resolve_from(&self, key: &str, is_dir: bool, default: Ownership) -> Ownership {
+        let Ownership {
+            mut uid,
+            mut gid,
+            mut perm,
+        } = default;
+
+        for rule in self.rules.iter().filter(|r| key.starts_with(&r.prefix)) {
+            uid = rule.uid.unwrap_or(uid);
+            gid = rule.gid.unwrap_or(gid);
+            let mode = if is_dir {
+                rule.effective_dir_mode()
+            } else {
+                rule.mode
+            };
+            perm = mode.unwrap_or(perm);
+        }
+
+        Ownership { uid, gid, perm }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_prefix_wins_per_field() {
+        let config = ConfigFile::parse(
+            r#"
+[perm."datasets/"] gid=300 mode=0640
+[perm."datasets/raw/"] uid=1000 mode=0444
+"#,
+        )
+        .unwrap();
+        let overrides = PermissionOverrides::from_config(&config).unwrap();
+
+        assert_eq!(
+            overrides.resolve("datasets/raw/a.csv", false),
+            Ownership {
+                uid: 1000,
+                gid: 300,
+                perm: 0o444
+            }
+        );
+        assert_eq!(
+            overrides.resolve("datasets/raw/", true),
+            Ownership {
+                uid: 1000,
+                gid: 300,
+                perm: 0o555
+            }
+        );
+        assert_eq!(
+            overrides.resolve("datasets/b.csv", false),
+            Ownership {
+                uid: DEFAULT_UID,
+                gid: 300,
+                perm: 0o640
+            }
+        );
+        assert_eq!(
+            overrides.resolve("models/m.bin", false),
+            Ownership {
+                uid: DEFAULT_UID,
+                gid: DEFAULT_GID,
+                perm: DEFAULT_FILE_MODE
+            }
+        );
+    }
+
+    #[test]
+    fn test_rule_mode_overrides_default_perm() {
+        let config = ConfigFile::parse("[perm.\"private/\"] mode=0600").unwrap();
+        let overrides = PermissionOverrides::from_config(&config).unwrap();
+
+        assert_eq!(
+            overrides
+                .resolve_with_default("public/a", false, 0o666)
+                .perm,
+            0o666
+        );
+        assert_eq!(
+            overrides
+                .resolve_with_default("private/a", false, 0o666)
+                .perm,
+            0o600
+        );
+    }
+
+    #[test]
+    fn test_permits_by_class() {
+        let owner = Ownership {
+            uid: 1000,
+            gid: 100,
+            perm: 0o640,
+        };
+        let (r, w, x) = (libc::R_OK, libc::W_OK, libc::X_OK);
+        assert!(owner.permits(1000, 1, r | w, false));
+        assert!(!owner.permits(1000, 1, x, false));
+        assert!(owner.permits(2000, 100, r, false));
+        assert!(!owner.permits(2000, 100, w, false));
+        assert!(!owner.permits(2000, 1, r, false));
+        assert!(owner.permits(2000, 1, libc::F_OK, false));
+
+        // root 可以读写，但执行需要有执行位
+        assert!(owner.permits(0, 0, r | w, false));
+        assert!(!owner.permits(0, 0, x, false));
+        assert!(owner.permits(0, 0, x, true));
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        for text in [
+            "[perm] uid=1",
+            "[perm.\"a/\"] uid=-1",
+            "[perm.\"a/\"] mode=0999",
+            "[perm.\"a/\"] owner=1",
+            "[perm.\"a/\"] uid=1\n[perm.\"a/\"] gid=1",
+        ] {
+            let config = ConfigFile::parse(text).unwrap();
+            assert!(
+                PermissionOverrides::from_config(&config).is_err(),
+                "{}",
+                text
+            );
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::cache::MetadataLookup;
+use crate::cos_client::ObjectMeta;
+use crate::downloader::Downloader;
+use crate::limiter::background;
+use crate::manifest::Manifest;
+use crate::object_store::ObjectStore;
+
+/// 挂载根目录下的预取清单对象键
+pub const PREFETCH_MANIFEST_KEY: &str = ".cosfs-prefetch";
+
+/// 预取的并发下载数
+const PREFETCH_CONCURRENCY: usize = 4;
+
+/// 预取进度统计
+#[derive(Debug, Default)]
+pub struct PrefetchStats {
+    pub total: AtomicU64,
+    pub completed: AtomicU64,
+    pub failed: AtomicU64,
+    pub bytes: AtomicU64,
+}
+
+impl PrefetchStats {
+    pub fn summary(&self) -> String {
+        format!(
+            "{}/{} objects prefetched ({} failed, {} bytes)",
+            self.completed.load(Ordering::Relaxed),
+            self.total.load(Ordering::Relaxed),
+            self.failed.load(Ordering::Relaxed),
+            self.bytes.load(Ordering::Relaxed)
+        )
+    }
+}
+
+/// 解析预取清单：每行一个键或 glob，忽略空行和 `#` 注释；
+/// 也接受 `ls --format json` 导出的对象清单，预取其中列出的所有对象
+pub fn parse_manifest(content: &str) -> Vec<String> {
+    if let Ok(manifest) = Manifest::parse(content) {
+        return manifest
+            .objects
+            .into_iter()
+            .map(|entry| entry.key)
+            .collect();
+    }
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.trim_start_matches('/').to_string())
+        .collect()
+}
+
+/// glob 匹配：`*` 匹配单级路径内任意字符，`**` 跨越目录，`?` 匹配单个字符
+pub fn glob_match(pattern: &str, key: &str) -> bool {
+    fn matches(p: &[u8], k: &[u8]) -> bool {
+        match p.first() {
+            None => k.is_empty(),
+            Some(b'*') if p.get(1) == Some(&b'*') => {
+                let rest = p[2..].strip_prefix(b"/").unwrap_or(&p[2..]);
+                (0..=k.len()).any(|i| matches(rest, &k[i..]))
+            }
+            Some(b'*') => {
+                let rest = &p[1..];
+                for i in 0..=k.len() {
+                    if matches(rest, &k[i..]) {
+                        return true;
+                    }
+                    if k.get(i) == Some(&b'/') {
+                        break;
+                    }
+                }
+                false
+            }
+            Some(b'?') => k.first().is_some_and(|&c| c != b'/') && matches(&p[1..], &k[1..]),
+            Some(&c) => k.first() == Some(&c) && matches(&p[1..], &k[1..]),
+        }
+    }
+    matches(pattern.as_bytes(), key.as_bytes())
+}
+
+/// 把对象下载到内容缓存，调用方通常在后台任务中运行；请求以后台优先级发出
+pub async fn run_prefetch<S: ObjectStore>(
+    downloader: Arc<Downloader<S>>,
+    keys: Vec<String>,
+    stats: Arc<PrefetchStats>,
+) {
+    stats.total.store(keys.len() as u64, Ordering::Relaxed);
+
+    info!("Prefetching {} objects into the cache", keys.len());
+    let permits = Arc::new(Semaphore::new(PREFETCH_CONCURRENCY));
+    let mut tasks = JoinSet::new();
+
+    for key in keys {
+        let downloader = Arc::clone(&downloader);
+        let stats = Arc::clone(&stats);
+        let permits = Arc::clone(&permits);
+        tasks.spawn(background(async move {
+            let Ok(_permit) = permits.acquire_owned().await else {
+                return;
+            };
+            match prefetch_object(&downloader, &key).await {
+                Ok(size) => {
+                    stats.completed.fetch_add(1, Ordering::Relaxed);
+                    stats.bytes.fetch_add(size, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    warn!("Failed to prefetch {}: {:#}", key, e);
+                    stats.failed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }));
+    }
+
+    while tasks.join_next().await.is_some() {}
+    info!("Prefetch finished: {}", stats.summary());
+}
+
+/// 下载单个对象：小对象整体缓存，大对象按块写入部分缓存。返回对象大小
+async fn prefetch_object<S: ObjectStore>(downloader: &Downloader<S>, key: &str) -> Result<u64> {
+    let cache = downloader.cache();
+    let meta: ObjectMeta = match cache.lookup_metadata(key) {
+        MetadataLookup::Fresh(meta) | MetadataLookup::Stale(meta) => meta,
+        MetadataLookup::Miss => {
+            let meta = downloader.store().head_object(key).await?;
+            cache.set_metadata(key.to_string(), meta.clone());
+            meta
+        }
+    };
+
+    if !cache.options().should_cache_content(meta.size) {
+        debug!("Skipping prefetch of {} ({} bytes)", key, meta.size);
+        return Ok(0);
+    }
+
+    let block_size = cache.options().block_size;
+    if meta.size <= block_size {
+        if !cache.is_content_cached(key, &meta.etag, meta.size) {
+            downloader.fetch_object(key, &meta.etag, meta.size).await?;
+        }
+        return Ok(meta.size);
+    }
+
+    for (start, end) in cache.missing_ranges(key, &meta.etag, 0, meta.size) {
+        let mut offset = start;
+        while offset < end {
+            let len = block_size.min(end - offset);
+            if !downloader
+                .fetch_range(key, &meta.etag, offset, offset + len)
+                .await?
+            {
+                return Err(anyhow!("cache disk is full"));
+            }
+            offset += len;
+        }
+    }
+    Ok(meta.size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = "# models\n/models/base.bin\n\ndatasets/**/*.parquet\n";
+        assert_eq!(
+            parse_manifest(manifest),
+            vec!["models/base.bin", "datasets/**/*.parquet"]
+        );
+
+        let manifest = r#"{"bucket": "b", "objects": [
+            {"key": "models/base.bin", "size": 1, "etag": "\"0123\"", "last_modified": "2019-05-24T10:56:40.000Z"}
+        ]}"#;
+        assert_eq!(parse_manifest(manifest), vec!["models/base.bin"]);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("models/base.bin", "models/base.bin"));
+        assert!(glob_match("data/*.txt", "data/file1.txt"));
+        assert!(!glob_match("data/*.txt", "data/subdir/file3.txt"));
+        assert!(glob_match("data/**/*.txt", "data/subdir/file3.txt"));
+        assert!(glob_match("data/**/*.txt", "data/file1.txt"));
+        assert!(glob_match("**", "anything/at/all"));
+        assert!(glob_match("file?.jpg", "file2.jpg"));
+        assert!(!glob_match("file?.jpg", "file10.jpg"));
+    }
+}
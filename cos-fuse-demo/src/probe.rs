@@ -0,0 +1,188 @@
+use anyhow::{bail, Result};
+use bytes::Bytes;
+use log::{info, warn};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cos_client::error_status;
+use crate::object_store::ObjectStore;
+
+/// 探测写权限时上传（随后删除）的空对象的键前缀
+const PROBE_KEY_PREFIX: &str = ".cosfs-probe-";
+
+/// 挂载时探测到的对桶的访问能力
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// 可以读取对象；桶为空时无法确认，按可读处理
+    pub read: bool,
+    /// 可以上传并删除对象；没有探测时为 false
+    pub write: bool,
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names = vec!["list"];
+        if self.read {
+            names.push("read");
+        }
+        if self.write {
+            names.push("write");
+        }
+        write!(f, "{}", names.join(", "))
+    }
+}
+
+/// 依次探测桶访问（HEAD Bucket）、列表（`max-keys=1`）、读取（HEAD 第一个对象）
+/// 以及可选的写入（上传并删除一个空对象）。
+///
+/// 桶不存在或不能列出对象时返回错误，说明缺少的权限；读写权限缺失只记录警告并降级
+pub async fn probe<S: ObjectStore>(client: &S, write: bool) -> Result<Capabilities> {
+    match client.head_bucket().await {
+        Ok(()) => {}
+        Err(e) if error_status(&e) == Some(StatusCode::NOT_FOUND) => bail!(
+            "Bucket {} does not exist in region {}",
+            client.bucket(),
+            client.region()
+        ),
+        // 只授予了 GetBucket 的策略也能正常挂载，继续看列表权限
+        Err(e) if is_denied(&e) => warn!("Missing permission cos:HeadBucket ({})", e),
+        Err(e) => return Err(e.context("Failed to access bucket")),
+    }
+
+    let first_key = match client.list_first_key().await {
+        Ok(key) => key,
+        Err(e) if is_denied(&e) => bail!(
+            "Missing permission cos:GetBucket, cannot list objects ({})",
+            e
+        ),
+        Err(e) => return Err(e.context("Failed to list objects")),
+    };
+
+    let mut capabilities = Capabilities {
+        read: true,
+        write: false,
+    };
+    if let Some(key) = first_key {
+        match client.head_object(&key).await {
+            Ok(_) => {}
+            Err(e) if is_denied(&e) => {
+                warn!(
+                    "Missing permission cos:HeadObject/cos:GetObject, opening files will fail with EACCES ({})",
+                    e
+                );
+                capabilities.read = false;
+            }
+            Err(e) => warn!("Failed to probe read access with {}: {:#}", key, e),
+        }
+    }
+
+    if write {
+        capabilities.write = probe_write(client).await;
+    }
+    info!("Bucket capabilities: {}", capabilities);
+    Ok(capabilities)
+}
+
+/// 上传并删除一个空对象；任一步失败都按只读处理
+async fn probe_write<S: ObjectStore>(client: &S) -> bool {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let key = format!("{}{}-{}", PROBE_KEY_PREFIX, std::process::id(), nanos);
+
+    if let Err(e) = client
+        .put_object(&key, Bytes::new(), HeaderMap::new())
+        .await
+    {
+        if is_denied(&e) {
+            warn!(
+                "Missing permission cos:PutObject, mounting read-only ({})",
+                e
+            );
+        } else {
+            warn!("Failed to probe write access, mounting read-only: {:#}", e);
+        }
+        return false;
+    }
+    if let Err(e) = client.delete_object(&key).await {
+        if is_denied(&e) {
+            warn!(
+                "Missing permission cos:DeleteObject, mounting read-only; probe object {} was left behind ({})",
+                key, e
+            );
+        } else {
+            warn!(
+                "Failed to delete probe object {}, mounting read-only: {:#}",
+                key, e
+            );
+        }
+        return false;
+    }
+    true
+}
+
+/// 是否是权限不足
+fn is_denied(error: &anyhow::Error) -> bool {
+    matches!(
+        error_status(error),
+        Some(StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cos_client::StatusError;
+    use crate::mock_backend::{Fault, MockBackend, Op};
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_capabilities_display() {
+        let capabilities = Capabilities {
+            read: false,
+            write: false,
+        };
+        assert_eq!(capabilities.to_string(), "list");
+        let capabilities = Capabilities {
+            read: true,
+            write: true,
+        };
+        assert_eq!(capabilities.to_string(), "list, read, write");
+    }
+
+    #[test]
+    fn test_is_denied() {
+        assert!(is_denied(
+            &StatusError::new("LIST", StatusCode::FORBIDDEN).into()
+        ));
+        assert!(!is_denied(
+            &StatusError::new("LIST", StatusCode::NOT_FOUND).into()
+        ));
+        assert!(!is_denied(&anyhow!("connection refused")));
+        // 加了上下文的错误仍能识别
+        let error = anyhow::Error::from(StatusError::new("HEAD", StatusCode::UNAUTHORIZED))
+            .context("probe");
+        assert!(is_denied(&error));
+    }
+
+    #[tokio::test]
+    async fn test_probe_with_faults() {
+        let mock = MockBackend::new("mock");
+        mock.insert("a.txt", "a");
+        let capabilities = probe(&mock, true).await.unwrap();
+        assert!(capabilities.read && capabilities.write);
+        assert_eq!(mock.object("a.txt").unwrap(), "a");
+
+        mock.inject_for(Op::Head, "a.txt", Fault::Status(StatusCode::FORBIDDEN), 1);
+        mock.inject(Op::Put, Fault::Status(StatusCode::FORBIDDEN), 1);
+        let capabilities = probe(&mock, true).await.unwrap();
+        assert!(!capabilities.read && !capabilities.write);
+
+        // 列表失败时无法挂载
+        mock.inject(Op::List, Fault::SlowDown, 1);
+        assert!(probe(&mock, false).await.is_err());
+    }
+}
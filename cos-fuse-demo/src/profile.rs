@@ -0,0 +1,148 @@
+use anyhow::{anyhow, bail, Result};
+use std::env;
+use std::path::PathBuf;
+
+use crate::backend::Provider;
+use crate::config_file::ConfigFile;
+use crate::signer::Credentials;
+
+/// 配置文件中命名配置节的名字：`[profile."prod"]`
+pub const PROFILE_SECTION: &str = "profile";
+
+/// 指定了 `--profile` 但没有 `--config` 时读取的配置文件：`$HOME/.config/cosfs/config`
+pub fn default_config_path() -> Option<PathBuf> {
+    let home = env::var_os("HOME").filter(|home| !home.is_empty())?;
+    Some(PathBuf::from(home).join(".config/cosfs/config"))
+}
+
+/// 一组命名的挂载默认值，命令行上显式指定的参数优先
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    pub provider: Option<Provider>,
+    pub bucket: Option<String>,
+    pub region: Option<String>,
+    /// 访问域名（不含 bucket），如 `cos.accelerate.myqcloud.com`
+    pub endpoint: Option<String>,
+    pub cache_dir: Option<String>,
+    pub credentials: Option<Credentials>,
+}
+
+impl Profile {
+    /// 读取 `[profile."<name>"]` 节
+    pub fn from_config(config: &ConfigFile, name: &str) -> Result<Self> {
+        let mut sections = config
+            .sections(PROFILE_SECTION)
+            .filter(|section| section.name.get(1).map(String::as_str) == Some(name));
+        let section = sections
+            .next()
+            .ok_or_else(|| anyhow!("profile '{}' not found", name))?;
+        if section.name.len() != 2 {
+            bail!(
+                "line {}: expected [{}.\"name\"], got [{}]",
+                section.line,
+                PROFILE_SECTION,
+                section.name.join(".")
+            );
+        }
+        if let Some(duplicate) = sections.next() {
+            bail!("line {}: duplicate profile '{}'", duplicate.line, name);
+        }
+
+        let mut profile = Profile::default();
+        let (mut secret_id, mut secret_key, mut session_token) = (None, None, None);
+        for (key, raw) in &section.entries {
+            let value = Some(raw.clone());
+            match key.as_str() {
+                "provider" => {
+                    let provider = raw
+                        .parse()
+                        .map_err(|e| anyhow!("line {}: {}", section.line, e))?;
+                    profile.provider = Some(provider);
+                }
+                "bucket" => profile.bucket = value,
+                "region" => profile.region = value,
+                "endpoint" => profile.endpoint = value,
+                "cache_dir" => profile.cache_dir = value,
+                "secret_id" => secret_id = value,
+                "secret_key" => secret_key = value,
+                "session_token" => session_token = value,
+                _ => bail!(
+                    "line {}: unknown key '{}' (expected provider, bucket, region, endpoint, \
+                     cache_dir, secret_id, secret_key or session_token)",
+                    section.line,
+                    key
+                ),
+            }
+        }
+
+        profile.credentials = match (secret_id, secret_key) {
+            (Some(secret_id), Some(secret_key)) => Some(Credentials {
+                secret_id,
+                secret_key,
+                session_token,
+            }),
+            (None, None) if session_token.is_none() => None,
+            _ => bail!(
+                "line {}: secret_id and secret_key must be set together",
+                section.line
+            ),
+        };
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[profile."prod"]
+bucket = "prod-1250000000"
+region = "ap-shanghai"
+cache_dir = "/var/cache/cosfs/prod"
+secret_id = "AKIDprod"
+secret_key = "key"
+
+[profile."dev"] bucket=dev-1250000000 region=ap-beijing endpoint=cos.accelerate.myqcloud.com
+
+[profile."minio"] provider=s3 bucket=data endpoint=http://minio:9000
+"#;
+
+    #[test]
+    fn test_select_profile() {
+        let config = ConfigFile::parse(CONFIG).unwrap();
+
+        let prod = Profile::from_config(&config, "prod").unwrap();
+        assert_eq!(prod.bucket.as_deref(), Some("prod-1250000000"));
+        assert_eq!(prod.cache_dir.as_deref(), Some("/var/cache/cosfs/prod"));
+        let credentials = prod.credentials.unwrap();
+        assert_eq!(credentials.secret_id, "AKIDprod");
+        assert_eq!(credentials.session_token, None);
+
+        let dev = Profile::from_config(&config, "dev").unwrap();
+        assert_eq!(dev.region.as_deref(), Some("ap-beijing"));
+        assert_eq!(dev.endpoint.as_deref(), Some("cos.accelerate.myqcloud.com"));
+        assert!(dev.credentials.is_none());
+        assert_eq!(dev.provider, None);
+
+        let minio = Profile::from_config(&config, "minio").unwrap();
+        assert_eq!(minio.provider, Some(Provider::S3));
+        assert_eq!(minio.region, None);
+
+        assert!(Profile::from_config(&config, "staging").is_err());
+    }
+
+    #[test]
+    fn test_invalid_profiles_are_rejected() {
+        for text in [
+            "[profile.\"a\"] bucket_name=x",
+            "[profile.\"a\"] secret_id=x",
+            "[profile.\"a\"] provider=gcs",
+            "[profile.\"a\"] bucket=x\n[profile.\"a\"] region=y",
+            "[profile.\"a\".\"b\"] bucket=x",
+        ] {
+            let config = ConfigFile::parse(text).unwrap();
+            assert!(Profile::from_config(&config, "a").is_err(), "{}", text);
+        }
+    }
+}
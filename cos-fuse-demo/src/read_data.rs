@@ -0,0 +1,168 @@
+use bytes::Bytes;
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::os::unix::io::AsRawFd;
+use std::ptr::NonNull;
+
+use crate::buffer_pool::PooledBuf;
+
+/// 不小于这个长度的已缓存区间映射到内存答复，更短的区间复制到池中的缓冲区更便宜
+pub const MIN_MAPPED_LEN: u64 = 128 << 10;
+
+/// 读请求答复的内容。fuser 以一次 writev 把答复头和这里的内容一起写入 `/dev/fuse`，
+/// 能直接引用的内容（映射的缓存文件、内存中的对象）不在用户态再复制一次
+#[derive(Debug)]
+pub enum ReadData {
+    /// 从缓冲区池取得（或已经分配好）的缓冲区
+    Buffer(PooledBuf),
+    /// 映射到内存的缓存文件区间，内核直接从页缓存复制到 `/dev/fuse`
+    Mapped(MappedRange),
+    /// 内存中对象内容的一段，与对象内容共享
+    Shared(Bytes),
+}
+
+impl ReadData {
+    pub fn empty() -> Self {
+        Self::Shared(Bytes::new())
+    }
+
+    /// 转成独立的 Vec（归档读取等需要拥有内容的调用方）
+    pub fn into_vec(self) -> Vec<u8> {
+        match self {
+            Self::Buffer(buf) => buf.into_vec(),
+            other => other.to_vec(),
+        }
+    }
+}
+
+impl From<PooledBuf> for ReadData {
+    fn from(buf: PooledBuf) -> Self {
+        Self::Buffer(buf)
+    }
+}
+
+impl From<Vec<u8>> for ReadData {
+    fn from(buf: Vec<u8>) -> Self {
+        Self::Buffer(PooledBuf::unpooled(buf))
+    }
+}
+
+impl Deref for ReadData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Buffer(buf) => buf,
+            Self::Mapped(range) => range,
+            Self::Shared(bytes) => bytes,
+        }
+    }
+}
+
+/// 只读映射的文件区间，丢弃时解除映射。映射建立后文件被删除（缓存淘汰）不影响已映射的内容；
+/// 缓存文件只追加写入未缓存的区间、从不原地截断（对象变化时删除后重建），已缓存的区间在映射期间
+/// 不会变化。重建的文件可能比记录的区间短，调用方需在映射前按打开的文件检查长度
+#[derive(Debug)]
+pub struct MappedRange {
+    /// 映射起点（按页对齐）
+    map: NonNull<libc::c_void>,
+    map_len: usize,
+    /// 区间在映射中的偏移与长度
+    start: usize,
+    len: usize,
+}
+
+// SAFETY: 映射只读，且只在 drop 时解除，可以在线程间移动和共享
+unsafe impl Send for MappedRange {}
+unsafe impl Sync for MappedRange {}
+
+impl MappedRange {
+    /// 映射 `file` 的 [offset, offset + len)，`len` 必须大于 0 且区间在文件范围内
+    pub fn map(file: &File, offset: u64, len: usize) -> io::Result<Self> {
+        if len == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty mapping"));
+        }
+        // SAFETY: sysconf 没有前置条件
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+        let map_offset = offset / page * page;
+        let start = (offset - map_offset) as usize;
+        let map_len = start + len;
+
+        // 预先填充页表，答复时不再逐页缺页
+        #[cfg(target_os = "linux")]
+        let flags = libc::MAP_SHARED | libc::MAP_POPULATE;
+        #[cfg(not(target_os = "linux"))]
+        let flags = libc::MAP_SHARED;
+        // SAFETY: 映射新的只读区域，fd 在调用期间有效，偏移按页对齐
+        let map = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                map_len,
+                libc::PROT_READ,
+                flags,
+                file.as_raw_fd(),
+                map_offset as libc::off_t,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            map: NonNull::new(map).expect("mmap does not return null on success"),
+            map_len,
+            start,
+            len,
+        })
+    }
+}
+
+impl Deref for MappedRange {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: [start, start + len) 位于映射之内，映射在 self 存活期间有效且只读
+        unsafe {
+            std::slice::from_raw_parts(
+                self.map.as_ptr().cast::<u8>().add(self.start),
+                self.len,
+            )
+        }
+    }
+}
+
+impl Drop for MappedRange {
+    fn drop(&mut self) {
+        // SAFETY: 解除 map 时建立的同一个映射
+        unsafe {
+            libc::munmap(self.map.as_ptr(), self.map_len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_mapped_range() {
+        let mut file = tempfile::tempfile().unwrap();
+        let content: Vec<u8> = (0..20_000u32).map(|i| i as u8).collect();
+        file.write_all(&content).unwrap();
+
+        // 不按页对齐的偏移
+        let range = MappedRange::map(&file, 5_000, 10_000).unwrap();
+        assert_eq!(&range[..], &content[5_000..15_000]);
+
+        // 文件删除后映射仍然有效
+        drop(file);
+        let data = ReadData::Mapped(range);
+        assert_eq!(data.len(), 10_000);
+        assert_eq!(data.into_vec(), &content[5_000..15_000]);
+
+        let shared = ReadData::Shared(Bytes::from(content.clone()).slice(10..20));
+        assert_eq!(&shared[..], &content[10..20]);
+        assert!(MappedRange::map(&tempfile::tempfile().unwrap(), 0, 0).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+/// 默认的最小预读窗口
+pub const DEFAULT_READAHEAD_MIN: u64 = 1 << 20;
+
+/// 默认的最大预读窗口
+pub const DEFAULT_READAHEAD_MAX: u64 = 64 << 20;
+
+/// 文件句柄的访问模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessPattern {
+    /// 顺序读取，包括间隔不超过预读窗口的跳读（固定步长读取记录）
+    Sequential,
+    /// 从后往前读取（例如从文件末尾向前查找）
+    Backward,
+    Random,
+}
+
+/// 一个文件句柄的自适应预读状态。
+///
+/// 按相邻两次读取的位置判断访问模式：顺序或倒序时提前下载读取方向上的一个窗口，
+/// 读者越过窗口的一半时再下载下一个窗口，并把窗口加倍（直到上限）；随机读取时窗口减半、不预读，
+/// 在延迟和浪费的带宽之间取得平衡
+#[derive(Debug)]
+pub struct Readahead {
+    min: u64,
+    max: u64,
+    window: u64,
+    pattern: AccessPattern,
+    /// 上次读取的 [start, end)
+    last: Option<(u64, u64)>,
+    /// 预读到的边界：顺序时为已预读区间的终点，倒序时为起点；None 表示刚进入该模式
+    frontier: Option<u64>,
+}
+
+impl Readahead {
+    pub fn new(min: u64, max: u64) -> Self {
+        let min = min.clamp(1, max.max(1));
+        Self {
+            min,
+            max: max.max(min),
+            window: min,
+            pattern: AccessPattern::Random,
+            last: None,
+            frontier: None,
+        }
+    }
+
+    /// 当前的访问模式
+    pub fn pattern(&self) -> AccessPattern {
+        self.pattern
+    }
+
+    /// 当前的预读窗口
+    #[cfg(test)]
+    fn window(&self) -> u64 {
+        self.window
+    }
+
+    /// 记录一次 [offset, offset + size) 的读取，返回需要预读的 [start, stop)（可能超出文件末尾）
+    pub fn observe(&mut self, offset: u64, size: u64) -> Option<(u64, u64)> {
+        let end = offset.saturating_add(size);
+        let pattern = match self.last {
+            None if offset == 0 => AccessPattern::Sequential,
+            None => AccessPattern::Random,
+            Some((start, last_end)) => {
+                if offset >= start
+                    && offset <= last_end.saturating_add(self.window)
+                    && end > last_end
+                {
+                    AccessPattern::Sequential
+                } else if end <= start && start - end <= self.window {
+                    AccessPattern::Backward
+                } else {
+                    AccessPattern::Random
+                }
+            }
+        };
+        self.last = Some((offset, end));
+
+        if pattern != self.pattern {
+            self.pattern = pattern;
+            self.frontier = None;
+            if pattern != AccessPattern::Random {
+                self.window = self.min;
+            }
+        }
+
+        match pattern {
+            AccessPattern::Random => {
+                self.window = (self.window / 2).max(self.min);
+                None
+            }
+            AccessPattern::Sequential => {
+                let frontier = match self.frontier {
+                    None => end,
+                    // 已预读的部分还剩一半以上时不必再下载
+                    Some(frontier) if frontier.saturating_sub(end) > self.window / 2 => {
+                        return None;
+                    }
+                    Some(frontier) => {
+                        self.window = (self.window * 2).min(self.max);
+                        frontier.max(end)
+                    }
+                };
+                let stop = end.saturating_add(self.window);
+                self.frontier = Some(stop);
+                Some((frontier, stop))
+            }
+            AccessPattern::Backward => {
+                let frontier = match self.frontier {
+                    None => offset,
+                    Some(frontier) if offset.saturating_sub(frontier) > self.window / 2 => {
+                        return None;
+                    }
+                    Some(frontier) => {
+                        self.window = (self.window * 2).min(self.max);
+                        frontier.min(offset)
+                    }
+                };
+                let start = offset.saturating_sub(self.window);
+                self.frontier = Some(start);
+                (start < frontier).then_some((start, frontier))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KIB: u64 = 1 << 10;
+    const MIB: u64 = 1 << 20;
+
+    #[test]
+    fn test_readahead_window() {
+        // 顺序读取：先预读一个最小窗口，读过一半后窗口加倍，直到上限
+        let mut readahead = Readahead::new(MIB, 4 * MIB);
+        assert_eq!(
+            readahead.observe(0, 128 * KIB),
+            Some((128 * KIB, 128 * KIB + MIB))
+        );
+        assert_eq!(readahead.pattern(), AccessPattern::Sequential);
+        assert_eq!(readahead.observe(128 * KIB, 128 * KIB), None);
+        let mut offset = 256 * KIB;
+        let mut issued = Vec::new();
+        while offset < 16 * MIB {
+            if let Some(range) = readahead.observe(offset, 128 * KIB) {
+                issued.push(range);
+            }
+            offset += 128 * KIB;
+        }
+        assert_eq!(readahead.window(), 4 * MIB);
+        // 预读区间首尾相接，没有重复下载
+        assert!(issued.windows(2).all(|w| w[0].1 == w[1].0));
+        assert_eq!(issued[0], (128 * KIB + MIB, 640 * KIB + 2 * MIB));
+
+        // 固定步长的跳读仍按顺序预读
+        let mut readahead = Readahead::new(MIB, 4 * MIB);
+        assert!(readahead.observe(0, 4 * KIB).is_some());
+        readahead.observe(64 * KIB, 4 * KIB);
+        assert_eq!(readahead.pattern(), AccessPattern::Sequential);
+
+        // 随机读取不预读，窗口减半到下限
+        let mut readahead = Readahead::new(MIB, 64 * MIB);
+        assert_eq!(readahead.observe(100 * MIB, 4 * KIB), None);
+        assert_eq!(readahead.observe(10 * MIB, 4 * KIB), None);
+        assert_eq!(readahead.pattern(), AccessPattern::Random);
+        assert_eq!(readahead.window(), MIB);
+
+        // 从后往前读取时预读前面的窗口
+        let mut readahead = Readahead::new(MIB, 64 * MIB);
+        readahead.observe(10 * MIB, 64 * KIB);
+        assert_eq!(
+            readahead.observe(10 * MIB - 64 * KIB, 64 * KIB),
+            Some((9 * MIB - 64 * KIB, 10 * MIB - 64 * KIB))
+        );
+        assert_eq!(readahead.pattern(), AccessPattern::Backward);
+        assert_eq!(readahead.observe(0, 64 * KIB), None);
+    }
+}
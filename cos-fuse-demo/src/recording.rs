@@ -0,0 +1,370 @@
+use anyhow::{anyhow, bail, Context, Result};
+use bytes::Bytes;
+use log::{debug, info};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// 录制文件开头的标识与格式版本
+const MAGIC: &[u8] = b"COSFSREC1\n";
+
+/// 参与匹配的请求头：同一地址的不同区间、条件请求各自对应不同的响应
+const KEY_HEADERS: &[&str] = &["range", "if-match", "if-none-match"];
+
+/// 录制时响应体的保存方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BodyMode {
+    /// 完整保存
+    #[default]
+    Full,
+    /// 只保存前 N 字节，回放时其余部分以 0 填充
+    Truncate(usize),
+    /// 只保存长度与 MD5，回放时整个响应体以 0 填充
+    Hash,
+}
+
+impl FromStr for BodyMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Self::Full),
+            "hash" => Ok(Self::Hash),
+            _ => s
+                .strip_prefix("truncate=")
+                .and_then(|n| crate::config::parse_size(n).ok())
+                .map(|n| Self::Truncate(n as usize))
+                .ok_or_else(|| {
+                    format!(
+                        "invalid body mode '{}' (expected full, hash or truncate=SIZE)",
+                        s
+                    )
+                }),
+        }
+    }
+}
+
+impl fmt::Display for BodyMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => f.write_str("full"),
+            Self::Truncate(n) => write!(f, "truncate={}", n),
+            Self::Hash => f.write_str("hash"),
+        }
+    }
+}
+
+/// 一次请求与响应。文件中每条记录为 4 字节长度（小端）加 JSON 头，再加 8 字节长度加保存的响应体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Exchange {
+    method: String,
+    /// 路径与查询参数，不含域名
+    path: String,
+    /// 参与匹配的请求头
+    request_headers: Vec<(String, String)>,
+    status: u16,
+    headers: Vec<(String, String)>,
+    /// 原始响应体长度
+    body_len: u64,
+    /// 响应体保存方式为 hash 时的 MD5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_md5: Option<String>,
+}
+
+impl Exchange {
+    fn key(&self) -> String {
+        request_key(&self.method, &self.path, &self.request_headers)
+    }
+}
+
+fn request_key(method: &str, path: &str, headers: &[(String, String)]) -> String {
+    let mut key = format!("{} {}", method, path);
+    for (name, value) in headers {
+        key.push_str(&format!(" {}={}", name, value));
+    }
+    key
+}
+
+/// 后端请求的录制或回放。录制把每次请求与完整响应追加到文件；回放从文件中按请求取出响应，
+/// 完全不访问网络，用于把问题报告中的会话原样重现、整理成回归测试
+pub struct Recording {
+    mode: Mode,
+}
+
+enum Mode {
+    Record {
+        writer: Mutex<BufWriter<File>>,
+        bodies: BodyMode,
+    },
+    Replay {
+        /// 同一请求的多次响应按录制顺序返回，用完后一直返回最后一次
+        exchanges: Mutex<HashMap<String, VecDeque<(Exchange, Bytes)>>>,
+    },
+}
+
+impl fmt::Debug for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.mode {
+            Mode::Record { bodies, .. } => write!(f, "Recording(record, bodies={})", bodies),
+            Mode::Replay { .. } => f.write_str("Recording(replay)"),
+        }
+    }
+}
+
+impl Recording {
+    /// 新建录制文件，已存在时覆盖
+    pub fn record(path: &Path, bodies: BodyMode) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create recording {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(MAGIC)?;
+        writer.flush()?;
+        info!(
+            "Recording backend requests to {} (bodies: {})",
+            path.display(),
+            bodies
+        );
+        Ok(Self {
+            mode: Mode::Record {
+                writer: Mutex::new(writer),
+                bodies,
+            },
+        })
+    }
+
+    /// 读取录制文件用于回放
+    pub fn replay(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open recording {}", path.display()))?;
+        let mut reader = BufReader::new(file);
+        let mut magic = vec![0u8; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            bail!("{} is not a cosfs recording", path.display());
+        }
+
+        let mut exchanges: HashMap<String, VecDeque<(Exchange, Bytes)>> = HashMap::new();
+        let mut count = 0;
+        while let Some((exchange, body)) = read_exchange(&mut reader)
+            .with_context(|| format!("Corrupt recording {}", path.display()))?
+        {
+            exchanges
+                .entry(exchange.key())
+                .or_default()
+                .push_back((exchange, body));
+            count += 1;
+        }
+        info!(
+            "Replaying {} backend responses from {}",
+            count,
+            path.display()
+        );
+        Ok(Self {
+            mode: Mode::Replay {
+                exchanges: Mutex::new(exchanges),
+            },
+        })
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(self.mode, Mode::Replay { .. })
+    }
+
+    /// 发送请求：录制时读完整个响应体并记下，回放时取出录制的响应
+    pub async fn send(
+        &self,
+        client: &reqwest::Client,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response> {
+        let method = request.method().to_string();
+        let url = request.url();
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        let request_headers: Vec<(String, String)> = KEY_HEADERS
+            .iter()
+            .filter_map(|name| {
+                let value = request.headers().get(*name)?.to_str().ok()?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect();
+
+        match &self.mode {
+            Mode::Record { writer, bodies } => {
+                let response = client.execute(request).await?;
+                let status = response.status();
+                let headers = response.headers().clone();
+                let body = response.bytes().await?;
+                let exchange = Exchange {
+                    method,
+                    path,
+                    request_headers,
+                    status: status.as_u16(),
+                    headers: headers
+                        .iter()
+                        .filter_map(|(name, value)| {
+                            Some((name.to_string(), value.to_str().ok()?.to_string()))
+                        })
+                        .collect(),
+                    body_len: body.len() as u64,
+                    body_md5: (*bodies == BodyMode::Hash)
+                        .then(|| format!("{:x}", md5::compute(&body))),
+                };
+                let saved = match bodies {
+                    BodyMode::Full => &body[..],
+                    BodyMode::Truncate(n) => &body[..body.len().min(*n)],
+                    BodyMode::Hash => &[],
+                };
+                {
+                    let mut writer = writer.lock().unwrap();
+                    write_exchange(&mut *writer, &exchange, saved)?;
+                    writer.flush()?;
+                }
+                build_response(status.as_u16(), &headers, body)
+            }
+            Mode::Replay { exchanges } => {
+                let key = request_key(&method, &path, &request_headers);
+                let (exchange, saved) = {
+                    let mut exchanges = exchanges.lock().unwrap();
+                    let queue = exchanges
+                        .get_mut(&key)
+                        .ok_or_else(|| anyhow!("No recorded response for {}", key))?;
+                    if queue.len() > 1 {
+                        queue.pop_front().unwrap()
+                    } else {
+                        queue.front().unwrap().clone()
+                    }
+                };
+                debug!("Replaying {} -> {}", key, exchange.status);
+                let mut headers = HeaderMap::new();
+                for (name, value) in &exchange.headers {
+                    headers.append(
+                        HeaderName::from_bytes(name.as_bytes())?,
+                        HeaderValue::from_str(value)?,
+                    );
+                }
+                // 截断或只记了摘要的响应体以 0 补足原始长度，大小与 Content-Length 保持一致
+                let body = if saved.len() as u64 == exchange.body_len {
+                    saved
+                } else {
+                    let mut body = saved.to_vec();
+                    body.resize(exchange.body_len as usize, 0);
+                    Bytes::from(body)
+                };
+                build_response(exchange.status, &headers, body)
+            }
+        }
+    }
+}
+
+fn build_response(status: u16, headers: &HeaderMap, body: Bytes) -> Result<reqwest::Response> {
+    let mut response = http::Response::builder().status(status);
+    for (name, value) in headers {
+        response = response.header(name, value);
+    }
+    Ok(reqwest::Response::from(response.body(body)?))
+}
+
+fn write_exchange(writer: &mut impl Write, exchange: &Exchange, body: &[u8]) -> Result<()> {
+    let header = serde_json::to_vec(exchange)?;
+    writer.write_all(&(header.len() as u32).to_le_bytes())?;
+    writer.write_all(&header)?;
+    writer.write_all(&(body.len() as u64).to_le_bytes())?;
+    writer.write_all(body)?;
+    Ok(())
+}
+
+/// 读取下一条记录，文件结束时返回 None
+fn read_exchange(reader: &mut impl Read) -> Result<Option<(Exchange, Bytes)>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut header = vec![0u8; u32::from_le_bytes(len) as usize];
+    reader.read_exact(&mut header)?;
+    let exchange: Exchange = serde_json::from_slice(&header)?;
+
+    let mut len = [0u8; 8];
+    reader.read_exact(&mut len)?;
+    let mut body = vec![0u8; u64::from_le_bytes(len) as usize];
+    reader.read_exact(&mut body)?;
+    Ok(Some((exchange, Bytes::from(body))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::RANGE;
+
+    #[test]
+    fn test_body_mode_parsing() {
+        assert_eq!("full".parse::<BodyMode>().unwrap(), BodyMode::Full);
+        assert_eq!("hash".parse::<BodyMode>().unwrap(), BodyMode::Hash);
+        assert_eq!(
+            "truncate=4K".parse::<BodyMode>().unwrap(),
+            BodyMode::Truncate(4096)
+        );
+        assert!("truncate".parse::<BodyMode>().is_err());
+        assert_eq!(BodyMode::Truncate(10).to_string(), "truncate=10");
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.bin");
+        let exchange = |status: u16, body_len: u64| Exchange {
+            method: "GET".to_string(),
+            path: "/data/a.txt".to_string(),
+            request_headers: vec![("range".to_string(), "bytes=0-9".to_string())],
+            status,
+            headers: vec![("etag".to_string(), "\"abc\"".to_string())],
+            body_len,
+            body_md5: None,
+        };
+        let mut file = File::create(&path).unwrap();
+        file.write_all(MAGIC).unwrap();
+        write_exchange(&mut file, &exchange(206, 10), b"0123").unwrap();
+        write_exchange(&mut file, &exchange(404, 0), b"").unwrap();
+        drop(file);
+
+        let recording = Recording::replay(&path).unwrap();
+        assert!(recording.is_replay());
+        let client = reqwest::Client::new();
+        let request = || {
+            client
+                .get("https://bucket.cos.ap-beijing.myqcloud.com/data/a.txt")
+                .header(RANGE, "bytes=0-9")
+                .build()
+                .unwrap()
+        };
+
+        // 截断的响应体补足原始长度；同一请求依次返回，用完后重复最后一次
+        let response = recording.send(&client, request()).await.unwrap();
+        assert_eq!(response.status(), 206);
+        assert_eq!(response.headers()["etag"], "\"abc\"");
+        assert_eq!(&response.bytes().await.unwrap()[..], b"0123\0\0\0\0\0\0");
+        for _ in 0..2 {
+            let response = recording.send(&client, request()).await.unwrap();
+            assert_eq!(response.status(), 404);
+        }
+
+        // 没有录制的请求失败而不访问网络
+        let other = client
+            .get("https://bucket.cos.ap-beijing.myqcloud.com/data/b.txt")
+            .build()
+            .unwrap();
+        assert!(recording.send(&client, other).await.is_err());
+
+        File::create(&path).unwrap().write_all(b"garbage").unwrap();
+        assert!(Recording::replay(&path).is_err());
+    }
+}
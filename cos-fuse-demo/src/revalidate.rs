@@ -0,0 +1,69 @@
+use log::{debug, warn};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use crate::cache::Cache;
+use crate::cos_client::ObjectMeta;
+use crate::limiter::background;
+use crate::object_store::ObjectStore;
+
+/// 后台重新验证过期缓存（stale-while-revalidate）：
+/// 先返回过期的缓存结果，同时以后台优先级发出条件 GET 检查 ETag 并刷新缓存
+pub struct Revalidator<S> {
+    store: Arc<S>,
+    cache: Arc<Cache>,
+    /// 正在重新验证的键，避免对同一个键重复发起请求
+    pending: Arc<Mutex<HashSet<String>>>,
+}
+
+impl<S: ObjectStore> Revalidator<S> {
+    pub fn new(store: Arc<S>, cache: Arc<Cache>) -> Self {
+        Self {
+            store,
+            cache,
+            pending: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// 为过期的元数据安排一次后台重新验证（必须在 tokio 运行时中调用）
+    pub fn schedule(&self, stale: ObjectMeta) {
+        if !self.pending.lock().unwrap().insert(stale.key.clone()) {
+            return;
+        }
+
+        let store = Arc::clone(&self.store);
+        let cache = Arc::clone(&self.cache);
+        let pending = Arc::clone(&self.pending);
+
+        tokio::spawn(background(async move {
+            let key = stale.key.clone();
+            let block_size = cache.options().block_size;
+            debug!("Revalidating stale cache entry for key: {}", key);
+
+            match store
+                .get_object_if_changed(&key, &stale.etag, block_size)
+                .await
+            {
+                Ok(None) => {
+                    // 未变化：刷新元数据的缓存时间
+                    cache.set_metadata(key.clone(), stale);
+                }
+                Ok(Some((meta, body))) => {
+                    debug!("Object {} changed, refreshing cache", key);
+                    if let Some(body) = body {
+                        if cache.is_content_cached(&key, &stale.etag, stale.size) {
+                            if let Err(e) = cache.cache_content(&key, &meta.etag, &body) {
+                                warn!("Failed to refresh cached content for {}: {:#}", key, e);
+                            }
+                        }
+                    }
+                    // 大对象的部分缓存按 ETag 校验，新元数据写入后旧区间自动作废
+                    cache.set_metadata(key.clone(), meta);
+                }
+                Err(e) => warn!("Failed to revalidate {}: {:#}", key, e),
+            }
+
+            pending.lock().unwrap().remove(&key);
+        }));
+    }
+}
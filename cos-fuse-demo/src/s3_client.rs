@@ -0,0 +1,861 @@
+//! Amazon S3 及 MinIO 等 S3 兼容服务的客户端，以 SigV4 签名。
+//!
+//! 文件系统上传时使用 `x-cos-` 开头的头（自定义元数据、存储类型、加密方式），
+//! 发送前改写为对应的 `x-amz-` 头；响应中的 `x-amz-` 头也改写回来，
+//! 元数据解析、请求 ID 与错误处理与 COS 客户端共用
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use log::warn;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::cos_client::{
+    build_http_client, complete_multipart_body, meta_from_headers, normalize_prefix, not_found,
+    parse_list_page, parse_list_response, strip_key_prefix, ClientOptions, EtagResult,
+    InitiateMultipartUploadResult, ListPage, ObjectMeta, PartialDownload, StatusError,
+    MAX_RESUME_ATTEMPTS, PRESERVED_HEADERS, SSE_HEADER, SSE_KMS_KEY_ID_HEADER, TRACE_ID_HEADER,
+    USER_META_PREFIX,
+};
+use crate::limiter::{RequestClass, RequestLimiter};
+use crate::object_store::ObjectStore;
+use crate::recording::Recording;
+use crate::signer::{self, Credentials};
+use crate::sigv4::{self, SignedRequest, UNSIGNED_PAYLOAD};
+
+/// 没有指定地域时使用的地域，MinIO 默认也使用它
+pub const DEFAULT_REGION: &str = "us-east-1";
+
+/// 两边名字不是简单替换前缀的头：(COS 头, S3 头)
+const RENAMED_HEADERS: &[(&str, &str)] = &[
+    (
+        SSE_KMS_KEY_ID_HEADER,
+        "x-amz-server-side-encryption-aws-kms-key-id",
+    ),
+    (TRACE_ID_HEADER, "x-amz-id-2"),
+];
+
+/// SSE-KMS 加密方式在两边的写法
+const COS_KMS: &str = "cos/kms";
+const S3_KMS: &str = "aws:kms";
+
+#[derive(Debug)]
+pub struct S3Client {
+    bucket: String,
+    region: String,
+    /// 桶的地址：指定了访问地址时为路径形式（`http://minio:9000/bucket`），
+    /// 否则为 `https://<bucket>.s3.<region>.amazonaws.com`
+    base_url: String,
+    /// 对象键前缀，空或以 `/` 结尾
+    prefix: String,
+    client: reqwest::Client,
+    limiter: RequestLimiter,
+    credentials: Option<Credentials>,
+    /// 下载时要求的 ETag（快照挂载）
+    pinned_etags: Option<Arc<HashMap<String, String>>>,
+    /// 请求的录制或回放
+    recording: Option<Arc<Recording>>,
+}
+
+impl S3Client {
+    /// 创建客户端：`options.endpoint` 为 MinIO 等服务的地址（如 `http://minio:9000`，
+    /// 省略协议时为 https），未设置时访问 Amazon S3；地域为空时使用 [`DEFAULT_REGION`]
+    pub fn with_options(bucket: String, region: String, options: &ClientOptions) -> Result<Self> {
+        let region = if region.is_empty() {
+            DEFAULT_REGION.to_string()
+        } else {
+            region
+        };
+        let base_url = match &options.endpoint {
+            Some(endpoint) => {
+                let endpoint = endpoint.trim_end_matches('/');
+                if endpoint.contains("://") {
+                    format!("{}/{}", endpoint, bucket)
+                } else {
+                    format!("https://{}/{}", endpoint, bucket)
+                }
+            }
+            None => format!("https://{}.s3.{}.amazonaws.com", bucket, region),
+        };
+        url::Url::parse(&base_url)
+            .map_err(|e| anyhow!("Invalid S3 endpoint {}: {}", base_url, e))?;
+        let client = match &options.http_client {
+            Some(client) => client.clone(),
+            None => build_http_client(&options.extra_headers)?,
+        };
+
+        Ok(Self {
+            bucket,
+            region,
+            base_url,
+            prefix: normalize_prefix(&options.prefix),
+            client,
+            limiter: RequestLimiter::new(&options.limits),
+            credentials: options.credentials.clone(),
+            pinned_etags: options.pinned_etags.clone(),
+            recording: options.recording.clone(),
+        })
+    }
+
+    /// 改写 `x-cos-` 头后发送，有访问密钥时签名；响应中的 `x-amz-` 头改写为 `x-cos-` 头
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let mut request = request.build()?;
+        to_s3_headers(request.headers_mut());
+        if let Some(credentials) = &self.credentials {
+            self.sign(credentials, &mut request)?;
+        }
+        let mut response = match &self.recording {
+            Some(recording) => recording.send(&self.client, request).await?,
+            None => self.client.execute(request).await?,
+        };
+        from_s3_headers(response.headers_mut());
+        Ok(response)
+    }
+
+    /// 加上 `x-amz-date`、`x-amz-content-sha256` 与 `Authorization` 头（临时密钥另加
+    /// `x-amz-security-token`），签名覆盖 host、全部查询参数与全部 `x-amz-` 头；请求体不参与签名
+    fn sign(&self, credentials: &Credentials, request: &mut reqwest::Request) -> Result<()> {
+        let now = SystemTime::now();
+        let headers = request.headers_mut();
+        headers.insert("x-amz-date", HeaderValue::from_str(&sigv4::amz_date(now))?);
+        headers.insert(
+            "x-amz-content-sha256",
+            HeaderValue::from_static(UNSIGNED_PAYLOAD),
+        );
+        if let Some(token) = &credentials.session_token {
+            headers.insert("x-amz-security-token", HeaderValue::from_str(token)?);
+        }
+
+        let url = request.url();
+        let host = sigv4::host_header(url);
+        let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        let query: Vec<(&str, &str)> = query
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        let mut signed: Vec<(&str, &str)> = vec![("host", host.as_str())];
+        for (name, value) in request.headers() {
+            if name.as_str().starts_with("x-amz-") {
+                signed.push((name.as_str(), value.to_str()?));
+            }
+        }
+        let authorization = sigv4::authorization(
+            credentials,
+            &self.region,
+            &SignedRequest {
+                method: request.method().as_str(),
+                path: url.path(),
+                query: &query,
+                headers: &signed,
+                payload_hash: UNSIGNED_PAYLOAD,
+            },
+            now,
+        );
+        request.headers_mut().insert(
+            reqwest::header::AUTHORIZATION,
+            HeaderValue::from_str(&authorization)?,
+        );
+        Ok(())
+    }
+
+    /// 加上前缀的完整对象键
+    fn full_key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+
+    /// 请求对象使用的地址
+    fn key_url(&self, key: &str) -> String {
+        format!(
+            "{}/{}",
+            self.base_url,
+            signer::encode_path(&self.full_key(key))
+        )
+    }
+
+    /// 复制请求的来源：`/<bucket>/<key>`
+    fn copy_source(&self, key: &str) -> String {
+        format!(
+            "/{}/{}",
+            self.bucket,
+            signer::encode_path(&self.full_key(key))
+        )
+    }
+
+    /// 列表请求的地址，只列出挂载前缀加上 `sub_prefix` 之下的对象
+    fn list_url(&self, sub_prefix: &str, query: &str) -> String {
+        let mut params = Vec::new();
+        let prefix = self.full_key(sub_prefix);
+        if !prefix.is_empty() {
+            params.push(format!("prefix={}", signer::uri_encode(&prefix)));
+        }
+        if !query.is_empty() {
+            params.push(query.to_string());
+        }
+        if params.is_empty() {
+            format!("{}/", self.base_url)
+        } else {
+            format!("{}/?{}", self.base_url, params.join("&"))
+        }
+    }
+
+    /// 分块上传某个分块使用的地址
+    fn part_url(&self, key: &str, upload_id: &str, part_number: u32) -> String {
+        format!(
+            "{}?partNumber={}&uploadId={}",
+            self.key_url(key),
+            part_number,
+            signer::uri_encode(upload_id)
+        )
+    }
+
+    /// 可续传的 GET：响应体中途断开时用 Range 请求从断点继续（If-Match 保证续传的是同一版本的对象）
+    /// `if_match` 不为空时只读取该版本，对象已变化时以 412 失败
+    async fn get_resumable(
+        &self,
+        key: &str,
+        offset: u64,
+        len: Option<u64>,
+        if_match: Option<&str>,
+    ) -> Result<Bytes> {
+        let _permit = self.limiter.acquire(RequestClass::Data).await?;
+        let url = self.key_url(key);
+        let mut download = PartialDownload::new(offset, len);
+        let pinned = self.pinned_etags.as_ref().and_then(|etags| etags.get(key));
+        let mut etag: Option<String> = if_match.map(String::from).or_else(|| pinned.cloned());
+        let mut attempts = 0;
+
+        'request: loop {
+            let range = download.range();
+            let mut request = self.client.get(&url);
+            if let Some(range) = &range {
+                request = request.header(reqwest::header::RANGE, range.as_str());
+            }
+            if let Some(etag) = &etag {
+                request = request.header(reqwest::header::IF_MATCH, etag.as_str());
+            }
+            let mut response = self.send(request).await?;
+
+            if response.status() == 404 {
+                return Err(not_found("GET", &response, key));
+            }
+            if response.status() == StatusCode::PRECONDITION_FAILED {
+                if if_match.is_some() {
+                    return Err(StatusError::from_response("GET", &response).into());
+                }
+                if pinned.is_some() {
+                    return Err(anyhow!(
+                        "Object changed since the snapshot manifest: {}",
+                        key
+                    ));
+                }
+                return Err(anyhow!("Object changed while downloading: {}", key));
+            }
+            // 区间超出对象末尾
+            if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+                return Ok(download.finish());
+            }
+            if !response.status().is_success() {
+                return Err(StatusError::from_response("GET", &response).into());
+            }
+
+            if range.is_some() && response.status() != StatusCode::PARTIAL_CONTENT {
+                download.restart_from_zero();
+            }
+            if etag.is_none() {
+                etag = response
+                    .headers()
+                    .get(reqwest::header::ETAG)
+                    .and_then(|v| v.to_str().ok())
+                    .map(String::from);
+            }
+
+            loop {
+                match response.chunk().await {
+                    Ok(Some(chunk)) => {
+                        download.push(&chunk);
+                        if download.is_complete() {
+                            return Ok(download.finish());
+                        }
+                    }
+                    Ok(None) => return Ok(download.finish()),
+                    Err(e) if attempts < MAX_RESUME_ATTEMPTS => {
+                        attempts += 1;
+                        warn!(
+                            "Download of {} interrupted after {} bytes, resuming ({}/{}): {}",
+                            key,
+                            download.received(),
+                            attempts,
+                            MAX_RESUME_ATTEMPTS,
+                            e
+                        );
+                        continue 'request;
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+    }
+}
+
+impl ObjectStore for S3Client {
+    fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        self.key_url(key)
+    }
+
+    /// ListObjects（v1）：以 `marker` 续列，MinIO 与 S3 都支持
+    async fn list_page(
+        &self,
+        prefix: &str,
+        delimiter: bool,
+        marker: Option<&str>,
+    ) -> Result<ListPage> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let mut query = Vec::new();
+        if delimiter {
+            query.push("delimiter=%2F".to_string());
+        }
+        if let Some(marker) = marker {
+            query.push(format!("marker={}", signer::uri_encode(marker)));
+        }
+        let url = self.list_url(prefix, &query.join("&"));
+
+        let response = self.send(self.client.get(&url)).await?;
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("LIST", &response).into());
+        }
+
+        let body = response.text().await?;
+        let (objects, prefixes, next_marker) = parse_list_page(&body)?;
+        let prefixes = prefixes
+            .into_iter()
+            .filter_map(|p| {
+                p.strip_prefix(&self.prefix)
+                    .filter(|p| !p.is_empty())
+                    .map(str::to_string)
+            })
+            .collect();
+        Ok(ListPage {
+            objects: strip_key_prefix(&self.prefix, objects),
+            prefixes,
+            next_marker,
+        })
+    }
+
+    async fn head_object(&self, key: &str) -> Result<ObjectMeta> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let response = self.send(self.client.head(self.key_url(key))).await?;
+
+        if response.status() == 404 {
+            return Err(not_found("HEAD", &response, key));
+        }
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("HEAD", &response).into());
+        }
+        Ok(meta_from_headers(key, response.headers()))
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Bytes> {
+        self.get_resumable(key, 0, None, None).await
+    }
+
+    async fn get_object_range(&self, key: &str, offset: u64, len: u64) -> Result<Bytes> {
+        if len == 0 {
+            return Ok(Bytes::new());
+        }
+        self.get_resumable(key, offset, Some(len), None).await
+    }
+
+    async fn get_object_range_if_match(
+        &self,
+        key: &str,
+        etag: &str,
+        offset: u64,
+        len: u64,
+    ) -> Result<Bytes> {
+        if len == 0 {
+            return Ok(Bytes::new());
+        }
+        self.get_resumable(key, offset, Some(len), Some(etag)).await
+    }
+
+    async fn put_object(&self, key: &str, body: Bytes, headers: HeaderMap) -> Result<String> {
+        let _permit = self.limiter.acquire(RequestClass::Data).await?;
+        let response = self
+            .send(
+                self.client
+                    .put(self.key_url(key))
+                    .headers(headers)
+                    .body(body),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("PUT", &response).into());
+        }
+        Ok(response
+            .headers()
+            .get("etag")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string())
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let response = self.send(self.client.delete(self.key_url(key))).await?;
+
+        if !response.status().is_success() && response.status() != 404 {
+            return Err(StatusError::from_response("DELETE", &response).into());
+        }
+        Ok(())
+    }
+
+    async fn copy_object(&self, source_key: &str, dest_key: &str) -> Result<()> {
+        let _permit = self.limiter.acquire(RequestClass::Data).await?;
+        let response = self
+            .send(
+                self.client
+                    .put(self.key_url(dest_key))
+                    .header("x-amz-copy-source", self.copy_source(source_key)),
+            )
+            .await?;
+
+        // 复制失败时也可能返回 200，错误在响应体中
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("PUT Copy", &response).into());
+        }
+        let body = response.text().await?;
+        if body.contains("<Error>") {
+            return Err(anyhow!(
+                "Copy of {} to {} failed: {}",
+                source_key,
+                dest_key,
+                body
+            ));
+        }
+        Ok(())
+    }
+
+    fn region(&self) -> &str {
+        &self.region
+    }
+
+    fn has_credentials(&self) -> bool {
+        self.credentials.is_some()
+    }
+
+    fn presign_url(&self, key: &str, expires: Duration) -> Result<String> {
+        let credentials = self.credentials.as_ref().ok_or_else(|| {
+            anyhow!(
+                "Presigning requires {} and {}",
+                sigv4::ACCESS_KEY_ENV,
+                sigv4::SECRET_KEY_ENV
+            )
+        })?;
+        let url = url::Url::parse(&self.key_url(key))?;
+        Ok(sigv4::presign_url(
+            credentials,
+            &self.region,
+            &url,
+            expires,
+            SystemTime::now(),
+        ))
+    }
+
+    async fn list_first_key_under(&self, prefix: &str) -> Result<Option<String>> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        // 挂载前缀本身可能是目录标记，多取一个
+        let response = self
+            .send(self.client.get(self.list_url(prefix, "max-keys=2")))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("LIST", &response).into());
+        }
+        let body = response.text().await?;
+        Ok(strip_key_prefix(&self.prefix, parse_list_response(&body)?)
+            .into_iter()
+            .next()
+            .map(|meta| meta.key))
+    }
+
+    async fn head_bucket(&self) -> Result<()> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let response = self
+            .send(self.client.head(format!("{}/", self.base_url)))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("HEAD Bucket", &response).into());
+        }
+        Ok(())
+    }
+
+    async fn get_object_if_changed(
+        &self,
+        key: &str,
+        etag: &str,
+        max_body: u64,
+    ) -> Result<Option<(ObjectMeta, Option<Bytes>)>> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let response = self
+            .send(
+                self.client
+                    .get(self.key_url(key))
+                    .header(reqwest::header::IF_NONE_MATCH, etag),
+            )
+            .await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        if response.status() == 404 {
+            return Err(not_found("Conditional GET", &response, key));
+        }
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("Conditional GET", &response).into());
+        }
+
+        let meta = meta_from_headers(key, response.headers());
+        let body = if meta.size <= max_body {
+            Some(response.bytes().await?)
+        } else {
+            None
+        };
+        Ok(Some((meta, body)))
+    }
+
+    /// 复制到自身并使用 REPLACE 元数据指令，标准头沿用原值
+    async fn replace_metadata(
+        &self,
+        key: &str,
+        etag: &str,
+        user_meta: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = self.key_url(key);
+
+        let current = self.send(self.client.head(&url)).await?;
+        if current.status() == 404 {
+            return Err(not_found("HEAD", &current, key));
+        }
+        if !current.status().is_success() {
+            return Err(StatusError::from_response("HEAD", &current).into());
+        }
+
+        let mut headers = HeaderMap::new();
+        for name in PRESERVED_HEADERS {
+            if let Some(value) = current.headers().get(*name) {
+                headers.insert(*name, value.clone());
+            }
+        }
+        for (name, value) in user_meta {
+            headers.insert(
+                HeaderName::from_bytes(format!("{}{}", USER_META_PREFIX, name).as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+
+        let response = self
+            .send(
+                self.client
+                    .put(&url)
+                    .headers(headers)
+                    .header("x-amz-copy-source", self.copy_source(key))
+                    .header("x-amz-copy-source-if-match", etag)
+                    .header("x-amz-metadata-directive", "REPLACE"),
+            )
+            .await?;
+
+        if response.status() == StatusCode::PRECONDITION_FAILED {
+            return Err(anyhow!("Object changed while updating metadata: {}", key));
+        }
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("PUT Copy", &response).into());
+        }
+        Ok(())
+    }
+
+    async fn initiate_multipart_upload(&self, key: &str, headers: HeaderMap) -> Result<String> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = format!("{}?uploads", self.key_url(key));
+        let response = self.send(self.client.post(&url).headers(headers)).await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("Initiate Multipart Upload", &response).into());
+        }
+        let body = response.text().await?;
+        let result: InitiateMultipartUploadResult = quick_xml::de::from_str(&body)
+            .map_err(|e| anyhow!("Failed to parse initiate multipart upload response: {}", e))?;
+        Ok(result.upload_id)
+    }
+
+    async fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        body: Bytes,
+    ) -> Result<String> {
+        let _permit = self.limiter.acquire(RequestClass::Data).await?;
+        let url = self.part_url(key, upload_id, part_number);
+        let response = self.send(self.client.put(&url).body(body)).await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("Upload Part", &response).into());
+        }
+        Ok(response
+            .headers()
+            .get("etag")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string())
+    }
+
+    async fn upload_part_copy(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        source_key: &str,
+        source_etag: &str,
+        range: Range<u64>,
+    ) -> Result<String> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = self.part_url(key, upload_id, part_number);
+        let response = self
+            .send(
+                self.client
+                    .put(&url)
+                    .header("x-amz-copy-source", self.copy_source(source_key))
+                    .header(
+                        "x-amz-copy-source-range",
+                        format!("bytes={}-{}", range.start, range.end - 1),
+                    )
+                    .header("x-amz-copy-source-if-match", source_etag),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("Upload Part - Copy", &response).into());
+        }
+        let body = response.text().await?;
+        let result: EtagResult = quick_xml::de::from_str(&body)
+            .map_err(|e| anyhow!("Failed to parse upload part copy response: {}", e))?;
+        Ok(result.etag)
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[(u32, String)],
+    ) -> Result<String> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = format!(
+            "{}?uploadId={}",
+            self.key_url(key),
+            signer::uri_encode(upload_id)
+        );
+        let response = self
+            .send(self.client.post(&url).body(complete_multipart_body(parts)))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(StatusError::from_response("Complete Multipart Upload", &response).into());
+        }
+        // 合并失败时也可能返回 200，错误在响应体中
+        let body = response.text().await?;
+        let result: EtagResult = quick_xml::de::from_str(&body)
+            .map_err(|_| anyhow!("Complete multipart upload of {} failed: {}", key, body))?;
+        Ok(result.etag)
+    }
+
+    async fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<()> {
+        let _permit = self.limiter.acquire(RequestClass::Metadata).await?;
+        let url = format!(
+            "{}?uploadId={}",
+            self.key_url(key),
+            signer::uri_encode(upload_id)
+        );
+        let response = self.send(self.client.delete(&url)).await?;
+
+        if !response.status().is_success() && response.status() != 404 {
+            return Err(StatusError::from_response("Abort Multipart Upload", &response).into());
+        }
+        Ok(())
+    }
+}
+
+/// 请求中的 `x-cos-` 头改写为 `x-amz-` 头，SSE-KMS 的写法一并改写
+fn to_s3_headers(headers: &mut HeaderMap) {
+    let names: Vec<HeaderName> = headers
+        .keys()
+        .filter(|name| name.as_str().starts_with("x-cos-"))
+        .cloned()
+        .collect();
+    for name in names {
+        let s3 = match RENAMED_HEADERS
+            .iter()
+            .find(|(cos, _)| *cos == name.as_str())
+        {
+            Some((_, s3)) => s3.to_string(),
+            None => format!("x-amz-{}", &name.as_str()["x-cos-".len()..]),
+        };
+        let Ok(s3) = HeaderName::from_bytes(s3.as_bytes()) else {
+            continue;
+        };
+        let values: Vec<HeaderValue> = headers.get_all(&name).iter().cloned().collect();
+        headers.remove(&name);
+        for value in values {
+            headers.append(s3.clone(), value);
+        }
+    }
+    if headers
+        .get("x-amz-server-side-encryption")
+        .is_some_and(|v| v == COS_KMS)
+    {
+        headers.insert(
+            "x-amz-server-side-encryption",
+            HeaderValue::from_static(S3_KMS),
+        );
+    }
+}
+
+/// 响应中的 `x-amz-` 头改写为 `x-cos-` 头，原来的头保留
+fn from_s3_headers(headers: &mut HeaderMap) {
+    let renamed: Vec<(HeaderName, HeaderValue)> = headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let cos = match RENAMED_HEADERS.iter().find(|(_, s3)| *s3 == name.as_str()) {
+                Some((cos, _)) => cos.to_string(),
+                None => format!("x-cos-{}", name.as_str().strip_prefix("x-amz-")?),
+            };
+            Some((HeaderName::from_bytes(cos.as_bytes()).ok()?, value.clone()))
+        })
+        .collect();
+    for (name, value) in renamed {
+        headers.insert(name, value);
+    }
+    if headers.get(SSE_HEADER).is_some_and(|v| v == S3_KMS) {
+        headers.insert(SSE_HEADER, HeaderValue::from_static(COS_KMS));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_urls() {
+        let options = ClientOptions {
+            endpoint: Some("http://minio:9000/".to_string()),
+            prefix: "team-a".to_string(),
+            ..Default::default()
+        };
+        let client = S3Client::with_options("data".to_string(), String::new(), &options).unwrap();
+        assert_eq!(client.region(), DEFAULT_REGION);
+        assert_eq!(
+            client.object_url("a b.txt"),
+            "http://minio:9000/data/team-a/a%20b.txt"
+        );
+        assert_eq!(
+            client.list_url("logs/", "max-keys=2"),
+            "http://minio:9000/data/?prefix=team-a%2Flogs%2F&max-keys=2"
+        );
+        assert_eq!(client.copy_source("x"), "/data/team-a/x");
+
+        // 没有访问地址时访问 Amazon S3（虚拟主机形式）
+        let client = S3Client::with_options(
+            "data".to_string(),
+            "eu-west-1".to_string(),
+            &ClientOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            client.object_url("x"),
+            "https://data.s3.eu-west-1.amazonaws.com/x"
+        );
+        assert_eq!(
+            client.list_url("", ""),
+            "https://data.s3.eu-west-1.amazonaws.com/"
+        );
+    }
+
+    #[test]
+    fn test_header_translation() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-cos-meta-mtime", HeaderValue::from_static("1.5"));
+        headers.insert(SSE_HEADER, HeaderValue::from_static(COS_KMS));
+        headers.insert(SSE_KMS_KEY_ID_HEADER, HeaderValue::from_static("key-1"));
+        headers.insert("content-type", HeaderValue::from_static("text/plain"));
+        to_s3_headers(&mut headers);
+        assert_eq!(headers["x-amz-meta-mtime"], "1.5");
+        assert_eq!(headers["x-amz-server-side-encryption"], S3_KMS);
+        assert_eq!(
+            headers["x-amz-server-side-encryption-aws-kms-key-id"],
+            "key-1"
+        );
+        assert_eq!(headers["content-type"], "text/plain");
+        assert!(headers
+            .keys()
+            .all(|name| !name.as_str().starts_with("x-cos-")));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-amz-meta-mode", HeaderValue::from_static("33188"));
+        headers.insert("x-amz-request-id", HeaderValue::from_static("REQ"));
+        headers.insert("x-amz-id-2", HeaderValue::from_static("HOST"));
+        headers.insert(
+            "x-amz-server-side-encryption",
+            HeaderValue::from_static(S3_KMS),
+        );
+        headers.insert("content-length", HeaderValue::from_static("7"));
+        from_s3_headers(&mut headers);
+        let meta = meta_from_headers("k", &headers);
+        assert_eq!(meta.size, 7);
+        assert_eq!(
+            meta.details.user_meta.get("mode").map(String::as_str),
+            Some("33188")
+        );
+        assert_eq!(meta.details.request_id.as_deref(), Some("REQ"));
+        assert_eq!(meta.server_side_encryption.as_deref(), Some(COS_KMS));
+        assert_eq!(headers[TRACE_ID_HEADER], "HOST");
+    }
+
+    #[tokio::test]
+    async fn test_sign_request() {
+        let options = ClientOptions {
+            endpoint: Some("http://minio:9000".to_string()),
+            credentials: Some(Credentials {
+                secret_id: "minio".to_string(),
+                secret_key: "minio123".to_string(),
+                session_token: Some("token".to_string()),
+            }),
+            ..Default::default()
+        };
+        let client = S3Client::with_options("data".to_string(), String::new(), &options).unwrap();
+        let mut request = client
+            .client
+            .put(client.key_url("a"))
+            .header("x-amz-meta-mtime", "1")
+            .build()
+            .unwrap();
+        client
+            .sign(options.credentials.as_ref().unwrap(), &mut request)
+            .unwrap();
+
+        let authorization = request.headers()["authorization"].to_str().unwrap();
+        assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=minio/"));
+        assert!(authorization.contains("/us-east-1/s3/aws4_request, SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-meta-mtime;x-amz-security-token, Signature="));
+        assert_eq!(request.headers()["x-amz-content-sha256"], UNSIGNED_PAYLOAD);
+        assert_eq!(request.headers()["x-amz-security-token"], "token");
+    }
+}
@@ -0,0 +1,110 @@
+//! 与 s3fs、goofys 等工具共用一个桶时的目录与元数据约定
+
+use reqwest::header::{HeaderMap, HeaderValue};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use crate::cos_client::USER_META_PREFIX;
+
+/// s3fs 早期版本与 Hadoop s3n 使用的目录标记后缀：`dir_$folder$` 表示目录 `dir/`
+pub const FOLDER_MARKER_SUFFIX: &str = "_$folder$";
+
+/// s3fs 记录属主与模式的自定义元数据键。经 S3 兼容接口写入的 `x-amz-meta-*`
+/// 在 COS 上读出为 `x-cos-meta-*`，两边看到的是同一组元数据
+pub const MODE_META: &str = "mode";
+pub const UID_META: &str = "uid";
+pub const GID_META: &str = "gid";
+
+/// `st_mode` 中的文件类型位
+const S_IFMT: u32 = 0o170000;
+pub const S_IFREG: u32 = 0o100000;
+pub const S_IFDIR: u32 = 0o040000;
+
+/// 把其他工具的目录标记换成本程序使用的 `dir/` 形式，其余键原样返回
+pub fn normalize_key(key: &str) -> Cow<'_, str> {
+    match key.strip_suffix(FOLDER_MARKER_SUFFIX) {
+        Some(dir) if !dir.is_empty() && !dir.ends_with('/') => Cow::Owned(format!("{}/", dir)),
+        _ => Cow::Borrowed(key),
+    }
+}
+
+/// 自定义元数据中 s3fs 记录的属主与权限位，缺失或无法解析的字段为 None
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetaOwnership {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub perm: Option<u16>,
+}
+
+impl MetaOwnership {
+    /// s3fs 以十进制记录完整的 `st_mode`（如 `33188` 即 `0100644`），只取权限位
+    pub fn from_user_meta(user_meta: &BTreeMap<String, String>) -> Self {
+        let field = |name: &str| {
+            user_meta
+                .get(name)
+                .and_then(|v| v.trim().parse::<u32>().ok())
+        };
+        Self {
+            uid: field(UID_META),
+            gid: field(GID_META),
+            perm: field(MODE_META).map(|mode| (mode & 0o7777) as u16),
+        }
+    }
+}
+
+/// 上传时附加的 s3fs 元数据头，`mode` 为包含文件类型位的 `st_mode`
+pub fn ownership_headers(uid: u32, gid: u32, mode: u32) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in [(UID_META, uid), (GID_META, gid), (MODE_META, mode)] {
+        let name = format!("{}{}", USER_META_PREFIX, name);
+        headers.insert(
+            reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .expect("metadata header names are valid"),
+            HeaderValue::from(value),
+        );
+    }
+    headers
+}
+
+/// 只有权限位时补上文件类型位
+pub fn file_mode(perm: u32, is_dir: bool) -> u32 {
+    if perm & S_IFMT != 0 {
+        return perm;
+    }
+    perm | if is_dir { S_IFDIR } else { S_IFREG }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_s3fs_conventions() {
+        assert_eq!(normalize_key("logs/2024_$folder$"), "logs/2024/");
+        assert_eq!(normalize_key("data/"), "data/");
+        assert_eq!(normalize_key("a.txt"), "a.txt");
+        assert_eq!(normalize_key("_$folder$"), "_$folder$");
+
+        let meta: BTreeMap<String, String> = [("mode", "33188"), ("uid", "1000"), ("gid", "x")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        assert_eq!(
+            MetaOwnership::from_user_meta(&meta),
+            MetaOwnership {
+                uid: Some(1000),
+                gid: None,
+                perm: Some(0o644),
+            }
+        );
+
+        let headers = ownership_headers(1000, 100, file_mode(0o640, false));
+        assert_eq!(headers["x-cos-meta-uid"], "1000");
+        assert_eq!(headers["x-cos-meta-gid"], "100");
+        assert_eq!(
+            headers["x-cos-meta-mode"],
+            (S_IFREG | 0o640).to_string().as_str()
+        );
+        assert_eq!(file_mode(0o755, true), 0o40755);
+    }
+}
@@ -0,0 +1,156 @@
+use log::{debug, info, warn};
+use std::env;
+use std::io;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::supervise::{probe, MountState};
+
+/// systemd 传入的通知套接字
+const NOTIFY_SOCKET_ENV: &str = "NOTIFY_SOCKET";
+
+/// 看门狗超时（微秒）及其针对的进程
+const WATCHDOG_USEC_ENV: &str = "WATCHDOG_USEC";
+const WATCHDOG_PID_ENV: &str = "WATCHDOG_PID";
+
+/// 发送 READY=1 前等待挂载点可用的最长时间
+const READY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// 子进程不应继承的变量：通知由直接被 systemd 启动的进程发送
+pub const ENV_VARS: &[&str] = &[NOTIFY_SOCKET_ENV, WATCHDOG_USEC_ENV, WATCHDOG_PID_ENV];
+
+/// 是否由 `Type=notify` 的 systemd 单元启动
+pub fn enabled() -> bool {
+    env::var_os(NOTIFY_SOCKET_ENV).is_some_and(|socket| !socket.is_empty())
+}
+
+/// 向 systemd 发送状态，例如 `READY=1`；不在 systemd 下运行时什么也不做
+pub fn notify(state: &str) -> io::Result<()> {
+    let Some(socket) = env::var_os(NOTIFY_SOCKET_ENV).filter(|socket| !socket.is_empty()) else {
+        return Ok(());
+    };
+    let socket = socket.to_string_lossy();
+    let addr = match socket.strip_prefix('@') {
+        Some(name) => abstract_addr(name)?,
+        None => SocketAddr::from_pathname(socket.as_ref())?,
+    };
+    UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &addr)?;
+    debug!("sd_notify: {}", state.replace('\n', " "));
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn abstract_addr(name: &str) -> io::Result<SocketAddr> {
+    use std::os::linux::net::SocketAddrExt;
+    SocketAddr::from_abstract_name(name)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn abstract_addr(_name: &str) -> io::Result<SocketAddr> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "abstract sockets are only supported on Linux",
+    ))
+}
+
+/// 单元配置了 `WatchdogSec` 时的看门狗超时
+pub fn watchdog_timeout() -> Option<Duration> {
+    if let Some(pid) = env::var_os(WATCHDOG_PID_ENV) {
+        if pid.to_str()?.parse::<u32>().ok()? != std::process::id() {
+            return None;
+        }
+    }
+    let usec: u64 = env::var(WATCHDOG_USEC_ENV).ok()?.parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec))
+}
+
+/// 等所有挂载点都能正常 statfs（首次列表已完成）后发送 READY=1
+pub fn notify_ready(mount_points: &[PathBuf]) {
+    if !enabled() {
+        return;
+    }
+    let deadline = Instant::now() + READY_TIMEOUT;
+    for mount_point in mount_points {
+        while !is_live(mount_point, Duration::from_secs(5)) {
+            if Instant::now() > deadline {
+                warn!(
+                    "{} is not mounted after {:?}, not reporting readiness to systemd",
+                    mount_point.display(),
+                    READY_TIMEOUT
+                );
+                return;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    let status = format!(
+        "READY=1\nSTATUS=Serving {}",
+        mount_points
+            .iter()
+            .map(|mount_point| mount_point.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    match notify(&status) {
+        Ok(()) => info!("Notified systemd of readiness"),
+        Err(e) => warn!("Failed to notify systemd: {}", e),
+    }
+}
+
+/// 配置了看门狗时启动后台线程：每半个超时探测一次挂载点，都能响应才发送 WATCHDOG=1，
+/// 挂载失去响应时停止喂狗，由 systemd 按单元配置重启
+pub fn spawn_watchdog(mount_points: Vec<PathBuf>) -> Option<JoinHandle<()>> {
+    let timeout = watchdog_timeout()?;
+    let interval = timeout / 2;
+    info!("systemd watchdog enabled, pinging every {:?}", interval);
+
+    Some(thread::spawn(move || loop {
+        thread::sleep(interval);
+        if let Some(hung) = mount_points
+            .iter()
+            .find(|mount_point| !is_live(mount_point, interval))
+        {
+            warn!(
+                "{} is not responding, skipping the systemd watchdog ping",
+                hung.display()
+            );
+            continue;
+        }
+        if let Err(e) = notify("WATCHDOG=1") {
+            warn!("Failed to ping the systemd watchdog: {}", e);
+        }
+    }))
+}
+
+/// 挂载点在 `timeout` 内正常响应 statfs
+fn is_live(mount_point: &Path, timeout: Duration) -> bool {
+    matches!(probe(mount_point, timeout), MountState::Healthy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notify_datagram() {
+        let dir = env::temp_dir().join(format!("cosfs-notify-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notify.sock");
+        let _ = std::fs::remove_file(&path);
+        let receiver = UnixDatagram::bind(&path).unwrap();
+
+        env::set_var(NOTIFY_SOCKET_ENV, &path);
+        notify("READY=1").unwrap();
+        env::remove_var(NOTIFY_SOCKET_ENV);
+        // 未设置时静默忽略
+        notify("READY=1").unwrap();
+
+        let mut buf = [0u8; 64];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"READY=1");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,273 @@
+//! COS XML API 的请求签名（HMAC-SHA1）
+
+use hmac::{Hmac, Mac};
+use sha1::{Digest, Sha1};
+use std::env;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 读取访问密钥的环境变量
+pub const SECRET_ID_ENV: &str = "COS_SECRET_ID";
+pub const SECRET_KEY_ENV: &str = "COS_SECRET_KEY";
+pub const SESSION_TOKEN_ENV: &str = "COS_SESSION_TOKEN";
+
+/// 访问密钥
+#[derive(Clone)]
+pub struct Credentials {
+    pub secret_id: String,
+    pub secret_key: String,
+    /// 临时密钥的会话令牌
+    pub session_token: Option<String>,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 不在日志里输出密钥
+        f.debug_struct("Credentials")
+            .field("secret_id", &self.secret_id)
+            .field("session_token", &self.session_token.as_ref().map(|_| "***"))
+            .finish_non_exhaustive()
+    }
+}
+
+impl Credentials {
+    /// 从 `COS_SECRET_ID`、`COS_SECRET_KEY`（以及可选的 `COS_SESSION_TOKEN`）读取
+    pub fn from_env() -> Option<Self> {
+        let secret_id = env::var(SECRET_ID_ENV).ok().filter(|v| !v.is_empty())?;
+        let secret_key = env::var(SECRET_KEY_ENV).ok().filter(|v| !v.is_empty())?;
+        Some(Self {
+            secret_id,
+            secret_key,
+            session_token: env::var(SESSION_TOKEN_ENV).ok().filter(|v| !v.is_empty()),
+        })
+    }
+}
+
+/// 生成预签名 URL：`base_url/key` 在 `[now, now + expires)` 内可以直接 GET
+pub fn presign_url(
+    credentials: &Credentials,
+    base_url: &str,
+    key: &str,
+    expires: Duration,
+    now: SystemTime,
+) -> String {
+    // 不签名请求头和查询参数，链接可以从任何客户端使用
+    let query: Vec<String> = sign(credentials, "get", key, &[], expires, now)
+        .into_iter()
+        .map(|(name, value)| format!("{}={}", name, uri_encode(&value)))
+        .collect();
+
+    let mut url = format!("{}/{}?{}", base_url, encode_path(key), query.join("&"));
+    if let Some(token) = &credentials.session_token {
+        url.push_str("&x-cos-security-token=");
+        url.push_str(&uri_encode(token));
+    }
+    url
+}
+
+/// 生成 `Authorization` 头的值；临时密钥的会话令牌需要另外放在 `x-cos-security-token` 头中
+pub fn authorization(
+    credentials: &Credentials,
+    method: &str,
+    key: &str,
+    params: &[(&str, &str)],
+    expires: Duration,
+    now: SystemTime,
+) -> String {
+    sign(credentials, method, key, params, expires, now)
+        .into_iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// 计算签名，返回 `q-*` 参数；签名覆盖方法、路径和查询参数 `params`，不覆盖请求头
+fn sign(
+    credentials: &Credentials,
+    method: &str,
+    key: &str,
+    params: &[(&str, &str)],
+    expires: Duration,
+    now: SystemTime,
+) -> Vec<(&'static str, String)> {
+    let start = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let end = start + expires.as_secs().max(1);
+    let key_time = format!("{};{}", start, end);
+
+    let mut params: Vec<(String, String)> = params
+        .iter()
+        .map(|(name, value)| (uri_encode(name).to_ascii_lowercase(), uri_encode(value)))
+        .collect();
+    params.sort();
+    let param_list: Vec<&str> = params.iter().map(|(name, _)| name.as_str()).collect();
+    let http_params: Vec<String> = params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+
+    let http_string = format!(
+        "{}\n/{}\n{}\n\n",
+        method.to_ascii_lowercase(),
+        key,
+        http_params.join("&")
+    );
+    let string_to_sign = format!(
+        "sha1\n{}\n{}\n",
+        key_time,
+        hex(&sha1(http_string.as_bytes()))
+    );
+    let sign_key = hex(&hmac_sha1(
+        credentials.secret_key.as_bytes(),
+        key_time.as_bytes(),
+    ));
+    let signature = hex(&hmac_sha1(sign_key.as_bytes(), string_to_sign.as_bytes()));
+
+    vec![
+        ("q-sign-algorithm", "sha1".to_string()),
+        ("q-ak", credentials.secret_id.clone()),
+        ("q-sign-time", key_time.clone()),
+        ("q-key-time", key_time),
+        ("q-header-list", String::new()),
+        ("q-url-param-list", param_list.join(";")),
+        ("q-signature", signature),
+    ]
+}
+
+/// 按 RFC 3986 编码，只保留非保留字符
+pub fn uri_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// 编码对象键作为 URL 路径，保留 `/`
+pub fn encode_path(key: &str) -> String {
+    key.split('/').map(uri_encode).collect::<Vec<_>>().join("/")
+}
+
+/// 还原 `%XX` 编码；编码不合法时返回 None
+pub fn uri_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn hmac_sha1(key: &[u8], message: &[u8]) -> [u8; 20] {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    Sha1::digest(data).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha1_and_hmac() {
+        assert_eq!(
+            hex(&sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            hex(&sha1(&[b'a'; 1000])),
+            "291e9a6c66994949b57ba5e650361e98fc36b1ba"
+        );
+        // RFC 2202
+        assert_eq!(
+            hex(&hmac_sha1(b"Jefe", b"what do ya want for nothing?")),
+            "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"
+        );
+        assert_eq!(
+            hex(&hmac_sha1(
+                &[0xaa; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "aa4ae5e15272d00e95705637ce8a3b55ed402112"
+        );
+    }
+
+    #[test]
+    fn test_presign_url() {
+        let credentials = Credentials {
+            secret_id: "AKID".to_string(),
+            secret_key: "secret".to_string(),
+            session_token: Some("tok en".to_string()),
+        };
+        let url = presign_url(
+            &credentials,
+            "https://b-125.cos.ap-beijing.myqcloud.com",
+            "dir/a b.txt",
+            Duration::from_secs(3600),
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        );
+
+        assert!(url.starts_with(
+            "https://b-125.cos.ap-beijing.myqcloud.com/dir/a%20b.txt?q-sign-algorithm=sha1\
+             &q-ak=AKID&q-sign-time=1700000000%3B1700003600&q-key-time=1700000000%3B1700003600\
+             &q-header-list=&q-url-param-list=&q-signature="
+        ));
+        assert!(url.ends_with("&x-cos-security-token=tok%20en"));
+    }
+
+    #[test]
+    fn test_authorization_signs_query_params() {
+        let credentials = Credentials {
+            secret_id: "AKID".to_string(),
+            secret_key: "secret".to_string(),
+            session_token: None,
+        };
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let auth = authorization(
+            &credentials,
+            "GET",
+            "a",
+            &[("acl", "")],
+            Duration::from_secs(60),
+            now,
+        );
+        assert!(auth.starts_with(
+            "q-sign-algorithm=sha1&q-ak=AKID&q-sign-time=1700000000;1700000060\
+             &q-key-time=1700000000;1700000060&q-header-list=&q-url-param-list=acl&q-signature="
+        ));
+
+        // 查询参数参与签名
+        let plain = authorization(&credentials, "GET", "a", &[], Duration::from_secs(60), now);
+        assert_ne!(auth.rsplit('=').next(), plain.rsplit('=').next());
+    }
+
+    #[test]
+    fn test_uri_round_trip() {
+        assert_eq!(encode_path("日志/a+b.txt"), "%E6%97%A5%E5%BF%97/a%2Bb.txt");
+        assert_eq!(
+            uri_decode("%E6%97%A5%E5%BF%97/a%2Bb.txt").unwrap(),
+            "日志/a+b.txt"
+        );
+        assert_eq!(uri_decode("%zz"), None);
+    }
+}
@@ -0,0 +1,362 @@
+use anyhow::{anyhow, Result};
+use log::warn;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::crc64::Crc64;
+
+/// 暂存文件扩展名
+const SPOOL_FILE_EXTENSION: &str = "spool";
+
+/// 计算校验值时每次读取的大小
+const READ_CHUNK: usize = 1024 * 1024;
+
+/// 暂存文件的落盘策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FsyncPolicy {
+    /// 从不主动 fsync，交给操作系统
+    Never,
+    /// 文件关闭（准备上传）时 fsync 一次
+    #[default]
+    OnClose,
+    /// 每次写入后都 fsync
+    Always,
+}
+
+impl FromStr for FsyncPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "never" => Ok(FsyncPolicy::Never),
+            "close" | "on-close" => Ok(FsyncPolicy::OnClose),
+            "always" => Ok(FsyncPolicy::Always),
+            _ => Err(format!(
+                "unknown fsync policy: {} (expected never, close or always)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FsyncPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FsyncPolicy::Never => "never",
+            FsyncPolicy::OnClose => "close",
+            FsyncPolicy::Always => "always",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 写入暂存区配置
+#[derive(Debug, Clone)]
+pub struct SpoolOptions {
+    /// 暂存目录，应与读缓存目录分开
+    pub dir: PathBuf,
+
+    /// 暂存区总大小上限（None 表示不限制）
+    pub max_size: Option<u64>,
+
+    /// 落盘策略
+    pub fsync: FsyncPolicy,
+}
+
+/// 脏数据暂存区：写入在上传前先落在这里，配额与读缓存互不影响
+pub struct Spool {
+    options: SpoolOptions,
+    used: AtomicU64,
+    next_id: AtomicU64,
+}
+
+impl Spool {
+    /// 打开暂存目录。目录不能与读缓存目录重叠，否则缓存清理会误删脏数据
+    pub fn open(options: &SpoolOptions, cache_dir: &Path) -> Result<Self> {
+        fs::create_dir_all(&options.dir)?;
+        fs::create_dir_all(cache_dir)?;
+
+        let spool_dir = options.dir.canonicalize()?;
+        let cache_dir = cache_dir.canonicalize()?;
+        if spool_dir.starts_with(&cache_dir) || cache_dir.starts_with(&spool_dir) {
+            return Err(anyhow!(
+                "Spool directory {} overlaps the cache directory {}",
+                spool_dir.display(),
+                cache_dir.display()
+            ));
+        }
+
+        // 上次运行遗留的暂存文件仍计入配额，不自动删除
+        let mut used = 0;
+        let mut leftovers = 0;
+        for entry in fs::read_dir(&spool_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some(SPOOL_FILE_EXTENSION) {
+                used += fs::metadata(&path)?.len();
+                leftovers += 1;
+            }
+        }
+        if leftovers > 0 {
+            warn!(
+                "Spool directory {} contains {} files ({} bytes) from a previous run",
+                spool_dir.display(),
+                leftovers,
+                used
+            );
+        }
+
+        Ok(Self {
+            options: SpoolOptions {
+                dir: spool_dir,
+                ..options.clone()
+            },
+            used: AtomicU64::new(used),
+            next_id: AtomicU64::new(0),
+        })
+    }
+
+    /// 暂存区当前占用的字节数
+    pub fn used_bytes(&self) -> u64 {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// 为一次写入创建新的暂存文件
+    pub fn create(self: &Arc<Self>) -> io::Result<SpoolFile> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let path = self.options.dir.join(format!(
+            "{}-{}.{}",
+            std::process::id(),
+            id,
+            SPOOL_FILE_EXTENSION
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+
+        Ok(SpoolFile {
+            spool: Arc::clone(self),
+            path,
+            file,
+            size: 0,
+            mtime: SystemTime::now(),
+        })
+    }
+
+    /// 预留配额，超出上限时返回 ENOSPC
+    fn reserve(&self, bytes: u64) -> io::Result<()> {
+        let max = self.options.max_size;
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                let next = used + bytes;
+                match max {
+                    Some(max) if next > max => None,
+                    _ => Some(next),
+                }
+            })
+            .map(|_| ())
+            .map_err(|_| io::Error::from_raw_os_error(libc::ENOSPC))
+    }
+
+    fn release(&self, bytes: u64) {
+        self.used.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+/// 单个暂存文件，丢弃时删除文件并归还配额
+pub struct SpoolFile {
+    spool: Arc<Spool>,
+    path: PathBuf,
+    file: File,
+    size: u64,
+    /// 上传时记录到对象元数据中的 mtime
+    mtime: SystemTime,
+}
+
+impl SpoolFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn mtime(&self) -> SystemTime {
+        self.mtime
+    }
+
+    /// 显式设置 mtime（setattr，例如 `cp -p`、rsync 在写完后调用 utimensat）
+    pub fn set_mtime(&mut self, mtime: SystemTime) {
+        self.mtime = mtime;
+    }
+
+    /// 读取开头最多 `len` 字节，上传时用于嗅探 Content-Type
+    pub fn head(&self, len: usize) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0; len.min(self.size as usize)];
+        self.file.read_exact_at(&mut buf, 0)?;
+        Ok(buf)
+    }
+
+    /// 从 `offset` 读取恰好 `buf.len()` 字节
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        self.file.read_exact_at(buf, offset)
+    }
+
+    /// 内容的 CRC64-ECMA，与 COS 返回的 `x-cos-hash-crc64ecma` 可以直接比较
+    pub fn crc64(&self) -> io::Result<u64> {
+        let mut crc = Crc64::new();
+        self.read_chunks(|data| crc.update(data))?;
+        Ok(crc.finish())
+    }
+
+    /// 内容的 MD5（十六进制），与单段上传对象的 ETag 相同
+    pub fn md5(&self) -> io::Result<String> {
+        let mut md5 = md5::Context::new();
+        self.read_chunks(|data| md5.consume(data))?;
+        Ok(format!("{:x}", md5.compute()))
+    }
+
+    /// 按顺序分段读取全部内容
+    fn read_chunks(&self, mut f: impl FnMut(&[u8])) -> io::Result<()> {
+        let mut buf = vec![0; READ_CHUNK.min(self.size as usize)];
+        let mut offset = 0;
+        while offset < self.size {
+            let len = buf.len().min((self.size - offset) as usize);
+            self.file.read_exact_at(&mut buf[..len], offset)?;
+            f(&buf[..len]);
+            offset += len as u64;
+        }
+        Ok(())
+    }
+
+    /// 在指定偏移写入数据，文件增长部分计入暂存区配额
+    pub fn write_at(&mut self, offset: u64, data: &[u8]) -> io::Result<()> {
+        let end = offset + data.len() as u64;
+        let grow = end.saturating_sub(self.size);
+        self.spool.reserve(grow)?;
+
+        if let Err(e) = self.file.write_all_at(data, offset) {
+            self.spool.release(grow);
+            return Err(e);
+        }
+        self.size += grow;
+        self.mtime = SystemTime::now();
+
+        if self.spool.options.fsync == FsyncPolicy::Always {
+            self.file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// 截断或扩展到 `size`（扩展出的部分读出为 0），增长部分计入暂存区配额
+    pub fn set_len(&mut self, size: u64) -> io::Result<()> {
+        if size > self.size {
+            self.spool.reserve(size - self.size)?;
+            if let Err(e) = self.file.set_len(size) {
+                self.spool.release(size - self.size);
+                return Err(e);
+            }
+        } else {
+            self.file.set_len(size)?;
+            self.spool.release(self.size - size);
+        }
+        self.size = size;
+        self.mtime = SystemTime::now();
+        Ok(())
+    }
+
+    /// 写入结束，按策略落盘后即可上传
+    pub fn finish(&self) -> io::Result<()> {
+        if self.spool.options.fsync != FsyncPolicy::Never {
+            self.file.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Failed to remove spool file {}: {}", self.path.display(), e);
+        }
+        self.spool.release(self.size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn open_spool(root: &TempDir, max_size: Option<u64>) -> Arc<Spool> {
+        let options = SpoolOptions {
+            dir: root.path().join("spool"),
+            max_size,
+            fsync: FsyncPolicy::OnClose,
+        };
+        Arc::new(Spool::open(&options, &root.path().join("cache")).unwrap())
+    }
+
+    #[test]
+    fn test_quota_is_separate_and_released() {
+        let root = TempDir::new().unwrap();
+        let spool = open_spool(&root, Some(10));
+
+        let mut file = spool.create().unwrap();
+        file.write_at(0, b"12345678").unwrap();
+        file.write_at(4, b"5678").unwrap();
+        assert_eq!(spool.used_bytes(), 8);
+
+        let err = file.write_at(8, b"abc").unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOSPC));
+        file.finish().unwrap();
+
+        let path = file.path().to_path_buf();
+        drop(file);
+        assert!(!path.exists());
+        assert_eq!(spool.used_bytes(), 0);
+    }
+
+    #[test]
+    fn test_mtime_follows_writes_until_set() {
+        let root = TempDir::new().unwrap();
+        let spool = open_spool(&root, None);
+
+        let mut file = spool.create().unwrap();
+        file.write_at(0, b"hello").unwrap();
+        assert!(file.mtime() > SystemTime::UNIX_EPOCH);
+        assert_eq!(file.head(3).unwrap(), b"hel");
+        assert_eq!(file.head(64).unwrap(), b"hello");
+
+        let preserved = SystemTime::UNIX_EPOCH + std::time::Duration::new(1_000_000_000, 42);
+        file.set_mtime(preserved);
+        assert_eq!(file.mtime(), preserved);
+    }
+
+    #[test]
+    fn test_rejects_overlapping_cache_dir() {
+        let root = TempDir::new().unwrap();
+        let options = SpoolOptions {
+            dir: root.path().join("cache/spool"),
+            max_size: None,
+            fsync: FsyncPolicy::Never,
+        };
+        assert!(Spool::open(&options, &root.path().join("cache")).is_err());
+    }
+
+    #[test]
+    fn test_fsync_policy_parsing() {
+        assert_eq!("always".parse::<FsyncPolicy>(), Ok(FsyncPolicy::Always));
+        assert_eq!("close".parse::<FsyncPolicy>(), Ok(FsyncPolicy::OnClose));
+        assert!("sometimes".parse::<FsyncPolicy>().is_err());
+    }
+}
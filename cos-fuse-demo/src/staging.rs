@@ -0,0 +1,517 @@
+use anyhow::{bail, Result};
+use log::{debug, warn};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::cos_client::{
+    ObjectDetails, ObjectMeta, MTIME_META_HEADER, SSE_HEADER, USER_META_PREFIX,
+};
+use crate::extents::ExtentMap;
+use crate::object_store::ObjectStore;
+use crate::partial_upload::{
+    plan_parts, upload_partial, upload_whole, DEFAULT_MIN_OBJECT_SIZE, DEFAULT_PART_SIZE,
+};
+use crate::permissions::Ownership;
+use crate::spool::SpoolFile;
+use crate::upload::{UploadOptions, SNIFF_LEN};
+
+/// 从 COS 取回旧内容时单个请求的大小
+const FETCH_CHUNK: u64 = 8 * 1024 * 1024;
+
+/// 以写方式打开的对象：写入先落在暂存文件中，flush、fsync 或关闭时上传到 COS。
+///
+/// 打开已有对象时不下载全部内容：暂存文件与对象等长，`local` 记下其中已有内容的区间
+/// （写入过的或按需取回的），读取和整体上传之前才取回缺少的旧内容；
+/// 只改动了大对象的一小部分时，没有改动的分块在服务端从旧对象复制
+pub struct WriteHandle {
+    key: String,
+    file: SpoolFile,
+    /// 最近一次上传（或打开时）COS 上的对象，新建的文件为 None
+    base: Option<ObjectMeta>,
+    /// 暂存文件开头仍对应 `base` 内容的长度：截断后再扩展出的部分是空洞而不是旧内容
+    base_len: u64,
+    /// 暂存文件中已有内容的区间
+    local: ExtentMap,
+    /// 新建文件的属主与权限，`--s3fs-compat` 上传时记录到元数据中
+    owner: Option<Ownership>,
+    /// 自上次上传以来是否有改动
+    dirty: bool,
+    /// 文件在打开期间被删除，改动只留在暂存中，不再上传
+    unlinked: bool,
+}
+
+impl WriteHandle {
+    /// 新建的空文件；没有写入也会在关闭时上传，`touch` 得到空对象
+    pub fn create(key: String, file: SpoolFile, owner: Ownership) -> Self {
+        Self {
+            key,
+            file,
+            base: None,
+            base_len: 0,
+            local: ExtentMap::new(),
+            owner: Some(owner),
+            dirty: true,
+            unlinked: false,
+        }
+    }
+
+    /// 打开已有对象 `base`（应来自 HEAD，带有 CRC64 与自定义元数据）；
+    /// `truncate` 为 true 时（O_TRUNC）从空文件开始
+    pub fn open(mut file: SpoolFile, base: ObjectMeta, truncate: bool) -> io::Result<Self> {
+        let mut base_len = 0;
+        if !truncate {
+            file.set_len(base.size)?;
+            file.set_mtime(base.last_modified);
+            base_len = base.size;
+        }
+        Ok(Self {
+            key: base.key.clone(),
+            file,
+            dirty: truncate && base.size > 0,
+            base: Some(base),
+            base_len,
+            local: ExtentMap::new(),
+            owner: None,
+            unlinked: false,
+        })
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// 是否从未上传过（新建后还没有成功上传）
+    pub fn is_new(&self) -> bool {
+        self.base.is_none()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn is_unlinked(&self) -> bool {
+        self.unlinked
+    }
+
+    /// 取回全部旧内容，之后读取暂存不再需要 COS 上的对象（删除打开着的文件之前调用）
+    pub async fn load<S: ObjectStore>(&mut self, client: &S) -> Result<()> {
+        self.fetch(client, 0, self.file.size()).await
+    }
+
+    /// 文件已被删除：句柄仍可以读写暂存，但不再上传，关闭时暂存随之丢弃
+    pub fn unlink(&mut self) {
+        self.unlinked = true;
+    }
+
+    /// 上传之前 lookup/getattr 报告的元数据：暂存的大小与 mtime，其余沿用旧对象
+    pub fn meta(&self) -> ObjectMeta {
+        let mut meta = match &self.base {
+            Some(base) => base.clone(),
+            None => ObjectMeta {
+                key: self.key.clone(),
+                size: 0,
+                last_modified: self.file.mtime(),
+                etag: String::new(),
+                content_type: None,
+                server_side_encryption: None,
+                details: ObjectDetails::default(),
+            },
+        };
+        meta.size = self.file.size();
+        meta.last_modified = self.file.mtime();
+        meta
+    }
+
+    pub fn write(&mut self, offset: u64, data: &[u8]) -> io::Result<()> {
+        self.file.write_at(offset, data)?;
+        self.local.insert(offset, offset + data.len() as u64);
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// 截断或扩展到 `size`，扩展出的部分读出为 0
+    pub fn truncate(&mut self, size: u64) -> io::Result<()> {
+        if size == self.file.size() {
+            return Ok(());
+        }
+        self.file.set_len(size)?;
+        self.local.truncate(size);
+        self.base_len = self.base_len.min(size);
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// 显式设置 mtime（utimensat），上传时按 `--hires-mtime` 记录
+    pub fn set_mtime(&mut self, mtime: SystemTime) {
+        self.file.set_mtime(mtime);
+        self.dirty = true;
+    }
+
+    /// 读取 [offset, offset + size)，先取回其中缺少的旧内容
+    pub async fn read<S: ObjectStore>(
+        &mut self,
+        client: &S,
+        offset: u64,
+        size: u64,
+    ) -> Result<Vec<u8>> {
+        let end = offset.saturating_add(size).min(self.file.size());
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+        self.fetch(client, offset, end).await?;
+        let mut buf = vec![0; (end - offset) as usize];
+        self.file.read_at(&mut buf, offset)?;
+        Ok(buf)
+    }
+
+    /// 从 COS 取回 [start, end) 中暂存文件还没有的旧内容；取回的内容不算改动，mtime 不变。
+    /// 请求带上 `base` 的 ETag（If-Match），对象在打开期间被其他客户端改写时以 412 失败，
+    /// 不会把新旧两个版本的内容拼在一起
+    async fn fetch<S: ObjectStore>(&mut self, client: &S, start: u64, end: u64) -> Result<()> {
+        let Some(base) = &self.base else {
+            return Ok(());
+        };
+        let etag = base.etag.clone();
+        let mtime = self.file.mtime();
+        for (gap_start, gap_end) in self.local.missing(start, end.min(self.base_len)) {
+            let mut offset = gap_start;
+            while offset < gap_end {
+                let len = (gap_end - offset).min(FETCH_CHUNK);
+                let data = client
+                    .get_object_range_if_match(&self.key, &etag, offset, len)
+                    .await?;
+                if data.len() as u64 != len {
+                    bail!("Object {} changed while it was open for writing", self.key);
+                }
+                self.file.write_at(offset, &data)?;
+                self.local.insert(offset, offset + len);
+                offset += len;
+            }
+        }
+        self.file.set_mtime(mtime);
+        Ok(())
+    }
+
+    /// 上传暂存的内容，返回新对象的元数据；没有改动、内容与 COS 上相同或文件已被删除时
+    /// 返回 None。失败时暂存内容保持不变，可以再次上传
+    pub async fn upload<S: ObjectStore>(
+        &mut self,
+        client: &S,
+        options: &UploadOptions,
+    ) -> Result<Option<ObjectMeta>> {
+        if !self.dirty || self.unlinked {
+            return Ok(None);
+        }
+        let size = self.file.size();
+        // 按内容识别类型需要开头的字节
+        self.fetch(client, 0, SNIFF_LEN as u64).await?;
+        self.file.finish()?;
+
+        let mut headers = options.headers_for_file(&self.key, &self.file)?;
+        if let Some(owner) = self.owner {
+            options.insert_ownership(&mut headers, owner.uid, owner.gid, owner.perm, false);
+        }
+        if let Some(base) = &self.base {
+            if self.local.covers(0, size) && options.is_unchanged(&self.file, base)? {
+                debug!("{} is unchanged, skipping upload", self.key);
+                self.dirty = false;
+                return Ok(None);
+            }
+            // 覆盖已有对象时保留其自定义元数据（包括 s3fs 记录的属主），配置的头优先
+            for (name, value) in &base.details.user_meta {
+                let name = format!("{}{}", USER_META_PREFIX, name);
+                if name == MTIME_META_HEADER {
+                    continue;
+                }
+                if let (Ok(name), Ok(value)) = (
+                    HeaderName::from_bytes(name.as_bytes()),
+                    HeaderValue::from_str(value),
+                ) {
+                    headers.entry(name).or_insert(value);
+                }
+            }
+        }
+        let mut meta = uploaded_meta(&self.key, size, &headers);
+        if options.store_mtime {
+            meta.last_modified = self.file.mtime();
+        }
+
+        let mut etag = None;
+        if let Some(base) = &self.base {
+            if let Some(parts) = plan_parts(
+                self.base_len,
+                size,
+                &self.local,
+                DEFAULT_PART_SIZE,
+                DEFAULT_MIN_OBJECT_SIZE,
+            ) {
+                let old = ObjectMeta {
+                    size: self.base_len,
+                    ..base.clone()
+                };
+                match upload_partial(
+                    client,
+                    &old,
+                    &self.file,
+                    &self.local,
+                    &parts,
+                    headers.clone(),
+                )
+                .await
+                {
+                    Ok(uploaded) => etag = Some(uploaded),
+                    Err(e) => warn!(
+                        "Partial upload of {} failed, uploading it whole: {:#}",
+                        self.key, e
+                    ),
+                }
+            }
+        }
+        meta.etag = match etag {
+            Some(etag) => etag,
+            None => {
+                self.fetch(client, 0, size).await?;
+                upload_whole(client, &self.key, &self.file, headers, DEFAULT_PART_SIZE).await?
+            }
+        };
+
+        self.base = Some(meta.clone());
+        self.base_len = size;
+        self.dirty = false;
+        Ok(Some(meta))
+    }
+}
+
+/// 上传成功后新对象的元数据，取自上传时的头
+fn uploaded_meta(key: &str, size: u64, headers: &HeaderMap) -> ObjectMeta {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let user_meta: BTreeMap<String, String> = headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let name = name.as_str().strip_prefix(USER_META_PREFIX)?;
+            Some((name.to_string(), value.to_str().ok()?.to_string()))
+        })
+        .collect();
+    ObjectMeta {
+        key: key.to_string(),
+        size,
+        last_modified: SystemTime::now(),
+        etag: String::new(),
+        content_type: header(CONTENT_TYPE.as_str()),
+        server_side_encryption: header(SSE_HEADER),
+        details: ObjectDetails {
+            user_meta,
+            ..Default::default()
+        },
+    }
+}
+
+/// 以写方式打开着的对象：上传之前 lookup/getattr 报告暂存的大小与 mtime，
+/// 列表刷新时保留还没有上传的新文件。同一对象可以被多个句柄打开，最后一个关闭时移除
+#[derive(Default)]
+pub struct StagedFiles {
+    files: Mutex<HashMap<String, (usize, ObjectMeta)>>,
+}
+
+impl StagedFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记一个新打开的句柄
+    pub fn open(&self, meta: ObjectMeta) {
+        let mut files = self.files.lock().unwrap();
+        let entry = files.entry(meta.key.clone()).or_insert((0, meta.clone()));
+        entry.0 += 1;
+        entry.1 = meta;
+    }
+
+    /// 写入、截断或上传之后更新；没有打开的对象忽略
+    pub fn update(&self, meta: ObjectMeta) {
+        if let Some(entry) = self.files.lock().unwrap().get_mut(&meta.key) {
+            entry.1 = meta;
+        }
+    }
+
+    /// 一个句柄关闭
+    pub fn close(&self, key: &str) {
+        let mut files = self.files.lock().unwrap();
+        if let Some(entry) = files.get_mut(key) {
+            entry.0 -= 1;
+            if entry.0 == 0 {
+                files.remove(key);
+            }
+        }
+    }
+
+    /// 对象在打开期间被删除，句柄随后关闭时不再计数
+    pub fn remove(&self, key: &str) {
+        self.files.lock().unwrap().remove(key);
+    }
+
+    pub fn get(&self, key: &str) -> Option<ObjectMeta> {
+        self.files
+            .lock()
+            .unwrap()
+            .get(key)
+            .map(|(_, meta)| meta.clone())
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        self.files.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cos_client::{error_status, CosClient};
+    use crate::mock_backend::{MockBackend, Op};
+    use crate::spool::{FsyncPolicy, Spool, SpoolOptions};
+    use reqwest::StatusCode;
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+    use tempfile::TempDir;
+
+    fn open_spool(root: &TempDir) -> Arc<Spool> {
+        let options = SpoolOptions {
+            dir: root.path().join("spool"),
+            max_size: None,
+            fsync: FsyncPolicy::Never,
+        };
+        Arc::new(Spool::open(&options, &root.path().join("cache")).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_write_handle() {
+        let root = TempDir::new().unwrap();
+        let spool = open_spool(&root);
+        let client = CosClient::new("b-125".to_string(), "ap-beijing".to_string());
+        let owner = Ownership {
+            uid: 1000,
+            gid: 1000,
+            perm: 0o644,
+        };
+
+        let mut handle = WriteHandle::create("a.txt".to_string(), spool.create().unwrap(), owner);
+        assert!(handle.is_new() && handle.is_dirty());
+        handle.write(0, b"hello world").unwrap();
+        handle.truncate(5).unwrap();
+        handle.truncate(8).unwrap();
+        assert_eq!(handle.meta().size, 8);
+        // 新文件没有旧内容，不访问网络
+        assert_eq!(handle.read(&client, 0, 100).await.unwrap(), b"hello\0\0\0");
+        assert_eq!(spool.used_bytes(), 8);
+
+        // 打开已有对象：截断打开时没有改动与否取决于原来是否为空
+        let base = ObjectMeta {
+            key: "b.txt".to_string(),
+            size: 10,
+            last_modified: UNIX_EPOCH + Duration::from_secs(1_000_000_000),
+            etag: "\"abc\"".to_string(),
+            content_type: None,
+            server_side_encryption: None,
+            details: ObjectDetails::default(),
+        };
+        let handle = WriteHandle::open(spool.create().unwrap(), base.clone(), true).unwrap();
+        assert!(handle.is_dirty());
+        assert_eq!(handle.meta().size, 0);
+
+        let mut handle = WriteHandle::open(spool.create().unwrap(), base.clone(), false).unwrap();
+        assert!(!handle.is_new() && !handle.is_dirty());
+        assert_eq!(handle.meta().size, 10);
+        assert_eq!(handle.meta().last_modified, base.last_modified);
+        // 只读取写入过的区间时不需要旧内容
+        handle.write(2, b"xy").unwrap();
+        assert_eq!(handle.read(&client, 2, 2).await.unwrap(), b"xy");
+        assert!(handle.meta().last_modified > base.last_modified);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_requires_same_version() {
+        let root = TempDir::new().unwrap();
+        let spool = open_spool(&root);
+        let mock = MockBackend::new("b");
+        mock.insert("c.txt", "0123456789");
+        let base = mock.head_object("c.txt").await.unwrap();
+
+        let mut handle = WriteHandle::open(spool.create().unwrap(), base.clone(), false).unwrap();
+        handle.write(0, b"ab").unwrap();
+        assert_eq!(handle.read(&mock, 0, 4).await.unwrap(), b"ab23");
+
+        // 其他客户端改写了对象：取回旧内容以 412 失败，而不是拼入新版本的内容
+        mock.insert("c.txt", "abcdefghij");
+        let e = handle.read(&mock, 0, 10).await.unwrap_err();
+        assert_eq!(error_status(&e), Some(StatusCode::PRECONDITION_FAILED));
+        let e = handle
+            .upload(&mock, &UploadOptions::default())
+            .await
+            .unwrap_err();
+        assert_eq!(error_status(&e), Some(StatusCode::PRECONDITION_FAILED));
+        assert_eq!(mock.object("c.txt").unwrap(), "abcdefghij");
+        // 已取回的区间不再请求
+        assert_eq!(handle.read(&mock, 2, 2).await.unwrap(), b"23");
+    }
+
+    #[tokio::test]
+    async fn test_unlinked_handle() {
+        let root = TempDir::new().unwrap();
+        let spool = open_spool(&root);
+        let mock = MockBackend::new("b");
+        mock.insert("d.txt", "0123456789");
+        let base = mock.head_object("d.txt").await.unwrap();
+
+        let mut handle = WriteHandle::open(spool.create().unwrap(), base, false).unwrap();
+        handle.write(0, b"ab").unwrap();
+        handle.load(&mock).await.unwrap();
+        mock.delete_object("d.txt").await.unwrap();
+        handle.unlink();
+
+        // 删除后仍能读写暂存，但不再上传
+        handle.write(10, b"!").unwrap();
+        assert_eq!(handle.read(&mock, 0, 100).await.unwrap(), b"ab23456789!");
+        let options = UploadOptions::default();
+        assert!(handle.upload(&mock, &options).await.unwrap().is_none());
+        assert!(mock.object("d.txt").is_none());
+        assert_eq!(mock.requests(Op::Put), 0);
+    }
+
+    #[test]
+    fn test_staged_files() {
+        let staged = StagedFiles::new();
+        let meta = |size| ObjectMeta {
+            key: "a".to_string(),
+            size,
+            last_modified: UNIX_EPOCH,
+            etag: String::new(),
+            content_type: None,
+            server_side_encryption: None,
+            details: ObjectDetails::default(),
+        };
+        staged.open(meta(0));
+        staged.open(meta(0));
+        staged.update(meta(5));
+        assert_eq!(staged.get("a").unwrap().size, 5);
+        assert_eq!(staged.keys(), ["a"]);
+
+        staged.close("a");
+        assert!(staged.get("a").is_some());
+        staged.close("a");
+        assert!(staged.get("a").is_none());
+        // 没有打开的对象不登记
+        staged.update(meta(1));
+        assert!(staged.get("a").is_none());
+
+        staged.open(meta(0));
+        staged.remove("a");
+        assert!(staged.get("a").is_none());
+    }
+}
@@ -0,0 +1,288 @@
+use anyhow::{anyhow, bail, Context, Result};
+use log::{info, warn};
+use std::ffi::{CString, OsString};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::process::{Child, Command};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::sd_notify;
+
+/// 启用监督模式的参数，传给子进程时去掉
+const SUPERVISE_ARG: &str = "--supervise";
+
+/// 子进程启动后等待挂载完成的最长时间
+const MOUNT_START_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// 重新挂载的等待时间从这里开始倍增
+const MIN_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+
+/// 挂载正常运行超过该时长后，重新挂载的等待时间重新从最小值开始
+const STABLE_UPTIME: Duration = Duration::from_secs(300);
+
+/// 一次探测看到的挂载点状态
+#[derive(Debug)]
+pub enum MountState {
+    /// statfs 正常返回
+    Healthy,
+    /// 挂载点上还没有（或已经没有）挂载
+    NotMounted,
+    /// 挂载点返回错误，通常是进程崩溃或内核断开后的 ENOTCONN
+    Dead(io::Error),
+    /// statfs 没有在探测间隔内返回
+    Hung,
+}
+
+/// 监督模式：以前台模式启动挂载子进程，每隔 `interval` 对挂载点做一次 statfs；
+/// 子进程退出、挂载点失去响应或消失时记录原因，清理挂载点并重新挂载。
+/// `args` 是展开 `-o` 之后的完整命令行，收到 Ctrl+C 时停止子进程并返回
+pub fn run(args: Vec<OsString>, mount_point: &Path, interval: Duration) -> Result<()> {
+    let program = std::env::current_exe().context("Failed to locate the cosfs executable")?;
+    let child_args = child_args(&args);
+    let mount_point = mount_point
+        .canonicalize()
+        .with_context(|| format!("Mount point does not exist: {}", mount_point.display()))?;
+
+    let (stop_tx, stop_rx) = mpsc::channel();
+    ctrlc::set_handler(move || {
+        let _ = stop_tx.send(());
+    })
+    .map_err(|e| anyhow!("Failed to set Ctrl-C handler: {}", e))?;
+
+    // 由 systemd 启动时由监督进程通知就绪和喂狗，子进程不发送
+    let watchdog = sd_notify::watchdog_timeout();
+    let interval = watchdog.map_or(interval, |timeout| interval.min(timeout / 2));
+    let mut ready_sent = false;
+
+    let mut delay = MIN_RESTART_DELAY;
+    let mut ever_mounted = false;
+    loop {
+        let mut command = Command::new(&program);
+        command.args(&child_args);
+        for var in sd_notify::ENV_VARS {
+            command.env_remove(var);
+        }
+        let mut child = command
+            .spawn()
+            .context("Failed to start the mount process")?;
+        info!(
+            "Supervising mount process {} for {}",
+            child.id(),
+            mount_point.display()
+        );
+        let started = Instant::now();
+        let mut mounted_at = None;
+
+        let incident = loop {
+            match stop_rx.recv_timeout(interval) {
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => {
+                    info!("Stopping mount process {}...", child.id());
+                    notify("STOPPING=1");
+                    stop_child(&mut child, libc::SIGINT);
+                    return Ok(());
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+
+            if let Some(status) = child.try_wait()? {
+                break format!("mount process exited ({})", status);
+            }
+            match probe(&mount_point, interval) {
+                MountState::Healthy => {
+                    if mounted_at.is_none() {
+                        info!("Mount at {} is up", mount_point.display());
+                        mounted_at = Some(Instant::now());
+                        ever_mounted = true;
+                    }
+                    if !ready_sent {
+                        ready_sent = true;
+                        notify(&format!(
+                            "READY=1\nSTATUS=Serving {}",
+                            mount_point.display()
+                        ));
+                    } else if watchdog.is_some() {
+                        notify("WATCHDOG=1");
+                    }
+                }
+                MountState::NotMounted if mounted_at.is_some() => {
+                    break "mount disappeared".to_string()
+                }
+                MountState::NotMounted if started.elapsed() > MOUNT_START_TIMEOUT => {
+                    break format!("not mounted after {:?}", MOUNT_START_TIMEOUT)
+                }
+                MountState::NotMounted => {}
+                MountState::Dead(e) => break format!("mount point is not responding: {}", e),
+                MountState::Hung => break format!("statfs did not return within {:?}", interval),
+            }
+        };
+
+        stop_child(&mut child, libc::SIGKILL);
+        // 第一次就没能挂载，多半是参数或权限问题，重试也无济于事
+        if !ever_mounted {
+            bail!("Mount at {} failed: {}", mount_point.display(), incident);
+        }
+        if mounted_at.is_some_and(|at| at.elapsed() > STABLE_UPTIME) {
+            delay = MIN_RESTART_DELAY;
+        }
+        warn!(
+            "Mount at {} failed: {}; remounting in {:?}",
+            mount_point.display(),
+            incident,
+            delay
+        );
+        notify(&format!("STATUS=Remounting after failure: {}", incident));
+        force_unmount(&mount_point);
+
+        if stop_rx.recv_timeout(delay) != Err(RecvTimeoutError::Timeout) {
+            return Ok(());
+        }
+        delay = (delay * 2).min(MAX_RESTART_DELAY);
+    }
+}
+
+/// 通知 systemd，失败只记录警告
+fn notify(state: &str) {
+    if let Err(e) = sd_notify::notify(state) {
+        warn!("Failed to notify systemd: {}", e);
+    }
+}
+
+/// 子进程的参数：去掉 `--supervise`，并强制前台运行
+fn child_args(args: &[OsString]) -> Vec<OsString> {
+    let mut child_args: Vec<OsString> = args
+        .iter()
+        .skip(1)
+        .filter(|arg| arg.as_os_str() != SUPERVISE_ARG)
+        .cloned()
+        .collect();
+    // `--` 之后的参数都是位置参数
+    let position = child_args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(child_args.len());
+    child_args.insert(position, OsString::from("--foreground"));
+    child_args
+}
+
+/// 结束子进程并回收
+fn stop_child(child: &mut Child, signal: libc::c_int) {
+    if let Ok(None) = child.try_wait() {
+        // SAFETY: 向自己启动、尚未回收的子进程发信号
+        unsafe {
+            libc::kill(child.id() as libc::pid_t, signal);
+        }
+    }
+    let _ = child.wait();
+}
+
+/// 在独立线程中探测挂载点，失去响应的 FUSE 挂载会让 stat 一直阻塞
+pub fn probe(mount_point: &Path, timeout: Duration) -> MountState {
+    let (tx, rx): (_, Receiver<MountState>) = mpsc::channel();
+    let mount_point = mount_point.to_path_buf();
+    thread::spawn(move || {
+        let _ = tx.send(probe_blocking(&mount_point));
+    });
+    rx.recv_timeout(timeout).unwrap_or(MountState::Hung)
+}
+
+fn probe_blocking(mount_point: &Path) -> MountState {
+    let metadata = match mount_point.metadata() {
+        Ok(metadata) => metadata,
+        Err(e) => return MountState::Dead(e),
+    };
+    // 与父目录在同一设备上说明没有挂载
+    let parent = mount_point.parent().unwrap_or(Path::new("/"));
+    if parent
+        .metadata()
+        .is_ok_and(|parent| parent.dev() == metadata.dev())
+    {
+        return MountState::NotMounted;
+    }
+
+    let Ok(path) = CString::new(mount_point.as_os_str().as_bytes()) else {
+        return MountState::Dead(io::Error::from(io::ErrorKind::InvalidInput));
+    };
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: path 是以 NUL 结尾的路径，stat 是有效的输出缓冲区
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return MountState::Dead(io::Error::last_os_error());
+    }
+    MountState::Healthy
+}
+
+/// 卸载失去响应的挂载点，使其可以重新挂载；失败只记录警告
+fn force_unmount(mount_point: &Path) {
+    for &(program, args) in unmount_commands() {
+        match Command::new(program).args(args).arg(mount_point).status() {
+            Ok(status) if status.success() => {
+                info!("Unmounted {} with {}", mount_point.display(), program);
+                return;
+            }
+            _ => continue,
+        }
+    }
+    // 子进程已经卸载了挂载点时这里也会失败，不一定是问题
+    let still_mounted = !matches!(
+        probe(mount_point, Duration::from_secs(1)),
+        MountState::NotMounted
+    );
+    if still_mounted {
+        warn!(
+            "Failed to unmount {}; remounting may fail until it is unmounted",
+            mount_point.display()
+        );
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn unmount_commands() -> &'static [(&'static str, &'static [&'static str])] {
+    &[("umount", &["-f"]), ("diskutil", &["unmount", "force"])]
+}
+
+#[cfg(not(target_os = "macos"))]
+fn unmount_commands() -> &'static [(&'static str, &'static [&'static str])] {
+    // -z：延迟卸载，仍有进程占用挂载点时也能成功
+    &[
+        ("fusermount3", &["-uz"]),
+        ("fusermount", &["-uz"]),
+        ("umount", &["-l"]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_child_args() {
+        let args = ["cosfs", "-b", "b", "--supervise", "-m", "/mnt/cos"].map(OsString::from);
+        assert_eq!(
+            child_args(&args),
+            ["-b", "b", "-m", "/mnt/cos", "--foreground"].map(OsString::from)
+        );
+
+        let args = ["cosfs", "--supervise", "--", "b", "/mnt/cos"].map(OsString::from);
+        assert_eq!(
+            child_args(&args),
+            ["--foreground", "--", "b", "/mnt/cos"].map(OsString::from)
+        );
+    }
+
+    #[test]
+    fn test_probe_unmounted_directory() {
+        let dir = std::env::temp_dir();
+        assert!(matches!(
+            probe(&dir, Duration::from_secs(5)),
+            MountState::NotMounted | MountState::Healthy
+        ));
+        assert!(matches!(
+            probe(Path::new("/nonexistent/cosfs"), Duration::from_secs(5)),
+            MountState::Dead(_)
+        ));
+    }
+}
@@ -0,0 +1,131 @@
+use std::ffi::OsStr;
+use std::fmt;
+use std::os::unix::ffi::OsStrExt;
+use std::str::FromStr;
+
+/// 内核与工具频繁探测、但 COS 上不存在的扩展属性命名空间
+const SYSTEM_NAMESPACES: &[&str] = &["security.", "system.", "trusted."];
+
+/// SELinux 标签的扩展属性
+const SELINUX_XATTR: &str = "security.selinux";
+
+/// `security.*`、`system.*`、`trusted.*` 扩展属性的答复方式。
+/// 每次访问文件内核都会探测 `security.capability` 等属性，这些请求在快速路径上直接答复
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum SystemXattrs {
+    /// 属性不存在（ENODATA）
+    #[default]
+    Absent,
+    /// 不支持这些命名空间（EOPNOTSUPP），`cp -a`、`rsync -X` 等工具不再尝试复制
+    Unsupported,
+    /// 所有文件的 `security.selinux` 都返回给定的 SELinux 上下文，其他属性不存在
+    SelinuxLabel(String),
+}
+
+/// 快速路径的判定结果
+#[derive(Debug, PartialEq)]
+pub enum SystemXattr<'a> {
+    /// 不属于这些命名空间，按普通扩展属性处理
+    NotSystem,
+    Absent,
+    Unsupported,
+    Value(&'a str),
+}
+
+impl SystemXattrs {
+    /// 判定扩展属性 `name` 的答复
+    pub fn lookup(&self, name: &OsStr) -> SystemXattr<'_> {
+        let name = name.as_bytes();
+        if !SYSTEM_NAMESPACES
+            .iter()
+            .any(|namespace| name.starts_with(namespace.as_bytes()))
+        {
+            return SystemXattr::NotSystem;
+        }
+        match self {
+            SystemXattrs::Absent => SystemXattr::Absent,
+            SystemXattrs::Unsupported => SystemXattr::Unsupported,
+            SystemXattrs::SelinuxLabel(label) if name == SELINUX_XATTR.as_bytes() => {
+                SystemXattr::Value(label)
+            }
+            SystemXattrs::SelinuxLabel(_) => SystemXattr::Absent,
+        }
+    }
+
+    /// listxattr 中列出的属性
+    pub fn listed(&self) -> &'static [&'static str] {
+        match self {
+            SystemXattrs::SelinuxLabel(_) => &[SELINUX_XATTR],
+            _ => &[],
+        }
+    }
+}
+
+impl FromStr for SystemXattrs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "absent" => Ok(SystemXattrs::Absent),
+            "unsupported" => Ok(SystemXattrs::Unsupported),
+            _ => match s.strip_prefix("selinux-label=") {
+                Some(label) if !label.is_empty() => Ok(SystemXattrs::SelinuxLabel(label.to_string())),
+                _ => Err(format!(
+                    "invalid system xattr mode: {} (expected absent, unsupported or selinux-label=CONTEXT)",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for SystemXattrs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SystemXattrs::Absent => f.write_str("absent"),
+            SystemXattrs::Unsupported => f.write_str("unsupported"),
+            SystemXattrs::SelinuxLabel(label) => write!(f, "selinux-label={}", label),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_xattrs() {
+        let capability = OsStr::new("security.capability");
+        let selinux = OsStr::new(SELINUX_XATTR);
+        let acl = OsStr::new("system.posix_acl_access");
+
+        let absent = SystemXattrs::default();
+        assert_eq!(absent.lookup(capability), SystemXattr::Absent);
+        assert_eq!(absent.lookup(acl), SystemXattr::Absent);
+        assert_eq!(
+            absent.lookup(OsStr::new("user.cos.crc64")),
+            SystemXattr::NotSystem
+        );
+        assert!(absent.listed().is_empty());
+
+        let unsupported: SystemXattrs = "unsupported".parse().unwrap();
+        assert_eq!(unsupported.lookup(selinux), SystemXattr::Unsupported);
+
+        let label: SystemXattrs = "selinux-label=system_u:object_r:fusefs_t:s0"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            label.lookup(selinux),
+            SystemXattr::Value("system_u:object_r:fusefs_t:s0")
+        );
+        assert_eq!(label.lookup(capability), SystemXattr::Absent);
+        assert_eq!(label.listed(), [SELINUX_XATTR]);
+        assert_eq!(
+            label.to_string(),
+            "selinux-label=system_u:object_r:fusefs_t:s0"
+        );
+
+        assert!("selinux-label=".parse::<SystemXattrs>().is_err());
+        assert!("off".parse::<SystemXattrs>().is_err());
+    }
+}
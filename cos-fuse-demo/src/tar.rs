@@ -0,0 +1,189 @@
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tar::{Builder, EntryType, Header};
+
+/// tar 的块大小，头和内容都按块对齐
+pub const BLOCK_SIZE: usize = 512;
+
+/// 流式写出 GNU 格式的 tar 归档，头由 `tar` crate 生成：超长路径用 GNU 长文件名条目，
+/// 超过 8 GiB 的大小用 base-256 编码。文件内容由调用方分段写入，不需要事先全部读到内存
+pub struct TarWriter<W: Write> {
+    builder: Builder<W>,
+    /// 当前文件还需要写入的字节数
+    remaining: u64,
+    /// 当前文件内容结束后需要补齐的字节数
+    padding: usize,
+}
+
+impl<W: Write> TarWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            builder: Builder::new(inner),
+            remaining: 0,
+            padding: 0,
+        }
+    }
+
+    /// 写入目录条目，`path` 不含结尾的 `/`
+    pub fn append_dir(&mut self, path: &str, mtime: SystemTime) -> io::Result<()> {
+        self.check_idle()?;
+        self.write_header(&format!("{}/", path), 0, mtime, 0o755, EntryType::Directory)
+    }
+
+    /// 写入文件头，随后用 `write_data` 写入恰好 `size` 字节的内容
+    pub fn begin_file(&mut self, path: &str, size: u64, mtime: SystemTime) -> io::Result<()> {
+        self.check_idle()?;
+        self.write_header(path, size, mtime, 0o644, EntryType::Regular)?;
+        self.remaining = size;
+        self.padding = padding_for(size);
+        self.finish_data()
+    }
+
+    /// 写入当前文件的一段内容，内容写满后自动补齐到块边界
+    pub fn write_data(&mut self, data: &[u8]) -> io::Result<()> {
+        if data.len() as u64 > self.remaining {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "data exceeds the size declared in the tar header",
+            ));
+        }
+        self.builder.get_mut().write_all(data)?;
+        self.remaining -= data.len() as u64;
+        self.finish_data()
+    }
+
+    /// 写入结尾的两个空块，返回底层的输出
+    pub fn finish(self) -> io::Result<W> {
+        self.check_idle()?;
+        let mut inner = self.builder.into_inner()?;
+        inner.flush()?;
+        Ok(inner)
+    }
+
+    fn finish_data(&mut self) -> io::Result<()> {
+        if self.remaining == 0 && self.padding > 0 {
+            self.builder
+                .get_mut()
+                .write_all(&[0u8; BLOCK_SIZE][..self.padding])?;
+            self.padding = 0;
+        }
+        Ok(())
+    }
+
+    fn check_idle(&self) -> io::Result<()> {
+        if self.remaining > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("previous file is missing {} bytes", self.remaining),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 只写出头（以及需要时的长文件名条目），内容随后由 `write_data` 直接写入
+    fn write_header(
+        &mut self,
+        path: &str,
+        size: u64,
+        mtime: SystemTime,
+        mode: u32,
+        entry_type: EntryType,
+    ) -> io::Result<()> {
+        let mtime = mtime
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let mut header = Header::new_gnu();
+        header.set_entry_type(entry_type);
+        header.set_size(size);
+        header.set_mtime(mtime);
+        header.set_mode(mode);
+        header.set_uid(0);
+        header.set_gid(0);
+        self.builder.append_data(&mut header, path, io::empty())
+    }
+}
+
+fn padding_for(size: u64) -> usize {
+    (BLOCK_SIZE - (size % BLOCK_SIZE as u64) as usize) % BLOCK_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::time::Duration;
+
+    #[test]
+    fn test_write_archive() {
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let long_path = format!("{}/{}", "d".repeat(120), "f".repeat(20));
+        let huge_name = "x".repeat(200);
+
+        let mut tar = TarWriter::new(Vec::new());
+        tar.append_dir("data", mtime).unwrap();
+        tar.begin_file("data/a.txt", 5, mtime).unwrap();
+        tar.write_data(b"he").unwrap();
+        tar.write_data(b"llo").unwrap();
+        assert!(tar.write_data(b"!").is_err());
+        tar.begin_file(&long_path, 0, mtime).unwrap();
+        tar.begin_file(&huge_name, 1, mtime).unwrap();
+        assert!(tar.finish().is_err());
+
+        let mut tar = TarWriter::new(Vec::new());
+        tar.append_dir("data", mtime).unwrap();
+        tar.begin_file("data/a.txt", 5, mtime).unwrap();
+        tar.write_data(b"hello").unwrap();
+        tar.begin_file(&long_path, 0, mtime).unwrap();
+        tar.begin_file(&huge_name, 1, mtime).unwrap();
+        tar.write_data(b"z").unwrap();
+        let out = tar.finish().unwrap();
+        assert_eq!(out.len() % BLOCK_SIZE, 0);
+        assert!(out[out.len() - 2 * BLOCK_SIZE..].iter().all(|&b| b == 0));
+
+        // 用 `tar` crate 读回，长路径与内容完整
+        let mut archive = tar::Archive::new(out.as_slice());
+        let entries: Vec<(String, EntryType, u64, Vec<u8>)> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let path = entry.path().unwrap().to_string_lossy().into_owned();
+                let header = entry.header();
+                let (entry_type, mtime) = (header.entry_type(), header.mtime().unwrap());
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data).unwrap();
+                (path, entry_type, mtime, data)
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    "data/".to_string(),
+                    EntryType::Directory,
+                    1_700_000_000,
+                    vec![]
+                ),
+                (
+                    "data/a.txt".to_string(),
+                    EntryType::Regular,
+                    1_700_000_000,
+                    b"hello".to_vec()
+                ),
+                (long_path, EntryType::Regular, 1_700_000_000, vec![]),
+                (huge_name, EntryType::Regular, 1_700_000_000, b"z".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_large_size() {
+        let mut tar = TarWriter::new(Vec::new());
+        tar.begin_file("big", 10 << 30, UNIX_EPOCH).unwrap();
+        let out = tar.builder.get_ref();
+        let header = Header::from_byte_slice(&out[..BLOCK_SIZE]);
+        assert_eq!(header.size().unwrap(), 10 << 30);
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use log::info;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cos_client::{civil_from_days, normalize_prefix, ObjectMeta};
+use crate::object_store::ObjectStore;
+
+/// 回收站：删除的对象先在服务端复制到 `<prefix><UTC 时间戳>/<原对象键>` 再删除原对象，
+/// 误执行 `rm -rf` 时仍可以找回。回收站本身不出现在挂载中，也就不会被同一条命令一起删掉
+#[derive(Debug, Clone)]
+pub struct Trash {
+    prefix: String,
+}
+
+impl Trash {
+    /// `prefix` 如 `.trash/`，前导和结尾的 `/` 会被规范化
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: normalize_prefix(prefix),
+        }
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// 对象是否位于回收站中
+    pub fn contains(&self, key: &str) -> bool {
+        key.starts_with(&self.prefix)
+    }
+
+    /// 在 `deleted_at` 删除的 `key` 在回收站中的对象键
+    pub fn trash_key(&self, key: &str, deleted_at: SystemTime) -> String {
+        format!("{}{}/{}", self.prefix, format_timestamp(deleted_at), key)
+    }
+
+    /// 把对象移入回收站并返回新的对象键；复制失败时不删除原对象
+    pub async fn move_to_trash<S: ObjectStore>(&self, client: &S, key: &str) -> Result<String> {
+        let trash_key = self.trash_key(key, SystemTime::now());
+        client
+            .copy_object(key, &trash_key)
+            .await
+            .with_context(|| format!("Failed to copy {} to {}", key, trash_key))?;
+        client.delete_object(key).await?;
+        info!("Moved {} to {}", key, trash_key);
+        Ok(trash_key)
+    }
+
+    /// 解析回收站中的对象，不符合 `<prefix><时间戳>/<原对象键>` 的返回 None
+    pub fn entry(&self, meta: &ObjectMeta) -> Option<TrashEntry> {
+        let rest = meta.key.strip_prefix(&self.prefix)?;
+        let (deleted_at, original) = rest.split_once('/')?;
+        if deleted_at.len() != TIMESTAMP_LEN || !deleted_at.ends_with('Z') || original.is_empty() {
+            return None;
+        }
+        Some(TrashEntry {
+            key: meta.key.clone(),
+            deleted_at: deleted_at.to_string(),
+            original: original.to_string(),
+            size: meta.size,
+        })
+    }
+
+    /// 从对象列表中取出回收站中的对象，按删除时间和原对象键排序
+    pub fn entries(&self, objects: &[ObjectMeta]) -> Vec<TrashEntry> {
+        let mut entries: Vec<TrashEntry> =
+            objects.iter().filter_map(|meta| self.entry(meta)).collect();
+        entries.sort_by(|a, b| (&a.deleted_at, &a.original).cmp(&(&b.deleted_at, &b.original)));
+        entries
+    }
+
+    /// 把回收站中的对象复制回原对象键并从回收站删除
+    pub async fn restore<S: ObjectStore>(&self, client: &S, entry: &TrashEntry) -> Result<()> {
+        client
+            .copy_object(&entry.key, &entry.original)
+            .await
+            .with_context(|| format!("Failed to copy {} to {}", entry.key, entry.original))?;
+        client.delete_object(&entry.key).await?;
+        info!("Restored {} from {}", entry.original, entry.key);
+        Ok(())
+    }
+}
+
+/// 回收站中的一个对象
+#[derive(Debug, Clone)]
+pub struct TrashEntry {
+    /// 在回收站中的对象键
+    pub key: String,
+    /// 删除时间戳，如 `20240524T105640Z`
+    pub deleted_at: String,
+    /// 删除前的对象键
+    pub original: String,
+    pub size: u64,
+}
+
+/// 时间戳 `YYYYMMDDTHHMMSSZ` 的长度
+const TIMESTAMP_LEN: usize = 16;
+
+/// UTC 时间戳，如 `20240524T105640Z`，按字典序排列即按时间排列
+pub fn format_timestamp(at: SystemTime) -> String {
+    let secs = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let rem = secs % 86400;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_trash_key() {
+        let trash = Trash::new("/.trash");
+        assert_eq!(trash.prefix(), ".trash/");
+        let deleted_at = UNIX_EPOCH + Duration::from_secs(1558695400);
+        assert_eq!(
+            trash.trash_key("data/a.txt", deleted_at),
+            ".trash/20190524T105640Z/data/a.txt"
+        );
+        assert!(trash.contains(".trash/20190524T105640Z/data/a.txt"));
+        assert!(!trash.contains("data/.trash/a.txt"));
+
+        assert_eq!(format_timestamp(UNIX_EPOCH), "19700101T000000Z");
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+    }
+
+    #[test]
+    fn test_entry() {
+        let trash = Trash::new(".trash/");
+        let meta = |key: &str| ObjectMeta {
+            key: key.to_string(),
+            size: 3,
+            last_modified: UNIX_EPOCH,
+            etag: String::new(),
+            content_type: None,
+            server_side_encryption: None,
+            details: Default::default(),
+        };
+
+        let entry = trash
+            .entry(&meta(".trash/20190524T105640Z/data/a.txt"))
+            .unwrap();
+        assert_eq!(entry.deleted_at, "20190524T105640Z");
+        assert_eq!(entry.original, "data/a.txt");
+        assert_eq!(entry.size, 3);
+
+        assert!(trash.entry(&meta("data/a.txt")).is_none());
+        assert!(trash.entry(&meta(".trash/notes/a.txt")).is_none());
+        assert!(trash.entry(&meta(".trash/20190524T105640Z/")).is_none());
+    }
+}
@@ -0,0 +1,448 @@
+use anyhow::{anyhow, bail, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use std::io;
+use std::time::SystemTime;
+
+use crate::config_file::{ConfigFile, Section};
+use crate::content_type::guess_content_type;
+use crate::cos_client::{
+    format_mtime_meta, ObjectMeta, MTIME_META_HEADER, SSE_HEADER, SSE_KMS_KEY_ID_HEADER,
+};
+use crate::s3fs_compat;
+use crate::spool::SpoolFile;
+use crate::trash::Trash;
+
+/// 配置文件中上传设置节的名字：`[upload]` 或 `[upload."prefix/"]`
+pub const UPLOAD_SECTION: &str = "upload";
+
+/// 可配置的标准头（配置键 -> 头名）
+const STANDARD_HEADERS: &[(&str, &str)] = &[
+    ("cache_control", "cache-control"),
+    ("content_disposition", "content-disposition"),
+    ("content_encoding", "content-encoding"),
+    ("content_language", "content-language"),
+    ("content_type", "content-type"),
+    ("expires", "expires"),
+];
+
+/// 自定义元数据键的前缀：`meta.team = "web"` 对应 `x-cos-meta-team: web`
+const META_KEY_PREFIX: &str = "meta.";
+const META_HEADER_PREFIX: &str = "x-cos-meta-";
+
+/// 嗅探 Content-Type 时读取的开头字节数
+pub const SNIFF_LEN: usize = 512;
+
+/// 服务端加密方式：SSE-COS（COS 托管密钥）与 SSE-KMS
+const SSE_COS: &str = "AES256";
+const SSE_KMS: &str = "cos/kms";
+
+/// 某个前缀（空串表示整个挂载）下上传时附加的头
+#[derive(Debug, Clone, Default)]
+struct UploadRule {
+    prefix: String,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl UploadRule {
+    fn push(&mut self, name: &str, value: &str, section: &Section) -> Result<()> {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| anyhow!("line {}: invalid header name '{}'", section.line, name))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|_| anyhow!("line {}: invalid value for {}", section.line, name))?;
+        self.headers.push((name, value));
+        Ok(())
+    }
+}
+
+/// 上传设置
+#[derive(Debug, Clone)]
+pub struct UploadOptions {
+    /// 扩展名识别不了时是否按内容开头嗅探 Content-Type
+    pub sniff_content_type: bool,
+
+    /// 是否在 `x-cos-meta-mtime` 中记录纳秒精度的 mtime（`--hires-mtime`）
+    pub store_mtime: bool,
+
+    /// 是否像 s3fs 一样在 `x-cos-meta-uid/gid/mode` 中记录属主与模式（`--s3fs-compat`）
+    pub s3fs_compat: bool,
+
+    /// 删除时移入回收站而不是直接删除（`--trash-prefix`）
+    pub trash: Option<Trash>,
+
+    /// 按前缀长度升序排列，更具体的前缀覆盖同名头
+    rules: Vec<UploadRule>,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            sniff_content_type: true,
+            store_mtime: false,
+            s3fs_compat: false,
+            trash: None,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl UploadOptions {
+    /// 从配置文件的 `[upload]`（整个挂载）和 `[upload."prefix/"]` 节读取
+    pub fn from_config(config: &ConfigFile) -> Result<Self> {
+        let mut options = UploadOptions::default();
+        for section in config.sections(UPLOAD_SECTION) {
+            let prefix = match section.name.as_slice() {
+                [_] => String::new(),
+                [_, prefix] => prefix.trim_start_matches('/').to_string(),
+                _ => bail!(
+                    "line {}: expected [{}] or [{}.\"prefix/\"], got [{}]",
+                    section.line,
+                    UPLOAD_SECTION,
+                    UPLOAD_SECTION,
+                    section.name.join(".")
+                ),
+            };
+            if options.rules.iter().any(|r| r.prefix == prefix) {
+                bail!(
+                    "line {}: duplicate upload settings for prefix '{}'",
+                    section.line,
+                    prefix
+                );
+            }
+
+            let rule = options.parse_rule(section, prefix)?;
+            options.rules.push(rule);
+        }
+        options.rules.sort_by_key(|rule| rule.prefix.len());
+        Ok(options)
+    }
+
+    fn parse_rule(&mut self, section: &Section, prefix: String) -> Result<UploadRule> {
+        let mut rule = UploadRule {
+            prefix,
+            headers: Vec::new(),
+        };
+        for (key, value) in &section.entries {
+            if key == "sniff_content_type" {
+                if !rule.prefix.is_empty() {
+                    bail!(
+                        "line {}: sniff_content_type can only be set in [{}]",
+                        section.line,
+                        UPLOAD_SECTION
+                    );
+                }
+                self.sniff_content_type = value
+                    .parse()
+                    .map_err(|_| anyhow!("line {}: expected true or false", section.line))?;
+                continue;
+            }
+
+            if key == "trash_prefix" {
+                if !rule.prefix.is_empty() {
+                    bail!(
+                        "line {}: trash_prefix can only be set in [{}]",
+                        section.line,
+                        UPLOAD_SECTION
+                    );
+                }
+                self.trash = Some(Trash::new(value));
+                continue;
+            }
+
+            if key == "sse" {
+                let sse = match value.to_ascii_lowercase().as_str() {
+                    "cos" | "aes256" => SSE_COS,
+                    "kms" | "cos/kms" => SSE_KMS,
+                    _ => bail!(
+                        "line {}: unknown sse '{}' (expected cos or kms)",
+                        section.line,
+                        value
+                    ),
+                };
+                rule.push(SSE_HEADER, sse, section)?;
+                continue;
+            }
+            if key == "sse_kms_key_id" {
+                // 指定密钥即意味着 SSE-KMS
+                rule.push(SSE_HEADER, SSE_KMS, section)?;
+                rule.push(SSE_KMS_KEY_ID_HEADER, value, section)?;
+                continue;
+            }
+
+            let name = match STANDARD_HEADERS.iter().find(|(k, _)| k == key) {
+                Some((_, header)) => header.to_string(),
+                None => match key.strip_prefix(META_KEY_PREFIX) {
+                    Some(meta) if !meta.is_empty() => {
+                        format!("{}{}", META_HEADER_PREFIX, meta.to_ascii_lowercase())
+                    }
+                    _ => bail!(
+                        "line {}: unknown key '{}' (expected cache_control, content_disposition, \
+                         content_encoding, content_language, content_type, expires, sse, \
+                         sse_kms_key_id, sniff_content_type, trash_prefix or meta.<name>)",
+                        section.line,
+                        key
+                    ),
+                },
+            };
+            rule.push(&name, value, section)?;
+        }
+        Ok(rule)
+    }
+
+    /// 上传 `key` 时附加的头：猜测的 Content-Type，加上匹配前缀配置的头
+    pub fn headers_for(&self, key: &str, head: &[u8]) -> HeaderMap {
+        let head = self.sniff_content_type.then_some(head);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static(guess_content_type(key, head)),
+        );
+        for rule in self.rules.iter().filter(|r| key.starts_with(&r.prefix)) {
+            // 更具体的前缀改了加密方式时，不能沿用外层的 KMS 密钥
+            if rule.headers.iter().any(|(name, _)| name == SSE_HEADER) {
+                headers.remove(SSE_KMS_KEY_ID_HEADER);
+            }
+            for (name, value) in &rule.headers {
+                headers.insert(name.clone(), value.clone());
+            }
+        }
+        headers
+    }
+
+    /// flush 时上传暂存文件所用的头：按内容识别类型，并保留写入或 setattr 设置的 mtime，
+    /// 这样 `cp -p`、rsync 复制进来的文件不会变成上传时间
+    pub fn headers_for_file(&self, key: &str, file: &SpoolFile) -> io::Result<HeaderMap> {
+        let head = if self.sniff_content_type {
+            file.head(SNIFF_LEN)?
+        } else {
+            Vec::new()
+        };
+        let mut headers = self.headers_for(key, &head);
+        self.insert_mtime(&mut headers, file.mtime());
+        Ok(headers)
+    }
+
+    /// 暂存文件与已有对象内容相同（`touch`、原样保存）时返回 true，可以跳过 PUT，
+    /// 既省带宽也不在开启版本控制的桶里产生新版本。`existing` 需要来自 HEAD，列表结果不带 CRC64。
+    /// 开启 `store_mtime` 时 mtime 变了仍需上传，否则新的 mtime 会丢失
+    pub fn is_unchanged(&self, file: &SpoolFile, existing: &ObjectMeta) -> io::Result<bool> {
+        if file.size() != existing.size {
+            return Ok(false);
+        }
+        if self.store_mtime && file.mtime() != existing.last_modified {
+            return Ok(false);
+        }
+        if let Some(crc64) = existing
+            .details
+            .crc64
+            .as_deref()
+            .and_then(|crc64| crc64.parse::<u64>().ok())
+        {
+            return Ok(file.crc64()? == crc64);
+        }
+        // 没有 CRC64 时，单段上传对象的 ETag 就是内容的 MD5；分块上传的 ETag 无法比较
+        let etag = existing.etag.trim_matches('"');
+        if etag.len() == 32 && etag.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(file.md5()?.eq_ignore_ascii_case(etag));
+        }
+        Ok(false)
+    }
+
+    /// 开启 `store_mtime` 时在上传头中记录文件的 mtime
+    pub fn insert_mtime(&self, headers: &mut HeaderMap, mtime: SystemTime) {
+        if !self.store_mtime {
+            return;
+        }
+        let value = HeaderValue::from_str(&format_mtime_meta(mtime))
+            .expect("formatted mtime is a valid header value");
+        headers.insert(MTIME_META_HEADER, value);
+    }
+
+    /// 开启 `s3fs_compat` 时在上传头中记录属主与模式，s3fs 挂载同一个桶时看到相同的属性
+    pub fn insert_ownership(
+        &self,
+        headers: &mut HeaderMap,
+        uid: u32,
+        gid: u32,
+        perm: u16,
+        is_dir: bool,
+    ) {
+        if !self.s3fs_compat {
+            return;
+        }
+        let mode = s3fs_compat::file_mode(u32::from(perm), is_dir);
+        headers.extend(s3fs_compat::ownership_headers(uid, gid, mode));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spool::{FsyncPolicy, Spool, SpoolOptions};
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+    use tempfile::TempDir;
+
+    fn options(text: &str) -> Result<UploadOptions> {
+        UploadOptions::from_config(&ConfigFile::parse(text).unwrap())
+    }
+
+    #[test]
+    fn test_prefix_headers_override_mount_defaults() {
+        let options = options(
+            r#"
+[upload]
+cache_control = "max-age=300"
+meta.team = "infra"
+
+[upload."static/"]
+cache_control = "max-age=31536000, immutable"
+content_disposition = "inline"
+"#,
+        )
+        .unwrap();
+
+        let headers = options.headers_for("static/app.js", b"");
+        assert_eq!(headers["cache-control"], "max-age=31536000, immutable");
+        assert_eq!(headers["content-disposition"], "inline");
+        assert_eq!(headers["x-cos-meta-team"], "infra");
+        assert_eq!(headers["content-type"], "text/javascript; charset=utf-8");
+
+        let headers = options.headers_for("logs/1", b"plain text");
+        assert_eq!(headers["cache-control"], "max-age=300");
+        assert_eq!(headers["content-type"], "text/plain; charset=utf-8");
+        assert!(headers.get("content-disposition").is_none());
+    }
+
+    #[test]
+    fn test_server_side_encryption() {
+        let upload = options(
+            r#"
+[upload] sse=cos
+[upload."secret/"] sse_kms_key_id="kms-key-1"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(upload.headers_for("a", b"")[SSE_HEADER], "AES256");
+        let headers = upload.headers_for("secret/a", b"");
+        assert_eq!(headers[SSE_HEADER], "cos/kms");
+        assert_eq!(headers[SSE_KMS_KEY_ID_HEADER], "kms-key-1");
+        assert!(options("[upload] sse=aes128").is_err());
+
+        let upload = options("[upload] sse_kms_key_id=k\n[upload.\"public/\"] sse=cos").unwrap();
+        let headers = upload.headers_for("public/a", b"");
+        assert_eq!(headers[SSE_HEADER], "AES256");
+        assert!(headers.get(SSE_KMS_KEY_ID_HEADER).is_none());
+    }
+
+    #[test]
+    fn test_sniffing_can_be_disabled() {
+        let upload = options("[upload] sniff_content_type=false").unwrap();
+        assert_eq!(
+            upload.headers_for("logs/1", b"plain text")["content-type"],
+            "application/octet-stream"
+        );
+        assert!(options("[upload.\"a/\"] sniff_content_type=false").is_err());
+    }
+
+    #[test]
+    fn test_trash_prefix() {
+        assert!(UploadOptions::default().trash.is_none());
+        let upload = options("[upload] trash_prefix=/.trash").unwrap();
+        assert_eq!(upload.trash.unwrap().prefix(), ".trash/");
+        assert!(options("[upload.\"a/\"] trash_prefix=.trash/").is_err());
+    }
+
+    #[test]
+    fn test_mtime_header() {
+        let mtime = UNIX_EPOCH + Duration::new(1, 500);
+        let mut upload = UploadOptions::default();
+        let mut headers = upload.headers_for("a", b"");
+        upload.insert_mtime(&mut headers, mtime);
+        assert!(headers.get(MTIME_META_HEADER).is_none());
+
+        upload.store_mtime = true;
+        upload.insert_mtime(&mut headers, mtime);
+        assert_eq!(headers[MTIME_META_HEADER], "1.000000500");
+    }
+
+    #[test]
+    fn test_headers_for_spool_file_keep_mtime() {
+        let root = TempDir::new().unwrap();
+        let options = SpoolOptions {
+            dir: root.path().join("spool"),
+            max_size: None,
+            fsync: FsyncPolicy::Never,
+        };
+        let spool = Arc::new(Spool::open(&options, &root.path().join("cache")).unwrap());
+        let mut file = spool.create().unwrap();
+        file.write_at(0, b"%PDF-1.7").unwrap();
+        file.set_mtime(UNIX_EPOCH + Duration::from_secs(1_000_000_000));
+
+        let upload = UploadOptions {
+            store_mtime: true,
+            ..Default::default()
+        };
+        let headers = upload.headers_for_file("report", &file).unwrap();
+        assert_eq!(headers["content-type"], "application/pdf");
+        assert_eq!(headers[MTIME_META_HEADER], "1000000000.000000000");
+    }
+
+    #[test]
+    fn test_unchanged_content_is_detected() {
+        let root = TempDir::new().unwrap();
+        let options = SpoolOptions {
+            dir: root.path().join("spool"),
+            max_size: None,
+            fsync: FsyncPolicy::Never,
+        };
+        let spool = Arc::new(Spool::open(&options, &root.path().join("cache")).unwrap());
+        let mut file = spool.create().unwrap();
+        file.write_at(0, b"123456789").unwrap();
+
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let mut existing = ObjectMeta {
+            key: "a".to_string(),
+            size: 9,
+            last_modified: mtime,
+            etag: "\"25f9e794323b453885f5181f1b624d0b\"".to_string(),
+            content_type: None,
+            server_side_encryption: None,
+            details: Default::default(),
+        };
+        let upload = UploadOptions::default();
+
+        // 没有 CRC64 时比较 ETag 中的 MD5
+        assert!(upload.is_unchanged(&file, &existing).unwrap());
+        existing.details.crc64 = Some(0x995D_C9BB_DF19_39FAu64.to_string());
+        assert!(upload.is_unchanged(&file, &existing).unwrap());
+        existing.details.crc64 = Some("1".to_string());
+        assert!(!upload.is_unchanged(&file, &existing).unwrap());
+
+        // 分块上传的 ETag 不能比较
+        existing.details.crc64 = None;
+        existing.etag = "\"abc-3\"".to_string();
+        assert!(!upload.is_unchanged(&file, &existing).unwrap());
+
+        // 记录 mtime 时，mtime 变化也要上传
+        existing.details.crc64 = Some(0x995D_C9BB_DF19_39FAu64.to_string());
+        let upload = UploadOptions {
+            store_mtime: true,
+            ..Default::default()
+        };
+        assert!(!upload.is_unchanged(&file, &existing).unwrap());
+        file.set_mtime(mtime);
+        assert!(upload.is_unchanged(&file, &existing).unwrap());
+
+        existing.size = 10;
+        assert!(!upload.is_unchanged(&file, &existing).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_settings_are_rejected() {
+        assert!(options("[upload] x_custom=1").is_err());
+        assert!(options("[upload] meta.=1").is_err());
+        assert!(options("[upload] cache_control=\"a\u{1}b\"").is_err());
+        assert!(options("[upload]\n[upload]").is_err());
+    }
+}
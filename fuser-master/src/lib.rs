@@ -147,6 +147,8 @@ pub struct KernelConfig {
     max_background: u16,
     congestion_threshold: Option<u16>,
     max_write: u32,
+    #[cfg(feature = "abi-7-28")]
+    max_pages: Option<u16>,
     #[cfg(feature = "abi-7-23")]
     time_gran: Duration,
     #[cfg(feature = "abi-7-40")]
@@ -164,6 +166,8 @@ impl KernelConfig {
             congestion_threshold: None,
             // use a max write size that fits into the session's buffer
             max_write: MAX_WRITE_SIZE as u32,
+            #[cfg(feature = "abi-7-28")]
+            max_pages: None,
             // 1ns means nano-second granularity.
             #[cfg(feature = "abi-7-23")]
             time_gran: Duration::new(0, 1),
@@ -245,6 +249,25 @@ impl KernelConfig {
         Ok(previous)
     }
 
+    /// Set the maximum number of pages in a single request
+    ///
+    /// By default this is derived from the maximum write and readahead sizes. Raising it lets
+    /// read requests (also bounded by the `max_read` mount option) carry more data than a write.
+    /// The kernel clamps the value to its own limit.
+    ///
+    /// On success returns the previous value.
+    /// # Errors
+    /// If the argument is zero, returns the nearest value which will succeed.
+    #[cfg(feature = "abi-7-28")]
+    pub fn set_max_pages(&mut self, value: u16) -> Result<u16, u16> {
+        if value == 0 {
+            return Err(1);
+        }
+        let previous = self.max_pages();
+        self.max_pages = Some(value);
+        Ok(previous)
+    }
+
     /// Set the maximum readahead size
     ///
     /// On success returns the previous value.
@@ -313,7 +336,9 @@ impl KernelConfig {
 
     #[cfg(feature = "abi-7-28")]
     fn max_pages(&self) -> u16 {
-        ((max(self.max_write, self.max_readahead) - 1) / page_size::get() as u32) as u16 + 1
+        let derived =
+            ((max(self.max_write, self.max_readahead) - 1) / page_size::get() as u32) as u16 + 1;
+        max(derived, self.max_pages.unwrap_or(0))
     }
 }
 
@@ -342,6 +367,14 @@ pub trait Filesystem {
         reply.error(ENOSYS);
     }
 
+    /// Interrupt a request in progress.
+    /// Sent when the process that issued the request identified by `unique` (see
+    /// [`Request::unique`]) receives a signal. The filesystem may abort the operation and
+    /// reply to the original request with EINTR, or ignore the interrupt and complete the
+    /// request normally. The interrupt itself takes no reply. The default implementation
+    /// ignores it.
+    fn interrupt(&mut self, _req: &Request<'_>, _unique: u64) {}
+
     /// Forget about an inode.
     /// The nlookup parameter indicates the number of lookups previously performed on
     /// this inode. If the filesystem implements inode lifetimes, it is recommended that
@@ -20,6 +20,8 @@
 
 #![warn(missing_debug_implementations)]
 #![allow(missing_docs)]
+// The ABI is mirrored in full; some structures (CUSE, ioctl, notify retrieve) are not used yet
+#![allow(dead_code)]
 
 use crate::consts::{FATTR_ATIME_NOW, FATTR_MTIME_NOW};
 use std::convert::TryFrom;
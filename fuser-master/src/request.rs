@@ -170,9 +170,8 @@ impl<'a> Request<'a> {
                 return Err(Errno::EIO);
             }
 
-            ll::Operation::Interrupt(_) => {
-                // TODO: handle FUSE_INTERRUPT
-                return Err(Errno::ENOSYS);
+            ll::Operation::Interrupt(x) => {
+                se.filesystem.interrupt(self, x.unique().into()); // no reply
             }
 
             ll::Operation::Lookup(x) => {